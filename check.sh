@@ -1,5 +1,5 @@
 
-cargo test -q
+cargo test -q --workspace
 npm --prefix web run build
 npm --prefix website run build
 node scripts/generate_docs_artifacts.mjs --check
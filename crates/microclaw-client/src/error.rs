@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API error (HTTP {status}): {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Unauthorized: check the MicroClaw web auth token")]
+    Unauthorized,
+
+    #[error("Invalid response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("Run failed: {0}")]
+    Run(String),
+
+    #[error("Stream ended before the run finished")]
+    StreamEnded,
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,309 @@
+//! Typed client for the MicroClaw web API (`/api/*`).
+//!
+//! ```no_run
+//! # async fn demo() -> microclaw_client::Result<()> {
+//! use microclaw_client::{MicroClawClient, SendRequest};
+//!
+//! let client = MicroClawClient::new("http://127.0.0.1:10961").with_auth_token("secret");
+//! let reply = client.chat(SendRequest::new("main", "hello")).await?;
+//! println!("{reply}");
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+mod sse;
+mod types;
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+pub use error::{ClientError, Result};
+pub use sse::{SseFrame, SseParser};
+pub use types::{
+    Health, History, HistoryItem, RunEvent, RunStatus, SendRequest, SendResponse, SessionItem,
+    Usage,
+};
+
+use types::SessionRequest;
+
+/// A run event together with its stream id, usable as `last_event_id` to resume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedEvent {
+    pub id: Option<u64>,
+    pub event: RunEvent,
+}
+
+pub type RunEventStream = Pin<Box<dyn Stream<Item = Result<StreamedEvent>> + Send>>;
+
+#[derive(Clone)]
+pub struct MicroClawClient {
+    base_url: String,
+    auth_token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl MicroClawClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        MicroClawClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            auth_token: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Bearer token matching the server's `web_auth_token`.
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        let token = token.into();
+        self.auth_token = if token.trim().is_empty() {
+            None
+        } else {
+            Some(token)
+        };
+        self
+    }
+
+    /// Use a preconfigured `reqwest::Client` (proxies, timeouts, TLS roots).
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let builder = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.auth_token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn send_json<T: DeserializeOwned>(&self, builder: reqwest::RequestBuilder) -> Result<T> {
+        let resp = check_status(builder.send().await?).await?;
+        let bytes = resp.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Server health. A degraded server answers 503 with the same body, so
+    /// that is returned as `Ok` with `ok: false`.
+    pub async fn health(&self) -> Result<Health> {
+        let resp = self
+            .request(reqwest::Method::GET, "/api/health")
+            .send()
+            .await?;
+        if resp.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            let bytes = resp.bytes().await?;
+            return Ok(serde_json::from_slice(&bytes)?);
+        }
+        let bytes = check_status(resp).await?.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    pub async fn sessions(&self) -> Result<Vec<SessionItem>> {
+        #[derive(Deserialize)]
+        struct Sessions {
+            sessions: Vec<SessionItem>,
+        }
+        let resp: Sessions = self
+            .send_json(self.request(reqwest::Method::GET, "/api/sessions"))
+            .await?;
+        Ok(resp.sessions)
+    }
+
+    pub async fn history(&self, session_key: &str, limit: Option<usize>) -> Result<History> {
+        let mut query = vec![("session_key", session_key.to_string())];
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        self.send_json(
+            self.request(reqwest::Method::GET, "/api/history")
+                .query(&query),
+        )
+        .await
+    }
+
+    pub async fn usage(&self, session_key: &str) -> Result<Usage> {
+        self.send_json(
+            self.request(reqwest::Method::GET, "/api/usage")
+                .query(&[("session_key", session_key)]),
+        )
+        .await
+    }
+
+    /// Send a message and wait for the full reply (no streaming).
+    pub async fn send(&self, req: &SendRequest) -> Result<SendResponse> {
+        self.send_json(self.request(reqwest::Method::POST, "/api/send").json(req))
+            .await
+    }
+
+    /// Start a streamed run and return its run id; follow it with [`Self::stream_run`].
+    pub async fn send_stream(&self, req: &SendRequest) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Started {
+            run_id: String,
+        }
+        let started: Started = self
+            .send_json(
+                self.request(reqwest::Method::POST, "/api/send_stream")
+                    .json(req),
+            )
+            .await?;
+        Ok(started.run_id)
+    }
+
+    pub async fn run_status(&self, run_id: &str) -> Result<RunStatus> {
+        self.send_json(
+            self.request(reqwest::Method::GET, "/api/run_status")
+                .query(&[("run_id", run_id)]),
+        )
+        .await
+    }
+
+    /// Subscribe to a run's events. Pass the last seen event id to resume
+    /// after a dropped connection; the server replays what was missed.
+    pub async fn stream_run(
+        &self,
+        run_id: &str,
+        last_event_id: Option<u64>,
+    ) -> Result<RunEventStream> {
+        let mut query = vec![("run_id", run_id.to_string())];
+        if let Some(id) = last_event_id {
+            query.push(("last_event_id", id.to_string()));
+        }
+        let resp = check_status(
+            self.request(reqwest::Method::GET, "/api/stream")
+                .query(&query)
+                .header(reqwest::header::ACCEPT, "text/event-stream")
+                .send()
+                .await?,
+        )
+        .await?;
+
+        let state = (
+            resp.bytes_stream().boxed(),
+            SseParser::default(),
+            VecDeque::<StreamedEvent>::new(),
+            false,
+        );
+        let stream = futures_util::stream::unfold(
+            state,
+            |(mut bytes, mut parser, mut queue, mut finished)| async move {
+                loop {
+                    if let Some(evt) = queue.pop_front() {
+                        if evt.event.is_terminal() {
+                            finished = true;
+                            queue.clear();
+                        }
+                        return Some((Ok(evt), (bytes, parser, queue, finished)));
+                    }
+                    if finished {
+                        return None;
+                    }
+                    match bytes.next().await {
+                        Some(Ok(chunk)) => {
+                            for frame in parser.push_chunk(&String::from_utf8_lossy(&chunk)) {
+                                queue.push_back(StreamedEvent {
+                                    id: frame.id,
+                                    event: RunEvent::decode(&frame.event, &frame.data),
+                                });
+                            }
+                        }
+                        Some(Err(e)) => {
+                            finished = true;
+                            return Some((Err(e.into()), (bytes, parser, queue, finished)));
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+        Ok(Box::pin(stream))
+    }
+
+    /// Send a message over the streaming API and return the final reply,
+    /// calling `on_event` for every event (deltas, tool calls, status).
+    pub async fn chat_with_events<F>(&self, req: SendRequest, mut on_event: F) -> Result<String>
+    where
+        F: FnMut(&RunEvent),
+    {
+        let run_id = self.send_stream(&req).await?;
+        let mut events = self.stream_run(&run_id, None).await?;
+        while let Some(item) = events.next().await {
+            let item = item?;
+            on_event(&item.event);
+            match item.event {
+                RunEvent::Done { response } => return Ok(response),
+                RunEvent::Error { error } => return Err(ClientError::Run(error)),
+                _ => {}
+            }
+        }
+        Err(ClientError::StreamEnded)
+    }
+
+    /// Send a message over the streaming API and return the final reply.
+    pub async fn chat(&self, req: SendRequest) -> Result<String> {
+        self.chat_with_events(req, |_| {}).await
+    }
+
+    /// Cancel the in-flight run of a session. Returns how many runs were signalled.
+    pub async fn stop(&self, session_key: &str) -> Result<usize> {
+        #[derive(Deserialize)]
+        struct Stopped {
+            cancelled: usize,
+        }
+        let resp: Stopped = self
+            .send_json(
+                self.request(reqwest::Method::POST, "/api/stop")
+                    .json(&SessionRequest { session_key }),
+            )
+            .await?;
+        Ok(resp.cancelled)
+    }
+
+    /// Clear a session's context. Returns whether anything was deleted.
+    pub async fn reset(&self, session_key: &str) -> Result<bool> {
+        self.post_deleted("/api/reset", session_key).await
+    }
+
+    pub async fn delete_session(&self, session_key: &str) -> Result<bool> {
+        self.post_deleted("/api/delete_session", session_key).await
+    }
+
+    async fn post_deleted(&self, path: &str, session_key: &str) -> Result<bool> {
+        #[derive(Deserialize)]
+        struct Deleted {
+            deleted: bool,
+        }
+        let resp: Deleted = self
+            .send_json(
+                self.request(reqwest::Method::POST, path)
+                    .json(&SessionRequest { session_key }),
+            )
+            .await?;
+        Ok(resp.deleted)
+    }
+}
+
+async fn check_status(resp: reqwest::Response) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(ClientError::Unauthorized);
+    }
+    if !status.is_success() {
+        let message = resp.text().await.unwrap_or_default();
+        return Err(ClientError::Api {
+            status: status.as_u16(),
+            message,
+        });
+    }
+    Ok(resp)
+}
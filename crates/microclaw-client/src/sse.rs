@@ -0,0 +1,99 @@
+/// A parsed server-sent event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseFrame {
+    pub id: Option<u64>,
+    pub event: String,
+    pub data: String,
+}
+
+/// Incremental SSE parser: feed raw chunks, get complete frames back.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    pending: String,
+    id: Option<u64>,
+    event: Option<String>,
+    data_lines: Vec<String>,
+}
+
+impl SseParser {
+    pub fn push_chunk(&mut self, chunk: &str) -> Vec<SseFrame> {
+        self.pending.push_str(chunk);
+        let mut frames = Vec::new();
+        while let Some(pos) = self.pending.find('\n') {
+            let mut line: String = self.pending.drain(..=pos).collect();
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+            if let Some(frame) = self.handle_line(&line) {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+
+    fn handle_line(&mut self, line: &str) -> Option<SseFrame> {
+        if line.is_empty() {
+            return self.flush();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((f, v)) => (f, v.strip_prefix(' ').unwrap_or(v)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data_lines.push(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            "id" => self.id = value.trim().parse().ok(),
+            _ => {}
+        }
+        None
+    }
+
+    fn flush(&mut self) -> Option<SseFrame> {
+        let event = self.event.take();
+        let id = self.id.take();
+        if self.data_lines.is_empty() {
+            return None;
+        }
+        let data = self.data_lines.join("\n");
+        self.data_lines.clear();
+        Some(SseFrame {
+            id,
+            event: event.unwrap_or_else(|| "message".to_string()),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_named_events_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push_chunk("id: 3\nevent: del").is_empty());
+        let frames = parser.push_chunk("ta\ndata: {\"delta\":\"hi\"}\n\n: keepalive\n\n");
+        assert_eq!(
+            frames,
+            vec![SseFrame {
+                id: Some(3),
+                event: "delta".into(),
+                data: "{\"delta\":\"hi\"}".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_multiline_data_and_default_event() {
+        let mut parser = SseParser::default();
+        let frames = parser.push_chunk("data: a\r\ndata: b\r\n\r\n");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].event, "message");
+        assert_eq!(frames[0].data, "a\nb");
+        assert_eq!(frames[0].id, None);
+    }
+}
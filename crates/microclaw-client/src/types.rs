@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Health {
+    /// False when a dependency check fails (the server answers 503).
+    #[serde(default)]
+    pub ok: bool,
+    pub version: String,
+    #[serde(default)]
+    pub web_enabled: bool,
+    /// Names of the failing checks.
+    #[serde(default)]
+    pub failures: Vec<String>,
+    /// Per-check details (`database`, `llm`, `scheduler`, `disk`, `telegram`).
+    #[serde(default)]
+    pub checks: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionItem {
+    pub session_key: String,
+    pub label: String,
+    pub chat_id: i64,
+    pub chat_type: String,
+    pub last_message_time: String,
+    #[serde(default)]
+    pub last_message_preview: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryItem {
+    pub id: String,
+    pub sender_name: String,
+    pub content: String,
+    pub is_from_bot: bool,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct History {
+    pub session_key: String,
+    pub chat_id: i64,
+    pub messages: Vec<HistoryItem>,
+}
+
+/// Body of `/api/send` and `/api/send_stream`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SendRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_name: Option<String>,
+    pub message: String,
+}
+
+impl SendRequest {
+    pub fn new(session_key: impl Into<String>, message: impl Into<String>) -> Self {
+        SendRequest {
+            session_key: Some(session_key.into()),
+            sender_name: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn with_sender_name(mut self, sender_name: impl Into<String>) -> Self {
+        self.sender_name = Some(sender_name.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SendResponse {
+    pub session_key: String,
+    pub chat_id: i64,
+    pub response: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Usage {
+    pub session_key: String,
+    pub chat_id: i64,
+    /// Human-readable report, the same text `/usage` returns in chat.
+    pub report: String,
+    #[serde(default)]
+    pub memory_observability: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunStatus {
+    pub run_id: String,
+    pub done: bool,
+    #[serde(default)]
+    pub last_event_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SessionRequest<'a> {
+    pub session_key: &'a str,
+}
+
+/// One event of a streamed run (`/api/stream`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunEvent {
+    ReplayMeta {
+        replay_truncated: bool,
+        oldest_event_id: Option<u64>,
+    },
+    Status {
+        message: String,
+    },
+    /// The message that started the run.
+    UserMessage {
+        message: String,
+    },
+    ToolStart {
+        name: String,
+    },
+    ToolResult {
+        name: String,
+        is_error: bool,
+        preview: String,
+        duration_ms: Option<u64>,
+    },
+    /// A high-risk tool call is waiting for confirmation.
+    ApprovalRequired {
+        name: String,
+        message: String,
+    },
+    Delta {
+        delta: String,
+    },
+    Done {
+        response: String,
+    },
+    Error {
+        error: String,
+    },
+    /// Event kinds this client version does not know yet.
+    Other {
+        event: String,
+        data: String,
+    },
+}
+
+impl RunEvent {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, RunEvent::Done { .. } | RunEvent::Error { .. })
+    }
+
+    pub(crate) fn decode(event: &str, data: &str) -> RunEvent {
+        let value: serde_json::Value = serde_json::from_str(data).unwrap_or_default();
+        let str_field = |key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        match event {
+            "replay_meta" => RunEvent::ReplayMeta {
+                replay_truncated: value
+                    .get("replay_truncated")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+                oldest_event_id: value.get("oldest_event_id").and_then(|v| v.as_u64()),
+            },
+            "status" => RunEvent::Status {
+                message: str_field("message"),
+            },
+            "user_message" => RunEvent::UserMessage {
+                message: str_field("message"),
+            },
+            "tool_start" => RunEvent::ToolStart {
+                name: str_field("name"),
+            },
+            "tool_result" => RunEvent::ToolResult {
+                name: str_field("name"),
+                is_error: value
+                    .get("is_error")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+                preview: str_field("preview"),
+                duration_ms: value.get("duration_ms").and_then(|v| v.as_u64()),
+            },
+            "approval_required" => RunEvent::ApprovalRequired {
+                name: str_field("name"),
+                message: str_field("message"),
+            },
+            "delta" => RunEvent::Delta {
+                delta: str_field("delta"),
+            },
+            "done" => RunEvent::Done {
+                response: str_field("response"),
+            },
+            "error" => RunEvent::Error {
+                error: str_field("error"),
+            },
+            _ => RunEvent::Other {
+                event: event.to_string(),
+                data: data.to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use axum::extract::Query;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use microclaw_client::{ClientError, MicroClawClient, RunEvent, SendRequest};
+use serde_json::json;
+
+const TOKEN: &str = "test-token";
+
+fn authorized(headers: &HeaderMap) -> bool {
+    headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == format!("Bearer {TOKEN}"))
+}
+
+async fn mock_server() -> String {
+    let app = Router::new()
+        .route(
+            "/api/health",
+            get(|headers: HeaderMap| async move {
+                if !authorized(&headers) {
+                    return Err((StatusCode::UNAUTHORIZED, "unauthorized"));
+                }
+                Ok((
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(json!({
+                        "ok": false,
+                        "version": "9.9.9",
+                        "web_enabled": true,
+                        "failures": ["disk"],
+                        "checks": {"disk": {"ok": false}, "database": {"ok": true}}
+                    })),
+                ))
+            }),
+        )
+        .route(
+            "/api/send_stream",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(body["session_key"], "main");
+                assert_eq!(body["message"], "hello");
+                Json(json!({"ok": true, "run_id": "run-1"}))
+            }),
+        )
+        .route(
+            "/api/stream",
+            get(
+                |Query(q): Query<std::collections::HashMap<String, String>>| async move {
+                    assert_eq!(q.get("run_id").map(String::as_str), Some("run-1"));
+                    let body = concat!(
+                        "event: replay_meta\ndata: {\"replay_truncated\":false}\n\n",
+                        "id: 1\nevent: user_message\ndata: {\"message\":\"hello\"}\n\n",
+                        "id: 2\nevent: tool_start\ndata: {\"name\":\"bash\"}\n\n",
+                        ": keepalive\n\n",
+                        "id: 3\nevent: delta\ndata: {\"delta\":\"Hel\"}\n\n",
+                        "id: 4\nevent: delta\ndata: {\"delta\":\"lo\"}\n\n",
+                        "id: 5\nevent: done\ndata: {\"response\":\"Hello\"}\n\n",
+                    );
+                    ([("content-type", "text/event-stream")], body).into_response()
+                },
+            ),
+        )
+        .route(
+            "/api/stop",
+            post(|| async { Json(json!({"ok": true, "cancelled": 1})) }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_health_sends_bearer_token() {
+    let base = mock_server().await;
+    let health = MicroClawClient::new(&base)
+        .with_auth_token(TOKEN)
+        .health()
+        .await
+        .unwrap();
+    assert_eq!(health.version, "9.9.9");
+    assert!(!health.ok);
+    assert_eq!(health.failures, vec!["disk".to_string()]);
+
+    let err = MicroClawClient::new(&base).health().await.unwrap_err();
+    assert!(matches!(err, ClientError::Unauthorized));
+}
+
+#[tokio::test]
+async fn test_chat_streams_events_until_done() {
+    let base = mock_server().await;
+    let client = MicroClawClient::new(format!("{base}/"));
+    let mut events = Vec::new();
+    let reply = client
+        .chat_with_events(SendRequest::new("main", "hello"), |e| {
+            events.push(e.clone())
+        })
+        .await
+        .unwrap();
+    assert_eq!(reply, "Hello");
+    let deltas: String = events
+        .iter()
+        .filter_map(|e| match e {
+            RunEvent::Delta { delta } => Some(delta.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(deltas, "Hello");
+    assert!(events.contains(&RunEvent::ToolStart {
+        name: "bash".into()
+    }));
+    assert_eq!(
+        events.get(1),
+        Some(&RunEvent::UserMessage {
+            message: "hello".into()
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_stop_returns_cancelled_count() {
+    let base = mock_server().await;
+    let cancelled = MicroClawClient::new(&base).stop("main").await.unwrap();
+    assert_eq!(cancelled, 1);
+}
@@ -25,6 +25,7 @@ impl FirecrackerClient {
         memory_mb: u32,
         vm_ip: &str,
         gateway_ip: &str,
+        netmask: u8,
         tap_device: &str,
         tenant_id: &str,
     ) -> Result<u32> {
@@ -55,7 +56,7 @@ impl FirecrackerClient {
         // 配置 boot source
         let boot_args = format!(
             "init=/init console=ttyS0 reboot=k panic=1 pci=off \
-             FC_VM_IP={vm_ip} FC_VM_GATEWAY={gateway_ip} FC_VM_NETMASK=30 \
+             FC_VM_IP={vm_ip} FC_VM_GATEWAY={gateway_ip} FC_VM_NETMASK={netmask} \
              FC_TENANT_ID={tenant_id} FC_DNS=8.8.8.8 FC_PORT=8080"
         );
 
@@ -221,8 +222,8 @@ impl FirecrackerClient {
     }
 }
 
-/// 根据 VM IP 生成 MAC 地址
-fn generate_mac(vm_ip: &str) -> String {
+/// 根据 VM IP 生成 MAC 地址 (bridge 模式的 ebtables 防伪造规则也用它)
+pub fn generate_mac(vm_ip: &str) -> String {
     let parts: Vec<u8> = vm_ip
         .split('.')
         .filter_map(|p| p.parse().ok())
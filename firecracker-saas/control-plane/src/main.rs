@@ -11,7 +11,7 @@ use tokio::sync::RwLock;
 use tracing_subscriber::EnvFilter;
 
 use crate::db::Database;
-use crate::network::SubnetAllocator;
+use crate::network::{NetworkMode, SubnetAllocator};
 use crate::tenant::TenantManager;
 
 pub struct AppState {
@@ -40,7 +40,19 @@ async fn main() -> anyhow::Result<()> {
     let db = Arc::new(Database::new(&db_path)?);
     tracing::info!("Database opened at {}", db_path);
 
-    let subnet_allocator = SubnetAllocator::new("172.16.0.0/16");
+    let network_mode = NetworkMode::from_env()?;
+    let subnet_allocator = SubnetAllocator::new("172.16.0.0/16", network_mode);
+    if let NetworkMode::Bridge { name } = subnet_allocator.mode() {
+        // 启动时创建 bridge 和共享 NAT 规则，之后创建租户只需挂 TAP
+        network::ensure_bridge(
+            name,
+            &subnet_allocator.bridge_gateway_ip(),
+            &subnet_allocator.bridge_subnet(),
+        )?;
+        tracing::info!("Network mode: bridge ({})", name);
+    } else {
+        tracing::info!("Network mode: nat (per-tenant /30)");
+    }
 
     let mut tenant_manager =
         TenantManager::new(fc_bin, vmlinux, rootfs, data_dir, snapshot_dir, subnet_allocator, db);
@@ -3,26 +3,112 @@ use std::process::Command;
 
 use anyhow::{bail, Result};
 
-/// 子网分配器: 为每个租户分配独立的 /30 子网
+/// 租户网络模式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// 每个租户一个 /30 子网 + 独立的 NAT/FORWARD 规则 (默认)
+    Nat,
+    /// 所有 TAP 挂到同一个 Linux bridge 上，整个 /16 共用一条 NAT 规则；
+    /// 租户之间通过 bridge 端口隔离 (isolated) + ebtables 防 IP 伪造隔离
+    Bridge { name: String },
+}
+
+impl NetworkMode {
+    /// 从 NETWORK_MODE (nat|bridge) 和 BRIDGE_NAME 环境变量读取
+    pub fn from_env() -> Result<Self> {
+        let mode = std::env::var("NETWORK_MODE").unwrap_or_else(|_| "nat".to_string());
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "nat" => Ok(NetworkMode::Nat),
+            "bridge" => Ok(NetworkMode::Bridge {
+                name: std::env::var("BRIDGE_NAME").unwrap_or_else(|_| "mcbr0".to_string()),
+            }),
+            other => bail!("invalid NETWORK_MODE '{}': expected 'nat' or 'bridge'", other),
+        }
+    }
+
+    /// VM 内 eth0 的前缀长度 (通过 FC_VM_NETMASK 传给 guest)
+    pub fn vm_netmask(&self) -> u8 {
+        match self {
+            NetworkMode::Nat => 30,
+            NetworkMode::Bridge { .. } => 16,
+        }
+    }
+}
+
+/// 子网分配器: NAT 模式下为每个租户分配独立的 /30 子网，
+/// bridge 模式下在共享的 /16 中为每个租户分配一个地址
 pub struct SubnetAllocator {
     base_network: String, // e.g. "172.16"
+    mode: NetworkMode,
     next_index: u16,
     allocated: HashMap<String, u16>, // tenant_id -> subnet index
 }
 
 impl SubnetAllocator {
-    pub fn new(cidr: &str) -> Self {
+    pub fn new(cidr: &str, mode: NetworkMode) -> Self {
         // 从 CIDR 提取基础网络 (简化: 只支持 172.16.0.0/16)
         let base = cidr.split('.').take(2).collect::<Vec<_>>().join(".");
 
         Self {
             base_network: base,
+            mode,
             next_index: 1,
             allocated: HashMap::new(),
         }
     }
 
-    /// 分配一个 /30 子网，返回 (gateway_ip, vm_ip)
+    pub fn mode(&self) -> &NetworkMode {
+        &self.mode
+    }
+
+    /// bridge 的网关地址 (bridge 模式下所有租户共用)
+    pub fn bridge_gateway_ip(&self) -> String {
+        format!("{}.0.1", self.base_network)
+    }
+
+    /// 整个租户网段，bridge 模式下用于唯一的一条 MASQUERADE 规则
+    pub fn bridge_subnet(&self) -> String {
+        format!("{}.0.0/16", self.base_network)
+    }
+
+    /// 根据 index 计算 (gateway_ip, vm_ip)
+    fn addresses(&self, index: u16) -> (String, String) {
+        match self.mode {
+            // 每个租户用一个 /30:
+            // 172.16.{index}.1 = gateway (host TAP)
+            // 172.16.{index}.2 = VM
+            NetworkMode::Nat => (
+                format!("{}.{}.1", self.base_network, index),
+                format!("{}.{}.2", self.base_network, index),
+            ),
+            // 共享 /16: 172.16.0.1 = bridge，index 1 → 172.16.0.2，依次递增
+            NetworkMode::Bridge { .. } => {
+                let host = u32::from(index) + 1;
+                (
+                    self.bridge_gateway_ip(),
+                    format!("{}.{}.{}", self.base_network, host / 256, host % 256),
+                )
+            }
+        }
+    }
+
+    /// 从 VM IP 反推 index (用于从 DB 恢复)
+    pub fn index_of(&self, vm_ip: &str) -> Option<u16> {
+        let parts: Vec<&str> = vm_ip.split('.').collect();
+        if parts.len() != 4 {
+            return None;
+        }
+        match self.mode {
+            NetworkMode::Nat => parts[2].parse::<u16>().ok(),
+            NetworkMode::Bridge { .. } => {
+                let hi = parts[2].parse::<u32>().ok()?;
+                let lo = parts[3].parse::<u32>().ok()?;
+                u16::try_from((hi * 256 + lo).checked_sub(1)?).ok()
+            }
+        }
+    }
+
+    /// 分配一个子网 (或 bridge 地址)，返回 (gateway_ip, vm_ip)
     pub fn allocate(&mut self, tenant_id: &str) -> Result<(String, String)> {
         if self.allocated.contains_key(tenant_id) {
             bail!("subnet already allocated for tenant '{}'", tenant_id);
@@ -36,11 +122,7 @@ impl SubnetAllocator {
         self.next_index += 1;
         self.allocated.insert(tenant_id.to_string(), index);
 
-        // 每个租户用一个 /30:
-        // 172.16.{index}.1 = gateway (host TAP)
-        // 172.16.{index}.2 = VM
-        let gateway_ip = format!("{}.{}.1", self.base_network, index);
-        let vm_ip = format!("{}.{}.2", self.base_network, index);
+        let (gateway_ip, vm_ip) = self.addresses(index);
 
         tracing::info!(
             "Allocated subnet for '{}': gateway={}, vm={}",
@@ -72,8 +154,17 @@ impl SubnetAllocator {
     }
 }
 
-/// 创建 TAP 网络设备
-pub fn create_tap_device(tap_name: &str, gateway_ip: &str) -> Result<()> {
+/// 创建 TAP 网络设备。bridge 模式下挂到共享 bridge，否则配置独立 /30 + NAT
+pub fn create_tap_device(
+    tap_name: &str,
+    gateway_ip: &str,
+    vm_ip: &str,
+    mode: &NetworkMode,
+) -> Result<()> {
+    if let NetworkMode::Bridge { name } = mode {
+        return attach_tap_to_bridge(tap_name, name, vm_ip);
+    }
+
     tracing::info!("Creating TAP device: {} (gateway={})", tap_name, gateway_ip);
 
     // 删除已存在的同名 TAP 设备 (忽略错误，可能不存在)
@@ -114,10 +205,137 @@ pub fn create_tap_device(tap_name: &str, gateway_ip: &str) -> Result<()> {
     Ok(())
 }
 
-/// 删除 TAP 网络设备及其关联的 iptables 规则
+/// 初始化共享 bridge (幂等): bridge 地址、IP 转发，以及整个租户网段唯一的一组 NAT/FORWARD 规则
+pub fn ensure_bridge(bridge: &str, gateway_ip: &str, subnet: &str) -> Result<()> {
+    if !std::path::Path::new(&format!("/sys/class/net/{}", bridge)).exists() {
+        tracing::info!("Creating bridge: {} (gateway={}, subnet={})", bridge, gateway_ip, subnet);
+        run_cmd("ip", &["link", "add", "name", bridge, "type", "bridge"])?;
+    }
+    if get_tap_gateway_ip(bridge).as_deref() != Some(gateway_ip) {
+        let prefix = subnet.rsplit('/').next().unwrap_or("16");
+        run_cmd("ip", &["addr", "add", &format!("{}/{}", gateway_ip, prefix), "dev", bridge])?;
+    }
+    run_cmd("ip", &["link", "set", bridge, "up"])?;
+    run_cmd("sysctl", &["-w", "net.ipv4.ip_forward=1"])?;
+
+    let host_iface = detect_host_interface()?;
+    ensure_iptables_rule(
+        &["-t", "nat"],
+        &["POSTROUTING", "-s", subnet, "-o", &host_iface, "-j", "MASQUERADE"],
+    )?;
+    // 经宿主机路由回 bridge 的租户间流量 (VM 把另一个租户的 IP 发给网关) 一律丢弃；
+    // 插到链首，避免被其他 FORWARD ACCEPT 规则 (如 docker) 抢先放行
+    ensure_iptables_rule_first(&["FORWARD", "-i", bridge, "-o", bridge, "-j", "DROP"])?;
+    ensure_iptables_rule(&[], &["FORWARD", "-i", bridge, "-o", &host_iface, "-j", "ACCEPT"])?;
+    ensure_iptables_rule(
+        &[],
+        &[
+            "FORWARD", "-i", &host_iface, "-o", bridge,
+            "-m", "state", "--state", "RELATED,ESTABLISHED", "-j", "ACCEPT",
+        ],
+    )?;
+    Ok(())
+}
+
+/// 仅当规则不存在时追加 (iptables -C 检查)，避免重复调用导致规则堆积
+fn ensure_iptables_rule(table: &[&str], rule: &[&str]) -> Result<()> {
+    let exists = Command::new("iptables")
+        .args(table)
+        .arg("-C")
+        .args(rule)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if exists {
+        return Ok(());
+    }
+    let mut args: Vec<&str> = table.to_vec();
+    args.push("-A");
+    args.extend_from_slice(rule);
+    run_cmd("iptables", &args)
+}
+
+/// 同 ensure_iptables_rule，但规则不存在时插到 filter 表对应链的最前面
+fn ensure_iptables_rule_first(rule: &[&str]) -> Result<()> {
+    let exists = Command::new("iptables")
+        .arg("-C")
+        .args(rule)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if exists {
+        return Ok(());
+    }
+    let mut args = vec!["-I"];
+    args.extend_from_slice(rule);
+    run_cmd("iptables", &args)
+}
+
+/// bridge 模式: 创建 TAP 并挂到共享 bridge 上。
+/// - isolated 端口之间不能互相转发，租户只能访问 bridge (网关)
+/// - ebtables 丢弃源 MAC/IP 不是本租户地址的帧，防止冒充其他租户；
+///   INPUT (发往宿主机) 和 FORWARD (经 bridge 转发) 两条链都装
+fn attach_tap_to_bridge(tap_name: &str, bridge: &str, vm_ip: &str) -> Result<()> {
+    tracing::info!("Creating TAP device: {} (bridge={}, vm={})", tap_name, bridge, vm_ip);
+
+    // 删除已存在的同名 TAP 设备及其 ebtables 规则 (忽略错误，可能不存在)
+    let _ = delete_ebtables_rules_by_interface(tap_name);
+    let _ = run_cmd("ip", &["link", "del", tap_name]);
+
+    run_cmd("ip", &["tuntap", "add", "dev", tap_name, "mode", "tap"])?;
+    run_cmd("ip", &["link", "set", tap_name, "master", bridge])?;
+    run_cmd("bridge", &["link", "set", "dev", tap_name, "isolated", "on"])?;
+    let vm_mac = crate::firecracker::generate_mac(vm_ip);
+    for chain in EBTABLES_CHAINS {
+        run_cmd(
+            "ebtables",
+            &["-A", chain, "-i", tap_name, "-s", "!", &vm_mac, "-j", "DROP"],
+        )?;
+        run_cmd(
+            "ebtables",
+            &["-A", chain, "-i", tap_name, "-p", "IPv4", "--ip-src", "!", vm_ip, "-j", "DROP"],
+        )?;
+        run_cmd(
+            "ebtables",
+            &["-A", chain, "-i", tap_name, "-p", "ARP", "--arp-ip-src", "!", vm_ip, "-j", "DROP"],
+        )?;
+        run_cmd(
+            "ebtables",
+            &["-A", chain, "-i", tap_name, "-p", "ARP", "--arp-mac-src", "!", &vm_mac, "-j", "DROP"],
+        )?;
+    }
+    run_cmd("ip", &["link", "set", tap_name, "up"])?;
+    Ok(())
+}
+
+/// 租户 TAP 的 ebtables 防伪造规则所在的链
+const EBTABLES_CHAINS: [&str; 2] = ["INPUT", "FORWARD"];
+
+/// 删除 ebtables INPUT/FORWARD 链中所有关联指定 TAP 的规则
+fn delete_ebtables_rules_by_interface(iface: &str) -> Result<()> {
+    let needle = format!("-i {} ", iface);
+    for chain in EBTABLES_CHAINS {
+        let output = Command::new("ebtables").args(["-L", chain, "--Lx"]).output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stdout.lines() {
+            if line.contains(&needle) {
+                // "ebtables -t filter -A INPUT ..." → "-t filter -D INPUT ..."
+                let delete_rule = line.replacen("-A ", "-D ", 1);
+                let args: Vec<&str> = delete_rule.split_whitespace().skip(1).collect();
+                let _ = Command::new("ebtables").args(&args).output();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 删除 TAP 网络设备及其关联的 iptables/ebtables 规则
 pub fn delete_tap_device(tap_name: &str) -> Result<()> {
     tracing::info!("Deleting TAP device: {}", tap_name);
 
+    // bridge 模式下的 TAP 没有 IP，也没有独立的 NAT 规则，只需清理 ebtables
+    let _ = delete_ebtables_rules_by_interface(tap_name);
+
     // 读取 TAP 设备的 gateway IP（用于推导子网，清理 NAT 规则）
     let gateway_ip = get_tap_gateway_ip(tap_name);
 
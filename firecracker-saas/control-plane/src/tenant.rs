@@ -143,8 +143,8 @@ impl TenantManager {
 
         let count = tenants.len();
         for mut tenant in tenants {
-            // Rebuild subnet allocation from vm_ip (parse 172.16.{index}.2)
-            if let Some(index) = parse_subnet_index(&tenant.vm_ip) {
+            // Rebuild subnet allocation from vm_ip (172.16.{index}.2 or bridge address)
+            if let Some(index) = self.subnet_allocator.index_of(&tenant.vm_ip) {
                 self.subnet_allocator
                     .restore_allocation(&tenant.id, index);
             }
@@ -279,7 +279,12 @@ impl TenantManager {
         tenant_data_dir: &str,
     ) -> Result<u32> {
         // 2. 创建 TAP 设备
-        crate::network::create_tap_device(tap_device, gateway_ip)?;
+        crate::network::create_tap_device(
+            tap_device,
+            gateway_ip,
+            vm_ip,
+            self.subnet_allocator.mode(),
+        )?;
 
         // 3. 创建数据卷
         std::fs::create_dir_all(tenant_data_dir)?;
@@ -311,6 +316,7 @@ impl TenantManager {
                 req.tier.memory_mb(),
                 vm_ip,
                 gateway_ip,
+                self.subnet_allocator.mode().vm_netmask(),
                 tap_device,
                 &req.tenant_id,
             )
@@ -387,6 +393,7 @@ impl TenantManager {
                 tenant.tier.memory_mb(),
                 &tenant.vm_ip,
                 &tenant.gateway_ip,
+                self.subnet_allocator.mode().vm_netmask(),
                 &tenant.tap_device,
                 &tenant.id,
             )
@@ -576,16 +583,6 @@ fn write_tenant_env(data_dir: &str, env_vars: &HashMap<String, String>) -> Resul
     result
 }
 
-/// Parse the subnet index from a VM IP like "172.16.{index}.2".
-fn parse_subnet_index(vm_ip: &str) -> Option<u16> {
-    let parts: Vec<&str> = vm_ip.split('.').collect();
-    if parts.len() == 4 {
-        parts[2].parse::<u16>().ok()
-    } else {
-        None
-    }
-}
-
 /// Check if a process with the given PID is alive.
 fn process_alive(pid: u32) -> bool {
     std::path::Path::new(&format!("/proc/{}", pid)).exists()
@@ -19,6 +19,27 @@ function writeIfChanged(file, content) {
   return false;
 }
 
+// Drop `#[cfg(test)] mod ... { ... }` blocks so test-only tools are not listed.
+function stripTestModules(text) {
+  const re = /#\[cfg\(test\)\]\s*mod\s+\w+\s*\{/g;
+  let out = '';
+  let last = 0;
+  let m;
+  while ((m = re.exec(text)) !== null) {
+    out += text.slice(last, m.index);
+    let depth = 1;
+    let i = m.index + m[0].length;
+    while (i < text.length && depth > 0) {
+      if (text[i] === '{') depth += 1;
+      else if (text[i] === '}') depth -= 1;
+      i += 1;
+    }
+    last = i;
+    re.lastIndex = i;
+  }
+  return out + text.slice(last);
+}
+
 function parseBuiltinTools() {
   const toolsDir = path.join(ROOT, 'src/tools');
   const files = fs
@@ -28,7 +49,7 @@ function parseBuiltinTools() {
   const names = new Set();
   const re = /fn\s+name\s*\(\s*&self\s*\)\s*->\s*&str\s*\{\s*"([^"]+)"\s*\}/g;
   for (const file of files) {
-    const text = fs.readFileSync(file, 'utf8');
+    const text = stripTestModules(fs.readFileSync(file, 'utf8'));
     let m;
     while ((m = re.exec(text)) !== null) {
       names.add(m[1]);
@@ -0,0 +1,306 @@
+//! Azure OpenAI authentication and `api-version` handling
+//! (`llm_provider: azure`).
+//!
+//! Requests go through the OpenAI-compatible provider. With `api_key` they
+//! carry the `api-key` header; otherwise a Microsoft Entra ID access token
+//! for Cognitive Services is fetched with client credentials or from the
+//! managed identity endpoint (App Service / Functions or the VM's IMDS) and
+//! cached until shortly before it expires.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+use crate::error::MicroClawError;
+
+/// Accepted values for `azure.auth`.
+pub const AUTH_MODES: &[&str] = &["api_key", "client_secret", "managed_identity"];
+/// `api-version` used for `/openai/deployments/<name>` URLs by default.
+pub const DEFAULT_API_VERSION: &str = "2024-10-21";
+const RESOURCE: &str = "https://cognitiveservices.azure.com";
+const DEFAULT_AUTHORITY: &str = "https://login.microsoftonline.com";
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+/// Refresh tokens this long before they expire.
+const EXPIRY_MARGIN_SECS: i64 = 300;
+
+pub fn is_azure_provider(provider: &str) -> bool {
+    provider.trim().eq_ignore_ascii_case("azure")
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+fn env_var(name: &str) -> Option<String> {
+    non_empty(std::env::var(name).ok().as_deref())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AzureAuth {
+    ApiKey(String),
+    ClientSecret {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+    ManagedIdentity {
+        client_id: Option<String>,
+    },
+}
+
+impl AzureAuth {
+    /// How to authenticate: `azure.auth` if set, else the API key, else
+    /// client credentials when complete, else the managed identity.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let azure = &config.azure;
+        let tenant_id =
+            non_empty(azure.tenant_id.as_deref()).or_else(|| env_var("AZURE_TENANT_ID"));
+        let client_id =
+            non_empty(azure.client_id.as_deref()).or_else(|| env_var("AZURE_CLIENT_ID"));
+        let client_secret =
+            non_empty(azure.client_secret.as_deref()).or_else(|| env_var("AZURE_CLIENT_SECRET"));
+        let api_key = non_empty(Some(&config.api_key));
+        let mode = match azure.auth.as_deref() {
+            Some(mode) => mode,
+            None if api_key.is_some() => "api_key",
+            None if client_secret.is_some() => "client_secret",
+            None => "managed_identity",
+        };
+        match mode {
+            "api_key" => api_key
+                .map(AzureAuth::ApiKey)
+                .ok_or_else(|| "azure.auth is api_key but api_key is empty".to_string()),
+            "client_secret" => match (tenant_id, client_id, client_secret) {
+                (Some(tenant_id), Some(client_id), Some(client_secret)) => {
+                    Ok(AzureAuth::ClientSecret {
+                        tenant_id,
+                        client_id,
+                        client_secret,
+                    })
+                }
+                _ => Err(
+                    "Azure client credentials need azure.tenant_id, azure.client_id and azure.client_secret (or AZURE_TENANT_ID / AZURE_CLIENT_ID / AZURE_CLIENT_SECRET)"
+                        .into(),
+                ),
+            },
+            _ => Ok(AzureAuth::ManagedIdentity { client_id }),
+        }
+    }
+}
+
+/// `url` with the `api-version` query parameter Azure expects.
+pub fn with_api_version(url: &str, configured: Option<&str>) -> String {
+    if url.contains("api-version=") {
+        return url.to_string();
+    }
+    let version = match non_empty(configured) {
+        Some(version) => version,
+        None if url.contains("/openai/deployments/") => DEFAULT_API_VERSION.to_string(),
+        None => return url.to_string(),
+    };
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}api-version={version}")
+}
+
+/// Access token and expiry from a token endpoint response. Entra returns
+/// `expires_in` (seconds); managed identity endpoints return `expires_on`
+/// (epoch seconds), both sometimes as strings.
+fn parse_token_response(value: &Value, now: DateTime<Utc>) -> Option<(String, DateTime<Utc>)> {
+    let number = |key: &str| {
+        let field = value.get(key)?;
+        field
+            .as_i64()
+            .or_else(|| field.as_str().and_then(|s| s.trim().parse().ok()))
+    };
+    let token = value.get("access_token")?.as_str()?.to_string();
+    let expires_at = number("expires_on")
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .or_else(|| number("expires_in").map(|secs| now + chrono::Duration::seconds(secs)))
+        .unwrap_or(now + chrono::Duration::seconds(3600));
+    Some((token, expires_at))
+}
+
+/// Authenticates Azure OpenAI requests, caching Entra ID tokens.
+pub struct AzureCredential {
+    http: reqwest::Client,
+    auth: Result<AzureAuth, String>,
+    token: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+impl AzureCredential {
+    pub fn new(config: &Config) -> Self {
+        AzureCredential {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            auth: AzureAuth::from_config(config),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Add the `api-key` or `Authorization: Bearer` header to `request`.
+    pub async fn authorize(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder, MicroClawError> {
+        let auth = self
+            .auth
+            .as_ref()
+            .map_err(|e| MicroClawError::LlmApi(e.clone()))?;
+        if let AzureAuth::ApiKey(key) = auth {
+            return Ok(request.header("api-key", key));
+        }
+        let mut cached = self.token.lock().await;
+        let fresh = cached.as_ref().filter(|(_, expires_at)| {
+            *expires_at - chrono::Duration::seconds(EXPIRY_MARGIN_SECS) > Utc::now()
+        });
+        let token = match fresh {
+            Some((token, _)) => token.clone(),
+            None => {
+                let (token, expires_at) = self.fetch_token(auth).await.map_err(|e| {
+                    MicroClawError::LlmApi(format!("Azure Entra ID token request failed: {e}"))
+                })?;
+                *cached = Some((token.clone(), expires_at));
+                token
+            }
+        };
+        Ok(request.bearer_auth(token))
+    }
+
+    async fn fetch_token(&self, auth: &AzureAuth) -> Result<(String, DateTime<Utc>), String> {
+        let request = match auth {
+            AzureAuth::ApiKey(_) => return Err("API key auth does not use tokens".into()),
+            AzureAuth::ClientSecret {
+                tenant_id,
+                client_id,
+                client_secret,
+            } => {
+                let authority =
+                    env_var("AZURE_AUTHORITY_HOST").unwrap_or_else(|| DEFAULT_AUTHORITY.into());
+                let scope = format!("{RESOURCE}/.default");
+                self.http
+                    .post(format!(
+                        "{}/{tenant_id}/oauth2/v2.0/token",
+                        authority.trim_end_matches('/')
+                    ))
+                    .form(&[
+                        ("grant_type", "client_credentials"),
+                        ("client_id", client_id.as_str()),
+                        ("client_secret", client_secret.as_str()),
+                        ("scope", scope.as_str()),
+                    ])
+            }
+            AzureAuth::ManagedIdentity { client_id } => {
+                let (request, version) =
+                    match (env_var("IDENTITY_ENDPOINT"), env_var("IDENTITY_HEADER")) {
+                        // App Service, Functions and Container Apps.
+                        (Some(endpoint), Some(header)) => (
+                            self.http.get(endpoint).header("X-IDENTITY-HEADER", header),
+                            "2019-08-01",
+                        ),
+                        _ => (
+                            self.http.get(IMDS_TOKEN_URL).header("Metadata", "true"),
+                            "2018-02-01",
+                        ),
+                    };
+                let mut query = vec![("api-version", version), ("resource", RESOURCE)];
+                if let Some(client_id) = client_id {
+                    query.push(("client_id", client_id.as_str()));
+                }
+                request.query(&query)
+            }
+        };
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        let value: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+        if !status.is_success() {
+            let detail = value
+                .get("error_description")
+                .or_else(|| value.get("message"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("HTTP {status}: {text}"));
+            return Err(detail);
+        }
+        parse_token_response(&value, Utc::now())
+            .ok_or_else(|| "token response has no access_token".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_with_api_version() {
+        let deployment = "https://r.openai.azure.com/openai/deployments/gpt/chat/completions";
+        assert_eq!(
+            with_api_version(deployment, None),
+            format!("{deployment}?api-version={DEFAULT_API_VERSION}")
+        );
+        assert_eq!(
+            with_api_version(deployment, Some("2025-04-01-preview")),
+            format!("{deployment}?api-version=2025-04-01-preview")
+        );
+        let v1 = "https://r.openai.azure.com/openai/v1/chat/completions";
+        assert_eq!(with_api_version(v1, None), v1);
+        let explicit = format!("{deployment}?api-version=2024-06-01");
+        assert_eq!(with_api_version(&explicit, Some("x")), explicit);
+    }
+
+    #[test]
+    fn test_auth_from_config() {
+        let mut config: Config = serde_yaml::from_str(
+            "llm_provider: azure\napi_key: key\nazure:\n  tenant_id: t\n  client_id: c\n",
+        )
+        .unwrap();
+        assert_eq!(
+            AzureAuth::from_config(&config),
+            Ok(AzureAuth::ApiKey("key".into()))
+        );
+        config.azure.auth = Some("client_secret".into());
+        config.azure.client_secret = Some("s".into());
+        assert_eq!(
+            AzureAuth::from_config(&config),
+            Ok(AzureAuth::ClientSecret {
+                tenant_id: "t".into(),
+                client_id: "c".into(),
+                client_secret: "s".into(),
+            })
+        );
+        config.azure.auth = Some("managed_identity".into());
+        assert_eq!(
+            AzureAuth::from_config(&config),
+            Ok(AzureAuth::ManagedIdentity {
+                client_id: Some("c".into())
+            })
+        );
+        config.azure.auth = Some("api_key".into());
+        config.api_key.clear();
+        assert!(AzureAuth::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_parse_token_response() {
+        let now = Utc::now();
+        let (token, expires_at) =
+            parse_token_response(&json!({"access_token": "a", "expires_in": 3599}), now).unwrap();
+        assert_eq!(token, "a");
+        assert_eq!(expires_at, now + chrono::Duration::seconds(3599));
+        let (_, expires_at) = parse_token_response(
+            &json!({"access_token": "b", "expires_on": "1893456000"}),
+            now,
+        )
+        .unwrap();
+        assert_eq!(expires_at.timestamp(), 1_893_456_000);
+        assert!(parse_token_response(&json!({"error": "x"}), now).is_none());
+    }
+}
@@ -0,0 +1,293 @@
+//! `microclaw db backup` / `microclaw db restore` (and `db prune`, see
+//! `retention.rs`).
+//!
+//! A backup is a directory holding a consistent copy of the SQLite database
+//! (taken with the online backup API, so it is safe while the bot runs), the
+//! memory files under `runtime/groups`, the skills directory and a
+//! `manifest.json`. Restoring first backs up the current state under
+//! `runtime/backups/`, then puts the snapshot in place.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::db::{self, DATABASE_FILE};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const MEMORY_DIR: &str = "groups";
+const SKILLS_DIR: &str = "skills";
+
+const DB_USAGE: &str = "Usage: microclaw db backup <dir>
+       microclaw db restore <dir> --yes
+       microclaw db prune [--dry-run]
+       microclaw db encrypt|decrypt --yes
+
+backup   Write a consistent snapshot of the database, memory files and
+         skills to <dir>, which must not exist or be empty. Safe while the
+         bot is running.
+restore  Replace the database, memory files and skills with the snapshot
+         in <dir>. Stop the bot first. The current state is backed up to
+         <data_dir>/runtime/backups/ before anything is replaced.
+prune    Apply the `retention:` limits now; --dry-run only reports what
+         would be removed.
+encrypt  Encrypt the database in place with a passphrase (SQLCipher builds
+         only), for use with `db_encryption: true`. Stop the bot first.
+decrypt  Turn an encrypted database back into plaintext. Stop the bot
+         first.";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    microclaw_version: String,
+    schema_version: i64,
+    created_at: String,
+    #[serde(default)]
+    memory: bool,
+    #[serde(default)]
+    skills: bool,
+}
+
+/// The directories a backup reads and a restore replaces.
+pub struct DataPaths {
+    pub runtime_dir: PathBuf,
+    pub skills_dir: PathBuf,
+}
+
+impl DataPaths {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            runtime_dir: PathBuf::from(config.runtime_data_dir()),
+            skills_dir: PathBuf::from(config.skills_data_dir()),
+        }
+    }
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if path.is_dir() {
+            copy_dir(&path, &dst.join(name))?;
+        } else if path.is_file() {
+            std::fs::copy(&path, dst.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Check the database snapshot in a backup directory; returns its schema
+/// version.
+fn check_snapshot(path: &Path) -> anyhow::Result<i64> {
+    let (integrity, version) = db::inspect_database_snapshot(path)
+        .with_context(|| format!("cannot open {}", path.display()))?;
+    if integrity != "ok" {
+        bail!("{} failed the integrity check: {integrity}", path.display());
+    }
+    if version > db::current_schema_version() {
+        bail!(
+            "{} has schema version {version}; this build supports up to {}",
+            path.display(),
+            db::current_schema_version()
+        );
+    }
+    Ok(version)
+}
+
+fn backup(paths: &DataPaths, dest: &Path) -> anyhow::Result<Manifest> {
+    let live_db = paths.runtime_dir.join(DATABASE_FILE);
+    if !live_db.is_file() {
+        bail!("no database at {}", live_db.display());
+    }
+    if dest.exists()
+        && std::fs::read_dir(dest)
+            .with_context(|| format!("{} is not a directory", dest.display()))?
+            .next()
+            .is_some()
+    {
+        bail!("{} already exists and is not empty", dest.display());
+    }
+    std::fs::create_dir_all(dest)?;
+
+    let snapshot = dest.join(DATABASE_FILE);
+    db::backup_database(&paths.runtime_dir, &snapshot).context("database backup failed")?;
+    let schema_version = check_snapshot(&snapshot)?;
+
+    let memory_dir = paths.runtime_dir.join(MEMORY_DIR);
+    let memory = memory_dir.is_dir();
+    if memory {
+        copy_dir(&memory_dir, &dest.join(MEMORY_DIR)).context("copying memory files failed")?;
+    }
+    let skills = paths.skills_dir.is_dir();
+    if skills {
+        copy_dir(&paths.skills_dir, &dest.join(SKILLS_DIR)).context("copying skills failed")?;
+    }
+
+    let manifest = Manifest {
+        microclaw_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        memory,
+        skills,
+    };
+    std::fs::write(
+        dest.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+/// Restore the backup at `src`. Returns its manifest and where the state it
+/// replaced was saved, if there was any.
+fn restore(paths: &DataPaths, src: &Path) -> anyhow::Result<(Manifest, Option<PathBuf>)> {
+    let manifest: Manifest = serde_json::from_str(
+        &std::fs::read_to_string(src.join(MANIFEST_FILE))
+            .with_context(|| format!("{} is not a MicroClaw backup", src.display()))?,
+    )
+    .with_context(|| format!("invalid {MANIFEST_FILE} in {}", src.display()))?;
+    let snapshot = src.join(DATABASE_FILE);
+    check_snapshot(&snapshot)?;
+
+    let previous = if paths.runtime_dir.join(DATABASE_FILE).is_file() {
+        let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+        let dir = paths
+            .runtime_dir
+            .join("backups")
+            .join(format!("pre-restore-{stamp}"));
+        backup(paths, &dir).context("backing up the current state failed; nothing was restored")?;
+        Some(dir)
+    } else {
+        None
+    };
+
+    db::restore_database(&snapshot, &paths.runtime_dir).context("database restore failed")?;
+    let dirs = [
+        (
+            manifest.memory,
+            MEMORY_DIR,
+            paths.runtime_dir.join(MEMORY_DIR),
+        ),
+        (manifest.skills, SKILLS_DIR, paths.skills_dir.clone()),
+    ];
+    for (included, name, live) in dirs {
+        if !included {
+            continue;
+        }
+        if live.exists() {
+            std::fs::remove_dir_all(&live)
+                .with_context(|| format!("cannot replace {}", live.display()))?;
+        }
+        copy_dir(&src.join(name), &live).with_context(|| format!("restoring {name} failed"))?;
+    }
+    Ok((manifest, previous))
+}
+
+pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
+    let positional: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !a.starts_with('-'))
+        .collect();
+    let confirmed = args.iter().any(|a| a == "--yes" || a == "-y");
+    match positional.as_slice() {
+        ["backup", dir] => {
+            let config = Config::load()?;
+            crate::db_crypt::init(&config)?;
+            let manifest = backup(&DataPaths::from_config(&config), Path::new(dir))?;
+            println!(
+                "Backed up schema v{} database{}{} to {dir}",
+                manifest.schema_version,
+                if manifest.memory { ", memory" } else { "" },
+                if manifest.skills { ", skills" } else { "" },
+            );
+        }
+        ["restore", dir] if confirmed => {
+            let config = Config::load()?;
+            crate::db_crypt::init(&config)?;
+            let (manifest, previous) = restore(&DataPaths::from_config(&config), Path::new(dir))?;
+            println!(
+                "Restored the backup from {} (MicroClaw {}).",
+                manifest.created_at, manifest.microclaw_version
+            );
+            if let Some(previous) = previous {
+                println!("The previous state was saved to {}", previous.display());
+            }
+        }
+        ["restore", _] => {
+            eprintln!(
+                "Restoring replaces the database, memory files and skills. Stop MicroClaw, then re-run with --yes."
+            );
+            std::process::exit(1);
+        }
+        [action @ ("encrypt" | "decrypt")] if confirmed => {
+            let config = Config::load()?;
+            crate::db_crypt::run_convert_cli(
+                Path::new(&config.runtime_data_dir()),
+                *action == "encrypt",
+            )?;
+        }
+        ["encrypt" | "decrypt"] => {
+            eprintln!("This rewrites the database file. Stop MicroClaw, then re-run with --yes.");
+            std::process::exit(1);
+        }
+        ["prune"] => crate::retention::run_cli(args.iter().any(|a| a == "--dry-run"))?,
+        [] | ["help"] => println!("{DB_USAGE}"),
+        _ => {
+            eprintln!("{DB_USAGE}");
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_backup_and_restore_round_trip() {
+        let root = std::env::temp_dir().join(format!("mc_backup_{}", uuid::Uuid::new_v4()));
+        let paths = DataPaths {
+            runtime_dir: root.join("runtime"),
+            skills_dir: root.join("skills"),
+        };
+        let db = Database::new(paths.runtime_dir.to_str().unwrap()).unwrap();
+        db.save_session(7, "[\"before\"]").unwrap();
+        std::fs::create_dir_all(paths.runtime_dir.join("groups/7")).unwrap();
+        std::fs::write(paths.runtime_dir.join("groups/7/AGENTS.md"), "before").unwrap();
+        std::fs::create_dir_all(paths.skills_dir.join("demo")).unwrap();
+        std::fs::write(paths.skills_dir.join("demo/SKILL.md"), "skill").unwrap();
+
+        let dest = root.join("snap");
+        let manifest = backup(&paths, &dest).unwrap();
+        assert!(manifest.memory && manifest.skills);
+        assert!(backup(&paths, &dest)
+            .unwrap_err()
+            .to_string()
+            .contains("not empty"));
+
+        db.save_session(7, "[\"after\"]").unwrap();
+        std::fs::write(paths.runtime_dir.join("groups/7/AGENTS.md"), "after").unwrap();
+        std::fs::remove_dir_all(paths.skills_dir.join("demo")).unwrap();
+
+        let (_, previous) = restore(&paths, &dest).unwrap();
+        assert_eq!(db.load_session(7).unwrap().unwrap().0, "[\"before\"]");
+        assert_eq!(
+            std::fs::read_to_string(paths.runtime_dir.join("groups/7/AGENTS.md")).unwrap(),
+            "before"
+        );
+        assert!(paths.skills_dir.join("demo/SKILL.md").is_file());
+        let previous = previous.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(previous.join("groups/7/AGENTS.md")).unwrap(),
+            "after"
+        );
+
+        assert!(restore(&paths, &root.join("missing")).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,871 @@
+//! Native AWS Bedrock provider (`llm_provider: bedrock`).
+//!
+//! Calls the Bedrock Runtime Converse API with SigV4-signed requests instead
+//! of the OpenAI-compatible endpoint, which needs a Bedrock API key. AWS
+//! credentials are resolved the way the AWS SDKs do it: environment
+//! variables, then the shared credentials file (`aws_profile` /
+//! `AWS_PROFILE`), then container or EC2 instance credentials (IMDSv2).
+//! Temporary credentials are cached until shortly before they expire.
+//!
+//! The region comes from `aws_region`, an `llm_base_url` like
+//! `https://bedrock-runtime.<region>.amazonaws.com`, `AWS_REGION` /
+//! `AWS_DEFAULT_REGION`, or the profile's `region` in `~/.aws/config`. An `llm_base_url` pointing
+//! at the `/openai/v1` endpoint keeps using the OpenAI-compatible provider.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+use crate::error::MicroClawError;
+use crate::llm::{sanitize_messages, LlmProvider};
+use crate::llm_types::{
+    ContentBlock, Message, MessageContent, MessagesResponse, ResponseContentBlock, ToolDefinition,
+    Usage,
+};
+
+const SERVICE: &str = "bedrock";
+const IMDS_BASE: &str = "http://169.254.169.254/latest";
+const ECS_CREDENTIALS_HOST: &str = "http://169.254.170.2";
+/// Refresh temporary credentials this long before they expire.
+const EXPIRY_MARGIN_SECS: i64 = 300;
+
+/// Whether `llm_provider: bedrock` should use the native Converse API rather
+/// than the OpenAI-compatible endpoint configured in `llm_base_url`.
+pub fn uses_native_api(config: &Config) -> bool {
+    !config
+        .llm_base_url
+        .as_deref()
+        .is_some_and(|url| url.contains("/openai"))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl AwsCredentials {
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|at| at - chrono::Duration::seconds(EXPIRY_MARGIN_SECS) > Utc::now())
+    }
+
+    /// Parse the JSON returned by IMDS and the ECS credentials endpoint.
+    fn from_metadata_json(value: &Value) -> Option<Self> {
+        let field = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+        Some(AwsCredentials {
+            access_key_id: field("AccessKeyId")?,
+            secret_access_key: field("SecretAccessKey")?,
+            session_token: field("Token"),
+            expires_at: field("Expiration")
+                .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                .map(|t| t.with_timezone(&Utc)),
+        })
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn aws_file(env: &str, name: &str) -> Option<String> {
+    let path =
+        env_var(env).or_else(|| env_var("HOME").map(|home| format!("{home}/.aws/{name}")))?;
+    std::fs::read_to_string(path).ok()
+}
+
+/// Key/value pairs of one `[section]` of an AWS INI file.
+fn ini_section(text: &str, section: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut in_section = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name.trim() == section;
+            continue;
+        }
+        if in_section {
+            if let Some((key, value)) = line.split_once('=') {
+                values.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+    }
+    values
+}
+
+fn profile_name(profile: Option<&str>) -> String {
+    profile
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .or_else(|| env_var("AWS_PROFILE"))
+        .unwrap_or_else(|| "default".into())
+}
+
+/// `~/.aws/config` names profiles `[profile name]`, except `[default]`.
+fn config_section(profile: &str) -> String {
+    if profile == "default" {
+        profile.to_string()
+    } else {
+        format!("profile {profile}")
+    }
+}
+
+fn credentials_from_section(values: &HashMap<String, String>) -> Option<AwsCredentials> {
+    Some(AwsCredentials {
+        access_key_id: values.get("aws_access_key_id")?.clone(),
+        secret_access_key: values.get("aws_secret_access_key")?.clone(),
+        session_token: values.get("aws_session_token").cloned(),
+        expires_at: None,
+    })
+}
+
+/// Credentials from the environment or the shared credentials/config files,
+/// with where they came from.
+pub fn static_credentials(profile: Option<&str>) -> Option<(AwsCredentials, String)> {
+    if let (Some(access_key_id), Some(secret_access_key)) = (
+        env_var("AWS_ACCESS_KEY_ID"),
+        env_var("AWS_SECRET_ACCESS_KEY"),
+    ) {
+        let creds = AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: env_var("AWS_SESSION_TOKEN"),
+            expires_at: None,
+        };
+        return Some((creds, "environment".into()));
+    }
+    let profile = profile_name(profile);
+    if let Some(creds) = aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials")
+        .and_then(|text| credentials_from_section(&ini_section(&text, &profile)))
+    {
+        return Some((creds, format!("profile {profile}")));
+    }
+    aws_file("AWS_CONFIG_FILE", "config")
+        .and_then(|text| credentials_from_section(&ini_section(&text, &config_section(&profile))))
+        .map(|creds| (creds, format!("profile {profile}")))
+}
+
+/// Region for Bedrock requests (`aws_region`, then the endpoint host, the
+/// environment and the profile), or `None` when nothing sets one.
+pub fn resolve_region(
+    aws_region: Option<&str>,
+    base_url: Option<&str>,
+    profile: Option<&str>,
+) -> Option<String> {
+    aws_region
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            let url = reqwest::Url::parse(base_url?).ok()?;
+            let host = url.host_str()?;
+            let region = host.strip_prefix("bedrock-runtime.")?.split('.').next()?;
+            Some(region.to_string())
+        })
+        .or_else(|| env_var("AWS_REGION"))
+        .or_else(|| env_var("AWS_DEFAULT_REGION"))
+        .or_else(|| {
+            let text = aws_file("AWS_CONFIG_FILE", "config")?;
+            let profile = profile_name(profile);
+            ini_section(&text, &config_section(&profile))
+                .remove("region")
+                .filter(|r| !r.is_empty())
+        })
+}
+
+/// Credentials from the ECS task role endpoint or the EC2 instance role.
+async fn instance_credentials(http: &reqwest::Client) -> Result<AwsCredentials, String> {
+    let ecs_url = env_var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI")
+        .map(|uri| format!("{ECS_CREDENTIALS_HOST}{uri}"))
+        .or_else(|| env_var("AWS_CONTAINER_CREDENTIALS_FULL_URI"));
+    if let Some(url) = ecs_url {
+        let mut request = http.get(url);
+        if let Some(token) = env_var("AWS_CONTAINER_AUTHORIZATION_TOKEN") {
+            request = request.header("authorization", token);
+        }
+        let value: Value = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("container credentials: {e}"))?
+            .json()
+            .await
+            .map_err(|e| format!("container credentials: {e}"))?;
+        return AwsCredentials::from_metadata_json(&value)
+            .ok_or_else(|| "container credentials: unexpected response".into());
+    }
+
+    if env_var("AWS_EC2_METADATA_DISABLED").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+        return Err("instance metadata is disabled (AWS_EC2_METADATA_DISABLED)".into());
+    }
+    let token = http
+        .put(format!("{IMDS_BASE}/api/token"))
+        .header("x-aws-ec2-metadata-token-ttl-seconds", "21600")
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("instance metadata: {e}"))?
+        .text()
+        .await
+        .map_err(|e| format!("instance metadata: {e}"))?;
+    let get = |path: String| {
+        http.get(format!(
+            "{IMDS_BASE}/meta-data/iam/security-credentials/{path}"
+        ))
+        .header("x-aws-ec2-metadata-token", token.clone())
+        .send()
+    };
+    let roles = get(String::new())
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("instance role: {e}"))?
+        .text()
+        .await
+        .map_err(|e| format!("instance role: {e}"))?;
+    let role = roles
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .ok_or("no IAM role attached to this instance")?
+        .to_string();
+    let value: Value = get(role)
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("instance role credentials: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("instance role credentials: {e}"))?;
+    AwsCredentials::from_metadata_json(&value)
+        .ok_or_else(|| "instance role credentials: unexpected response".into())
+}
+
+// ---------------------------------------------------------------------------
+// SigV4
+// ---------------------------------------------------------------------------
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// RFC 3986 encoding as SigV4 wants it (unreserved characters kept).
+fn uri_encode(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// A request to sign. `path` is the path as sent (already percent-encoded;
+/// non-S3 services encode it once more for signing), `query` is canonical,
+/// and `headers` are lowercase names including `host` and `x-amz-date`.
+struct SigningRequest<'a> {
+    method: &'a str,
+    path: &'a str,
+    query: &'a str,
+    headers: Vec<(String, String)>,
+    payload: &'a [u8],
+}
+
+/// `(signed_headers, signature)` for `request`.
+fn signature(
+    request: &SigningRequest,
+    creds: &AwsCredentials,
+    region: &str,
+    service: &str,
+    amz_date: &str,
+) -> (String, String) {
+    let canonical_path = request
+        .path
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut headers = request.headers.clone();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_request = format!(
+        "{}\n{canonical_path}\n{}\n{canonical_headers}\n{signed_headers}\n{}",
+        request.method,
+        request.query,
+        sha256_hex(request.payload)
+    );
+    let date = &amz_date[..8];
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        sha256_hex(canonical_request.as_bytes())
+    );
+    let mut key = hmac_sha256(format!("AWS4{}", creds.secret_access_key).as_bytes(), date);
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part);
+    }
+    (signed_headers, hex(&hmac_sha256(&key, &string_to_sign)))
+}
+
+/// Headers to add to a JSON `POST` to `url` so Bedrock accepts it.
+pub fn sign_post(
+    creds: &AwsCredentials,
+    url: &reqwest::Url,
+    payload: &[u8],
+    region: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut headers = vec![
+        ("content-type".to_string(), "application/json".to_string()),
+        ("host".to_string(), host),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = &creds.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    let request = SigningRequest {
+        method: "POST",
+        path: url.path(),
+        query: url.query().unwrap_or_default(),
+        headers,
+        payload,
+    };
+    let (signed_headers, signature) = signature(&request, creds, region, SERVICE, &amz_date);
+    let scope = format!("{}/{region}/{SERVICE}/aws4_request", &amz_date[..8]);
+    let mut out = vec![
+        ("x-amz-date", amz_date),
+        (
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                creds.access_key_id
+            ),
+        ),
+    ];
+    if let Some(token) = &creds.session_token {
+        out.push(("x-amz-security-token", token.clone()));
+    }
+    out
+}
+
+/// Converse endpoint for `model` under `base` (`https://bedrock-runtime.<region>.amazonaws.com`).
+pub fn converse_url(base: &str, model: &str) -> String {
+    format!(
+        "{}/model/{}/converse",
+        base.trim_end_matches('/'),
+        uri_encode(model)
+    )
+}
+
+/// Error message from a failed Bedrock response body.
+pub fn error_message(status: reqwest::StatusCode, text: &str) -> String {
+    serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|v| {
+            v.get("message")
+                .or_else(|| v.get("Message"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| format!("HTTP {status}: {text}"))
+}
+
+// ---------------------------------------------------------------------------
+// Provider
+// ---------------------------------------------------------------------------
+
+pub struct BedrockProvider {
+    http: reqwest::Client,
+    metadata_http: reqwest::Client,
+    model: String,
+    max_tokens: u32,
+    thinking_budget: u32,
+    temperature: Option<f64>,
+    region: Option<String>,
+    profile: Option<String>,
+    base_url: Option<String>,
+    credentials: Mutex<Option<AwsCredentials>>,
+}
+
+impl BedrockProvider {
+    pub fn new(config: &Config) -> Self {
+        BedrockProvider {
+            http: reqwest::Client::new(),
+            metadata_http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .unwrap_or_default(),
+            model: config.model.trim().to_string(),
+            max_tokens: config.max_tokens,
+            thinking_budget: config.thinking.budget_tokens,
+            temperature: config.temperature,
+            region: resolve_region(
+                config.aws_region.as_deref(),
+                config.llm_base_url.as_deref(),
+                config.aws_profile.as_deref(),
+            ),
+            profile: config.aws_profile.clone(),
+            base_url: config
+                .llm_base_url
+                .as_deref()
+                .map(|u| u.trim().trim_end_matches('/').to_string())
+                .filter(|u| !u.is_empty()),
+            credentials: Mutex::new(None),
+        }
+    }
+
+    async fn credentials(&self) -> Result<AwsCredentials, MicroClawError> {
+        let mut cached = self.credentials.lock().await;
+        if let Some(creds) = cached.as_ref().filter(|c| c.is_fresh()) {
+            return Ok(creds.clone());
+        }
+        let creds = match static_credentials(self.profile.as_deref()) {
+            Some((creds, _)) => creds,
+            None => instance_credentials(&self.metadata_http).await.map_err(|e| {
+                MicroClawError::LlmApi(format!(
+                    "No AWS credentials found for Bedrock: set AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY, configure a profile in ~/.aws/credentials, or attach an IAM role ({e})"
+                ))
+            })?,
+        };
+        *cached = Some(creds.clone());
+        Ok(creds)
+    }
+
+    fn build_body(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Value {
+        let mut inference = json!({ "maxTokens": self.max_tokens });
+        let mut body = json!({
+            "messages": translate_messages(sanitize_messages(messages), self.thinking_budget > 0),
+        });
+        if self.thinking_budget > 0 {
+            // Bedrock passes this through to Claude models as is.
+            inference["maxTokens"] = json!(self.max_tokens.saturating_add(self.thinking_budget));
+            body["additionalModelRequestFields"] = json!({
+                "thinking": { "type": "enabled", "budget_tokens": self.thinking_budget }
+            });
+        }
+        if let (Some(temperature), 0) = (self.temperature, self.thinking_budget) {
+            inference["temperature"] = json!(temperature);
+        }
+        body["inferenceConfig"] = inference;
+        if !system.trim().is_empty() {
+            body["system"] = json!([{ "text": system }]);
+        }
+        if let Some(tools) = tools.filter(|t| !t.is_empty()) {
+            body["toolConfig"] = json!({ "tools": translate_tools(&tools) });
+        }
+        body
+    }
+
+    async fn converse(&self, body: &Value) -> Result<MessagesResponse, MicroClawError> {
+        let region = self.region.clone().ok_or_else(|| {
+            MicroClawError::LlmApi(
+                "Bedrock region is not set: set aws_region, AWS_REGION, or a region in ~/.aws/config"
+                    .into(),
+            )
+        })?;
+        let base = self
+            .base_url
+            .clone()
+            .unwrap_or_else(|| format!("https://bedrock-runtime.{region}.amazonaws.com"));
+        let url = reqwest::Url::parse(&converse_url(&base, &self.model))
+            .map_err(|e| MicroClawError::LlmApi(format!("Invalid Bedrock URL: {e}")))?;
+        let payload = serde_json::to_vec(body)?;
+        let creds = self.credentials().await?;
+        let mut request = self
+            .http
+            .post(url.clone())
+            .header("content-type", "application/json");
+        for (name, value) in sign_post(&creds, &url, &payload, &region, Utc::now()) {
+            request = request.header(name, value);
+        }
+        let wire = crate::wire_log::begin("bedrock", url.as_str(), body);
+        let response = request.body(payload).send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        wire.finish(status.as_u16(), &text);
+        if !status.is_success() {
+            if status.as_u16() == 403 {
+                // Possibly expired temporary credentials; resolve them again next time.
+                *self.credentials.lock().await = None;
+            }
+            return Err(MicroClawError::LlmHttp {
+                status: status.as_u16(),
+                message: error_message(status, &text),
+            });
+        }
+        let value: Value = serde_json::from_str(&text).map_err(|e| {
+            MicroClawError::LlmApi(format!(
+                "Failed to parse Bedrock response: {e}\nBody: {text}"
+            ))
+        })?;
+        Ok(parse_response(&value))
+    }
+}
+
+fn translate_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({ "toolSpec": {
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": { "json": tool.input_schema },
+            }})
+        })
+        .collect()
+}
+
+/// Converse image format for a MIME type (`image/jpeg` -> `jpeg`).
+fn image_format(media_type: &str) -> String {
+    match media_type.trim_start_matches("image/") {
+        "jpg" => "jpeg".into(),
+        other => other.to_string(),
+    }
+}
+
+/// Convert the conversation into Converse `messages`. Reasoning blocks are
+/// only sent back while thinking is enabled, like the Anthropic provider.
+fn translate_messages(messages: Vec<Message>, keep_thinking: bool) -> Vec<Value> {
+    let mut out: Vec<Value> = Vec::new();
+    for msg in messages {
+        let role = if msg.role == "assistant" {
+            "assistant"
+        } else {
+            "user"
+        };
+        let mut content = Vec::new();
+        match msg.content {
+            MessageContent::Text(text) => {
+                if !text.is_empty() {
+                    content.push(json!({ "text": text }));
+                }
+            }
+            MessageContent::Blocks(blocks) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } => {
+                            if !text.is_empty() {
+                                content.push(json!({ "text": text }));
+                            }
+                        }
+                        ContentBlock::Image { source } => content.push(json!({
+                            "image": {
+                                "format": image_format(&source.media_type),
+                                "source": { "bytes": source.data },
+                            }
+                        })),
+                        ContentBlock::ToolUse { id, name, input } => content.push(json!({
+                            "toolUse": { "toolUseId": id, "name": name, "input": input }
+                        })),
+                        ContentBlock::ToolResult {
+                            tool_use_id,
+                            content: result,
+                            is_error,
+                        } => {
+                            let mut block = json!({
+                                "toolUseId": tool_use_id,
+                                "content": [{ "text": result }],
+                            });
+                            if is_error == Some(true) {
+                                block["status"] = json!("error");
+                            }
+                            content.push(json!({ "toolResult": block }));
+                        }
+                        ContentBlock::Thinking {
+                            thinking,
+                            signature,
+                        } if keep_thinking && !thinking.is_empty() => content.push(json!({
+                            "reasoningContent": {
+                                "reasoningText": { "text": thinking, "signature": signature }
+                            }
+                        })),
+                        ContentBlock::RedactedThinking { data } if keep_thinking => {
+                            content.push(json!({ "reasoningContent": { "redactedContent": data } }))
+                        }
+                        ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => {}
+                    }
+                }
+            }
+        }
+        if content.is_empty() {
+            continue;
+        }
+        match out.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["content"].as_array_mut() {
+                    existing.extend(content);
+                }
+            }
+            _ => out.push(json!({ "role": role, "content": content })),
+        }
+    }
+    out
+}
+
+fn parse_response(value: &Value) -> MessagesResponse {
+    let mut content = Vec::new();
+    let blocks = value
+        .pointer("/output/message/content")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for block in blocks {
+        if let Some(text) = block.get("text").and_then(Value::as_str) {
+            content.push(ResponseContentBlock::Text {
+                text: text.to_string(),
+            });
+        } else if let Some(tool) = block.get("toolUse") {
+            let field = |key: &str| {
+                tool.get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            content.push(ResponseContentBlock::ToolUse {
+                id: field("toolUseId"),
+                name: field("name"),
+                input: tool.get("input").cloned().unwrap_or_else(|| json!({})),
+            });
+        } else if let Some(reasoning) = block.get("reasoningContent") {
+            if let Some(text) = reasoning.get("reasoningText") {
+                let field = |key: &str| {
+                    text.get(key)
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                content.push(ResponseContentBlock::Thinking {
+                    thinking: field("text"),
+                    signature: field("signature"),
+                });
+            } else if let Some(data) = reasoning.get("redactedContent").and_then(Value::as_str) {
+                content.push(ResponseContentBlock::RedactedThinking {
+                    data: data.to_string(),
+                });
+            }
+        }
+    }
+    let stop_reason = value
+        .get("stopReason")
+        .and_then(Value::as_str)
+        .unwrap_or("end_turn");
+    let has_text = content
+        .iter()
+        .any(|b| matches!(b, ResponseContentBlock::Text { text } if !text.is_empty()));
+    if matches!(stop_reason, "content_filtered" | "guardrail_intervened") && !has_text {
+        content.push(ResponseContentBlock::Text {
+            text: "(Bedrock's content filters blocked this reply)".into(),
+        });
+    }
+    if content.is_empty() {
+        content.push(ResponseContentBlock::Text {
+            text: String::new(),
+        });
+    }
+    let stop_reason = match stop_reason {
+        "tool_use" | "max_tokens" => stop_reason,
+        _ => "end_turn",
+    };
+    let usage = value.get("usage").map(|usage| {
+        let count = |key: &str| {
+            usage
+                .get(key)
+                .and_then(Value::as_u64)
+                .map(|n| u32::try_from(n).unwrap_or(u32::MAX))
+                .unwrap_or(0)
+        };
+        Usage {
+            input_tokens: count("inputTokens"),
+            output_tokens: count("outputTokens"),
+        }
+    });
+    MessagesResponse {
+        content,
+        stop_reason: Some(stop_reason.into()),
+        usage,
+        served_by: None,
+    }
+}
+
+#[async_trait]
+impl LlmProvider for BedrockProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let body = self.build_body(system, messages, tools);
+        self.converse(&body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::ImageSource;
+
+    #[test]
+    fn test_sigv4_matches_aws_example() {
+        // "Create a signed AWS API request" example from the AWS docs.
+        let creds = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".into(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".into(),
+            session_token: None,
+            expires_at: None,
+        };
+        let request = SigningRequest {
+            method: "GET",
+            path: "/",
+            query: "Action=ListUsers&Version=2010-05-08",
+            headers: vec![
+                (
+                    "content-type".into(),
+                    "application/x-www-form-urlencoded; charset=utf-8".into(),
+                ),
+                ("host".into(), "iam.amazonaws.com".into()),
+                ("x-amz-date".into(), "20150830T123600Z".into()),
+            ],
+            payload: b"",
+        };
+        let (signed_headers, signature) =
+            signature(&request, &creds, "us-east-1", "iam", "20150830T123600Z");
+        assert_eq!(signed_headers, "content-type;host;x-amz-date");
+        assert_eq!(
+            signature,
+            "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_converse_url_and_profiles() {
+        assert_eq!(
+            converse_url(
+                "https://bedrock-runtime.us-west-2.amazonaws.com/",
+                "anthropic.claude-sonnet-4-5-20250929-v1:0"
+            ),
+            "https://bedrock-runtime.us-west-2.amazonaws.com/model/anthropic.claude-sonnet-4-5-20250929-v1%3A0/converse"
+        );
+        let text = "[default]\naws_access_key_id = AKIA1\naws_secret_access_key = s1\n\n[profile work]\nregion = eu-west-1\n";
+        let creds = credentials_from_section(&ini_section(text, "default")).unwrap();
+        assert_eq!(creds.access_key_id, "AKIA1");
+        assert!(creds.session_token.is_none());
+        assert_eq!(
+            ini_section(text, &config_section("work")).get("region"),
+            Some(&"eu-west-1".to_string())
+        );
+        assert!(credentials_from_section(&ini_section(text, "work")).is_none());
+        let metadata = json!({
+            "AccessKeyId": "ASIA2", "SecretAccessKey": "s2", "Token": "t",
+            "Expiration": "2030-01-01T00:00:00Z"
+        });
+        let creds = AwsCredentials::from_metadata_json(&metadata).unwrap();
+        assert_eq!(creds.session_token.as_deref(), Some("t"));
+        assert!(creds.is_fresh());
+    }
+
+    #[test]
+    fn test_translate_messages_and_parse_response() {
+        let messages = vec![
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![
+                    ContentBlock::Image {
+                        source: ImageSource {
+                            source_type: "base64".into(),
+                            media_type: "image/jpeg".into(),
+                            data: "AAAA".into(),
+                        },
+                    },
+                    ContentBlock::Text {
+                        text: "what is this?".into(),
+                    },
+                ]),
+            },
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "tooluse_1".into(),
+                    name: "read_file".into(),
+                    input: json!({"path": "a.txt"}),
+                }]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "tooluse_1".into(),
+                    content: "missing".into(),
+                    is_error: Some(true),
+                }]),
+            },
+        ];
+        let converted = translate_messages(messages, false);
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[0]["content"][0]["image"]["format"], "jpeg");
+        assert_eq!(converted[1]["content"][0]["toolUse"]["name"], "read_file");
+        assert_eq!(converted[2]["content"][0]["toolResult"]["status"], "error");
+
+        let response = parse_response(&json!({
+            "output": {"message": {"role": "assistant", "content": [
+                {"reasoningContent": {"reasoningText": {"text": "hmm", "signature": "sig"}}},
+                {"text": "Checking."},
+                {"toolUse": {"toolUseId": "tooluse_2", "name": "web_search", "input": {"q": "rust"}}}
+            ]}},
+            "stopReason": "tool_use",
+            "usage": {"inputTokens": 12, "outputTokens": 7, "totalTokens": 19}
+        }));
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage.as_ref().unwrap().input_tokens, 12);
+        assert!(matches!(
+            &response.content[0],
+            ResponseContentBlock::Thinking { signature, .. } if signature == "sig"
+        ));
+        assert!(matches!(
+            &response.content[2],
+            ResponseContentBlock::ToolUse { id, .. } if id == "tooluse_2"
+        ));
+    }
+}
@@ -0,0 +1,491 @@
+//! Session branches (`/fork`, `/branch`) and named sessions (`/session`).
+//!
+//! `/fork [name] [turn]` parks the chat's current session under its branch
+//! name and continues on a copy of it, optionally cut back to the given user
+//! turn, so an alternative approach can be explored without losing the
+//! original. `/session new <name>` parks it the same way but starts an empty
+//! session, so one group can keep separate contexts per project. `/branch`
+//! and `/session` list, switch between and delete them; each keeps its own
+//! messages and compaction summary. The chat history in `messages` is shared.
+
+use crate::compare::is_user_turn;
+use crate::db::{call_blocking, BranchStart};
+use crate::llm_types::Message;
+use crate::run_control;
+use crate::runtime::AppState;
+
+/// Most branches one chat can keep, the active one included.
+pub const MAX_BRANCHES: usize = 20;
+const MAX_NAME_CHARS: usize = 32;
+
+const BRANCH_USAGE: &str = "Usage: /fork [name] [turn] — continue on a copy of this session, optionally cut back to a user turn\n/branch — list branches\n/branch <name> — switch to a branch\n/branch delete <name> — delete a parked branch";
+const SESSION_USAGE: &str = "Usage: /session list — list this chat's sessions\n/session new <name> — start an empty session\n/session switch <name> — switch to a session\n/session delete <name> — delete a session you are not in";
+
+/// Which command family a reply should refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Branch,
+    Session,
+}
+
+impl Kind {
+    fn noun(self) -> &'static str {
+        match self {
+            Kind::Branch => "branch",
+            Kind::Session => "session",
+        }
+    }
+
+    fn plural(self) -> &'static str {
+        match self {
+            Kind::Branch => "branches",
+            Kind::Session => "sessions",
+        }
+    }
+
+    fn switch_command(self) -> &'static str {
+        match self {
+            Kind::Branch => "/branch",
+            Kind::Session => "/session switch",
+        }
+    }
+
+    fn list_command(self) -> &'static str {
+        match self {
+            Kind::Branch => "/branch",
+            Kind::Session => "/session list",
+        }
+    }
+
+    fn delete_command(self) -> &'static str {
+        match self {
+            Kind::Branch => "/branch delete",
+            Kind::Session => "/session delete",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BranchCommand<'a> {
+    Fork {
+        name: Option<&'a str>,
+        turn: Option<usize>,
+    },
+    New(&'a str),
+    List(Kind),
+    Switch(Kind, &'a str),
+    Delete(Kind, &'a str),
+    Usage(Kind),
+}
+
+fn parse(text: &str) -> Option<BranchCommand<'_>> {
+    let text = text.trim();
+    let (command, rest) = text
+        .split_once(char::is_whitespace)
+        .map(|(command, rest)| (command, rest.trim()))
+        .unwrap_or((text, ""));
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    match command {
+        "/fork" => Some(match args.as_slice() {
+            [] => BranchCommand::Fork {
+                name: None,
+                turn: None,
+            },
+            [arg] => match arg.parse::<usize>() {
+                Ok(turn) => BranchCommand::Fork {
+                    name: None,
+                    turn: Some(turn),
+                },
+                Err(_) => BranchCommand::Fork {
+                    name: Some(arg),
+                    turn: None,
+                },
+            },
+            [name, turn] => match turn.parse::<usize>() {
+                Ok(turn) => BranchCommand::Fork {
+                    name: Some(name),
+                    turn: Some(turn),
+                },
+                Err(_) => BranchCommand::Usage(Kind::Branch),
+            },
+            _ => BranchCommand::Usage(Kind::Branch),
+        }),
+        "/branch" | "/branches" => Some(match args.as_slice() {
+            [] | ["list"] => BranchCommand::List(Kind::Branch),
+            ["delete", name] => BranchCommand::Delete(Kind::Branch, name),
+            [name] if *name != "delete" => BranchCommand::Switch(Kind::Branch, name),
+            _ => BranchCommand::Usage(Kind::Branch),
+        }),
+        "/session" | "/sessions" => Some(match args.as_slice() {
+            [] | ["list"] => BranchCommand::List(Kind::Session),
+            ["new", name] => BranchCommand::New(name),
+            ["switch", name] => BranchCommand::Switch(Kind::Session, name),
+            ["delete", name] => BranchCommand::Delete(Kind::Session, name),
+            _ => BranchCommand::Usage(Kind::Session),
+        }),
+        _ => None,
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_CHARS
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !name.chars().all(|c| c.is_ascii_digit())
+}
+
+fn user_turns(messages: &[Message]) -> usize {
+    messages.iter().filter(|m| is_user_turn(m)).count()
+}
+
+/// The session up to and including user turn `turn` (1-based) and the
+/// replies to it. `None` when the session has fewer turns.
+fn cut_at_turn(messages: &[Message], turn: usize) -> Option<&[Message]> {
+    if turn == 0 {
+        return None;
+    }
+    let mut seen = 0;
+    for (idx, message) in messages.iter().enumerate() {
+        if is_user_turn(message) {
+            seen += 1;
+            if seen == turn + 1 {
+                return Some(&messages[..idx]);
+            }
+        }
+    }
+    (seen == turn).then_some(messages)
+}
+
+fn parse_messages(json: &str) -> Vec<Message> {
+    serde_json::from_str(json).unwrap_or_default()
+}
+
+fn describe(name: &str, turns: usize) -> String {
+    let unit = if turns == 1 { "turn" } else { "turns" };
+    format!("{name} ({turns} {unit})")
+}
+
+/// The active branch, its messages and the names of the parked ones.
+async fn load(
+    state: &AppState,
+    chat_id: i64,
+) -> Result<(String, Vec<Message>, Vec<String>), String> {
+    let (active, session, parked) = call_blocking(state.db.clone(), move |db| {
+        Ok((
+            db.load_session_branch(chat_id)?,
+            db.load_session(chat_id)?,
+            db.list_session_branches(chat_id)?,
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let messages = session
+        .map(|(json, _)| parse_messages(&json))
+        .unwrap_or_default();
+    Ok((
+        active,
+        messages,
+        parked.into_iter().map(|b| b.name).collect(),
+    ))
+}
+
+/// Check a name for a new branch or session; `Err` carries the reply.
+fn check_new_name(kind: Kind, name: &str, active: &str, parked: &[String]) -> Result<(), String> {
+    if parked.len() + 1 >= MAX_BRANCHES {
+        return Err(format!(
+            "This chat already has {MAX_BRANCHES} {}. Delete one with {} <name>.",
+            kind.plural(),
+            kind.delete_command(),
+        ));
+    }
+    if !valid_name(name) {
+        return Err(format!(
+            "Names use letters, digits, '-' and '_' (up to {MAX_NAME_CHARS} characters)."
+        ));
+    }
+    if name == active || parked.iter().any(|p| p == name) {
+        return Err(format!(
+            "A {} named '{name}' already exists. Switch to it with {} {name}.",
+            kind.noun(),
+            kind.switch_command()
+        ));
+    }
+    Ok(())
+}
+
+async fn list(state: &AppState, chat_id: i64, kind: Kind) -> Result<String, String> {
+    let (active, session, parked) = call_blocking(state.db.clone(), move |db| {
+        Ok((
+            db.load_session_branch(chat_id)?,
+            db.load_session(chat_id)?,
+            db.list_session_branches(chat_id)?,
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let active_turns = session
+        .map(|(json, _)| user_turns(&parse_messages(&json)))
+        .unwrap_or(0);
+    let mut lines = vec![format!("• {} — active", describe(&active, active_turns))];
+    for branch in parked {
+        lines.push(format!(
+            "• {}",
+            describe(
+                &branch.name,
+                user_turns(&parse_messages(&branch.messages_json))
+            )
+        ));
+    }
+    Ok(format!("Your {}:\n{}", kind.plural(), lines.join("\n")))
+}
+
+async fn fork(
+    state: &AppState,
+    chat_id: i64,
+    name: Option<&str>,
+    turn: Option<usize>,
+) -> Result<String, String> {
+    let (active, messages, parked) = load(state, chat_id).await?;
+    let turns = user_turns(&messages);
+    if turns == 0 {
+        return Ok("Nothing to fork yet — this session has no messages.".to_string());
+    }
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => (1..)
+            .map(|n| format!("fork-{n}"))
+            .find(|candidate| *candidate != active && !parked.contains(candidate))
+            .expect("unbounded range"),
+    };
+    if let Err(reply) = check_new_name(Kind::Branch, &name, &active, &parked) {
+        return Ok(reply);
+    }
+    let kept = match turn {
+        None => &messages[..],
+        Some(turn) => match cut_at_turn(&messages, turn) {
+            Some(kept) => kept,
+            None => {
+                return Ok(format!(
+                    "This session has {turns} user turns; pick a turn from 1 to {turns}."
+                ))
+            }
+        },
+    };
+    let kept_turns = user_turns(kept);
+    let json = serde_json::to_string(kept).map_err(|e| e.to_string())?;
+    let target = name.clone();
+    call_blocking(state.db.clone(), move |db| {
+        db.switch_session_branch(chat_id, &target, BranchStart::Fork(&json))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(format!(
+        "Forked {active} into {}. Switch back with /branch {active}.",
+        describe(&name, kept_turns)
+    ))
+}
+
+async fn new_session(state: &AppState, chat_id: i64, name: &str) -> Result<String, String> {
+    let (active, _, parked) = load(state, chat_id).await?;
+    if let Err(reply) = check_new_name(Kind::Session, name, &active, &parked) {
+        return Ok(reply);
+    }
+    let target = name.to_string();
+    call_blocking(state.db.clone(), move |db| {
+        db.switch_session_branch(chat_id, &target, BranchStart::Empty)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(format!(
+        "Started session {name}. Switch back with /session switch {active}."
+    ))
+}
+
+async fn switch(state: &AppState, chat_id: i64, kind: Kind, name: &str) -> Result<String, String> {
+    let target = name.to_string();
+    let (active, switched) = call_blocking(state.db.clone(), move |db| {
+        let active = db.load_session_branch(chat_id)?;
+        if active == target {
+            return Ok((active, false));
+        }
+        let switched = db.switch_session_branch(chat_id, &target, BranchStart::Parked)?;
+        Ok((active, switched))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let noun = kind.noun();
+    Ok(if active == name {
+        format!("Already on {noun} {name}.")
+    } else if switched {
+        format!("Switched from {active} to {name}.")
+    } else {
+        format!(
+            "No {noun} named '{name}'. List them with {}.",
+            kind.list_command()
+        )
+    })
+}
+
+async fn delete(state: &AppState, chat_id: i64, kind: Kind, name: &str) -> Result<String, String> {
+    let target = name.to_string();
+    let (active, deleted) = call_blocking(state.db.clone(), move |db| {
+        let active = db.load_session_branch(chat_id)?;
+        if active == target {
+            return Ok((active, false));
+        }
+        let deleted = db.delete_session_branch(chat_id, &target)?;
+        Ok((active, deleted))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let noun = kind.noun();
+    Ok(if active == name {
+        format!("{name} is the active {noun}; switch to another {noun} before deleting it.")
+    } else if deleted {
+        format!("Deleted {noun} {name}.")
+    } else {
+        format!("No {noun} named '{name}'.")
+    })
+}
+
+/// Handle `/fork`, `/branch` and `/session`. Returns `None` when the text is
+/// not one of these commands.
+pub async fn handle_branch_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let command = parse(text)?;
+    let changes_session = matches!(
+        command,
+        BranchCommand::Fork { .. } | BranchCommand::New(_) | BranchCommand::Switch(..)
+    );
+    // A running turn saves its session when it finishes and would overwrite
+    // the branch switched to.
+    if changes_session && run_control::has_active_run(chat_id) {
+        return Some(
+            "A reply is still in progress. Try again when it finishes, or /stop it.".into(),
+        );
+    }
+    let result = match command {
+        BranchCommand::Usage(Kind::Branch) => return Some(BRANCH_USAGE.to_string()),
+        BranchCommand::Usage(Kind::Session) => return Some(SESSION_USAGE.to_string()),
+        BranchCommand::List(kind) => list(state, chat_id, kind).await,
+        BranchCommand::Fork { name, turn } => fork(state, chat_id, name, turn).await,
+        BranchCommand::New(name) => new_session(state, chat_id, name).await,
+        BranchCommand::Switch(kind, name) => switch(state, chat_id, kind, name).await,
+        BranchCommand::Delete(kind, name) => delete(state, chat_id, kind, name).await,
+    };
+    Some(result.unwrap_or_else(|e| format!("Failed to update sessions: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::{ContentBlock, MessageContent};
+
+    fn text(role: &str, text: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: MessageContent::Text(text.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_branch_command() {
+        assert_eq!(
+            parse("/fork"),
+            Some(BranchCommand::Fork {
+                name: None,
+                turn: None
+            })
+        );
+        assert_eq!(
+            parse("/fork 2"),
+            Some(BranchCommand::Fork {
+                name: None,
+                turn: Some(2)
+            })
+        );
+        assert_eq!(
+            parse("/fork idea-b 3"),
+            Some(BranchCommand::Fork {
+                name: Some("idea-b"),
+                turn: Some(3)
+            })
+        );
+        assert_eq!(parse("/fork a b"), Some(BranchCommand::Usage(Kind::Branch)));
+        assert_eq!(parse("/branch"), Some(BranchCommand::List(Kind::Branch)));
+        assert_eq!(parse("/branches"), Some(BranchCommand::List(Kind::Branch)));
+        assert_eq!(
+            parse("/branch main"),
+            Some(BranchCommand::Switch(Kind::Branch, "main"))
+        );
+        assert_eq!(
+            parse("/branch delete x"),
+            Some(BranchCommand::Delete(Kind::Branch, "x"))
+        );
+        assert_eq!(
+            parse("/branch delete"),
+            Some(BranchCommand::Usage(Kind::Branch))
+        );
+        assert_eq!(parse("/session"), Some(BranchCommand::List(Kind::Session)));
+        assert_eq!(
+            parse("/session new project-a"),
+            Some(BranchCommand::New("project-a"))
+        );
+        assert_eq!(
+            parse("/session switch main"),
+            Some(BranchCommand::Switch(Kind::Session, "main"))
+        );
+        assert_eq!(
+            parse("/session delete project-a"),
+            Some(BranchCommand::Delete(Kind::Session, "project-a"))
+        );
+        assert_eq!(
+            parse("/session new"),
+            Some(BranchCommand::Usage(Kind::Session))
+        );
+        assert_eq!(parse("/forks"), None);
+        assert_eq!(parse("please /fork"), None);
+    }
+
+    #[test]
+    fn test_valid_name() {
+        assert!(valid_name("idea-b_2"));
+        assert!(!valid_name("12"));
+        assert!(!valid_name("has space"));
+        assert!(!valid_name(&"x".repeat(MAX_NAME_CHARS + 1)));
+    }
+
+    #[test]
+    fn test_cut_at_turn_keeps_replies_and_tool_results() {
+        let messages = vec![
+            text("user", "one"),
+            text("assistant", "reply one"),
+            text("user", "two"),
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "t1".into(),
+                    name: "bash".into(),
+                    input: serde_json::json!({}),
+                }]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".into(),
+                    content: "ok".into(),
+                    is_error: None,
+                }]),
+            },
+            text("assistant", "reply two"),
+            text("user", "three"),
+        ];
+        assert_eq!(user_turns(&messages), 3);
+        assert_eq!(cut_at_turn(&messages, 1).unwrap().len(), 2);
+        assert_eq!(cut_at_turn(&messages, 2).unwrap().len(), 6);
+        assert_eq!(cut_at_turn(&messages, 3).unwrap().len(), 7);
+        assert!(cut_at_turn(&messages, 0).is_none());
+        assert!(cut_at_turn(&messages, 4).is_none());
+    }
+}
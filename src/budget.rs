@@ -0,0 +1,562 @@
+//! Spending limits per chat (`chat_budget`, `chat_budgets`) and for all chats
+//! together (`global_budget`).
+//!
+//! Before each agent turn, the tokens and estimated cost for the current day
+//! and month (from `llm_usage_logs`, with days and months in `timezone`) are
+//! compared with the global budget and the chat's. Over a hard limit the turn
+//! gets a refusal instead of an LLM call; past a soft `warn_*_usd` limit the
+//! control chats get one warning per period. Control chats can lift a hard
+//! limit for a while with `/budget override <chat_id|global>`.
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::config::{ChatBudget, ClawhookEvent};
+use crate::db::call_blocking;
+use crate::i18n::{self, Msg};
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding the end of an override (RFC 3339).
+pub const BUDGET_OVERRIDE_KEY: &str = "budget_override";
+/// `chat_settings` keys holding the start of the last day/month a soft-limit
+/// warning was sent for.
+const WARNED_DAILY_KEY: &str = "budget_warned_daily";
+const WARNED_MONTHLY_KEY: &str = "budget_warned_monthly";
+/// `chat_settings` row that holds the global budget's override and warnings.
+pub const GLOBAL_BUDGET_CHAT_ID: i64 = 0;
+const DEFAULT_OVERRIDE_HOURS: i64 = 24;
+
+const BUDGET_USAGE: &str = "Usage:
+/budget — show this chat's budget and spending
+/budget <chat_id|global> — show another chat's or the global budget (control chats)
+/budget override <chat_id|global> [hours] — lift a budget for 24 hours or the given time (control chats)
+/budget clear <chat_id|global> — end an override (control chats)";
+
+/// Tokens and estimated cost spent in one period.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PeriodUsage {
+    pub tokens: i64,
+    pub usd: f64,
+}
+
+/// Start of the current day and month in `tz`, and when each ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Periods {
+    day_start: DateTime<Utc>,
+    day_end: DateTime<Utc>,
+    month_start: DateTime<Utc>,
+    month_end: DateTime<Utc>,
+}
+
+fn local_midnight(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let naive = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+}
+
+fn periods(now: DateTime<Utc>, tz: Tz) -> Periods {
+    let today = now.with_timezone(&tz).date_naive();
+    let month_first = today.with_day(1).unwrap_or(today);
+    let next_month_first = if month_first.month() == 12 {
+        NaiveDate::from_ymd_opt(month_first.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(month_first.year(), month_first.month() + 1, 1)
+    }
+    .unwrap_or(month_first);
+    Periods {
+        day_start: local_midnight(tz, today),
+        day_end: local_midnight(tz, today.succ_opt().unwrap_or(today)),
+        month_start: local_midnight(tz, month_first),
+        month_end: local_midnight(tz, next_month_first),
+    }
+}
+
+/// The first limit of `budget` that `daily` or `monthly` usage has reached:
+/// `(is_monthly, "used of limit")`.
+fn first_exceeded(
+    budget: &ChatBudget,
+    daily: PeriodUsage,
+    monthly: PeriodUsage,
+) -> Option<(bool, String)> {
+    let token_limits = [
+        (false, budget.daily_tokens, daily.tokens),
+        (true, budget.monthly_tokens, monthly.tokens),
+    ];
+    for (is_monthly, limit, used) in token_limits {
+        if let Some(limit) = limit.filter(|limit| used >= *limit) {
+            return Some((is_monthly, format!("{used} of {limit} tokens")));
+        }
+    }
+    let usd_limits = [
+        (false, budget.daily_usd, daily.usd),
+        (true, budget.monthly_usd, monthly.usd),
+    ];
+    for (is_monthly, limit, used) in usd_limits {
+        if let Some(limit) = limit.filter(|limit| used >= *limit) {
+            return Some((is_monthly, format!("${used:.2} of ${limit:.2}")));
+        }
+    }
+    None
+}
+
+/// The first soft limit of `budget` that `daily` or `monthly` spending has
+/// reached: `(is_monthly, "used (warning at limit)")`.
+fn first_warning(
+    budget: &ChatBudget,
+    daily: PeriodUsage,
+    monthly: PeriodUsage,
+) -> Option<(bool, String)> {
+    [
+        (false, budget.warn_daily_usd, daily.usd),
+        (true, budget.warn_monthly_usd, monthly.usd),
+    ]
+    .into_iter()
+    .find_map(|(is_monthly, limit, used)| {
+        limit
+            .filter(|limit| used >= *limit)
+            .map(|limit| (is_monthly, format!("${used:.2} (warning at ${limit:.2})")))
+    })
+}
+
+fn timezone(state: &AppState) -> Tz {
+    state.config.timezone.parse().unwrap_or(Tz::UTC)
+}
+
+fn budget_for(state: &AppState, target: i64) -> ChatBudget {
+    if target == GLOBAL_BUDGET_CHAT_ID {
+        state.config.global_budget.clone()
+    } else {
+        state.config.budget_for_chat(target)
+    }
+}
+
+fn describe(target: i64) -> String {
+    if target == GLOBAL_BUDGET_CHAT_ID {
+        "all chats together".to_string()
+    } else {
+        format!("chat {target}")
+    }
+}
+
+/// Spending since `since` by `target`, or by every chat for the global budget.
+async fn usage_since(
+    state: &AppState,
+    target: i64,
+    since: DateTime<Utc>,
+) -> Result<PeriodUsage, String> {
+    let since = since.to_rfc3339();
+    let chat_id = (target != GLOBAL_BUDGET_CHAT_ID).then_some(target);
+    let rows = call_blocking(state.db.clone(), move |db| {
+        db.get_llm_usage_by_model(chat_id, Some(&since), None)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows
+        .iter()
+        .fold(PeriodUsage::default(), |acc, row| PeriodUsage {
+            tokens: acc.tokens + row.total_tokens,
+            usd: acc.usd
+                + state
+                    .config
+                    .estimate_cost_usd(&row.model, row.input_tokens, row.output_tokens)
+                    .unwrap_or(0.0),
+        }))
+}
+
+/// End of the chat's active override, if any.
+async fn active_override(state: &AppState, chat_id: i64) -> Option<DateTime<Utc>> {
+    let until = call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, BUDGET_OVERRIDE_KEY)
+    })
+    .await
+    .ok()
+    .flatten()?;
+    DateTime::parse_from_rfc3339(&until)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+        .filter(|t| *t > Utc::now())
+}
+
+/// Send the control chats a soft-limit warning for `target`, unless one
+/// already went out this period.
+async fn warn_control_chats(
+    state: &AppState,
+    target: i64,
+    is_monthly: bool,
+    spent: &str,
+    period_start: DateTime<Utc>,
+) {
+    let key = if is_monthly {
+        WARNED_MONTHLY_KEY
+    } else {
+        WARNED_DAILY_KEY
+    };
+    let marker = period_start.to_rfc3339();
+    let already = call_blocking(state.db.clone(), move |db| db.get_chat_setting(target, key))
+        .await
+        .ok()
+        .flatten();
+    if already.as_deref() == Some(marker.as_str()) {
+        return;
+    }
+    let stored = marker.clone();
+    if let Err(e) = call_blocking(state.db.clone(), move |db| {
+        db.set_chat_setting(target, key, Some(&stored))
+    })
+    .await
+    {
+        tracing::warn!(
+            "Failed to record budget warning for {}: {e}",
+            describe(target)
+        );
+        return;
+    }
+    let period = if is_monthly { "monthly" } else { "daily" };
+    let text = format!(
+        "⚠️ Budget warning: {} has spent {spent} of its {period} budget.",
+        describe(target)
+    );
+    tracing::info!("{text}");
+    for control_chat in &state.config.control_chat_ids {
+        if let Err(e) = crate::channel::deliver_and_store_bot_message(
+            &state.channel_registry,
+            state.db.clone(),
+            &state.config.bot_username,
+            *control_chat,
+            &text,
+        )
+        .await
+        {
+            tracing::warn!("Failed to send budget warning to chat {control_chat}: {e}");
+        }
+    }
+}
+
+/// Compare `target`'s spending with its budget: warns on a soft limit and
+/// returns `(period, spent, resets_at)` when a hard limit is reached.
+async fn check_budget(
+    state: &AppState,
+    target: i64,
+) -> Option<(&'static str, String, DateTime<Utc>)> {
+    let budget = budget_for(state, target);
+    if budget.is_unlimited() {
+        return None;
+    }
+    let periods = periods(Utc::now(), timezone(state));
+    let daily = usage_since(state, target, periods.day_start).await;
+    let monthly = usage_since(state, target, periods.month_start).await;
+    let (daily, monthly) = match (daily, monthly) {
+        (Ok(daily), Ok(monthly)) => (daily, monthly),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::warn!("Budget check for {} failed: {e}", describe(target));
+            return None;
+        }
+    };
+    if let Some((is_monthly, spent)) = first_warning(&budget, daily, monthly) {
+        let period_start = if is_monthly {
+            periods.month_start
+        } else {
+            periods.day_start
+        };
+        warn_control_chats(state, target, is_monthly, &spent, period_start).await;
+    }
+    if active_override(state, target).await.is_some() {
+        return None;
+    }
+    let (is_monthly, spent) = first_exceeded(&budget, daily, monthly)?;
+    Some(if is_monthly {
+        ("monthly", spent, periods.month_end)
+    } else {
+        ("daily", spent, periods.day_end)
+    })
+}
+
+/// Refusal message when the global budget or `chat_id`'s is exhausted, else
+/// `None`.
+pub async fn check_chat_budget(state: &AppState, chat_id: i64) -> Option<String> {
+    let tz = timezone(state);
+    let period_name = |lang, period: &str| {
+        i18n::t(
+            lang,
+            if period == "monthly" {
+                Msg::PeriodMonthly
+            } else {
+                Msg::PeriodDaily
+            },
+        )
+    };
+    if let Some((period, spent, resets_at)) = check_budget(state, GLOBAL_BUDGET_CHAT_ID).await {
+        tracing::info!("Global {period} budget reached ({spent})");
+        crate::clawhooks::emit(
+            &state.config,
+            ClawhookEvent::BudgetExceeded,
+            chat_id,
+            serde_json::json!({"scope": "global", "period": period, "spent": spent, "resets_at": resets_at.to_rfc3339()}),
+        );
+        let lang = i18n::chat_language(state, chat_id).await;
+        return Some(i18n::tf(
+            lang,
+            Msg::BudgetGlobalReached,
+            &[
+                ("period", &period_name(lang, period)),
+                (
+                    "resets_at",
+                    &resets_at
+                        .with_timezone(&tz)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string(),
+                ),
+                ("tz", &tz),
+            ],
+        ));
+    }
+    let (period, spent, resets_at) = check_budget(state, chat_id).await?;
+    tracing::info!("Chat {chat_id} is over its {period} budget ({spent})");
+    crate::clawhooks::emit(
+        &state.config,
+        ClawhookEvent::BudgetExceeded,
+        chat_id,
+        serde_json::json!({"scope": "chat", "period": period, "spent": spent, "resets_at": resets_at.to_rfc3339()}),
+    );
+    let lang = i18n::chat_language(state, chat_id).await;
+    Some(i18n::tf(
+        lang,
+        Msg::BudgetChatReached,
+        &[
+            ("period", &period_name(lang, period)),
+            ("spent", &spent),
+            (
+                "resets_at",
+                &resets_at
+                    .with_timezone(&tz)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+            ),
+            ("tz", &tz),
+            ("chat_id", &chat_id),
+        ],
+    ))
+}
+
+fn format_limit(used: String, limit: Option<String>) -> String {
+    match limit {
+        Some(limit) => format!("{used} / {limit}"),
+        None => format!("{used} (no limit)"),
+    }
+}
+
+async fn budget_status(state: &AppState, target: i64) -> Result<String, String> {
+    let budget = budget_for(state, target);
+    if budget.is_unlimited() {
+        return Ok(format!("No budget is configured for {}.", describe(target)));
+    }
+    let tz = timezone(state);
+    let periods = periods(Utc::now(), tz);
+    let daily = usage_since(state, target, periods.day_start).await?;
+    let monthly = usage_since(state, target, periods.month_start).await?;
+    let line = |label: &str, usage: PeriodUsage, tokens: Option<i64>, usd: Option<f64>| {
+        let mut parts = vec![format_limit(
+            format!("{} tokens", usage.tokens),
+            tokens.map(|t| t.to_string()),
+        )];
+        if usd.is_some() || state.config.has_pricing() {
+            parts.push(format_limit(
+                format!("${:.2}", usage.usd),
+                usd.map(|u| format!("${u:.2}")),
+            ));
+        }
+        format!("- {label}: {}", parts.join(", "))
+    };
+    let mut lines = vec![
+        format!("Budget for {} ({tz}):", describe(target)),
+        line("Today", daily, budget.daily_tokens, budget.daily_usd),
+        line(
+            "This month",
+            monthly,
+            budget.monthly_tokens,
+            budget.monthly_usd,
+        ),
+    ];
+    let warnings: Vec<String> = [
+        ("daily", budget.warn_daily_usd),
+        ("monthly", budget.warn_monthly_usd),
+    ]
+    .into_iter()
+    .filter_map(|(period, limit)| limit.map(|l| format!("${l:.2} {period}")))
+    .collect();
+    if !warnings.is_empty() {
+        lines.push(format!("Warns control chats at {}.", warnings.join(", ")));
+    }
+    if let Some(until) = active_override(state, target).await {
+        lines.push(format!(
+            "Override active until {}.",
+            until.with_timezone(&tz).format("%Y-%m-%d %H:%M")
+        ));
+    } else if first_exceeded(&budget, daily, monthly).is_some() {
+        lines.push("Over budget: new turns are refused.".into());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// A chat id, or `global` for the global budget.
+fn parse_target(arg: &str) -> Option<i64> {
+    if arg.eq_ignore_ascii_case("global") {
+        Some(GLOBAL_BUDGET_CHAT_ID)
+    } else {
+        arg.parse().ok()
+    }
+}
+
+/// Handle `/budget`. Returns `None` when the text is not this command.
+pub async fn handle_budget_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    if parts.next() != Some("/budget") {
+        return None;
+    }
+    let args: Vec<&str> = parts.collect();
+    let is_control = state.config.control_chat_ids.contains(&chat_id);
+    let control_only = "Only control chats can view or change other chats' budgets.".to_string();
+
+    let reply = match args.as_slice() {
+        [] => budget_status(state, chat_id).await,
+        [target] => match parse_target(target) {
+            Some(_) if !is_control => return Some(control_only),
+            Some(target) => budget_status(state, target).await,
+            None => return Some(BUDGET_USAGE.to_string()),
+        },
+        ["override", target, rest @ ..] if rest.len() <= 1 => {
+            let (Some(target), Ok(hours)) = (
+                parse_target(target),
+                rest.first()
+                    .map_or(Ok(DEFAULT_OVERRIDE_HOURS), |h| h.parse::<i64>()),
+            ) else {
+                return Some(BUDGET_USAGE.to_string());
+            };
+            if !is_control {
+                return Some(control_only);
+            }
+            if hours <= 0 {
+                return Some(BUDGET_USAGE.to_string());
+            }
+            let until = Utc::now() + chrono::Duration::hours(hours);
+            let value = until.to_rfc3339();
+            call_blocking(state.db.clone(), move |db| {
+                db.set_chat_setting(target, BUDGET_OVERRIDE_KEY, Some(&value))
+            })
+            .await
+            .map(|_| {
+                format!(
+                    "Budget lifted for {} until {}.",
+                    describe(target),
+                    until
+                        .with_timezone(&timezone(state))
+                        .format("%Y-%m-%d %H:%M")
+                )
+            })
+            .map_err(|e| e.to_string())
+        }
+        ["clear", target] => {
+            let Some(target) = parse_target(target) else {
+                return Some(BUDGET_USAGE.to_string());
+            };
+            if !is_control {
+                return Some(control_only);
+            }
+            call_blocking(state.db.clone(), move |db| {
+                db.set_chat_setting(target, BUDGET_OVERRIDE_KEY, None)
+            })
+            .await
+            .map(|_| format!("Budget override for {} cleared.", describe(target)))
+            .map_err(|e| e.to_string())
+        }
+        _ => return Some(BUDGET_USAGE.to_string()),
+    };
+    Some(reply.unwrap_or_else(|e| format!("Failed to read budget: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_periods_follow_timezone() {
+        let tz: Tz = "Asia/Shanghai".parse().unwrap();
+        // 2026-12-31 20:00 UTC is already 2027-01-01 04:00 in Shanghai.
+        let p = periods(utc("2026-12-31T20:00:00Z"), tz);
+        assert_eq!(p.day_start, utc("2026-12-31T16:00:00Z"));
+        assert_eq!(p.day_end, utc("2027-01-01T16:00:00Z"));
+        assert_eq!(p.month_start, utc("2026-12-31T16:00:00Z"));
+        assert_eq!(p.month_end, utc("2027-01-31T16:00:00Z"));
+
+        let p = periods(utc("2026-12-15T10:00:00Z"), Tz::UTC);
+        assert_eq!(p.month_start, utc("2026-12-01T00:00:00Z"));
+        assert_eq!(p.month_end, utc("2027-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_first_exceeded() {
+        let budget = ChatBudget {
+            daily_tokens: Some(1000),
+            monthly_usd: Some(5.0),
+            ..Default::default()
+        };
+        let under = PeriodUsage {
+            tokens: 999,
+            usd: 1.0,
+        };
+        assert_eq!(first_exceeded(&budget, under, under), None);
+
+        let daily = PeriodUsage {
+            tokens: 1000,
+            usd: 1.0,
+        };
+        assert_eq!(
+            first_exceeded(&budget, daily, under),
+            Some((false, "1000 of 1000 tokens".into()))
+        );
+
+        let monthly = PeriodUsage {
+            tokens: 999,
+            usd: 5.5,
+        };
+        assert_eq!(
+            first_exceeded(&budget, under, monthly),
+            Some((true, "$5.50 of $5.00".into()))
+        );
+        assert_eq!(first_exceeded(&ChatBudget::default(), daily, monthly), None);
+    }
+
+    #[test]
+    fn test_first_warning() {
+        let budget = ChatBudget {
+            monthly_usd: Some(10.0),
+            warn_daily_usd: Some(1.0),
+            warn_monthly_usd: Some(8.0),
+            ..Default::default()
+        };
+        let quiet = PeriodUsage {
+            tokens: 10,
+            usd: 0.5,
+        };
+        assert_eq!(first_warning(&budget, quiet, quiet), None);
+        let busy_month = PeriodUsage {
+            tokens: 10,
+            usd: 8.25,
+        };
+        assert_eq!(
+            first_warning(&budget, quiet, busy_month),
+            Some((true, "$8.25 (warning at $8.00)".into()))
+        );
+        // A soft limit alone does not refuse turns.
+        assert_eq!(first_exceeded(&budget, quiet, busy_month), None);
+        assert_eq!(parse_target("GLOBAL"), Some(GLOBAL_BUDGET_CHAT_ID));
+        assert_eq!(parse_target("42"), Some(42));
+        assert_eq!(parse_target("all"), None);
+    }
+}
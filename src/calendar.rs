@@ -0,0 +1,744 @@
+//! Calendar access for the `calendar` tool (`calendar.provider`).
+//!
+//! `caldav` talks to any CalDAV collection (Nextcloud, Fastmail, iCloud,
+//! Radicale, ...): events are listed with a `calendar-query` REPORT that asks
+//! the server to expand recurrences, and created with a PUT of a new `.ics`
+//! resource. `google` uses the Calendar v3 API with an OAuth refresh token,
+//! exchanging it for access tokens that are cached until shortly before they
+//! expire.
+
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_API: &str = "https://www.googleapis.com/calendar/v3";
+/// Refresh tokens this long before they expire.
+const EXPIRY_MARGIN_SECS: i64 = 300;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarEvent {
+    pub id: String,
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub all_day: bool,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NewEvent {
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+enum Backend {
+    CalDav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    Google {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+        calendar_id: String,
+        token: Mutex<Option<(String, DateTime<Utc>)>>,
+    },
+}
+
+pub struct CalendarClient {
+    http: reqwest::Client,
+    backend: Backend,
+    /// Zone for floating times and all-day events.
+    timezone: Tz,
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+impl CalendarClient {
+    /// The configured calendar, or `None` when `calendar.provider` is unset.
+    pub fn from_config(config: &Config) -> Option<Arc<Self>> {
+        let calendar = &config.calendar;
+        let backend = match calendar.provider.as_deref()? {
+            "caldav" => Backend::CalDav {
+                url: non_empty(calendar.url.as_deref())?,
+                username: non_empty(calendar.username.as_deref()),
+                password: calendar.password.clone(),
+            },
+            "google" => Backend::Google {
+                client_id: non_empty(calendar.client_id.as_deref())?,
+                client_secret: non_empty(calendar.client_secret.as_deref())?,
+                refresh_token: non_empty(calendar.refresh_token.as_deref())?,
+                calendar_id: non_empty(Some(&calendar.calendar_id))
+                    .unwrap_or_else(|| "primary".into()),
+                token: Mutex::new(None),
+            },
+            _ => return None,
+        };
+        Some(Arc::new(CalendarClient {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(20))
+                .build()
+                .unwrap_or_default(),
+            backend,
+            timezone: Tz::from_str(&config.timezone).unwrap_or(Tz::UTC),
+        }))
+    }
+
+    /// Events overlapping `from..to`, soonest first, at most `limit`.
+    pub async fn upcoming(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<CalendarEvent>, String> {
+        let mut events = match &self.backend {
+            Backend::CalDav { .. } => self.caldav_query(from, to).await?,
+            Backend::Google { .. } => self.google_list(from, to, limit).await?,
+        };
+        events.retain(|e| e.end.unwrap_or(e.start) >= from && e.start < to);
+        events.sort_by_key(|e| e.start);
+        events.truncate(limit);
+        Ok(events)
+    }
+
+    pub async fn create(&self, event: &NewEvent) -> Result<CalendarEvent, String> {
+        match &self.backend {
+            Backend::CalDav { .. } => self.caldav_create(event).await,
+            Backend::Google { .. } => self.google_create(event).await,
+        }
+    }
+
+    // ── CalDAV ────────────────────────────────────────────────────────────
+
+    fn caldav_request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, url);
+        match &self.backend {
+            Backend::CalDav {
+                username: Some(username),
+                password,
+                ..
+            } => request.basic_auth(username, password.as_deref()),
+            _ => request,
+        }
+    }
+
+    async fn caldav_query(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>, String> {
+        let Backend::CalDav { url, .. } = &self.backend else {
+            unreachable!("caldav_query on a non-CalDAV backend");
+        };
+        let (start, end) = (ics_utc(from), ics_utc(to));
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <C:calendar-data><C:expand start="{start}" end="{end}"/></C:calendar-data>
+  </D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT"><C:time-range start="{start}" end="{end}"/></C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#
+        );
+        let method = reqwest::Method::from_bytes(b"REPORT").map_err(|e| e.to_string())?;
+        let response = self
+            .caldav_request(method, url)
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            return Err(format!("CalDAV REPORT failed: HTTP {status}"));
+        }
+        Ok(calendar_data(&text)
+            .iter()
+            .flat_map(|ics| parse_ics(ics, self.timezone))
+            .collect())
+    }
+
+    async fn caldav_create(&self, event: &NewEvent) -> Result<CalendarEvent, String> {
+        let Backend::CalDav { url, .. } = &self.backend else {
+            unreachable!("caldav_create on a non-CalDAV backend");
+        };
+        let uid = format!("{}@microclaw", uuid::Uuid::new_v4());
+        let target = format!("{}/{}.ics", url.trim_end_matches('/'), uuid_part(&uid));
+        let response = self
+            .caldav_request(reqwest::Method::PUT, &target)
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .header("If-None-Match", "*")
+            .body(to_ics(&uid, event, Utc::now()))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("CalDAV PUT failed: HTTP {status}"));
+        }
+        Ok(CalendarEvent {
+            id: uid,
+            summary: event.summary.clone(),
+            start: event.start,
+            end: Some(event.end),
+            all_day: false,
+            location: event.location.clone(),
+            description: event.description.clone(),
+        })
+    }
+
+    // ── Google ────────────────────────────────────────────────────────────
+
+    async fn google_token(&self) -> Result<String, String> {
+        let Backend::Google {
+            client_id,
+            client_secret,
+            refresh_token,
+            token,
+            ..
+        } = &self.backend
+        else {
+            unreachable!("google_token on a non-Google backend");
+        };
+        let mut cached = token.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if *expires_at - chrono::Duration::seconds(EXPIRY_MARGIN_SECS) > Utc::now() {
+                return Ok(token.clone());
+            }
+        }
+        let response = self
+            .http
+            .post(GOOGLE_TOKEN_URL)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let value: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(format!(
+                "Google token request failed: {}",
+                google_error(&value).unwrap_or_else(|| format!("HTTP {status}"))
+            ));
+        }
+        let access = value
+            .get("access_token")
+            .and_then(Value::as_str)
+            .ok_or("Google token response has no access_token")?
+            .to_string();
+        let expires_in = value
+            .get("expires_in")
+            .and_then(Value::as_i64)
+            .unwrap_or(3600);
+        *cached = Some((
+            access.clone(),
+            Utc::now() + chrono::Duration::seconds(expires_in),
+        ));
+        Ok(access)
+    }
+
+    fn google_events_url(&self) -> String {
+        let Backend::Google { calendar_id, .. } = &self.backend else {
+            unreachable!("google_events_url on a non-Google backend");
+        };
+        format!(
+            "{GOOGLE_API}/calendars/{}/events",
+            urlencoding::encode(calendar_id)
+        )
+    }
+
+    async fn google_list(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<CalendarEvent>, String> {
+        let token = self.google_token().await?;
+        let response = self
+            .http
+            .get(self.google_events_url())
+            .bearer_auth(token)
+            .query(&[
+                ("timeMin", from.to_rfc3339()),
+                ("timeMax", to.to_rfc3339()),
+                ("singleEvents", "true".into()),
+                ("orderBy", "startTime".into()),
+                ("maxResults", limit.clamp(1, 2500).to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let value: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(format!(
+                "Google Calendar request failed: {}",
+                google_error(&value).unwrap_or_else(|| format!("HTTP {status}"))
+            ));
+        }
+        Ok(value
+            .get("items")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| parse_google_event(item, self.timezone))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn google_create(&self, event: &NewEvent) -> Result<CalendarEvent, String> {
+        let token = self.google_token().await?;
+        let mut body = json!({
+            "summary": event.summary,
+            "start": { "dateTime": event.start.to_rfc3339() },
+            "end": { "dateTime": event.end.to_rfc3339() },
+        });
+        if let Some(location) = &event.location {
+            body["location"] = json!(location);
+        }
+        if let Some(description) = &event.description {
+            body["description"] = json!(description);
+        }
+        let response = self
+            .http
+            .post(self.google_events_url())
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let value: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            return Err(format!(
+                "Google Calendar request failed: {}",
+                google_error(&value).unwrap_or_else(|| format!("HTTP {status}"))
+            ));
+        }
+        parse_google_event(&value, self.timezone)
+            .ok_or_else(|| "Google Calendar returned an unexpected event".into())
+    }
+}
+
+fn google_error(value: &Value) -> Option<String> {
+    value
+        .pointer("/error/message")
+        .or_else(|| value.get("error_description"))
+        .or_else(|| value.get("error"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// One event from a Calendar v3 `events` resource; cancelled instances are
+/// dropped.
+fn parse_google_event(item: &Value, tz: Tz) -> Option<CalendarEvent> {
+    if item.get("status").and_then(Value::as_str) == Some("cancelled") {
+        return None;
+    }
+    let time = |key: &str| -> Option<(DateTime<Utc>, bool)> {
+        let field = item.get(key)?;
+        if let Some(dt) = field.get("dateTime").and_then(Value::as_str) {
+            let dt = DateTime::parse_from_rfc3339(dt).ok()?;
+            return Some((dt.with_timezone(&Utc), false));
+        }
+        let date = NaiveDate::parse_from_str(field.get("date")?.as_str()?, "%Y-%m-%d").ok()?;
+        Some((local_to_utc(date.and_hms_opt(0, 0, 0)?, tz)?, true))
+    };
+    let (start, all_day) = time("start")?;
+    let text = |key: &str| {
+        item.get(key)
+            .and_then(Value::as_str)
+            .and_then(|v| non_empty(Some(v)))
+    };
+    Some(CalendarEvent {
+        id: text("id").unwrap_or_default(),
+        summary: text("summary").unwrap_or_else(|| "(no title)".into()),
+        start,
+        end: time("end").map(|(end, _)| end),
+        all_day,
+        location: text("location"),
+        description: text("description"),
+    })
+}
+
+// ── iCalendar ─────────────────────────────────────────────────────────────────
+
+fn local_to_utc(naive: NaiveDateTime, tz: Tz) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn ics_utc(dt: DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn uuid_part(uid: &str) -> &str {
+    uid.split('@').next().unwrap_or(uid)
+}
+
+/// The `calendar-data` payloads of a CalDAV multistatus response.
+fn calendar_data(xml: &str) -> Vec<String> {
+    static DATA: OnceLock<Regex> = OnceLock::new();
+    let re = DATA.get_or_init(|| {
+        Regex::new(r"(?s)<(?:[A-Za-z0-9_-]+:)?calendar-data\b[^>]*>(.*?)</(?:[A-Za-z0-9_-]+:)?calendar-data>")
+            .expect("valid regex")
+    });
+    re.captures_iter(xml)
+        .map(|c| {
+            let data = c[1].trim();
+            match data
+                .strip_prefix("<![CDATA[")
+                .and_then(|d| d.strip_suffix("]]>"))
+            {
+                Some(raw) => raw.to_string(),
+                None => xml_unescape(data),
+            }
+        })
+        .collect()
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&#13;", "")
+        .replace("&#xD;", "")
+        .replace("&#x0D;", "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Content lines with folded continuations joined back.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.split('\n') {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// `DTSTART`/`DTEND` value: UTC (`...Z`), local to `TZID`, floating
+/// (`tz`), or a `VALUE=DATE` day starting at midnight in `tz`.
+fn parse_ics_time(
+    value: &str,
+    params: &[(String, String)],
+    tz: Tz,
+) -> Option<(DateTime<Utc>, bool)> {
+    let value = value.trim();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim_matches('"'))
+    };
+    if param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((local_to_utc(date.and_hms_opt(0, 0, 0)?, tz)?, true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&naive), false));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    // Unknown zone names (e.g. Windows ones) fall back to the configured zone.
+    let zone = param("TZID")
+        .and_then(|id| Tz::from_str(id).ok())
+        .unwrap_or(tz);
+    Some((local_to_utc(naive, zone)?, false))
+}
+
+/// `DURATION` values like `PT1H30M`, `P1D` or `P2W`.
+fn parse_ics_duration(value: &str) -> Option<chrono::Duration> {
+    static DURATION: OnceLock<Regex> = OnceLock::new();
+    let re = DURATION.get_or_init(|| {
+        Regex::new(r"^([+-])?P(?:(\d+)W)?(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)S)?)?$")
+            .expect("valid regex")
+    });
+    let caps = re.captures(value.trim())?;
+    let n = |i: usize| {
+        caps.get(i)
+            .and_then(|m| m.as_str().parse::<i64>().ok())
+            .unwrap_or(0)
+    };
+    let secs = n(2) * 7 * 86_400 + n(3) * 86_400 + n(4) * 3600 + n(5) * 60 + n(6);
+    Some(chrono::Duration::seconds(
+        if caps.get(1).is_some_and(|m| m.as_str() == "-") {
+            -secs
+        } else {
+            secs
+        },
+    ))
+}
+
+/// `VEVENT`s of an iCalendar object. Nested components (alarms) are skipped.
+pub fn parse_ics(ics: &str, tz: Tz) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<CalendarEvent> = None;
+    let mut duration = None;
+    let mut nested = 0usize;
+    for line in unfold(ics) {
+        let Some((head, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut parts = head.split(';');
+        let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let params: Vec<(String, String)> = parts
+            .filter_map(|p| p.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(CalendarEvent {
+                    id: String::new(),
+                    summary: "(no title)".into(),
+                    start: DateTime::<Utc>::MIN_UTC,
+                    end: None,
+                    all_day: false,
+                    location: None,
+                    description: None,
+                });
+                duration = None;
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(event)) if value.eq_ignore_ascii_case("VEVENT") => {
+                if event.end.is_none() {
+                    event.end = duration.map(|d| event.start + d);
+                }
+                if event.start != DateTime::<Utc>::MIN_UTC {
+                    events.extend(current.take());
+                }
+                current = None;
+            }
+            (_, Some(_)) if nested > 0 => {}
+            ("UID", Some(event)) => event.id = value.trim().to_string(),
+            ("SUMMARY", Some(event)) => event.summary = unescape_text(value),
+            ("LOCATION", Some(event)) => event.location = non_empty(Some(&unescape_text(value))),
+            ("DESCRIPTION", Some(event)) => {
+                event.description = non_empty(Some(&unescape_text(value)))
+            }
+            ("DTSTART", Some(event)) => {
+                if let Some((start, all_day)) = parse_ics_time(value, &params, tz) {
+                    event.start = start;
+                    event.all_day = all_day;
+                }
+            }
+            ("DTEND", Some(event)) => {
+                event.end = parse_ics_time(value, &params, tz).map(|(end, _)| end)
+            }
+            ("DURATION", Some(_)) => duration = parse_ics_duration(value),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Fold a content line to 75 octets per line.
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn to_ics(uid: &str, event: &NewEvent, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".into(),
+        "PRODID:-//MicroClaw//Calendar//EN".into(),
+        "BEGIN:VEVENT".into(),
+        format!("UID:{uid}"),
+        format!("DTSTAMP:{}", ics_utc(now)),
+        format!("DTSTART:{}", ics_utc(event.start)),
+        format!("DTEND:{}", ics_utc(event.end)),
+        format!("SUMMARY:{}", escape_text(&event.summary)),
+    ];
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", escape_text(location)));
+    }
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    lines.push("END:VEVENT".into());
+    lines.push("END:VCALENDAR".into());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus_events() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+ <d:response><d:propstat><d:prop><cal:calendar-data>BEGIN:VCALENDAR&#13;
+BEGIN:VEVENT&#13;
+UID:standup-1&#13;
+SUMMARY:Standup\, daily&#13;
+DTSTART;TZID=Europe/Berlin:20261016T093000&#13;
+DURATION:PT15M&#13;
+LOCATION:Room &amp; call&#13;
+BEGIN:VALARM&#13;
+DESCRIPTION:alarm text&#13;
+END:VALARM&#13;
+END:VEVENT&#13;
+END:VCALENDAR</cal:calendar-data></d:prop></d:propstat></d:response>
+ <d:response><d:propstat><d:prop><cal:calendar-data><![CDATA[BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:offsite
+SUMMARY:Offsite with a very long title that the server folded across two
+  lines
+DTSTART;VALUE=DATE:20261020
+DTEND;VALUE=DATE:20261021
+END:VEVENT
+END:VCALENDAR]]></cal:calendar-data></d:prop></d:propstat></d:response>
+</d:multistatus>"#;
+        let events: Vec<CalendarEvent> = calendar_data(xml)
+            .iter()
+            .flat_map(|ics| parse_ics(ics, Tz::UTC))
+            .collect();
+        assert_eq!(events.len(), 2);
+
+        let standup = &events[0];
+        assert_eq!(standup.id, "standup-1");
+        assert_eq!(standup.summary, "Standup, daily");
+        assert_eq!(standup.start.to_rfc3339(), "2026-10-16T07:30:00+00:00");
+        assert_eq!(
+            standup.end.unwrap().to_rfc3339(),
+            "2026-10-16T07:45:00+00:00"
+        );
+        assert_eq!(standup.location.as_deref(), Some("Room & call"));
+        assert_eq!(standup.description, None);
+        assert!(!standup.all_day);
+
+        let offsite = &events[1];
+        assert!(offsite.all_day);
+        assert_eq!(
+            offsite.summary,
+            "Offsite with a very long title that the server folded across two lines"
+        );
+        assert_eq!(offsite.start.to_rfc3339(), "2026-10-20T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_ics_round_trip() {
+        let event = NewEvent {
+            summary: "Dinner; with Ann, Bob".into(),
+            start: Utc.with_ymd_and_hms(2026, 10, 16, 18, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2026, 10, 16, 20, 0, 0).unwrap(),
+            location: None,
+            description: Some(format!("line one\n{}", "x".repeat(100))),
+        };
+        let ics = to_ics("abc@microclaw", &event, Utc::now());
+        assert!(ics.lines().all(|line| line.len() <= 76));
+        assert!(ics.contains("DTSTART:20261016T180000Z\r\n"));
+
+        let parsed = parse_ics(&ics, Tz::UTC);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].id, "abc@microclaw");
+        assert_eq!(parsed[0].summary, event.summary);
+        assert_eq!(parsed[0].end, Some(event.end));
+        assert_eq!(parsed[0].description, event.description);
+    }
+
+    #[test]
+    fn test_parse_google_event() {
+        let tz = Tz::from_str("America/New_York").unwrap();
+        let timed = json!({
+            "id": "evt1",
+            "summary": "1:1",
+            "start": {"dateTime": "2026-10-16T10:00:00-04:00"},
+            "end": {"dateTime": "2026-10-16T10:30:00-04:00"},
+            "location": "Zoom"
+        });
+        let event = parse_google_event(&timed, tz).unwrap();
+        assert_eq!(event.start.to_rfc3339(), "2026-10-16T14:00:00+00:00");
+        assert_eq!(event.location.as_deref(), Some("Zoom"));
+
+        let all_day =
+            json!({"id": "h", "start": {"date": "2026-10-19"}, "end": {"date": "2026-10-20"}});
+        let event = parse_google_event(&all_day, tz).unwrap();
+        assert!(event.all_day);
+        assert_eq!(event.summary, "(no title)");
+        assert_eq!(event.start.to_rfc3339(), "2026-10-19T04:00:00+00:00");
+
+        assert!(parse_google_event(&json!({"id": "x", "status": "cancelled"}), tz).is_none());
+        assert_eq!(
+            parse_ics_duration("P1DT2H"),
+            Some(chrono::Duration::hours(26))
+        );
+    }
+}
@@ -1,8 +1,11 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::channel_adapter::ChannelRegistry;
+use crate::config::Config;
 use crate::db::{call_blocking, Database, StoredMessage};
-use crate::tools::auth_context_from_input;
+use crate::runtime::AppState;
+use crate::tools::{auth_context_from_input, chat_workspace_dir};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConversationKind {
@@ -152,3 +155,187 @@ pub async fn deliver_and_store_bot_message(
         .await
         .map_err(|e| format!("Failed to store sent message: {e}"))
 }
+
+/// Whether an inbound file fits `max_document_size_mb`.
+pub fn inbound_file_within_limit(config: &Config, size: u64) -> bool {
+    let max_bytes = config
+        .max_document_size_mb
+        .saturating_mul(1024)
+        .saturating_mul(1024);
+    size <= max_bytes
+}
+
+/// Save an inbound file under the chat workspace's `uploads/` directory with a
+/// timestamped, sanitized name, so `read_file` / `bash` can reach it.
+pub async fn save_inbound_file(
+    config: &Config,
+    channel: &str,
+    chat_id: i64,
+    filename: &str,
+    bytes: &[u8],
+) -> Result<PathBuf, String> {
+    let dir = chat_workspace_dir(
+        Path::new(config.working_dir_for_channel(channel)),
+        config.working_dir_isolation,
+        channel,
+        chat_id,
+    )
+    .join("uploads");
+    let safe_name = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let path = dir.join(format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        safe_name
+    ));
+    let saved = match tokio::fs::create_dir_all(&dir).await {
+        Ok(()) => tokio::fs::write(&path, bytes).await,
+        Err(e) => Err(e),
+    };
+    match saved {
+        Ok(()) => Ok(path),
+        Err(e) => {
+            tracing::error!(
+                "{channel}: failed to save attachment {}: {e}",
+                path.display()
+            );
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Save an inbound file into the chat's workspace `uploads/` directory and
+/// return the `[attachment] ...` note that is appended to the user message.
+/// Files above `max_document_size_mb` are skipped.
+pub async fn save_inbound_attachment(
+    config: &Config,
+    channel: &str,
+    chat_id: i64,
+    filename: &str,
+    mime: &str,
+    bytes: &[u8],
+) -> String {
+    if !inbound_file_within_limit(config, bytes.len() as u64) {
+        return format!(
+            "[attachment] filename={} bytes={} skipped: larger than {} MB",
+            filename,
+            bytes.len(),
+            config.max_document_size_mb
+        );
+    }
+    match save_inbound_file(config, channel, chat_id, filename, bytes).await {
+        Ok(path) => format!(
+            "[attachment] filename={} bytes={} mime={} saved_path={}",
+            filename,
+            bytes.len(),
+            mime,
+            path.display()
+        ),
+        Err(e) => format!("[attachment] filename={filename} save failed: {e}"),
+    }
+}
+
+/// Largest image passed to the model inline. Providers reject bigger images
+/// (Anthropic: 5 MB), so those are only saved to the workspace.
+pub const MAX_IMAGE_INPUT_BYTES: usize = 5 * 1024 * 1024;
+
+/// Media type of a PNG, JPEG, GIF or WebP image, from its magic bytes.
+pub fn sniff_image_media_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF") {
+        Some("image/gif")
+    } else if data.starts_with(b"RIFF") && data.len() >= 12 && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// `(base64, media_type)` image input for the agent when `bytes` is a
+/// supported image small enough to send inline.
+pub fn image_input(bytes: &[u8]) -> Option<(String, String)> {
+    use base64::Engine;
+    if bytes.len() > MAX_IMAGE_INPUT_BYTES {
+        return None;
+    }
+    let media_type = sniff_image_media_type(bytes)?;
+    Some((
+        base64::engine::general_purpose::STANDARD.encode(bytes),
+        media_type.to_string(),
+    ))
+}
+
+/// Per-chat slash commands shared by every channel. `sender_id` is the
+/// platform user id, used to pick the `user` workspace for `/workspace` and
+/// `/file`. Returns `None` when `text` is not one of these commands.
+pub async fn local_command_reply(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    sender_id: Option<&str>,
+    text: &str,
+) -> Option<String> {
+    let text = text.trim();
+    if !text.starts_with('/') {
+        return None;
+    }
+    if let Some(reply) =
+        crate::compare::handle_compare_command(state, caller_channel, chat_id, text).await
+    {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::preferences::handle_preferences_command(state, chat_id, text).await
+    {
+        return Some(reply);
+    }
+    if let Some(reply) =
+        crate::identity::handle_link_command(state, caller_channel, chat_id, sender_id, text).await
+    {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::router::handle_router_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::budget::handle_budget_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::provider_health::handle_status_command(state, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::i18n::handle_language_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::thinking::handle_thinking_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::read_only::handle_read_only_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::chat_prompt::handle_prompt_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::persona::handle_persona_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) = crate::branches::handle_branch_command(state, chat_id, text).await {
+        return Some(reply);
+    }
+    if let Some(reply) =
+        crate::workspace::handle_workspace_command(state, caller_channel, chat_id, sender_id, text)
+            .await
+    {
+        return Some(reply);
+    }
+    crate::file_preview::handle_file_command(state, caller_channel, chat_id, sender_id, text).await
+}
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -36,6 +36,55 @@ pub trait ChannelAdapter: Send + Sync {
     ) -> Result<String, String> {
         Err(format!("attachments not supported for {}", self.name()))
     }
+
+    /// Send several files as one message where the channel supports it.
+    /// Default: one `send_attachment` per file, captioning the first.
+    async fn send_attachments(
+        &self,
+        external_chat_id: &str,
+        file_paths: &[PathBuf],
+        caption: Option<&str>,
+    ) -> Result<String, String> {
+        for (i, path) in file_paths.iter().enumerate() {
+            let caption = if i == 0 { caption } else { None };
+            self.send_attachment(external_chat_id, path, caption)
+                .await?;
+        }
+        Ok(attachments_content(file_paths, caption))
+    }
+
+    /// Send a file preview card. Default: the card as text with a `/file`
+    /// hint; channels with buttons can offer the full file inline.
+    async fn send_file_preview(
+        &self,
+        external_chat_id: &str,
+        card: &str,
+        display_path: &str,
+        _file_path: &Path,
+    ) -> Result<(), String> {
+        self.send_text(
+            external_chat_id,
+            &format!(
+                "{card}
+Send `/file {display_path}` for the full file."
+            ),
+        )
+        .await
+    }
+}
+
+/// Stored message content for files sent together.
+pub fn attachments_content(file_paths: &[PathBuf], caption: Option<&str>) -> String {
+    let mut content = file_paths
+        .iter()
+        .map(|p| format!("[attachment:{}]", p.display()))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some(caption) = caption {
+        content.push(' ');
+        content.push_str(caption);
+    }
+    content
 }
 
 #[derive(Default)]
@@ -88,4 +137,11 @@ impl ChannelRegistry {
     pub fn has_any(&self) -> bool {
         !self.adapters.is_empty()
     }
+
+    /// Registered channel names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.adapters.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
 }
@@ -1,28 +1,47 @@
+use std::collections::HashMap;
 use std::error::Error;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use serde::Deserialize;
 use serde_json::json;
 use serenity::async_trait;
-use serenity::model::channel::Message as DiscordMessage;
+use serenity::builder::{
+    CreateAttachment, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
+    CreateThread, EditInteractionResponse, EditMessage,
+};
+use serenity::http::Http;
+use serenity::model::application::{Command, CommandInteraction, CommandOptionType, Interaction};
+use serenity::model::channel::{
+    AutoArchiveDuration, Channel, Message as DiscordMessage, Reaction, ReactionType,
+};
 use serenity::model::gateway::Ready;
-use serenity::model::id::ChannelId;
+use serenity::model::id::{ChannelId, MessageId, UserId};
 use serenity::prelude::*;
 use tracing::{error, info, warn};
 
 use crate::agent_engine::archive_conversation;
+use crate::agent_engine::compact_session_now;
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::AgentEvent;
 use crate::agent_engine::AgentRequestContext;
-use crate::channel::ConversationKind;
-use crate::channel_adapter::ChannelAdapter;
+use crate::channel::{
+    image_input, inbound_file_within_limit, local_command_reply, save_inbound_attachment,
+    ConversationKind,
+};
+use crate::channel_adapter::{attachments_content, ChannelAdapter};
 use crate::db::call_blocking;
 use crate::db::StoredMessage;
+use crate::i18n::{self, Msg};
 use crate::llm_types::Message as LlmMessage;
+use crate::reactions;
+use crate::run_control;
 use crate::runtime::AppState;
-use crate::text::{floor_char_boundary, split_text};
-use crate::usage::build_usage_report;
+use crate::streaming::{StreamingDraft, STATUS_REFRESH_INTERVAL};
+use crate::text::{split_markdown, MAX_REPLY_CHUNKS, REPLY_FILE_NAME};
+use crate::tools::schedule::format_task_list;
+use crate::usage::{build_usage_report, send_usage_chart};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DiscordChannelConfig {
@@ -31,6 +50,9 @@ pub struct DiscordChannelConfig {
     pub allowed_channels: Vec<u64>,
 }
 
+/// Discord message length limit.
+const DISCORD_MAX_LEN: usize = 2000;
+
 pub struct DiscordAdapter {
     token: String,
     http_client: reqwest::Client,
@@ -90,6 +112,57 @@ impl DiscordAdapter {
             http_client: reqwest::Client::new(),
         }
     }
+
+    /// Post one message carrying `file_paths` as attachments.
+    async fn post_files(
+        &self,
+        external_chat_id: &str,
+        file_paths: &[&Path],
+        caption: Option<&str>,
+    ) -> Result<(), String> {
+        let discord_chat_id = external_chat_id
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid Discord external_chat_id '{}'", external_chat_id))?;
+
+        let payload = json!({ "content": caption.unwrap_or_default() });
+        let mut form = reqwest::multipart::Form::new().text("payload_json", payload.to_string());
+        for (i, file_path) in file_paths.iter().enumerate() {
+            let filename = file_path
+                .file_name()
+                .and_then(|v| v.to_str())
+                .unwrap_or("attachment.bin")
+                .to_string();
+            let bytes = tokio::fs::read(file_path)
+                .await
+                .map_err(|e| format!("Failed to read attachment file: {e}"))?;
+            form = form.part(
+                format!("files[{i}]"),
+                reqwest::multipart::Part::bytes(bytes).file_name(filename),
+            );
+        }
+
+        let url = format!("https://discord.com/api/v10/channels/{discord_chat_id}/messages");
+        let resp = self
+            .http_client
+            .post(url)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bot {}", self.token),
+            )
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| format_reqwest_error("Failed to send Discord attachment", &e))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!(
+                "Failed to send Discord attachment: HTTP {status} {}",
+                body.chars().take(300).collect::<String>()
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -109,17 +182,33 @@ impl ChannelAdapter for DiscordAdapter {
 
         let url = format!("https://discord.com/api/v10/channels/{discord_chat_id}/messages");
 
-        for chunk in split_text(text, 2000) {
-            let body = json!({ "content": chunk });
-            let resp = self
-                .http_client
-                .post(&url)
-                .header(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bot {}", self.token),
-                )
-                .header(reqwest::header::CONTENT_TYPE, "application/json")
-                .json(&body)
+        let mut chunks = split_markdown(text, DISCORD_MAX_LEN);
+        let as_file = chunks.len() > MAX_REPLY_CHUNKS;
+        if as_file {
+            chunks.truncate(1);
+        }
+        for (i, chunk) in chunks.iter().enumerate() {
+            let request = self.http_client.post(&url).header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bot {}", self.token),
+            );
+            // A long reply's full text rides along with its first chunk.
+            let request = if as_file && i == 0 {
+                let payload = json!({ "content": chunk });
+                let form = reqwest::multipart::Form::new()
+                    .text("payload_json", payload.to_string())
+                    .part(
+                        "files[0]",
+                        reqwest::multipart::Part::bytes(text.as_bytes().to_vec())
+                            .file_name(REPLY_FILE_NAME),
+                    );
+                request.multipart(form)
+            } else {
+                request
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .json(&json!({ "content": chunk }))
+            };
+            let resp = request
                 .send()
                 .await
                 .map_err(|e| format_reqwest_error("Failed to send Discord message", &e))?;
@@ -143,59 +232,146 @@ impl ChannelAdapter for DiscordAdapter {
         file_path: &Path,
         caption: Option<&str>,
     ) -> Result<String, String> {
-        let discord_chat_id = external_chat_id
-            .parse::<u64>()
-            .map_err(|_| format!("Invalid Discord external_chat_id '{}'", external_chat_id))?;
-
-        let filename = file_path
-            .file_name()
-            .and_then(|v| v.to_str())
-            .unwrap_or("attachment.bin")
-            .to_string();
-        let bytes = tokio::fs::read(file_path)
-            .await
-            .map_err(|e| format!("Failed to read attachment file: {e}"))?;
-
-        let payload = json!({ "content": caption.unwrap_or_default() });
-        let form = reqwest::multipart::Form::new()
-            .text("payload_json", payload.to_string())
-            .part(
-                "files[0]",
-                reqwest::multipart::Part::bytes(bytes).file_name(filename),
-            );
-
-        let url = format!("https://discord.com/api/v10/channels/{discord_chat_id}/messages");
-        let resp = self
-            .http_client
-            .post(url)
-            .header(
-                reqwest::header::AUTHORIZATION,
-                format!("Bot {}", self.token),
-            )
-            .multipart(form)
-            .send()
-            .await
-            .map_err(|e| format_reqwest_error("Failed to send Discord attachment", &e))?;
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            return Err(format!(
-                "Failed to send Discord attachment: HTTP {status} {}",
-                body.chars().take(300).collect::<String>()
-            ));
-        }
-
+        self.post_files(external_chat_id, &[file_path], caption)
+            .await?;
         Ok(match caption {
             Some(c) => format!("[attachment:{}] {}", file_path.display(), c),
             None => format!("[attachment:{}]", file_path.display()),
         })
     }
+
+    /// All files in one message (Discord allows up to 10).
+    async fn send_attachments(
+        &self,
+        external_chat_id: &str,
+        file_paths: &[PathBuf],
+        caption: Option<&str>,
+    ) -> Result<String, String> {
+        let paths: Vec<&Path> = file_paths.iter().map(PathBuf::as_path).collect();
+        self.post_files(external_chat_id, &paths, caption).await?;
+        Ok(attachments_content(file_paths, caption))
+    }
 }
 
 struct Handler {
     app_state: Arc<AppState>,
 }
 
+/// Parent channel and creator of a Discord thread.
+#[derive(Clone, Copy)]
+struct ThreadInfo {
+    parent_id: ChannelId,
+    owner_id: Option<UserId>,
+}
+
+fn thread_cache() -> &'static Mutex<HashMap<ChannelId, Option<ThreadInfo>>> {
+    static CACHE: OnceLock<Mutex<HashMap<ChannelId, Option<ThreadInfo>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Thread details for a guild channel, `None` for regular channels. Results
+/// are memoized so each channel is fetched once.
+async fn thread_info(ctx: &Context, channel_id: ChannelId) -> Option<ThreadInfo> {
+    if let Some(cached) = thread_cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&channel_id)
+    {
+        return *cached;
+    }
+    let info = match channel_id.to_channel(&ctx.http).await {
+        Ok(Channel::Guild(channel)) if channel.thread_metadata.is_some() => {
+            channel.parent_id.map(|parent_id| ThreadInfo {
+                parent_id,
+                owner_id: channel.owner_id,
+            })
+        }
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Discord: failed to look up channel {channel_id}: {e}");
+            return None;
+        }
+    };
+    thread_cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(channel_id, info);
+    info
+}
+
+/// Save the message's attachments into the chat workspace and append their
+/// notes to `text`. Returns the first image as model input.
+async fn collect_attachments(
+    state: &AppState,
+    msg: &DiscordMessage,
+    chat_id: i64,
+    text: &mut String,
+) -> Option<(String, String)> {
+    let mut image_data = None;
+    for attachment in &msg.attachments {
+        let note = if !inbound_file_within_limit(&state.config, u64::from(attachment.size)) {
+            format!(
+                "[attachment] filename={} bytes={} skipped: larger than {} MB",
+                attachment.filename, attachment.size, state.config.max_document_size_mb
+            )
+        } else {
+            match attachment.download().await {
+                Ok(bytes) => {
+                    if image_data.is_none() {
+                        image_data = image_input(&bytes);
+                    }
+                    let mime = attachment
+                        .content_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream");
+                    save_inbound_attachment(
+                        &state.config,
+                        "discord",
+                        chat_id,
+                        &attachment.filename,
+                        mime,
+                        &bytes,
+                    )
+                    .await
+                }
+                Err(e) => {
+                    warn!(
+                        "Discord: failed to download attachment {}: {e}",
+                        attachment.filename
+                    );
+                    format!(
+                        "[attachment] filename={} download failed: {e}",
+                        attachment.filename
+                    )
+                }
+            }
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&note);
+    }
+    image_data
+}
+
+/// Thread title from the first line of the message, without mentions.
+fn thread_name(text: &str, sender_name: &str) -> String {
+    let first_line = text
+        .lines()
+        .map(|l| {
+            l.split_whitespace()
+                .filter(|w| !(w.starts_with("<@") && w.ends_with('>')))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|l| !l.is_empty());
+    match first_line {
+        // Discord caps thread names at 100 characters.
+        Some(line) => line.chars().take(90).collect(),
+        None => format!("Chat with {sender_name}"),
+    }
+}
+
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, ctx: Context, msg: DiscordMessage) {
@@ -204,8 +380,17 @@ impl EventHandler for Handler {
             return;
         }
 
-        let text = msg.content.clone();
+        let mut text = msg.content.clone();
         let external_channel_id = msg.channel_id.get();
+        let thread = if msg.guild_id.is_some() {
+            thread_info(&ctx, msg.channel_id).await
+        } else {
+            None
+        };
+        // Threads inherit the allowlist entry of their parent channel.
+        let policy_channel_id = thread
+            .map(|t| t.parent_id.get())
+            .unwrap_or(external_channel_id);
         let channel_id = {
             let external_chat_id = external_channel_id.to_string();
             let chat_type = "discord".to_string();
@@ -224,11 +409,22 @@ impl EventHandler for Handler {
                 .app_state
                 .config
                 .discord_allowed_channels
-                .contains(&external_channel_id)
+                .contains(&policy_channel_id)
         {
             return;
         }
 
+        // Handle /stop command — cancel the in-flight run for this channel
+        if text.trim() == "/stop" {
+            let cancelled = run_control::cancel_chat_runs(channel_id);
+            let lang = i18n::chat_language(&self.app_state, channel_id).await;
+            let _ = msg
+                .channel_id
+                .say(&ctx.http, run_control::stop_command_reply(lang, cancelled))
+                .await;
+            return;
+        }
+
         // Handle /reset command
         if text.trim() == "/reset" {
             let _ = call_blocking(self.app_state.db.clone(), move |db| {
@@ -294,6 +490,7 @@ impl EventHandler for Handler {
             {
                 Ok(text) => {
                     let _ = msg.channel_id.say(&ctx.http, text).await;
+                    send_usage_chart(&self.app_state, "discord", channel_id).await;
                 }
                 Err(e) => {
                     let _ = msg
@@ -305,6 +502,34 @@ impl EventHandler for Handler {
             return;
         }
 
+        // Only DMs are personal; server channels share the "discord" chat type.
+        if msg.guild_id.is_some()
+            && matches!(text.split_whitespace().next(), Some("/link" | "/unlink"))
+        {
+            send_discord_response(
+                &ctx,
+                msg.channel_id,
+                "Identity linking only works in private chats.",
+            )
+            .await;
+            return;
+        }
+        let author_id = msg.author.id.get().to_string();
+        if let Some(reply) = local_command_reply(
+            &self.app_state,
+            "discord",
+            channel_id,
+            Some(&author_id),
+            &text,
+        )
+        .await
+        {
+            send_discord_response(&ctx, msg.channel_id, &reply).await;
+            return;
+        }
+
+        let image_data = collect_attachments(&self.app_state, &msg, channel_id, &mut text).await;
+
         if text.is_empty() {
             if msg.guild_id.is_some() {
                 info!(
@@ -337,10 +562,12 @@ impl EventHandler for Handler {
 
         // Determine if we should respond
         let should_respond = if msg.guild_id.is_some() {
-            // In a guild: only respond to @mentions
+            // In a guild: respond to @mentions, and to everything in threads
+            // the bot started.
             let cache = &ctx.cache;
             let bot_id = cache.current_user().id;
             msg.mentions.iter().any(|u| u.id == bot_id)
+                || thread.is_some_and(|t| t.owner_id == Some(bot_id))
         } else {
             // DM: respond to all messages
             true
@@ -350,6 +577,54 @@ impl EventHandler for Handler {
             return;
         }
 
+        // Move channel conversations into their own thread. Threads are
+        // channels, so the thread gets its own chat id and session.
+        let (reply_channel, channel_id) = if msg.guild_id.is_some()
+            && thread.is_none()
+            && self.app_state.config.discord_reply_in_threads
+        {
+            let builder = CreateThread::new(thread_name(&text, &sender_name))
+                .auto_archive_duration(AutoArchiveDuration::OneDay);
+            match msg
+                .channel_id
+                .create_thread_from_message(&ctx.http, msg.id, builder)
+                .await
+            {
+                Ok(created) => {
+                    thread_cache()
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(
+                            created.id,
+                            Some(ThreadInfo {
+                                parent_id: msg.channel_id,
+                                owner_id: Some(ctx.cache.current_user().id),
+                            }),
+                        );
+                    let thread_chat_id = self.resolve_chat_id(created.id.get()).await;
+                    let stored = StoredMessage {
+                        id: msg.id.get().to_string(),
+                        chat_id: thread_chat_id,
+                        sender_name: sender_name.clone(),
+                        content: text.clone(),
+                        is_from_bot: false,
+                        timestamp: chrono::Utc::now().to_rfc3339(),
+                    };
+                    let _ = call_blocking(self.app_state.db.clone(), move |db| {
+                        db.store_message(&stored)
+                    })
+                    .await;
+                    (created.id, thread_chat_id)
+                }
+                Err(e) => {
+                    warn!("Discord: failed to create thread, replying in channel: {e}");
+                    (msg.channel_id, channel_id)
+                }
+            }
+        } else {
+            (msg.channel_id, channel_id)
+        };
+
         info!(
             "Discord message from {} in channel {}: {}",
             sender_name,
@@ -357,42 +632,209 @@ impl EventHandler for Handler {
             text.chars().take(100).collect::<String>()
         );
 
+        let chat_type = if msg.guild_id.is_some() {
+            "group"
+        } else {
+            "private"
+        };
+        if self.app_state.config.cancel_on_new_message {
+            run_control::supersede_runs_from(channel_id, &sender_name).await;
+        }
+        self.run_agent_and_reply(
+            &ctx,
+            reply_channel,
+            channel_id,
+            chat_type,
+            Some(&sender_name),
+            Some(&msg.author.id.get().to_string()),
+            image_data,
+        )
+        .await;
+    }
+
+    /// Reaction controls (see `crate::reactions`) on the bot's own messages.
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        let ReactionType::Unicode(emoji) = &add_reaction.emoji else {
+            return;
+        };
+        let Some(action) = reactions::reaction_action(emoji) else {
+            return;
+        };
+        let bot_id = ctx.cache.current_user().id;
+        let author_id = match add_reaction.message_author_id {
+            Some(id) => Some(id),
+            None => add_reaction
+                .message(&ctx.http)
+                .await
+                .ok()
+                .map(|m| m.author.id),
+        };
+        if author_id != Some(bot_id) || add_reaction.user_id == Some(bot_id) {
+            return;
+        }
+        let Ok(user) = add_reaction.user(&ctx.http).await else {
+            return;
+        };
+        if user.bot {
+            return;
+        }
+
+        let external_channel_id = add_reaction.channel_id.get();
+        let policy_channel_id = match add_reaction.guild_id {
+            Some(_) => thread_info(&ctx, add_reaction.channel_id)
+                .await
+                .map(|t| t.parent_id.get())
+                .unwrap_or(external_channel_id),
+            None => external_channel_id,
+        };
+        let allowed = &self.app_state.config.discord_allowed_channels;
+        if !allowed.is_empty() && !allowed.contains(&policy_channel_id) {
+            return;
+        }
+
+        let channel_id = self.resolve_chat_id(external_channel_id).await;
+        info!(
+            "Discord reaction {action:?} from {} in channel {channel_id}",
+            user.name
+        );
+        match reactions::handle_reaction(&self.app_state, "discord", channel_id, &user.name, action)
+            .await
+        {
+            reactions::ReactionOutcome::Ignore => {}
+            reactions::ReactionOutcome::Reply(text) => {
+                let _ = add_reaction.channel_id.say(&ctx.http, text).await;
+            }
+            reactions::ReactionOutcome::RunTurn => {
+                let chat_type = if add_reaction.guild_id.is_some() {
+                    "group"
+                } else {
+                    "private"
+                };
+                self.run_agent_and_reply(
+                    &ctx,
+                    add_reaction.channel_id,
+                    channel_id,
+                    chat_type,
+                    None,
+                    Some(&user.id.get().to_string()),
+                    None,
+                )
+                .await;
+            }
+        }
+    }
+
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        info!("Discord bot connected as {}", ready.user.name);
+        match Command::set_global_commands(&ctx.http, slash_commands()).await {
+            Ok(commands) => info!("Registered {} Discord slash commands", commands.len()),
+            Err(e) => warn!("Failed to register Discord slash commands: {e}"),
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Command(command) = interaction {
+            self.handle_slash_command(&ctx, &command).await;
+        }
+    }
+}
+
+/// Application commands registered on startup.
+fn slash_commands() -> Vec<CreateCommand> {
+    vec![
+        CreateCommand::new("ask")
+            .description("Ask the assistant (runs a full agent turn)")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "prompt", "What to ask")
+                    .required(true),
+            ),
+        CreateCommand::new("usage").description("Token usage and cost for this channel"),
+        CreateCommand::new("tasks").description("Scheduled tasks for this channel"),
+        CreateCommand::new("compact").description("Summarize older session messages now"),
+    ]
+}
+
+fn command_string_option<'a>(command: &'a CommandInteraction, name: &str) -> Option<&'a str> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.value.as_str())
+}
+
+impl Handler {
+    async fn resolve_chat_id(&self, external_channel_id: u64) -> i64 {
+        let external_chat_id = external_channel_id.to_string();
+        let title = format!("discord-{external_channel_id}");
+        call_blocking(self.app_state.db.clone(), move |db| {
+            db.resolve_or_create_chat_id("discord", &external_chat_id, Some(&title), "discord")
+        })
+        .await
+        .unwrap_or(external_channel_id as i64)
+    }
+
+    /// Run an agent turn for `channel_id` and send the response to
+    /// `reply_channel`, with a typing indicator while it runs.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_agent_and_reply(
+        &self,
+        ctx: &Context,
+        reply_channel: ChannelId,
+        channel_id: i64,
+        chat_type: &str,
+        sender: Option<&str>,
+        sender_id: Option<&str>,
+        image_data: Option<(String, String)>,
+    ) {
         // Start typing indicator
-        let typing = msg.channel_id.start_typing(&ctx.http);
+        let typing = reply_channel.start_typing(&ctx.http);
 
-        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
+        let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
+        let events = tokio::spawn(stream_reply_events(
+            ctx.http.clone(),
+            reply_channel,
+            event_rx,
+            StreamingDraft::new(DISCORD_MAX_LEN)
+                .with_text(self.app_state.config.stream_replies)
+                .with_thinking(self.app_state.config.show_thinking)
+                .with_progress(self.app_state.config.progress_status_for_channel("discord")),
+        ));
         // Process with shared agent engine (reuses the same loop as Telegram)
-        match process_with_agent_with_events(
+        let result = process_with_agent_with_events(
             &self.app_state,
             AgentRequestContext {
                 caller_channel: "discord",
                 chat_id: channel_id,
-                chat_type: if msg.guild_id.is_some() {
-                    "group"
-                } else {
-                    "private"
-                },
+                chat_type,
+                sender,
+                sender_id,
             },
             None,
-            None,
+            image_data,
             Some(&event_tx),
         )
-        .await
-        {
+        .await;
+        drop(typing);
+        drop(event_tx);
+        let (streamed, used_send_message_tool) = events.await.unwrap_or((None, false));
+
+        match result {
             Ok(response) => {
-                drop(typing);
-                drop(event_tx);
-                let mut used_send_message_tool = false;
-                while let Some(event) = event_rx.recv().await {
-                    if let AgentEvent::ToolStart { name } = event {
-                        if name == "send_message" {
-                            used_send_message_tool = true;
-                        }
-                    }
+                if let (true, Some(message_id)) = (response.is_empty(), streamed) {
+                    let _ = reply_channel.delete_message(&ctx.http, message_id).await;
                 }
-
                 if !response.is_empty() {
-                    send_discord_response(&ctx, msg.channel_id, &response).await;
+                    match streamed {
+                        // Keep the partial text of a cancelled turn visible.
+                        Some(message_id)
+                            if !response.starts_with(run_control::CANCELLED_PREFIX) =>
+                        {
+                            finish_streamed_response(ctx, reply_channel, message_id, &response)
+                                .await;
+                        }
+                        _ => send_discord_response(ctx, reply_channel, &response).await,
+                    }
 
                     // Store bot response
                     let bot_msg = StoredMessage {
@@ -408,8 +850,9 @@ impl EventHandler for Handler {
                     })
                     .await;
                 } else if !used_send_message_tool {
-                    let fallback = "I couldn't produce a visible reply after an automatic retry. Please try again.".to_string();
-                    send_discord_response(&ctx, msg.channel_id, &fallback).await;
+                    let lang = i18n::chat_language(&self.app_state, channel_id).await;
+                    let fallback = i18n::t(lang, Msg::NoVisibleReply).to_string();
+                    send_discord_response(ctx, reply_channel, &fallback).await;
 
                     let bot_msg = StoredMessage {
                         id: uuid::Uuid::new_v4().to_string(),
@@ -426,44 +869,328 @@ impl EventHandler for Handler {
                 }
             }
             Err(e) => {
-                drop(typing);
                 error!("Error processing Discord message: {e}");
-                let _ = msg.channel_id.say(&ctx.http, format!("Error: {e}")).await;
+                if let Some(message_id) = streamed {
+                    let _ = reply_channel.delete_message(&ctx.http, message_id).await;
+                }
+                let lang = i18n::chat_language(&self.app_state, channel_id).await;
+                let error = i18n::tf(lang, Msg::Error, &[("error", &e)]);
+                let _ = reply_channel.say(&ctx.http, error).await;
             }
         }
     }
 
-    async fn ready(&self, _ctx: Context, ready: Ready) {
-        info!("Discord bot connected as {}", ready.user.name);
+    async fn store_bot_message(&self, chat_id: i64, content: String) {
+        let bot_msg = StoredMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            chat_id,
+            sender_name: self.app_state.config.bot_username.clone(),
+            content,
+            is_from_bot: true,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let _ = call_blocking(self.app_state.db.clone(), move |db| {
+            db.store_message(&bot_msg)
+        })
+        .await;
+    }
+
+    async fn handle_slash_command(&self, ctx: &Context, command: &CommandInteraction) {
+        let external_channel_id = command.channel_id.get();
+        let policy_channel_id = match command.guild_id {
+            Some(_) => thread_info(ctx, command.channel_id)
+                .await
+                .map(|t| t.parent_id.get())
+                .unwrap_or(external_channel_id),
+            None => external_channel_id,
+        };
+        let allowed = &self.app_state.config.discord_allowed_channels;
+        if !allowed.is_empty() && !allowed.contains(&policy_channel_id) {
+            respond_now(ctx, command, "This channel is not enabled for the bot.").await;
+            return;
+        }
+        let chat_id = self.resolve_chat_id(external_channel_id).await;
+
+        match command.data.name.as_str() {
+            "ask" => self.handle_ask(ctx, command, chat_id).await,
+            "usage" => {
+                match build_usage_report(self.app_state.db.clone(), &self.app_state.config, chat_id)
+                    .await
+                {
+                    Ok(text) => {
+                        respond_now(ctx, command, &text).await;
+                        send_usage_chart(&self.app_state, "discord", chat_id).await;
+                    }
+                    Err(e) => {
+                        let text = format!("Failed to query usage statistics: {e}");
+                        respond_now(ctx, command, &text).await;
+                    }
+                }
+            }
+            "tasks" => {
+                let text = match call_blocking(self.app_state.db.clone(), move |db| {
+                    db.get_tasks_for_chat(chat_id)
+                })
+                .await
+                {
+                    Ok(tasks) if tasks.is_empty() => {
+                        "No scheduled tasks found for this channel.".to_string()
+                    }
+                    Ok(tasks) => format_task_list(&tasks),
+                    Err(e) => format!("Failed to list tasks: {e}"),
+                };
+                respond_now(ctx, command, &text).await;
+            }
+            "compact" => {
+                // Summarization calls the model, which can exceed the 3s ack window.
+                if let Err(e) = command.defer(&ctx.http).await {
+                    warn!("Discord: failed to defer /compact: {e}");
+                    return;
+                }
+                let text = compact_session_now(&self.app_state, "discord", chat_id).await;
+                respond_deferred(ctx, command, &text).await;
+            }
+            other => {
+                respond_now(ctx, command, &format!("Unknown command: /{other}")).await;
+            }
+        }
+    }
+
+    /// `/ask`: acknowledge with a deferred reply, run the agent, then edit the
+    /// placeholder with the answer.
+    async fn handle_ask(&self, ctx: &Context, command: &CommandInteraction, chat_id: i64) {
+        let prompt = command_string_option(command, "prompt")
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        if prompt.is_empty() {
+            respond_now(ctx, command, "Usage: /ask prompt:<question>").await;
+            return;
+        }
+        if let Err(e) = command.defer(&ctx.http).await {
+            warn!("Discord: failed to defer /ask: {e}");
+            return;
+        }
+
+        let external_channel_id = command.channel_id.get();
+        let title = format!("discord-{external_channel_id}");
+        let _ = call_blocking(self.app_state.db.clone(), move |db| {
+            db.upsert_chat(chat_id, Some(&title), "discord")
+        })
+        .await;
+        let sender_name = command.user.name.clone();
+        let stored = StoredMessage {
+            id: command.id.get().to_string(),
+            chat_id,
+            sender_name: sender_name.clone(),
+            content: prompt.clone(),
+            is_from_bot: false,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let _ = call_blocking(self.app_state.db.clone(), move |db| {
+            db.store_message(&stored)
+        })
+        .await;
+        info!(
+            "Discord /ask from {} in channel {}: {}",
+            sender_name,
+            chat_id,
+            prompt.chars().take(100).collect::<String>()
+        );
+
+        if self.app_state.config.cancel_on_new_message {
+            run_control::supersede_runs_from(chat_id, &sender_name).await;
+        }
+        let reply = match process_with_agent_with_events(
+            &self.app_state,
+            AgentRequestContext {
+                caller_channel: "discord",
+                chat_id,
+                chat_type: if command.guild_id.is_some() {
+                    "group"
+                } else {
+                    "private"
+                },
+                sender: Some(&sender_name),
+                sender_id: Some(&command.user.id.get().to_string()),
+            },
+            None,
+            None,
+            None,
+        )
+        .await
+        {
+            Ok(response) if !response.is_empty() => {
+                self.store_bot_message(chat_id, response.clone()).await;
+                response
+            }
+            Ok(_) => {
+                let lang = i18n::chat_language(&self.app_state, chat_id).await;
+                i18n::t(lang, Msg::Done).to_string()
+            }
+            Err(e) => {
+                error!("Error processing Discord /ask: {e}");
+                let lang = i18n::chat_language(&self.app_state, chat_id).await;
+                i18n::tf(lang, Msg::Error, &[("error", &e)])
+            }
+        };
+        respond_deferred(ctx, command, &reply).await;
     }
 }
 
-/// Split and send long messages (Discord limit is 2000 chars).
-async fn send_discord_response(ctx: &Context, channel_id: ChannelId, text: &str) {
-    const MAX_LEN: usize = 2000;
+/// Reply to an interaction within the initial acknowledgement window.
+async fn respond_now(ctx: &Context, command: &CommandInteraction, text: &str) {
+    let chunks = split_markdown(text, DISCORD_MAX_LEN);
+    let first = chunks.first().cloned().unwrap_or_default();
+    let response =
+        CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(first));
+    if let Err(e) = command.create_response(&ctx.http, response).await {
+        warn!("Discord: failed to answer /{}: {e}", command.data.name);
+        return;
+    }
+    send_followups(ctx, command, text, &chunks[1.min(chunks.len())..]).await;
+}
 
-    if text.len() <= MAX_LEN {
-        let _ = channel_id.say(&ctx.http, text).await;
+/// Replace a deferred "thinking" placeholder with the reply.
+async fn respond_deferred(ctx: &Context, command: &CommandInteraction, text: &str) {
+    let chunks = split_markdown(text, DISCORD_MAX_LEN);
+    let first = chunks.first().cloned().unwrap_or_default();
+    if let Err(e) = command
+        .edit_response(&ctx.http, EditInteractionResponse::new().content(first))
+        .await
+    {
+        warn!(
+            "Discord: failed to edit /{} response: {e}",
+            command.data.name
+        );
         return;
     }
+    send_followups(ctx, command, text, &chunks[1.min(chunks.len())..]).await;
+}
 
-    let mut remaining = text;
-    while !remaining.is_empty() {
-        let chunk_len = if remaining.len() <= MAX_LEN {
-            remaining.len()
-        } else {
-            let boundary = floor_char_boundary(remaining, MAX_LEN.min(remaining.len()));
-            remaining[..boundary].rfind('\n').unwrap_or(boundary)
-        };
+/// Send the chunks after the first, or the full `text` as a file when there
+/// are too many of them.
+async fn send_followups(
+    ctx: &Context,
+    command: &CommandInteraction,
+    text: &str,
+    chunks: &[String],
+) {
+    if chunks.len() >= MAX_REPLY_CHUNKS {
+        let followup = CreateInteractionResponseFollowup::new().add_file(reply_file(text));
+        match command.create_followup(&ctx.http, followup).await {
+            Ok(_) => return,
+            Err(e) => warn!("Discord: failed to send long reply as a file: {e}"),
+        }
+    }
+    for chunk in chunks {
+        let followup = CreateInteractionResponseFollowup::new().content(chunk);
+        if let Err(e) = command.create_followup(&ctx.http, followup).await {
+            warn!("Discord: failed to send follow-up: {e}");
+            return;
+        }
+    }
+}
+
+/// The full text of a long reply as a Markdown attachment.
+fn reply_file(text: &str) -> CreateAttachment {
+    CreateAttachment::bytes(text.as_bytes().to_vec(), REPLY_FILE_NAME)
+}
 
-        let chunk = &remaining[..chunk_len];
+/// Send a reply, split to Discord's 2000-char limit. Very long replies are
+/// sent as their first chunk with the full text attached as a file.
+async fn send_discord_response(ctx: &Context, channel_id: ChannelId, text: &str) {
+    let chunks = split_markdown(text, DISCORD_MAX_LEN);
+    if chunks.len() > MAX_REPLY_CHUNKS {
+        let message = CreateMessage::new()
+            .content(chunks[0].as_str())
+            .add_file(reply_file(text));
+        match channel_id.send_message(&ctx.http, message).await {
+            Ok(_) => return,
+            Err(e) => warn!("Discord: failed to send long reply as a file: {e}"),
+        }
+    }
+    for chunk in chunks {
         let _ = channel_id.say(&ctx.http, chunk).await;
-        remaining = &remaining[chunk_len..];
+    }
+}
+
+/// Consume agent events while a turn runs, showing `draft` (the streamed
+/// text and/or progress status) in one message that is edited as the turn
+/// goes on. Returns that message and whether the agent used `send_message`.
+async fn stream_reply_events(
+    http: Arc<Http>,
+    channel: ChannelId,
+    mut event_rx: tokio::sync::mpsc::UnboundedReceiver<AgentEvent>,
+    mut draft: StreamingDraft,
+) -> (Option<MessageId>, bool) {
+    let mut streamed = None;
+    let mut used_send_message_tool = false;
+    loop {
+        // Wake up periodically so the progress status's elapsed time moves.
+        let event = match tokio::time::timeout(STATUS_REFRESH_INTERVAL, event_rx.recv()).await {
+            Ok(Some(event)) => Some(event),
+            Ok(None) => break,
+            Err(_) => None,
+        };
+        if let Some(event) = &event {
+            // A merged run is answered by the queued turn.
+            if matches!(event, AgentEvent::ToolStart { name, .. } if name == "send_message")
+                || matches!(event, AgentEvent::Merged)
+            {
+                used_send_message_tool = true;
+            }
+            draft.on_event(event);
+        }
+        if !draft.is_active() {
+            continue;
+        }
+        let now = std::time::Instant::now();
+        let Some(text) = draft.due(now) else {
+            continue;
+        };
+        match streamed {
+            None => match channel.say(&http, text.clone()).await {
+                Ok(sent) => streamed = Some(sent.id),
+                Err(e) => warn!("Discord: failed to send streamed reply: {e}"),
+            },
+            Some(message_id) => {
+                let edit = EditMessage::new().content(text.clone());
+                let _ = channel.edit_message(&http, message_id, edit).await;
+            }
+        }
+        draft.mark_shown(text, now);
+    }
+    (streamed, used_send_message_tool)
+}
 
-        if remaining.starts_with('\n') {
-            remaining = &remaining[1..];
+/// Replace a streamed message with the final response; chunks beyond the
+/// first are sent as new messages, or as a file for very long replies.
+async fn finish_streamed_response(
+    ctx: &Context,
+    channel: ChannelId,
+    message_id: MessageId,
+    text: &str,
+) {
+    let chunks = split_markdown(text, DISCORD_MAX_LEN);
+    let as_file = chunks.len() > MAX_REPLY_CHUNKS;
+    let mut chunks = chunks.into_iter();
+    if let Some(first) = chunks.next() {
+        let edit = EditMessage::new().content(first);
+        if let Err(e) = channel.edit_message(&ctx.http, message_id, edit).await {
+            warn!("Discord: failed to edit streamed reply: {e}");
         }
     }
+    if as_file {
+        let message = CreateMessage::new().add_file(reply_file(text));
+        match channel.send_message(&ctx.http, message).await {
+            Ok(_) => return,
+            Err(e) => warn!("Discord: failed to send long reply as a file: {e}"),
+        }
+    }
+    for chunk in chunks {
+        let _ = channel.say(&ctx.http, chunk).await;
+    }
 }
 
 async fn run_discord_client(
@@ -487,7 +1214,10 @@ fn is_disallowed_gateway_intents(err: &serenity::Error) -> bool {
 
 /// Start the Discord bot. Called from run_bot() if discord_bot_token is configured.
 pub async fn start_discord_bot(app_state: Arc<AppState>, token: &str) {
-    let base_intents = GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES;
+    let base_intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+        | GatewayIntents::DIRECT_MESSAGE_REACTIONS;
     let full_intents = base_intents | GatewayIntents::MESSAGE_CONTENT;
 
     info!("Starting Discord bot (requesting MESSAGE_CONTENT intent)...");
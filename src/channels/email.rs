@@ -0,0 +1,1029 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::agent_engine::archive_conversation;
+use crate::agent_engine::process_with_agent;
+use crate::agent_engine::AgentRequestContext;
+use crate::channel::{local_command_reply, save_inbound_attachment, ConversationKind};
+use crate::channel_adapter::ChannelAdapter;
+use crate::db::call_blocking;
+use crate::db::{Database, StoredMessage};
+use crate::i18n::{self, Msg};
+use crate::llm_types::Message as LlmMessage;
+use crate::run_control;
+use crate::runtime::AppState;
+use crate::usage::{build_usage_report, send_usage_chart};
+
+/// Max unseen messages pulled from the inbox per poll.
+const MAX_FETCH_PER_POLL: usize = 20;
+/// Max message ids carried in the References header of a reply.
+const MAX_REFERENCES: usize = 20;
+
+fn default_imap_port() -> u16 {
+    993
+}
+fn default_smtp_port() -> u16 {
+    465
+}
+fn default_mailbox() -> String {
+    "INBOX".into()
+}
+fn default_poll_interval_secs() -> u64 {
+    60
+}
+fn default_require_sender_auth() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmailChannelConfig {
+    pub imap_host: String,
+    #[serde(default = "default_imap_port")]
+    pub imap_port: u16,
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    /// Use STARTTLS on `smtp_port` (typically 587) instead of implicit TLS.
+    #[serde(default)]
+    pub smtp_starttls: bool,
+    pub username: String,
+    pub password: String,
+    /// Sender address for replies. Defaults to `username`.
+    #[serde(default)]
+    pub from_address: Option<String>,
+    #[serde(default = "default_mailbox")]
+    pub mailbox: String,
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    /// Sender addresses (`alice@example.com`) or domains (`@example.com`)
+    /// allowed to talk to the bot. Empty means nobody; `"*"` means everyone.
+    #[serde(default)]
+    pub allowed_senders: Vec<String>,
+    /// For allowlisted senders, also require the receiving server's
+    /// `Authentication-Results` to show DMARC, or DKIM/SPF aligned with the
+    /// From domain, passing. The From header alone is trivially spoofed.
+    #[serde(default = "default_require_sender_auth")]
+    pub require_sender_auth: bool,
+    /// authserv-id your receiving mail server writes into
+    /// `Authentication-Results` (e.g. `mx.example.com`). Headers from any
+    /// other server are ignored, so `require_sender_auth` needs this set.
+    #[serde(default)]
+    pub auth_serv_id: Option<String>,
+}
+
+impl EmailChannelConfig {
+    fn sender_address(&self) -> &str {
+        self.from_address
+            .as_deref()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or(&self.username)
+    }
+}
+
+/// External chat ids have the form `<sender address>|<thread root message id>`,
+/// so each thread maps to its own chat and replies can be addressed without
+/// extra lookups.
+fn external_chat_id(address: &str, thread_root: &str) -> String {
+    format!("{}|{}", address.to_ascii_lowercase(), thread_root)
+}
+
+fn recipient_from_external_chat_id(external_chat_id: &str) -> &str {
+    external_chat_id
+        .split_once('|')
+        .map(|(addr, _)| addr)
+        .unwrap_or(external_chat_id)
+}
+
+fn reply_subject(subject: &str) -> String {
+    let trimmed = subject.trim();
+    if trimmed.is_empty() {
+        "Re: (no subject)".to_string()
+    } else if trimmed.to_ascii_lowercase().starts_with("re:") {
+        trimmed.to_string()
+    } else {
+        format!("Re: {trimmed}")
+    }
+}
+
+fn allows_everyone(allowed: &[String]) -> bool {
+    allowed.iter().any(|entry| entry.trim() == "*")
+}
+
+fn sender_allowed(allowed: &[String], address: &str) -> bool {
+    if allows_everyone(allowed) {
+        return true;
+    }
+    let address = address.to_ascii_lowercase();
+    allowed.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        if entry.starts_with('@') {
+            address.ends_with(&entry)
+        } else {
+            address == entry
+        }
+    })
+}
+
+fn domain_of(address: &str) -> &str {
+    address
+        .rsplit_once('@')
+        .map_or(address, |(_, domain)| domain)
+}
+
+fn domain_aligned(from_domain: &str, domain: &str) -> bool {
+    let domain = domain.trim_matches(['<', '>', '"']).to_ascii_lowercase();
+    !domain.is_empty()
+        && (from_domain == domain
+            || from_domain
+                .strip_suffix(domain.as_str())
+                .is_some_and(|rest| rest.ends_with('.')))
+}
+
+/// The authserv-id an `Authentication-Results` header starts with.
+fn auth_serv_id(results: &str) -> &str {
+    results
+        .split(';')
+        .next()
+        .and_then(|id| id.split_whitespace().next())
+        .unwrap_or_default()
+}
+
+/// Whether an `Authentication-Results` header vouches for the From address:
+/// a DMARC pass, or a DKIM (`header.d`) or SPF (`smtp.mailfrom`) pass for the
+/// From domain or a parent of it.
+fn sender_authenticated(results: &str, from_address: &str) -> bool {
+    let from_domain = domain_of(from_address).to_ascii_lowercase();
+    // The first element is the authserv-id, checked by the caller.
+    results.split(';').skip(1).any(|clause| {
+        let mut tokens = clause.split_whitespace();
+        let Some(verdict) = tokens.next().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let aligned = |property: &str| {
+            clause.split_whitespace().any(|token| {
+                token
+                    .to_ascii_lowercase()
+                    .strip_prefix(property)
+                    .is_some_and(|value| domain_aligned(&from_domain, domain_of(value)))
+            })
+        };
+        match verdict.as_str() {
+            "dmarc=pass" => true,
+            "dkim=pass" => aligned("header.d="),
+            "spf=pass" => aligned("smtp.mailfrom="),
+            _ => false,
+        }
+    })
+}
+
+/// Auto-replies, bounces and list traffic, which must not get an answer or
+/// two bots end up mailing each other forever.
+fn is_automated(message: &mail_parser::Message, from_address: &str) -> bool {
+    let header = |name: &str| {
+        message
+            .header_raw(name)
+            .map(|v| v.trim().to_ascii_lowercase())
+    };
+    let auto_submitted = header("Auto-Submitted").is_some_and(|v| !v.starts_with("no"));
+    let bulk = header("Precedence").is_some_and(|v| matches!(v.as_str(), "bulk" | "junk" | "list"));
+    let null_return_path = header("Return-Path").is_some_and(|v| v == "<>");
+    let local_part = from_address
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    auto_submitted
+        || bulk
+        || null_return_path
+        || matches!(local_part.as_str(), "mailer-daemon" | "postmaster")
+}
+
+/// Drop quoted history from a reply so only the new text reaches the agent.
+fn strip_quoted_reply(body: &str) -> String {
+    let mut kept = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("-----Original Message-----")
+            || (trimmed.starts_with("On ") && trimmed.ends_with("wrote:"))
+        {
+            break;
+        }
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        kept.push(line);
+    }
+    kept.join("\n").trim().to_string()
+}
+
+#[derive(Debug)]
+struct InboundAttachment {
+    filename: String,
+    mime: String,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct InboundEmail {
+    message_id: String,
+    thread_root: String,
+    from_address: String,
+    from_name: String,
+    subject: String,
+    text: String,
+    references: Vec<String>,
+    attachments: Vec<InboundAttachment>,
+    /// Set from the topmost `Authentication-Results` carrying the configured
+    /// authserv-id, i.e. the one our own mail server added.
+    authenticated: bool,
+    /// Auto-reply, bounce or bulk mail (see [`is_automated`]).
+    automated: bool,
+}
+
+fn parse_inbound_email(raw: &[u8], trusted_serv_id: Option<&str>) -> Option<InboundEmail> {
+    let message = MessageParser::default().parse(raw)?;
+    let from = message.from()?.first()?;
+    let from_address = from.address()?.to_string();
+    let from_name = from.name().unwrap_or(&from_address).to_string();
+    let trusted_serv_id = trusted_serv_id.map(str::trim).filter(|id| !id.is_empty());
+    let authenticated = trusted_serv_id.is_some_and(|trusted| {
+        message
+            .headers()
+            .iter()
+            .filter(|h| {
+                h.name
+                    .as_str()
+                    .eq_ignore_ascii_case("Authentication-Results")
+            })
+            .filter_map(|h| std::str::from_utf8(&raw[h.offset_start..h.offset_end]).ok())
+            .find(|results| auth_serv_id(results).eq_ignore_ascii_case(trusted))
+            .is_some_and(|results| sender_authenticated(results, &from_address))
+    });
+    let automated = is_automated(&message, &from_address);
+    let message_id = message
+        .message_id()
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| format!("{}@microclaw", uuid::Uuid::new_v4()));
+
+    let mut references: Vec<String> = message
+        .references()
+        .as_text_list()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect();
+    if references.is_empty() {
+        if let Some(parent) = message.in_reply_to().as_text() {
+            references.push(parent.to_string());
+        }
+    }
+    let thread_root = references
+        .first()
+        .cloned()
+        .unwrap_or_else(|| message_id.clone());
+
+    let attachments = message
+        .attachments()
+        .map(|part| InboundAttachment {
+            filename: part
+                .attachment_name()
+                .unwrap_or("attachment.bin")
+                .to_string(),
+            mime: part
+                .content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(sub) => format!("{}/{}", ct.ctype(), sub),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+            bytes: part.contents().to_vec(),
+        })
+        .collect();
+
+    Some(InboundEmail {
+        message_id,
+        thread_root,
+        from_address,
+        from_name,
+        subject: message.subject().unwrap_or_default().to_string(),
+        text: strip_quoted_reply(&message.body_text(0).unwrap_or_default()),
+        references,
+        attachments,
+        authenticated,
+        automated,
+    })
+}
+
+/// SMTP transport with implicit TLS, or STARTTLS when `starttls` is set.
+/// Also used by the `send_email` tool.
+pub fn build_smtp_transport(
+    host: &str,
+    port: u16,
+    starttls: bool,
+    username: &str,
+    password: &str,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    let builder = if starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+    }
+    .map_err(|e| format!("Invalid SMTP host: {e}"))?;
+    let builder = builder.port(port);
+    Ok(if username.is_empty() {
+        builder.build()
+    } else {
+        builder
+            .credentials(Credentials::new(username.to_string(), password.to_string()))
+            .build()
+    })
+}
+
+fn smtp_transport(cfg: &EmailChannelConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    build_smtp_transport(
+        &cfg.smtp_host,
+        cfg.smtp_port,
+        cfg.smtp_starttls,
+        &cfg.username,
+        &cfg.password,
+    )
+}
+
+pub struct EmailAdapter {
+    config: EmailChannelConfig,
+    db: Arc<Database>,
+}
+
+impl EmailAdapter {
+    pub fn new(config: EmailChannelConfig, db: Arc<Database>) -> Self {
+        EmailAdapter { config, db }
+    }
+
+    async fn reply_builder(
+        &self,
+        external_chat_id: &str,
+    ) -> Result<lettre::message::MessageBuilder, String> {
+        let from: Mailbox = self
+            .config
+            .sender_address()
+            .parse()
+            .map_err(|e| format!("Invalid email from_address: {e}"))?;
+        let to: Mailbox = recipient_from_external_chat_id(external_chat_id)
+            .parse()
+            .map_err(|e| format!("Invalid email recipient: {e}"))?;
+        let external = external_chat_id.to_string();
+        let thread = call_blocking(self.db.clone(), move |db| {
+            match db.get_chat_id_by_external("email", &external)? {
+                Some(chat_id) => db.load_email_thread(chat_id),
+                None => Ok(None),
+            }
+        })
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Email: failed to load thread state for {external_chat_id}: {e}");
+            None
+        })
+        .unwrap_or_default();
+
+        let mut builder = lettre::Message::builder()
+            .from(from)
+            .to(to)
+            .subject(reply_subject(&thread.subject));
+        if let Some(last) = thread.message_ids.last() {
+            builder = builder.in_reply_to(format!("<{last}>"));
+        }
+        if !thread.message_ids.is_empty() {
+            let refs = thread
+                .message_ids
+                .iter()
+                .map(|id| format!("<{id}>"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            builder = builder.references(refs);
+        }
+        Ok(builder)
+    }
+
+    async fn send(&self, message: lettre::Message) -> Result<(), String> {
+        smtp_transport(&self.config)?
+            .send(message)
+            .await
+            .map_err(|e| format!("Failed to send email: {e}"))?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ChannelAdapter for EmailAdapter {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn chat_type_routes(&self) -> Vec<(&str, ConversationKind)> {
+        vec![("email", ConversationKind::Private)]
+    }
+
+    async fn send_text(&self, external_chat_id: &str, text: &str) -> Result<(), String> {
+        let message = self
+            .reply_builder(external_chat_id)
+            .await?
+            .header(ContentType::TEXT_PLAIN)
+            .body(text.to_string())
+            .map_err(|e| format!("Failed to build email: {e}"))?;
+        self.send(message).await
+    }
+
+    async fn send_attachment(
+        &self,
+        external_chat_id: &str,
+        file_path: &Path,
+        caption: Option<&str>,
+    ) -> Result<String, String> {
+        let filename = file_path
+            .file_name()
+            .and_then(|v| v.to_str())
+            .unwrap_or("attachment.bin")
+            .to_string();
+        let bytes = tokio::fs::read(file_path)
+            .await
+            .map_err(|e| format!("Failed to read attachment file: {e}"))?;
+        let octet_stream = ContentType::parse("application/octet-stream")
+            .map_err(|e| format!("Invalid attachment content type: {e}"))?;
+        let message = self
+            .reply_builder(external_chat_id)
+            .await?
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(caption.unwrap_or_default().to_string()))
+                    .singlepart(Attachment::new(filename).body(bytes, octet_stream)),
+            )
+            .map_err(|e| format!("Failed to build email: {e}"))?;
+        self.send(message).await?;
+
+        Ok(match caption {
+            Some(c) => format!("[attachment:{}] {}", file_path.display(), c),
+            None => format!("[attachment:{}]", file_path.display()),
+        })
+    }
+}
+
+/// Fetch unseen messages from the configured mailbox. Fetching `RFC822`
+/// marks them as seen, so each message is processed once.
+async fn fetch_unseen(cfg: &EmailChannelConfig) -> Result<Vec<Vec<u8>>, String> {
+    let tcp = tokio::net::TcpStream::connect((cfg.imap_host.as_str(), cfg.imap_port))
+        .await
+        .map_err(|e| format!("IMAP connect failed: {e}"))?;
+    let connector = tokio_native_tls::native_tls::TlsConnector::new()
+        .map_err(|e| format!("TLS init failed: {e}"))?;
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+        .connect(&cfg.imap_host, tcp)
+        .await
+        .map_err(|e| format!("IMAP TLS handshake failed: {e}"))?;
+
+    let mut client = async_imap::Client::new(tls);
+    client
+        .read_response()
+        .await
+        .ok_or_else(|| "IMAP server closed connection before greeting".to_string())?
+        .map_err(|e| format!("IMAP greeting failed: {e}"))?;
+    let mut session = client
+        .login(&cfg.username, &cfg.password)
+        .await
+        .map_err(|(e, _)| format!("IMAP login failed: {e}"))?;
+    session
+        .select(&cfg.mailbox)
+        .await
+        .map_err(|e| format!("IMAP select {} failed: {e}", cfg.mailbox))?;
+
+    let mut uids: Vec<u32> = session
+        .uid_search("UNSEEN")
+        .await
+        .map_err(|e| format!("IMAP search failed: {e}"))?
+        .into_iter()
+        .collect();
+    uids.sort_unstable();
+    uids.truncate(MAX_FETCH_PER_POLL);
+
+    let mut raw_messages = Vec::new();
+    if !uids.is_empty() {
+        let uid_set = uids
+            .iter()
+            .map(|u| u.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut fetches = session
+            .uid_fetch(uid_set, "RFC822")
+            .await
+            .map_err(|e| format!("IMAP fetch failed: {e}"))?;
+        while let Some(fetch) = fetches.next().await {
+            match fetch {
+                Ok(f) => {
+                    if let Some(body) = f.body() {
+                        raw_messages.push(body.to_vec());
+                    }
+                }
+                Err(e) => warn!("Email: failed to read fetched message: {e}"),
+            }
+        }
+    }
+
+    let _ = session.logout().await;
+    Ok(raw_messages)
+}
+
+/// Start the email channel: poll the IMAP inbox and reply over SMTP.
+pub async fn start_email_bot(app_state: Arc<AppState>) {
+    let email_cfg: EmailChannelConfig = match app_state.config.channel_config("email") {
+        Some(c) => c,
+        None => {
+            error!("Email channel not configured");
+            return;
+        }
+    };
+    if email_cfg.allowed_senders.is_empty() {
+        warn!("Email: allowed_senders is empty, no mail will be answered (use \"*\" for everyone)");
+    } else if !allows_everyone(&email_cfg.allowed_senders)
+        && email_cfg.require_sender_auth
+        && email_cfg.auth_serv_id.is_none()
+    {
+        warn!("Email: require_sender_auth is on but auth_serv_id is not set, so no sender can authenticate");
+    }
+    let interval = std::time::Duration::from_secs(email_cfg.poll_interval_secs.max(10));
+    info!(
+        "Email: polling {}@{}:{} every {}s",
+        email_cfg.mailbox,
+        email_cfg.imap_host,
+        email_cfg.imap_port,
+        interval.as_secs()
+    );
+
+    loop {
+        match fetch_unseen(&email_cfg).await {
+            Ok(raw_messages) => {
+                for raw in raw_messages {
+                    let Some(email) = parse_inbound_email(&raw, email_cfg.auth_serv_id.as_deref())
+                    else {
+                        warn!("Email: skipping unparseable message");
+                        continue;
+                    };
+                    let state = app_state.clone();
+                    let cfg = email_cfg.clone();
+                    tokio::spawn(async move {
+                        handle_email_message(state, &cfg, email).await;
+                    });
+                }
+            }
+            Err(e) => warn!("Email: inbox poll failed: {e}"),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn save_attachments(
+    state: &AppState,
+    chat_id: i64,
+    attachments: &[InboundAttachment],
+) -> Vec<String> {
+    let mut notes = Vec::new();
+    for att in attachments {
+        notes.push(
+            save_inbound_attachment(
+                &state.config,
+                "email",
+                chat_id,
+                &att.filename,
+                &att.mime,
+                &att.bytes,
+            )
+            .await,
+        );
+    }
+    notes
+}
+
+async fn reply(state: &AppState, external: &str, text: &str) {
+    let Some(adapter) = state.channel_registry.get("email") else {
+        error!("Email: adapter not registered");
+        return;
+    };
+    if let Err(e) = adapter.send_text(external, text).await {
+        error!("Email: failed to send reply: {e}");
+    }
+}
+
+async fn handle_email_message(
+    app_state: Arc<AppState>,
+    cfg: &EmailChannelConfig,
+    email: InboundEmail,
+) {
+    if email
+        .from_address
+        .eq_ignore_ascii_case(cfg.sender_address())
+    {
+        return;
+    }
+    if email.automated {
+        info!(
+            "Email: ignoring automated message from {}",
+            email.from_address
+        );
+        return;
+    }
+    if !sender_allowed(&cfg.allowed_senders, &email.from_address) {
+        info!("Email: ignoring message from {}", email.from_address);
+        return;
+    }
+    if !allows_everyone(&cfg.allowed_senders) && cfg.require_sender_auth && !email.authenticated {
+        info!(
+            "Email: ignoring message from {} that did not pass DMARC/DKIM/SPF",
+            email.from_address
+        );
+        return;
+    }
+
+    let external = external_chat_id(&email.from_address, &email.thread_root);
+    let chat_id = call_blocking(app_state.db.clone(), {
+        let external = external.clone();
+        let title = format!("email-{}", email.subject.trim());
+        move |db| db.resolve_or_create_chat_id("email", &external, Some(&title), "email")
+    })
+    .await
+    .unwrap_or(0);
+    if chat_id == 0 {
+        error!("Email: failed to resolve chat ID for {external}");
+        return;
+    }
+
+    let thread_update = call_blocking(app_state.db.clone(), {
+        let subject = email.subject.clone();
+        let ids: Vec<String> = email
+            .references
+            .iter()
+            .chain([&email.message_id])
+            .cloned()
+            .collect();
+        move |db| {
+            let mut thread = db.load_email_thread(chat_id)?.unwrap_or_default();
+            if thread.subject.is_empty() {
+                thread.subject = subject;
+            }
+            for id in ids {
+                if !thread.message_ids.contains(&id) {
+                    thread.message_ids.push(id);
+                }
+            }
+            let overflow = thread.message_ids.len().saturating_sub(MAX_REFERENCES);
+            // Keep the root so clients still thread the reply correctly.
+            if overflow > 0 {
+                thread.message_ids.drain(1..=overflow);
+            }
+            db.save_email_thread(chat_id, &thread)
+        }
+    })
+    .await;
+    if let Err(e) = thread_update {
+        warn!("Email: failed to save thread state for {external}: {e}");
+    }
+
+    let notes = save_attachments(&app_state, chat_id, &email.attachments).await;
+    let mut content = email.text.clone();
+    for note in notes {
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&note);
+    }
+    if content.trim().is_empty() {
+        return;
+    }
+
+    let stored = StoredMessage {
+        id: email.message_id.clone(),
+        chat_id,
+        sender_name: email.from_name.clone(),
+        content: content.clone(),
+        is_from_bot: false,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    let _ = call_blocking(app_state.db.clone(), move |db| db.store_message(&stored)).await;
+
+    // Commands are accepted on the first line of the body
+    let command = email.text.lines().next().unwrap_or("").trim();
+    if command == "/stop" {
+        let cancelled = run_control::cancel_chat_runs(chat_id);
+        let lang = i18n::chat_language(&app_state, chat_id).await;
+        reply(
+            &app_state,
+            &external,
+            run_control::stop_command_reply(lang, cancelled),
+        )
+        .await;
+        return;
+    }
+    if command == "/reset" {
+        let _ = call_blocking(app_state.db.clone(), move |db| {
+            db.clear_chat_context(chat_id)
+        })
+        .await;
+        let lang = i18n::chat_language(&app_state, chat_id).await;
+        reply(&app_state, &external, i18n::t(lang, Msg::ContextCleared)).await;
+        return;
+    }
+    if command == "/skills" {
+        let formatted = app_state.skills.list_skills_formatted();
+        reply(&app_state, &external, &formatted).await;
+        return;
+    }
+    if command == "/archive" {
+        let messages: Vec<LlmMessage> =
+            match call_blocking(app_state.db.clone(), move |db| db.load_session(chat_id)).await {
+                Ok(Some((json, _))) => serde_json::from_str(&json).unwrap_or_default(),
+                _ => Vec::new(),
+            };
+        let lang = i18n::chat_language(&app_state, chat_id).await;
+        if messages.is_empty() {
+            reply(
+                &app_state,
+                &external,
+                i18n::t(lang, Msg::NoSessionToArchive),
+            )
+            .await;
+        } else {
+            archive_conversation(&app_state.config.data_dir, "email", chat_id, &messages);
+            let text = i18n::tf(lang, Msg::Archived, &[("count", &messages.len())]);
+            reply(&app_state, &external, &text).await;
+        }
+        return;
+    }
+    if command == "/usage" {
+        match build_usage_report(app_state.db.clone(), &app_state.config, chat_id).await {
+            Ok(report) => {
+                reply(&app_state, &external, &report).await;
+                send_usage_chart(&app_state, "email", chat_id).await;
+            }
+            Err(e) => {
+                let text = format!("Failed to query usage statistics: {e}");
+                reply(&app_state, &external, &text).await;
+            }
+        }
+        return;
+    }
+
+    if let Some(text) = local_command_reply(
+        &app_state,
+        "email",
+        chat_id,
+        Some(&email.from_address),
+        command,
+    )
+    .await
+    {
+        reply(&app_state, &external, &text).await;
+        return;
+    }
+
+    info!(
+        "Email from {} ({}): {}",
+        email.from_address,
+        email.subject,
+        content.chars().take(100).collect::<String>()
+    );
+
+    if app_state.config.cancel_on_new_message {
+        run_control::supersede_runs_from(chat_id, &email.from_address).await;
+    }
+
+    match process_with_agent(
+        &app_state,
+        AgentRequestContext {
+            caller_channel: "email",
+            chat_id,
+            chat_type: "private",
+            sender: Some(&email.from_address),
+            sender_id: Some(&email.from_address),
+        },
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(response) => {
+            if response.is_empty() {
+                return;
+            }
+            reply(&app_state, &external, &response).await;
+            let bot_msg = StoredMessage {
+                id: uuid::Uuid::new_v4().to_string(),
+                chat_id,
+                sender_name: app_state.config.bot_username.clone(),
+                content: response,
+                is_from_bot: true,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            };
+            let _ = call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+        }
+        Err(e) => {
+            error!("Error processing email: {e}");
+            let lang = i18n::chat_language(&app_state, chat_id).await;
+            let error = i18n::tf(lang, Msg::Error, &[("error", &e)]);
+            reply(&app_state, &external, &error).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY_EMAIL: &str = "From: Alice <alice@example.com>\r\n\
+To: bot@example.com\r\n\
+Subject: Re: Quarterly report\r\n\
+Message-ID: <msg-2@example.com>\r\n\
+In-Reply-To: <msg-1@example.com>\r\n\
+References: <root@example.com> <msg-1@example.com>\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+\r\n\
+Sounds good, ship it.\r\n\
+\r\n\
+On Mon, Jan 1, 2024 at 9:00 AM Bot <bot@example.com> wrote:\r\n\
+> Draft attached.\r\n";
+
+    #[test]
+    fn test_parse_inbound_email_threads_on_root_reference() {
+        let email = parse_inbound_email(REPLY_EMAIL.as_bytes(), None).unwrap();
+        assert_eq!(email.from_address, "alice@example.com");
+        assert_eq!(email.from_name, "Alice");
+        assert_eq!(email.message_id, "msg-2@example.com");
+        assert_eq!(email.thread_root, "root@example.com");
+        assert_eq!(email.subject, "Re: Quarterly report");
+        assert_eq!(email.text, "Sounds good, ship it.");
+        assert!(email.attachments.is_empty());
+        assert!(!email.automated);
+    }
+
+    #[test]
+    fn test_parse_inbound_email_new_thread_uses_own_id() {
+        let raw = "From: bob@example.com\r\nSubject: Hi\r\nMessage-ID: <new@example.com>\r\n\r\nhello\r\n";
+        let email = parse_inbound_email(raw.as_bytes(), None).unwrap();
+        assert_eq!(email.thread_root, "new@example.com");
+        assert_eq!(email.from_name, "bob@example.com");
+        assert!(email.references.is_empty());
+    }
+
+    #[test]
+    fn test_parse_inbound_email_collects_attachments() {
+        let raw = "From: bob@example.com\r\n\
+Subject: data\r\n\
+Message-ID: <att@example.com>\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/mixed; boundary=\"XX\"\r\n\
+\r\n\
+--XX\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+see attached\r\n\
+--XX\r\n\
+Content-Type: text/csv\r\n\
+Content-Disposition: attachment; filename=\"data.csv\"\r\n\
+\r\n\
+a,b\r\n\
+--XX--\r\n";
+        let email = parse_inbound_email(raw.as_bytes(), None).unwrap();
+        assert_eq!(email.text, "see attached");
+        assert_eq!(email.attachments.len(), 1);
+        assert_eq!(email.attachments[0].filename, "data.csv");
+        assert_eq!(email.attachments[0].mime, "text/csv");
+    }
+
+    #[test]
+    fn test_strip_quoted_reply() {
+        let body = "new text\n> old\n-----Original Message-----\nolder";
+        assert_eq!(strip_quoted_reply(body), "new text");
+    }
+
+    #[test]
+    fn test_reply_subject() {
+        assert_eq!(reply_subject("Hello"), "Re: Hello");
+        assert_eq!(reply_subject("RE: Hello"), "RE: Hello");
+        assert_eq!(reply_subject("  "), "Re: (no subject)");
+    }
+
+    #[test]
+    fn test_external_chat_id_roundtrip() {
+        let id = external_chat_id("Alice@Example.com", "root@example.com");
+        assert_eq!(id, "alice@example.com|root@example.com");
+        assert_eq!(recipient_from_external_chat_id(&id), "alice@example.com");
+    }
+
+    #[test]
+    fn test_sender_authenticated() {
+        let from = "alice@mail.example.com";
+        assert!(sender_authenticated(
+            "mx.bot.net; dmarc=pass (p=reject) header.from=example.com",
+            from
+        ));
+        assert!(sender_authenticated(
+            "mx.bot.net;\r\n\tdkim=pass header.d=example.com header.s=s1; spf=fail",
+            from
+        ));
+        assert!(sender_authenticated(
+            "mx.bot.net; spf=pass smtp.mailfrom=bounce@mail.example.com",
+            from
+        ));
+        // A pass for some other domain says nothing about the From address.
+        assert!(!sender_authenticated(
+            "mx.bot.net; dkim=pass header.d=evil.com; spf=pass smtp.mailfrom=x@evil.com",
+            from
+        ));
+        assert!(!sender_authenticated(
+            "mx.bot.net; dkim=pass header.d=notexample.com",
+            from
+        ));
+        assert!(!sender_authenticated(
+            "mx.bot.net; dmarc=fail; dkim=none",
+            from
+        ));
+        assert!(!sender_authenticated("dmarc=pass", from));
+
+        let authenticated = |raw: &str, serv_id: Option<&str>| {
+            parse_inbound_email(raw.as_bytes(), serv_id)
+                .unwrap()
+                .authenticated
+        };
+        let raw = format!(
+            "Authentication-Results: mx.bot.net; dkim=pass header.d=example.com\r\n\
+             Authentication-Results: forged; dmarc=pass\r\n{REPLY_EMAIL}"
+        );
+        assert!(authenticated(&raw, Some("mx.bot.net")));
+        // Without a configured authserv-id no header is trusted.
+        assert!(!authenticated(&raw, None));
+        let raw = format!(
+            "Authentication-Results: mx.bot.net; dmarc=fail\r\n\
+             Authentication-Results: forged; dmarc=pass\r\n{REPLY_EMAIL}"
+        );
+        assert!(!authenticated(&raw, Some("mx.bot.net")));
+        // A header naming another server is ignored, even when it is on top.
+        let raw = format!(
+            "Authentication-Results: mx.evil.net; dmarc=pass\r\n\
+             Authentication-Results: mx.bot.net 1; dkim=pass header.d=example.com\r\n{REPLY_EMAIL}"
+        );
+        assert!(authenticated(&raw, Some("mx.bot.net")));
+        let raw = format!("Authentication-Results: mx.evil.net; dmarc=pass\r\n{REPLY_EMAIL}");
+        assert!(!authenticated(&raw, Some("mx.bot.net")));
+        assert!(!authenticated(REPLY_EMAIL, Some("mx.bot.net")));
+    }
+
+    #[test]
+    fn test_parse_inbound_email_flags_automated_mail() {
+        for header in [
+            "Auto-Submitted: auto-replied",
+            "Precedence: bulk",
+            "Precedence: list",
+            "Return-Path: <>",
+        ] {
+            let raw = format!("{header}\r\n{REPLY_EMAIL}");
+            let email = parse_inbound_email(raw.as_bytes(), None).unwrap();
+            assert!(email.automated, "{header}");
+        }
+        let raw =
+            format!("Auto-Submitted: no\r\nReturn-Path: <alice@example.com>\r\n{REPLY_EMAIL}");
+        assert!(!parse_inbound_email(raw.as_bytes(), None).unwrap().automated);
+        let raw = REPLY_EMAIL.replace("alice@example.com", "MAILER-DAEMON@example.com");
+        assert!(parse_inbound_email(raw.as_bytes(), None).unwrap().automated);
+    }
+
+    #[test]
+    fn test_sender_allowed() {
+        assert!(!sender_allowed(&[], "anyone@x.com"));
+        assert!(sender_allowed(&["*".to_string()], "anyone@x.com"));
+        let allowed = vec!["alice@example.com".to_string(), "@corp.com".to_string()];
+        assert!(sender_allowed(&allowed, "Alice@example.com"));
+        assert!(sender_allowed(&allowed, "bob@corp.com"));
+        assert!(!sender_allowed(&allowed, "eve@evil.com"));
+        assert!(!allows_everyone(&allowed));
+    }
+
+    #[test]
+    fn test_email_channel_config_defaults() {
+        let cfg: EmailChannelConfig = serde_yaml::from_str(
+            "imap_host: imap.example.com\nsmtp_host: smtp.example.com\nusername: bot@example.com\npassword: pw\n",
+        )
+        .unwrap();
+        assert_eq!(cfg.imap_port, 993);
+        assert_eq!(cfg.smtp_port, 465);
+        assert_eq!(cfg.mailbox, "INBOX");
+        assert_eq!(cfg.sender_address(), "bot@example.com");
+        assert!(cfg.require_sender_auth);
+        assert!(cfg.allowed_senders.is_empty());
+        assert!(cfg.auth_serv_id.is_none());
+    }
+}
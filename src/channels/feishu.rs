@@ -13,11 +13,12 @@ use crate::agent_engine::archive_conversation;
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::AgentEvent;
 use crate::agent_engine::AgentRequestContext;
-use crate::channel::ConversationKind;
+use crate::channel::{local_command_reply, ConversationKind};
 use crate::channel_adapter::ChannelAdapter;
 use crate::db::call_blocking;
 use crate::db::StoredMessage;
 use crate::llm_types::Message as LlmMessage;
+use crate::run_control;
 use crate::runtime::AppState;
 
 type WsSink = Arc<
@@ -30,8 +31,9 @@ type WsSink = Arc<
         >,
     >,
 >;
+use crate::i18n::{self, Msg};
 use crate::text::split_text;
-use crate::usage::build_usage_report;
+use crate::usage::{build_usage_report, send_usage_chart};
 
 // ---------------------------------------------------------------------------
 // Config
@@ -1331,6 +1333,19 @@ async fn handle_feishu_message(
     };
 
     let trimmed = text.trim();
+    if trimmed == "/stop" {
+        let cancelled = run_control::cancel_chat_runs(chat_id);
+        let lang = i18n::chat_language(&app_state, chat_id).await;
+        let _ = send_feishu_response(
+            &http_client,
+            base_url,
+            &token,
+            external_chat_id,
+            run_control::stop_command_reply(lang, cancelled),
+        )
+        .await;
+        return;
+    }
     if trimmed == "/reset" {
         let _ = call_blocking(app_state.db.clone(), move |db| {
             db.clear_chat_context(chat_id)
@@ -1395,6 +1410,7 @@ async fn handle_feishu_message(
                 let _ =
                     send_feishu_response(&http_client, base_url, &token, external_chat_id, &report)
                         .await;
+                send_usage_chart(&app_state, "feishu", chat_id).await;
             }
             Err(e) => {
                 let _ = send_feishu_response(
@@ -1409,6 +1425,13 @@ async fn handle_feishu_message(
         }
         return;
     }
+    if let Some(reply) =
+        local_command_reply(&app_state, "feishu", chat_id, Some(user), trimmed).await
+    {
+        let _ =
+            send_feishu_response(&http_client, base_url, &token, external_chat_id, &reply).await;
+        return;
+    }
 
     // Determine if we should respond
     let should_respond = is_dm || is_mentioned;
@@ -1423,6 +1446,10 @@ async fn handle_feishu_message(
         text.chars().take(100).collect::<String>()
     );
 
+    if app_state.config.cancel_on_new_message {
+        run_control::supersede_runs_from(chat_id, user).await;
+    }
+
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
 
     match process_with_agent_with_events(
@@ -1431,6 +1458,8 @@ async fn handle_feishu_message(
             caller_channel: "feishu",
             chat_id,
             chat_type: if is_dm { "private" } else { "group" },
+            sender: Some(user),
+            sender_id: Some(user),
         },
         None,
         None,
@@ -1442,10 +1471,13 @@ async fn handle_feishu_message(
             drop(event_tx);
             let mut used_send_message_tool = false;
             while let Some(event) = event_rx.recv().await {
-                if let AgentEvent::ToolStart { name } = event {
-                    if name == "send_message" {
+                match event {
+                    AgentEvent::ToolStart { name, .. } if name == "send_message" => {
                         used_send_message_tool = true;
                     }
+                    // Answered by the turn queued ahead of this message.
+                    AgentEvent::Merged => used_send_message_tool = true,
+                    _ => {}
                 }
             }
 
@@ -1473,8 +1505,8 @@ async fn handle_feishu_message(
                 let _ =
                     call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
             } else if !used_send_message_tool {
-                let fallback =
-                    "I couldn't produce a visible reply after an automatic retry. Please try again.";
+                let lang = i18n::chat_language(&app_state, chat_id).await;
+                let fallback = i18n::t(lang, Msg::NoVisibleReply);
                 let _ = send_feishu_response(
                     &http_client,
                     base_url,
@@ -1503,7 +1535,11 @@ async fn handle_feishu_message(
                 base_url,
                 &token,
                 external_chat_id,
-                &format!("Error: {e}"),
+                &i18n::tf(
+                    i18n::chat_language(&app_state, chat_id).await,
+                    Msg::Error,
+                    &[("error", &e)],
+                ),
             )
             .await;
         }
@@ -1,11 +1,16 @@
 pub mod delivery;
 pub mod discord;
+pub mod email;
 pub mod feishu;
+pub mod signal;
 pub mod slack;
 pub mod telegram;
 
 // Re-export adapter types
 pub use discord::DiscordAdapter;
+pub use email::EmailAdapter;
 pub use feishu::FeishuAdapter;
+pub use signal::SignalAdapter;
 pub use slack::SlackAdapter;
 pub use telegram::TelegramAdapter;
+pub mod webhook;
@@ -0,0 +1,738 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use base64::Engine;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+
+use crate::agent_engine::archive_conversation;
+use crate::agent_engine::process_with_agent;
+use crate::agent_engine::AgentRequestContext;
+use crate::channel::{image_input, local_command_reply, save_inbound_attachment, ConversationKind};
+use crate::channel_adapter::ChannelAdapter;
+use crate::db::call_blocking;
+use crate::db::StoredMessage;
+use crate::i18n::{self, Msg};
+use crate::llm::SseEventParser;
+use crate::llm_types::Message as LlmMessage;
+use crate::run_control;
+use crate::runtime::AppState;
+use crate::text::split_text;
+use crate::usage::{build_usage_report, send_usage_chart};
+
+/// Signal renders longer messages as a text attachment; stay below that.
+const SIGNAL_MAX_MESSAGE_LEN: usize = 2000;
+/// External chat ids of group conversations carry this prefix.
+const GROUP_PREFIX: &str = "group:";
+/// Placeholder signal-cli puts in message text where a mention was.
+const MENTION_PLACEHOLDER: char = '\u{FFFC}';
+
+fn default_http_url() -> String {
+    "http://127.0.0.1:8080".into()
+}
+fn default_group_require_mention() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignalChannelConfig {
+    /// Bot account registered with signal-cli, in E.164 form (`+15551234567`).
+    pub account: String,
+    /// Base URL of `signal-cli daemon --http`.
+    #[serde(default = "default_http_url")]
+    pub http_url: String,
+    /// Phone numbers allowed to talk to the bot. Empty means everyone.
+    #[serde(default)]
+    pub allowed_numbers: Vec<String>,
+    /// Group ids the bot answers in. Empty means every group it is a member of.
+    #[serde(default)]
+    pub allowed_groups: Vec<String>,
+    /// In groups, only answer messages that mention the bot.
+    #[serde(default = "default_group_require_mention")]
+    pub group_require_mention: bool,
+}
+
+fn normalize_number(number: &str) -> String {
+    number
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '+')
+        .collect()
+}
+
+fn number_allowed(allowed: &[String], number: &str) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    let number = normalize_number(number);
+    allowed.iter().any(|n| normalize_number(n) == number)
+}
+
+fn group_allowed(allowed: &[String], group_id: &str) -> bool {
+    allowed.is_empty() || allowed.iter().any(|g| g.trim() == group_id)
+}
+
+fn external_chat_id(sender: &str, group_id: Option<&str>) -> String {
+    match group_id {
+        Some(group) => format!("{GROUP_PREFIX}{group}"),
+        None => sender.to_string(),
+    }
+}
+
+/// JSON-RPC params that address a conversation by its external chat id.
+fn target_params(external_chat_id: &str) -> Value {
+    match external_chat_id.strip_prefix(GROUP_PREFIX) {
+        Some(group) => json!({ "groupId": group }),
+        None => json!({ "recipient": [external_chat_id] }),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SignalAttachmentRef {
+    id: String,
+    filename: String,
+    content_type: String,
+}
+
+#[derive(Debug)]
+struct InboundSignal {
+    sender: String,
+    sender_name: String,
+    group_id: Option<String>,
+    timestamp: i64,
+    text: String,
+    mentioned_numbers: Vec<String>,
+    attachments: Vec<SignalAttachmentRef>,
+}
+
+/// Parse a `receive` event from signal-cli. Accepts both the bare
+/// `{"envelope": ...}` payload and a JSON-RPC notification wrapping it.
+/// Only data messages are returned; receipts, typing and sync messages are
+/// ignored.
+fn parse_receive_event(event: &Value) -> Option<InboundSignal> {
+    let envelope = event
+        .get("envelope")
+        .or_else(|| event.pointer("/params/envelope"))?;
+    let data = envelope.get("dataMessage")?;
+    let sender = envelope
+        .get("sourceNumber")
+        .or_else(|| envelope.get("source"))
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())?
+        .to_string();
+    let sender_name = envelope
+        .get("sourceName")
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or(&sender)
+        .to_string();
+    let group_id = data
+        .pointer("/groupInfo/groupId")
+        .and_then(|v| v.as_str())
+        .map(str::to_string);
+    let timestamp = data
+        .get("timestamp")
+        .or_else(|| envelope.get("timestamp"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or_default();
+
+    let mut mentions: Vec<&Value> = data
+        .get("mentions")
+        .and_then(|v| v.as_array())
+        .map(|v| v.iter().collect())
+        .unwrap_or_default();
+    mentions.sort_by_key(|m| m.get("start").and_then(|v| v.as_i64()).unwrap_or_default());
+    let mentioned_numbers = mentions
+        .iter()
+        .filter_map(|m| m.get("number").and_then(|v| v.as_str()))
+        .map(str::to_string)
+        .collect();
+
+    // Replace mention placeholders, in order, with readable @names.
+    let raw_text = data.get("message").and_then(|v| v.as_str()).unwrap_or("");
+    let mut mention_names = mentions.iter().map(|m| {
+        m.get("name")
+            .or_else(|| m.get("number"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("someone")
+    });
+    let mut text = String::with_capacity(raw_text.len());
+    for c in raw_text.chars() {
+        if c == MENTION_PLACEHOLDER {
+            text.push('@');
+            text.push_str(mention_names.next().unwrap_or("someone"));
+        } else {
+            text.push(c);
+        }
+    }
+
+    let attachments = data
+        .get("attachments")
+        .and_then(|v| v.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|a| {
+                    let id = a.get("id").and_then(|v| v.as_str())?.to_string();
+                    Some(SignalAttachmentRef {
+                        filename: a
+                            .get("filename")
+                            .and_then(|v| v.as_str())
+                            .filter(|v| !v.is_empty())
+                            .unwrap_or(&id)
+                            .to_string(),
+                        content_type: a
+                            .get("contentType")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("application/octet-stream")
+                            .to_string(),
+                        id,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(InboundSignal {
+        sender,
+        sender_name,
+        group_id,
+        timestamp,
+        text: text.trim().to_string(),
+        mentioned_numbers,
+        attachments,
+    })
+}
+
+fn should_respond(cfg: &SignalChannelConfig, bot_username: &str, msg: &InboundSignal) -> bool {
+    if msg.group_id.is_none() || !cfg.group_require_mention {
+        return true;
+    }
+    let account = normalize_number(&cfg.account);
+    if msg
+        .mentioned_numbers
+        .iter()
+        .any(|n| normalize_number(n) == account)
+    {
+        return true;
+    }
+    let tag = format!("@{}", bot_username.to_lowercase());
+    !bot_username.is_empty() && msg.text.to_lowercase().contains(&tag)
+}
+
+async fn json_rpc(
+    http_client: &reqwest::Client,
+    cfg: &SignalChannelConfig,
+    method: &str,
+    mut params: Value,
+) -> Result<Value, String> {
+    if let Some(obj) = params.as_object_mut() {
+        obj.insert("account".into(), json!(cfg.account));
+    }
+    let body = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": uuid::Uuid::new_v4().to_string(),
+    });
+    let url = format!("{}/api/v1/rpc", cfg.http_url.trim_end_matches('/'));
+    let resp = http_client
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("signal-cli request failed: {e}"))?;
+    let status = resp.status();
+    let payload: Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("signal-cli returned invalid JSON (HTTP {status}): {e}"))?;
+    if let Some(err) = payload.get("error") {
+        let message = err
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error");
+        return Err(format!("signal-cli {method} failed: {message}"));
+    }
+    Ok(payload.get("result").cloned().unwrap_or(Value::Null))
+}
+
+pub struct SignalAdapter {
+    config: SignalChannelConfig,
+    http_client: reqwest::Client,
+}
+
+impl SignalAdapter {
+    pub fn new(config: SignalChannelConfig) -> Self {
+        SignalAdapter {
+            config,
+            http_client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ChannelAdapter for SignalAdapter {
+    fn name(&self) -> &str {
+        "signal"
+    }
+
+    fn chat_type_routes(&self) -> Vec<(&str, ConversationKind)> {
+        vec![
+            ("signal_dm", ConversationKind::Private),
+            ("signal_group", ConversationKind::Group),
+        ]
+    }
+
+    async fn send_text(&self, external_chat_id: &str, text: &str) -> Result<(), String> {
+        for chunk in split_text(text, SIGNAL_MAX_MESSAGE_LEN) {
+            let mut params = target_params(external_chat_id);
+            params["message"] = json!(chunk);
+            json_rpc(&self.http_client, &self.config, "send", params).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_attachment(
+        &self,
+        external_chat_id: &str,
+        file_path: &Path,
+        caption: Option<&str>,
+    ) -> Result<String, String> {
+        // signal-cli reads the file itself, so the path must be visible to the daemon.
+        let absolute = std::fs::canonicalize(file_path)
+            .map_err(|e| format!("Failed to resolve attachment path: {e}"))?;
+        let mut params = target_params(external_chat_id);
+        params["attachments"] = json!([absolute.to_string_lossy()]);
+        params["message"] = json!(caption.unwrap_or_default());
+        json_rpc(&self.http_client, &self.config, "send", params).await?;
+
+        Ok(match caption {
+            Some(c) => format!("[attachment:{}] {}", file_path.display(), c),
+            None => format!("[attachment:{}]", file_path.display()),
+        })
+    }
+}
+
+/// Start the Signal channel: follow signal-cli's event stream and reconnect on failure.
+pub async fn start_signal_bot(app_state: Arc<AppState>) {
+    let signal_cfg: SignalChannelConfig = match app_state.config.channel_config("signal") {
+        Some(c) => c,
+        None => {
+            error!("Signal channel not configured");
+            return;
+        }
+    };
+    let http_client = reqwest::Client::new();
+
+    loop {
+        info!(
+            "Signal: connecting to signal-cli at {} as {}",
+            signal_cfg.http_url, signal_cfg.account
+        );
+        if let Err(e) = run_event_stream(app_state.clone(), &signal_cfg, &http_client).await {
+            warn!("Signal: event stream error: {e}");
+        }
+        info!("Signal: reconnecting in 5s...");
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    }
+}
+
+async fn run_event_stream(
+    app_state: Arc<AppState>,
+    cfg: &SignalChannelConfig,
+    http_client: &reqwest::Client,
+) -> Result<(), String> {
+    let url = format!("{}/api/v1/events", cfg.http_url.trim_end_matches('/'));
+    let resp = http_client
+        .get(&url)
+        .query(&[("account", cfg.account.as_str())])
+        .header(reqwest::header::ACCEPT, "text/event-stream")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to open event stream: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("Event stream returned HTTP {}", resp.status()));
+    }
+
+    let mut byte_stream = resp.bytes_stream();
+    let mut sse = SseEventParser::default();
+    while let Some(chunk) = byte_stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Event stream read failed: {e}"))?;
+        for data in sse.push_chunk(&String::from_utf8_lossy(&chunk)) {
+            let event: Value = match serde_json::from_str(&data) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Signal: failed to parse event: {e}");
+                    continue;
+                }
+            };
+            let Some(msg) = parse_receive_event(&event) else {
+                continue;
+            };
+            if normalize_number(&msg.sender) == normalize_number(&cfg.account) {
+                continue;
+            }
+            let state = app_state.clone();
+            let cfg = cfg.clone();
+            let http_client = http_client.clone();
+            tokio::spawn(async move {
+                handle_signal_message(state, &cfg, &http_client, msg).await;
+            });
+        }
+    }
+
+    Err("Event stream ended".to_string())
+}
+
+async fn fetch_attachment(
+    http_client: &reqwest::Client,
+    cfg: &SignalChannelConfig,
+    external: &str,
+    attachment_id: &str,
+) -> Result<Vec<u8>, String> {
+    let mut params = target_params(external);
+    params["id"] = json!(attachment_id);
+    let result = json_rpc(http_client, cfg, "getAttachment", params).await?;
+    let encoded = result
+        .get("data")
+        .or(Some(&result))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "getAttachment returned no data".to_string())?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("Invalid attachment data: {e}"))
+}
+
+async fn reply(state: &AppState, external: &str, text: &str) {
+    let Some(adapter) = state.channel_registry.get("signal") else {
+        error!("Signal: adapter not registered");
+        return;
+    };
+    if let Err(e) = adapter.send_text(external, text).await {
+        error!("Signal: failed to send reply: {e}");
+    }
+}
+
+async fn handle_signal_message(
+    app_state: Arc<AppState>,
+    cfg: &SignalChannelConfig,
+    http_client: &reqwest::Client,
+    msg: InboundSignal,
+) {
+    if !number_allowed(&cfg.allowed_numbers, &msg.sender) {
+        info!("Signal: ignoring message from {}", msg.sender);
+        return;
+    }
+    if let Some(group) = msg.group_id.as_deref() {
+        if !group_allowed(&cfg.allowed_groups, group) {
+            return;
+        }
+    }
+
+    let external = external_chat_id(&msg.sender, msg.group_id.as_deref());
+    let chat_type = if msg.group_id.is_some() {
+        "signal_group"
+    } else {
+        "signal_dm"
+    };
+    let chat_id = call_blocking(app_state.db.clone(), {
+        let external = external.clone();
+        let title = format!("signal-{external}");
+        move |db| db.resolve_or_create_chat_id("signal", &external, Some(&title), chat_type)
+    })
+    .await
+    .unwrap_or(0);
+    if chat_id == 0 {
+        error!("Signal: failed to resolve chat ID for {external}");
+        return;
+    }
+
+    let mut content = msg.text.clone();
+    // The first image attachment is also passed to the model.
+    let mut image_data = None;
+    for att in &msg.attachments {
+        let note = match fetch_attachment(http_client, cfg, &external, &att.id).await {
+            Ok(bytes) => {
+                if image_data.is_none() {
+                    image_data = image_input(&bytes);
+                }
+                save_inbound_attachment(
+                    &app_state.config,
+                    "signal",
+                    chat_id,
+                    &att.filename,
+                    &att.content_type,
+                    &bytes,
+                )
+                .await
+            }
+            Err(e) => {
+                warn!("Signal: failed to fetch attachment {}: {e}", att.id);
+                format!(
+                    "[attachment] filename={} download failed: {e}",
+                    att.filename
+                )
+            }
+        };
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&note);
+    }
+    if content.trim().is_empty() {
+        return;
+    }
+
+    let stored = StoredMessage {
+        id: format!("{}-{}", msg.sender, msg.timestamp),
+        chat_id,
+        sender_name: msg.sender_name.clone(),
+        content: content.clone(),
+        is_from_bot: false,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    let _ = call_blocking(app_state.db.clone(), move |db| db.store_message(&stored)).await;
+
+    let command = msg.text.trim();
+    if command == "/stop" {
+        let cancelled = run_control::cancel_chat_runs(chat_id);
+        let lang = i18n::chat_language(&app_state, chat_id).await;
+        reply(
+            &app_state,
+            &external,
+            run_control::stop_command_reply(lang, cancelled),
+        )
+        .await;
+        return;
+    }
+    if command == "/reset" {
+        let _ = call_blocking(app_state.db.clone(), move |db| {
+            db.clear_chat_context(chat_id)
+        })
+        .await;
+        reply(
+            &app_state,
+            &external,
+            "Context cleared (session + chat history).",
+        )
+        .await;
+        return;
+    }
+    if command == "/skills" {
+        let formatted = app_state.skills.list_skills_formatted();
+        reply(&app_state, &external, &formatted).await;
+        return;
+    }
+    if command == "/archive" {
+        let messages: Vec<LlmMessage> =
+            match call_blocking(app_state.db.clone(), move |db| db.load_session(chat_id)).await {
+                Ok(Some((json, _))) => serde_json::from_str(&json).unwrap_or_default(),
+                _ => Vec::new(),
+            };
+        if messages.is_empty() {
+            reply(&app_state, &external, "No session to archive.").await;
+        } else {
+            archive_conversation(&app_state.config.data_dir, "signal", chat_id, &messages);
+            reply(
+                &app_state,
+                &external,
+                &format!("Archived {} messages.", messages.len()),
+            )
+            .await;
+        }
+        return;
+    }
+    if command == "/usage" {
+        match build_usage_report(app_state.db.clone(), &app_state.config, chat_id).await {
+            Ok(report) => {
+                reply(&app_state, &external, &report).await;
+                send_usage_chart(&app_state, "signal", chat_id).await;
+            }
+            Err(e) => {
+                let text = format!("Failed to query usage statistics: {e}");
+                reply(&app_state, &external, &text).await;
+            }
+        }
+        return;
+    }
+
+    if let Some(text) =
+        local_command_reply(&app_state, "signal", chat_id, Some(&msg.sender), command).await
+    {
+        reply(&app_state, &external, &text).await;
+        return;
+    }
+
+    if !should_respond(cfg, &app_state.config.bot_username, &msg) {
+        return;
+    }
+
+    info!(
+        "Signal message from {} in {}: {}",
+        msg.sender,
+        external,
+        content.chars().take(100).collect::<String>()
+    );
+
+    if app_state.config.cancel_on_new_message {
+        run_control::supersede_runs_from(chat_id, &msg.sender).await;
+    }
+
+    match process_with_agent(
+        &app_state,
+        AgentRequestContext {
+            caller_channel: "signal",
+            chat_id,
+            chat_type: if msg.group_id.is_some() {
+                "group"
+            } else {
+                "private"
+            },
+            sender: Some(&msg.sender),
+            sender_id: Some(&msg.sender),
+        },
+        None,
+        image_data,
+    )
+    .await
+    {
+        Ok(response) => {
+            if response.is_empty() {
+                return;
+            }
+            reply(&app_state, &external, &response).await;
+            let bot_msg = StoredMessage {
+                id: uuid::Uuid::new_v4().to_string(),
+                chat_id,
+                sender_name: app_state.config.bot_username.clone(),
+                content: response,
+                is_from_bot: true,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            };
+            let _ = call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
+        }
+        Err(e) => {
+            error!("Error processing Signal message: {e}");
+            let lang = i18n::chat_language(&app_state, chat_id).await;
+            let error = i18n::tf(lang, Msg::Error, &[("error", &e)]);
+            reply(&app_state, &external, &error).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> SignalChannelConfig {
+        serde_yaml::from_str("account: \"+15550000000\"\n").unwrap()
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let cfg = test_config();
+        assert_eq!(cfg.http_url, "http://127.0.0.1:8080");
+        assert!(cfg.group_require_mention);
+        assert!(cfg.allowed_numbers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_direct_message() {
+        let event = json!({
+            "envelope": {
+                "sourceNumber": "+15551112222",
+                "sourceName": "Alice",
+                "timestamp": 1700000000000_i64,
+                "dataMessage": {
+                    "timestamp": 1700000000000_i64,
+                    "message": "hello bot",
+                    "attachments": [
+                        {"id": "abc123", "contentType": "image/png", "filename": "cat.png"}
+                    ]
+                }
+            },
+            "account": "+15550000000"
+        });
+        let msg = parse_receive_event(&event).unwrap();
+        assert_eq!(msg.sender, "+15551112222");
+        assert_eq!(msg.sender_name, "Alice");
+        assert!(msg.group_id.is_none());
+        assert_eq!(msg.text, "hello bot");
+        assert_eq!(msg.attachments.len(), 1);
+        assert_eq!(msg.attachments[0].filename, "cat.png");
+        assert_eq!(external_chat_id(&msg.sender, None), "+15551112222");
+    }
+
+    #[test]
+    fn test_parse_group_message_with_mention_notification() {
+        let event = json!({
+            "jsonrpc": "2.0",
+            "method": "receive",
+            "params": {
+                "envelope": {
+                    "sourceNumber": "+15551112222",
+                    "dataMessage": {
+                        "message": "\u{FFFC} what's the weather?",
+                        "groupInfo": {"groupId": "R3JvdXA=", "type": "DELIVER"},
+                        "mentions": [
+                            {"name": "MicroClaw", "number": "+15550000000", "start": 0, "length": 1}
+                        ]
+                    }
+                }
+            }
+        });
+        let msg = parse_receive_event(&event).unwrap();
+        assert_eq!(msg.group_id.as_deref(), Some("R3JvdXA="));
+        assert_eq!(msg.text, "@MicroClaw what's the weather?");
+        assert_eq!(msg.sender_name, "+15551112222");
+        assert!(should_respond(&test_config(), "microclaw", &msg));
+    }
+
+    #[test]
+    fn test_parse_ignores_non_data_messages() {
+        let receipt = json!({
+            "envelope": {
+                "sourceNumber": "+15551112222",
+                "receiptMessage": {"isDelivery": true, "timestamps": [1]}
+            }
+        });
+        assert!(parse_receive_event(&receipt).is_none());
+    }
+
+    #[test]
+    fn test_group_requires_mention() {
+        let cfg = test_config();
+        let mut msg = InboundSignal {
+            sender: "+15551112222".into(),
+            sender_name: "Alice".into(),
+            group_id: Some("g1".into()),
+            timestamp: 1,
+            text: "just chatting".into(),
+            mentioned_numbers: Vec::new(),
+            attachments: Vec::new(),
+        };
+        assert!(!should_respond(&cfg, "microclaw", &msg));
+        msg.text = "hey @MicroClaw".into();
+        assert!(should_respond(&cfg, "microclaw", &msg));
+        msg.text = "just chatting".into();
+        msg.group_id = None;
+        assert!(should_respond(&cfg, "microclaw", &msg));
+    }
+
+    #[test]
+    fn test_allowlists_and_targets() {
+        let allowed = vec!["+1 (555) 111-2222".to_string()];
+        assert!(number_allowed(&allowed, "+15551112222"));
+        assert!(!number_allowed(&allowed, "+15553334444"));
+        assert!(number_allowed(&[], "+15553334444"));
+        assert!(group_allowed(&[], "g1"));
+        assert!(!group_allowed(&["g2".to_string()], "g1"));
+
+        assert_eq!(target_params("group:g1"), json!({"groupId": "g1"}));
+        assert_eq!(
+            target_params("+15551112222"),
+            json!({"recipient": ["+15551112222"]})
+        );
+    }
+}
@@ -10,14 +10,16 @@ use crate::agent_engine::archive_conversation;
 use crate::agent_engine::process_with_agent_with_events;
 use crate::agent_engine::AgentEvent;
 use crate::agent_engine::AgentRequestContext;
-use crate::channel::ConversationKind;
+use crate::channel::{local_command_reply, ConversationKind};
 use crate::channel_adapter::ChannelAdapter;
 use crate::db::call_blocking;
 use crate::db::StoredMessage;
+use crate::i18n::{self, Msg};
 use crate::llm_types::Message as LlmMessage;
+use crate::run_control;
 use crate::runtime::AppState;
 use crate::text::split_text;
-use crate::usage::build_usage_report;
+use crate::usage::{build_usage_report, send_usage_chart};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct SlackChannelConfig {
@@ -486,6 +488,17 @@ async fn handle_slack_message(
 
     // Handle slash commands
     let trimmed = text.trim();
+    if trimmed == "/stop" {
+        let cancelled = run_control::cancel_chat_runs(chat_id);
+        let lang = i18n::chat_language(&app_state, chat_id).await;
+        let _ = send_slack_response(
+            bot_token,
+            channel,
+            run_control::stop_command_reply(lang, cancelled),
+        )
+        .await;
+        return;
+    }
     if trimmed == "/reset" {
         let _ = call_blocking(app_state.db.clone(), move |db| {
             db.clear_chat_context(chat_id)
@@ -529,6 +542,7 @@ async fn handle_slack_message(
         match build_usage_report(app_state.db.clone(), &app_state.config, chat_id).await {
             Ok(report) => {
                 let _ = send_slack_response(bot_token, channel, &report).await;
+                send_usage_chart(&app_state, "slack", chat_id).await;
             }
             Err(e) => {
                 let _ = send_slack_response(
@@ -542,6 +556,13 @@ async fn handle_slack_message(
         return;
     }
 
+    if let Some(reply) =
+        local_command_reply(&app_state, "slack", chat_id, Some(user), trimmed).await
+    {
+        let _ = send_slack_response(bot_token, channel, &reply).await;
+        return;
+    }
+
     // Determine if we should respond
     let mention_tag = format!("<@{bot_user_id}>");
     let should_respond = is_dm || is_app_mention || text.contains(&mention_tag);
@@ -557,6 +578,10 @@ async fn handle_slack_message(
         text.chars().take(100).collect::<String>()
     );
 
+    if app_state.config.cancel_on_new_message {
+        run_control::supersede_runs_from(chat_id, user).await;
+    }
+
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
 
     match process_with_agent_with_events(
@@ -565,6 +590,8 @@ async fn handle_slack_message(
             caller_channel: "slack",
             chat_id,
             chat_type: if is_dm { "private" } else { "group" },
+            sender: Some(user),
+            sender_id: Some(user),
         },
         None,
         None,
@@ -576,10 +603,13 @@ async fn handle_slack_message(
             drop(event_tx);
             let mut used_send_message_tool = false;
             while let Some(event) = event_rx.recv().await {
-                if let AgentEvent::ToolStart { name } = event {
-                    if name == "send_message" {
+                match event {
+                    AgentEvent::ToolStart { name, .. } if name == "send_message" => {
                         used_send_message_tool = true;
                     }
+                    // Answered by the turn queued ahead of this message.
+                    AgentEvent::Merged => used_send_message_tool = true,
+                    _ => {}
                 }
             }
 
@@ -599,7 +629,8 @@ async fn handle_slack_message(
                 let _ =
                     call_blocking(app_state.db.clone(), move |db| db.store_message(&bot_msg)).await;
             } else if !used_send_message_tool {
-                let fallback = "I couldn't produce a visible reply after an automatic retry. Please try again.";
+                let lang = i18n::chat_language(&app_state, chat_id).await;
+                let fallback = i18n::t(lang, Msg::NoVisibleReply);
                 let _ = send_slack_response(bot_token, channel, fallback).await;
 
                 let bot_msg = StoredMessage {
@@ -616,7 +647,9 @@ async fn handle_slack_message(
         }
         Err(e) => {
             error!("Error processing Slack message: {e}");
-            let _ = send_slack_response(bot_token, channel, &format!("Error: {e}")).await;
+            let lang = i18n::chat_language(&app_state, chat_id).await;
+            let error = i18n::tf(lang, Msg::Error, &[("error", &e)]);
+            let _ = send_slack_response(bot_token, channel, &error).await;
         }
     }
 }
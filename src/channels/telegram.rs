@@ -1,24 +1,40 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use serde::Deserialize;
 use teloxide::prelude::*;
-use teloxide::types::{ChatAction, InputFile, ParseMode};
+use teloxide::types::{
+    ChatAction, ChosenInlineResult, InlineKeyboardButton, InlineKeyboardMarkup, InlineQuery,
+    InlineQueryResult, InlineQueryResultArticle, InputFile, InputMedia, InputMediaDocument,
+    InputMediaPhoto, InputMessageContent, InputMessageContentText, MessageId,
+    MessageReactionUpdated, ParseMode, ThreadId,
+};
 use tracing::{error, info, warn};
 
 use crate::agent_engine::{
     archive_conversation, process_with_agent_with_events, AgentEvent, AgentRequestContext,
 };
-use crate::channel::ConversationKind;
-use crate::channel_adapter::ChannelAdapter;
+use crate::channel::{
+    inbound_file_within_limit, local_command_reply, save_inbound_file, ConversationKind,
+};
+use crate::channel_adapter::{attachments_content, ChannelAdapter};
 use crate::db::{call_blocking, StoredMessage};
+use crate::file_preview;
+use crate::i18n::{self, Msg};
+use crate::inline_mode;
 use crate::llm_types::Message;
 #[cfg(test)]
 use crate::llm_types::{ContentBlock, ImageSource, MessageContent};
+use crate::reactions;
+use crate::run_control;
 use crate::runtime::AppState;
-use crate::text::floor_char_boundary;
-use crate::usage::build_usage_report;
+use crate::streaming::{StreamingDraft, STATUS_REFRESH_INTERVAL};
+use crate::text::{split_markdown, MAX_REPLY_CHUNKS, REPLY_FILE_NAME};
+use crate::usage::{build_usage_report, send_usage_chart};
+
+/// Telegram message length limit.
+const TELEGRAM_MAX_LEN: usize = 4096;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct TelegramChannelConfig {
@@ -29,14 +45,90 @@ pub struct TelegramChannelConfig {
     pub allowed_groups: Vec<i64>,
 }
 
+/// Which bot an update arrived on. The primary bot is channel `telegram`;
+/// extra bots from `telegram_bots` are `telegram:<id>`.
+#[derive(Debug, Clone)]
+pub struct TelegramIdentity {
+    pub channel: String,
+    pub bot_username: String,
+    pub allowed_groups: Vec<i64>,
+}
+
+impl TelegramIdentity {
+    pub fn primary(config: &crate::config::Config) -> Self {
+        TelegramIdentity {
+            channel: "telegram".into(),
+            bot_username: config.bot_username.clone(),
+            allowed_groups: config.allowed_groups.clone(),
+        }
+    }
+
+    pub fn extra(bot: &crate::config::TelegramBotConfig) -> Self {
+        TelegramIdentity {
+            channel: bot.channel_name(),
+            bot_username: bot.bot_username.clone(),
+            allowed_groups: bot.allowed_groups.clone(),
+        }
+    }
+
+    /// DB chat type for a Telegram chat kind (`private`, `group`, ...).
+    fn chat_type(&self, kind: &str) -> String {
+        format!("{}_{kind}", self.channel)
+    }
+}
+
 pub struct TelegramAdapter {
     bot: Bot,
     config: TelegramChannelConfig,
+    name: String,
+    routes: Vec<(String, ConversationKind)>,
 }
 
 impl TelegramAdapter {
     pub fn new(bot: Bot, config: TelegramChannelConfig) -> Self {
-        TelegramAdapter { bot, config }
+        let routes = [
+            ("telegram_private", ConversationKind::Private),
+            ("private", ConversationKind::Private),
+            ("telegram_group", ConversationKind::Group),
+            ("group", ConversationKind::Group),
+            ("supergroup", ConversationKind::Group),
+            ("channel", ConversationKind::Group),
+            ("telegram_supergroup", ConversationKind::Group),
+            ("telegram_channel", ConversationKind::Group),
+        ]
+        .into_iter()
+        .map(|(t, k)| (t.to_string(), k))
+        .collect();
+        TelegramAdapter {
+            bot,
+            config,
+            name: "telegram".into(),
+            routes,
+        }
+    }
+
+    /// Adapter for an extra bot, registered as channel `telegram:<id>`.
+    pub fn for_bot(bot: Bot, bot_config: &crate::config::TelegramBotConfig) -> Self {
+        let identity = TelegramIdentity::extra(bot_config);
+        let routes = [
+            ("private", ConversationKind::Private),
+            ("group", ConversationKind::Group),
+            ("supergroup", ConversationKind::Group),
+            ("channel", ConversationKind::Group),
+        ]
+        .into_iter()
+        .map(|(kind, k)| (identity.chat_type(kind), k))
+        .collect();
+        TelegramAdapter {
+            bot,
+            config: TelegramChannelConfig {
+                bot_token: bot_config.bot_token.clone(),
+                bot_username: bot_config.bot_username.clone(),
+                allowed_groups: bot_config.allowed_groups.clone(),
+            },
+            name: identity.channel,
+            routes,
+        }
     }
 
     pub fn bot(&self) -> &Bot {
@@ -79,27 +171,16 @@ impl TelegramAdapter {
 #[async_trait]
 impl ChannelAdapter for TelegramAdapter {
     fn name(&self) -> &str {
-        "telegram"
+        &self.name
     }
 
     fn chat_type_routes(&self) -> Vec<(&str, ConversationKind)> {
-        vec![
-            ("telegram_private", ConversationKind::Private),
-            ("private", ConversationKind::Private),
-            ("telegram_group", ConversationKind::Group),
-            ("group", ConversationKind::Group),
-            ("supergroup", ConversationKind::Group),
-            ("channel", ConversationKind::Group),
-            ("telegram_supergroup", ConversationKind::Group),
-            ("telegram_channel", ConversationKind::Group),
-        ]
+        self.routes.iter().map(|(t, k)| (t.as_str(), *k)).collect()
     }
 
     async fn send_text(&self, external_chat_id: &str, text: &str) -> Result<(), String> {
-        let telegram_chat_id = external_chat_id
-            .parse::<i64>()
-            .map_err(|_| format!("Invalid Telegram external_chat_id '{}'", external_chat_id))?;
-        send_response(&self.bot, ChatId(telegram_chat_id), text).await;
+        let (chat, thread) = parse_external_chat_id(external_chat_id)?;
+        send_response(&self.bot, chat, thread, text).await;
         Ok(())
     }
 
@@ -109,25 +190,25 @@ impl ChannelAdapter for TelegramAdapter {
         file_path: &Path,
         caption: Option<&str>,
     ) -> Result<String, String> {
-        let telegram_chat_id = external_chat_id
-            .parse::<i64>()
-            .map_err(|_| format!("Invalid Telegram external_chat_id '{}'", external_chat_id))?;
+        let (chat, thread) = parse_external_chat_id(external_chat_id)?;
 
         let (caption_for_attachment, overflow_text) = Self::split_telegram_caption(caption);
 
         if Self::is_likely_image(file_path) {
-            let mut req = self
-                .bot
-                .send_photo(ChatId(telegram_chat_id), InputFile::file(file_path));
+            let mut req = self.bot.send_photo(chat, InputFile::file(file_path));
+            if let Some(thread) = thread {
+                req = req.message_thread_id(thread);
+            }
             if let Some(c) = &caption_for_attachment {
                 req = req.caption(c.clone());
             }
             req.await
                 .map_err(|e| format!("Failed to send Telegram photo: {e}"))?;
         } else {
-            let mut req = self
-                .bot
-                .send_document(ChatId(telegram_chat_id), InputFile::file(file_path));
+            let mut req = self.bot.send_document(chat, InputFile::file(file_path));
+            if let Some(thread) = thread {
+                req = req.message_thread_id(thread);
+            }
             if let Some(c) = &caption_for_attachment {
                 req = req.caption(c.clone());
             }
@@ -136,7 +217,7 @@ impl ChannelAdapter for TelegramAdapter {
         }
 
         if let Some(extra) = overflow_text {
-            send_response(&self.bot, ChatId(telegram_chat_id), &extra).await;
+            send_response(&self.bot, chat, thread, &extra).await;
         }
 
         Ok(match caption {
@@ -144,8 +225,129 @@ impl ChannelAdapter for TelegramAdapter {
             None => format!("[attachment:{}]", file_path.display()),
         })
     }
+
+    /// Photos go out as one album and other files as another, since Telegram
+    /// media groups can't mix the two. The caption goes on the first album.
+    async fn send_attachments(
+        &self,
+        external_chat_id: &str,
+        file_paths: &[PathBuf],
+        caption: Option<&str>,
+    ) -> Result<String, String> {
+        let (chat, thread) = parse_external_chat_id(external_chat_id)?;
+        let (mut album_caption, overflow_text) = Self::split_telegram_caption(caption);
+
+        let (photos, documents): (Vec<&PathBuf>, Vec<&PathBuf>) =
+            file_paths.iter().partition(|p| Self::is_likely_image(p));
+        for (paths, is_photo) in [(photos, true), (documents, false)] {
+            if paths.is_empty() {
+                continue;
+            }
+            let caption = album_caption.take();
+            if paths.len() == 1 {
+                self.send_attachment(external_chat_id, paths[0], caption.as_deref())
+                    .await?;
+                continue;
+            }
+            let media: Vec<InputMedia> = paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| {
+                    let file = InputFile::file(path.as_path());
+                    let caption = caption.clone().filter(|_| i == 0);
+                    if is_photo {
+                        let mut photo = InputMediaPhoto::new(file);
+                        if let Some(c) = caption {
+                            photo = photo.caption(c);
+                        }
+                        InputMedia::Photo(photo)
+                    } else {
+                        let mut document = InputMediaDocument::new(file);
+                        if let Some(c) = caption {
+                            document = document.caption(c);
+                        }
+                        InputMedia::Document(document)
+                    }
+                })
+                .collect();
+            let mut req = self.bot.send_media_group(chat, media);
+            if let Some(thread) = thread {
+                req = req.message_thread_id(thread);
+            }
+            req.await
+                .map_err(|e| format!("Failed to send Telegram media group: {e}"))?;
+        }
+
+        if let Some(extra) = overflow_text {
+            send_response(&self.bot, chat, thread, &extra).await;
+        }
+        Ok(attachments_content(file_paths, caption))
+    }
+
+    async fn send_file_preview(
+        &self,
+        external_chat_id: &str,
+        card: &str,
+        _display_path: &str,
+        file_path: &Path,
+    ) -> Result<(), String> {
+        let (chat, thread) = parse_external_chat_id(external_chat_id)?;
+        let token = file_preview::register_file_token(external_chat_id, file_path);
+        let keyboard = InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+            "Full file",
+            format!("{FILE_CALLBACK_PREFIX}{token}"),
+        )]]);
+        let mut req = self
+            .bot
+            .send_message(chat, render_markdown_v2_safe(card))
+            .parse_mode(ParseMode::MarkdownV2)
+            .reply_markup(keyboard.clone());
+        if let Some(thread) = thread {
+            req = req.message_thread_id(thread);
+        }
+        if req.await.is_err() {
+            send_plain(&self.bot, chat, thread, card)
+                .await
+                .map_err(|e| format!("Failed to send Telegram file preview: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// External chat id of a Telegram conversation. Forum topics are separate
+/// conversations keyed `<chat_id>:<message_thread_id>`.
+pub fn topic_external_chat_id(chat_id: i64, thread_id: Option<i32>) -> String {
+    match thread_id {
+        Some(thread) => format!("{chat_id}:{thread}"),
+        None => chat_id.to_string(),
+    }
+}
+
+/// Parse an external chat id written by [`topic_external_chat_id`].
+pub fn parse_external_chat_id(
+    external_chat_id: &str,
+) -> Result<(ChatId, Option<ThreadId>), String> {
+    let invalid = || format!("Invalid Telegram external_chat_id '{external_chat_id}'");
+    let (chat, thread) = match external_chat_id.split_once(':') {
+        Some((chat, thread)) => (chat, Some(thread.parse::<i32>().map_err(|_| invalid())?)),
+        None => (external_chat_id, None),
+    };
+    let chat = chat.parse::<i64>().map_err(|_| invalid())?;
+    Ok((ChatId(chat), thread.map(|t| ThreadId(MessageId(t)))))
+}
+
+/// Forum topic of a message. Reply threads outside forums are not topics.
+fn message_thread(msg: &teloxide::types::Message) -> Option<ThreadId> {
+    if msg.is_topic_message {
+        msg.thread_id
+    } else {
+        None
+    }
 }
 
+/// Callback data prefix of the "Full file" button on preview cards.
+const FILE_CALLBACK_PREFIX: &str = "file:";
+
 /// Escape XML special characters in user-supplied content to prevent prompt injection.
 /// User messages are wrapped in XML tags; escaping ensures the content cannot break out.
 fn sanitize_xml(s: &str) -> String {
@@ -165,55 +367,357 @@ fn format_user_message(sender_name: &str, content: &str) -> String {
     )
 }
 
-pub async fn start_telegram_bot(state: Arc<AppState>, bot: Bot) -> anyhow::Result<()> {
-    let handler = Update::filter_message().endpoint(handle_message);
+pub async fn start_telegram_bot(
+    state: Arc<AppState>,
+    bot: Bot,
+    identity: TelegramIdentity,
+) -> anyhow::Result<()> {
+    let handler = dptree::entry()
+        // Every update proves polling works (see `health`).
+        .inspect(|identity: Arc<TelegramIdentity>| crate::health::beat(&identity.channel))
+        .branch(Update::filter_message().endpoint(handle_message))
+        .branch(Update::filter_callback_query().endpoint(handle_callback_query))
+        .branch(Update::filter_inline_query().endpoint(handle_inline_query))
+        .branch(Update::filter_chosen_inline_result().endpoint(handle_chosen_inline_result))
+        .branch(Update::filter_message_reaction_updated().endpoint(handle_reaction));
+
+    let listener = teloxide::update_listeners::polling_default(bot.clone()).await;
+    let listener_channel = identity.channel.clone();
+    let listener_errors = Arc::new(move |e: teloxide::RequestError| {
+        let channel = listener_channel.clone();
+        async move {
+            error!("Telegram {channel}: polling failed: {e}");
+            crate::health::fail(&channel, e.to_string());
+        }
+    });
 
     Dispatcher::builder(bot, handler)
         .default_handler(|_| async {})
-        .dependencies(dptree::deps![state])
+        .dependencies(dptree::deps![state, Arc::new(identity)])
+        // Updates are handled sequentially per chat; /stop and reactions must
+        // bypass that queue so they can interrupt the run they would
+        // otherwise wait behind.
+        .distribution_function(|upd: &Update| {
+            let is_stop = match &upd.kind {
+                teloxide::types::UpdateKind::Message(m) => m.text().map(str::trim) == Some("/stop"),
+                teloxide::types::UpdateKind::MessageReaction(_) => true,
+                _ => false,
+            };
+            if is_stop {
+                return None;
+            }
+            // Forum topics are independent conversations and run in parallel.
+            let thread = match &upd.kind {
+                teloxide::types::UpdateKind::Message(m) => message_thread(m),
+                _ => None,
+            };
+            upd.chat().map(|c| (c.id, thread))
+        })
         .enable_ctrlc_handler()
         .build()
-        .dispatch()
+        .dispatch_with_listener(listener, listener_errors)
         .await;
 
     Ok(())
 }
-async fn handle_message(
+fn ask_again_keyboard(query: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::switch_inline_query_current_chat("Ask again", query),
+    ]])
+}
+
+/// Inline queries fire on every keystroke, so answer right away with a
+/// placeholder; the agent only runs once the user picks it (see
+/// `handle_chosen_inline_result`).
+async fn handle_inline_query(
     bot: Bot,
-    msg: teloxide::types::Message,
     state: Arc<AppState>,
+    q: InlineQuery,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let raw_chat_id = msg.chat.id.0;
-    let (runtime_chat_type, db_chat_type) = match msg.chat.kind {
-        teloxide::types::ChatKind::Private(_) => ("private", "telegram_private"),
+    let query = q.query.trim();
+    let allowed = state.config.telegram_inline_mode
+        && inline_mode::user_allowed(&state.config.telegram_inline_allowed_users, q.from.id.0);
+    let results = if query.is_empty() || !allowed {
+        Vec::new()
+    } else {
+        let preview: String = query.chars().take(64).collect();
+        // The keyboard makes Telegram report an inline_message_id we can edit.
+        let article = InlineQueryResultArticle::new(
+            "ask",
+            format!("Ask: {preview}"),
+            InputMessageContent::Text(InputMessageContentText::new(format!("⏳ {query}"))),
+        )
+        .description("Answer with web search and page fetch")
+        .reply_markup(ask_again_keyboard(query));
+        vec![InlineQueryResult::Article(article)]
+    };
+    bot.answer_inline_query(q.id, results)
+        .cache_time(0)
+        .is_personal(true)
+        .await?;
+    Ok(())
+}
+
+/// Run the restricted inline agent and replace the placeholder with its answer.
+async fn handle_chosen_inline_result(
+    bot: Bot,
+    state: Arc<AppState>,
+    identity: Arc<TelegramIdentity>,
+    r: ChosenInlineResult,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(inline_message_id) = r.inline_message_id else {
+        return Ok(());
+    };
+    let query = r.query.trim();
+    if !state.config.telegram_inline_mode
+        || query.is_empty()
+        || !inline_mode::user_allowed(&state.config.telegram_inline_allowed_users, r.from.id.0)
+    {
+        return Ok(());
+    }
+    let sender_name = r
+        .from
+        .username
+        .clone()
+        .unwrap_or_else(|| r.from.first_name.clone());
+    let external_chat_id = format!("inline:{}", r.from.id.0);
+    let title = format!("inline / {sender_name}");
+    let channel = identity.channel.clone();
+    let chat_type = identity.chat_type("inline");
+    let chat_id = call_blocking(state.db.clone(), move |db| {
+        db.resolve_or_create_chat_id(&channel, &external_chat_id, Some(&title), &chat_type)
+    })
+    .await
+    .unwrap_or(0);
+
+    let sender_id = r.from.id.0.to_string();
+    let answer = match inline_mode::answer_inline_query(
+        &state,
+        &identity.channel,
+        chat_id,
+        &sender_name,
+        &sender_id,
+        query,
+    )
+    .await
+    {
+        Ok(answer) => answer,
+        Err(e) => {
+            warn!("Telegram inline query failed: {e}");
+            format!("Couldn't answer \"{query}\": {e}")
+        }
+    };
+    bot.edit_message_text_inline(inline_message_id, inline_mode::clip_answer(&answer))
+        .reply_markup(ask_again_keyboard(query))
+        .await?;
+    Ok(())
+}
+
+/// "Full file" button on a preview card: send the file as a document.
+async fn handle_callback_query(
+    bot: Bot,
+    q: CallbackQuery,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let token = q
+        .data
+        .as_deref()
+        .and_then(|d| d.strip_prefix(FILE_CALLBACK_PREFIX));
+    let chat_id = q.message.as_ref().map(|m| m.chat().id);
+    let thread = q
+        .message
+        .as_ref()
+        .and_then(|m| m.regular_message())
+        .and_then(message_thread);
+    let (Some(token), Some(chat_id)) = (token, chat_id) else {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    };
+    let external_chat_id = topic_external_chat_id(chat_id.0, thread.map(|t| t.0 .0));
+    match file_preview::lookup_file_token(token, &external_chat_id) {
+        Some(path) if path.is_file() => {
+            bot.answer_callback_query(q.id).await?;
+            let mut req = bot.send_document(chat_id, InputFile::file(&path));
+            if let Some(thread) = thread {
+                req = req.message_thread_id(thread);
+            }
+            if let Err(e) = req.await {
+                warn!("Telegram: failed to send {}: {e}", path.display());
+            }
+        }
+        _ => {
+            bot.answer_callback_query(q.id)
+                .text("This file is no longer available.")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Runtime chat type (`private`/`group`) and Telegram chat kind.
+fn chat_kinds(chat: &teloxide::types::Chat) -> (&'static str, &'static str) {
+    match chat.kind {
+        teloxide::types::ChatKind::Private(_) => ("private", "private"),
         teloxide::types::ChatKind::Public(teloxide::types::ChatPublic {
             kind: teloxide::types::PublicChatKind::Group,
             ..
-        }) => ("group", "telegram_group"),
+        }) => ("group", "group"),
         teloxide::types::ChatKind::Public(teloxide::types::ChatPublic {
             kind: teloxide::types::PublicChatKind::Supergroup(_),
             ..
-        }) => ("group", "telegram_supergroup"),
+        }) => ("group", "supergroup"),
         teloxide::types::ChatKind::Public(teloxide::types::ChatPublic {
             kind: teloxide::types::PublicChatKind::Channel(_),
             ..
-        }) => ("group", "telegram_channel"),
+        }) => ("group", "channel"),
+    }
+}
+
+/// Reaction controls (see `crate::reactions`). Reactions carry no topic, so
+/// they are ignored in forum supergroups.
+async fn handle_reaction(
+    bot: Bot,
+    reaction: MessageReactionUpdated,
+    state: Arc<AppState>,
+    identity: Arc<TelegramIdentity>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let added = reaction
+        .new_reaction
+        .iter()
+        .filter(|r| !reaction.old_reaction.contains(r))
+        .filter_map(|r| r.emoji())
+        .find_map(|emoji| reactions::reaction_action(emoji));
+    let (Some(action), Some(user)) = (added, reaction.user()) else {
+        return Ok(());
+    };
+    if user.is_bot {
+        return Ok(());
+    }
+    let raw_chat_id = reaction.chat.id.0;
+    let (runtime_chat_type, chat_kind) = chat_kinds(&reaction.chat);
+    let is_forum = matches!(
+        &reaction.chat.kind,
+        teloxide::types::ChatKind::Public(teloxide::types::ChatPublic {
+            kind: teloxide::types::PublicChatKind::Supergroup(sg),
+            ..
+        }) if sg.is_forum
+    );
+    if is_forum
+        || (matches!(chat_kind, "group" | "supergroup")
+            && !identity.allowed_groups.is_empty()
+            && !identity.allowed_groups.contains(&raw_chat_id))
+    {
+        return Ok(());
+    }
+
+    let external_chat_id = topic_external_chat_id(raw_chat_id, None);
+    let chat_title = reaction.chat.title().map(|t| t.to_string());
+    let chat_type = identity.chat_type(chat_kind);
+    let channel = identity.channel.clone();
+    let Ok(chat_id) = call_blocking(state.db.clone(), move |db| {
+        db.resolve_or_create_chat_id(
+            &channel,
+            &external_chat_id,
+            chat_title.as_deref(),
+            &chat_type,
+        )
+    })
+    .await
+    else {
+        return Ok(());
+    };
+
+    let sender_name = user
+        .username
+        .clone()
+        .unwrap_or_else(|| user.first_name.clone());
+    info!("Reaction {action:?} from {sender_name} in chat {chat_id}");
+    match reactions::handle_reaction(&state, &identity.channel, chat_id, &sender_name, action).await
+    {
+        reactions::ReactionOutcome::Ignore => {}
+        reactions::ReactionOutcome::Reply(text) => {
+            let _ = send_plain(&bot, reaction.chat.id, None, text).await;
+        }
+        reactions::ReactionOutcome::RunTurn => {
+            run_agent_and_reply(
+                &bot,
+                &state,
+                &identity,
+                reaction.chat.id,
+                None,
+                chat_id,
+                runtime_chat_type,
+                None,
+                Some(&user.id.0.to_string()),
+                None,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_message(
+    bot: Bot,
+    msg: teloxide::types::Message,
+    state: Arc<AppState>,
+    identity: Arc<TelegramIdentity>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let raw_chat_id = msg.chat.id.0;
+    let (runtime_chat_type, chat_kind) = chat_kinds(&msg.chat);
+    let db_chat_type = identity.chat_type(chat_kind);
+    // Each forum topic gets its own chat (history, session, workspace).
+    let thread = message_thread(&msg);
+    let chat_key = topic_external_chat_id(raw_chat_id, thread.map(|t| t.0 .0));
+    let chat_title = match thread {
+        Some(_) => msg
+            .reply_to_message()
+            .and_then(|m| m.forum_topic_created())
+            .map(|topic| format!("{} / {}", msg.chat.title().unwrap_or("topic"), topic.name)),
+        None => msg.chat.title().map(|t| t.to_string()),
     };
-    let chat_title = msg.chat.title().map(|t| t.to_string());
 
     // Extract content: text, photo, or voice
     let mut text = msg.text().unwrap_or("").to_string();
     let mut image_data: Option<(String, String)> = None; // (base64, media_type)
     let mut document_saved_path: Option<String> = None;
 
+    // Handle /stop command — cancel the in-flight run for this chat
+    if text.trim() == "/stop" {
+        let external_chat_id = chat_key.clone();
+        let chat_title_for_lookup = chat_title.clone();
+        let chat_type_for_lookup = db_chat_type.to_string();
+        let channel_for_lookup = identity.channel.clone();
+        let chat_id = call_blocking(state.db.clone(), move |db| {
+            db.resolve_or_create_chat_id(
+                &channel_for_lookup,
+                &external_chat_id,
+                chat_title_for_lookup.as_deref(),
+                &chat_type_for_lookup,
+            )
+        })
+        .await
+        .unwrap_or(raw_chat_id);
+        let cancelled = run_control::cancel_chat_runs(chat_id);
+        let lang = i18n::chat_language(&state, chat_id).await;
+        let _ = send_plain(
+            &bot,
+            msg.chat.id,
+            thread,
+            run_control::stop_command_reply(lang, cancelled),
+        )
+        .await;
+        return Ok(());
+    }
+
     // Handle /reset command — clear session
     if text.trim() == "/reset" {
-        let external_chat_id = raw_chat_id.to_string();
+        let external_chat_id = chat_key.clone();
         let chat_title_for_lookup = chat_title.clone();
         let chat_type_for_lookup = db_chat_type.to_string();
+        let channel_for_lookup = identity.channel.clone();
         let chat_id = call_blocking(state.db.clone(), move |db| {
             db.resolve_or_create_chat_id(
-                "telegram",
+                &channel_for_lookup,
                 &external_chat_id,
                 chat_title_for_lookup.as_deref(),
                 &chat_type_for_lookup,
@@ -222,27 +726,32 @@ async fn handle_message(
         .await
         .unwrap_or(raw_chat_id);
         let _ = call_blocking(state.db.clone(), move |db| db.clear_chat_context(chat_id)).await;
-        let _ = bot
-            .send_message(msg.chat.id, "Context cleared (session + chat history).")
-            .await;
+        let _ = send_plain(
+            &bot,
+            msg.chat.id,
+            thread,
+            "Context cleared (session + chat history).",
+        )
+        .await;
         return Ok(());
     }
 
     // Handle /skills command — list available skills
     if text.trim() == "/skills" {
         let formatted = state.skills.list_skills_formatted();
-        let _ = bot.send_message(msg.chat.id, formatted).await;
+        let _ = send_plain(&bot, msg.chat.id, thread, formatted).await;
         return Ok(());
     }
 
     // Handle /archive command — archive current session to markdown
     if text.trim() == "/archive" {
-        let external_chat_id = raw_chat_id.to_string();
+        let external_chat_id = chat_key.clone();
         let chat_title_for_lookup = chat_title.clone();
         let chat_type_for_lookup = db_chat_type.to_string();
+        let channel_for_lookup = identity.channel.clone();
         let chat_id = call_blocking(state.db.clone(), move |db| {
             db.resolve_or_create_chat_id(
-                "telegram",
+                &channel_for_lookup,
                 &external_chat_id,
                 chat_title_for_lookup.as_deref(),
                 &chat_type_for_lookup,
@@ -255,34 +764,37 @@ async fn handle_message(
         {
             let messages: Vec<Message> = serde_json::from_str(&json).unwrap_or_default();
             if messages.is_empty() {
-                let _ = bot
-                    .send_message(msg.chat.id, "No session to archive.")
-                    .await;
+                let _ = send_plain(&bot, msg.chat.id, thread, "No session to archive.").await;
             } else {
-                archive_conversation(&state.config.data_dir, "telegram", chat_id, &messages);
-                let _ = bot
-                    .send_message(
-                        msg.chat.id,
-                        format!("Archived {} messages.", messages.len()),
-                    )
-                    .await;
+                archive_conversation(
+                    &state.config.data_dir,
+                    &identity.channel,
+                    chat_id,
+                    &messages,
+                );
+                let _ = send_plain(
+                    &bot,
+                    msg.chat.id,
+                    thread,
+                    format!("Archived {} messages.", messages.len()),
+                )
+                .await;
             }
         } else {
-            let _ = bot
-                .send_message(msg.chat.id, "No session to archive.")
-                .await;
+            let _ = send_plain(&bot, msg.chat.id, thread, "No session to archive.").await;
         }
         return Ok(());
     }
 
     // Handle /usage command — token usage summary
     if text.trim() == "/usage" {
-        let external_chat_id = raw_chat_id.to_string();
+        let external_chat_id = chat_key.clone();
         let chat_title_for_lookup = chat_title.clone();
         let chat_type_for_lookup = db_chat_type.to_string();
+        let channel_for_lookup = identity.channel.clone();
         let chat_id = call_blocking(state.db.clone(), move |db| {
             db.resolve_or_create_chat_id(
-                "telegram",
+                &channel_for_lookup,
                 &external_chat_id,
                 chat_title_for_lookup.as_deref(),
                 &chat_type_for_lookup,
@@ -292,27 +804,102 @@ async fn handle_message(
         .unwrap_or(raw_chat_id);
         match build_usage_report(state.db.clone(), &state.config, chat_id).await {
             Ok(response) => {
-                let _ = bot.send_message(msg.chat.id, response).await;
+                let _ = send_plain(&bot, msg.chat.id, thread, response).await;
+                send_usage_chart(&state, &identity.channel, chat_id).await;
             }
             Err(e) => {
-                let _ = bot
-                    .send_message(
-                        msg.chat.id,
-                        format!("Failed to query usage statistics: {e}"),
-                    )
-                    .await;
+                let _ = send_plain(
+                    &bot,
+                    msg.chat.id,
+                    thread,
+                    format!("Failed to query usage statistics: {e}"),
+                )
+                .await;
             }
         }
         return Ok(());
     }
 
+    // Per-chat commands shared with the other channels (/compare, /persona, /fork, ...)
+    if text.trim_start().starts_with('/') {
+        let external_chat_id = chat_key.clone();
+        let chat_title_for_lookup = chat_title.clone();
+        let chat_type_for_lookup = db_chat_type.to_string();
+        let channel_for_lookup = identity.channel.clone();
+        let chat_id = call_blocking(state.db.clone(), move |db| {
+            db.resolve_or_create_chat_id(
+                &channel_for_lookup,
+                &external_chat_id,
+                chat_title_for_lookup.as_deref(),
+                &chat_type_for_lookup,
+            )
+        })
+        .await
+        .unwrap_or(raw_chat_id);
+        let from_id = msg.from.as_ref().map(|u| u.id.0.to_string());
+        if let Some(reply) = local_command_reply(
+            &state,
+            &identity.channel,
+            chat_id,
+            from_id.as_deref(),
+            &text,
+        )
+        .await
+        {
+            send_response(&bot, msg.chat.id, thread, &reply).await;
+            return Ok(());
+        }
+    }
+
+    // Uploads are saved into the chat workspace, which needs the internal chat
+    // id; so do replies about them in the chat's language.
+    let upload_chat_id =
+        if msg.photo().is_some() || msg.document().is_some() || msg.voice().is_some() {
+            let external_chat_id = chat_key.clone();
+            let chat_title_for_lookup = chat_title.clone();
+            let chat_type_for_lookup = db_chat_type.to_string();
+            let channel_for_lookup = identity.channel.clone();
+            call_blocking(state.db.clone(), move |db| {
+                db.resolve_or_create_chat_id(
+                    &channel_for_lookup,
+                    &external_chat_id,
+                    chat_title_for_lookup.as_deref(),
+                    &chat_type_for_lookup,
+                )
+            })
+            .await
+            .unwrap_or(raw_chat_id)
+        } else {
+            raw_chat_id
+        };
+
+    let mut photo_note: Option<String> = None;
     if let Some(photos) = msg.photo() {
         // Pick the largest photo (last in the array)
         if let Some(photo) = photos.last() {
             match download_telegram_file(&bot, &photo.file.id.0).await {
                 Ok(bytes) => {
-                    let base64 = base64_encode(&bytes);
                     let media_type = guess_image_media_type(&bytes);
+                    if inbound_file_within_limit(&state.config, bytes.len() as u64) {
+                        let name = format!("photo.{}", image_extension(&media_type));
+                        if let Ok(path) = save_inbound_file(
+                            &state.config,
+                            &identity.channel,
+                            upload_chat_id,
+                            &name,
+                            &bytes,
+                        )
+                        .await
+                        {
+                            photo_note = Some(format!(
+                                "[photo] bytes={} mime={} saved_path={}",
+                                bytes.len(),
+                                media_type,
+                                path.display()
+                            ));
+                        }
+                    }
+                    let base64 = base64_encode(&bytes);
                     image_data = Some((base64, media_type));
                 }
                 Err(e) => {
@@ -324,26 +911,33 @@ async fn handle_message(
         if text.is_empty() {
             text = msg.caption().unwrap_or("").to_string();
         }
+        if let Some(note) = photo_note {
+            text = if text.trim().is_empty() {
+                note
+            } else {
+                format!("{}\n\n{note}", text.trim())
+            };
+        }
     }
 
     // Handle document messages (text/code/file attachments)
     if let Some(document) = msg.document() {
-        let max_bytes = state
-            .config
-            .max_document_size_mb
-            .saturating_mul(1024)
-            .saturating_mul(1024);
         let doc_bytes = u64::from(document.file.size);
-        if doc_bytes > max_bytes {
-            let _ = bot
-                .send_message(
-                    msg.chat.id,
-                    format!(
-                        "Document is too large ({} bytes). Max allowed is {} MB.",
-                        doc_bytes, state.config.max_document_size_mb
-                    ),
-                )
-                .await;
+        if !inbound_file_within_limit(&state.config, doc_bytes) {
+            let _ = send_plain(
+                &bot,
+                msg.chat.id,
+                thread,
+                i18n::tf(
+                    i18n::chat_language(&state, upload_chat_id).await,
+                    Msg::DocumentTooLarge,
+                    &[
+                        ("bytes", &doc_bytes),
+                        ("max_mb", &state.config.max_document_size_mb),
+                    ],
+                ),
+            )
+            .await;
             return Ok(());
         }
 
@@ -353,31 +947,16 @@ async fn handle_message(
                     .file_name
                     .as_deref()
                     .unwrap_or("telegram-document.bin");
-                let safe_name = original_name
-                    .chars()
-                    .map(|c| match c {
-                        'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
-                        _ => '_',
-                    })
-                    .collect::<String>();
-
-                let dir = Path::new(&state.config.working_dir)
-                    .join("uploads")
-                    .join("telegram")
-                    .join(raw_chat_id.to_string());
-                if let Err(e) = std::fs::create_dir_all(&dir) {
-                    error!("Failed to create upload dir {}: {e}", dir.display());
-                } else {
-                    let ts = chrono::Utc::now().format("%Y%m%d-%H%M%S");
-                    let path = dir.join(format!("{}-{}", ts, safe_name));
-                    match tokio::fs::write(&path, &bytes).await {
-                        Ok(()) => {
-                            document_saved_path = Some(path.display().to_string());
-                        }
-                        Err(e) => {
-                            error!("Failed to save telegram document {}: {e}", path.display());
-                        }
-                    }
+                if let Ok(path) = save_inbound_file(
+                    &state.config,
+                    &identity.channel,
+                    upload_chat_id,
+                    original_name,
+                    &bytes,
+                )
+                .await
+                {
+                    document_saved_path = Some(path.display().to_string());
                 }
 
                 let file_note = format!(
@@ -400,6 +979,10 @@ async fn handle_message(
                 } else {
                     text = format!("{}\n\n{}", text.trim(), file_note);
                 }
+                // Images sent as files (uncompressed) reach the model too.
+                if image_data.is_none() {
+                    image_data = crate::channel::image_input(&bytes);
+                }
             }
             Err(e) => {
                 error!("Failed to download document: {e}");
@@ -414,45 +997,47 @@ async fn handle_message(
         }
     }
 
-    // Handle voice messages
+    // Handle voice messages: transcribe and feed the text in with a [voice] marker
     if let Some(voice) = msg.voice() {
-        if let Some(ref openai_key) = state.config.openai_api_key {
-            match download_telegram_file(&bot, &voice.file.id.0).await {
-                Ok(bytes) => {
-                    let sender_name = msg
-                        .from
-                        .as_ref()
-                        .map(|u| u.username.clone().unwrap_or_else(|| u.first_name.clone()))
-                        .unwrap_or_else(|| "Unknown".into());
-                    match crate::transcribe::transcribe_audio(openai_key, &bytes).await {
-                        Ok(transcription) => {
-                            text = format!(
-                                "[voice message from {}]: {}",
-                                sanitize_xml(&sender_name),
-                                sanitize_xml(&transcription)
-                            );
-                        }
-                        Err(e) => {
-                            error!("Whisper transcription failed: {e}");
-                            text = format!(
-                                "[voice message from {}]: [transcription failed: {e}]",
-                                sanitize_xml(&sender_name)
-                            );
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to download voice message: {e}");
+        let Some(transcriber) = crate::transcribe::Transcriber::from_config(&state.config) else {
+            let _ = send_plain(
+                &bot,
+                msg.chat.id,
+                thread,
+                i18n::t(
+                    i18n::chat_language(&state, upload_chat_id).await,
+                    Msg::VoiceNotConfigured,
+                ),
+            )
+            .await;
+            return Ok(());
+        };
+        let transcription = match download_telegram_file(&bot, &voice.file.id.0).await {
+            Ok(bytes) => transcriber.transcribe(&bytes).await,
+            Err(e) => Err(format!("download failed: {e}")),
+        };
+        match transcription {
+            Ok(transcription) => {
+                text = format!("[voice] {}", sanitize_xml(&transcription));
+                if let Some(caption) = msg.caption().filter(|c| !c.trim().is_empty()) {
+                    text.push_str(&format!("\n{caption}"));
                 }
             }
-        } else {
-            let _ = bot
-                .send_message(
+            Err(e) => {
+                error!("Voice transcription failed: {e}");
+                let _ = send_plain(
+                    &bot,
                     msg.chat.id,
-                    "Voice messages not supported (no Whisper API key configured)",
+                    thread,
+                    i18n::tf(
+                        i18n::chat_language(&state, upload_chat_id).await,
+                        Msg::VoiceFailed,
+                        &[("error", &e)],
+                    ),
                 )
                 .await;
-            return Ok(());
+                return Ok(());
+            }
         }
     }
 
@@ -465,18 +1050,20 @@ async fn handle_message(
         .as_ref()
         .map(|u| u.username.clone().unwrap_or_else(|| u.first_name.clone()))
         .unwrap_or_else(|| "Unknown".into());
+    let sender_id = msg.from.as_ref().map(|u| u.id.0.to_string());
 
     // Check group allowlist
-    if (db_chat_type == "telegram_group" || db_chat_type == "telegram_supergroup")
-        && !state.config.allowed_groups.is_empty()
-        && !state.config.allowed_groups.contains(&raw_chat_id)
+    if matches!(chat_kind, "group" | "supergroup")
+        && !identity.allowed_groups.is_empty()
+        && !identity.allowed_groups.contains(&raw_chat_id)
     {
-        let external_chat_id = raw_chat_id.to_string();
+        let external_chat_id = chat_key.clone();
         let chat_title_for_lookup = chat_title.clone();
         let chat_type_for_lookup = db_chat_type.to_string();
+        let channel_for_lookup = identity.channel.clone();
         let chat_id = call_blocking(state.db.clone(), move |db| {
             db.resolve_or_create_chat_id(
-                "telegram",
+                &channel_for_lookup,
                 &external_chat_id,
                 chat_title_for_lookup.as_deref(),
                 &chat_type_for_lookup,
@@ -521,12 +1108,13 @@ async fn handle_message(
         return Ok(());
     }
 
-    let external_chat_id = raw_chat_id.to_string();
+    let external_chat_id = chat_key.clone();
     let chat_title_for_lookup = chat_title.clone();
     let chat_type_for_lookup = db_chat_type.to_string();
+    let channel_for_lookup = identity.channel.clone();
     let chat_id = call_blocking(state.db.clone(), move |db| {
         db.resolve_or_create_chat_id(
-            "telegram",
+            &channel_for_lookup,
             &external_chat_id,
             chat_title_for_lookup.as_deref(),
             &chat_type_for_lookup,
@@ -575,7 +1163,7 @@ async fn handle_message(
     let should_respond = match runtime_chat_type {
         "private" => true,
         _ => {
-            let bot_mention = format!("@{}", state.config.bot_username);
+            let bot_mention = format!("@{}", identity.bot_username);
             text.contains(&bot_mention)
         }
     };
@@ -591,53 +1179,104 @@ async fn handle_message(
         text.chars().take(100).collect::<String>()
     );
 
+    if state.config.cancel_on_new_message {
+        run_control::supersede_runs_from(chat_id, &sender_name).await;
+    }
+
+    run_agent_and_reply(
+        &bot,
+        &state,
+        &identity,
+        msg.chat.id,
+        thread,
+        chat_id,
+        runtime_chat_type,
+        Some(&sender_name),
+        sender_id.as_deref(),
+        image_data,
+    )
+    .await;
+    Ok(())
+}
+
+/// Run an agent turn for `chat_id` and deliver the response, with a typing
+/// indicator while it runs.
+#[allow(clippy::too_many_arguments)]
+async fn run_agent_and_reply(
+    bot: &Bot,
+    state: &AppState,
+    identity: &TelegramIdentity,
+    tg_chat: ChatId,
+    thread: Option<ThreadId>,
+    chat_id: i64,
+    runtime_chat_type: &str,
+    sender: Option<&str>,
+    sender_id: Option<&str>,
+    image_data: Option<(String, String)>,
+) {
     // Start continuous typing indicator
-    let typing_chat_id = msg.chat.id;
+    let typing_chat_id = tg_chat;
     let typing_bot = bot.clone();
     let typing_handle = tokio::spawn(async move {
         loop {
-            let _ = typing_bot
-                .send_chat_action(typing_chat_id, ChatAction::Typing)
-                .await;
+            let mut action = typing_bot.send_chat_action(typing_chat_id, ChatAction::Typing);
+            if let Some(thread) = thread {
+                action = action.message_thread_id(thread);
+            }
+            let _ = action.await;
             tokio::time::sleep(std::time::Duration::from_secs(4)).await;
         }
     });
 
     // Process through platform-agnostic agent engine.
-    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
-    match process_with_agent_with_events(
-        &state,
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel::<AgentEvent>();
+    let events = tokio::spawn(stream_reply_events(
+        bot.clone(),
+        tg_chat,
+        thread,
+        event_rx,
+        StreamingDraft::new(TELEGRAM_MAX_LEN)
+            .with_text(state.config.stream_replies)
+            .with_thinking(state.config.show_thinking)
+            .with_progress(state.config.progress_status_for_channel(&identity.channel)),
+    ));
+    let result = process_with_agent_with_events(
+        state,
         AgentRequestContext {
-            caller_channel: "telegram",
+            caller_channel: &identity.channel,
             chat_id,
             chat_type: runtime_chat_type,
+            sender,
+            sender_id,
         },
         None,
         image_data,
         Some(&event_tx),
     )
-    .await
-    {
+    .await;
+    typing_handle.abort();
+    drop(event_tx);
+    let (streamed, used_send_message_tool) = events.await.unwrap_or((None, false));
+
+    match result {
         Ok(response) => {
-            typing_handle.abort();
-            drop(event_tx);
-            let mut used_send_message_tool = false;
-            while let Some(event) = event_rx.recv().await {
-                if let AgentEvent::ToolStart { name } = event {
-                    if name == "send_message" {
-                        used_send_message_tool = true;
-                    }
-                }
+            if let (true, Some(message_id)) = (response.is_empty(), streamed) {
+                let _ = bot.delete_message(tg_chat, message_id).await;
             }
-
             if !response.is_empty() {
-                send_response(&bot, msg.chat.id, &response).await;
+                match streamed {
+                    // Keep the partial text of a cancelled turn visible.
+                    Some(message_id) if !response.starts_with(run_control::CANCELLED_PREFIX) => {
+                        finish_streamed_response(bot, tg_chat, thread, message_id, &response).await;
+                    }
+                    _ => send_response(bot, tg_chat, thread, &response).await,
+                }
 
                 // Store bot response
                 let bot_msg = StoredMessage {
                     id: uuid::Uuid::new_v4().to_string(),
                     chat_id,
-                    sender_name: state.config.bot_username.clone(),
+                    sender_name: identity.bot_username.clone(),
                     content: response,
                     is_from_bot: true,
                     timestamp: chrono::Utc::now().to_rfc3339(),
@@ -651,12 +1290,13 @@ async fn handle_message(
                     chat_id
                 );
             } else {
-                let fallback = "I couldn't produce a visible reply after an automatic retry. Please try again.".to_string();
-                send_response(&bot, msg.chat.id, &fallback).await;
+                let lang = i18n::chat_language(state, chat_id).await;
+                let fallback = i18n::t(lang, Msg::NoVisibleReply).to_string();
+                send_response(bot, tg_chat, thread, &fallback).await;
                 let bot_msg = StoredMessage {
                     id: uuid::Uuid::new_v4().to_string(),
                     chat_id,
-                    sender_name: state.config.bot_username.clone(),
+                    sender_name: identity.bot_username.clone(),
                     content: fallback,
                     is_from_bot: true,
                     timestamp: chrono::Utc::now().to_rfc3339(),
@@ -665,13 +1305,95 @@ async fn handle_message(
             }
         }
         Err(e) => {
-            typing_handle.abort();
             error!("Error processing message: {}", e);
-            let _ = bot.send_message(msg.chat.id, format!("Error: {e}")).await;
+            if let Some(message_id) = streamed {
+                let _ = bot.delete_message(tg_chat, message_id).await;
+            }
+            let lang = i18n::chat_language(state, chat_id).await;
+            let error = i18n::tf(lang, Msg::Error, &[("error", &e)]);
+            let _ = send_plain(bot, tg_chat, thread, error).await;
         }
     }
+}
 
-    Ok(())
+/// Consume agent events while a turn runs, showing `draft` (the streamed
+/// text and/or progress status) in one message that is edited as the turn
+/// goes on. Returns that message and whether the agent used `send_message`.
+async fn stream_reply_events(
+    bot: Bot,
+    chat: ChatId,
+    thread: Option<ThreadId>,
+    mut event_rx: tokio::sync::mpsc::UnboundedReceiver<AgentEvent>,
+    mut draft: StreamingDraft,
+) -> (Option<MessageId>, bool) {
+    let mut streamed = None;
+    let mut used_send_message_tool = false;
+    loop {
+        // Wake up periodically so the progress status's elapsed time moves.
+        let event = match tokio::time::timeout(STATUS_REFRESH_INTERVAL, event_rx.recv()).await {
+            Ok(Some(event)) => Some(event),
+            Ok(None) => break,
+            Err(_) => None,
+        };
+        if let Some(event) = &event {
+            // A merged run is answered by the queued turn.
+            if matches!(event, AgentEvent::ToolStart { name, .. } if name == "send_message")
+                || matches!(event, AgentEvent::Merged)
+            {
+                used_send_message_tool = true;
+            }
+            draft.on_event(event);
+        }
+        if !draft.is_active() {
+            continue;
+        }
+        let now = std::time::Instant::now();
+        let Some(text) = draft.due(now) else {
+            continue;
+        };
+        match streamed {
+            None => match send_plain(&bot, chat, thread, text.clone()).await {
+                Ok(sent) => streamed = Some(sent.id),
+                Err(e) => warn!("Telegram: failed to send streamed reply: {e}"),
+            },
+            Some(message_id) => {
+                let _ = bot.edit_message_text(chat, message_id, text.clone()).await;
+            }
+        }
+        draft.mark_shown(text, now);
+    }
+    (streamed, used_send_message_tool)
+}
+
+/// Replace a streamed message with the final response; chunks beyond the
+/// first are sent as new messages, or as a file for very long replies.
+async fn finish_streamed_response(
+    bot: &Bot,
+    chat: ChatId,
+    thread: Option<ThreadId>,
+    message_id: MessageId,
+    text: &str,
+) {
+    let chunks = split_response_text(text);
+    let as_file = chunks.len() > MAX_REPLY_CHUNKS;
+    let mut chunks = chunks.into_iter();
+    let Some(first) = chunks.next() else {
+        return;
+    };
+    let edited = bot
+        .edit_message_text(chat, message_id, render_markdown_v2_safe(&first))
+        .parse_mode(ParseMode::MarkdownV2)
+        .await;
+    if let Err(err) = edited {
+        warn!("Telegram MarkdownV2 edit failed, falling back to plain text: {err}");
+        let _ = bot.edit_message_text(chat, message_id, first).await;
+    }
+    if as_file && send_reply_file(bot, chat, thread, text).await {
+        return;
+    }
+    for chunk in chunks {
+        send_telegram_markdown_or_plain(bot, chat, thread, &chunk).await;
+    }
 }
 
 async fn download_telegram_file(
@@ -692,64 +1414,24 @@ fn base64_encode(data: &[u8]) -> String {
 }
 
 fn guess_image_media_type(data: &[u8]) -> String {
-    if data.starts_with(&[0x89, 0x50, 0x4E, 0x47]) {
-        "image/png".into()
-    } else if data.starts_with(&[0xFF, 0xD8]) {
-        "image/jpeg".into()
-    } else if data.starts_with(b"GIF") {
-        "image/gif".into()
-    } else if data.starts_with(b"RIFF") && data.len() >= 12 && &data[8..12] == b"WEBP" {
-        "image/webp".into()
-    } else {
-        "image/jpeg".into() // default
-    }
+    crate::channel::sniff_image_media_type(data)
+        .unwrap_or("image/jpeg")
+        .into()
 }
 
-fn split_response_text(text: &str) -> Vec<String> {
-    const MAX_LEN: usize = 4096;
-
-    if text.len() <= MAX_LEN {
-        return vec![text.to_string()];
-    }
-
-    let mut chunks = Vec::new();
-    let mut remaining = text.to_string();
-
-    while !remaining.is_empty() {
-        let chunk_len = if remaining.len() <= MAX_LEN {
-            remaining.len()
-        } else {
-            let boundary = floor_char_boundary(&remaining, MAX_LEN.min(remaining.len()));
-            remaining[..boundary]
-                .rfind(char::from(10))
-                .unwrap_or(boundary)
-        };
-
-        let mut chunk = remaining[..chunk_len].to_string();
-        let mut next_remaining = remaining[chunk_len..].to_string();
-        if next_remaining.starts_with(char::from(10)) {
-            next_remaining = next_remaining[1..].to_string();
-        }
-
-        // Keep fenced code blocks balanced per chunk; otherwise Telegram rejects MarkdownV2
-        // and the whole chunk falls back to plain text.
-        let fence_count = chunk
-            .lines()
-            .filter(|line| line.trim_start().starts_with("```"))
-            .count();
-        if fence_count % 2 == 1 {
-            chunk.push(char::from(10));
-            chunk.push_str("```");
-            if !next_remaining.is_empty() {
-                next_remaining = format!("```{}{}", char::from(10), next_remaining);
-            }
-        }
-
-        chunks.push(chunk);
-        remaining = next_remaining;
+fn image_extension(media_type: &str) -> &'static str {
+    match media_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "jpg",
     }
+}
 
-    chunks
+fn split_response_text(text: &str) -> Vec<String> {
+    // Telegram counts the limit after entity parsing, so splitting the raw
+    // Markdown keeps each chunk within it once rendered as MarkdownV2.
+    split_markdown(text, TELEGRAM_MAX_LEN)
 }
 
 fn escape_markdown_v2(text: &str) -> String {
@@ -867,22 +1549,74 @@ fn render_markdown_v2_safe(text: &str) -> String {
     out
 }
 
-async fn send_telegram_markdown_or_plain(bot: &Bot, chat_id: ChatId, text: &str) {
+/// Send plain text, into the forum topic when `thread` is set.
+async fn send_plain(
+    bot: &Bot,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    text: impl Into<String>,
+) -> Result<teloxide::types::Message, teloxide::RequestError> {
+    let mut req = bot.send_message(chat_id, text);
+    if let Some(thread) = thread {
+        req = req.message_thread_id(thread);
+    }
+    req.await
+}
+
+async fn send_telegram_markdown_or_plain(
+    bot: &Bot,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    text: &str,
+) {
     let markdown_text = render_markdown_v2_safe(text);
-    let markdown = bot
+    let mut req = bot
         .send_message(chat_id, markdown_text)
-        .parse_mode(ParseMode::MarkdownV2)
-        .await;
+        .parse_mode(ParseMode::MarkdownV2);
+    if let Some(thread) = thread {
+        req = req.message_thread_id(thread);
+    }
 
-    if let Err(err) = markdown {
+    if let Err(err) = req.await {
         warn!("Telegram MarkdownV2 send failed, falling back to plain text: {err}");
-        let _ = bot.send_message(chat_id, text).await;
+        let _ = send_plain(bot, chat_id, thread, text).await;
     }
 }
 
-pub async fn send_response(bot: &Bot, chat_id: ChatId, text: &str) {
-    for chunk in split_response_text(text) {
-        send_telegram_markdown_or_plain(bot, chat_id, &chunk).await;
+/// Send the full text as a Markdown file. Returns false if the upload failed.
+async fn send_reply_file(bot: &Bot, chat_id: ChatId, thread: Option<ThreadId>, text: &str) -> bool {
+    let file = InputFile::memory(text.as_bytes().to_vec()).file_name(REPLY_FILE_NAME);
+    let mut req = bot
+        .send_document(chat_id, file)
+        .caption(format!("Full reply ({} characters)", text.chars().count()));
+    if let Some(thread) = thread {
+        req = req.message_thread_id(thread);
+    }
+    match req.await {
+        Ok(_) => true,
+        Err(err) => {
+            warn!("Telegram: failed to send long reply as a file: {err}");
+            false
+        }
+    }
+}
+
+/// Send a reply split into messages. Very long replies are sent as their
+/// first chunk plus the full text as a file.
+pub async fn send_response(bot: &Bot, chat_id: ChatId, thread: Option<ThreadId>, text: &str) {
+    let chunks = split_response_text(text);
+    if chunks.len() > MAX_REPLY_CHUNKS {
+        send_telegram_markdown_or_plain(bot, chat_id, thread, &chunks[0]).await;
+        if send_reply_file(bot, chat_id, thread, text).await {
+            return;
+        }
+        for chunk in &chunks[1..] {
+            send_telegram_markdown_or_plain(bot, chat_id, thread, chunk).await;
+        }
+        return;
+    }
+    for chunk in chunks {
+        send_telegram_markdown_or_plain(bot, chat_id, thread, &chunk).await;
     }
 }
 
@@ -1052,6 +1786,19 @@ mod tests {
         assert_eq!(strip_thinking(input), "");
     }
 
+    #[test]
+    fn test_topic_external_chat_id_roundtrip() {
+        assert_eq!(topic_external_chat_id(-100123, None), "-100123");
+        assert_eq!(topic_external_chat_id(-100123, Some(42)), "-100123:42");
+        assert_eq!(
+            parse_external_chat_id("-100123:42").unwrap(),
+            (ChatId(-100123), Some(ThreadId(MessageId(42))))
+        );
+        assert_eq!(parse_external_chat_id("555").unwrap(), (ChatId(555), None));
+        assert!(parse_external_chat_id("abc").is_err());
+        assert!(parse_external_chat_id("-100:x").is_err());
+    }
+
     #[test]
     fn test_split_response_text_short() {
         let chunks = split_response_text("hello world");
@@ -1154,6 +1901,13 @@ mod tests {
         assert_eq!(guess_image_media_type(&data), "image/jpeg");
     }
 
+    #[test]
+    fn test_image_extension_for_saved_photos() {
+        assert_eq!(image_extension("image/png"), "png");
+        assert_eq!(image_extension("image/webp"), "webp");
+        assert_eq!(image_extension("image/jpeg"), "jpg");
+    }
+
     #[test]
     fn test_guess_image_media_type_png() {
         let data = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A];
@@ -1180,6 +1934,18 @@ mod tests {
         assert_eq!(guess_image_media_type(&data), "image/jpeg");
     }
 
+    #[test]
+    fn test_image_input_only_for_supported_images() {
+        let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A];
+        let (data, media_type) = crate::channel::image_input(&png).unwrap();
+        assert_eq!(media_type, "image/png");
+        assert_eq!(data, base64_encode(&png));
+        assert!(crate::channel::image_input(b"%PDF-1.7").is_none());
+        let mut large = vec![0xFF, 0xD8];
+        large.resize(crate::channel::MAX_IMAGE_INPUT_BYTES + 1, 0);
+        assert!(crate::channel::image_input(&large).is_none());
+    }
+
     #[test]
     fn test_base64_encode() {
         let data = b"hello world";
@@ -0,0 +1,399 @@
+//! Inbound webhook channel.
+//!
+//! `POST /webhook/<name>` turns a JSON payload from an external service into a
+//! message for the hook's configured chat, rendered through its template and
+//! optionally answered by the agent. `POST /webhook/task/<id>` runs a
+//! webhook-triggered scheduled task with the request body appended. Both
+//! authenticate with a shared secret, compared in constant time.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::http::{HeaderMap, StatusCode};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+
+use crate::agent_engine::process_with_agent;
+use crate::agent_engine::AgentRequestContext;
+use crate::channel::{deliver_and_store_bot_message, get_chat_routing};
+use crate::db::call_blocking;
+use crate::db::StoredMessage;
+use crate::runtime::AppState;
+
+/// Max characters of the raw payload inlined by `{{payload}}`.
+const MAX_PAYLOAD_CHARS: usize = 8000;
+/// `schedule_type` of scheduled tasks that run when their URL is posted to.
+pub const TASK_WEBHOOK_TRIGGER: &str = "webhook";
+const DEFAULT_TEMPLATE: &str = "Webhook `{{webhook}}` received:\n```json\n{{payload}}\n```";
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookDef {
+    /// Shared secret. Sent as `Authorization: Bearer`, `X-Webhook-Token`,
+    /// `?token=`, or used as the HMAC key of `X-Hub-Signature-256` (GitHub).
+    pub secret: String,
+    /// Internal chat id that receives the message.
+    pub chat_id: i64,
+    /// Message template with `{{path.to.field}}`, `{{header.name}}`,
+    /// `{{payload}}` and `{{webhook}}` placeholders.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Run the message through the agent. When false the rendered template is
+    /// delivered to the chat as-is (notification only).
+    #[serde(default = "default_true")]
+    pub respond: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WebhookChannelConfig {
+    #[serde(default)]
+    pub hooks: HashMap<String, WebhookDef>,
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+/// Check a request against `secret`: GitHub's `X-Hub-Signature-256` HMAC
+/// when present, otherwise a bearer, `X-Webhook-Token` or `?token=` value.
+/// An empty secret never matches.
+fn verify_secret(
+    secret: &str,
+    headers: &HeaderMap,
+    query_token: Option<&str>,
+    body: &[u8],
+) -> bool {
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return false;
+    }
+    if let Some(sig) = header_str(headers, "x-hub-signature-256") {
+        let Some(hex) = sig.strip_prefix("sha256=") else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(body);
+        let expected: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        return constant_time_eq(expected.as_bytes(), hex.to_ascii_lowercase().as_bytes());
+    }
+    let provided = header_str(headers, "authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| header_str(headers, "x-webhook-token"))
+        .or(query_token)
+        .unwrap_or("");
+    constant_time_eq(provided.trim().as_bytes(), secret.as_bytes())
+}
+
+fn lookup_path<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(payload, |value, key| match value {
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => value.get(key),
+    })
+}
+
+fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Render a webhook template. Unknown placeholders render as empty strings.
+fn render_template(template: &str, webhook: &str, payload: &Value, headers: &HeaderMap) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let key = rest[start + 2..start + end].trim();
+        let rendered = match key {
+            "webhook" => webhook.to_string(),
+            "payload" => {
+                let pretty = serde_json::to_string_pretty(payload).unwrap_or_default();
+                if pretty.chars().count() > MAX_PAYLOAD_CHARS {
+                    let cut: String = pretty.chars().take(MAX_PAYLOAD_CHARS).collect();
+                    format!("{cut}\n... (truncated)")
+                } else {
+                    pretty
+                }
+            }
+            _ => match key.strip_prefix("header.") {
+                Some(name) => header_str(headers, name).unwrap_or("").to_string(),
+                None => lookup_path(payload, key)
+                    .map(value_to_text)
+                    .unwrap_or_default(),
+            },
+        };
+        out.push_str(&rendered);
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Handle `POST /webhook/:name`. Verifies the request, stores the rendered
+/// message in the target chat and processes it in the background.
+pub async fn handle_webhook(
+    state: Arc<AppState>,
+    name: &str,
+    headers: &HeaderMap,
+    query_token: Option<&str>,
+    body: &[u8],
+) -> Result<Value, (StatusCode, String)> {
+    let cfg = state
+        .config
+        .channel_config::<WebhookChannelConfig>("webhook")
+        .unwrap_or_default();
+    let Some(def) = cfg.hooks.get(name).cloned() else {
+        return Err((StatusCode::NOT_FOUND, "unknown webhook".into()));
+    };
+    if !verify_secret(&def.secret, headers, query_token, body) {
+        warn!("Webhook {name}: rejected unauthenticated request");
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+    let payload: Value = serde_json::from_slice(body).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("invalid JSON payload: {e}"),
+        )
+    })?;
+
+    let template = def.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+    let text = render_template(template, name, &payload, headers);
+    let chat_id = def.chat_id;
+    let routing = get_chat_routing(&state.channel_registry, state.db.clone(), chat_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("target chat {chat_id} has no known channel"),
+            )
+        })?;
+
+    info!(
+        "Webhook {name}: delivering to chat {chat_id} via {}",
+        routing.channel_name
+    );
+
+    if !def.respond {
+        deliver_and_store_bot_message(
+            &state.channel_registry,
+            state.db.clone(),
+            &state.config.bot_username,
+            chat_id,
+            &text,
+        )
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+        return Ok(json!({ "ok": true, "chat_id": chat_id, "processed": false }));
+    }
+
+    let stored = StoredMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        chat_id,
+        sender_name: format!("webhook:{name}"),
+        content: text,
+        is_from_bot: false,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    call_blocking(state.db.clone(), move |db| db.store_message(&stored))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let name = name.to_string();
+    tokio::spawn(async move {
+        let reply = match process_with_agent(
+            &state,
+            AgentRequestContext {
+                caller_channel: &routing.channel_name,
+                chat_id,
+                chat_type: routing.conversation.as_agent_chat_type(),
+                sender: None,
+                sender_id: None,
+            },
+            None,
+            None,
+        )
+        .await
+        {
+            Ok(reply) => reply,
+            Err(e) => {
+                error!("Webhook {name}: agent run failed: {e}");
+                format!("Webhook `{name}` failed: {e}")
+            }
+        };
+        if reply.is_empty() {
+            return;
+        }
+        if let Err(e) = deliver_and_store_bot_message(
+            &state.channel_registry,
+            state.db.clone(),
+            &state.config.bot_username,
+            chat_id,
+            &reply,
+        )
+        .await
+        {
+            error!("Webhook {name}: failed to deliver reply: {e}");
+        }
+    });
+
+    Ok(json!({ "ok": true, "chat_id": chat_id, "processed": true }))
+}
+
+/// The prompt a webhook-triggered task runs with: its own prompt, plus the
+/// request body when there is one.
+fn task_prompt(prompt: &str, body: &[u8]) -> String {
+    let payload = match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Null) => return prompt.to_string(),
+        Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_default(),
+        Err(_) => String::from_utf8_lossy(body).trim().to_string(),
+    };
+    if payload.is_empty() {
+        return prompt.to_string();
+    }
+    let payload = if payload.chars().count() > MAX_PAYLOAD_CHARS {
+        let cut: String = payload.chars().take(MAX_PAYLOAD_CHARS).collect();
+        format!("{cut}\n... (truncated)")
+    } else {
+        payload
+    };
+    format!("{prompt}\n\nThe trigger sent this payload:\n```\n{payload}\n```")
+}
+
+/// Handle `POST /webhook/task/:id` for a `trigger: webhook` scheduled task:
+/// the task secret authenticates the request like a hook secret, and the
+/// task runs in the background.
+pub async fn handle_task_webhook(
+    state: Arc<AppState>,
+    task_id: i64,
+    headers: &HeaderMap,
+    query_token: Option<&str>,
+    body: &[u8],
+) -> Result<Value, (StatusCode, String)> {
+    let task = call_blocking(state.db.clone(), move |db| db.get_task_by_id(task_id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .filter(|t| t.schedule_type == TASK_WEBHOOK_TRIGGER)
+        .ok_or((StatusCode::NOT_FOUND, "unknown task".to_string()))?;
+    if !verify_secret(&task.schedule_value, headers, query_token, body) {
+        warn!("Task #{task_id} webhook: rejected unauthenticated request");
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    }
+    if task.status != "active" {
+        return Err((StatusCode::CONFLICT, format!("task is {}", task.status)));
+    }
+
+    info!("Task #{task_id} webhook: running for chat {}", task.chat_id);
+    let prompt = task_prompt(&task.prompt, body);
+    let chat_id = task.chat_id;
+    tokio::spawn(async move {
+        crate::scheduler::run_webhook_task(&state, &task, &prompt).await;
+    });
+    Ok(json!({ "ok": true, "task_id": task_id, "chat_id": chat_id }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn def(secret: &str) -> WebhookDef {
+        WebhookDef {
+            secret: secret.into(),
+            chat_id: 1,
+            template: None,
+            respond: true,
+        }
+    }
+
+    #[test]
+    fn test_render_template_paths_headers_and_payload() {
+        let payload = json!({
+            "action": "opened",
+            "repository": {"full_name": "acme/app"},
+            "commits": [{"id": "abc"}],
+            "count": 3
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert("x-github-event", "pull_request".parse().unwrap());
+        let out = render_template(
+            "{{ header.x-github-event }} {{action}} on {{repository.full_name}} ({{commits.0.id}}, {{count}}, [{{missing.field}}]) via {{webhook}}",
+            "github",
+            &payload,
+            &headers,
+        );
+        assert_eq!(
+            out,
+            "pull_request opened on acme/app (abc, 3, []) via github"
+        );
+
+        let default = render_template(DEFAULT_TEMPLATE, "grafana", &json!({"a": 1}), &headers);
+        assert!(default.contains("Webhook `grafana` received"));
+        assert!(default.contains("\"a\": 1"));
+    }
+
+    #[test]
+    fn test_verify_bearer_header_and_query_token() {
+        let hook = def("s3cret");
+        let mut headers = HeaderMap::new();
+        assert!(!verify_secret(&hook.secret, &headers, None, b"{}"));
+        assert!(verify_secret(&hook.secret, &headers, Some("s3cret"), b"{}"));
+        headers.insert("authorization", "Bearer s3cret".parse().unwrap());
+        assert!(verify_secret(&hook.secret, &headers, None, b"{}"));
+        assert!(!verify_secret("", &headers, None, b"{}"));
+    }
+
+    #[test]
+    fn test_task_prompt_appends_payload() {
+        assert_eq!(task_prompt("Summarize", b""), "Summarize");
+        assert_eq!(task_prompt("Summarize", b"null"), "Summarize");
+        let prompt = task_prompt("Summarize the changelog", br#"{"version":"1.2"}"#);
+        assert!(prompt.starts_with("Summarize the changelog\n\n"));
+        assert!(prompt.contains("\"version\": \"1.2\""));
+        assert!(task_prompt("Go", b"deployed v3").contains("deployed v3"));
+    }
+
+    #[test]
+    fn test_verify_github_signature() {
+        let hook = def("It's a Secret to Everybody");
+        let body = b"Hello, World!";
+        // Example from GitHub's webhook validation docs.
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-hub-signature-256",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+                .parse()
+                .unwrap(),
+        );
+        assert!(verify_secret(&hook.secret, &headers, None, body));
+        assert!(!verify_secret(&hook.secret, &headers, None, b"tampered"));
+    }
+}
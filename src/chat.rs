@@ -0,0 +1,559 @@
+//! `microclaw chat`: a terminal REPL against the normal agent loop, and
+//! `microclaw run`: one headless turn.
+//!
+//! Messages go through the same tools, memory, skills and database as the
+//! channels, on a synthetic local-only `cli` channel. No messaging channel
+//! needs to be configured, and the scheduler and channel bots are not
+//! started, so both can run next to `microclaw start`.
+
+use std::io;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::DefaultTerminal;
+
+use crate::agent_engine::{process_with_agent_with_events, AgentEvent, AgentRequestContext};
+use crate::channel::{deliver_and_store_bot_message, local_command_reply, ConversationKind};
+use crate::channel_adapter::{ChannelAdapter, ChannelRegistry};
+use crate::config::Config;
+use crate::db::{call_blocking, Database, StoredMessage};
+use crate::memory::MemoryManager;
+use crate::runtime::AppState;
+use crate::skills::SkillManager;
+
+const CHANNEL: &str = "cli";
+const EXTERNAL_CHAT_ID: &str = "local";
+/// External id of the `microclaw run` chat; `--session <name>` appends the
+/// name.
+const RUN_CHAT_ID: &str = "run";
+const HELP: &str = "Enter sends, PageUp/PageDown scroll, /reset clears the context, \
+/quit (or Esc, Ctrl-C) exits. Other slash commands work as in the Web UI.";
+
+pub struct CliAdapter;
+
+#[async_trait::async_trait]
+impl ChannelAdapter for CliAdapter {
+    fn name(&self) -> &str {
+        CHANNEL
+    }
+
+    fn chat_type_routes(&self) -> Vec<(&str, ConversationKind)> {
+        vec![(CHANNEL, ConversationKind::Private)]
+    }
+
+    fn is_local_only(&self) -> bool {
+        true
+    }
+
+    fn allows_cross_chat(&self) -> bool {
+        false
+    }
+
+    async fn send_text(&self, _external_chat_id: &str, _text: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LocalCommand {
+    Quit,
+    Reset,
+    Help,
+}
+
+fn parse_local_command(text: &str) -> Option<LocalCommand> {
+    match text.trim() {
+        "/quit" | "/exit" => Some(LocalCommand::Quit),
+        "/reset" => Some(LocalCommand::Reset),
+        "/help" => Some(LocalCommand::Help),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    User,
+    Bot,
+    Info,
+}
+
+/// What the agent task sends back to the UI.
+enum Update {
+    Tool(String),
+    Reply(String),
+    Error(String),
+}
+
+struct App {
+    transcript: Vec<(Role, String)>,
+    input: String,
+    busy: bool,
+    /// Lines scrolled up from the bottom of the transcript.
+    scroll_back: u16,
+}
+
+/// Rows `text` takes when wrapped to `width` columns. Approximate (wraps by
+/// character rather than by word), which is enough to keep the newest line
+/// in view.
+fn wrapped_height(text: &str, width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    text.split('\n')
+        .map(|line| line.chars().count().div_ceil(width).max(1))
+        .sum::<usize>()
+        .min(u16::MAX as usize) as u16
+}
+
+fn transcript_lines(app: &App) -> (Vec<Line<'static>>, String) {
+    let mut lines = Vec::new();
+    let mut plain = String::new();
+    for (role, text) in &app.transcript {
+        let (label, style) = match role {
+            Role::User => (
+                "you",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Role::Bot => (
+                "bot",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Role::Info => ("", Style::default().fg(Color::DarkGray)),
+        };
+        for (i, line) in text.split('\n').enumerate() {
+            let prefix = if i == 0 && !label.is_empty() {
+                format!("{label}> ")
+            } else {
+                String::new()
+            };
+            let body_style = if *role == Role::Info {
+                style
+            } else {
+                Style::default()
+            };
+            plain.push_str(&prefix);
+            plain.push_str(line);
+            plain.push('\n');
+            lines.push(Line::from(vec![
+                Span::styled(prefix, style),
+                Span::styled(line.to_string(), body_style),
+            ]));
+        }
+        plain.push('\n');
+        lines.push(Line::default());
+    }
+    (lines, plain)
+}
+
+fn draw(f: &mut ratatui::Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(f.area());
+
+    let (lines, plain) = transcript_lines(app);
+    let inner = chunks[0].width.saturating_sub(2);
+    let visible = chunks[0].height.saturating_sub(2);
+    let total = wrapped_height(plain.trim_end_matches('\n'), inner);
+    let bottom = total.saturating_sub(visible);
+    let scroll = bottom.saturating_sub(app.scroll_back);
+    let transcript = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" MicroClaw chat — /help "),
+        );
+    f.render_widget(transcript, chunks[0]);
+
+    let title = if app.busy {
+        " thinking… "
+    } else {
+        " message "
+    };
+    let input = Paragraph::new(app.input.as_str())
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(input, chunks[1]);
+    let cursor_x = chunks[1].x + 1 + (app.input.chars().count() as u16).min(inner);
+    f.set_cursor_position((cursor_x, chunks[1].y + 1));
+}
+
+/// The UI loop; runs on a blocking thread. Sends submitted lines to the
+/// agent task and shows whatever comes back.
+fn run_ui(
+    mut terminal: DefaultTerminal,
+    submit: tokio::sync::mpsc::UnboundedSender<String>,
+    updates: mpsc::Receiver<Update>,
+) -> io::Result<()> {
+    let mut app = App {
+        transcript: vec![(Role::Info, HELP.to_string())],
+        input: String::new(),
+        busy: false,
+        scroll_back: 0,
+    };
+    loop {
+        while let Ok(update) = updates.try_recv() {
+            match update {
+                Update::Tool(name) => app.transcript.push((Role::Info, format!("[{name}]"))),
+                Update::Reply(text) => {
+                    app.transcript.push((Role::Bot, text));
+                    app.busy = false;
+                }
+                Update::Error(e) => {
+                    app.transcript.push((Role::Info, format!("Error: {e}")));
+                    app.busy = false;
+                }
+            }
+            app.scroll_back = 0;
+        }
+        terminal.draw(|f| draw(f, &app))?;
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::PageUp => app.scroll_back = app.scroll_back.saturating_add(10),
+            KeyCode::PageDown => app.scroll_back = app.scroll_back.saturating_sub(10),
+            KeyCode::Backspace => {
+                app.input.pop();
+            }
+            KeyCode::Enter => {
+                let text = app.input.trim().to_string();
+                if text.is_empty() || app.busy {
+                    continue;
+                }
+                app.input.clear();
+                match parse_local_command(&text) {
+                    Some(LocalCommand::Quit) => return Ok(()),
+                    Some(LocalCommand::Help) => {
+                        app.transcript.push((Role::Info, HELP.to_string()));
+                        continue;
+                    }
+                    Some(LocalCommand::Reset) | None => {}
+                }
+                app.transcript.push((Role::User, text.clone()));
+                app.scroll_back = 0;
+                app.busy = true;
+                if submit.send(text).is_err() {
+                    return Ok(());
+                }
+            }
+            KeyCode::Char(c) => app.input.push(c),
+            _ => {}
+        }
+    }
+}
+
+async fn reset(state: &AppState, chat_id: i64) -> Result<String, String> {
+    let cleared = call_blocking(state.db.clone(), move |db| db.clear_chat_context(chat_id))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(if cleared {
+        "Context cleared.".into()
+    } else {
+        "Nothing to clear.".into()
+    })
+}
+
+async fn answer(
+    state: &AppState,
+    chat_id: i64,
+    sender: &str,
+    text: String,
+    updates: &mpsc::Sender<Update>,
+) -> Result<String, String> {
+    if parse_local_command(&text) == Some(LocalCommand::Reset) {
+        return reset(state, chat_id).await;
+    }
+    if let Some(reply) = local_command_reply(state, CHANNEL, chat_id, None, &text).await {
+        return Ok(reply);
+    }
+    let msg = StoredMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        chat_id,
+        sender_name: sender.to_string(),
+        content: text,
+        is_from_bot: false,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    call_blocking(state.db.clone(), move |db| db.store_message(&msg))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+    let forward = {
+        let updates = updates.clone();
+        tokio::spawn(async move {
+            while let Some(event) = event_rx.recv().await {
+                if let AgentEvent::ToolStart { name, .. } = event {
+                    let _ = updates.send(Update::Tool(name));
+                }
+            }
+        })
+    };
+    let response = process_with_agent_with_events(
+        state,
+        AgentRequestContext {
+            caller_channel: CHANNEL,
+            chat_id,
+            chat_type: CHANNEL,
+            sender: Some(sender),
+            sender_id: None,
+        },
+        None,
+        None,
+        Some(&event_tx),
+    )
+    .await
+    .map_err(|e| e.to_string());
+    drop(event_tx);
+    let _ = forward.await;
+    let response = response?;
+
+    deliver_and_store_bot_message(
+        &state.channel_registry,
+        state.db.clone(),
+        &state.config.bot_username,
+        chat_id,
+        &response,
+    )
+    .await?;
+    Ok(response)
+}
+
+fn local_state(
+    config: Config,
+    db: Database,
+    memory: MemoryManager,
+    skills: SkillManager,
+    mcp_manager: crate::mcp::McpManager,
+) -> Arc<AppState> {
+    let mut registry = ChannelRegistry::new();
+    registry.register(Arc::new(CliAdapter));
+    Arc::new(crate::runtime::build_state(
+        config,
+        Arc::new(db),
+        memory,
+        skills,
+        mcp_manager,
+        registry,
+    ))
+}
+
+async fn local_chat_id(
+    state: &AppState,
+    external_chat_id: String,
+    title: &'static str,
+) -> anyhow::Result<i64> {
+    Ok(call_blocking(state.db.clone(), move |db| {
+        db.resolve_or_create_chat_id(CHANNEL, &external_chat_id, Some(title), CHANNEL)
+    })
+    .await?)
+}
+
+fn sender_name() -> String {
+    std::env::var("USER")
+        .ok()
+        .filter(|u| !u.trim().is_empty())
+        .unwrap_or_else(|| "cli-user".to_string())
+}
+
+pub async fn run(
+    config: Config,
+    db: Database,
+    memory: MemoryManager,
+    skills: SkillManager,
+    mcp_manager: crate::mcp::McpManager,
+) -> anyhow::Result<()> {
+    let state = local_state(config, db, memory, skills, mcp_manager);
+    let chat_id = local_chat_id(&state, EXTERNAL_CHAT_ID.to_string(), "Terminal").await?;
+    let sender = sender_name();
+
+    let (submit_tx, mut submit_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let (update_tx, update_rx) = mpsc::channel();
+    let agent = tokio::spawn(async move {
+        while let Some(text) = submit_rx.recv().await {
+            let update = match answer(&state, chat_id, &sender, text, &update_tx).await {
+                Ok(reply) => Update::Reply(reply),
+                Err(e) => Update::Error(e),
+            };
+            if update_tx.send(update).is_err() {
+                break;
+            }
+        }
+    });
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let terminal = ratatui::Terminal::new(ratatui::backend::CrosstermBackend::new(stdout))?;
+    let result = tokio::task::spawn_blocking(move || run_ui(terminal, submit_tx, update_rx)).await;
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    agent.abort();
+    result??;
+    Ok(())
+}
+
+pub const RUN_USAGE: &str = "Usage: microclaw run [--session <name>] [--verbose] \"<prompt>\"
+
+Runs one agent turn (with tools) and prints the final answer to stdout.
+With no prompt, or -, the prompt is read from stdin; piped input is
+otherwise appended to the prompt. Each run starts from
+an empty context unless --session names a conversation to continue.
+--verbose prints tool calls to stderr. Exits with 1 when the turn fails.";
+
+/// Arguments of `microclaw run`.
+#[derive(Debug, PartialEq)]
+pub struct OneShot {
+    prompt: Option<String>,
+    session: Option<String>,
+    verbose: bool,
+}
+
+impl OneShot {
+    /// Parse `microclaw run` arguments; `Ok(None)` means help was asked for.
+    pub fn from_args(args: &[String]) -> anyhow::Result<Option<Self>> {
+        let mut prompt: Option<String> = None;
+        let mut session = None;
+        let mut verbose = false;
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--help" | "-h" => return Ok(None),
+                "--verbose" | "-v" => verbose = true,
+                "--session" => {
+                    let name = iter
+                        .next()
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .ok_or_else(|| anyhow::anyhow!("--session needs a name"))?;
+                    session = Some(name.to_string());
+                }
+                "-" => prompt = None,
+                flag if flag.starts_with("--") => anyhow::bail!("unknown option {flag}"),
+                text => {
+                    prompt = Some(match prompt {
+                        Some(prev) => format!("{prev} {text}"),
+                        None => text.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(Some(Self {
+            prompt,
+            session,
+            verbose,
+        }))
+    }
+}
+
+/// `microclaw run`: one turn, answer on stdout.
+pub async fn run_once(
+    config: Config,
+    db: Database,
+    memory: MemoryManager,
+    skills: SkillManager,
+    mcp_manager: crate::mcp::McpManager,
+    args: OneShot,
+) -> anyhow::Result<()> {
+    let mut piped = String::new();
+    if args.prompt.is_none() || !io::IsTerminal::is_terminal(&io::stdin()) {
+        io::Read::read_to_string(&mut io::stdin(), &mut piped)?;
+    }
+    let prompt = match args.prompt {
+        Some(prompt) if !piped.trim().is_empty() => format!("{prompt}\n\n{}", piped.trim()),
+        Some(prompt) => prompt,
+        None => piped,
+    };
+    let prompt = prompt.trim().to_string();
+    if prompt.is_empty() {
+        anyhow::bail!("the prompt is empty");
+    }
+
+    let state = local_state(config, db, memory, skills, mcp_manager);
+    let external_chat_id = match &args.session {
+        Some(name) => format!("{RUN_CHAT_ID}:{name}"),
+        None => RUN_CHAT_ID.to_string(),
+    };
+    let chat_id = local_chat_id(&state, external_chat_id, "microclaw run").await?;
+    if args.session.is_none() {
+        call_blocking(state.db.clone(), move |db| db.clear_chat_context(chat_id)).await?;
+    }
+
+    let (update_tx, update_rx) = mpsc::channel();
+    let verbose = args.verbose;
+    let progress = std::thread::spawn(move || {
+        for update in update_rx {
+            if let Update::Tool(name) = update {
+                if verbose {
+                    eprintln!("[{name}]");
+                }
+            }
+        }
+    });
+    let result = answer(&state, chat_id, &sender_name(), prompt, &update_tx).await;
+    drop(update_tx);
+    let _ = progress.join();
+    println!("{}", result.map_err(anyhow::Error::msg)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_commands_and_wrapping() {
+        assert_eq!(parse_local_command(" /quit "), Some(LocalCommand::Quit));
+        assert_eq!(parse_local_command("/exit"), Some(LocalCommand::Quit));
+        assert_eq!(parse_local_command("/reset"), Some(LocalCommand::Reset));
+        assert_eq!(parse_local_command("/fork"), None);
+        assert_eq!(parse_local_command("hello"), None);
+
+        assert_eq!(wrapped_height("", 10), 1);
+        assert_eq!(wrapped_height("0123456789", 10), 1);
+        assert_eq!(wrapped_height("0123456789a", 10), 2);
+        assert_eq!(wrapped_height("a\n\nb", 10), 3);
+        assert_eq!(wrapped_height("abc", 0), 3);
+    }
+
+    #[test]
+    fn test_one_shot_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            OneShot::from_args(&args(&["--session", "nightly", "sum", "up"]))
+                .unwrap()
+                .unwrap(),
+            OneShot {
+                prompt: Some("sum up".into()),
+                session: Some("nightly".into()),
+                verbose: false,
+            }
+        );
+        let stdin = OneShot::from_args(&args(&["-v", "-"])).unwrap().unwrap();
+        assert!(stdin.prompt.is_none() && stdin.verbose);
+        assert!(OneShot::from_args(&args(&["--help"])).unwrap().is_none());
+        assert!(OneShot::from_args(&args(&["--session"])).is_err());
+        assert!(OneShot::from_args(&args(&["--bogus", "x"])).is_err());
+    }
+}
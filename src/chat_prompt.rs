@@ -0,0 +1,111 @@
+//! Per-chat system prompt additions (`/prompt`).
+//!
+//! `/prompt set <text>` stores standing instructions for one chat in
+//! `chat_settings`; they are appended to the global system prompt of every
+//! turn in that chat, so one bot can behave differently in, say, a support
+//! group and a dev group. `/prompt show` prints them and `/prompt clear`
+//! removes them.
+
+use crate::db::call_blocking;
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding the chat's prompt addition.
+pub const PROMPT_SETTING_KEY: &str = "system_prompt";
+/// Longest accepted addition, in characters.
+pub const MAX_PROMPT_CHARS: usize = 4000;
+
+const PROMPT_USAGE: &str = "Usage: /prompt show — show this chat's instructions\n/prompt set <text> — add instructions to the system prompt for this chat\n/prompt clear — remove them";
+
+#[derive(Debug, PartialEq, Eq)]
+enum PromptCommand<'a> {
+    Show,
+    Set(&'a str),
+    Clear,
+    Usage,
+}
+
+fn parse(text: &str) -> Option<PromptCommand<'_>> {
+    let text = text.trim();
+    let rest = match text.split_once(char::is_whitespace) {
+        Some(("/prompt", rest)) => rest.trim(),
+        None if text == "/prompt" => "",
+        _ => return None,
+    };
+    let (action, arg) = rest
+        .split_once(char::is_whitespace)
+        .map(|(action, arg)| (action, arg.trim()))
+        .unwrap_or((rest, ""));
+    Some(match action.to_ascii_lowercase().as_str() {
+        "" | "show" if arg.is_empty() => PromptCommand::Show,
+        "set" if !arg.is_empty() => PromptCommand::Set(arg),
+        "clear" if arg.is_empty() => PromptCommand::Clear,
+        _ => PromptCommand::Usage,
+    })
+}
+
+/// The chat's prompt addition, if it set one.
+pub async fn chat_prompt(state: &AppState, chat_id: i64) -> Option<String> {
+    call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, PROMPT_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten()
+    .filter(|p| !p.trim().is_empty())
+}
+
+/// Handle `/prompt`. Returns `None` when the text is not this command.
+pub async fn handle_prompt_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let stored = match parse(text)? {
+        PromptCommand::Usage => return Some(PROMPT_USAGE.to_string()),
+        PromptCommand::Show => {
+            return Some(match chat_prompt(state, chat_id).await {
+                Some(prompt) => format!("This chat's instructions:\n\n{prompt}"),
+                None => format!("This chat has no instructions of its own.\n{PROMPT_USAGE}"),
+            })
+        }
+        PromptCommand::Set(prompt) => {
+            let chars = prompt.chars().count();
+            if chars > MAX_PROMPT_CHARS {
+                return Some(format!(
+                    "Instructions are limited to {MAX_PROMPT_CHARS} characters (got {chars})."
+                ));
+            }
+            Some(prompt.to_string())
+        }
+        PromptCommand::Clear => None,
+    };
+    let cleared = stored.is_none();
+    if let Err(e) = call_blocking(state.db.clone(), move |db| {
+        db.set_chat_setting(chat_id, PROMPT_SETTING_KEY, stored.as_deref())
+    })
+    .await
+    {
+        return Some(format!("Failed to update this chat's instructions: {e}"));
+    }
+    Some(if cleared {
+        "Cleared this chat's instructions.".to_string()
+    } else {
+        "Saved. These instructions apply to this chat from the next message.".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prompt_command() {
+        assert_eq!(parse("/prompt"), Some(PromptCommand::Show));
+        assert_eq!(parse("/prompt show"), Some(PromptCommand::Show));
+        assert_eq!(parse("/prompt CLEAR"), Some(PromptCommand::Clear));
+        assert_eq!(
+            parse("/prompt set Answer in German.\nKeep it short."),
+            Some(PromptCommand::Set("Answer in German.\nKeep it short."))
+        );
+        assert_eq!(parse("/prompt set"), Some(PromptCommand::Usage));
+        assert_eq!(parse("/prompt clear now"), Some(PromptCommand::Usage));
+        assert_eq!(parse("/prompts"), None);
+        assert_eq!(parse("what is a /prompt"), None);
+    }
+}
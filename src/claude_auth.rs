@@ -0,0 +1,248 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::error::MicroClawError;
+
+pub const CLAUDE_OAUTH_PROVIDER: &str = "claude-oauth";
+/// Long-lived token (`claude setup-token`); used as is, without refreshing.
+pub const CLAUDE_OAUTH_TOKEN_ENV: &str = "CLAUDE_CODE_OAUTH_TOKEN";
+
+const CLAUDE_OAUTH_TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
+const CLAUDE_OAUTH_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+/// Refresh the access token this long before it expires.
+const EXPIRY_MARGIN_MS: i64 = 5 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ClaudeOAuthTokens {
+    access_token: String,
+    refresh_token: Option<String>,
+    /// Unix milliseconds, as `claude login` writes it.
+    expires_at: Option<i64>,
+}
+
+pub fn is_claude_oauth_provider(provider: &str) -> bool {
+    provider.eq_ignore_ascii_case(CLAUDE_OAUTH_PROVIDER)
+}
+
+pub fn default_claude_credentials_path() -> PathBuf {
+    let base = std::env::var("CLAUDE_CONFIG_DIR")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| expand_tilde(&v))
+        .unwrap_or_else(|| expand_tilde("~/.claude"));
+    Path::new(&base).join(".credentials.json")
+}
+
+fn expand_tilde(input: &str) -> String {
+    match (input.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}/{rest}"),
+        _ if input == "~" => std::env::var("HOME").unwrap_or_else(|_| input.to_string()),
+        _ => input.to_string(),
+    }
+}
+
+fn env_token() -> Option<String> {
+    std::env::var(CLAUDE_OAUTH_TOKEN_ENV)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn parse_tokens(credentials: &Value) -> Option<ClaudeOAuthTokens> {
+    let oauth = credentials.get("claudeAiOauth")?;
+    let text = |key: &str| {
+        oauth
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    Some(ClaudeOAuthTokens {
+        access_token: text("accessToken")?,
+        refresh_token: text("refreshToken"),
+        expires_at: oauth.get("expiresAt").and_then(Value::as_i64),
+    })
+}
+
+fn read_credentials(path: &Path) -> Result<Value, MicroClawError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        MicroClawError::Config(format!(
+            "Failed to read Claude credentials {}: {e}",
+            path.display()
+        ))
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        MicroClawError::Config(format!(
+            "Failed to parse Claude credentials {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+fn missing_credentials(path: &Path) -> MicroClawError {
+    MicroClawError::Config(format!(
+        "claude-oauth requires a Claude Pro/Max login: run `claude login` (expected credentials: {}) or set {CLAUDE_OAUTH_TOKEN_ENV}.",
+        path.display()
+    ))
+}
+
+pub fn claude_credentials_available() -> Result<bool, MicroClawError> {
+    if env_token().is_some() {
+        return Ok(true);
+    }
+    let path = default_claude_credentials_path();
+    if !path.exists() {
+        return Ok(false);
+    }
+    Ok(parse_tokens(&read_credentials(&path)?).is_some())
+}
+
+/// The stored access token, without refreshing it.
+pub fn resolve_claude_oauth_token() -> Result<String, MicroClawError> {
+    if let Some(token) = env_token() {
+        return Ok(token);
+    }
+    let path = default_claude_credentials_path();
+    if !path.exists() {
+        return Err(missing_credentials(&path));
+    }
+    parse_tokens(&read_credentials(&path)?)
+        .map(|tokens| tokens.access_token)
+        .ok_or_else(|| missing_credentials(&path))
+}
+
+/// Write a refresh response into the credentials, keeping the other fields.
+/// Returns the new access token.
+fn apply_refresh(credentials: &mut Value, response: &Value, now_ms: i64) -> Option<String> {
+    let access_token = response
+        .get("access_token")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|v| !v.is_empty())?
+        .to_string();
+    let oauth = credentials.get_mut("claudeAiOauth")?.as_object_mut()?;
+    oauth.insert("accessToken".into(), Value::String(access_token.clone()));
+    if let Some(refresh_token) = response
+        .get("refresh_token")
+        .and_then(Value::as_str)
+        .filter(|v| !v.trim().is_empty())
+    {
+        oauth.insert("refreshToken".into(), Value::String(refresh_token.into()));
+    }
+    if let Some(expires_in) = response.get("expires_in").and_then(Value::as_i64) {
+        oauth.insert("expiresAt".into(), Value::from(now_ms + expires_in * 1000));
+    }
+    Some(access_token)
+}
+
+/// Bearer token for `claude-oauth` requests. The token from `claude login`
+/// is refreshed (and written back) when it expires within five minutes.
+pub async fn claude_oauth_bearer_token() -> Result<String, MicroClawError> {
+    if let Some(token) = env_token() {
+        return Ok(token);
+    }
+    // One refresh at a time; the refresh token may only be usable once.
+    static REFRESH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+    let _guard = REFRESH_LOCK.lock().await;
+
+    let path = default_claude_credentials_path();
+    if !path.exists() {
+        return Err(missing_credentials(&path));
+    }
+    let mut credentials = read_credentials(&path)?;
+    let tokens = parse_tokens(&credentials).ok_or_else(|| missing_credentials(&path))?;
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    if tokens
+        .expires_at
+        .is_none_or(|at| at - EXPIRY_MARGIN_MS > now_ms)
+    {
+        return Ok(tokens.access_token);
+    }
+    let expired = |reason: String| {
+        MicroClawError::LlmApi(format!(
+            "Claude OAuth token expired and could not be refreshed ({reason}); run `claude login`"
+        ))
+    };
+    let refresh_token = tokens
+        .refresh_token
+        .ok_or_else(|| expired("no refresh token".into()))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()?;
+    let resp = client
+        .post(CLAUDE_OAUTH_TOKEN_URL)
+        .json(&serde_json::json!({
+            "grant_type": "refresh_token",
+            "refresh_token": refresh_token,
+            "client_id": CLAUDE_OAUTH_CLIENT_ID,
+        }))
+        .send()
+        .await
+        .map_err(|e| expired(e.to_string()))?;
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(expired(format!(
+            "HTTP {status}: {}",
+            text.chars().take(200).collect::<String>()
+        )));
+    }
+    let response: Value = serde_json::from_str(&text)
+        .map_err(|e| expired(format!("invalid refresh response: {e}")))?;
+    let access_token = apply_refresh(&mut credentials, &response, now_ms)
+        .ok_or_else(|| expired("the refresh response has no access token".into()))?;
+    let serialized = serde_json::to_string_pretty(&credentials).map_err(|e| {
+        MicroClawError::Config(format!("Failed to serialize Claude credentials: {e}"))
+    })?;
+    std::fs::write(&path, serialized)?;
+    Ok(access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_claude_oauth_provider() {
+        assert!(is_claude_oauth_provider("claude-oauth"));
+        assert!(is_claude_oauth_provider("Claude-OAuth"));
+        assert!(!is_claude_oauth_provider("anthropic"));
+    }
+
+    #[test]
+    fn test_parse_and_refresh_credentials() {
+        let mut credentials = json!({
+            "claudeAiOauth": {
+                "accessToken": "sk-ant-oat01-old",
+                "refreshToken": "sk-ant-ort01-old",
+                "expiresAt": 1_760_000_000_000_i64,
+                "scopes": ["user:inference"],
+                "subscriptionType": "max"
+            }
+        });
+        assert_eq!(
+            parse_tokens(&credentials),
+            Some(ClaudeOAuthTokens {
+                access_token: "sk-ant-oat01-old".into(),
+                refresh_token: Some("sk-ant-ort01-old".into()),
+                expires_at: Some(1_760_000_000_000),
+            })
+        );
+        assert_eq!(parse_tokens(&json!({"claudeAiOauth": {}})), None);
+
+        let token = apply_refresh(
+            &mut credentials,
+            &json!({"access_token": "sk-ant-oat01-new", "expires_in": 28800}),
+            1_760_000_100_000,
+        );
+        assert_eq!(token.as_deref(), Some("sk-ant-oat01-new"));
+        let oauth = &credentials["claudeAiOauth"];
+        assert_eq!(oauth["refreshToken"], "sk-ant-ort01-old");
+        assert_eq!(oauth["expiresAt"], 1_760_028_900_000_i64);
+        assert_eq!(oauth["subscriptionType"], "max");
+        assert!(apply_refresh(&mut credentials, &json!({"error": "x"}), 0).is_none());
+    }
+}
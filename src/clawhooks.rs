@@ -0,0 +1,172 @@
+//! Clawhooks: signed outbound webhooks for agent events (`clawhooks:` in the
+//! config).
+//!
+//! Each event is POSTed as JSON to every hook subscribed to it. The body is
+//! signed GitHub-style with the hook's secret, as
+//! `X-Microclaw-Signature: sha256=<hex HMAC-SHA256 of the body>`. Deliveries
+//! run in the background and are retried on network errors, 429 and 5xx;
+//! failures are only logged, so a slow receiver never holds up a turn.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::{debug, warn};
+
+use crate::config::{ClawhookConfig, ClawhookEvent, Config};
+
+/// Waits before the second and third attempts.
+const RETRY_DELAYS: [Duration; 2] = [Duration::from_secs(2), Duration::from_secs(10)];
+
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent("MicroClaw/1.0")
+            .build()
+            .expect("failed to build HTTP client")
+    })
+}
+
+/// Send `event` for `chat_id` with event-specific `data` to the hooks that
+/// want it. Returns immediately.
+pub fn emit(config: &Config, event: ClawhookEvent, chat_id: i64, data: Value) {
+    let hooks: Vec<ClawhookConfig> = config
+        .clawhooks
+        .iter()
+        .filter(|hook| hook.wants(event))
+        .cloned()
+        .collect();
+    if hooks.is_empty() {
+        return;
+    }
+    let body = event_body(&config.bot_username, event, chat_id, data).to_string();
+    for hook in hooks {
+        let body = body.clone();
+        tokio::spawn(async move { deliver(&hook, event, &body).await });
+    }
+}
+
+fn event_body(bot: &str, event: ClawhookEvent, chat_id: i64, data: Value) -> Value {
+    json!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "event": event.as_str(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "bot": bot,
+        "chat_id": chat_id,
+        "data": data,
+    })
+}
+
+/// `sha256=<hex>` HMAC of `body` keyed by `secret`.
+pub fn signature(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+/// One POST; `Err((message, retryable))` on failure.
+async fn post(
+    hook: &ClawhookConfig,
+    event: ClawhookEvent,
+    body: &str,
+    signature: &str,
+) -> Result<(), (String, bool)> {
+    let resp = http_client()
+        .post(&hook.url)
+        .header("Content-Type", "application/json")
+        .header("X-Microclaw-Event", event.as_str())
+        .header("X-Microclaw-Signature", signature)
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|e| (e.to_string(), true))?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    Err((
+        format!("HTTP {status}"),
+        status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+    ))
+}
+
+async fn deliver(hook: &ClawhookConfig, event: ClawhookEvent, body: &str) {
+    let signature = signature(&hook.secret, body);
+    let mut attempt = 0;
+    loop {
+        match post(hook, event, body, &signature).await {
+            Ok(()) => {
+                debug!("Clawhook {} delivered to {}", event.as_str(), hook.url);
+                crate::metrics::clawhook_delivery(event.as_str(), true);
+                return;
+            }
+            Err((_, true)) if attempt < RETRY_DELAYS.len() => {
+                tokio::time::sleep(RETRY_DELAYS[attempt]).await;
+                attempt += 1;
+            }
+            Err((error, _)) => {
+                warn!(
+                    "Clawhook {} to {} failed after {} attempt(s): {error}",
+                    event.as_str(),
+                    hook.url,
+                    attempt + 1
+                );
+                crate::metrics::clawhook_delivery(event.as_str(), false);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_matches_github_style_hmac() {
+        // echo -n '{"a":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            signature("secret", r#"{"a":1}"#),
+            "sha256=aa9e2e3575f5d7098b6caccd790888c36d5fdb63342a73bada2d6a51747a8494"
+        );
+    }
+
+    #[test]
+    fn test_event_body_and_subscriptions() {
+        let body = event_body(
+            "bot",
+            ClawhookEvent::ToolFailed,
+            42,
+            json!({"tool": "bash"}),
+        );
+        assert_eq!(body["event"], "tool_failed");
+        assert_eq!(body["chat_id"], 42);
+        assert_eq!(body["data"]["tool"], "bash");
+        assert!(body["id"].as_str().is_some_and(|id| !id.is_empty()));
+
+        let all = ClawhookConfig {
+            url: "https://example.com".into(),
+            secret: "s".into(),
+            events: Vec::new(),
+        };
+        assert!(ClawhookEvent::ALL.iter().all(|e| all.wants(*e)));
+        let some = ClawhookConfig {
+            events: vec![ClawhookEvent::BudgetExceeded],
+            ..all
+        };
+        assert!(some.wants(ClawhookEvent::BudgetExceeded));
+        assert!(!some.wants(ClawhookEvent::TurnCompleted));
+    }
+}
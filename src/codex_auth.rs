@@ -1,8 +1,11 @@
 use base64::Engine;
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
 
 use crate::error::MicroClawError;
+use crate::runtime::AppState;
 
 pub const OPENAI_CODEX_PROVIDER: &str = "openai-codex";
 
@@ -28,7 +31,11 @@ pub struct CodexAuthResolved {
 }
 
 pub fn provider_allows_empty_api_key(provider: &str) -> bool {
-    provider.eq_ignore_ascii_case("ollama") || provider.eq_ignore_ascii_case(OPENAI_CODEX_PROVIDER)
+    provider.eq_ignore_ascii_case("ollama")
+        || provider.eq_ignore_ascii_case("bedrock")
+        || provider.eq_ignore_ascii_case("azure")
+        || provider.eq_ignore_ascii_case(OPENAI_CODEX_PROVIDER)
+        || crate::claude_auth::is_claude_oauth_provider(provider)
 }
 
 pub fn is_openai_codex_provider(provider: &str) -> bool {
@@ -244,22 +251,58 @@ struct CodexRefreshResponse {
     refresh_token: Option<String>,
 }
 
-pub fn refresh_openai_codex_auth_if_needed() -> Result<(), MicroClawError> {
+/// The background refresh renews the token this long before it expires.
+const REFRESH_AHEAD_SECS: i64 = 600;
+/// Longest sleep between background checks, so a token replaced by
+/// `codex login` is picked up.
+const MAX_CHECK_SECS: i64 = 3600;
+const MIN_CHECK_SECS: i64 = 60;
+const RETRY_BASE_SECS: u64 = 30;
+const RETRY_MAX_SECS: u64 = 1800;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CodexRefreshError {
+    /// Network trouble or a server-side error; worth retrying.
+    Transient(String),
+    /// The refresh token is missing or was rejected; `codex login` is needed.
+    ReloginRequired(String),
+}
+
+impl std::fmt::Display for CodexRefreshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodexRefreshError::Transient(e) => write!(f, "Codex token refresh failed: {e}"),
+            CodexRefreshError::ReloginRequired(e) => {
+                write!(f, "Codex login expired ({e}); run `codex login`")
+            }
+        }
+    }
+}
+
+/// A rejected grant means the refresh token is dead; anything else may pass.
+fn classify_refresh_failure(status: u16, body: &str) -> CodexRefreshError {
+    let detail: String = body.trim().chars().take(200).collect();
+    let detail = format!("HTTP {status}: {detail}");
+    if matches!(status, 400 | 401 | 403) {
+        CodexRefreshError::ReloginRequired(detail)
+    } else {
+        CodexRefreshError::Transient(detail)
+    }
+}
+
+/// Refresh the access token in `auth.json` when it expires within
+/// `ahead_secs`. Returns the expiry (unix seconds) of the token now on file,
+/// when it is a JWT with one.
+fn refresh_codex_tokens(ahead_secs: i64) -> Result<Option<i64>, CodexRefreshError> {
     let auth_path = default_codex_auth_path();
     if !auth_path.exists() {
-        return Ok(());
+        return Ok(None);
     }
     let content = std::fs::read_to_string(&auth_path).map_err(|e| {
-        MicroClawError::Config(format!(
-            "Failed to read Codex auth file {}: {e}",
-            auth_path.display()
-        ))
+        CodexRefreshError::Transient(format!("reading {}: {e}", auth_path.display()))
     })?;
     let mut parsed: serde_json::Value = serde_json::from_str(&content).map_err(|e| {
-        MicroClawError::Config(format!(
-            "Failed to parse Codex auth file {}: {e}",
-            auth_path.display()
-        ))
+        CodexRefreshError::Transient(format!("parsing {}: {e}", auth_path.display()))
     })?;
 
     let tokens = parsed
@@ -279,16 +322,26 @@ pub fn refresh_openai_codex_auth_if_needed() -> Result<(), MicroClawError> {
         .unwrap_or("")
         .trim()
         .to_string();
-    if access.is_empty() || refresh.is_empty() {
-        return Ok(());
+    if access.is_empty() {
+        return Ok(None);
     }
-    if !is_jwt_expired(&access) {
-        return Ok(());
+    let expires_at = jwt_expiry(&access);
+    let Some(exp) = expires_at else {
+        return Ok(None);
+    };
+    if chrono::Utc::now().timestamp() + ahead_secs < exp {
+        return Ok(expires_at);
+    }
+    if refresh.is_empty() {
+        return Err(CodexRefreshError::ReloginRequired(
+            "the access token expires and auth.json has no refresh token".into(),
+        ));
     }
 
     let client = reqwest::blocking::Client::builder()
         .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+        .build()
+        .map_err(|e| CodexRefreshError::Transient(e.to_string()))?;
     let body = serde_json::json!({
         "grant_type": "refresh_token",
         "refresh_token": refresh,
@@ -298,18 +351,22 @@ pub fn refresh_openai_codex_auth_if_needed() -> Result<(), MicroClawError> {
         .post("https://auth.openai.com/oauth/token")
         .header("content-type", "application/json")
         .body(body.to_string())
-        .send()?;
-    if !resp.status().is_success() {
-        return Ok(());
+        .send()
+        .map_err(|e| CodexRefreshError::Transient(e.to_string()))?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().unwrap_or_default();
+        return Err(classify_refresh_failure(status.as_u16(), &body));
     }
-    let parsed_resp: CodexRefreshResponse = resp.json().map_err(|e| {
-        MicroClawError::Config(format!(
-            "Failed to parse OpenAI Codex refresh response: {e}"
-        ))
-    })?;
+    let parsed_resp: CodexRefreshResponse = resp
+        .json()
+        .map_err(|e| CodexRefreshError::Transient(format!("parsing the refresh response: {e}")))?;
     if parsed_resp.access_token.trim().is_empty() {
-        return Ok(());
+        return Err(CodexRefreshError::Transient(
+            "the refresh response has no access token".into(),
+        ));
     }
+    let new_expiry = jwt_expiry(&parsed_resp.access_token);
 
     if let Some(tokens_obj) = parsed.get_mut("tokens").and_then(|t| t.as_object_mut()) {
         tokens_obj.insert(
@@ -326,19 +383,109 @@ pub fn refresh_openai_codex_auth_if_needed() -> Result<(), MicroClawError> {
         }
     }
     parsed["last_refresh"] = serde_json::Value::String(chrono::Utc::now().to_rfc3339());
-    std::fs::write(
-        &auth_path,
-        serde_json::to_string_pretty(&parsed).map_err(|e| {
-            MicroClawError::Config(format!("Failed to serialize refreshed Codex auth: {e}"))
-        })?,
-    )?;
-    Ok(())
+    let serialized = serde_json::to_string_pretty(&parsed)
+        .map_err(|e| CodexRefreshError::Transient(format!("serializing auth.json: {e}")))?;
+    std::fs::write(&auth_path, serialized).map_err(|e| {
+        CodexRefreshError::Transient(format!("writing {}: {e}", auth_path.display()))
+    })?;
+    Ok(new_expiry)
+}
+
+pub fn refresh_openai_codex_auth_if_needed() -> Result<(), MicroClawError> {
+    refresh_codex_tokens(0)
+        .map(|_| ())
+        .map_err(|e| MicroClawError::Config(e.to_string()))
+}
+
+/// How long to sleep before the next background check of a token expiring
+/// at `expires_at`.
+fn next_check_delay(expires_at: Option<i64>, now: i64) -> std::time::Duration {
+    let secs = expires_at
+        .map_or(MAX_CHECK_SECS, |exp| exp - REFRESH_AHEAD_SECS - now)
+        .clamp(MIN_CHECK_SECS, MAX_CHECK_SECS);
+    std::time::Duration::from_secs(secs as u64)
+}
+
+/// Backoff after `failures` failed refreshes in a row.
+fn retry_delay(failures: u32) -> std::time::Duration {
+    let secs = RETRY_BASE_SECS.saturating_mul(1 << failures.saturating_sub(1).min(10));
+    std::time::Duration::from_secs(secs.min(RETRY_MAX_SECS))
+}
+
+async fn alert_relogin_required(state: &AppState, reason: &str) {
+    let text = format!(
+        "⚠️ OpenAI Codex login expired and the token could not be refreshed ({reason}). Run `codex login` on the MicroClaw host; requests to openai-codex fail until then."
+    );
+    warn!("{text}");
+    for control_chat in &state.config.control_chat_ids {
+        if let Err(e) = crate::channel::deliver_and_store_bot_message(
+            &state.channel_registry,
+            state.db.clone(),
+            &state.config.bot_username,
+            *control_chat,
+            &text,
+        )
+        .await
+        {
+            warn!("Failed to send Codex login alert to chat {control_chat}: {e}");
+        }
+    }
+}
+
+/// Keep the Codex access token fresh while `openai-codex` is configured:
+/// refresh it ahead of expiry, retry failures with backoff, and alert the
+/// control chats once when the refresh token is dead.
+pub fn spawn_codex_refresh(state: Arc<AppState>) {
+    let config = &state.config;
+    let uses_codex = is_openai_codex_provider(&config.llm_provider)
+        || config
+            .llm_fallbacks
+            .iter()
+            .any(|f| is_openai_codex_provider(&f.provider));
+    let env_token =
+        std::env::var("OPENAI_CODEX_ACCESS_TOKEN").is_ok_and(|token| !token.trim().is_empty());
+    if !uses_codex || env_token {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut failures = 0u32;
+        let mut alerted = false;
+        loop {
+            let outcome = tokio::task::spawn_blocking(|| refresh_codex_tokens(REFRESH_AHEAD_SECS))
+                .await
+                .unwrap_or_else(|e| Err(CodexRefreshError::Transient(e.to_string())));
+            let delay = match outcome {
+                Ok(expires_at) => {
+                    if failures > 0 || alerted {
+                        info!("Codex token refresh recovered");
+                    }
+                    failures = 0;
+                    alerted = false;
+                    next_check_delay(expires_at, chrono::Utc::now().timestamp())
+                }
+                Err(e @ CodexRefreshError::Transient(_)) => {
+                    failures += 1;
+                    let delay = retry_delay(failures);
+                    warn!("{e}; retrying in {}s", delay.as_secs());
+                    delay
+                }
+                Err(CodexRefreshError::ReloginRequired(reason)) => {
+                    if !alerted {
+                        alert_relogin_required(&state, &reason).await;
+                        alerted = true;
+                    }
+                    std::time::Duration::from_secs(RETRY_MAX_SECS)
+                }
+            };
+            tokio::time::sleep(delay).await;
+        }
+    });
 }
 
-fn is_jwt_expired(token: &str) -> bool {
+fn jwt_expiry(token: &str) -> Option<i64> {
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() < 2 {
-        return false;
+        return None;
     }
     let mut payload = parts[1].to_string();
     while !payload.len().is_multiple_of(4) {
@@ -348,14 +495,10 @@ fn is_jwt_expired(token: &str) -> bool {
         .decode(payload.as_bytes())
         .ok()
         .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok());
-    let exp = decoded
+    decoded
         .as_ref()
         .and_then(|v| v.get("exp"))
-        .and_then(|v| v.as_i64());
-    match exp {
-        Some(ts) => chrono::Utc::now().timestamp() >= ts,
-        None => false,
-    }
+        .and_then(|v| v.as_i64())
 }
 
 #[cfg(test)]
@@ -374,6 +517,9 @@ mod tests {
     fn test_provider_allows_empty_api_key() {
         assert!(provider_allows_empty_api_key("ollama"));
         assert!(provider_allows_empty_api_key("openai-codex"));
+        assert!(provider_allows_empty_api_key("bedrock"));
+        assert!(provider_allows_empty_api_key("azure"));
+        assert!(provider_allows_empty_api_key("claude-oauth"));
         assert!(!provider_allows_empty_api_key("openai"));
     }
 
@@ -482,6 +628,33 @@ mod tests {
         assert!(auth.account_id.is_none());
     }
 
+    #[test]
+    fn test_refresh_schedule_and_failure_classes() {
+        let payload =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"exp":1900000000}"#);
+        assert_eq!(jwt_expiry(&format!("h.{payload}.s")), Some(1_900_000_000));
+        assert_eq!(jwt_expiry("opaque-token"), None);
+
+        let now = 1_900_000_000 - 7200;
+        assert_eq!(next_check_delay(Some(now + 1800), now).as_secs(), 1200);
+        assert_eq!(next_check_delay(Some(now + 600), now).as_secs(), 60);
+        assert_eq!(next_check_delay(Some(now + 86400), now).as_secs(), 3600);
+        assert_eq!(next_check_delay(None, now).as_secs(), 3600);
+
+        assert_eq!(retry_delay(1).as_secs(), 30);
+        assert_eq!(retry_delay(3).as_secs(), 120);
+        assert_eq!(retry_delay(40).as_secs(), 1800);
+
+        assert!(matches!(
+            classify_refresh_failure(400, r#"{"error":"invalid_grant"}"#),
+            CodexRefreshError::ReloginRequired(_)
+        ));
+        assert!(matches!(
+            classify_refresh_failure(503, "unavailable"),
+            CodexRefreshError::Transient(_)
+        ));
+    }
+
     #[test]
     fn test_parse_codex_config_default_openai_base_url() {
         let content = r#"
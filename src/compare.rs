@@ -0,0 +1,496 @@
+//! `/compare <model>`: replay the previous user turn against another model and
+//! record which answer the user prefers.
+//!
+//! Tools are not executed during the replay. Tool calls made by the second
+//! model are answered from the results recorded in the original turn, so
+//! both answers are based on the same observations.
+
+use std::collections::HashMap;
+
+use crate::agent_engine::{build_turn_system_prompt, message_to_text, strip_thinking};
+use crate::db::call_blocking;
+use crate::error::MicroClawError;
+use crate::llm::LlmProvider;
+use crate::llm_types::{
+    ContentBlock, Message, MessageContent, ResponseContentBlock, ToolDefinition,
+};
+use crate::runtime::AppState;
+
+const COMPARE_USAGE: &str =
+    "Usage: /compare <model> — replay your previous message with another model\n/compare stats — preference totals per model pair\n/prefer a|b|tie — record which answer was better";
+
+#[derive(Debug, Clone)]
+struct RecordedToolCall {
+    name: String,
+    input: serde_json::Value,
+    content: String,
+    is_error: bool,
+    used: bool,
+}
+
+#[derive(Debug)]
+struct LastTurn {
+    /// Conversation up to and including the replayed user message.
+    prompt_messages: Vec<Message>,
+    user_text: String,
+    original_answer: String,
+    recorded: Vec<RecordedToolCall>,
+}
+
+/// A message typed by the user, as opposed to a turn carrying tool results.
+pub(crate) fn is_user_turn(message: &Message) -> bool {
+    if message.role != "user" {
+        return false;
+    }
+    match &message.content {
+        MessageContent::Text(_) => true,
+        MessageContent::Blocks(blocks) => !blocks
+            .iter()
+            .any(|b| matches!(b, ContentBlock::ToolResult { .. })),
+    }
+}
+
+fn assistant_text(message: &Message) -> String {
+    let text = match &message.content {
+        MessageContent::Text(t) => t.clone(),
+        MessageContent::Blocks(blocks) => blocks
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    strip_thinking(&text)
+}
+
+fn extract_last_turn(messages: &[Message]) -> Option<LastTurn> {
+    let user_idx = messages.iter().rposition(is_user_turn)?;
+    let trace = &messages[user_idx + 1..];
+
+    let mut calls: HashMap<&str, (&str, &serde_json::Value)> = HashMap::new();
+    let mut recorded = Vec::new();
+    for message in trace {
+        let MessageContent::Blocks(blocks) = &message.content else {
+            continue;
+        };
+        for block in blocks {
+            match block {
+                ContentBlock::ToolUse { id, name, input } => {
+                    calls.insert(id, (name, input));
+                }
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    is_error,
+                } => {
+                    if let Some((name, input)) = calls.remove(tool_use_id.as_str()) {
+                        recorded.push(RecordedToolCall {
+                            name: name.to_string(),
+                            input: input.clone(),
+                            content: content.clone(),
+                            is_error: is_error.unwrap_or(false),
+                            used: false,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let original_answer = trace
+        .iter()
+        .rev()
+        .filter(|m| m.role == "assistant")
+        .map(assistant_text)
+        .find(|t| !t.is_empty())?;
+
+    Some(LastTurn {
+        prompt_messages: messages[..=user_idx].to_vec(),
+        user_text: message_to_text(&messages[user_idx]),
+        original_answer,
+        recorded,
+    })
+}
+
+/// Answer a replayed tool call from the recording: an unused call with the
+/// same name and input first, then any unused call to the same tool.
+fn lookup_recorded(
+    recorded: &mut [RecordedToolCall],
+    name: &str,
+    input: &serde_json::Value,
+) -> (String, bool) {
+    let idx = recorded
+        .iter()
+        .position(|c| !c.used && c.name == name && &c.input == input)
+        .or_else(|| recorded.iter().position(|c| !c.used && c.name == name));
+    match idx {
+        Some(i) => {
+            recorded[i].used = true;
+            (recorded[i].content.clone(), recorded[i].is_error)
+        }
+        None => (
+            format!(
+                "Tool not executed during comparison replay: the original turn made no `{name}` call. Answer with the information already available."
+            ),
+            true,
+        ),
+    }
+}
+
+/// Run the replay loop against `llm`. Returns the final visible text and the
+/// summed (input, output) token usage.
+async fn replay_turn(
+    llm: &dyn LlmProvider,
+    system_prompt: &str,
+    mut messages: Vec<Message>,
+    tools: &[ToolDefinition],
+    recorded: &mut [RecordedToolCall],
+    max_iterations: usize,
+) -> Result<(String, i64, i64), MicroClawError> {
+    let mut input_tokens = 0i64;
+    let mut output_tokens = 0i64;
+    for _ in 0..max_iterations.max(1) {
+        let response = llm
+            .send_message(system_prompt, messages.clone(), Some(tools.to_vec()))
+            .await?;
+        if let Some(usage) = &response.usage {
+            input_tokens += i64::from(usage.input_tokens);
+            output_tokens += i64::from(usage.output_tokens);
+        }
+
+        let mut assistant_blocks = Vec::new();
+        let mut tool_results = Vec::new();
+        let mut text_parts = Vec::new();
+        for block in response.content {
+            match block {
+                ResponseContentBlock::Text { text } => {
+                    text_parts.push(text.clone());
+                    assistant_blocks.push(ContentBlock::Text { text });
+                }
+                ResponseContentBlock::ToolUse { id, name, input } => {
+                    let (content, is_error) = lookup_recorded(recorded, &name, &input);
+                    tool_results.push(ContentBlock::ToolResult {
+                        tool_use_id: id.clone(),
+                        content,
+                        is_error: is_error.then_some(true),
+                    });
+                    assistant_blocks.push(ContentBlock::ToolUse { id, name, input });
+                }
+                ResponseContentBlock::Thinking {
+                    thinking,
+                    signature,
+                } => assistant_blocks.push(ContentBlock::Thinking {
+                    thinking,
+                    signature,
+                }),
+                ResponseContentBlock::RedactedThinking { data } => {
+                    assistant_blocks.push(ContentBlock::RedactedThinking { data })
+                }
+            }
+        }
+
+        if tool_results.is_empty() || response.stop_reason.as_deref() != Some("tool_use") {
+            return Ok((
+                strip_thinking(&text_parts.join("\n")),
+                input_tokens,
+                output_tokens,
+            ));
+        }
+        messages.push(Message {
+            role: "assistant".into(),
+            content: MessageContent::Blocks(assistant_blocks),
+        });
+        messages.push(Message {
+            role: "user".into(),
+            content: MessageContent::Blocks(tool_results),
+        });
+    }
+    Ok((
+        format!("(stopped after {max_iterations} replayed tool iterations)"),
+        input_tokens,
+        output_tokens,
+    ))
+}
+
+fn format_comparison(model_a: &str, answer_a: &str, model_b: &str, answer_b: &str) -> String {
+    let answer_b = if answer_b.trim().is_empty() {
+        "(empty answer)"
+    } else {
+        answer_b
+    };
+    format!(
+        "[A] {model_a} (original)\n{answer_a}\n\n[B] {model_b}\n{answer_b}\n\nReply /prefer a, /prefer b or /prefer tie to record which answer was better."
+    )
+}
+
+async fn run_compare(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    model: &str,
+) -> Result<String, MicroClawError> {
+    if let Some(refusal) = crate::budget::check_chat_budget(state, chat_id).await {
+        return Ok(refusal);
+    }
+    let messages: Vec<Message> =
+        match call_blocking(state.db.clone(), move |db| db.load_session(chat_id)).await? {
+            Some((json, _)) => serde_json::from_str(&json).unwrap_or_default(),
+            None => Vec::new(),
+        };
+    let Some(mut turn) = extract_last_turn(&messages) else {
+        return Ok("Nothing to compare yet: no previous answered message in this chat.".into());
+    };
+
+    let query: String = turn.user_text.chars().take(500).collect();
+    let system_prompt = build_turn_system_prompt(state, caller_channel, chat_id, &query).await;
+    let mut alt_config = state.config.clone();
+    alt_config.model = model.to_string();
+    let llm = crate::llm::create_provider(&alt_config);
+    let (answer_b, input_tokens, output_tokens) = replay_turn(
+        llm.as_ref(),
+        &system_prompt,
+        turn.prompt_messages.clone(),
+        state.tools.definitions(),
+        &mut turn.recorded,
+        state.config.max_tool_iterations,
+    )
+    .await?;
+
+    let channel = caller_channel.to_string();
+    let provider = state.config.llm_provider.clone();
+    let model_a = state.config.model.clone();
+    let model_b = model.to_string();
+    let prompt = turn.user_text.clone();
+    let answer_a = turn.original_answer.clone();
+    let answer_b_for_db = answer_b.clone();
+    call_blocking(state.db.clone(), move |db| {
+        db.log_llm_usage(
+            chat_id,
+            &channel,
+            &provider,
+            &model_b,
+            input_tokens,
+            output_tokens,
+            "compare",
+        )?;
+        db.insert_model_comparison(
+            chat_id,
+            &channel,
+            &prompt,
+            &model_a,
+            &answer_a,
+            &model_b,
+            &answer_b_for_db,
+        )
+    })
+    .await?;
+
+    Ok(format_comparison(
+        &state.config.model,
+        &turn.original_answer,
+        model,
+        &answer_b,
+    ))
+}
+
+async fn comparison_stats(state: &AppState) -> Result<String, MicroClawError> {
+    let stats = call_blocking(state.db.clone(), |db| db.get_model_comparison_stats()).await?;
+    if stats.is_empty() {
+        return Ok("No model comparisons recorded yet.".into());
+    }
+    let mut lines = vec!["Model comparisons (A = original, B = replay):".to_string()];
+    for s in stats {
+        lines.push(format!(
+            "- {} vs {}: A {} / B {} / tie {} / undecided {}",
+            s.model_a, s.model_b, s.a_wins, s.b_wins, s.ties, s.undecided
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Handle `/compare ...` and `/prefer ...`. Returns `None` for other text.
+pub async fn handle_compare_command(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    text: &str,
+) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    let command = parts.next()?;
+    let arg = parts.next().unwrap_or("").trim();
+    let reply = match command {
+        "/compare" if arg.is_empty() => Ok(COMPARE_USAGE.to_string()),
+        "/compare" if arg == "stats" => comparison_stats(state).await,
+        "/compare" => run_compare(state, caller_channel, chat_id, arg).await,
+        "/prefer" => {
+            let choice = arg.to_ascii_lowercase();
+            if !matches!(choice.as_str(), "a" | "b" | "tie") {
+                return Some(COMPARE_USAGE.to_string());
+            }
+            call_blocking(state.db.clone(), move |db| {
+                db.set_latest_comparison_preference(chat_id, &choice)
+            })
+            .await
+            .map(|decided| match decided {
+                Some(c) => match c.preferred.as_deref() {
+                    Some("a") => format!("Recorded: {} preferred over {}.", c.model_a, c.model_b),
+                    Some("b") => format!("Recorded: {} preferred over {}.", c.model_b, c.model_a),
+                    _ => format!("Recorded a tie between {} and {}.", c.model_a, c.model_b),
+                },
+                None => "No comparison to rate. Run /compare <model> first.".to_string(),
+            })
+        }
+        _ => return None,
+    };
+    Some(reply.unwrap_or_else(|e| format!("Comparison failed: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::MessagesResponse;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn text(role: &str, t: &str) -> Message {
+        Message {
+            role: role.into(),
+            content: MessageContent::Text(t.into()),
+        }
+    }
+
+    fn blocks(role: &str, blocks: Vec<ContentBlock>) -> Message {
+        Message {
+            role: role.into(),
+            content: MessageContent::Blocks(blocks),
+        }
+    }
+
+    fn recorded_session() -> Vec<Message> {
+        vec![
+            text("user", "earlier question"),
+            text("assistant", "earlier answer"),
+            text("user", "what is in /tmp?"),
+            blocks(
+                "assistant",
+                vec![ContentBlock::ToolUse {
+                    id: "t1".into(),
+                    name: "bash".into(),
+                    input: json!({"command": "ls /tmp"}),
+                }],
+            ),
+            blocks(
+                "user",
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".into(),
+                    content: "a.txt".into(),
+                    is_error: None,
+                }],
+            ),
+            text("assistant", "<think>hmm</think>There is a.txt"),
+        ]
+    }
+
+    #[test]
+    fn test_extract_last_turn_collects_trace() {
+        let turn = extract_last_turn(&recorded_session()).unwrap();
+        assert_eq!(turn.prompt_messages.len(), 3);
+        assert_eq!(turn.user_text, "what is in /tmp?");
+        assert_eq!(turn.original_answer, "There is a.txt");
+        assert_eq!(turn.recorded.len(), 1);
+        assert_eq!(turn.recorded[0].content, "a.txt");
+
+        assert!(extract_last_turn(&[text("user", "unanswered")]).is_none());
+    }
+
+    #[test]
+    fn test_lookup_recorded_prefers_exact_then_same_tool() {
+        let mut turn = extract_last_turn(&recorded_session()).unwrap();
+        let (content, is_error) = lookup_recorded(
+            &mut turn.recorded,
+            "bash",
+            &json!({"command": "ls -la /tmp"}),
+        );
+        assert_eq!(content, "a.txt");
+        assert!(!is_error);
+        let (_, is_error) = lookup_recorded(&mut turn.recorded, "bash", &json!({}));
+        assert!(is_error);
+        let (_, is_error) = lookup_recorded(&mut turn.recorded, "web_fetch", &json!({}));
+        assert!(is_error);
+    }
+
+    struct ScriptedLlm {
+        responses: Mutex<Vec<MessagesResponse>>,
+        seen_tool_results: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ScriptedLlm {
+        async fn send_message(
+            &self,
+            _system: &str,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            if let Some(MessageContent::Blocks(blocks)) = messages.last().map(|m| &m.content) {
+                for b in blocks {
+                    if let ContentBlock::ToolResult { content, .. } = b {
+                        self.seen_tool_results.lock().unwrap().push(content.clone());
+                    }
+                }
+            }
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_turn_feeds_recorded_tool_results() {
+        let llm = ScriptedLlm {
+            responses: Mutex::new(vec![
+                MessagesResponse {
+                    content: vec![ResponseContentBlock::ToolUse {
+                        id: "x1".into(),
+                        name: "bash".into(),
+                        input: json!({"command": "ls /tmp"}),
+                    }],
+                    stop_reason: Some("tool_use".into()),
+                    usage: None,
+                    served_by: None,
+                },
+                MessagesResponse {
+                    content: vec![ResponseContentBlock::Text {
+                        text: "/tmp has a.txt".into(),
+                    }],
+                    stop_reason: Some("end_turn".into()),
+                    usage: None,
+                    served_by: None,
+                },
+            ]),
+            seen_tool_results: Mutex::new(Vec::new()),
+        };
+        let mut turn = extract_last_turn(&recorded_session()).unwrap();
+        let (answer, _, _) = replay_turn(
+            &llm,
+            "system",
+            turn.prompt_messages.clone(),
+            &[],
+            &mut turn.recorded,
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer, "/tmp has a.txt");
+        assert_eq!(*llm.seen_tool_results.lock().unwrap(), vec!["a.txt"]);
+    }
+
+    #[test]
+    fn test_format_comparison_labels_models() {
+        let out = format_comparison("m1", "one", "m2", "");
+        assert!(out.contains("[A] m1 (original)\none"));
+        assert!(out.contains("[B] m2\n(empty answer)"));
+        assert!(out.contains("/prefer a"));
+    }
+}
@@ -1,9 +1,12 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use crate::claude_auth::{
+    claude_credentials_available, is_claude_oauth_provider, CLAUDE_OAUTH_TOKEN_ENV,
+};
 use crate::codex_auth::{
     codex_auth_file_has_access_token, is_openai_codex_provider, provider_allows_empty_api_key,
 };
@@ -48,11 +51,26 @@ fn default_working_dir() -> String {
 fn default_working_dir_isolation() -> WorkingDirIsolation {
     WorkingDirIsolation::Chat
 }
+fn default_workspace_quota_mb() -> u64 {
+    0
+}
+fn default_workspace_export_max_mb() -> u64 {
+    50
+}
+fn default_file_preview_lines() -> usize {
+    12
+}
 fn default_timezone() -> String {
     "UTC".into()
 }
+fn default_language() -> String {
+    "en".into()
+}
 fn default_max_session_messages() -> usize {
-    40
+    0
+}
+fn default_context_compact_ratio() -> f64 {
+    0.7
 }
 fn default_compact_keep_recent() -> usize {
     20
@@ -84,10 +102,100 @@ fn default_web_run_history_limit() -> usize {
 fn default_web_session_idle_ttl_seconds() -> u64 {
     300
 }
+fn default_web_login_ttl_hours() -> u64 {
+    24 * 7
+}
 
 fn default_model_prices() -> Vec<ModelPrice> {
     Vec::new()
 }
+fn default_pricing_refresh_hours() -> u64 {
+    24
+}
+fn default_llm_fallback_timeout_secs() -> u64 {
+    120
+}
+fn default_llm_max_retries() -> u32 {
+    3
+}
+fn default_metrics_listen() -> String {
+    "127.0.0.1:9464".into()
+}
+fn default_otel_endpoint() -> String {
+    "http://localhost:4318/v1/traces".into()
+}
+fn default_otel_service_name() -> String {
+    "microclaw".into()
+}
+fn default_retention_interval_hours() -> u64 {
+    24
+}
+fn default_knowledge_chunk_chars() -> usize {
+    1200
+}
+fn default_knowledge_chunk_overlap() -> usize {
+    200
+}
+fn default_knowledge_top_k() -> usize {
+    5
+}
+fn default_knowledge_scan_interval_secs() -> u64 {
+    60
+}
+fn default_calendar_id() -> String {
+    "primary".into()
+}
+fn default_smtp_port() -> u16 {
+    465
+}
+fn default_feeds_poll_interval_mins() -> u64 {
+    30
+}
+fn default_feeds_max_items() -> usize {
+    5
+}
+fn default_feeds_summarize() -> bool {
+    true
+}
+fn default_heartbeat_interval_mins() -> u64 {
+    120
+}
+fn default_heartbeat_max_per_day() -> u32 {
+    3
+}
+fn default_heartbeat_idle_mins() -> u64 {
+    30
+}
+fn default_memory_consolidation_hour() -> u32 {
+    3
+}
+fn default_memory_consolidation_min_memories() -> usize {
+    10
+}
+fn default_wire_log_max_file_mb() -> u64 {
+    10
+}
+fn default_wire_log_max_files() -> usize {
+    5
+}
+fn default_stream_replies() -> bool {
+    true
+}
+fn default_progress_status() -> bool {
+    true
+}
+fn default_max_repeated_tool_failures() -> usize {
+    2
+}
+fn default_parallel_tools_enabled() -> bool {
+    true
+}
+fn default_parallel_tools_max_concurrent() -> usize {
+    4
+}
+fn default_turn_queue_max_concurrent() -> usize {
+    8
+}
 fn default_reflector_enabled() -> bool {
     true
 }
@@ -100,16 +208,171 @@ fn default_soul_path() -> Option<String> {
 fn default_skip_tool_approval() -> bool {
     false
 }
+fn default_block_private_networks() -> bool {
+    true
+}
 fn is_local_web_host(host: &str) -> bool {
     let h = host.trim().to_ascii_lowercase();
     h == "127.0.0.1" || h == "localhost" || h == "::1"
 }
 
+/// Log line format (`log_format`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line; see `logging::JsonFormat`.
+    Json,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WorkingDirIsolation {
     Shared,
     Chat,
+    /// Private directory per (channel, user), shared by that user's chats on
+    /// the channel.
+    #[serde(alias = "per_user")]
+    User,
+    /// Directory per named topic (`/workspace topic <name>`) inside the chat workspace.
+    #[serde(alias = "per_topic", alias = "thread")]
+    Topic,
+    /// Fresh directory per conversation session; `/reset` starts a new one.
+    #[serde(alias = "per_session")]
+    Session,
+}
+
+impl WorkingDirIsolation {
+    pub const ALL: [WorkingDirIsolation; 5] = [
+        WorkingDirIsolation::Shared,
+        WorkingDirIsolation::Chat,
+        WorkingDirIsolation::User,
+        WorkingDirIsolation::Topic,
+        WorkingDirIsolation::Session,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkingDirIsolation::Shared => "shared",
+            WorkingDirIsolation::Chat => "chat",
+            WorkingDirIsolation::User => "user",
+            WorkingDirIsolation::Topic => "topic",
+            WorkingDirIsolation::Session => "session",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        let value = value.strip_prefix("per_").unwrap_or(&value);
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == value)
+            .or_else(|| (value == "thread").then_some(WorkingDirIsolation::Topic))
+    }
+}
+
+/// Where the `bash` tool runs commands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BashBackend {
+    /// Directly on the host, in the chat's working directory.
+    #[default]
+    Host,
+    /// Inside a long-lived podman/docker container per workspace, with the
+    /// working directory bind-mounted; see `BashContainerConfig`.
+    Container,
+}
+
+/// Settings for `bash_backend: container`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BashContainerConfig {
+    /// `podman` or `docker` (or a path to either); empty picks whichever is
+    /// on PATH, preferring podman.
+    #[serde(default)]
+    pub runtime: String,
+    /// Image the containers are created from; it must provide `bash`.
+    #[serde(default = "default_bash_container_image")]
+    pub image: String,
+    /// Give containers the runtime's default network. Off means `--network none`.
+    #[serde(default)]
+    pub network: bool,
+    /// Extra arguments for `<runtime> run` (e.g. `["--memory", "1g"]`).
+    #[serde(default)]
+    pub run_args: Vec<String>,
+}
+
+impl Default for BashContainerConfig {
+    fn default() -> Self {
+        Self {
+            runtime: String::new(),
+            image: default_bash_container_image(),
+            network: false,
+            run_args: Vec::new(),
+        }
+    }
+}
+
+fn default_bash_container_image() -> String {
+    "debian:bookworm-slim".into()
+}
+
+/// Network restrictions applied to a chat by the outbound network policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkPosture {
+    /// Only `deny_domains` applies; private addresses are reachable.
+    Open,
+    /// Domain lists plus the private-address guard.
+    #[default]
+    Standard,
+    /// Only hosts on `allow_domains`; private addresses are always blocked.
+    Strict,
+}
+
+impl NetworkPosture {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetworkPosture::Open => "open",
+            NetworkPosture::Standard => "standard",
+            NetworkPosture::Strict => "strict",
+        }
+    }
+}
+
+/// Outbound HTTP policy for `web_fetch`, `browser` and (through a local
+/// proxy) `bash`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPolicyConfig {
+    #[serde(default)]
+    pub posture: NetworkPosture,
+    /// Per-chat posture overrides keyed by internal chat id.
+    #[serde(default)]
+    pub chat_postures: HashMap<i64, NetworkPosture>,
+    /// When non-empty, only these domains (and their subdomains) are reachable.
+    #[serde(default)]
+    pub allow_domains: Vec<String>,
+    #[serde(default)]
+    pub deny_domains: Vec<String>,
+    /// SSRF guard: refuse loopback, RFC 1918, link-local and other private targets.
+    #[serde(default = "default_block_private_networks")]
+    pub block_private_networks: bool,
+    /// Point `bash` at a local filtering proxy via `HTTP(S)_PROXY`.
+    #[serde(default)]
+    pub bash_proxy: bool,
+}
+
+impl Default for NetworkPolicyConfig {
+    fn default() -> Self {
+        Self {
+            posture: NetworkPosture::default(),
+            chat_postures: HashMap::new(),
+            allow_domains: Vec::new(),
+            deny_domains: Vec::new(),
+            block_private_networks: default_block_private_networks(),
+            bash_proxy: false,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -117,147 +380,1362 @@ pub struct ModelPrice {
     pub model: String,
     pub input_per_million_usd: f64,
     pub output_per_million_usd: f64,
+    /// Prompt-cache rates. Accepted so published price lists load as-is;
+    /// usage rows don't record cache tokens, so estimates don't use them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_million_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_million_usd: Option<f64>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Config {
-    // --- LLM / API ---
-    #[serde(default = "default_llm_provider")]
-    pub llm_provider: String,
-    #[serde(default = "default_api_key")]
-    pub api_key: String,
-    #[serde(default = "default_model")]
-    pub model: String,
+impl ModelPrice {
+    /// Trim the model name and check that every rate is a non-negative number.
+    pub fn normalize(&mut self) -> Result<(), String> {
+        self.model = self.model.trim().to_string();
+        if self.model.is_empty() {
+            return Err("model_prices entries must include non-empty model".into());
+        }
+        for (field, rate) in [
+            ("input_per_million_usd", Some(self.input_per_million_usd)),
+            ("output_per_million_usd", Some(self.output_per_million_usd)),
+            (
+                "cache_read_per_million_usd",
+                self.cache_read_per_million_usd,
+            ),
+            (
+                "cache_write_per_million_usd",
+                self.cache_write_per_million_usd,
+            ),
+        ] {
+            if rate.is_some_and(|r| !(r.is_finite() && r >= 0.0)) {
+                return Err(format!("model_prices[{}].{field} must be >= 0", self.model));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Token and USD spending limits for a chat. Unset limits don't apply; days
+/// and months follow `timezone`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatBudget {
     #[serde(default)]
-    pub llm_base_url: Option<String>,
-    #[serde(default = "default_max_tokens")]
-    pub max_tokens: u32,
-    #[serde(default = "default_max_tool_iterations")]
-    pub max_tool_iterations: usize,
-    #[serde(default = "default_max_history_messages")]
-    pub max_history_messages: usize,
-    #[serde(default = "default_max_document_size_mb")]
-    pub max_document_size_mb: u64,
-    #[serde(default = "default_memory_token_budget")]
-    pub memory_token_budget: usize,
-    #[serde(default = "default_max_session_messages")]
-    pub max_session_messages: usize,
-    #[serde(default = "default_compact_keep_recent")]
-    pub compact_keep_recent: usize,
+    pub daily_tokens: Option<i64>,
     #[serde(default)]
-    pub show_thinking: bool,
+    pub monthly_tokens: Option<i64>,
+    /// Estimated with `model_prices`.
+    #[serde(default)]
+    pub daily_usd: Option<f64>,
+    #[serde(default)]
+    pub monthly_usd: Option<f64>,
+    /// Soft limits: reaching one warns the control chats once per period
+    /// but does not refuse turns.
+    #[serde(default)]
+    pub warn_daily_usd: Option<f64>,
+    #[serde(default)]
+    pub warn_monthly_usd: Option<f64>,
+}
 
-    // --- Paths & environment ---
-    #[serde(default = "default_data_dir")]
-    pub data_dir: String,
-    #[serde(default = "default_working_dir")]
-    pub working_dir: String,
-    #[serde(default = "default_working_dir_isolation")]
-    pub working_dir_isolation: WorkingDirIsolation,
-    #[serde(default = "default_timezone")]
-    pub timezone: String,
-    #[serde(default = "default_control_chat_ids")]
-    pub control_chat_ids: Vec<i64>,
+impl ChatBudget {
+    pub fn is_unlimited(&self) -> bool {
+        self.daily_tokens.is_none()
+            && self.monthly_tokens.is_none()
+            && self.daily_usd.is_none()
+            && self.monthly_usd.is_none()
+            && self.warn_daily_usd.is_none()
+            && self.warn_monthly_usd.is_none()
+    }
 
-    // --- Web UI ---
-    #[serde(default = "default_web_enabled")]
-    pub web_enabled: bool,
-    #[serde(default = "default_web_host")]
-    pub web_host: String,
-    #[serde(default = "default_web_port")]
-    pub web_port: u16,
-    #[serde(default)]
-    pub web_auth_token: Option<String>,
-    #[serde(default = "default_web_max_inflight_per_session")]
-    pub web_max_inflight_per_session: usize,
-    #[serde(default = "default_web_max_requests_per_window")]
-    pub web_max_requests_per_window: usize,
-    #[serde(default = "default_web_rate_window_seconds")]
-    pub web_rate_window_seconds: u64,
-    #[serde(default = "default_web_run_history_limit")]
-    pub web_run_history_limit: usize,
-    #[serde(default = "default_web_session_idle_ttl_seconds")]
-    pub web_session_idle_ttl_seconds: u64,
+    /// Limits set here, falling back to `base` for unset ones.
+    pub fn or(&self, base: &ChatBudget) -> ChatBudget {
+        ChatBudget {
+            daily_tokens: self.daily_tokens.or(base.daily_tokens),
+            monthly_tokens: self.monthly_tokens.or(base.monthly_tokens),
+            daily_usd: self.daily_usd.or(base.daily_usd),
+            monthly_usd: self.monthly_usd.or(base.monthly_usd),
+            warn_daily_usd: self.warn_daily_usd.or(base.warn_daily_usd),
+            warn_monthly_usd: self.warn_monthly_usd.or(base.warn_monthly_usd),
+        }
+    }
+}
 
-    // --- Embedding ---
+/// Tool allow/deny lists for one channel. An empty `allow` permits every tool;
+/// `deny` always wins.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicy {
     #[serde(default)]
-    pub embedding_provider: Option<String>,
+    pub allow: Vec<String>,
     #[serde(default)]
-    pub embedding_api_key: Option<String>,
+    pub deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    pub fn permits(&self, tool_name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|t| t == tool_name))
+            && !self.deny.iter().any(|t| t == tool_name)
+    }
+}
+
+/// A named persona a chat can switch to with `/persona <name>`. Unset fields
+/// keep the chat's usual settings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersonaConfig {
+    /// Appended to the system prompt while the persona is active.
     #[serde(default)]
-    pub embedding_base_url: Option<String>,
+    pub system_prompt: String,
     #[serde(default)]
-    pub embedding_model: Option<String>,
+    pub model: Option<String>,
     #[serde(default)]
-    pub embedding_dim: Option<usize>,
+    pub temperature: Option<f64>,
+    /// Tools the persona may use; empty = every tool the channel allows.
     #[serde(default)]
-    pub openai_api_key: Option<String>,
+    pub tools: Vec<String>,
+    /// Sent when a chat switches to the persona.
+    #[serde(default)]
+    pub greeting: Option<String>,
+}
 
-    // --- Pricing ---
-    #[serde(default = "default_model_prices")]
-    pub model_prices: Vec<ModelPrice>,
+impl PersonaConfig {
+    pub fn permits(&self, tool_name: &str) -> bool {
+        self.tools.is_empty() || self.tools.iter().any(|t| t == tool_name)
+    }
+}
 
-    // --- Reflector ---
-    #[serde(default = "default_reflector_enabled")]
-    pub reflector_enabled: bool,
-    #[serde(default = "default_reflector_interval_mins")]
-    pub reflector_interval_mins: u64,
+/// Settings a `channels.<name>` section may override; unset fields inherit
+/// the top-level value. Read alongside the adapter's own channel config.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelOverrides {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub max_tool_iterations: Option<usize>,
+    #[serde(default)]
+    pub tool_policy: ToolPolicy,
+    #[serde(default)]
+    pub progress_status: Option<bool>,
+}
 
-    // --- Soul ---
-    /// Path to a SOUL.md file that defines the bot's personality, voice, and values.
-    /// If not set, looks for SOUL.md in data_dir root, then current directory.
-    #[serde(default = "default_soul_path")]
-    pub soul_path: Option<String>,
+impl ChannelOverrides {
+    /// Whether this channel needs its own LLM client.
+    pub fn changes_llm(&self) -> bool {
+        self.model.is_some() || self.max_tokens.is_some()
+    }
+}
 
-    /// Skip the high-risk tool approval loop (e.g. for bash).
-    /// Useful when running inside an isolated environment like Firecracker.
-    /// Can also be set via MICROCLAW_SKIP_TOOL_APPROVAL=true env var.
-    #[serde(default = "default_skip_tool_approval")]
-    pub skip_tool_approval: bool,
+/// A model tried, in order, when the configured one is rate limited, fails
+/// with a server error or times out.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LlmFallback {
+    pub provider: String,
+    pub model: String,
+    /// Defaults to `api_key` when `provider` is the same as `llm_provider`.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Defaults to `llm_base_url` when `provider` is the same as `llm_provider`.
+    #[serde(default)]
+    pub llm_base_url: Option<String>,
+}
 
-    // --- Channel registry (new dynamic config) ---
-    /// Per-channel configuration. Keys are channel names (e.g. "telegram", "discord", "slack", "web").
-    /// Each value is channel-specific config deserialized by the adapter.
-    /// If empty, synthesized from legacy flat fields below in post_deserialize().
+/// Optional routing of each turn to a small or large model, decided by a
+/// cheap classifier model. All three use `llm_provider`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelRouterConfig {
     #[serde(default)]
-    pub channels: HashMap<String, serde_yaml::Value>,
+    pub enabled: bool,
+    /// Model that labels each turn as simple or complex.
+    #[serde(default)]
+    pub classifier_model: String,
+    /// Model for short, factual turns.
+    #[serde(default)]
+    pub small_model: String,
+    /// Model for multi-step or tool-heavy turns; defaults to `model`.
+    #[serde(default)]
+    pub large_model: Option<String>,
+}
 
-    // --- Legacy channel fields (deprecated, use `channels:` instead) ---
-    #[serde(default = "default_telegram_bot_token")]
-    pub telegram_bot_token: String,
-    #[serde(default = "default_bot_username")]
-    pub bot_username: String,
+/// Extended thinking / reasoning. `budget_tokens` turns on Anthropic extended
+/// thinking; `reasoning_effort` is sent to OpenAI-compatible reasoning models.
+/// Chats can pick another level with `/thinking`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThinkingConfig {
+    /// Anthropic thinking budget in tokens (0 = off, otherwise at least 1024).
+    /// It is added on top of `max_tokens`.
     #[serde(default)]
-    pub allowed_groups: Vec<i64>,
+    pub budget_tokens: u32,
+    /// `minimal`, `low`, `medium` or `high`.
     #[serde(default)]
-    pub discord_bot_token: Option<String>,
+    pub reasoning_effort: Option<String>,
+}
+
+/// Opt-in log of every LLM request and response body, written as JSON lines
+/// to `<data_dir>/runtime/logs/llm-wire.jsonl` and rotated by size. Known
+/// secrets, common key formats and `redact_patterns` are replaced with
+/// `[REDACTED]`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireLogConfig {
     #[serde(default)]
-    pub discord_allowed_channels: Vec<u64>,
+    pub enabled: bool,
+    /// Rotate the file once it reaches this size.
+    #[serde(default = "default_wire_log_max_file_mb")]
+    pub max_file_mb: u64,
+    /// Files kept, including the current one.
+    #[serde(default = "default_wire_log_max_files")]
+    pub max_files: usize,
+    /// Extra regular expressions whose matches are redacted.
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
 }
 
-impl Config {
-    /// Data root directory from config.
-    pub fn data_root_dir(&self) -> PathBuf {
-        PathBuf::from(&self.data_dir)
+impl Default for WireLogConfig {
+    fn default() -> Self {
+        WireLogConfig {
+            enabled: false,
+            max_file_mb: default_wire_log_max_file_mb(),
+            max_files: default_wire_log_max_files(),
+            redact_patterns: Vec::new(),
+        }
     }
+}
 
-    /// Runtime data directory (db, memory, exports, etc.).
-    pub fn runtime_data_dir(&self) -> String {
-        self.data_root_dir()
-            .join("runtime")
-            .to_string_lossy()
-            .to_string()
+/// Automatic pruning of old data (see `retention.rs`). Every limit is off
+/// (0) unless set.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Delete stored chat messages older than this many days.
+    #[serde(default)]
+    pub messages_days: u64,
+    /// Delete scheduled task run history older than this many days.
+    #[serde(default)]
+    pub task_runs_days: u64,
+    /// Delete completed and cancelled tasks (with their run history) whose
+    /// last run is older than this many days.
+    #[serde(default)]
+    pub finished_tasks_days: u64,
+    /// Keep at most this many rows in each memory audit log (injection logs
+    /// and reflector runs).
+    #[serde(default)]
+    pub audit_log_max_rows: u64,
+    /// Run `VACUUM` after a pass that removed rows, to shrink the file.
+    #[serde(default)]
+    pub vacuum: bool,
+    #[serde(default = "default_retention_interval_hours")]
+    pub interval_hours: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            messages_days: 0,
+            task_runs_days: 0,
+            finished_tasks_days: 0,
+            audit_log_max_rows: 0,
+            vacuum: false,
+            interval_hours: default_retention_interval_hours(),
+        }
     }
+}
 
-    /// Skills directory under data root.
-    pub fn skills_data_dir(&self) -> String {
-        self.data_root_dir()
-            .join("skills")
-            .to_string_lossy()
-            .to_string()
+impl RetentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.messages_days > 0
+            || self.task_runs_days > 0
+            || self.finished_tasks_days > 0
+            || self.audit_log_max_rows > 0
     }
+}
 
-    pub fn resolve_config_path() -> Result<Option<PathBuf>, MicroClawError> {
+/// Prometheus metrics endpoint (see `metrics.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Serve `GET /metrics` on `listen`.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_metrics_listen")]
+    pub listen: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            enabled: false,
+            listen: default_metrics_listen(),
+        }
+    }
+}
+
+/// Document retrieval (see `knowledge.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnowledgeConfig {
+    /// Index `dir` in the background and offer the `ingest` and `retrieve`
+    /// tools.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Folder whose documents are indexed; default `<data_dir>/knowledge`.
+    #[serde(default)]
+    pub dir: String,
+    /// Target chunk size, in characters.
+    #[serde(default = "default_knowledge_chunk_chars")]
+    pub chunk_chars: usize,
+    /// Characters repeated between consecutive chunks.
+    #[serde(default = "default_knowledge_chunk_overlap")]
+    pub chunk_overlap: usize,
+    /// Chunks `retrieve` returns unless the call asks for another count.
+    #[serde(default = "default_knowledge_top_k")]
+    pub top_k: usize,
+    #[serde(default = "default_knowledge_scan_interval_secs")]
+    pub scan_interval_secs: u64,
+}
+
+impl Default for KnowledgeConfig {
+    fn default() -> Self {
+        KnowledgeConfig {
+            enabled: false,
+            dir: String::new(),
+            chunk_chars: default_knowledge_chunk_chars(),
+            chunk_overlap: default_knowledge_chunk_overlap(),
+            top_k: default_knowledge_top_k(),
+            scan_interval_secs: default_knowledge_scan_interval_secs(),
+        }
+    }
+}
+
+/// Calendar access for the `calendar` tool (see `calendar.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarConfig {
+    /// `caldav` or `google`; unset disables the tool.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// CalDAV calendar collection URL, e.g.
+    /// `https://cloud.example.com/remote.php/dav/calendars/me/personal/`.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Google OAuth client; `refresh_token` must carry the
+    /// `https://www.googleapis.com/auth/calendar.events` scope.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default = "default_calendar_id")]
+    pub calendar_id: String,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        CalendarConfig {
+            provider: None,
+            url: None,
+            username: None,
+            password: None,
+            client_id: None,
+            client_secret: None,
+            refresh_token: None,
+            calendar_id: default_calendar_id(),
+        }
+    }
+}
+
+/// Proactive check-ins (see `heartbeat.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Minutes between reviews of each chat.
+    #[serde(default = "default_heartbeat_interval_mins")]
+    pub interval_mins: u64,
+    /// Chats to check in with; empty means `control_chat_ids`.
+    #[serde(default)]
+    pub chat_ids: Vec<i64>,
+    /// `HH:MM-HH:MM` in `timezone` during which no check-ins are sent, e.g.
+    /// `22:00-08:00`.
+    #[serde(default)]
+    pub quiet_hours: Option<String>,
+    /// Check-in messages per chat and day.
+    #[serde(default = "default_heartbeat_max_per_day")]
+    pub max_per_day: u32,
+    /// Skip chats with a message in the last this many minutes.
+    #[serde(default = "default_heartbeat_idle_mins")]
+    pub idle_mins: u64,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            enabled: false,
+            interval_mins: default_heartbeat_interval_mins(),
+            chat_ids: Vec::new(),
+            quiet_hours: None,
+            max_per_day: default_heartbeat_max_per_day(),
+            idle_mins: default_heartbeat_idle_mins(),
+        }
+    }
+}
+
+/// Web UI login (see `web/auth.rs`). With neither `users` nor `oidc`, the
+/// web channel only knows `web_auth_token`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebAuthConfig {
+    /// Password logins.
+    #[serde(default)]
+    pub users: Vec<WebUserConfig>,
+    /// Single sign-on through an OpenID Connect provider.
+    #[serde(default)]
+    pub oidc: Option<WebOidcConfig>,
+    /// Hours a login stays valid.
+    #[serde(default = "default_web_login_ttl_hours")]
+    pub session_ttl_hours: u64,
+    /// Mark the session cookie `Secure`; set it when the UI is served over
+    /// HTTPS.
+    #[serde(default)]
+    pub secure_cookie: bool,
+}
+
+impl Default for WebAuthConfig {
+    fn default() -> Self {
+        WebAuthConfig {
+            users: Vec::new(),
+            oidc: None,
+            session_ttl_hours: default_web_login_ttl_hours(),
+            secure_cookie: false,
+        }
+    }
+}
+
+impl WebAuthConfig {
+    /// Whether browsers have to sign in.
+    pub fn login_enabled(&self) -> bool {
+        !self.users.is_empty() || self.oidc.is_some()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebUserConfig {
+    pub username: String,
+    /// `pbkdf2-sha256$...` hash from `microclaw config hash-password`.
+    pub password_hash: String,
+    /// May see every chat and change the config.
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebOidcConfig {
+    /// Issuer URL; the endpoints come from its
+    /// `/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: String,
+    /// Public URL of this server's `/auth/oidc/callback`.
+    pub redirect_url: String,
+    /// Verified emails allowed to sign in; `@example.com` allows a domain.
+    pub allowed_emails: Vec<String>,
+    /// Signed-in emails that are admins (see `WebUserConfig::admin`).
+    #[serde(default)]
+    pub admin_emails: Vec<String>,
+}
+
+/// Nightly memory consolidation (see `memory_consolidation.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryConsolidationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hour of the day (0-23, in `timezone`) at which the job runs.
+    #[serde(default = "default_memory_consolidation_hour")]
+    pub hour: u32,
+    /// Cheaper model (same provider) that does the consolidation. Unset =
+    /// `compaction_model`, then the main model.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Chats with fewer active structured memories are skipped, unless their
+    /// memory file is large.
+    #[serde(default = "default_memory_consolidation_min_memories")]
+    pub min_memories: usize,
+}
+
+impl Default for MemoryConsolidationConfig {
+    fn default() -> Self {
+        MemoryConsolidationConfig {
+            enabled: false,
+            hour: default_memory_consolidation_hour(),
+            model: None,
+            min_memories: default_memory_consolidation_min_memories(),
+        }
+    }
+}
+
+/// Alerts for scheduled tasks that keep failing (see `scheduler.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskFailureAlertConfig {
+    /// Alert once a task has failed this many runs in a row (0 = never).
+    #[serde(default = "default_task_failure_alert_after")]
+    pub after_failures: u32,
+    /// Internal chat id that receives the alerts, e.g. a control chat.
+    /// Unset = the task's own chat.
+    #[serde(default)]
+    pub chat_id: Option<i64>,
+}
+
+impl Default for TaskFailureAlertConfig {
+    fn default() -> Self {
+        TaskFailureAlertConfig {
+            after_failures: default_task_failure_alert_after(),
+            chat_id: None,
+        }
+    }
+}
+
+fn default_task_failure_alert_after() -> u32 {
+    3
+}
+
+/// RSS/Atom feed watching (see `feeds.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedsConfig {
+    /// How often subscribed feeds are fetched.
+    #[serde(default = "default_feeds_poll_interval_mins")]
+    pub poll_interval_mins: u64,
+    /// New items posted per feed and poll; the rest are only counted.
+    #[serde(default = "default_feeds_max_items")]
+    pub max_items: usize,
+    /// Have the model summarize new items instead of posting a plain list.
+    #[serde(default = "default_feeds_summarize")]
+    pub summarize: bool,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        FeedsConfig {
+            poll_interval_mins: default_feeds_poll_interval_mins(),
+            max_items: default_feeds_max_items(),
+            summarize: default_feeds_summarize(),
+        }
+    }
+}
+
+/// Workspace file watching for the `watch_path` tool (see `file_watch.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileWatchConfig {
+    /// Quiet period before a watch's collected changes are posted to its chat.
+    #[serde(default = "default_file_watch_debounce_secs")]
+    pub debounce_secs: u64,
+    /// Active watches a chat may have at once.
+    #[serde(default = "default_file_watch_max_per_chat")]
+    pub max_watches_per_chat: usize,
+}
+
+impl Default for FileWatchConfig {
+    fn default() -> Self {
+        FileWatchConfig {
+            debounce_secs: default_file_watch_debounce_secs(),
+            max_watches_per_chat: default_file_watch_max_per_chat(),
+        }
+    }
+}
+
+fn default_file_watch_debounce_secs() -> u64 {
+    5
+}
+
+fn default_file_watch_max_per_chat() -> usize {
+    20
+}
+
+/// How the `ocr` tool reads text from images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrBackend {
+    /// The OCR model when it can view images, otherwise tesseract.
+    #[default]
+    Auto,
+    /// Ask the OCR model to transcribe the image.
+    Vision,
+    /// Run the local `tesseract` binary.
+    Tesseract,
+}
+
+/// Settings for the `ocr` tool (see `tools/ocr.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OcrConfig {
+    #[serde(default)]
+    pub backend: OcrBackend,
+    /// Vision model on the configured provider used for OCR; empty uses the
+    /// chat's model.
+    #[serde(default)]
+    pub model: String,
+    /// tesseract `-l` languages, e.g. `eng+deu`.
+    #[serde(default = "default_ocr_languages")]
+    pub languages: String,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        OcrConfig {
+            backend: OcrBackend::default(),
+            model: String::new(),
+            languages: default_ocr_languages(),
+        }
+    }
+}
+
+fn default_ocr_languages() -> String {
+    "eng".into()
+}
+
+/// Agent events a clawhook can subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClawhookEvent {
+    /// A turn produced its final reply.
+    TurnCompleted,
+    /// A tool call failed (not counting approval prompts or cancels).
+    ToolFailed,
+    /// A message was refused because a chat or the global budget is spent.
+    BudgetExceeded,
+    /// A high-risk tool call is waiting for the user's approval.
+    ApprovalRequested,
+}
+
+impl ClawhookEvent {
+    pub const ALL: [ClawhookEvent; 4] = [
+        ClawhookEvent::TurnCompleted,
+        ClawhookEvent::ToolFailed,
+        ClawhookEvent::BudgetExceeded,
+        ClawhookEvent::ApprovalRequested,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ClawhookEvent::TurnCompleted => "turn_completed",
+            ClawhookEvent::ToolFailed => "tool_failed",
+            ClawhookEvent::BudgetExceeded => "budget_exceeded",
+            ClawhookEvent::ApprovalRequested => "approval_requested",
+        }
+    }
+}
+
+/// An outbound webhook for agent events; see `clawhooks.rs`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClawhookConfig {
+    /// http(s) URL the events are POSTed to.
+    pub url: String,
+    /// HMAC-SHA256 key for the `X-Microclaw-Signature` header.
+    pub secret: String,
+    /// Events to send; empty sends all of them.
+    #[serde(default)]
+    pub events: Vec<ClawhookEvent>,
+}
+
+impl ClawhookConfig {
+    pub fn wants(&self, event: ClawhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Outgoing mail for the `send_email` tool.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    /// SMTP server; unset disables the tool.
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    /// Use STARTTLS on `port` (typically 587) instead of implicit TLS.
+    #[serde(default)]
+    pub starttls: bool,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Sender address; defaults to `username`.
+    #[serde(default)]
+    pub from_address: Option<String>,
+    /// Recipient domains (`example.com`) mail may go to. Required.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        SmtpConfig {
+            host: None,
+            port: default_smtp_port(),
+            starttls: false,
+            username: String::new(),
+            password: None,
+            from_address: None,
+            allowed_domains: Vec::new(),
+        }
+    }
+}
+
+impl SmtpConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.host.as_deref().is_some_and(|h| !h.trim().is_empty())
+    }
+
+    pub fn sender_address(&self) -> &str {
+        self.from_address
+            .as_deref()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or(&self.username)
+    }
+}
+
+/// OpenTelemetry trace export (see `otel.rs`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtelConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// OTLP/HTTP traces URL (JSON encoding).
+    #[serde(default = "default_otel_endpoint")]
+    pub endpoint: String,
+    #[serde(default = "default_otel_service_name")]
+    pub service_name: String,
+    /// Extra request headers, e.g. an auth token for a hosted backend.
+    /// Values may be secret references.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl Default for OtelConfig {
+    fn default() -> Self {
+        OtelConfig {
+            enabled: false,
+            endpoint: default_otel_endpoint(),
+            service_name: default_otel_service_name(),
+            headers: HashMap::new(),
+        }
+    }
+}
+
+/// Concurrent execution of the low-risk tool calls a model makes in one
+/// response. Medium- and high-risk tools always run one at a time, in order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParallelToolsConfig {
+    #[serde(default = "default_parallel_tools_enabled")]
+    pub enabled: bool,
+    /// Calls running at once across all tools.
+    #[serde(default = "default_parallel_tools_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Per-tool limits on top of `max_concurrent` (e.g. `browser: 1`).
+    #[serde(default)]
+    pub per_tool: HashMap<String, usize>,
+}
+
+impl Default for ParallelToolsConfig {
+    fn default() -> Self {
+        ParallelToolsConfig {
+            enabled: default_parallel_tools_enabled(),
+            max_concurrent: default_parallel_tools_max_concurrent(),
+            per_tool: HashMap::new(),
+        }
+    }
+}
+
+/// Ordering of agent turns (see `turn_queue.rs`). Turns in one chat run one
+/// at a time; messages arriving meanwhile wait for the running turn.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnQueueConfig {
+    /// Agent turns running at once across all chats.
+    #[serde(default = "default_turn_queue_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Answer messages that queued up behind a running turn in one
+    /// follow-up turn instead of one turn each.
+    #[serde(default)]
+    pub merge: bool,
+}
+
+impl Default for TurnQueueConfig {
+    fn default() -> Self {
+        TurnQueueConfig {
+            max_concurrent: default_turn_queue_max_concurrent(),
+            merge: false,
+        }
+    }
+}
+
+/// Per-user rate limits and muting (see `user_limits.rs`). Every limit is
+/// off at 0.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserLimitsConfig {
+    /// Messages one user may send per minute before a cooldown.
+    #[serde(default)]
+    pub messages_per_minute: u32,
+    /// Messages one user may send per hour before a cooldown.
+    #[serde(default)]
+    pub messages_per_hour: u32,
+    /// How long a user who went over a message limit is ignored.
+    #[serde(default = "default_user_limits_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Failed tool calls within `tool_error_window_secs` that mute the user.
+    #[serde(default)]
+    pub mute_after_tool_errors: u32,
+    #[serde(default = "default_user_limits_tool_error_window_secs")]
+    pub tool_error_window_secs: u64,
+    /// How long a muted user is ignored.
+    #[serde(default = "default_user_limits_mute_secs")]
+    pub mute_secs: u64,
+}
+
+impl Default for UserLimitsConfig {
+    fn default() -> Self {
+        UserLimitsConfig {
+            messages_per_minute: 0,
+            messages_per_hour: 0,
+            cooldown_secs: default_user_limits_cooldown_secs(),
+            mute_after_tool_errors: 0,
+            tool_error_window_secs: default_user_limits_tool_error_window_secs(),
+            mute_secs: default_user_limits_mute_secs(),
+        }
+    }
+}
+
+fn default_user_limits_cooldown_secs() -> u64 {
+    300
+}
+
+fn default_user_limits_tool_error_window_secs() -> u64 {
+    600
+}
+
+fn default_user_limits_mute_secs() -> u64 {
+    1800
+}
+
+/// Azure OpenAI settings for `llm_provider: azure`. Without `api_key`, requests
+/// use a Microsoft Entra ID token: client credentials when a tenant, client id
+/// and secret are configured (here or as `AZURE_TENANT_ID` / `AZURE_CLIENT_ID`
+/// / `AZURE_CLIENT_SECRET`), otherwise the managed identity.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AzureConfig {
+    /// `api-version` query parameter. Defaults to a GA version for
+    /// `/openai/deployments/<name>` URLs; the `/openai/v1` API needs none.
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// `api_key`, `client_secret` or `managed_identity`; inferred when unset.
+    #[serde(default)]
+    pub auth: Option<String>,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// App registration (client secret) or user-assigned managed identity.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+/// OAuth client for `microclaw login <provider>` (device-code flow). The
+/// endpoints and scope default to the provider's where they are known
+/// (Google); other providers need them set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthClientConfig {
+    pub client_id: String,
+    /// Required by Google's "TVs and limited input devices" clients.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub device_authorization_url: Option<String>,
+    #[serde(default)]
+    pub token_url: Option<String>,
+}
+
+pub const REASONING_EFFORTS: &[&str] = &["minimal", "low", "medium", "high"];
+/// Smallest budget Anthropic accepts.
+pub const MIN_THINKING_BUDGET: u32 = 1024;
+
+impl ThinkingConfig {
+    /// Settings for a `/thinking` level: `off`, `low`, `medium` or `high`.
+    pub fn for_level(level: &str) -> Option<Self> {
+        let (budget_tokens, effort) = match level {
+            "off" => return Some(ThinkingConfig::default()),
+            "low" => (2048, "low"),
+            "medium" => (8192, "medium"),
+            "high" => (24576, "high"),
+            _ => return None,
+        };
+        Some(ThinkingConfig {
+            budget_tokens,
+            reasoning_effort: Some(effort.to_string()),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.budget_tokens > 0 || self.reasoning_effort.is_some()
+    }
+}
+
+/// An extra Telegram bot identity served by the same process. It shares the
+/// LLM, tools and database with the primary bot but keeps its own chats.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelegramBotConfig {
+    /// Short name; the bot's channel is `telegram:<id>`.
+    pub id: String,
+    pub bot_token: String,
+    #[serde(default)]
+    pub bot_username: String,
+    #[serde(default)]
+    pub allowed_groups: Vec<i64>,
+    /// Appended to the system prompt for this bot's chats.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Working dir root for this bot's chats (defaults to `working_dir`).
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+impl TelegramBotConfig {
+    pub fn channel_name(&self) -> String {
+        format!("telegram:{}", self.id)
+    }
+}
+
+/// Per-model capability override; unset fields keep the built-in value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCapabilityOverride {
+    #[serde(default)]
+    pub vision: Option<bool>,
+    #[serde(default)]
+    pub tool_use: Option<bool>,
+    #[serde(default)]
+    pub streaming: Option<bool>,
+    #[serde(default)]
+    pub prompt_caching: Option<bool>,
+    #[serde(default)]
+    pub structured_output: Option<bool>,
+    #[serde(default)]
+    pub max_context_tokens: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    // --- LLM / API ---
+    #[serde(default = "default_llm_provider")]
+    pub llm_provider: String,
+    #[serde(default = "default_api_key")]
+    pub api_key: String,
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default)]
+    pub llm_base_url: Option<String>,
+    /// Ordered fallback models; see `LlmFallback`.
+    #[serde(default)]
+    pub llm_fallbacks: Vec<LlmFallback>,
+    /// Complexity-based small/large model routing; see `ModelRouterConfig`.
+    #[serde(default)]
+    pub model_router: ModelRouterConfig,
+    /// `safetySettings` threshold for every harm category with
+    /// `llm_provider: gemini` (e.g. `BLOCK_ONLY_HIGH`); Gemini's default when
+    /// unset.
+    #[serde(default)]
+    pub gemini_safety_threshold: Option<String>,
+    /// AWS region for `llm_provider: bedrock`; defaults to `AWS_REGION` or
+    /// the profile's region.
+    #[serde(default)]
+    pub aws_region: Option<String>,
+    /// Shared credentials profile for `llm_provider: bedrock`; defaults to
+    /// `AWS_PROFILE` or `default`.
+    #[serde(default)]
+    pub aws_profile: Option<String>,
+    /// Azure OpenAI `api-version` and Entra ID auth; see `AzureConfig`.
+    #[serde(default)]
+    pub azure: AzureConfig,
+    /// OAuth clients keyed by provider id, for `microclaw login`. A token
+    /// stored under `data_dir/auth` is used instead of `api_key`.
+    #[serde(default)]
+    pub oauth: HashMap<String, OAuthClientConfig>,
+    /// Redacted LLM request/response log; see `WireLogConfig`.
+    #[serde(default)]
+    pub llm_wire_log: WireLogConfig,
+    /// Pruning of old messages, task history and audit logs; see
+    /// `RetentionConfig`.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// `text` (default) or `json` log lines.
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Prometheus `/metrics` endpoint; see `MetricsConfig`.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// OpenTelemetry tracing; see `OtelConfig`.
+    #[serde(default)]
+    pub otel: OtelConfig,
+    /// Document ingestion and retrieval; see `KnowledgeConfig`.
+    #[serde(default)]
+    pub knowledge: KnowledgeConfig,
+    /// CalDAV / Google Calendar access; see `CalendarConfig`.
+    #[serde(default)]
+    pub calendar: CalendarConfig,
+    /// SMTP server for the `send_email` tool; see `SmtpConfig`.
+    #[serde(default)]
+    pub smtp: SmtpConfig,
+    /// Feed subscriptions polling; see `FeedsConfig`.
+    #[serde(default)]
+    pub feeds: FeedsConfig,
+    /// Workspace file watches; see `FileWatchConfig`.
+    #[serde(default)]
+    pub file_watch: FileWatchConfig,
+    /// Text extraction for the `ocr` tool; see `OcrConfig`.
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    /// Signed outbound webhooks for agent events; see `ClawhookConfig`.
+    #[serde(default)]
+    pub clawhooks: Vec<ClawhookConfig>,
+    /// Proactive check-ins; see `HeartbeatConfig`.
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    /// Nightly memory consolidation; see `MemoryConsolidationConfig`.
+    #[serde(default)]
+    pub memory_consolidation: MemoryConsolidationConfig,
+    /// Alerts for repeatedly failing scheduled tasks; see `TaskFailureAlertConfig`.
+    #[serde(default)]
+    pub task_failure_alerts: TaskFailureAlertConfig,
+    /// Extended thinking / reasoning effort; see `ThinkingConfig`.
+    #[serde(default)]
+    pub thinking: ThinkingConfig,
+    /// Per-request timeout after which the next fallback is tried (0 = none).
+    /// Only applies when `llm_fallbacks` is set.
+    #[serde(default = "default_llm_fallback_timeout_secs")]
+    pub llm_fallback_timeout_secs: u64,
+    /// Retries per model for rate limits and transient errors, with
+    /// exponential backoff and jitter (0 = no retries).
+    #[serde(default = "default_llm_max_retries")]
+    pub llm_max_retries: u32,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: usize,
+    /// Sampling temperature; unset = the provider's default. Ignored while
+    /// extended thinking or `reasoning_effort` is on.
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub parallel_tools: ParallelToolsConfig,
+    /// An identical tool call (same tool and input) that failed this many
+    /// times in a turn is answered with a hint instead of being run again
+    /// (0 = off).
+    #[serde(default = "default_max_repeated_tool_failures")]
+    pub max_repeated_tool_failures: usize,
+    /// Refuse high-risk tools (see `tool_risk`) for the rest of a turn once
+    /// `web_fetch`, `web_search` or `browser` returned content in it.
+    #[serde(default)]
+    pub block_high_risk_after_untrusted: bool,
+    /// Read-only mode for every chat: only low-risk tools are offered and
+    /// run. Chats can also be locked one by one with `/readonly on`.
+    #[serde(default)]
+    pub read_only: bool,
+    /// A new message from the same sender cancels their in-flight run in
+    /// that chat (like `/stop`) before it is answered.
+    #[serde(default)]
+    pub cancel_on_new_message: bool,
+    #[serde(default)]
+    pub turn_queue: TurnQueueConfig,
+    /// Per-user message rate limits and muting; see `UserLimitsConfig`.
+    #[serde(default)]
+    pub user_limits: UserLimitsConfig,
+    #[serde(default = "default_max_history_messages")]
+    pub max_history_messages: usize,
+    #[serde(default = "default_max_document_size_mb")]
+    pub max_document_size_mb: u64,
+    #[serde(default = "default_memory_token_budget")]
+    pub memory_token_budget: usize,
+    /// Also compact sessions longer than this many messages (0 = only by
+    /// tokens, see `context_compact_ratio`).
+    #[serde(default = "default_max_session_messages")]
+    pub max_session_messages: usize,
+    /// Share of the model's context window (after `max_tokens`) a session may
+    /// fill before it is compacted.
+    #[serde(default = "default_context_compact_ratio")]
+    pub context_compact_ratio: f64,
+    #[serde(default = "default_compact_keep_recent")]
+    pub compact_keep_recent: usize,
+    /// Cheaper model (same provider) that writes compaction summaries.
+    /// Unset = the main model.
+    #[serde(default)]
+    pub compaction_model: Option<String>,
+    #[serde(default)]
+    pub show_thinking: bool,
+    /// Capability overrides keyed by model name, for models the built-in
+    /// registry does not know or gets wrong.
+    #[serde(default)]
+    pub model_capabilities: HashMap<String, ModelCapabilityOverride>,
+    /// Named personas, switched per chat with `/persona <name>`.
+    #[serde(default)]
+    pub personas: HashMap<String, PersonaConfig>,
+
+    // --- Paths & environment ---
+    #[serde(default = "default_data_dir")]
+    pub data_dir: String,
+    /// Encrypt the database with SQLCipher under a startup passphrase
+    /// (`MICROCLAW_DB_PASSPHRASE`); needs a build with the `sqlcipher` feature.
+    #[serde(default)]
+    pub db_encryption: bool,
+    #[serde(default = "default_working_dir")]
+    pub working_dir: String,
+    #[serde(default = "default_working_dir_isolation")]
+    pub working_dir_isolation: WorkingDirIsolation,
+    /// Soft disk quota for a chat workspace, reported by the workspace report (0 = no quota).
+    #[serde(default = "default_workspace_quota_mb")]
+    pub workspace_quota_mb: u64,
+    /// Largest workspace (uncompressed MB) that `export_workspace` will zip.
+    #[serde(default = "default_workspace_export_max_mb")]
+    pub workspace_export_max_mb: u64,
+    /// Send a preview card (name, size, first lines or diff) to the chat when
+    /// `write_file` / `edit_file` change a file.
+    #[serde(default)]
+    pub file_preview_cards: bool,
+    #[serde(default = "default_file_preview_lines")]
+    pub file_preview_lines: usize,
+    /// Show replies on Telegram and Discord while they are generated, editing
+    /// the message about once a second.
+    #[serde(default = "default_stream_replies")]
+    pub stream_replies: bool,
+    /// While a turn is calling tools on Telegram and Discord, show the
+    /// current tool and elapsed time in a message that the reply replaces.
+    /// Overridable per channel with `channels.<name>.progress_status`.
+    #[serde(default = "default_progress_status")]
+    pub progress_status: bool,
+    /// Outbound HTTP allow/deny lists and SSRF guard for network-using tools.
+    #[serde(default)]
+    pub network_policy: NetworkPolicyConfig,
+    /// Per-tool output limits in bytes (`0` = unlimited), over the defaults in
+    /// `tools::output_limit`. Longer output is cut and saved to a file in the
+    /// workspace.
+    #[serde(default)]
+    pub tool_output_limits: HashMap<String, usize>,
+    /// Run `bash` on the host or in a per-workspace container.
+    #[serde(default)]
+    pub bash_backend: BashBackend,
+    #[serde(default)]
+    pub bash_container: BashContainerConfig,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Language of the bot's own messages (`en`, `zh`); chats can switch
+    /// with `/language`. See `i18n.rs`.
+    #[serde(default = "default_language")]
+    pub language: String,
+    #[serde(default = "default_control_chat_ids")]
+    pub control_chat_ids: Vec<i64>,
+
+    // --- Web UI ---
+    #[serde(default = "default_web_enabled")]
+    pub web_enabled: bool,
+    #[serde(default = "default_web_host")]
+    pub web_host: String,
+    #[serde(default = "default_web_port")]
+    pub web_port: u16,
+    #[serde(default)]
+    pub web_auth_token: Option<String>,
+    /// Browser logins for the web channel; see `WebAuthConfig`.
+    #[serde(default)]
+    pub web_auth: WebAuthConfig,
+    /// Base URL the web server is reachable at from outside (e.g. behind a
+    /// reverse proxy), used in the links it hands out such as webhook task
+    /// URLs. Defaults to `http://<web_host>:<web_port>`.
+    #[serde(default)]
+    pub web_public_url: Option<String>,
+    /// Bearer key for the OpenAI-compatible `/v1/chat/completions` API
+    /// (disabled when unset).
+    #[serde(default)]
+    pub openai_compat_api_key: Option<String>,
+    #[serde(default = "default_web_max_inflight_per_session")]
+    pub web_max_inflight_per_session: usize,
+    #[serde(default = "default_web_max_requests_per_window")]
+    pub web_max_requests_per_window: usize,
+    #[serde(default = "default_web_rate_window_seconds")]
+    pub web_rate_window_seconds: u64,
+    #[serde(default = "default_web_run_history_limit")]
+    pub web_run_history_limit: usize,
+    #[serde(default = "default_web_session_idle_ttl_seconds")]
+    pub web_session_idle_ttl_seconds: u64,
+
+    // --- Embedding ---
+    #[serde(default)]
+    pub embedding_provider: Option<String>,
+    #[serde(default)]
+    pub embedding_api_key: Option<String>,
+    #[serde(default)]
+    pub embedding_base_url: Option<String>,
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    #[serde(default)]
+    pub embedding_dim: Option<usize>,
+    #[serde(default)]
+    pub openai_api_key: Option<String>,
+
+    // --- Voice transcription ---
+    /// `openai` (Whisper API), `local` (`voice_transcription_command`) or `off`.
+    /// Unset picks `local` when a command is configured, else `openai`.
+    #[serde(default)]
+    pub voice_transcription_provider: Option<String>,
+    /// OpenAI-compatible base URL for a self-hosted Whisper server.
+    #[serde(default)]
+    pub voice_transcription_base_url: Option<String>,
+    #[serde(default)]
+    pub voice_transcription_model: Option<String>,
+    /// Local speech-to-text command; `{file}` is replaced with the audio path
+    /// and the transcript is read from stdout.
+    #[serde(default)]
+    pub voice_transcription_command: Option<String>,
+
+    // --- Pricing ---
+    #[serde(default = "default_model_prices")]
+    pub model_prices: Vec<ModelPrice>,
+    /// YAML price list (same entries as `model_prices`) loaded at startup.
+    /// Inline `model_prices` entries take precedence over it.
+    #[serde(default)]
+    pub pricing_file: Option<String>,
+    /// URL of a YAML price list, fetched at startup and every
+    /// `pricing_refresh_hours`; the latest copy is saved to `pricing_file`.
+    #[serde(default)]
+    pub pricing_url: Option<String>,
+    /// 0 fetches `pricing_url` once at startup only.
+    #[serde(default = "default_pricing_refresh_hours")]
+    pub pricing_refresh_hours: u64,
+    /// Spending limits applied to every chat.
+    #[serde(default)]
+    pub chat_budget: ChatBudget,
+    /// Per-chat limits by chat id; set fields replace `chat_budget`'s.
+    #[serde(default)]
+    pub chat_budgets: HashMap<i64, ChatBudget>,
+    /// Limits on the combined spending of all chats.
+    #[serde(default)]
+    pub global_budget: ChatBudget,
+
+    // --- Reflector ---
+    #[serde(default = "default_reflector_enabled")]
+    pub reflector_enabled: bool,
+    #[serde(default = "default_reflector_interval_mins")]
+    pub reflector_interval_mins: u64,
+
+    // --- Soul ---
+    /// Path to a SOUL.md file that defines the bot's personality, voice, and values.
+    /// If not set, looks for SOUL.md in data_dir root, then current directory.
+    #[serde(default = "default_soul_path")]
+    pub soul_path: Option<String>,
+
+    /// Skip the high-risk tool approval loop (e.g. for bash).
+    /// Useful when running inside an isolated environment like Firecracker.
+    /// Can also be set via MICROCLAW_SKIP_TOOL_APPROVAL=true env var.
+    #[serde(default = "default_skip_tool_approval")]
+    pub skip_tool_approval: bool,
+
+    // --- Channel registry (new dynamic config) ---
+    /// Per-channel configuration. Keys are channel names (e.g. "telegram", "discord", "slack", "web").
+    /// Each value is channel-specific config deserialized by the adapter.
+    /// If empty, synthesized from legacy flat fields below in post_deserialize().
+    #[serde(default)]
+    pub channels: HashMap<String, serde_yaml::Value>,
+
+    // --- Legacy channel fields (deprecated, use `channels:` instead) ---
+    #[serde(default = "default_telegram_bot_token")]
+    pub telegram_bot_token: String,
+    #[serde(default = "default_bot_username")]
+    pub bot_username: String,
+    #[serde(default)]
+    pub allowed_groups: Vec<i64>,
+    /// Answer `@bot <question>` inline queries with web search/fetch only.
+    /// Also needs `/setinline` and `/setinlinefeedback` in BotFather.
+    #[serde(default)]
+    pub telegram_inline_mode: bool,
+    /// Telegram user ids allowed to use inline mode. Required when
+    /// `telegram_inline_mode` is on; nobody else can use it.
+    #[serde(default)]
+    pub telegram_inline_allowed_users: Vec<u64>,
+    /// Additional Telegram bots run alongside the primary one.
+    #[serde(default)]
+    pub telegram_bots: Vec<TelegramBotConfig>,
+    #[serde(default)]
+    pub discord_bot_token: Option<String>,
+    #[serde(default)]
+    pub discord_allowed_channels: Vec<u64>,
+    /// Answer server-channel mentions in a new thread per conversation.
+    #[serde(default)]
+    pub discord_reply_in_threads: bool,
+
+    /// Secret references (see `crate::secrets`) and what they resolved to,
+    /// so `save_yaml` writes the references back instead of the secrets.
+    #[serde(skip)]
+    pub secret_refs: Vec<(String, String)>,
+}
+
+/// Environment variable holding the active profile name.
+pub const PROFILE_ENV: &str = "MICROCLAW_PROFILE";
+
+/// Overlay `overlay` onto `base`: mappings merge key by key, anything else
+/// (including lists) replaces the base value.
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        // An empty profile file parses as null; it changes nothing.
+        (_, serde_yaml::Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
+impl Config {
+    /// Public base URL of the web server, or `None` when it is disabled.
+    pub fn web_base_url(&self) -> Option<String> {
+        if !self.web_enabled {
+            return None;
+        }
+        Some(
+            self.web_public_url
+                .clone()
+                .unwrap_or_else(|| format!("http://{}:{}", self.web_host, self.web_port)),
+        )
+    }
+
+    /// Data root directory from config.
+    pub fn data_root_dir(&self) -> PathBuf {
+        PathBuf::from(&self.data_dir)
+    }
+
+    /// Runtime data directory (db, memory, exports, etc.).
+    pub fn runtime_data_dir(&self) -> String {
+        self.data_root_dir()
+            .join("runtime")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Folder indexed for `retrieve` (`knowledge.dir`, default
+    /// `<data_dir>/knowledge`).
+    pub fn knowledge_dir(&self) -> PathBuf {
+        if self.knowledge.dir.trim().is_empty() {
+            self.data_root_dir().join("knowledge")
+        } else {
+            PathBuf::from(&self.knowledge.dir)
+        }
+    }
+
+    /// Skills directory under data root.
+    pub fn skills_data_dir(&self) -> String {
+        self.data_root_dir()
+            .join("skills")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn resolve_config_path() -> Result<Option<PathBuf>, MicroClawError> {
         // 1. Check MICROCLAW_CONFIG env var for custom path
         if let Ok(custom) = std::env::var("MICROCLAW_CONFIG") {
             if std::path::Path::new(&custom).exists() {
@@ -277,36 +1755,264 @@ impl Config {
         Ok(None)
     }
 
-    /// Load config from YAML file.
+    /// Profile selected with `--profile` (which sets `MICROCLAW_PROFILE`).
+    pub fn active_profile() -> Result<Option<String>, MicroClawError> {
+        let Ok(profile) = std::env::var(PROFILE_ENV) else {
+            return Ok(None);
+        };
+        let profile = profile.trim();
+        if profile.is_empty() {
+            return Ok(None);
+        }
+        if !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(MicroClawError::Config(format!(
+                "Invalid profile name '{profile}': use letters, digits, '-' and '_'"
+            )));
+        }
+        Ok(Some(profile.to_string()))
+    }
+
+    /// `microclaw.config.<profile>.yaml` (or `.yml`) next to the base config,
+    /// or in the current directory when there is no base config.
+    pub fn resolve_profile_path(
+        base: Option<&Path>,
+        profile: &str,
+    ) -> Result<PathBuf, MicroClawError> {
+        let dir = base
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        ["yaml", "yml"]
+            .iter()
+            .map(|ext| dir.join(format!("microclaw.config.{profile}.{ext}")))
+            .find(|path| path.exists())
+            .ok_or_else(|| {
+                MicroClawError::Config(format!(
+                    "Profile '{profile}' not found: create {}",
+                    dir.join(format!("microclaw.config.{profile}.yaml"))
+                        .display()
+                ))
+            })
+    }
+
+    fn read_yaml(path: &Path) -> Result<serde_yaml::Value, MicroClawError> {
+        let path_str = path.to_string_lossy();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| MicroClawError::Config(format!("Failed to read {path_str}: {e}")))?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| MicroClawError::Config(format!("Failed to parse {path_str}: {e}")))
+    }
+
+    /// Load config from YAML file, with the active profile layered on top.
     pub fn load() -> Result<Self, MicroClawError> {
+        Self::load_inner(true)
+    }
+
+    /// Like [`Config::load`], but accepts a config with no messaging channel
+    /// enabled (for `microclaw chat`).
+    pub fn load_without_channels() -> Result<Self, MicroClawError> {
+        Self::load_inner(false)
+    }
+
+    fn load_inner(require_channel: bool) -> Result<Self, MicroClawError> {
         let yaml_path = Self::resolve_config_path()?;
+        let profile = Self::active_profile()?;
+        if yaml_path.is_none() && profile.is_none() {
+            return Err(MicroClawError::Config(
+                "No microclaw.config.yaml found. Run `microclaw setup` to create one.".into(),
+            ));
+        }
+
+        let mut merged = serde_yaml::Value::Mapping(Default::default());
+        let mut sources = Vec::new();
+        if let Some(path) = &yaml_path {
+            merge_yaml(&mut merged, Self::read_yaml(path)?);
+            sources.push(path.to_string_lossy().to_string());
+        }
+        if let Some(profile) = &profile {
+            let path = Self::resolve_profile_path(yaml_path.as_deref(), profile)?;
+            merge_yaml(&mut merged, Self::read_yaml(&path)?);
+            sources.push(path.to_string_lossy().to_string());
+        }
+        let mut config: Config = serde_yaml::from_value(merged).map_err(|e| {
+            MicroClawError::Config(format!("Failed to parse {}: {e}", sources.join(" + ")))
+        })?;
+        config.normalize(require_channel)?;
+        Ok(config)
+    }
 
-        if let Some(path) = yaml_path {
-            let path_str = path.to_string_lossy().to_string();
-            let content = std::fs::read_to_string(&path)
-                .map_err(|e| MicroClawError::Config(format!("Failed to read {path_str}: {e}")))?;
-            let mut config: Config = serde_yaml::from_str(&content)
-                .map_err(|e| MicroClawError::Config(format!("Failed to parse {path_str}: {e}")))?;
-            config.post_deserialize()?;
-            return Ok(config);
+    /// Credential fields that may hold a secret reference, by config path.
+    /// Channel settings count when their key names a token, secret, password
+    /// or key.
+    fn secret_fields_mut(&mut self) -> Vec<(String, &mut String)> {
+        fn channel_secrets<'a>(
+            path: String,
+            value: &'a mut serde_yaml::Value,
+            secret: bool,
+            out: &mut Vec<(String, &'a mut String)>,
+        ) {
+            match value {
+                serde_yaml::Value::String(s) if secret => out.push((path, s)),
+                serde_yaml::Value::Mapping(map) => {
+                    for (key, value) in map.iter_mut() {
+                        let key = key.as_str().unwrap_or_default();
+                        let secret = ["token", "secret", "password", "key"]
+                            .iter()
+                            .any(|word| key.to_ascii_lowercase().contains(word));
+                        channel_secrets(format!("{path}.{key}"), value, secret, out);
+                    }
+                }
+                serde_yaml::Value::Sequence(items) => {
+                    for (i, item) in items.iter_mut().enumerate() {
+                        channel_secrets(format!("{path}[{i}]"), item, secret, out);
+                    }
+                }
+                _ => {}
+            }
         }
 
-        // No config file found at all
-        Err(MicroClawError::Config(
-            "No microclaw.config.yaml found. Run `microclaw setup` to create one.".into(),
-        ))
+        let mut fields = vec![
+            ("api_key".to_string(), &mut self.api_key),
+            (
+                "telegram_bot_token".to_string(),
+                &mut self.telegram_bot_token,
+            ),
+        ];
+        for (name, value) in [
+            ("discord_bot_token", &mut self.discord_bot_token),
+            ("web_auth_token", &mut self.web_auth_token),
+            ("openai_compat_api_key", &mut self.openai_compat_api_key),
+            ("embedding_api_key", &mut self.embedding_api_key),
+            ("openai_api_key", &mut self.openai_api_key),
+            ("azure.client_secret", &mut self.azure.client_secret),
+            ("calendar.password", &mut self.calendar.password),
+            ("calendar.client_secret", &mut self.calendar.client_secret),
+            ("calendar.refresh_token", &mut self.calendar.refresh_token),
+            ("smtp.password", &mut self.smtp.password),
+        ] {
+            if let Some(value) = value {
+                fields.push((name.to_string(), value));
+            }
+        }
+        if let Some(oidc) = &mut self.web_auth.oidc {
+            fields.push((
+                "web_auth.oidc.client_secret".to_string(),
+                &mut oidc.client_secret,
+            ));
+        }
+        for (i, fallback) in self.llm_fallbacks.iter_mut().enumerate() {
+            if let Some(key) = &mut fallback.api_key {
+                fields.push((format!("llm_fallbacks[{i}].api_key"), key));
+            }
+        }
+        for (i, bot) in self.telegram_bots.iter_mut().enumerate() {
+            fields.push((format!("telegram_bots[{i}].bot_token"), &mut bot.bot_token));
+        }
+        for (i, hook) in self.clawhooks.iter_mut().enumerate() {
+            fields.push((format!("clawhooks[{i}].secret"), &mut hook.secret));
+        }
+        for (provider, client) in self.oauth.iter_mut() {
+            if let Some(secret) = &mut client.client_secret {
+                fields.push((format!("oauth.{provider}.client_secret"), secret));
+            }
+        }
+        for (name, value) in self.otel.headers.iter_mut() {
+            fields.push((format!("otel.headers.{name}"), value));
+        }
+        for (name, value) in self.channels.iter_mut() {
+            channel_secrets(format!("channels.{name}"), value, false, &mut fields);
+        }
+        fields
+    }
+
+    /// Replace `env:`, `file:`, `exec:` and `keychain:` references with the
+    /// secrets they point at.
+    fn resolve_secrets(&mut self) -> Result<(), MicroClawError> {
+        let mut resolved = Vec::new();
+        for (path, value) in self.secret_fields_mut() {
+            if !crate::secrets::is_reference(value) {
+                continue;
+            }
+            let secret = crate::secrets::resolve(value)
+                .map_err(|e| MicroClawError::Config(format!("{path}: {e}")))?;
+            resolved.push((std::mem::replace(value, secret.clone()), secret));
+        }
+        self.secret_refs.extend(resolved);
+        Ok(())
+    }
+
+    fn validate_web_auth(&mut self) -> Result<(), MicroClawError> {
+        let auth = &mut self.web_auth;
+        if auth.session_ttl_hours == 0 {
+            return Err(MicroClawError::Config(
+                "web_auth.session_ttl_hours must be greater than 0".into(),
+            ));
+        }
+        let mut seen = std::collections::HashSet::new();
+        for user in &mut auth.users {
+            user.username = user.username.trim().to_string();
+            let name = &user.username;
+            if name.is_empty() || name.contains(':') || name.chars().any(char::is_whitespace) {
+                return Err(MicroClawError::Config(format!(
+                    "web_auth.users: invalid username {name:?} (no spaces or ':')"
+                )));
+            }
+            if !seen.insert(name.clone()) {
+                return Err(MicroClawError::Config(format!(
+                    "web_auth.users: duplicate username {name:?}"
+                )));
+            }
+            crate::web::auth::check_password_hash(&user.password_hash)
+                .map_err(|e| MicroClawError::Config(format!("web_auth.users ({name}): {e}")))?;
+        }
+        if let Some(oidc) = &mut auth.oidc {
+            for (field, value) in [
+                ("issuer", &oidc.issuer),
+                ("client_id", &oidc.client_id),
+                ("redirect_url", &oidc.redirect_url),
+            ] {
+                if value.trim().is_empty() {
+                    return Err(MicroClawError::Config(format!(
+                        "web_auth.oidc.{field} is required"
+                    )));
+                }
+            }
+            for list in [&mut oidc.allowed_emails, &mut oidc.admin_emails] {
+                for email in list.iter_mut() {
+                    *email = email.trim().to_lowercase();
+                }
+                list.retain(|e| !e.is_empty());
+            }
+            if oidc.allowed_emails.is_empty() {
+                return Err(MicroClawError::Config(
+                    "web_auth.oidc.allowed_emails must list the emails or @domains that may sign in"
+                        .into(),
+                ));
+            }
+        }
+        Ok(())
     }
 
     /// Apply post-deserialization normalization and validation.
     pub(crate) fn post_deserialize(&mut self) -> Result<(), MicroClawError> {
+        self.normalize(true)
+    }
+
+    fn normalize(&mut self, require_channel: bool) -> Result<(), MicroClawError> {
+        self.resolve_secrets()?;
         self.llm_provider = self.llm_provider.trim().to_lowercase();
 
         // Apply provider-specific default model if empty
         if self.model.is_empty() {
             self.model = match self.llm_provider.as_str() {
-                "anthropic" => "claude-sonnet-4-5-20250929".into(),
+                "anthropic" | "claude-oauth" => "claude-sonnet-4-5-20250929".into(),
                 "ollama" => "llama3.2".into(),
                 "openai-codex" => "gpt-5.3-codex".into(),
+                "gemini" => "gemini-2.5-flash".into(),
+                "bedrock" => "us.anthropic.claude-sonnet-4-5-20250929-v1:0".into(),
                 _ => "gpt-5.2".into(),
             };
         }
@@ -316,6 +2022,16 @@ impl Config {
             .parse::<chrono_tz::Tz>()
             .map_err(|_| MicroClawError::Config(format!("Invalid timezone: {}", self.timezone)))?;
 
+        self.language = crate::i18n::Language::parse(&self.language)
+            .ok_or_else(|| {
+                MicroClawError::Config(format!(
+                    "Unsupported language: {} (use en or zh)",
+                    self.language
+                ))
+            })?
+            .code()
+            .to_string();
+
         // Filter empty llm_base_url
         if let Some(ref url) = self.llm_base_url {
             if url.trim().is_empty() {
@@ -333,6 +2049,16 @@ impl Config {
                 self.web_auth_token = None;
             }
         }
+        if let Some(key) = &self.openai_compat_api_key {
+            if key.trim().is_empty() {
+                self.openai_compat_api_key = None;
+            }
+        }
+        self.web_public_url = self
+            .web_public_url
+            .as_deref()
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
         if let Some(provider) = &self.embedding_provider {
             let p = provider.trim().to_lowercase();
             self.embedding_provider = if p.is_empty() { None } else { Some(p) };
@@ -351,105 +2077,474 @@ impl Config {
             let m = v.trim().to_string();
             self.embedding_model = if m.is_empty() { None } else { Some(m) };
         }
-        if let Some(v) = self.embedding_dim {
-            if v == 0 {
-                self.embedding_dim = None;
+        if let Some(provider) = &self.voice_transcription_provider {
+            let p = provider.trim().to_lowercase();
+            match p.as_str() {
+                "" => self.voice_transcription_provider = None,
+                "openai" | "local" | "off" => self.voice_transcription_provider = Some(p),
+                _ => {
+                    return Err(MicroClawError::Config(format!(
+                        "voice_transcription_provider must be openai, local or off (got {provider})"
+                    )))
+                }
+            }
+        }
+        for v in [
+            &mut self.voice_transcription_base_url,
+            &mut self.voice_transcription_model,
+            &mut self.voice_transcription_command,
+        ] {
+            if v.as_deref().is_some_and(|s| s.trim().is_empty()) {
+                *v = None;
+            }
+        }
+        if let Some(v) = self.embedding_dim {
+            if v == 0 {
+                self.embedding_dim = None;
+            }
+        }
+        self.validate_web_auth()?;
+        self.bash_container.runtime = self.bash_container.runtime.trim().to_string();
+        self.bash_container.image = self.bash_container.image.trim().to_string();
+        if self.bash_backend == BashBackend::Container && self.bash_container.image.is_empty() {
+            return Err(MicroClawError::Config(
+                "bash_container.image is required when bash_backend is container".into(),
+            ));
+        }
+        self.ocr.model = self.ocr.model.trim().to_string();
+        self.ocr.languages = self.ocr.languages.trim().to_string();
+        if self.ocr.languages.is_empty() {
+            self.ocr.languages = default_ocr_languages();
+        }
+        if self.web_enabled
+            && !is_local_web_host(&self.web_host)
+            && self.web_auth_token.is_none()
+            && !self.web_auth.login_enabled()
+        {
+            return Err(MicroClawError::Config(
+                "web_auth_token is required when web_enabled=true and web_host is not local (or set up web_auth logins)".into(),
+            ));
+        }
+        if self.web_max_inflight_per_session == 0 {
+            self.web_max_inflight_per_session = default_web_max_inflight_per_session();
+        }
+        if self.web_max_requests_per_window == 0 {
+            self.web_max_requests_per_window = default_web_max_requests_per_window();
+        }
+        if self.web_rate_window_seconds == 0 {
+            self.web_rate_window_seconds = default_web_rate_window_seconds();
+        }
+        if self.web_run_history_limit == 0 {
+            self.web_run_history_limit = default_web_run_history_limit();
+        }
+        if self.web_session_idle_ttl_seconds == 0 {
+            self.web_session_idle_ttl_seconds = default_web_session_idle_ttl_seconds();
+        }
+        if self.max_document_size_mb == 0 {
+            self.max_document_size_mb = default_max_document_size_mb();
+        }
+        if self.memory_token_budget == 0 {
+            self.memory_token_budget = default_memory_token_budget();
+        }
+        for price in &mut self.model_prices {
+            price.normalize().map_err(MicroClawError::Config)?;
+        }
+        self.pricing_file = self
+            .pricing_file
+            .take()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        self.pricing_url = self
+            .pricing_url
+            .take()
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty());
+        if let Some(url) = &self.pricing_url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err(MicroClawError::Config(format!(
+                    "pricing_url must be an http(s) URL, got '{url}'"
+                )));
+            }
+        }
+
+        for (scope, budget) in [
+            ("chat_budget".to_string(), &self.chat_budget),
+            ("global_budget".to_string(), &self.global_budget),
+        ]
+        .into_iter()
+        .chain(
+            self.chat_budgets
+                .iter()
+                .map(|(id, b)| (format!("chat_budgets.{id}"), b)),
+        ) {
+            let tokens_ok = [budget.daily_tokens, budget.monthly_tokens]
+                .iter()
+                .flatten()
+                .all(|v| *v >= 0);
+            let usd_ok = [
+                budget.daily_usd,
+                budget.monthly_usd,
+                budget.warn_daily_usd,
+                budget.warn_monthly_usd,
+            ]
+            .iter()
+            .flatten()
+            .all(|v| v.is_finite() && *v >= 0.0);
+            if !tokens_ok || !usd_ok {
+                return Err(MicroClawError::Config(format!(
+                    "{scope}: budget limits must be >= 0"
+                )));
+            }
+            if (budget.daily_usd.is_some()
+                || budget.monthly_usd.is_some()
+                || budget.warn_daily_usd.is_some()
+                || budget.warn_monthly_usd.is_some())
+                && !self.has_pricing()
+            {
+                return Err(MicroClawError::Config(format!(
+                    "{scope}: USD limits need model_prices, pricing_file or pricing_url to estimate costs"
+                )));
+            }
+        }
+
+        // Allow env var override for skip_tool_approval
+        if let Ok(val) = std::env::var("MICROCLAW_SKIP_TOOL_APPROVAL") {
+            self.skip_tool_approval = matches!(val.as_str(), "1" | "true" | "yes");
+        }
+
+        // Synthesize `channels` map from legacy flat fields if empty
+        if self.channels.is_empty() {
+            if !self.telegram_bot_token.trim().is_empty() {
+                self.channels.insert(
+                    "telegram".into(),
+                    serde_yaml::to_value(serde_json::json!({
+                        "bot_token": self.telegram_bot_token,
+                        "bot_username": self.bot_username,
+                        "allowed_groups": self.allowed_groups,
+                    }))
+                    .unwrap(),
+                );
+            }
+            if let Some(ref token) = self.discord_bot_token {
+                if !token.trim().is_empty() {
+                    self.channels.insert(
+                        "discord".into(),
+                        serde_yaml::to_value(serde_json::json!({
+                            "bot_token": token,
+                            "allowed_channels": self.discord_allowed_channels,
+                        }))
+                        .unwrap(),
+                    );
+                }
+            }
+            if self.web_enabled {
+                self.channels.insert(
+                    "web".into(),
+                    serde_yaml::to_value(serde_json::json!({
+                        "enabled": true,
+                        "host": self.web_host,
+                        "port": self.web_port,
+                        "auth_token": self.web_auth_token,
+                    }))
+                    .unwrap(),
+                );
+            }
+        }
+
+        for (name, value) in &self.channels {
+            if !value.is_mapping() {
+                continue;
+            }
+            let overrides: ChannelOverrides = serde_yaml::from_value(value.clone())
+                .map_err(|e| MicroClawError::Config(format!("channels.{name}: {e}")))?;
+            if overrides
+                .model
+                .as_deref()
+                .is_some_and(|m| m.trim().is_empty())
+            {
+                return Err(MicroClawError::Config(format!(
+                    "channels.{name}.model must not be empty"
+                )));
+            }
+            if overrides.max_tokens == Some(0) || overrides.max_tool_iterations == Some(0) {
+                return Err(MicroClawError::Config(format!(
+                    "channels.{name}: max_tokens and max_tool_iterations must be greater than 0"
+                )));
+            }
+        }
+
+        if let Some(threshold) = &self.gemini_safety_threshold {
+            let threshold = threshold.trim().to_ascii_uppercase();
+            if !crate::gemini::SAFETY_THRESHOLDS.contains(&threshold.as_str()) {
+                return Err(MicroClawError::Config(format!(
+                    "gemini_safety_threshold must be one of {}",
+                    crate::gemini::SAFETY_THRESHOLDS.join(", ")
+                )));
+            }
+            self.gemini_safety_threshold = Some(threshold);
+        }
+        if self.thinking.budget_tokens > 0 && self.thinking.budget_tokens < MIN_THINKING_BUDGET {
+            return Err(MicroClawError::Config(format!(
+                "thinking.budget_tokens must be 0 or at least {MIN_THINKING_BUDGET}"
+            )));
+        }
+        if let Some(effort) = &self.thinking.reasoning_effort {
+            if !REASONING_EFFORTS.contains(&effort.as_str()) {
+                return Err(MicroClawError::Config(format!(
+                    "thinking.reasoning_effort must be one of {}",
+                    REASONING_EFFORTS.join(", ")
+                )));
+            }
+        }
+        if self.metrics.enabled && self.metrics.listen.parse::<std::net::SocketAddr>().is_err() {
+            return Err(MicroClawError::Config(format!(
+                "metrics.listen must be a host:port address, got {:?}",
+                self.metrics.listen
+            )));
+        }
+        if self.otel.enabled
+            && !(self.otel.endpoint.starts_with("http://")
+                || self.otel.endpoint.starts_with("https://"))
+        {
+            return Err(MicroClawError::Config(
+                "otel.endpoint must be an http:// or https:// URL".into(),
+            ));
+        }
+        if self.knowledge.enabled {
+            if self.knowledge.chunk_chars < 100
+                || self.knowledge.chunk_overlap >= self.knowledge.chunk_chars / 2
+            {
+                return Err(MicroClawError::Config(
+                    "knowledge.chunk_chars must be at least 100 and chunk_overlap less than half of it"
+                        .into(),
+                ));
+            }
+            if self.knowledge.top_k == 0 || self.knowledge.scan_interval_secs == 0 {
+                return Err(MicroClawError::Config(
+                    "knowledge.top_k and knowledge.scan_interval_secs must be greater than 0"
+                        .into(),
+                ));
+            }
+            // Pin the folder before `data_dir` is rewritten to the runtime dir.
+            self.knowledge.dir = self.knowledge_dir().to_string_lossy().to_string();
+        }
+        if let Some(provider) = &self.calendar.provider {
+            let provider = provider.trim().to_ascii_lowercase();
+            let set = |v: &Option<String>| v.as_deref().is_some_and(|v| !v.trim().is_empty());
+            match provider.as_str() {
+                "caldav" => {
+                    let url = self.calendar.url.as_deref().unwrap_or("").trim();
+                    if !(url.starts_with("http://") || url.starts_with("https://")) {
+                        return Err(MicroClawError::Config(
+                            "calendar.url must be an http:// or https:// CalDAV URL".into(),
+                        ));
+                    }
+                }
+                "google" => {
+                    if !(set(&self.calendar.client_id)
+                        && set(&self.calendar.client_secret)
+                        && set(&self.calendar.refresh_token))
+                    {
+                        return Err(MicroClawError::Config(
+                            "calendar.provider google needs calendar.client_id, client_secret and refresh_token"
+                                .into(),
+                        ));
+                    }
+                }
+                _ => {
+                    return Err(MicroClawError::Config(format!(
+                        "calendar.provider must be 'caldav' or 'google', got {provider:?}"
+                    )));
+                }
+            }
+            self.calendar.provider = Some(provider);
+        }
+        if self.feeds.poll_interval_mins == 0 || self.feeds.max_items == 0 {
+            return Err(MicroClawError::Config(
+                "feeds.poll_interval_mins and feeds.max_items must be greater than 0".into(),
+            ));
+        }
+        if self.telegram_inline_mode && self.telegram_inline_allowed_users.is_empty() {
+            return Err(MicroClawError::Config(
+                "telegram_inline_mode needs telegram_inline_allowed_users".into(),
+            ));
+        }
+        for (i, hook) in self.clawhooks.iter_mut().enumerate() {
+            hook.url = hook.url.trim().to_string();
+            if !hook.url.starts_with("http://") && !hook.url.starts_with("https://") {
+                return Err(MicroClawError::Config(format!(
+                    "clawhooks[{i}].url must be an http(s) URL"
+                )));
+            }
+            if hook.secret.trim().is_empty() {
+                return Err(MicroClawError::Config(format!(
+                    "clawhooks[{i}].secret is required"
+                )));
+            }
+        }
+        if self.heartbeat.enabled {
+            if self.heartbeat.interval_mins == 0 {
+                return Err(MicroClawError::Config(
+                    "heartbeat.interval_mins must be greater than 0".into(),
+                ));
+            }
+            if let Some(quiet) = &self.heartbeat.quiet_hours {
+                crate::heartbeat::QuietHours::parse(quiet).map_err(MicroClawError::Config)?;
+            }
+        }
+        if self.memory_consolidation.hour > 23 {
+            return Err(MicroClawError::Config(
+                "memory_consolidation.hour must be between 0 and 23".into(),
+            ));
+        }
+        if self.smtp.is_enabled() {
+            if self.smtp.sender_address().trim().is_empty() {
+                return Err(MicroClawError::Config(
+                    "smtp needs a username or from_address".into(),
+                ));
+            }
+            self.smtp.allowed_domains = self
+                .smtp
+                .allowed_domains
+                .iter()
+                .map(|d| d.trim().trim_start_matches('@').to_ascii_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect();
+            if self.smtp.allowed_domains.is_empty() {
+                return Err(MicroClawError::Config(
+                    "smtp.allowed_domains must list the recipient domains send_email may use"
+                        .into(),
+                ));
+            }
+        }
+        if self.retention.is_enabled() && self.retention.interval_hours == 0 {
+            return Err(MicroClawError::Config(
+                "retention.interval_hours must be greater than 0".into(),
+            ));
+        }
+        if self.llm_wire_log.max_file_mb == 0 || self.llm_wire_log.max_files == 0 {
+            return Err(MicroClawError::Config(
+                "llm_wire_log: max_file_mb and max_files must be greater than 0".into(),
+            ));
+        }
+        for pattern in &self.llm_wire_log.redact_patterns {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return Err(MicroClawError::Config(format!(
+                    "llm_wire_log.redact_patterns: invalid pattern {pattern:?}: {e}"
+                )));
+            }
+        }
+        self.oauth = std::mem::take(&mut self.oauth)
+            .into_iter()
+            .map(|(provider, client)| (provider.trim().to_lowercase(), client))
+            .collect();
+        for (provider, client) in &self.oauth {
+            if client.client_id.trim().is_empty() {
+                return Err(MicroClawError::Config(format!(
+                    "oauth.{provider}.client_id is required"
+                )));
+            }
+        }
+        if let Some(auth) = &self.azure.auth {
+            let auth = auth.trim().to_ascii_lowercase();
+            if !crate::azure::AUTH_MODES.contains(&auth.as_str()) {
+                return Err(MicroClawError::Config(format!(
+                    "azure.auth must be one of {}",
+                    crate::azure::AUTH_MODES.join(", ")
+                )));
             }
+            self.azure.auth = Some(auth);
         }
-        if self.web_enabled && !is_local_web_host(&self.web_host) && self.web_auth_token.is_none() {
+        if self.model_router.enabled
+            && (self.model_router.classifier_model.trim().is_empty()
+                || self.model_router.small_model.trim().is_empty())
+        {
             return Err(MicroClawError::Config(
-                "web_auth_token is required when web_enabled=true and web_host is not local".into(),
+                "model_router: classifier_model and small_model are required when enabled".into(),
             ));
         }
-        if self.web_max_inflight_per_session == 0 {
-            self.web_max_inflight_per_session = default_web_max_inflight_per_session();
-        }
-        if self.web_max_requests_per_window == 0 {
-            self.web_max_requests_per_window = default_web_max_requests_per_window();
-        }
-        if self.web_rate_window_seconds == 0 {
-            self.web_rate_window_seconds = default_web_rate_window_seconds();
-        }
-        if self.web_run_history_limit == 0 {
-            self.web_run_history_limit = default_web_run_history_limit();
+        if self.parallel_tools.max_concurrent == 0
+            || self
+                .parallel_tools
+                .per_tool
+                .values()
+                .any(|limit| *limit == 0)
+        {
+            return Err(MicroClawError::Config(
+                "parallel_tools: max_concurrent and per_tool limits must be greater than 0".into(),
+            ));
         }
-        if self.web_session_idle_ttl_seconds == 0 {
-            self.web_session_idle_ttl_seconds = default_web_session_idle_ttl_seconds();
+        if self.turn_queue.max_concurrent == 0 {
+            return Err(MicroClawError::Config(
+                "turn_queue.max_concurrent must be greater than 0".into(),
+            ));
         }
-        if self.max_document_size_mb == 0 {
-            self.max_document_size_mb = default_max_document_size_mb();
+        if !(0.1..=0.95).contains(&self.context_compact_ratio) {
+            return Err(MicroClawError::Config(
+                "context_compact_ratio must be between 0.1 and 0.95".into(),
+            ));
         }
-        if self.memory_token_budget == 0 {
-            self.memory_token_budget = default_memory_token_budget();
+        if self.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            return Err(MicroClawError::Config(
+                "temperature must be between 0.0 and 2.0".into(),
+            ));
         }
-        for price in &mut self.model_prices {
-            price.model = price.model.trim().to_string();
-            if price.model.is_empty() {
-                return Err(MicroClawError::Config(
-                    "model_prices entries must include non-empty model".into(),
-                ));
-            }
-            if !(price.input_per_million_usd.is_finite() && price.input_per_million_usd >= 0.0) {
+        for (name, persona) in &self.personas {
+            if name.trim().is_empty() || name.chars().any(char::is_whitespace) {
                 return Err(MicroClawError::Config(format!(
-                    "model_prices[{}].input_per_million_usd must be >= 0",
-                    price.model
+                    "personas: invalid name '{name}' (must be non-empty, without spaces)"
                 )));
             }
-            if !(price.output_per_million_usd.is_finite() && price.output_per_million_usd >= 0.0) {
+            if persona
+                .temperature
+                .is_some_and(|t| !(0.0..=2.0).contains(&t))
+            {
                 return Err(MicroClawError::Config(format!(
-                    "model_prices[{}].output_per_million_usd must be >= 0",
-                    price.model
+                    "personas.{name}.temperature must be between 0.0 and 2.0"
                 )));
             }
         }
 
-        // Allow env var override for skip_tool_approval
-        if let Ok(val) = std::env::var("MICROCLAW_SKIP_TOOL_APPROVAL") {
-            self.skip_tool_approval = matches!(val.as_str(), "1" | "true" | "yes");
+        for fallback in &mut self.llm_fallbacks {
+            fallback.provider = fallback.provider.trim().to_lowercase();
+            if fallback.provider.is_empty() || fallback.model.trim().is_empty() {
+                return Err(MicroClawError::Config(
+                    "llm_fallbacks: every entry needs a provider and a model".into(),
+                ));
+            }
         }
 
-        // Synthesize `channels` map from legacy flat fields if empty
-        if self.channels.is_empty() {
-            if !self.telegram_bot_token.trim().is_empty() {
-                self.channels.insert(
-                    "telegram".into(),
-                    serde_yaml::to_value(serde_json::json!({
-                        "bot_token": self.telegram_bot_token,
-                        "bot_username": self.bot_username,
-                        "allowed_groups": self.allowed_groups,
-                    }))
-                    .unwrap(),
-                );
+        let mut bot_ids = std::collections::HashSet::new();
+        for bot in &self.telegram_bots {
+            let valid_id = !bot.id.is_empty()
+                && bot
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+            if !valid_id {
+                return Err(MicroClawError::Config(format!(
+                    "telegram_bots: invalid id '{}' (use lowercase letters, digits, '_' or '-')",
+                    bot.id
+                )));
             }
-            if let Some(ref token) = self.discord_bot_token {
-                if !token.trim().is_empty() {
-                    self.channels.insert(
-                        "discord".into(),
-                        serde_yaml::to_value(serde_json::json!({
-                            "bot_token": token,
-                            "allowed_channels": self.discord_allowed_channels,
-                        }))
-                        .unwrap(),
-                    );
-                }
+            if !bot_ids.insert(bot.id.as_str()) {
+                return Err(MicroClawError::Config(format!(
+                    "telegram_bots: duplicate id '{}'",
+                    bot.id
+                )));
             }
-            if self.web_enabled {
-                self.channels.insert(
-                    "web".into(),
-                    serde_yaml::to_value(serde_json::json!({
-                        "enabled": true,
-                        "host": self.web_host,
-                        "port": self.web_port,
-                        "auth_token": self.web_auth_token,
-                    }))
-                    .unwrap(),
-                );
+            if bot.bot_token.trim().is_empty() {
+                return Err(MicroClawError::Config(format!(
+                    "telegram_bots: bot '{}' has no bot_token",
+                    bot.id
+                )));
             }
         }
 
         // Validate required fields
-        let has_telegram =
-            !self.telegram_bot_token.trim().is_empty() || self.channels.contains_key("telegram");
+        let has_telegram = !self.telegram_bot_token.trim().is_empty()
+            || self.channels.contains_key("telegram")
+            || !self.telegram_bots.is_empty();
         let has_discord = self
             .discord_bot_token
             .as_deref()
@@ -458,15 +2553,30 @@ impl Config {
             || self.channels.contains_key("discord");
         let has_slack = self.channels.contains_key("slack");
         let has_feishu = self.channels.contains_key("feishu");
+        let has_email = self.channels.contains_key("email");
+        let has_signal = self.channels.contains_key("signal");
         let has_web = self.web_enabled || self.channels.contains_key("web");
 
-        if !(has_telegram || has_discord || has_slack || has_feishu || has_web) {
+        if require_channel
+            && !(has_telegram
+                || has_discord
+                || has_slack
+                || has_feishu
+                || has_email
+                || has_signal
+                || has_web)
+        {
             return Err(MicroClawError::Config(
-                "At least one channel must be enabled: telegram_bot_token, discord_bot_token, channels.slack, channels.feishu, or web_enabled=true".into(),
+                "At least one channel must be enabled: telegram_bot_token, discord_bot_token, channels.slack, channels.feishu, channels.email, channels.signal, or web_enabled=true".into(),
             ));
         }
-        if self.api_key.is_empty() && !provider_allows_empty_api_key(&self.llm_provider) {
-            return Err(MicroClawError::Config("api_key is required".into()));
+        if self.api_key.is_empty()
+            && !provider_allows_empty_api_key(&self.llm_provider)
+            && !self.oauth.contains_key(&self.llm_provider)
+        {
+            return Err(MicroClawError::Config(
+                "api_key is required (or configure oauth.<provider> and run `microclaw login <provider>`)".into(),
+            ));
         }
         if is_openai_codex_provider(&self.llm_provider) {
             if !self.api_key.trim().is_empty() {
@@ -491,6 +2601,18 @@ impl Config {
                 ));
             }
         }
+        if is_claude_oauth_provider(&self.llm_provider) {
+            if !self.api_key.trim().is_empty() {
+                return Err(MicroClawError::Config(
+                    "claude-oauth ignores microclaw.config.yaml api_key. Run `claude login` instead, or use llm_provider: anthropic with an API key.".into(),
+                ));
+            }
+            if !claude_credentials_available()? {
+                return Err(MicroClawError::Config(format!(
+                    "claude-oauth requires ~/.claude/.credentials.json or {CLAUDE_OAUTH_TOKEN_ENV}. Run `claude login` with a Claude Pro/Max account."
+                )));
+            }
+        }
 
         Ok(())
     }
@@ -502,12 +2624,103 @@ impl Config {
             .and_then(|v| serde_yaml::from_value(v.clone()).ok())
     }
 
-    pub fn model_price(&self, model: &str) -> Option<&ModelPrice> {
-        let needle = model.trim();
-        self.model_prices
-            .iter()
-            .find(|p| p.model.eq_ignore_ascii_case(needle))
-            .or_else(|| self.model_prices.iter().find(|p| p.model == "*"))
+    /// Model, budget and tool overrides from `channels.<channel>`.
+    pub fn channel_overrides(&self, channel: &str) -> ChannelOverrides {
+        self.channels
+            .get(channel)
+            .and_then(|v| serde_yaml::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Model used for turns on `channel`.
+    pub fn model_for_channel(&self, channel: &str) -> String {
+        self.channel_overrides(channel)
+            .model
+            .unwrap_or_else(|| self.model.clone())
+    }
+
+    /// Whether turns on `channel` show a progress status.
+    pub fn progress_status_for_channel(&self, channel: &str) -> bool {
+        self.channel_overrides(channel)
+            .progress_status
+            .unwrap_or(self.progress_status)
+    }
+
+    /// A copy of this config with `channel`'s overrides applied, for building
+    /// the channel's LLM client.
+    pub fn for_channel(&self, channel: &str) -> Config {
+        let overrides = self.channel_overrides(channel);
+        let mut config = self.clone();
+        if let Some(model) = overrides.model {
+            config.model = model;
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            config.max_tokens = max_tokens;
+        }
+        if let Some(iterations) = overrides.max_tool_iterations {
+            config.max_tool_iterations = iterations;
+        }
+        config
+    }
+
+    /// This config with the LLM settings of a fallback entry.
+    pub fn with_fallback(&self, fallback: &LlmFallback) -> Config {
+        let same_provider = fallback.provider == self.llm_provider;
+        let mut config = self.clone();
+        config.llm_provider = fallback.provider.clone();
+        config.model = fallback.model.clone();
+        config.api_key = fallback
+            .api_key
+            .clone()
+            .or_else(|| same_provider.then(|| self.api_key.clone()))
+            .unwrap_or_default();
+        config.llm_base_url = fallback
+            .llm_base_url
+            .clone()
+            .or_else(|| self.llm_base_url.clone().filter(|_| same_provider));
+        config.llm_fallbacks = Vec::new();
+        config
+    }
+
+    /// The extra Telegram bot serving `channel` (`telegram:<id>`), if any.
+    pub fn telegram_bot(&self, channel: &str) -> Option<&TelegramBotConfig> {
+        let id = channel.strip_prefix("telegram:")?;
+        self.telegram_bots.iter().find(|b| b.id == id)
+    }
+
+    /// Working dir root for chats on `channel`.
+    pub fn working_dir_for_channel(&self, channel: &str) -> &str {
+        self.telegram_bot(channel)
+            .and_then(|b| b.working_dir.as_deref())
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or(&self.working_dir)
+    }
+
+    /// The bot's own username on `channel`.
+    pub fn bot_username_for_channel(&self, channel: &str) -> &str {
+        self.telegram_bot(channel)
+            .map(|b| b.bot_username.as_str())
+            .filter(|u| !u.is_empty())
+            .unwrap_or(&self.bot_username)
+    }
+
+    /// Price for `model`: an inline `model_prices` entry, then the loaded
+    /// price list, then a `*` fallback from either.
+    pub fn model_price(&self, model: &str) -> Option<ModelPrice> {
+        crate::pricing::find_price(&self.model_prices, &crate::pricing::loaded_prices(), model)
+    }
+
+    /// Whether costs can be estimated at all.
+    pub fn has_pricing(&self) -> bool {
+        !self.model_prices.is_empty() || self.pricing_file.is_some() || self.pricing_url.is_some()
+    }
+
+    /// Spending limits for `chat_id`.
+    pub fn budget_for_chat(&self, chat_id: i64) -> ChatBudget {
+        match self.chat_budgets.get(&chat_id) {
+            Some(budget) => budget.or(&self.chat_budget),
+            None => self.chat_budget.clone(),
+        }
     }
 
     pub fn estimate_cost_usd(
@@ -528,7 +2741,19 @@ impl Config {
     /// Save config as YAML to the given path.
     #[allow(dead_code)]
     pub fn save_yaml(&self, path: &str) -> Result<(), MicroClawError> {
-        let content = serde_yaml::to_string(self)
+        // Write secret references back wherever their secret ended up,
+        // including channel entries synthesized from legacy fields.
+        let mut config = self.clone();
+        let refs = std::mem::take(&mut config.secret_refs);
+        for (_, value) in config.secret_fields_mut() {
+            if let Some((reference, _)) = refs
+                .iter()
+                .find(|(_, secret)| !secret.is_empty() && secret == value)
+            {
+                *value = reference.clone();
+            }
+        }
+        let content = serde_yaml::to_string(&config)
             .map_err(|e| MicroClawError::Config(format!("Failed to serialize config: {e}")))?;
         std::fs::write(path, content)?;
         Ok(())
@@ -576,6 +2801,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 10961,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -591,6 +2818,71 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: HashMap::new(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: HashMap::new(),
         }
     }
@@ -660,6 +2952,24 @@ mod tests {
         assert_eq!(config.timezone, "UTC");
     }
 
+    #[test]
+    fn test_config_bash_backend() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.bash_backend, BashBackend::Host);
+        assert_eq!(config.bash_container.image, "debian:bookworm-slim");
+        assert!(!config.bash_container.network);
+
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nbash_backend: container\nbash_container:\n  runtime: ' docker '\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.bash_backend, BashBackend::Container);
+        assert_eq!(config.bash_container.runtime, "docker");
+
+        config.bash_container.image = " ".into();
+        assert!(config.post_deserialize().is_err());
+    }
+
     #[test]
     fn test_post_deserialize_empty_working_dir_uses_default() {
         let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nworking_dir: '  '\n";
@@ -749,6 +3059,34 @@ mod tests {
         assert!(msg.contains("Invalid timezone"));
     }
 
+    #[test]
+    fn test_post_deserialize_language() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nlanguage: zh-CN\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.language, "zh");
+
+        config.language = "klingon".into();
+        let err = config.post_deserialize().unwrap_err();
+        assert!(err.to_string().contains("Unsupported language"));
+    }
+
+    #[test]
+    fn test_post_deserialize_voice_transcription_provider() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nvoice_transcription_provider: Local\nvoice_transcription_model: ''\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(
+            config.voice_transcription_provider.as_deref(),
+            Some("local")
+        );
+        assert!(config.voice_transcription_model.is_none());
+
+        config.voice_transcription_provider = Some("deepgram".into());
+        let err = config.post_deserialize().unwrap_err();
+        assert!(err.to_string().contains("voice_transcription_provider"));
+    }
+
     #[test]
     fn test_post_deserialize_missing_api_key() {
         let yaml = "telegram_bot_token: tok\nbot_username: bot\n";
@@ -967,6 +3305,31 @@ mod tests {
         assert_eq!(config.llm_provider, "openai-codex");
     }
 
+    #[test]
+    fn test_post_deserialize_claude_oauth_uses_claude_login() {
+        let _guard = env_lock();
+        let prev_token = std::env::var(CLAUDE_OAUTH_TOKEN_ENV).ok();
+        std::env::set_var(CLAUDE_OAUTH_TOKEN_ENV, "sk-ant-oat01-env");
+
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\nllm_provider: Claude-OAuth\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let result = config.post_deserialize();
+        let with_key = "telegram_bot_token: tok\nbot_username: bot\nllm_provider: claude-oauth\napi_key: sk-ant-api\n";
+        let mut keyed: Config = serde_yaml::from_str(with_key).unwrap();
+        let keyed_err = keyed.post_deserialize().unwrap_err().to_string();
+
+        if let Some(prev) = prev_token {
+            std::env::set_var(CLAUDE_OAUTH_TOKEN_ENV, prev);
+        } else {
+            std::env::remove_var(CLAUDE_OAUTH_TOKEN_ENV);
+        }
+
+        result.unwrap();
+        assert_eq!(config.llm_provider, "claude-oauth");
+        assert_eq!(config.model, "claude-sonnet-4-5-20250929");
+        assert!(keyed_err.contains("claude-oauth ignores"), "{keyed_err}");
+    }
+
     #[test]
     fn test_post_deserialize_ollama_default_model_and_empty_key() {
         let yaml = "telegram_bot_token: tok\nbot_username: bot\nllm_provider: ollama\n";
@@ -1010,6 +3373,26 @@ mod tests {
         assert_eq!(config.web_auth_token.as_deref(), Some("token123"));
     }
 
+    #[test]
+    fn test_post_deserialize_web_auth_logins() {
+        let base = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\nweb_enabled: true\nweb_host: 0.0.0.0\n";
+        let yaml = format!(
+            "{base}web_auth:\n  oidc:\n    issuer: https://id.example.com\n    client_id: mc\n    redirect_url: https://mc.example.com/auth/oidc/callback\n    allowed_emails: [' @Example.com ']\n"
+        );
+        let mut config: Config = serde_yaml::from_str(&yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert!(config.web_auth.login_enabled());
+        let oidc = config.web_auth.oidc.as_ref().unwrap();
+        assert_eq!(oidc.allowed_emails, vec!["@example.com"]);
+
+        let yaml = format!(
+            "{base}web_auth:\n  users:\n    - username: alice\n      password_hash: hunter2\n"
+        );
+        let mut config: Config = serde_yaml::from_str(&yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err();
+        assert!(err.to_string().contains("hash-password"));
+    }
+
     #[test]
     fn test_model_prices_parse_and_estimate() {
         let yaml = r#"
@@ -1047,6 +3430,258 @@ model_prices:
             .contains("model_prices entries must include non-empty model"));
     }
 
+    #[test]
+    fn test_inline_mode_requires_allowed_users() {
+        let yaml = "telegram_bot_token: tok\nbot_username: bot\napi_key: key\ntelegram_inline_mode: true\n";
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        let err = config.post_deserialize().unwrap_err();
+        assert!(err.to_string().contains("telegram_inline_allowed_users"));
+        let mut config: Config =
+            serde_yaml::from_str(&format!("{yaml}telegram_inline_allowed_users: [7]\n")).unwrap();
+        config.post_deserialize().unwrap();
+    }
+
+    #[test]
+    fn test_clawhooks_parse_and_validate() {
+        let yaml = r#"
+telegram_bot_token: tok
+bot_username: bot
+api_key: key
+clawhooks:
+  - url: " https://ops.example.com/hook "
+    secret: s3cret
+    events: [tool_failed, approval_requested]
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.clawhooks[0].url, "https://ops.example.com/hook");
+        assert!(config.clawhooks[0].wants(ClawhookEvent::ToolFailed));
+        assert!(!config.clawhooks[0].wants(ClawhookEvent::TurnCompleted));
+
+        let mut config: Config =
+            serde_yaml::from_str(&yaml.replace("secret: s3cret", "secret: \"\"")).unwrap();
+        let err = config.post_deserialize().unwrap_err();
+        assert!(err.to_string().contains("clawhooks[0].secret is required"));
+        assert!(serde_yaml::from_str::<Config>(&yaml.replace("tool_failed", "tool_done")).is_err());
+
+        let secret_file =
+            std::env::temp_dir().join(format!("mc_clawhook_secret_{}", std::process::id()));
+        std::fs::write(&secret_file, "hook-key\n").unwrap();
+        let mut config: Config = serde_yaml::from_str(&yaml.replace(
+            "secret: s3cret",
+            &format!("secret: \"file:{}\"", secret_file.display()),
+        ))
+        .unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.clawhooks[0].secret, "hook-key");
+        std::fs::remove_file(secret_file).ok();
+    }
+
+    #[test]
+    fn test_telegram_bots_parse_and_lookup() {
+        let yaml = r#"
+api_key: key
+working_dir: /srv/work
+bot_username: personal_bot
+telegram_bots:
+  - id: team
+    bot_token: "123:abc"
+    bot_username: team_bot
+    allowed_groups: [-100]
+    system_prompt: Keep answers short.
+    working_dir: /srv/team
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        let bot = config.telegram_bot("telegram:team").unwrap();
+        assert_eq!(bot.channel_name(), "telegram:team");
+        assert_eq!(bot.allowed_groups, vec![-100]);
+        assert!(config.telegram_bot("telegram").is_none());
+        assert_eq!(config.working_dir_for_channel("telegram:team"), "/srv/team");
+        assert_eq!(config.working_dir_for_channel("telegram"), "/srv/work");
+        assert_eq!(config.bot_username_for_channel("telegram:team"), "team_bot");
+        assert_eq!(config.bot_username_for_channel("discord"), "personal_bot");
+    }
+
+    #[test]
+    fn test_telegram_bots_validation() {
+        for (bots, expected) in [
+            ("[{id: Team, bot_token: t}]", "invalid id"),
+            (
+                "[{id: a, bot_token: t}, {id: a, bot_token: u}]",
+                "duplicate id",
+            ),
+            ("[{id: a, bot_token: ''}]", "no bot_token"),
+        ] {
+            let yaml = format!("api_key: key\ntelegram_bots: {bots}\n");
+            let mut config: Config = serde_yaml::from_str(&yaml).unwrap();
+            let err = config.post_deserialize().unwrap_err().to_string();
+            assert!(err.contains(expected), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_channel_overrides() {
+        let yaml = r#"
+api_key: key
+model: big-model
+max_tokens: 8192
+channels:
+  web:
+    enabled: true
+    model: small-model
+    max_tool_iterations: 5
+    tool_policy:
+      deny: [bash]
+  discord:
+    bot_token: tok
+    progress_status: false
+    tool_policy:
+      allow: [web_search, read_file]
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.model_for_channel("web"), "small-model");
+        assert_eq!(config.model_for_channel("telegram"), "big-model");
+        let web = config.for_channel("web");
+        assert_eq!(web.model, "small-model");
+        assert_eq!(web.max_tokens, 8192);
+        assert_eq!(web.max_tool_iterations, 5);
+        assert!(config.channel_overrides("web").changes_llm());
+        assert!(!config.channel_overrides("discord").changes_llm());
+        assert!(!config.progress_status_for_channel("discord"));
+        assert!(config.progress_status_for_channel("web"));
+
+        let web_policy = config.channel_overrides("web").tool_policy;
+        assert!(!web_policy.permits("bash"));
+        assert!(web_policy.permits("read_file"));
+        let discord_policy = config.channel_overrides("discord").tool_policy;
+        assert!(discord_policy.permits("web_search"));
+        assert!(!discord_policy.permits("bash"));
+
+        let mut bad: Config =
+            serde_yaml::from_str("api_key: key\nchannels:\n  web:\n    max_tokens: 0\n").unwrap();
+        assert!(bad.post_deserialize().is_err());
+    }
+
+    #[test]
+    fn test_thinking_config_validation() {
+        let mut config: Config = serde_yaml::from_str(
+            "api_key: key\nthinking:\n  budget_tokens: 4096\n  reasoning_effort: high\n",
+        )
+        .unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.thinking.budget_tokens, 4096);
+
+        for yaml in [
+            "api_key: key\nthinking:\n  budget_tokens: 500\n",
+            "api_key: key\nthinking:\n  reasoning_effort: extreme\n",
+        ] {
+            let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+            assert!(config.post_deserialize().is_err());
+        }
+    }
+
+    #[test]
+    fn test_chat_budgets() {
+        let yaml = r#"
+api_key: key
+chat_budget:
+  daily_tokens: 100000
+chat_budgets:
+  42:
+    daily_tokens: 5000
+    monthly_tokens: 50000
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.budget_for_chat(7).daily_tokens, Some(100000));
+        assert!(config.budget_for_chat(7).monthly_tokens.is_none());
+        let chat = config.budget_for_chat(42);
+        assert_eq!(chat.daily_tokens, Some(5000));
+        assert_eq!(chat.monthly_tokens, Some(50000));
+
+        let mut no_prices: Config =
+            serde_yaml::from_str("api_key: key\nchat_budget:\n  daily_usd: 1.5\n").unwrap();
+        assert!(no_prices.post_deserialize().is_err());
+        let mut negative: Config =
+            serde_yaml::from_str("api_key: key\nchat_budget:\n  daily_tokens: -1\n").unwrap();
+        assert!(negative.post_deserialize().is_err());
+        let mut global_warn: Config =
+            serde_yaml::from_str("api_key: key\nglobal_budget:\n  warn_daily_usd: 2\n").unwrap();
+        assert!(global_warn.post_deserialize().is_err());
+    }
+
+    #[test]
+    fn test_personas() {
+        let yaml = r#"
+api_key: key
+personas:
+  reviewer:
+    system_prompt: You review code tersely.
+    model: claude-haiku-4-5
+    temperature: 0.2
+    tools: [read_file, grep]
+    greeting: Send me a diff.
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        let reviewer = &config.personas["reviewer"];
+        assert_eq!(reviewer.model.as_deref(), Some("claude-haiku-4-5"));
+        assert!(reviewer.permits("grep"));
+        assert!(!reviewer.permits("bash"));
+        assert!(PersonaConfig::default().permits("bash"));
+
+        let mut hot: Config = serde_yaml::from_str(
+            "api_key: key\npersonas:\n  poet:\n    system_prompt: Rhyme.\n    temperature: 3.0\n",
+        )
+        .unwrap();
+        assert!(hot.post_deserialize().is_err());
+        let mut spaced: Config = serde_yaml::from_str(
+            "api_key: key\npersonas:\n  \"two words\":\n    system_prompt: Hi.\n",
+        )
+        .unwrap();
+        assert!(spaced.post_deserialize().is_err());
+    }
+
+    #[test]
+    fn test_llm_fallbacks() {
+        let yaml = r#"
+llm_provider: Anthropic
+api_key: key
+llm_base_url: https://proxy.example/v1
+llm_fallbacks:
+  - provider: anthropic
+    model: claude-haiku-4-5
+  - provider: OpenAI
+    model: gpt-5-mini
+    api_key: other
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.llm_fallback_timeout_secs, 120);
+
+        let same = config.with_fallback(&config.llm_fallbacks[0]);
+        assert_eq!(same.model, "claude-haiku-4-5");
+        assert_eq!(same.api_key, "key");
+        assert_eq!(
+            same.llm_base_url.as_deref(),
+            Some("https://proxy.example/v1")
+        );
+        assert!(same.llm_fallbacks.is_empty());
+
+        let other = config.with_fallback(&config.llm_fallbacks[1]);
+        assert_eq!(other.llm_provider, "openai");
+        assert_eq!(other.api_key, "other");
+        assert!(other.llm_base_url.is_none());
+
+        let mut bad: Config = serde_yaml::from_str(
+            "api_key: key\nllm_fallbacks:\n  - provider: openai\n    model: ''\n",
+        )
+        .unwrap();
+        assert!(bad.post_deserialize().is_err());
+    }
+
     #[test]
     fn test_config_yaml_with_all_optional_fields() {
         let yaml = r#"
@@ -1083,4 +3718,76 @@ discord_allowed_channels: [111, 222]
         assert!(content.contains("telegram_bot_token"));
         std::fs::remove_file(path).ok();
     }
+
+    #[test]
+    fn test_merge_yaml_layers_profile() {
+        let mut base: serde_yaml::Value = serde_yaml::from_str(
+            "bot_username: bot\nweb_port: 10961\nchannels:\n  slack:\n    bot_token: a\n    app_token: b\nallowed_groups: [1, 2]\n",
+        )
+        .unwrap();
+        let profile: serde_yaml::Value = serde_yaml::from_str(
+            "web_port: 10962\nchannels:\n  slack:\n    bot_token: staging\nallowed_groups: [3]\n",
+        )
+        .unwrap();
+        merge_yaml(&mut base, profile);
+        merge_yaml(&mut base, serde_yaml::Value::Null);
+        assert_eq!(base["bot_username"].as_str(), Some("bot"));
+        assert_eq!(base["web_port"].as_u64(), Some(10962));
+        assert_eq!(
+            base["channels"]["slack"]["bot_token"].as_str(),
+            Some("staging")
+        );
+        assert_eq!(base["channels"]["slack"]["app_token"].as_str(), Some("b"));
+        assert_eq!(base["allowed_groups"].as_sequence().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_profile_path() {
+        let dir = std::env::temp_dir().join(format!("mc_profiles_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("microclaw.config.yaml");
+        std::fs::write(dir.join("microclaw.config.prod.yml"), "web_port: 1\n").unwrap();
+        assert_eq!(
+            Config::resolve_profile_path(Some(&base), "prod").unwrap(),
+            dir.join("microclaw.config.prod.yml")
+        );
+        assert!(Config::resolve_profile_path(Some(&base), "dev")
+            .unwrap_err()
+            .to_string()
+            .contains("Profile 'dev' not found"));
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_secret_references_resolve_and_save_back() {
+        let dir = std::env::temp_dir().join(format!("mc_secret_refs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("api_key");
+        std::fs::write(&key_file, "sk-from-file\n").unwrap();
+        let yaml = format!(
+            "telegram_bot_token: tok\nbot_username: bot\napi_key: \"file:{}\"\nchannels:\n  slack:\n    bot_token: \"file:{}\"\n    channel_name: \"file:not-a-secret\"\n",
+            key_file.display(),
+            key_file.display()
+        );
+        let mut config: Config = serde_yaml::from_str(&yaml).unwrap();
+        config.post_deserialize().unwrap();
+        assert_eq!(config.api_key, "sk-from-file");
+        let slack = &config.channels["slack"];
+        assert_eq!(slack["bot_token"].as_str(), Some("sk-from-file"));
+        assert_eq!(slack["channel_name"].as_str(), Some("file:not-a-secret"));
+
+        let saved = dir.join("config.yaml");
+        config.save_yaml(saved.to_str().unwrap()).unwrap();
+        let content = std::fs::read_to_string(&saved).unwrap();
+        assert!(!content.contains("sk-from-file"));
+        assert!(content.contains(&format!("file:{}", key_file.display())));
+
+        let mut missing: Config = serde_yaml::from_str(
+            "telegram_bot_token: tok\nbot_username: bot\napi_key: env:MICROCLAW_SURELY_UNSET_VAR\n",
+        )
+        .unwrap();
+        let err = missing.post_deserialize().unwrap_err().to_string();
+        assert!(err.contains("api_key: environment variable MICROCLAW_SURELY_UNSET_VAR is not set"));
+        std::fs::remove_dir_all(dir).ok();
+    }
 }
@@ -0,0 +1,204 @@
+//! Passphrase-encrypted config values (`enc:v1:...`).
+//!
+//! `microclaw config encrypt` turns a secret into an `enc:v1:` value that can
+//! be pasted into any credential field. Values are sealed with AES-256-GCM
+//! under a key derived from the passphrase with PBKDF2-HMAC-SHA256 and a
+//! random per-value salt. At startup the passphrase comes from
+//! `MICROCLAW_CONFIG_PASSPHRASE` (which may itself be a `file:`, `exec:` or
+//! `keychain:` reference) or is prompted for on a terminal.
+
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::num::NonZeroU32;
+use std::sync::{Mutex, OnceLock};
+
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+pub const PASSPHRASE_ENV: &str = "MICROCLAW_CONFIG_PASSPHRASE";
+
+const SALT_LEN: usize = 16;
+const KDF_ITERATIONS: u32 = 600_000;
+const AAD: &[u8] = b"microclaw-config-v1";
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(KDF_ITERATIONS).expect("non-zero iterations"),
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).expect("32-byte key"))
+}
+
+pub fn encrypt_with(passphrase: &str, plaintext: &str) -> Result<String, String> {
+    if passphrase.is_empty() {
+        return Err("the passphrase is empty".into());
+    }
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| "no secure randomness available".to_string())?;
+    let mut data = plaintext.as_bytes().to_vec();
+    derive_key(passphrase, &salt)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(AAD),
+            &mut data,
+        )
+        .map_err(|_| "encryption failed".to_string())?;
+    let mut sealed = Vec::with_capacity(SALT_LEN + NONCE_LEN + data.len());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&data);
+    Ok(format!(
+        "{ENCRYPTED_PREFIX}{}",
+        base64::engine::general_purpose::STANDARD.encode(sealed)
+    ))
+}
+
+pub fn decrypt_with(passphrase: &str, value: &str) -> Result<String, String> {
+    let encoded = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or("not an enc:v1: value")?;
+    let sealed = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| "the encrypted value is not valid base64")?;
+    if sealed.len() < SALT_LEN + NONCE_LEN + AES_256_GCM.tag_len() {
+        return Err("the encrypted value is truncated".into());
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce, data) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "bad nonce")?;
+    let mut data = data.to_vec();
+    let plaintext = derive_key(passphrase, salt)
+        .open_in_place(nonce, Aad::from(AAD), &mut data)
+        .map_err(|_| "wrong passphrase or corrupted value")?;
+    String::from_utf8(plaintext.to_vec()).map_err(|_| "decrypted value is not UTF-8".into())
+}
+
+pub(crate) fn prompt_hidden(prompt: &str) -> Result<String, String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    eprint!("{prompt}");
+    let _ = std::io::stderr().flush();
+    crossterm::terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+    let mut input = String::new();
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e.to_string()),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err("passphrase prompt cancelled".to_string())
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    };
+    let _ = crossterm::terminal::disable_raw_mode();
+    eprintln!();
+    result.map(|_| input)
+}
+
+fn passphrase() -> Result<String, String> {
+    static PASSPHRASE: OnceLock<Mutex<Option<String>>> = OnceLock::new();
+    let mut cached = PASSPHRASE
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+    let passphrase = match std::env::var(PASSPHRASE_ENV) {
+        Ok(value) if crate::secrets::is_reference(&value) && !value.starts_with("enc:") => {
+            crate::secrets::resolve(&value).map_err(|e| format!("{PASSPHRASE_ENV}: {e}"))?
+        }
+        Ok(value) => value,
+        Err(_) if std::io::stdin().is_terminal() => prompt_hidden("Config passphrase: ")?,
+        Err(_) => {
+            return Err(format!(
+                "the config has encrypted values; set {PASSPHRASE_ENV} or start from a terminal"
+            ))
+        }
+    };
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+/// Decrypt an `enc:v1:` value with the startup passphrase.
+pub fn decrypt(value: &str) -> Result<String, String> {
+    static KEYS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    // The same value may appear in several fields (legacy fields are copied
+    // into `channels`); the key derivation is deliberately slow.
+    let cache = KEYS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(secret) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(value) {
+        return Ok(secret.clone());
+    }
+    let secret = decrypt_with(&passphrase()?, value)?;
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(value.to_string(), secret.clone());
+    Ok(secret)
+}
+
+/// `microclaw config encrypt`: read a secret and print its `enc:v1:` form.
+pub fn run_encrypt_cli() -> anyhow::Result<()> {
+    let passphrase = if std::env::var(PASSPHRASE_ENV).is_err() && std::io::stdin().is_terminal() {
+        let first = prompt_hidden("Config passphrase: ").map_err(anyhow::Error::msg)?;
+        let second = prompt_hidden("Repeat passphrase: ").map_err(anyhow::Error::msg)?;
+        if first != second {
+            return Err(anyhow::anyhow!("the passphrases don't match"));
+        }
+        first
+    } else {
+        passphrase().map_err(anyhow::Error::msg)?
+    };
+    let secret = if std::io::stdin().is_terminal() {
+        prompt_hidden("Secret to encrypt: ").map_err(anyhow::Error::msg)?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if secret.is_empty() {
+        return Err(anyhow::anyhow!("nothing to encrypt"));
+    }
+    println!(
+        "{}",
+        encrypt_with(&passphrase, &secret).map_err(anyhow::Error::msg)?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let sealed = encrypt_with("correct horse", "sk-secret").unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_PREFIX));
+        assert!(!sealed.contains("sk-secret"));
+        assert_eq!(decrypt_with("correct horse", &sealed).unwrap(), "sk-secret");
+        assert!(decrypt_with("wrong", &sealed)
+            .unwrap_err()
+            .contains("wrong passphrase"));
+        assert!(decrypt_with("correct horse", "enc:v1:AAAA").is_err());
+        assert!(encrypt_with("", "x").is_err());
+    }
+}
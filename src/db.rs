@@ -31,6 +31,65 @@ where
         .map_err(|e| MicroClawError::ToolExecution(format!("DB task join error: {e}")))?
 }
 
+/// File name of the database inside the runtime data directory.
+pub const DATABASE_FILE: &str = "microclaw.db";
+
+/// Copy the database in `data_dir` to `dest` with SQLite's online backup API,
+/// which gives a consistent snapshot even while the bot is writing (a plain
+/// file copy can miss pages still in the WAL). With `db_encryption` the
+/// snapshot is encrypted with the same passphrase.
+pub fn backup_database(data_dir: &Path, dest: &Path) -> Result<(), MicroClawError> {
+    let conn = crate::db_crypt::open(
+        &data_dir.join(DATABASE_FILE),
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )?;
+    let mut snapshot = crate::db_crypt::open(dest, rusqlite::OpenFlags::default())?;
+    rusqlite::backup::Backup::new(&conn, &mut snapshot)?.run_to_completion(
+        100,
+        std::time::Duration::ZERO,
+        None,
+    )?;
+    Ok(())
+}
+
+/// Overwrite the database in `data_dir` with the snapshot at `src`, through
+/// the backup API so the live database's WAL stays consistent.
+pub fn restore_database(src: &Path, data_dir: &Path) -> Result<(), MicroClawError> {
+    std::fs::create_dir_all(data_dir)?;
+    let snapshot = crate::db_crypt::open(src, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut conn = crate::db_crypt::open(
+        &data_dir.join(DATABASE_FILE),
+        rusqlite::OpenFlags::default(),
+    )?;
+    rusqlite::backup::Backup::new(&snapshot, &mut conn)?.run_to_completion(
+        100,
+        std::time::Duration::ZERO,
+        None,
+    )?;
+    Ok(())
+}
+
+/// `PRAGMA integrity_check` result ("ok" when sound) and schema version of a
+/// database snapshot.
+pub fn inspect_database_snapshot(path: &Path) -> Result<(String, i64), MicroClawError> {
+    let conn = crate::db_crypt::open(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    let version: Option<String> = conn
+        .query_row(
+            "SELECT value FROM db_meta WHERE key = 'schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .unwrap_or(None);
+    Ok((integrity, version.and_then(|v| v.parse().ok()).unwrap_or(0)))
+}
+
+/// Schema version this build migrates databases to.
+pub fn current_schema_version() -> i64 {
+    SCHEMA_VERSION_CURRENT
+}
+
 #[derive(Debug, Clone)]
 pub struct StoredMessage {
     pub id: String,
@@ -63,6 +122,20 @@ pub struct TaskRunLog {
     pub result_summary: Option<String>,
 }
 
+/// Aggregates over a task's logged runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskRunStats {
+    pub runs: i64,
+    pub failures: i64,
+    pub avg_duration_ms: i64,
+    pub max_duration_ms: i64,
+    pub last_success_at: Option<String>,
+    /// Failures since the last successful run.
+    pub consecutive_failures: i64,
+    /// Summary of the most recent failed run.
+    pub last_error: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct LlmUsageSummary {
     pub requests: i64,
@@ -81,6 +154,26 @@ pub struct LlmModelUsageSummary {
     pub total_tokens: i64,
 }
 
+/// Usage of one model on one UTC day (`day` is `YYYY-MM-DD`).
+#[derive(Debug, Clone)]
+pub struct LlmDailyUsage {
+    pub day: String,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+}
+
+/// Usage of one sender in a chat (`user_id` as the channel reports it).
+#[derive(Debug, Clone)]
+pub struct LlmUserUsageSummary {
+    pub user_id: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct Memory {
     pub id: i64,
@@ -140,7 +233,223 @@ pub struct MemoryInjectionLog {
     pub tokens_est: i64,
 }
 
-const SCHEMA_VERSION_CURRENT: i64 = 4;
+#[derive(Debug, Clone)]
+pub struct ModelComparison {
+    pub id: i64,
+    pub chat_id: i64,
+    pub model_a: String,
+    pub model_b: String,
+    pub preferred: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelComparisonStat {
+    pub model_a: String,
+    pub model_b: String,
+    pub a_wins: i64,
+    pub b_wins: i64,
+    pub ties: i64,
+    pub undecided: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserPreference {
+    pub chat_id: i64,
+    pub key: String,
+    pub value: String,
+    /// `inferred` (reflector) or `user` (set via /preferences).
+    pub source: String,
+    pub updated_at: String,
+}
+
+/// Per-chat workspace settings managed by `/workspace`.
+#[derive(Debug, Clone, Default)]
+pub struct ChatWorkspace {
+    /// Isolation override; `None` inherits `working_dir_isolation`.
+    pub isolation: Option<String>,
+    pub topic: Option<String>,
+    pub session_id: Option<String>,
+}
+
+/// A chat that belongs to a cross-channel identity (see `identity.rs`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkedChat {
+    pub chat_id: i64,
+    pub channel: Option<String>,
+    pub chat_title: Option<String>,
+}
+
+/// A parked session branch (see `branches.rs`). The active branch lives in
+/// `sessions`.
+#[derive(Debug, Clone)]
+pub struct SessionBranch {
+    pub name: String,
+    pub messages_json: String,
+    pub updated_at: String,
+}
+
+/// An indexed file under the knowledge folder (see `knowledge.rs`).
+#[derive(Debug, Clone)]
+pub struct KnowledgeDocument {
+    /// Path relative to the knowledge folder.
+    pub source: String,
+    pub content_hash: String,
+    /// Model the chunk vectors came from; `None` when stored without vectors.
+    pub embedding_model: Option<String>,
+    pub chunk_count: usize,
+    pub indexed_at: String,
+}
+
+/// A chat's subscription to an RSS/Atom feed (see `feeds.rs`).
+#[derive(Debug, Clone)]
+pub struct FeedSubscription {
+    pub id: i64,
+    pub chat_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    /// Keyword filter; see `feeds::FeedFilter`.
+    pub filter: Option<String>,
+    pub created_at: String,
+    pub last_checked_at: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// A chat's watch on files in its workspace (see `file_watch.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathWatch {
+    pub id: i64,
+    pub chat_id: i64,
+    /// Workspace directory the pattern is relative to.
+    pub root: String,
+    /// Glob relative to `root`, e.g. `target/release/*`.
+    pub pattern: String,
+    pub created_at: String,
+}
+
+/// A named pad shared between chats (see `tools/scratchpad.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scratchpad {
+    pub name: String,
+    /// Chat that created the pad.
+    pub owner_chat_id: i64,
+    pub content: String,
+    /// Bumped on every write.
+    pub version: i64,
+    pub updated_by_chat_id: i64,
+    pub updated_at: String,
+    /// Chats besides the owner that may read and write the pad.
+    pub shared_with: Vec<i64>,
+}
+
+/// A signed-in web UI login (see `web/auth.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebSession {
+    pub username: String,
+    pub csrf_token: String,
+    pub expires_at: String,
+}
+
+/// Seen item keys kept per feed; older ones are forgotten.
+const MAX_SEEN_FEED_ITEMS: i64 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct KnowledgeChunk {
+    pub source: String,
+    pub chunk_index: usize,
+    pub content: String,
+    pub embedding: Option<Vec<f32>>,
+}
+
+fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// A chat's preferred translation of a term (see `tools/translate.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlossaryTerm {
+    pub term: String,
+    pub translation: String,
+    /// Target language the entry applies to; empty for every language.
+    pub language: String,
+    pub updated_at: String,
+}
+
+/// Reply threading state for an email chat (see `channels/email.rs`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmailThread {
+    pub subject: String,
+    /// Message ids seen in the thread, oldest first. The last one is the
+    /// message a reply answers.
+    pub message_ids: Vec<String>,
+}
+
+fn scratchpad_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Scratchpad> {
+    Ok(Scratchpad {
+        name: row.get(0)?,
+        owner_chat_id: row.get(1)?,
+        content: row.get(2)?,
+        version: row.get(3)?,
+        updated_by_chat_id: row.get(4)?,
+        updated_at: row.get(5)?,
+        shared_with: Vec::new(),
+    })
+}
+
+fn scratchpad_shares(conn: &Connection, name: &str) -> Result<Vec<i64>, MicroClawError> {
+    let mut stmt =
+        conn.prepare("SELECT chat_id FROM scratchpad_shares WHERE name = ?1 ORDER BY chat_id")?;
+    let chats = stmt
+        .query_map(params![name], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(chats)
+}
+
+/// What a retention pass removes; `None` keeps everything of that kind.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionCutoffs {
+    pub messages_before: Option<String>,
+    pub task_runs_before: Option<String>,
+    pub finished_tasks_before: Option<String>,
+    pub audit_log_max_rows: Option<i64>,
+}
+
+/// Rows a retention pass removed, or would remove on a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    pub messages: usize,
+    pub task_runs: usize,
+    pub finished_tasks: usize,
+    pub audit_logs: usize,
+}
+
+impl RetentionReport {
+    pub fn total(&self) -> usize {
+        self.messages + self.task_runs + self.finished_tasks + self.audit_logs
+    }
+}
+
+/// What the session becomes when switching branches.
+#[derive(Debug, Clone, Copy)]
+pub enum BranchStart<'a> {
+    /// Restore the parked branch of that name.
+    Parked,
+    /// A new branch continuing from these messages and the current
+    /// compaction summary.
+    Fork(&'a str),
+    /// A new, empty session.
+    Empty,
+}
+
+/// Name of the branch a chat's session is on until it forks.
+pub const DEFAULT_SESSION_BRANCH: &str = "main";
+
+const SCHEMA_VERSION_CURRENT: i64 = 21;
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -148,9 +457,9 @@ pub struct ScheduledTask {
     pub id: i64,
     pub chat_id: i64,
     pub prompt: String,
-    pub schedule_type: String,  // "cron" or "once"
-    pub schedule_value: String, // cron expression or ISO timestamp
-    pub next_run: String,       // ISO timestamp
+    pub schedule_type: String,  // "cron", "once" or "webhook"
+    pub schedule_value: String, // cron expression, ISO timestamp or webhook secret
+    pub next_run: String,       // ISO timestamp; empty for webhook tasks
     pub last_run: Option<String>,
     pub status: String, // "active", "paused", "completed", "cancelled"
     pub created_at: String,
@@ -383,6 +692,252 @@ fn apply_schema_migrations(conn: &Connection) -> Result<(), MicroClawError> {
         set_schema_version(conn, 4)?;
         version = 4;
     }
+    if version < 5 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS model_comparisons (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                caller_channel TEXT NOT NULL,
+                prompt TEXT NOT NULL,
+                model_a TEXT NOT NULL,
+                answer_a TEXT NOT NULL,
+                model_b TEXT NOT NULL,
+                answer_b TEXT NOT NULL,
+                preferred TEXT,
+                created_at TEXT NOT NULL,
+                decided_at TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_model_comparisons_chat_created
+                ON model_comparisons(chat_id, created_at);",
+        )?;
+        set_schema_version(conn, 5)?;
+        version = 5;
+    }
+    if version < 6 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS user_preferences (
+                chat_id INTEGER NOT NULL,
+                pref_key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                source TEXT NOT NULL DEFAULT 'inferred',
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (chat_id, pref_key)
+            );",
+        )?;
+        set_schema_version(conn, 6)?;
+        version = 6;
+    }
+    if version < 7 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS chat_workspaces (
+                chat_id INTEGER PRIMARY KEY,
+                isolation TEXT,
+                topic TEXT,
+                session_id TEXT,
+                updated_at TEXT NOT NULL
+            );",
+        )?;
+        set_schema_version(conn, 7)?;
+        version = 7;
+    }
+    if version < 8 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS identity_links (
+                chat_id INTEGER PRIMARY KEY,
+                identity_chat_id INTEGER NOT NULL,
+                linked_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_identity_links_identity
+                ON identity_links(identity_chat_id);
+            CREATE TABLE IF NOT EXISTS identity_link_codes (
+                code TEXT PRIMARY KEY,
+                identity_chat_id INTEGER NOT NULL,
+                expires_at TEXT NOT NULL
+            );",
+        )?;
+        set_schema_version(conn, 8)?;
+        version = 8;
+    }
+    if version < 9 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS chat_settings (
+                chat_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (chat_id, key)
+            );",
+        )?;
+        set_schema_version(conn, 9)?;
+        version = 9;
+    }
+    if version < 10 {
+        if !table_has_column(conn, "sessions", "summary")? {
+            conn.execute("ALTER TABLE sessions ADD COLUMN summary TEXT", [])?;
+        }
+        set_schema_version(conn, 10)?;
+        version = 10;
+    }
+    if version < 11 {
+        if !table_has_column(conn, "sessions", "persona")? {
+            conn.execute("ALTER TABLE sessions ADD COLUMN persona TEXT", [])?;
+        }
+        set_schema_version(conn, 11)?;
+        version = 11;
+    }
+    if version < 12 {
+        if !table_has_column(conn, "llm_usage_logs", "user_id")? {
+            conn.execute("ALTER TABLE llm_usage_logs ADD COLUMN user_id TEXT", [])?;
+        }
+        set_schema_version(conn, 12)?;
+        version = 12;
+    }
+    if version < 13 {
+        if !table_has_column(conn, "sessions", "branch")? {
+            conn.execute("ALTER TABLE sessions ADD COLUMN branch TEXT", [])?;
+        }
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS session_branches (
+                chat_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                messages_json TEXT NOT NULL,
+                summary TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (chat_id, name)
+            );",
+        )?;
+        set_schema_version(conn, 13)?;
+        version = 13;
+    }
+    if version < 14 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS knowledge_documents (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL UNIQUE,
+                content_hash TEXT NOT NULL,
+                embedding_model TEXT,
+                chunk_count INTEGER NOT NULL DEFAULT 0,
+                indexed_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS knowledge_chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                document_id INTEGER NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                embedding BLOB
+            );
+            CREATE INDEX IF NOT EXISTS idx_knowledge_chunks_document
+                ON knowledge_chunks(document_id);",
+        )?;
+        set_schema_version(conn, 14)?;
+        version = 14;
+    }
+    if version < 15 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS feed_subscriptions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                title TEXT,
+                filter TEXT,
+                created_at TEXT NOT NULL,
+                last_checked_at TEXT,
+                last_error TEXT,
+                UNIQUE (chat_id, url)
+            );
+            CREATE TABLE IF NOT EXISTS feed_seen_items (
+                subscription_id INTEGER NOT NULL,
+                item_key TEXT NOT NULL,
+                seen_at TEXT NOT NULL,
+                PRIMARY KEY (subscription_id, item_key)
+            );",
+        )?;
+        set_schema_version(conn, 15)?;
+        version = 15;
+    }
+    if version < 16 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS web_sessions (
+                token_hash TEXT PRIMARY KEY,
+                username TEXT NOT NULL,
+                csrf_token TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            );",
+        )?;
+        set_schema_version(conn, 16)?;
+        version = 16;
+    }
+    if version < 17 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS path_watches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id INTEGER NOT NULL,
+                root TEXT NOT NULL,
+                pattern TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                UNIQUE (chat_id, root, pattern)
+            );",
+        )?;
+        set_schema_version(conn, 17)?;
+        version = 17;
+    }
+    if version < 18 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS scratchpads (
+                name TEXT PRIMARY KEY,
+                owner_chat_id INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                version INTEGER NOT NULL DEFAULT 1,
+                updated_by_chat_id INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS scratchpad_shares (
+                name TEXT NOT NULL,
+                chat_id INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (name, chat_id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_scratchpad_shares_chat ON scratchpad_shares(chat_id);",
+        )?;
+        set_schema_version(conn, 18)?;
+        version = 18;
+    }
+    if version < 19 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS glossary_terms (
+                chat_id INTEGER NOT NULL,
+                language TEXT NOT NULL,
+                term TEXT NOT NULL COLLATE NOCASE,
+                translation TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (chat_id, language, term)
+            );",
+        )?;
+        set_schema_version(conn, 19)?;
+        version = 19;
+    }
+    if version < 20 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS email_threads (
+                chat_id INTEGER PRIMARY KEY,
+                subject TEXT NOT NULL,
+                message_ids TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );",
+        )?;
+        set_schema_version(conn, 20)?;
+        version = 20;
+    }
+    if version < 21 {
+        conn.execute(
+            "ALTER TABLE identity_link_codes ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+        set_schema_version(conn, 21)?;
+        version = 21;
+    }
     if version != SCHEMA_VERSION_CURRENT {
         set_schema_version(conn, SCHEMA_VERSION_CURRENT)?;
     }
@@ -408,7 +963,7 @@ impl Database {
             rusqlite::ffi::sqlite3_auto_extension(Some(init_fn));
         });
 
-        let conn = Connection::open(db_path)?;
+        let conn = crate::db_crypt::open(&db_path, rusqlite::OpenFlags::default())?;
         conn.execute_batch("PRAGMA journal_mode=WAL;")?;
 
         conn.execute_batch(
@@ -564,6 +1119,13 @@ impl Database {
         })
     }
 
+    /// A trivial query, to check the database answers.
+    pub fn ping(&self) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        conn.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+
     pub fn upsert_chat(
         &self,
         chat_id: i64,
@@ -808,6 +1370,22 @@ impl Database {
         }
     }
 
+    pub fn get_chat_id_by_external(
+        &self,
+        channel: &str,
+        external_chat_id: &str,
+    ) -> Result<Option<i64>, MicroClawError> {
+        let conn = self.lock_conn();
+        let chat_id = conn
+            .query_row(
+                "SELECT chat_id FROM chats WHERE channel = ?1 AND external_chat_id = ?2 LIMIT 1",
+                params![channel, external_chat_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(chat_id)
+    }
+
     pub fn get_chat_external_id(&self, chat_id: i64) -> Result<Option<String>, MicroClawError> {
         let conn = self.lock_conn();
         let result = conn.query_row(
@@ -916,7 +1494,7 @@ impl Database {
         let mut stmt = conn.prepare(
             "SELECT id, chat_id, prompt, schedule_type, schedule_value, next_run, last_run, status, created_at
              FROM scheduled_tasks
-             WHERE status = 'active' AND next_run <= ?1",
+             WHERE status = 'active' AND schedule_type != 'webhook' AND next_run <= ?1",
         )?;
         let tasks = stmt
             .query_map(params![now], |row| {
@@ -1084,6 +1662,46 @@ impl Database {
         Ok(logs)
     }
 
+    pub fn get_task_run_stats(&self, task_id: i64) -> Result<TaskRunStats, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stats = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(success = 0), 0),
+                    CAST(COALESCE(AVG(duration_ms), 0) AS INTEGER), COALESCE(MAX(duration_ms), 0),
+                    MAX(CASE WHEN success != 0 THEN started_at END)
+             FROM task_run_logs
+             WHERE task_id = ?1",
+            params![task_id],
+            |row| {
+                Ok(TaskRunStats {
+                    runs: row.get(0)?,
+                    failures: row.get(1)?,
+                    avg_duration_ms: row.get(2)?,
+                    max_duration_ms: row.get(3)?,
+                    last_success_at: row.get(4)?,
+                    ..Default::default()
+                })
+            },
+        )?;
+        stats.consecutive_failures = conn.query_row(
+            "SELECT COUNT(*) FROM task_run_logs
+             WHERE task_id = ?1 AND success = 0 AND id > COALESCE(
+                 (SELECT MAX(id) FROM task_run_logs WHERE task_id = ?1 AND success != 0), 0)",
+            params![task_id],
+            |row| row.get(0),
+        )?;
+        stats.last_error = conn
+            .query_row(
+                "SELECT result_summary FROM task_run_logs
+                 WHERE task_id = ?1 AND success = 0
+                 ORDER BY id DESC LIMIT 1",
+                params![task_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(stats)
+    }
+
     #[allow(dead_code)]
     pub fn delete_task(&self, task_id: i64) -> Result<bool, MicroClawError> {
         let conn = self.lock_conn();
@@ -1124,183 +1742,1581 @@ impl Database {
         }
     }
 
-    pub fn delete_session(&self, chat_id: i64) -> Result<bool, MicroClawError> {
-        let conn = self.lock_conn();
-        let rows = conn.execute("DELETE FROM sessions WHERE chat_id = ?1", params![chat_id])?;
-        Ok(rows > 0)
-    }
-
-    /// Clear conversational context for a chat without deleting chat metadata or memories.
-    /// This removes resumable session state and historical messages used to rebuild context.
-    pub fn clear_chat_context(&self, chat_id: i64) -> Result<bool, MicroClawError> {
+    /// Store the compaction summary for a chat's session. It lives in the
+    /// session row, so deleting or resetting the session drops it too.
+    pub fn save_session_summary(&self, chat_id: i64, summary: &str) -> Result<(), MicroClawError> {
         let conn = self.lock_conn();
-        let tx = conn.unchecked_transaction()?;
-        let mut affected = 0usize;
-        affected += tx.execute("DELETE FROM sessions WHERE chat_id = ?1", params![chat_id])?;
-        affected += tx.execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])?;
-        tx.commit()?;
-        Ok(affected > 0)
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO sessions (chat_id, messages_json, updated_at, summary)
+             VALUES (?1, '[]', ?2, ?3)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                summary = ?3,
+                updated_at = ?2",
+            params![chat_id, now, summary],
+        )?;
+        Ok(())
     }
 
-    pub fn delete_chat_data(&self, chat_id: i64) -> Result<bool, MicroClawError> {
+    pub fn load_session_summary(&self, chat_id: i64) -> Result<Option<String>, MicroClawError> {
         let conn = self.lock_conn();
-        let tx = conn.unchecked_transaction()?;
-        let mut affected = 0usize;
-
-        affected += tx.execute(
-            "DELETE FROM llm_usage_logs WHERE chat_id = ?1",
+        let result = conn.query_row(
+            "SELECT summary FROM sessions WHERE chat_id = ?1",
             params![chat_id],
+            |row| row.get::<_, Option<String>>(0),
+        );
+        match result {
+            Ok(summary) => Ok(summary.filter(|s| !s.trim().is_empty())),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record which persona the chat's session is running under (`None` =
+    /// the default). Leaves `updated_at` alone so it still tracks activity.
+    pub fn set_session_persona(
+        &self,
+        chat_id: i64,
+        persona: Option<&str>,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO sessions (chat_id, messages_json, updated_at, persona)
+             VALUES (?1, '[]', ?2, ?3)
+             ON CONFLICT(chat_id) DO UPDATE SET persona = ?3",
+            params![chat_id, now, persona],
         )?;
-        affected += tx.execute("DELETE FROM sessions WHERE chat_id = ?1", params![chat_id])?;
-        affected += tx.execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])?;
-        affected += tx.execute(
-            "DELETE FROM scheduled_tasks WHERE chat_id = ?1",
+        Ok(())
+    }
+
+    pub fn load_session_persona(&self, chat_id: i64) -> Result<Option<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let result = conn.query_row(
+            "SELECT persona FROM sessions WHERE chat_id = ?1",
             params![chat_id],
+            |row| row.get::<_, Option<String>>(0),
+        );
+        match result {
+            Ok(persona) => Ok(persona),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The branch the chat's session is on.
+    pub fn load_session_branch(&self, chat_id: i64) -> Result<String, MicroClawError> {
+        let conn = self.lock_conn();
+        let branch = conn
+            .query_row(
+                "SELECT branch FROM sessions WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(branch.unwrap_or_else(|| DEFAULT_SESSION_BRANCH.to_string()))
+    }
+
+    /// The chat's parked branches, by name.
+    pub fn list_session_branches(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<SessionBranch>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT name, messages_json, updated_at FROM session_branches
+             WHERE chat_id = ?1 ORDER BY name",
         )?;
-        affected += tx.execute(
-            "DELETE FROM memory_reflector_state WHERE chat_id = ?1",
-            params![chat_id],
+        let rows = stmt
+            .query_map(params![chat_id], |row| {
+                Ok(SessionBranch {
+                    name: row.get(0)?,
+                    messages_json: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Park the current session under its branch name and make `target` the
+    /// active branch, started as `start` says. Returns `false` when restoring
+    /// a parked branch that doesn't exist.
+    pub fn switch_session_branch(
+        &self,
+        chat_id: i64,
+        target: &str,
+        start: BranchStart<'_>,
+    ) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let current: Option<(String, Option<String>, Option<String>)> = tx
+            .query_row(
+                "SELECT messages_json, summary, branch FROM sessions WHERE chat_id = ?1",
+                params![chat_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let (messages_json, summary, branch) =
+            current.unwrap_or_else(|| ("[]".to_string(), None, None));
+        let branch = branch.unwrap_or_else(|| DEFAULT_SESSION_BRANCH.to_string());
+
+        let (next_messages, next_summary) = match start {
+            BranchStart::Fork(messages) => (messages.to_string(), summary.clone()),
+            BranchStart::Empty => ("[]".to_string(), None),
+            BranchStart::Parked => {
+                let parked: Option<(String, Option<String>)> = tx
+                    .query_row(
+                        "SELECT messages_json, summary FROM session_branches
+                         WHERE chat_id = ?1 AND name = ?2",
+                        params![chat_id, target],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()?;
+                let Some(parked) = parked else {
+                    return Ok(false);
+                };
+                tx.execute(
+                    "DELETE FROM session_branches WHERE chat_id = ?1 AND name = ?2",
+                    params![chat_id, target],
+                )?;
+                parked
+            }
+        };
+
+        let now = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO session_branches (chat_id, name, messages_json, summary, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(chat_id, name) DO UPDATE SET
+                messages_json = excluded.messages_json,
+                summary = excluded.summary,
+                updated_at = excluded.updated_at",
+            params![chat_id, branch, messages_json, summary, now],
         )?;
-        affected += tx.execute(
-            "DELETE FROM memory_reflector_runs WHERE chat_id = ?1",
-            params![chat_id],
+        tx.execute(
+            "INSERT INTO sessions (chat_id, messages_json, updated_at, summary, branch)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                messages_json = ?2,
+                updated_at = ?3,
+                summary = ?4,
+                branch = ?5",
+            params![chat_id, next_messages, now, next_summary, target],
         )?;
-        affected += tx.execute(
-            "DELETE FROM memory_injection_logs WHERE chat_id = ?1",
-            params![chat_id],
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub fn delete_session_branch(&self, chat_id: i64, name: &str) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute(
+            "DELETE FROM session_branches WHERE chat_id = ?1 AND name = ?2",
+            params![chat_id, name],
         )?;
-        affected += tx.execute(
-            "DELETE FROM memory_supersede_edges
-             WHERE from_memory_id IN (SELECT id FROM memories WHERE chat_id = ?1)
-                OR to_memory_id IN (SELECT id FROM memories WHERE chat_id = ?1)",
-            params![chat_id],
+        Ok(rows > 0)
+    }
+
+    pub fn delete_session(&self, chat_id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute("DELETE FROM sessions WHERE chat_id = ?1", params![chat_id])?;
+        Ok(rows > 0)
+    }
+
+    pub fn list_knowledge_documents(&self) -> Result<Vec<KnowledgeDocument>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT source, content_hash, embedding_model, chunk_count, indexed_at
+             FROM knowledge_documents ORDER BY source",
         )?;
-        affected += tx.execute("DELETE FROM memories WHERE chat_id = ?1", params![chat_id])?;
-        affected += tx.execute("DELETE FROM chats WHERE chat_id = ?1", params![chat_id])?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(KnowledgeDocument {
+                    source: row.get(0)?,
+                    content_hash: row.get(1)?,
+                    embedding_model: row.get(2)?,
+                    chunk_count: row.get::<_, i64>(3)? as usize,
+                    indexed_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
+    /// Store `source` with `chunks` (text and optional vector), replacing any
+    /// earlier version of it.
+    pub fn replace_knowledge_document(
+        &self,
+        source: &str,
+        content_hash: &str,
+        embedding_model: Option<&str>,
+        chunks: &[(String, Option<Vec<f32>>)],
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM knowledge_chunks WHERE document_id IN
+                (SELECT id FROM knowledge_documents WHERE source = ?1)",
+            params![source],
+        )?;
+        tx.execute(
+            "INSERT INTO knowledge_documents
+                (source, content_hash, embedding_model, chunk_count, indexed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(source) DO UPDATE SET
+                content_hash = excluded.content_hash,
+                embedding_model = excluded.embedding_model,
+                chunk_count = excluded.chunk_count,
+                indexed_at = excluded.indexed_at",
+            params![
+                source,
+                content_hash,
+                embedding_model,
+                chunks.len() as i64,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        let document_id: i64 = tx.query_row(
+            "SELECT id FROM knowledge_documents WHERE source = ?1",
+            params![source],
+            |row| row.get(0),
+        )?;
+        for (index, (content, embedding)) in chunks.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO knowledge_chunks (document_id, chunk_index, content, embedding)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    document_id,
+                    index as i64,
+                    content,
+                    embedding.as_deref().map(vector_to_blob)
+                ],
+            )?;
+        }
         tx.commit()?;
-        Ok(affected > 0)
+        Ok(())
     }
 
-    pub fn get_new_user_messages_since(
+    pub fn delete_knowledge_document(&self, source: &str) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM knowledge_chunks WHERE document_id IN
+                (SELECT id FROM knowledge_documents WHERE source = ?1)",
+            params![source],
+        )?;
+        let deleted = tx.execute(
+            "DELETE FROM knowledge_documents WHERE source = ?1",
+            params![source],
+        )?;
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    /// Every stored chunk; retrieval scores them in memory.
+    pub fn knowledge_chunks(&self) -> Result<Vec<KnowledgeChunk>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT d.source, c.chunk_index, c.content, c.embedding
+             FROM knowledge_chunks c JOIN knowledge_documents d ON d.id = c.document_id
+             ORDER BY d.source, c.chunk_index",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                let blob: Option<Vec<u8>> = row.get(3)?;
+                Ok(KnowledgeChunk {
+                    source: row.get(0)?,
+                    chunk_index: row.get::<_, i64>(1)? as usize,
+                    content: row.get(2)?,
+                    embedding: blob.as_deref().map(blob_to_vector),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Subscribe `chat_id` to `url`, or update the title and filter of an
+    /// existing subscription. Returns the subscription id.
+    pub fn upsert_feed_subscription(
         &self,
         chat_id: i64,
-        since: &str,
-    ) -> Result<Vec<StoredMessage>, MicroClawError> {
+        url: &str,
+        title: Option<&str>,
+        filter: Option<&str>,
+    ) -> Result<i64, MicroClawError> {
+        let conn = self.lock_conn();
+        let id = conn.query_row(
+            "INSERT INTO feed_subscriptions (chat_id, url, title, filter, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(chat_id, url) DO UPDATE SET title = excluded.title, filter = excluded.filter
+             RETURNING id",
+            params![chat_id, url, title, filter, chrono::Utc::now().to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Subscriptions of `chat_id`, or of every chat when `None`.
+    pub fn list_feed_subscriptions(
+        &self,
+        chat_id: Option<i64>,
+    ) -> Result<Vec<FeedSubscription>, MicroClawError> {
         let conn = self.lock_conn();
         let mut stmt = conn.prepare(
-            "SELECT id, chat_id, sender_name, content, is_from_bot, timestamp
-             FROM messages
-             WHERE chat_id = ?1 AND timestamp > ?2 AND is_from_bot = 0
-             ORDER BY timestamp ASC",
+            "SELECT id, chat_id, url, title, filter, created_at, last_checked_at, last_error
+             FROM feed_subscriptions WHERE ?1 IS NULL OR chat_id = ?1 ORDER BY id",
         )?;
-        let messages = stmt
-            .query_map(params![chat_id, since], |row| {
-                Ok(StoredMessage {
+        let rows = stmt
+            .query_map(params![chat_id], |row| {
+                Ok(FeedSubscription {
                     id: row.get(0)?,
                     chat_id: row.get(1)?,
-                    sender_name: row.get(2)?,
-                    content: row.get(3)?,
-                    is_from_bot: row.get::<_, i32>(4)? != 0,
-                    timestamp: row.get(5)?,
+                    url: row.get(2)?,
+                    title: row.get(3)?,
+                    filter: row.get(4)?,
+                    created_at: row.get(5)?,
+                    last_checked_at: row.get(6)?,
+                    last_error: row.get(7)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(messages)
+        Ok(rows)
     }
 
-    pub fn get_messages_since(
+    /// Watch `pattern` under `root` for `chat_id`. Returns the watch id; an
+    /// identical existing watch is reused.
+    pub fn create_path_watch(
         &self,
         chat_id: i64,
-        since: &str,
-        limit: usize,
-    ) -> Result<Vec<StoredMessage>, MicroClawError> {
+        root: &str,
+        pattern: &str,
+    ) -> Result<i64, MicroClawError> {
+        let conn = self.lock_conn();
+        let id = conn.query_row(
+            "INSERT INTO path_watches (chat_id, root, pattern, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(chat_id, root, pattern) DO UPDATE SET root = excluded.root
+             RETURNING id",
+            params![chat_id, root, pattern, chrono::Utc::now().to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Watches of `chat_id`, or of every chat when `None`.
+    pub fn list_path_watches(
+        &self,
+        chat_id: Option<i64>,
+    ) -> Result<Vec<PathWatch>, MicroClawError> {
         let conn = self.lock_conn();
         let mut stmt = conn.prepare(
-            "SELECT id, chat_id, sender_name, content, is_from_bot, timestamp
-             FROM messages
-             WHERE chat_id = ?1 AND timestamp > ?2
-             ORDER BY timestamp ASC
-             LIMIT ?3",
+            "SELECT id, chat_id, root, pattern, created_at
+             FROM path_watches WHERE ?1 IS NULL OR chat_id = ?1 ORDER BY id",
         )?;
-        let messages = stmt
-            .query_map(params![chat_id, since, limit as i64], |row| {
-                Ok(StoredMessage {
+        let rows = stmt
+            .query_map(params![chat_id], |row| {
+                Ok(PathWatch {
                     id: row.get(0)?,
                     chat_id: row.get(1)?,
-                    sender_name: row.get(2)?,
-                    content: row.get(3)?,
-                    is_from_bot: row.get::<_, i32>(4)? != 0,
-                    timestamp: row.get(5)?,
+                    root: row.get(2)?,
+                    pattern: row.get(3)?,
+                    created_at: row.get(4)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(messages)
+        Ok(rows)
     }
 
-    pub fn get_reflector_cursor(&self, chat_id: i64) -> Result<Option<String>, MicroClawError> {
+    pub fn delete_path_watch(&self, chat_id: i64, id: i64) -> Result<bool, MicroClawError> {
         let conn = self.lock_conn();
-        let result = conn.query_row(
-            "SELECT last_reflected_ts FROM memory_reflector_state WHERE chat_id = ?1",
-            params![chat_id],
-            |row| row.get::<_, String>(0),
-        );
-        match result {
-            Ok(ts) => Ok(Some(ts)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+        let deleted = conn.execute(
+            "DELETE FROM path_watches WHERE id = ?1 AND chat_id = ?2",
+            params![id, chat_id],
+        )?;
+        Ok(deleted > 0)
     }
 
-    pub fn set_reflector_cursor(
+    pub fn get_scratchpad(&self, name: &str) -> Result<Option<Scratchpad>, MicroClawError> {
+        let conn = self.lock_conn();
+        let pad = conn
+            .query_row(
+                "SELECT name, owner_chat_id, content, version, updated_by_chat_id, updated_at
+                 FROM scratchpads WHERE name = ?1",
+                params![name],
+                scratchpad_from_row,
+            )
+            .optional()?;
+        let Some(mut pad) = pad else {
+            return Ok(None);
+        };
+        pad.shared_with = scratchpad_shares(&conn, name)?;
+        Ok(Some(pad))
+    }
+
+    /// Replace (or with `append`, extend) the content of `name`, creating the
+    /// pad owned by `chat_id` if it does not exist yet.
+    pub fn write_scratchpad(
         &self,
+        name: &str,
         chat_id: i64,
-        last_reflected_ts: &str,
-    ) -> Result<(), MicroClawError> {
+        content: &str,
+        append: bool,
+    ) -> Result<Scratchpad, MicroClawError> {
         let conn = self.lock_conn();
         let now = chrono::Utc::now().to_rfc3339();
-        conn.execute(
-            "INSERT INTO memory_reflector_state (chat_id, last_reflected_ts, updated_at)
-             VALUES (?1, ?2, ?3)
-             ON CONFLICT(chat_id) DO UPDATE SET
-                last_reflected_ts = excluded.last_reflected_ts,
-                updated_at = excluded.updated_at",
-            params![chat_id, last_reflected_ts, now],
+        let mut pad = conn.query_row(
+            "INSERT INTO scratchpads
+                (name, owner_chat_id, content, version, updated_by_chat_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 1, ?2, ?4, ?4)
+             ON CONFLICT(name) DO UPDATE SET
+                content = CASE WHEN ?5 THEN scratchpads.content || excluded.content
+                               ELSE excluded.content END,
+                version = scratchpads.version + 1,
+                updated_by_chat_id = excluded.updated_by_chat_id,
+                updated_at = excluded.updated_at
+             RETURNING name, owner_chat_id, content, version, updated_by_chat_id, updated_at",
+            params![name, chat_id, content, now, append],
+            scratchpad_from_row,
         )?;
-        Ok(())
+        pad.shared_with = scratchpad_shares(&conn, name)?;
+        Ok(pad)
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub fn log_llm_usage(
+    /// Pads owned by or shared with `chat_id`, or all pads when `None`.
+    pub fn list_scratchpads(
         &self,
-        chat_id: i64,
-        caller_channel: &str,
-        provider: &str,
-        model: &str,
-        input_tokens: i64,
-        output_tokens: i64,
-        request_kind: &str,
-    ) -> Result<i64, MicroClawError> {
+        chat_id: Option<i64>,
+    ) -> Result<Vec<Scratchpad>, MicroClawError> {
         let conn = self.lock_conn();
-        let now = chrono::Utc::now().to_rfc3339();
-        let total_tokens = input_tokens.saturating_add(output_tokens);
-        conn.execute(
-            "INSERT INTO llm_usage_logs
-                (chat_id, caller_channel, provider, model, input_tokens, output_tokens, total_tokens, request_kind, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            params![
-                chat_id,
-                caller_channel,
-                provider,
-                model,
-                input_tokens,
-                output_tokens,
-                total_tokens,
-                request_kind,
-                now,
-            ],
+        let mut stmt = conn.prepare(
+            "SELECT name, owner_chat_id, content, version, updated_by_chat_id, updated_at
+             FROM scratchpads
+             WHERE ?1 IS NULL OR owner_chat_id = ?1
+                OR name IN (SELECT name FROM scratchpad_shares WHERE chat_id = ?1)
+             ORDER BY name",
+        )?;
+        let mut pads = stmt
+            .query_map(params![chat_id], scratchpad_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        for pad in &mut pads {
+            pad.shared_with = scratchpad_shares(&conn, &pad.name)?;
+        }
+        Ok(pads)
+    }
+
+    /// Give `chat_id` access to `name`. False if the pad does not exist.
+    pub fn share_scratchpad(&self, name: &str, chat_id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO scratchpad_shares (name, chat_id, created_at)
+             SELECT name, ?2, ?3 FROM scratchpads WHERE name = ?1",
+            params![name, chat_id, chrono::Utc::now().to_rfc3339()],
+        )?;
+        if inserted > 0 {
+            return Ok(true);
+        }
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM scratchpads WHERE name = ?1)",
+            params![name],
+            |row| row.get(0),
+        )?;
+        Ok(exists)
+    }
+
+    pub fn unshare_scratchpad(&self, name: &str, chat_id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let deleted = conn.execute(
+            "DELETE FROM scratchpad_shares WHERE name = ?1 AND chat_id = ?2",
+            params![name, chat_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    pub fn delete_scratchpad(&self, name: &str) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM scratchpad_shares WHERE name = ?1",
+            params![name],
+        )?;
+        let deleted = tx.execute("DELETE FROM scratchpads WHERE name = ?1", params![name])?;
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    pub fn save_email_thread(
+        &self,
+        chat_id: i64,
+        thread: &EmailThread,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let message_ids = serde_json::to_string(&thread.message_ids)?;
+        conn.execute(
+            "INSERT INTO email_threads (chat_id, subject, message_ids, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                subject = excluded.subject,
+                message_ids = excluded.message_ids,
+                updated_at = excluded.updated_at",
+            params![
+                chat_id,
+                thread.subject,
+                message_ids,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn load_email_thread(&self, chat_id: i64) -> Result<Option<EmailThread>, MicroClawError> {
+        let conn = self.lock_conn();
+        let row = conn
+            .query_row(
+                "SELECT subject, message_ids FROM email_threads WHERE chat_id = ?1",
+                params![chat_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        let Some((subject, message_ids)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(EmailThread {
+            subject,
+            message_ids: serde_json::from_str(&message_ids)?,
+        }))
+    }
+
+    /// Add or replace `term` in the chat's glossary for `language`.
+    pub fn set_glossary_term(
+        &self,
+        chat_id: i64,
+        language: &str,
+        term: &str,
+        translation: &str,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "INSERT INTO glossary_terms (chat_id, language, term, translation, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(chat_id, language, term) DO UPDATE SET
+                term = excluded.term,
+                translation = excluded.translation,
+                updated_at = excluded.updated_at",
+            params![
+                chat_id,
+                language,
+                term,
+                translation,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_glossary_term(
+        &self,
+        chat_id: i64,
+        language: &str,
+        term: &str,
+    ) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let deleted = conn.execute(
+            "DELETE FROM glossary_terms WHERE chat_id = ?1 AND language = ?2 AND term = ?3",
+            params![chat_id, language, term],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// The chat's glossary; with `language`, only entries for that language
+    /// and for every language.
+    pub fn list_glossary_terms(
+        &self,
+        chat_id: i64,
+        language: Option<&str>,
+    ) -> Result<Vec<GlossaryTerm>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT term, translation, language, updated_at
+             FROM glossary_terms
+             WHERE chat_id = ?1 AND (?2 IS NULL OR language = ?2 OR language = '')
+             ORDER BY language, term",
+        )?;
+        let terms = stmt
+            .query_map(params![chat_id, language], |row| {
+                Ok(GlossaryTerm {
+                    term: row.get(0)?,
+                    translation: row.get(1)?,
+                    language: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(terms)
+    }
+
+    pub fn delete_feed_subscription(&self, chat_id: i64, id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let deleted = tx.execute(
+            "DELETE FROM feed_subscriptions WHERE id = ?1 AND chat_id = ?2",
+            params![id, chat_id],
+        )?;
+        if deleted > 0 {
+            tx.execute(
+                "DELETE FROM feed_seen_items WHERE subscription_id = ?1",
+                params![id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    /// Remember `keys` as seen for a subscription and return the ones that
+    /// were not seen before, in the given order.
+    pub fn record_feed_items(
+        &self,
+        subscription_id: i64,
+        keys: &[String],
+    ) -> Result<Vec<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        let mut new_keys = Vec::new();
+        for key in keys {
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO feed_seen_items (subscription_id, item_key, seen_at)
+                 VALUES (?1, ?2, ?3)",
+                params![subscription_id, key, now],
+            )?;
+            if inserted > 0 {
+                new_keys.push(key.clone());
+            }
+        }
+        tx.execute(
+            "DELETE FROM feed_seen_items WHERE subscription_id = ?1 AND item_key NOT IN (
+                SELECT item_key FROM feed_seen_items WHERE subscription_id = ?1
+                ORDER BY seen_at DESC LIMIT ?2
+             )",
+            params![subscription_id, MAX_SEEN_FEED_ITEMS],
+        )?;
+        tx.commit()?;
+        Ok(new_keys)
+    }
+
+    pub fn update_feed_check(
+        &self,
+        subscription_id: i64,
+        error: Option<&str>,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "UPDATE feed_subscriptions SET last_checked_at = ?2, last_error = ?3 WHERE id = ?1",
+            params![subscription_id, chrono::Utc::now().to_rfc3339(), error],
+        )?;
+        Ok(())
+    }
+
+    /// Store a web login under the hash of its cookie token, dropping expired
+    /// ones.
+    pub fn create_web_session(
+        &self,
+        token_hash: &str,
+        session: &WebSession,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "DELETE FROM web_sessions WHERE expires_at <= ?1",
+            params![now],
+        )?;
+        conn.execute(
+            "INSERT INTO web_sessions (token_hash, username, csrf_token, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                token_hash,
+                session.username,
+                session.csrf_token,
+                now,
+                session.expires_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The unexpired web login for a cookie token hash.
+    pub fn get_web_session(&self, token_hash: &str) -> Result<Option<WebSession>, MicroClawError> {
+        let conn = self.lock_conn();
+        let result = conn.query_row(
+            "SELECT username, csrf_token, expires_at FROM web_sessions
+             WHERE token_hash = ?1 AND expires_at > ?2",
+            params![token_hash, chrono::Utc::now().to_rfc3339()],
+            |row| {
+                Ok(WebSession {
+                    username: row.get(0)?,
+                    csrf_token: row.get(1)?,
+                    expires_at: row.get(2)?,
+                })
+            },
+        );
+        match result {
+            Ok(session) => Ok(Some(session)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn delete_web_session(&self, token_hash: &str) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let rows = conn.execute(
+            "DELETE FROM web_sessions WHERE token_hash = ?1",
+            params![token_hash],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Clear conversational context for a chat without deleting chat metadata or memories.
+    /// This removes resumable session state and historical messages used to rebuild context.
+    pub fn clear_chat_context(&self, chat_id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let mut affected = 0usize;
+        affected += tx.execute("DELETE FROM sessions WHERE chat_id = ?1", params![chat_id])?;
+        affected += tx.execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])?;
+        affected += tx.execute(
+            "DELETE FROM session_branches WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        // A cleared context starts a new session-scoped workspace.
+        tx.execute(
+            "UPDATE chat_workspaces SET session_id = NULL WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        tx.commit()?;
+        Ok(affected > 0)
+    }
+
+    pub fn delete_chat_data(&self, chat_id: i64) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let mut affected = 0usize;
+
+        affected += tx.execute(
+            "DELETE FROM llm_usage_logs WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute("DELETE FROM sessions WHERE chat_id = ?1", params![chat_id])?;
+        affected += tx.execute(
+            "DELETE FROM session_branches WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute("DELETE FROM messages WHERE chat_id = ?1", params![chat_id])?;
+        affected += tx.execute(
+            "DELETE FROM scheduled_tasks WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM feed_seen_items WHERE subscription_id IN (
+                SELECT id FROM feed_subscriptions WHERE chat_id = ?1
+             )",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM feed_subscriptions WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM path_watches WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM scratchpad_shares WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM glossary_terms WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM email_threads WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM memory_reflector_state WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM memory_reflector_runs WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM memory_injection_logs WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM memory_supersede_edges
+             WHERE from_memory_id IN (SELECT id FROM memories WHERE chat_id = ?1)
+                OR to_memory_id IN (SELECT id FROM memories WHERE chat_id = ?1)",
+            params![chat_id],
+        )?;
+        affected += tx.execute("DELETE FROM memories WHERE chat_id = ?1", params![chat_id])?;
+        affected += tx.execute(
+            "DELETE FROM model_comparisons WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM user_preferences WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM chat_workspaces WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM identity_links WHERE chat_id = ?1 OR identity_chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM identity_link_codes WHERE identity_chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute(
+            "DELETE FROM chat_settings WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        affected += tx.execute("DELETE FROM chats WHERE chat_id = ?1", params![chat_id])?;
+
+        tx.commit()?;
+        Ok(affected > 0)
+    }
+
+    pub fn get_new_user_messages_since(
+        &self,
+        chat_id: i64,
+        since: &str,
+    ) -> Result<Vec<StoredMessage>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, chat_id, sender_name, content, is_from_bot, timestamp
+             FROM messages
+             WHERE chat_id = ?1 AND timestamp > ?2 AND is_from_bot = 0
+             ORDER BY timestamp ASC",
+        )?;
+        let messages = stmt
+            .query_map(params![chat_id, since], |row| {
+                Ok(StoredMessage {
+                    id: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    sender_name: row.get(2)?,
+                    content: row.get(3)?,
+                    is_from_bot: row.get::<_, i32>(4)? != 0,
+                    timestamp: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(messages)
+    }
+
+    pub fn get_messages_since(
+        &self,
+        chat_id: i64,
+        since: &str,
+        limit: usize,
+    ) -> Result<Vec<StoredMessage>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, chat_id, sender_name, content, is_from_bot, timestamp
+             FROM messages
+             WHERE chat_id = ?1 AND timestamp > ?2
+             ORDER BY timestamp ASC
+             LIMIT ?3",
+        )?;
+        let messages = stmt
+            .query_map(params![chat_id, since, limit as i64], |row| {
+                Ok(StoredMessage {
+                    id: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    sender_name: row.get(2)?,
+                    content: row.get(3)?,
+                    is_from_bot: row.get::<_, i32>(4)? != 0,
+                    timestamp: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(messages)
+    }
+
+    pub fn get_reflector_cursor(&self, chat_id: i64) -> Result<Option<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let result = conn.query_row(
+            "SELECT last_reflected_ts FROM memory_reflector_state WHERE chat_id = ?1",
+            params![chat_id],
+            |row| row.get::<_, String>(0),
+        );
+        match result {
+            Ok(ts) => Ok(Some(ts)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn set_reflector_cursor(
+        &self,
+        chat_id: i64,
+        last_reflected_ts: &str,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO memory_reflector_state (chat_id, last_reflected_ts, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                last_reflected_ts = excluded.last_reflected_ts,
+                updated_at = excluded.updated_at",
+            params![chat_id, last_reflected_ts, now],
+        )?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_llm_usage(
+        &self,
+        chat_id: i64,
+        caller_channel: &str,
+        provider: &str,
+        model: &str,
+        input_tokens: i64,
+        output_tokens: i64,
+        request_kind: &str,
+    ) -> Result<i64, MicroClawError> {
+        self.log_llm_usage_for_user(
+            chat_id,
+            None,
+            caller_channel,
+            provider,
+            model,
+            input_tokens,
+            output_tokens,
+            request_kind,
+        )
+    }
+
+    /// Like [`Database::log_llm_usage`], attributing the call to the sender
+    /// whose message triggered it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn log_llm_usage_for_user(
+        &self,
+        chat_id: i64,
+        user_id: Option<&str>,
+        caller_channel: &str,
+        provider: &str,
+        model: &str,
+        input_tokens: i64,
+        output_tokens: i64,
+        request_kind: &str,
+    ) -> Result<i64, MicroClawError> {
+        let conn = self.lock_conn();
+        let now = chrono::Utc::now().to_rfc3339();
+        let total_tokens = input_tokens.saturating_add(output_tokens);
+        conn.execute(
+            "INSERT INTO llm_usage_logs
+                (chat_id, caller_channel, provider, model, input_tokens, output_tokens, total_tokens, request_kind, created_at, user_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                chat_id,
+                caller_channel,
+                provider,
+                model,
+                input_tokens,
+                output_tokens,
+                total_tokens,
+                request_kind,
+                now,
+                user_id,
+            ],
+        )?;
+        crate::metrics::llm_tokens(model, input_tokens, output_tokens);
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Usage in a chat per triggering user, most tokens first. Rows logged
+    /// without a user are left out.
+    pub fn get_llm_usage_by_user(
+        &self,
+        chat_id: i64,
+        since: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<LlmUserUsageSummary>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT
+                user_id,
+                COUNT(*) AS requests,
+                COALESCE(SUM(input_tokens), 0) AS input_tokens,
+                COALESCE(SUM(output_tokens), 0) AS output_tokens,
+                COALESCE(SUM(total_tokens), 0) AS total_tokens
+             FROM llm_usage_logs
+             WHERE chat_id = ?1 AND user_id IS NOT NULL
+               AND (?2 IS NULL OR created_at >= ?2)
+             GROUP BY user_id
+             ORDER BY total_tokens DESC
+             LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![chat_id, since, limit as i64], |row| {
+            Ok(LlmUserUsageSummary {
+                user_id: row.get(0)?,
+                requests: row.get(1)?,
+                input_tokens: row.get(2)?,
+                output_tokens: row.get(3)?,
+                total_tokens: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Usage per UTC day and model since `since`, for one chat or all chats,
+    /// oldest day first.
+    pub fn get_llm_usage_by_day(
+        &self,
+        chat_id: Option<i64>,
+        since: &str,
+    ) -> Result<Vec<LlmDailyUsage>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT
+                substr(created_at, 1, 10) AS day,
+                model,
+                COALESCE(SUM(input_tokens), 0),
+                COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(total_tokens), 0)
+             FROM llm_usage_logs
+             WHERE (?1 IS NULL OR chat_id = ?1) AND created_at >= ?2
+             GROUP BY day, model
+             ORDER BY day ASC",
+        )?;
+        let rows = stmt.query_map(params![chat_id, since], |row| {
+            Ok(LlmDailyUsage {
+                day: row.get(0)?,
+                model: row.get(1)?,
+                input_tokens: row.get(2)?,
+                output_tokens: row.get(3)?,
+                total_tokens: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn insert_model_comparison(
+        &self,
+        chat_id: i64,
+        caller_channel: &str,
+        prompt: &str,
+        model_a: &str,
+        answer_a: &str,
+        model_b: &str,
+        answer_b: &str,
+    ) -> Result<i64, MicroClawError> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "INSERT INTO model_comparisons
+                (chat_id, caller_channel, prompt, model_a, answer_a, model_b, answer_b, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                chat_id,
+                caller_channel,
+                prompt,
+                model_a,
+                answer_a,
+                model_b,
+                answer_b,
+                chrono::Utc::now().to_rfc3339(),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Record the preference (`a`, `b` or `tie`) on the chat's most recent comparison.
+    pub fn set_latest_comparison_preference(
+        &self,
+        chat_id: i64,
+        preferred: &str,
+    ) -> Result<Option<ModelComparison>, MicroClawError> {
+        let conn = self.lock_conn();
+        let latest = conn
+            .query_row(
+                "SELECT id, model_a, model_b FROM model_comparisons
+                 WHERE chat_id = ?1 ORDER BY id DESC LIMIT 1",
+                params![chat_id],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((id, model_a, model_b)) = latest else {
+            return Ok(None);
+        };
+        conn.execute(
+            "UPDATE model_comparisons SET preferred = ?1, decided_at = ?2 WHERE id = ?3",
+            params![preferred, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(Some(ModelComparison {
+            id,
+            chat_id,
+            model_a,
+            model_b,
+            preferred: Some(preferred.to_string()),
+        }))
+    }
+
+    pub fn get_model_comparison_stats(&self) -> Result<Vec<ModelComparisonStat>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT
+                model_a,
+                model_b,
+                COALESCE(SUM(CASE WHEN preferred = 'a' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN preferred = 'b' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN preferred = 'tie' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN preferred IS NULL THEN 1 ELSE 0 END), 0)
+             FROM model_comparisons
+             GROUP BY model_a, model_b
+             ORDER BY COUNT(*) DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ModelComparisonStat {
+                model_a: row.get(0)?,
+                model_b: row.get(1)?,
+                a_wins: row.get(2)?,
+                b_wins: row.get(3)?,
+                ties: row.get(4)?,
+                undecided: row.get(5)?,
+            })
+        })?;
+        let mut stats = Vec::new();
+        for row in rows {
+            stats.push(row?);
+        }
+        Ok(stats)
+    }
+
+    pub fn get_user_preferences(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<UserPreference>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, pref_key, value, source, updated_at FROM user_preferences
+             WHERE chat_id = ?1 ORDER BY pref_key",
+        )?;
+        let rows = stmt.query_map(params![chat_id], |row| {
+            Ok(UserPreference {
+                chat_id: row.get(0)?,
+                key: row.get(1)?,
+                value: row.get(2)?,
+                source: row.get(3)?,
+                updated_at: row.get(4)?,
+            })
+        })?;
+        let mut prefs = Vec::new();
+        for row in rows {
+            prefs.push(row?);
+        }
+        Ok(prefs)
+    }
+
+    /// Upsert a preference. Inferred values never overwrite ones the user set
+    /// explicitly; returns whether the row was written.
+    pub fn upsert_user_preference(
+        &self,
+        chat_id: i64,
+        key: &str,
+        value: &str,
+        source: &str,
+    ) -> Result<bool, MicroClawError> {
+        let conn = self.lock_conn();
+        let changed = conn.execute(
+            "INSERT INTO user_preferences (chat_id, pref_key, value, source, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(chat_id, pref_key) DO UPDATE SET
+                value = excluded.value,
+                source = excluded.source,
+                updated_at = excluded.updated_at
+             WHERE excluded.source = 'user' OR user_preferences.source != 'user'",
+            params![chat_id, key, value, source, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Delete one preference, or all of them for the chat when `key` is `None`.
+    pub fn delete_user_preferences(
+        &self,
+        chat_id: i64,
+        key: Option<&str>,
+    ) -> Result<usize, MicroClawError> {
+        let conn = self.lock_conn();
+        let deleted = match key {
+            Some(key) => conn.execute(
+                "DELETE FROM user_preferences WHERE chat_id = ?1 AND pref_key = ?2",
+                params![chat_id, key],
+            )?,
+            None => conn.execute(
+                "DELETE FROM user_preferences WHERE chat_id = ?1",
+                params![chat_id],
+            )?,
+        };
+        Ok(deleted)
+    }
+
+    /// Delete (or, with `dry_run`, count) the rows past the retention cutoffs.
+    pub fn apply_retention(
+        &self,
+        cutoffs: &RetentionCutoffs,
+        dry_run: bool,
+    ) -> Result<RetentionReport, MicroClawError> {
+        fn prune(
+            tx: &rusqlite::Transaction<'_>,
+            table: &str,
+            condition: &str,
+            value: &dyn rusqlite::ToSql,
+            dry_run: bool,
+        ) -> Result<usize, MicroClawError> {
+            if dry_run {
+                let count: i64 = tx.query_row(
+                    &format!("SELECT COUNT(*) FROM {table} WHERE {condition}"),
+                    params![value],
+                    |row| row.get(0),
+                )?;
+                Ok(count as usize)
+            } else {
+                Ok(tx.execute(
+                    &format!("DELETE FROM {table} WHERE {condition}"),
+                    params![value],
+                )?)
+            }
+        }
+
+        const FINISHED_TASK: &str = "status IN ('completed', 'cancelled')
+             AND COALESCE(last_run, created_at) < ?1";
+
+        let conn = self.lock_conn();
+        let tx = conn.unchecked_transaction()?;
+        let mut report = RetentionReport::default();
+        if let Some(before) = &cutoffs.messages_before {
+            report.messages = prune(&tx, "messages", "timestamp < ?1", before, dry_run)?;
+        }
+        if let Some(before) = &cutoffs.task_runs_before {
+            report.task_runs = prune(&tx, "task_run_logs", "started_at < ?1", before, dry_run)?;
+        }
+        if let Some(before) = &cutoffs.finished_tasks_before {
+            if !dry_run {
+                tx.execute(
+                    &format!(
+                        "DELETE FROM task_run_logs WHERE task_id IN
+                         (SELECT id FROM scheduled_tasks WHERE {FINISHED_TASK})"
+                    ),
+                    params![before],
+                )?;
+            }
+            report.finished_tasks = prune(&tx, "scheduled_tasks", FINISHED_TASK, before, dry_run)?;
+        }
+        if let Some(max_rows) = cutoffs.audit_log_max_rows {
+            for table in ["memory_injection_logs", "memory_reflector_runs"] {
+                let condition =
+                    format!("id NOT IN (SELECT id FROM {table} ORDER BY id DESC LIMIT ?1)");
+                report.audit_logs += prune(&tx, table, &condition, &max_rows, dry_run)?;
+            }
+        }
+        tx.commit()?;
+        Ok(report)
+    }
+
+    /// Rebuild the database file to return the space freed by deletes.
+    pub fn vacuum(&self) -> Result<(), MicroClawError> {
+        self.lock_conn().execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    /// A per-chat switch such as the `/router` opt-out.
+    pub fn get_chat_setting(
+        &self,
+        chat_id: i64,
+        key: &str,
+    ) -> Result<Option<String>, MicroClawError> {
+        let conn = self.lock_conn();
+        let value = conn
+            .query_row(
+                "SELECT value FROM chat_settings WHERE chat_id = ?1 AND key = ?2",
+                params![chat_id, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    /// Set (`Some`) or clear (`None`) a per-chat setting.
+    pub fn set_chat_setting(
+        &self,
+        chat_id: i64,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        match value {
+            Some(value) => conn.execute(
+                "INSERT INTO chat_settings (chat_id, key, value, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(chat_id, key) DO UPDATE SET
+                    value = excluded.value,
+                    updated_at = excluded.updated_at",
+                params![chat_id, key, value, chrono::Utc::now().to_rfc3339()],
+            )?,
+            None => conn.execute(
+                "DELETE FROM chat_settings WHERE chat_id = ?1 AND key = ?2",
+                params![chat_id, key],
+            )?,
+        };
+        Ok(())
+    }
+
+    pub fn get_chat_workspace(&self, chat_id: i64) -> Result<ChatWorkspace, MicroClawError> {
+        let conn = self.lock_conn();
+        let workspace = conn
+            .query_row(
+                "SELECT isolation, topic, session_id FROM chat_workspaces WHERE chat_id = ?1",
+                params![chat_id],
+                |row| {
+                    Ok(ChatWorkspace {
+                        isolation: row.get(0)?,
+                        topic: row.get(1)?,
+                        session_id: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(workspace.unwrap_or_default())
+    }
+
+    /// Set the isolation override (`None` = inherit) and, when given, the active topic.
+    pub fn set_chat_workspace(
+        &self,
+        chat_id: i64,
+        isolation: Option<&str>,
+        topic: Option<&str>,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "INSERT INTO chat_workspaces (chat_id, isolation, topic, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                isolation = excluded.isolation,
+                topic = COALESCE(excluded.topic, chat_workspaces.topic),
+                updated_at = excluded.updated_at",
+            params![chat_id, isolation, topic, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Current session workspace id, creating one when the chat has none.
+    pub fn ensure_workspace_session_id(&self, chat_id: i64) -> Result<String, MicroClawError> {
+        let conn = self.lock_conn();
+        let existing: Option<Option<String>> = conn
+            .query_row(
+                "SELECT session_id FROM chat_workspaces WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(Some(id)) = existing {
+            return Ok(id);
+        }
+        let id = format!(
+            "{}-{}",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+            &uuid::Uuid::new_v4().simple().to_string()[..6]
+        );
+        conn.execute(
+            "INSERT INTO chat_workspaces (chat_id, session_id, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                session_id = excluded.session_id,
+                updated_at = excluded.updated_at",
+            params![chat_id, id, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(id)
+    }
+
+    /// Home chat of the identity `chat_id` is linked to, if any.
+    pub fn get_identity_chat_id(&self, chat_id: i64) -> Result<Option<i64>, MicroClawError> {
+        let conn = self.lock_conn();
+        let identity = conn
+            .query_row(
+                "SELECT identity_chat_id FROM identity_links WHERE chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(identity)
+    }
+
+    /// Store a one-time link code for the identity rooted at `identity_chat_id`.
+    pub fn create_identity_link_code(
+        &self,
+        identity_chat_id: i64,
+        code: &str,
+        expires_at: &str,
+    ) -> Result<(), MicroClawError> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "DELETE FROM identity_link_codes WHERE identity_chat_id = ?1 OR expires_at < ?2",
+            params![identity_chat_id, chrono::Utc::now().to_rfc3339()],
+        )?;
+        conn.execute(
+            "INSERT INTO identity_link_codes (code, identity_chat_id, expires_at)
+             VALUES (?1, ?2, ?3)",
+            params![code, identity_chat_id, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Consume a link code; returns its identity when the code exists and has
+    /// not expired at `now`. A miss counts as a wrong guess against every
+    /// outstanding code, and codes with `max_failures` wrong guesses are dropped.
+    pub fn take_identity_link_code(
+        &self,
+        code: &str,
+        now: &str,
+        max_failures: i64,
+    ) -> Result<Option<i64>, MicroClawError> {
+        let conn = self.lock_conn();
+        let identity: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT identity_chat_id, expires_at FROM identity_link_codes WHERE code = ?1",
+                params![code],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        conn.execute(
+            "DELETE FROM identity_link_codes WHERE code = ?1",
+            params![code],
+        )?;
+        let identity = identity
+            .filter(|(_, expires_at)| expires_at.as_str() >= now)
+            .map(|(id, _)| id);
+        if identity.is_none() {
+            conn.execute(
+                "UPDATE identity_link_codes SET failed_attempts = failed_attempts + 1",
+                [],
+            )?;
+            conn.execute(
+                "DELETE FROM identity_link_codes WHERE failed_attempts >= ?1 OR expires_at < ?2",
+                params![max_failures, now],
+            )?;
+        }
+        Ok(identity)
+    }
+
+    /// Link `chat_id` (and any chats already linked to it) into the identity
+    /// rooted at `identity_chat_id`.
+    pub fn link_chat_identity(
+        &self,
+        chat_id: i64,
+        identity_chat_id: i64,
+    ) -> Result<(), MicroClawError> {
+        let mut conn = self.lock_conn();
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE identity_links SET identity_chat_id = ?2 WHERE identity_chat_id = ?1",
+            params![chat_id, identity_chat_id],
+        )?;
+        tx.execute(
+            "INSERT INTO identity_links (chat_id, identity_chat_id, linked_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(chat_id) DO UPDATE SET
+                identity_chat_id = excluded.identity_chat_id,
+                linked_at = excluded.linked_at",
+            params![chat_id, identity_chat_id, now],
+        )?;
+        tx.execute(
+            "DELETE FROM identity_links WHERE chat_id = identity_chat_id",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove `chat_id` from its identity. Unlinking the home chat dissolves
+    /// the identity. Returns how many links were removed.
+    pub fn unlink_chat_identity(&self, chat_id: i64) -> Result<usize, MicroClawError> {
+        let conn = self.lock_conn();
+        let removed = conn.execute(
+            "DELETE FROM identity_links WHERE chat_id = ?1 OR identity_chat_id = ?1",
+            params![chat_id],
+        )?;
+        Ok(removed)
+    }
+
+    /// The home chat and every chat linked to it.
+    pub fn get_identity_chats(
+        &self,
+        identity_chat_id: i64,
+    ) -> Result<Vec<LinkedChat>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, channel, chat_title FROM chats
+             WHERE chat_id = ?1
+                OR chat_id IN (SELECT chat_id FROM identity_links WHERE identity_chat_id = ?1)
+             ORDER BY chat_id",
+        )?;
+        let rows = stmt.query_map(params![identity_chat_id], |row| {
+            Ok(LinkedChat {
+                chat_id: row.get(0)?,
+                channel: row.get(1)?,
+                chat_title: row.get(2)?,
+            })
+        })?;
+        let mut chats = Vec::new();
+        for row in rows {
+            chats.push(row?);
+        }
+        Ok(chats)
+    }
+
+    /// Usage summed over the home chat and every chat linked to it.
+    pub fn get_identity_usage_summary_since(
+        &self,
+        identity_chat_id: i64,
+        since: Option<&str>,
+    ) -> Result<LlmUsageSummary, MicroClawError> {
+        let conn = self.lock_conn();
+        let summary = conn.query_row(
+            "SELECT
+                COUNT(*),
+                COALESCE(SUM(input_tokens), 0),
+                COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(total_tokens), 0),
+                MAX(created_at)
+             FROM llm_usage_logs
+             WHERE (chat_id = ?1
+                    OR chat_id IN (SELECT chat_id FROM identity_links WHERE identity_chat_id = ?1))
+               AND (?2 IS NULL OR created_at >= ?2)",
+            params![identity_chat_id, since],
+            |row| {
+                Ok(LlmUsageSummary {
+                    requests: row.get(0)?,
+                    input_tokens: row.get(1)?,
+                    output_tokens: row.get(2)?,
+                    total_tokens: row.get(3)?,
+                    last_request_at: row.get(4)?,
+                })
+            },
         )?;
-        Ok(conn.last_insert_rowid())
+        Ok(summary)
     }
 
     pub fn get_llm_usage_summary(
@@ -1408,6 +3424,30 @@ impl Database {
         })
     }
 
+    /// Usage rows per `request_kind` starting with `prefix`, e.g. the
+    /// `router_small` / `router_large` routing decisions.
+    pub fn count_llm_usage_by_kind(
+        &self,
+        chat_id: Option<i64>,
+        prefix: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<(String, i64)>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT request_kind, COUNT(*) FROM llm_usage_logs
+             WHERE (?1 IS NULL OR chat_id = ?1)
+               AND request_kind LIKE ?2 || '%'
+               AND (?3 IS NULL OR created_at >= ?3)
+             GROUP BY request_kind ORDER BY request_kind",
+        )?;
+        let rows = stmt
+            .query_map(params![chat_id, prefix, since], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
     pub fn get_llm_usage_by_model(
         &self,
         chat_id: Option<i64>,
@@ -1606,6 +3646,23 @@ impl Database {
         Ok(memories)
     }
 
+    /// Chats holding at least `min_active` unarchived memories.
+    pub fn get_chat_ids_with_memories(
+        &self,
+        min_active: usize,
+    ) -> Result<Vec<i64>, MicroClawError> {
+        let conn = self.lock_conn();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id FROM memories
+             WHERE chat_id IS NOT NULL AND is_archived = 0
+             GROUP BY chat_id HAVING COUNT(*) >= ?1",
+        )?;
+        let ids = stmt
+            .query_map(params![min_active as i64], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
     pub fn get_active_chat_ids_since(&self, since: &str) -> Result<Vec<i64>, MicroClawError> {
         let conn = self.lock_conn();
         let mut stmt = conn.prepare(
@@ -2774,6 +4831,16 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_webhook_tasks_are_never_due() {
+        let (db, dir) = test_db();
+        db.create_scheduled_task(100, "on deploy", "webhook", "secret", "")
+            .unwrap();
+        assert!(db.get_due_tasks("2099-01-01T00:00:00Z").unwrap().is_empty());
+        assert_eq!(db.get_tasks_for_chat(100).unwrap().len(), 1);
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_delete_task() {
         let (db, dir) = test_db();
@@ -2871,6 +4938,138 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_get_task_run_stats() {
+        let (db, dir) = test_db();
+        let task_id = db
+            .create_scheduled_task(100, "test", "cron", "0 * * * * *", "2024-01-01T00:00:00Z")
+            .unwrap();
+        assert_eq!(
+            db.get_task_run_stats(task_id).unwrap(),
+            TaskRunStats::default()
+        );
+
+        let runs = [
+            (true, 1000, "ok"),
+            (false, 2000, "Error: timeout"),
+            (true, 3000, "ok"),
+            (false, 4000, "Error: rate limited"),
+            (false, 6000, "Error: model down"),
+        ];
+        for (i, (success, duration, summary)) in runs.into_iter().enumerate() {
+            db.log_task_run(
+                task_id,
+                100,
+                &format!("2024-01-01T00:0{i}:00Z"),
+                &format!("2024-01-01T00:0{i}:05Z"),
+                duration,
+                success,
+                Some(summary),
+            )
+            .unwrap();
+        }
+
+        let stats = db.get_task_run_stats(task_id).unwrap();
+        assert_eq!(stats.runs, 5);
+        assert_eq!(stats.failures, 3);
+        assert_eq!(stats.avg_duration_ms, 3200);
+        assert_eq!(stats.max_duration_ms, 6000);
+        assert_eq!(
+            stats.last_success_at.as_deref(),
+            Some("2024-01-01T00:02:00Z")
+        );
+        assert_eq!(stats.consecutive_failures, 2);
+        assert_eq!(stats.last_error.as_deref(), Some("Error: model down"));
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_glossary_terms() {
+        let (db, dir) = test_db();
+        db.set_glossary_term(1, "de", "invoice", "Rechnung")
+            .unwrap();
+        db.set_glossary_term(1, "", "MicroClaw", "MicroClaw")
+            .unwrap();
+        db.set_glossary_term(1, "fr", "invoice", "facture").unwrap();
+        db.set_glossary_term(2, "de", "invoice", "Faktura").unwrap();
+        // Terms match case-insensitively; the latest spelling wins.
+        db.set_glossary_term(1, "de", "Invoice", "Abrechnung")
+            .unwrap();
+
+        let de = db.list_glossary_terms(1, Some("de")).unwrap();
+        let pairs: Vec<(&str, &str)> = de
+            .iter()
+            .map(|t| (t.term.as_str(), t.translation.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [("MicroClaw", "MicroClaw"), ("Invoice", "Abrechnung")]
+        );
+        assert_eq!(db.list_glossary_terms(1, None).unwrap().len(), 3);
+
+        assert!(db.delete_glossary_term(1, "de", "INVOICE").unwrap());
+        assert!(!db.delete_glossary_term(1, "de", "invoice").unwrap());
+        assert_eq!(db.list_glossary_terms(2, None).unwrap().len(), 1);
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_email_thread_roundtrip() {
+        let (db, dir) = test_db();
+        let chat_id = db
+            .resolve_or_create_chat_id("email", "alice@example.com|root@x", None, "email")
+            .unwrap();
+        assert_eq!(
+            db.get_chat_id_by_external("email", "alice@example.com|root@x")
+                .unwrap(),
+            Some(chat_id)
+        );
+        assert!(db.load_email_thread(chat_id).unwrap().is_none());
+
+        let mut thread = EmailThread {
+            subject: "Quarterly report".into(),
+            message_ids: vec!["root@x".into()],
+        };
+        db.save_email_thread(chat_id, &thread).unwrap();
+        thread.message_ids.push("msg-2@x".into());
+        db.save_email_thread(chat_id, &thread).unwrap();
+        assert_eq!(db.load_email_thread(chat_id).unwrap(), Some(thread));
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_scratchpad_write_share_and_delete() {
+        let (db, dir) = test_db();
+        let pad = db.write_scratchpad("plan", 1, "step 1\n", false).unwrap();
+        assert_eq!((pad.owner_chat_id, pad.version), (1, 1));
+        let pad = db.write_scratchpad("plan", 2, "step 2\n", true).unwrap();
+        assert_eq!(pad.content, "step 1\nstep 2\n");
+        assert_eq!(
+            (pad.owner_chat_id, pad.version, pad.updated_by_chat_id),
+            (1, 2, 2)
+        );
+
+        assert!(db.list_scratchpads(Some(2)).unwrap().is_empty());
+        assert!(db.share_scratchpad("plan", 2).unwrap());
+        assert!(db.share_scratchpad("plan", 2).unwrap());
+        assert!(!db.share_scratchpad("missing", 2).unwrap());
+        let pads = db.list_scratchpads(Some(2)).unwrap();
+        assert_eq!(pads.len(), 1);
+        assert_eq!(pads[0].shared_with, vec![2]);
+        assert_eq!(
+            db.get_scratchpad("plan").unwrap().unwrap().shared_with,
+            vec![2]
+        );
+
+        assert!(db.unshare_scratchpad("plan", 2).unwrap());
+        assert!(!db.unshare_scratchpad("plan", 2).unwrap());
+        db.share_scratchpad("plan", 3).unwrap();
+        assert!(db.delete_scratchpad("plan").unwrap());
+        assert!(db.get_scratchpad("plan").unwrap().is_none());
+        assert!(db.list_scratchpads(Some(3)).unwrap().is_empty());
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_save_and_load_session() {
         let (db, dir) = test_db();
@@ -2911,6 +5110,157 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_session_summary_survives_saves_and_clears_on_reset() {
+        let (db, dir) = test_db();
+        assert!(db.load_session_summary(100).unwrap().is_none());
+        db.save_session_summary(100, "## Facts\n- likes tea")
+            .unwrap();
+        db.save_session(100, r#"[{"role":"user","content":"hi"}]"#)
+            .unwrap();
+        assert_eq!(
+            db.load_session_summary(100).unwrap().as_deref(),
+            Some("## Facts\n- likes tea")
+        );
+        db.clear_chat_context(100).unwrap();
+        assert!(db.load_session_summary(100).unwrap().is_none());
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_session_persona_is_recorded_with_the_session() {
+        let (db, dir) = test_db();
+        assert!(db.load_session_persona(100).unwrap().is_none());
+        db.set_session_persona(100, Some("reviewer")).unwrap();
+        db.save_session(100, r#"[{"role":"user","content":"hi"}]"#)
+            .unwrap();
+        assert_eq!(
+            db.load_session_persona(100).unwrap().as_deref(),
+            Some("reviewer")
+        );
+        let (messages, _) = db.load_session(100).unwrap().unwrap();
+        assert!(messages.contains("hi"));
+        db.set_session_persona(100, None).unwrap();
+        assert!(db.load_session_persona(100).unwrap().is_none());
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_apply_retention_dry_run_then_delete() {
+        let (db, dir) = test_db();
+        for (id, ts) in [
+            ("old", "2020-01-01T00:00:00+00:00"),
+            ("new", "2030-01-01T00:00:00+00:00"),
+        ] {
+            db.store_message(&StoredMessage {
+                id: id.into(),
+                chat_id: 1,
+                sender_name: "alice".into(),
+                content: id.into(),
+                is_from_bot: false,
+                timestamp: ts.into(),
+            })
+            .unwrap();
+        }
+        let done = db
+            .create_scheduled_task(1, "done", "once", "x", "2020-01-01T00:00:00+00:00")
+            .unwrap();
+        db.update_task_status(done, "completed").unwrap();
+        let active = db
+            .create_scheduled_task(1, "active", "cron", "x", "2020-01-01T00:00:00+00:00")
+            .unwrap();
+        db.log_task_run(
+            done,
+            1,
+            "2020-01-01T00:00:00+00:00",
+            "2020-01-01T00:00:01+00:00",
+            1,
+            true,
+            None,
+        )
+        .unwrap();
+        db.log_task_run(
+            active,
+            1,
+            "2030-01-01T00:00:00+00:00",
+            "2030-01-01T00:00:01+00:00",
+            1,
+            true,
+            None,
+        )
+        .unwrap();
+        for _ in 0..3 {
+            db.log_memory_injection(1, "keyword", 1, 1, 0, 10).unwrap();
+        }
+
+        let cutoffs = RetentionCutoffs {
+            messages_before: Some("2021-01-01T00:00:00+00:00".into()),
+            task_runs_before: Some("2021-01-01T00:00:00+00:00".into()),
+            finished_tasks_before: Some("2999-01-01T00:00:00+00:00".into()),
+            audit_log_max_rows: Some(1),
+        };
+        let expected = RetentionReport {
+            messages: 1,
+            task_runs: 1,
+            finished_tasks: 1,
+            audit_logs: 2,
+        };
+        assert_eq!(db.apply_retention(&cutoffs, true).unwrap(), expected);
+        assert_eq!(db.get_all_messages(1).unwrap().len(), 2);
+        assert_eq!(db.apply_retention(&cutoffs, false).unwrap(), expected);
+        let messages = db.get_all_messages(1).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "new");
+        assert_eq!(db.get_tasks_for_chat(1).unwrap().len(), 1);
+        assert_eq!(db.get_task_run_logs(active, 10).unwrap().len(), 1);
+        assert_eq!(db.apply_retention(&cutoffs, false).unwrap().total(), 0);
+        db.vacuum().unwrap();
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_session_branches_park_and_restore() {
+        let (db, dir) = test_db();
+        db.save_session(100, r#"[{"role":"user","content":"a"}]"#)
+            .unwrap();
+        db.save_session_summary(100, "earlier").unwrap();
+        assert_eq!(db.load_session_branch(100).unwrap(), "main");
+
+        assert!(db
+            .switch_session_branch(100, "alt", BranchStart::Fork("[]"))
+            .unwrap());
+        assert_eq!(db.load_session_branch(100).unwrap(), "alt");
+        assert_eq!(db.load_session(100).unwrap().unwrap().0, "[]");
+        assert_eq!(
+            db.load_session_summary(100).unwrap().as_deref(),
+            Some("earlier")
+        );
+        let parked = db.list_session_branches(100).unwrap();
+        assert_eq!(parked.len(), 1);
+        assert_eq!(parked[0].name, "main");
+        assert!(parked[0].messages_json.contains("\"a\""));
+
+        assert!(!db
+            .switch_session_branch(100, "missing", BranchStart::Parked)
+            .unwrap());
+        assert!(db
+            .switch_session_branch(100, "main", BranchStart::Parked)
+            .unwrap());
+        assert!(db.load_session(100).unwrap().unwrap().0.contains("\"a\""));
+        let parked = db.list_session_branches(100).unwrap();
+        assert_eq!(parked[0].name, "alt");
+
+        assert!(db.delete_session_branch(100, "alt").unwrap());
+        assert!(db.list_session_branches(100).unwrap().is_empty());
+        db.switch_session_branch(100, "b", BranchStart::Empty)
+            .unwrap();
+        assert!(db.load_session_summary(100).unwrap().is_none());
+        db.clear_chat_context(100).unwrap();
+        assert!(db.list_session_branches(100).unwrap().is_empty());
+        assert_eq!(db.load_session_branch(100).unwrap(), "main");
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_clear_chat_context_removes_session_and_messages_only() {
         let (db, dir) = test_db();
@@ -3234,6 +5584,184 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_model_comparison_preference_and_stats() {
+        let (db, dir) = test_db();
+        assert!(db
+            .set_latest_comparison_preference(100, "a")
+            .unwrap()
+            .is_none());
+        db.insert_model_comparison(100, "telegram", "hi", "m1", "A1", "m2", "B1")
+            .unwrap();
+        db.insert_model_comparison(100, "telegram", "hi", "m1", "A2", "m2", "B2")
+            .unwrap();
+        db.insert_model_comparison(200, "discord", "yo", "m1", "A3", "m3", "B3")
+            .unwrap();
+
+        let decided = db
+            .set_latest_comparison_preference(100, "b")
+            .unwrap()
+            .unwrap();
+        assert_eq!(decided.model_b, "m2");
+
+        let stats = db.get_model_comparison_stats().unwrap();
+        let m2 = stats.iter().find(|s| s.model_b == "m2").unwrap();
+        assert_eq!((m2.a_wins, m2.b_wins, m2.undecided), (0, 1, 1));
+        let m3 = stats.iter().find(|s| s.model_b == "m3").unwrap();
+        assert_eq!(m3.undecided, 1);
+
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_chat_settings_and_usage_kinds() {
+        let (db, dir) = test_db();
+        assert_eq!(db.get_chat_setting(1, "router").unwrap(), None);
+        db.set_chat_setting(1, "router", Some("off")).unwrap();
+        db.set_chat_setting(1, "router", Some("on")).unwrap();
+        assert_eq!(
+            db.get_chat_setting(1, "router").unwrap().as_deref(),
+            Some("on")
+        );
+        assert_eq!(db.get_chat_setting(2, "router").unwrap(), None);
+        db.set_chat_setting(1, "router", None).unwrap();
+        assert_eq!(db.get_chat_setting(1, "router").unwrap(), None);
+
+        for kind in ["router_small", "router_small", "router_large", "agent_loop"] {
+            db.log_llm_usage(1, "web", "p", "m", 1, 1, kind).unwrap();
+        }
+        db.log_llm_usage(2, "web", "p", "m", 1, 1, "router_large")
+            .unwrap();
+        assert_eq!(
+            db.count_llm_usage_by_kind(Some(1), "router_", None)
+                .unwrap(),
+            vec![
+                ("router_large".to_string(), 1),
+                ("router_small".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            db.count_llm_usage_by_kind(None, "router_", None).unwrap()[0],
+            ("router_large".to_string(), 2)
+        );
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_identity_links_and_codes() {
+        let (db, dir) = test_db();
+        let tg = db
+            .resolve_or_create_chat_id("telegram", "1", None, "telegram_private")
+            .unwrap();
+        let dc = db
+            .resolve_or_create_chat_id("discord", "2", None, "discord")
+            .unwrap();
+        let web = db
+            .resolve_or_create_chat_id("web", "main", None, "web")
+            .unwrap();
+
+        let later = (chrono::Utc::now() + chrono::Duration::minutes(5)).to_rfc3339();
+        let now = chrono::Utc::now().to_rfc3339();
+        db.create_identity_link_code(tg, "ABC123", &later).unwrap();
+        assert_eq!(
+            db.take_identity_link_code("ABC123", &now, 5).unwrap(),
+            Some(tg)
+        );
+        assert_eq!(db.take_identity_link_code("ABC123", &now, 5).unwrap(), None);
+        db.create_identity_link_code(tg, "OLD000", &now).unwrap();
+        assert_eq!(
+            db.take_identity_link_code("OLD000", &later, 5).unwrap(),
+            None
+        );
+        // Wrong guesses burn outstanding codes.
+        db.create_identity_link_code(tg, "GOOD22", &later).unwrap();
+        assert_eq!(db.take_identity_link_code("BAD111", &now, 2).unwrap(), None);
+        assert_eq!(db.take_identity_link_code("BAD222", &now, 2).unwrap(), None);
+        assert_eq!(db.take_identity_link_code("GOOD22", &now, 2).unwrap(), None);
+
+        // web links to discord, then discord (with web) joins telegram.
+        db.link_chat_identity(web, dc).unwrap();
+        db.link_chat_identity(dc, tg).unwrap();
+        assert_eq!(db.get_identity_chat_id(web).unwrap(), Some(tg));
+        assert_eq!(db.get_identity_chat_id(dc).unwrap(), Some(tg));
+        assert_eq!(db.get_identity_chat_id(tg).unwrap(), None);
+        let chats: Vec<_> = db
+            .get_identity_chats(tg)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.chat_id)
+            .collect();
+        assert_eq!(chats, vec![tg, dc, web]);
+
+        db.log_llm_usage(tg, "telegram", "p", "m", 10, 5, "agent_loop")
+            .unwrap();
+        db.log_llm_usage(web, "web", "p", "m", 1, 1, "agent_loop")
+            .unwrap();
+        let usage = db.get_identity_usage_summary_since(tg, None).unwrap();
+        assert_eq!((usage.requests, usage.total_tokens), (2, 17));
+
+        assert_eq!(db.unlink_chat_identity(web).unwrap(), 1);
+        assert_eq!(db.unlink_chat_identity(tg).unwrap(), 1);
+        assert_eq!(db.get_identity_chat_id(dc).unwrap(), None);
+
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_user_preferences_inferred_never_overrides_user() {
+        let (db, dir) = test_db();
+        assert!(db
+            .upsert_user_preference(100, "verbosity", "brief", "inferred")
+            .unwrap());
+        assert!(db
+            .upsert_user_preference(100, "verbosity", "detailed", "user")
+            .unwrap());
+        assert!(!db
+            .upsert_user_preference(100, "verbosity", "brief", "inferred")
+            .unwrap());
+        db.upsert_user_preference(100, "formatting", "bullets", "inferred")
+            .unwrap();
+
+        let prefs = db.get_user_preferences(100).unwrap();
+        assert_eq!(prefs.len(), 2);
+        let verbosity = prefs.iter().find(|p| p.key == "verbosity").unwrap();
+        assert_eq!(
+            (verbosity.value.as_str(), verbosity.source.as_str()),
+            ("detailed", "user")
+        );
+
+        assert_eq!(
+            db.delete_user_preferences(100, Some("formatting")).unwrap(),
+            1
+        );
+        assert_eq!(db.delete_user_preferences(100, None).unwrap(), 1);
+        assert!(db.get_user_preferences(100).unwrap().is_empty());
+
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_chat_workspace_override_topic_and_session_rotation() {
+        let (db, dir) = test_db();
+        assert!(db.get_chat_workspace(100).unwrap().isolation.is_none());
+
+        db.set_chat_workspace(100, Some("topic"), Some("release"))
+            .unwrap();
+        db.set_chat_workspace(100, Some("user"), None).unwrap();
+        let ws = db.get_chat_workspace(100).unwrap();
+        assert_eq!(ws.isolation.as_deref(), Some("user"));
+        assert_eq!(ws.topic.as_deref(), Some("release"));
+
+        let session = db.ensure_workspace_session_id(100).unwrap();
+        assert_eq!(db.ensure_workspace_session_id(100).unwrap(), session);
+        db.clear_chat_context(100).unwrap();
+        let ws = db.get_chat_workspace(100).unwrap();
+        assert!(ws.session_id.is_none());
+        assert_eq!(ws.isolation.as_deref(), Some("user"));
+
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_delete_chat_data_cleans_llm_usage() {
         let (db, dir) = test_db();
@@ -3319,6 +5847,67 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_get_llm_usage_by_user() {
+        let (db, dir) = test_db();
+        for (user, input) in [(Some("alice"), 10), (Some("bob"), 40), (Some("alice"), 5)] {
+            db.log_llm_usage_for_user(
+                100,
+                user,
+                "slack",
+                "anthropic",
+                "claude-a",
+                input,
+                1,
+                "agent_loop",
+            )
+            .unwrap();
+        }
+        db.log_llm_usage(100, "slack", "anthropic", "claude-a", 99, 1, "compaction")
+            .unwrap();
+        db.log_llm_usage_for_user(
+            200,
+            Some("alice"),
+            "slack",
+            "anthropic",
+            "claude-a",
+            7,
+            1,
+            "agent_loop",
+        )
+        .unwrap();
+
+        let users = db.get_llm_usage_by_user(100, None, 10).unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].user_id, "bob");
+        assert_eq!(users[0].total_tokens, 41);
+        assert_eq!(users[1].user_id, "alice");
+        assert_eq!(users[1].requests, 2);
+        assert_eq!(users[1].total_tokens, 17);
+        assert!(db
+            .get_llm_usage_by_user(100, Some("2100-01-01T00:00:00Z"), 10)
+            .unwrap()
+            .is_empty());
+
+        let days = db
+            .get_llm_usage_by_day(Some(100), "2000-01-01T00:00:00Z")
+            .unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(
+            days[0].day,
+            chrono::Utc::now().format("%Y-%m-%d").to_string()
+        );
+        assert_eq!(days[0].total_tokens, 58 + 100);
+        assert_eq!(
+            db.get_llm_usage_by_day(None, "2000-01-01T00:00:00Z")
+                .unwrap()[0]
+                .total_tokens,
+            58 + 100 + 8
+        );
+
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_insert_and_get_memories_for_context() {
         let (db, dir) = test_db();
@@ -3372,6 +5961,10 @@ mod tests {
         assert_eq!(global.len(), 1);
         assert_eq!(global[0].content, "global mem");
 
+        assert_eq!(db.get_chat_ids_with_memories(2).unwrap(), vec![100]);
+        db.archive_memory(mems[0].id).unwrap();
+        assert!(db.get_chat_ids_with_memories(2).unwrap().is_empty());
+
         cleanup(&dir);
     }
 
@@ -3546,6 +6139,28 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_web_sessions_expire() {
+        let (db, dir) = test_db();
+        let session = WebSession {
+            username: "alice".into(),
+            csrf_token: "csrf".into(),
+            expires_at: (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339(),
+        };
+        db.create_web_session("live", &session).unwrap();
+        let expired = WebSession {
+            expires_at: (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339(),
+            ..session.clone()
+        };
+        db.create_web_session("old", &expired).unwrap();
+
+        assert_eq!(db.get_web_session("live").unwrap(), Some(session));
+        assert_eq!(db.get_web_session("old").unwrap(), None);
+        assert!(db.delete_web_session("live").unwrap());
+        assert_eq!(db.get_web_session("live").unwrap(), None);
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_delete_memory() {
         let (db, dir) = test_db();
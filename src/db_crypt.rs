@@ -0,0 +1,212 @@
+//! Encryption at rest for the database (`db_encryption: true`).
+//!
+//! Needs a build with the `sqlcipher` (or `sqlcipher-vendored`) feature, which
+//! links SQLCipher instead of plain SQLite and encrypts every page of
+//! `microclaw.db` (chats, messages, memories). The passphrase is read once at
+//! startup from `MICROCLAW_DB_PASSPHRASE` (which may be a `file:`, `exec:` or
+//! `keychain:` reference) or prompted for on a terminal; every connection to
+//! the database, backups included, is keyed with it. `microclaw db encrypt`
+//! converts an existing plaintext database and `microclaw db decrypt` turns
+//! it back.
+
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use rusqlite::{params, Connection, OpenFlags};
+
+use crate::config::Config;
+use crate::error::MicroClawError;
+
+pub const DB_PASSPHRASE_ENV: &str = "MICROCLAW_DB_PASSPHRASE";
+
+static KEY: OnceLock<String> = OnceLock::new();
+
+/// Whether this build links SQLCipher.
+pub fn is_supported() -> bool {
+    Connection::open_in_memory()
+        .and_then(|conn| conn.query_row("PRAGMA cipher_version", [], |row| row.get::<_, String>(0)))
+        .is_ok()
+}
+
+fn read_passphrase() -> Result<String, String> {
+    let passphrase = match std::env::var(DB_PASSPHRASE_ENV) {
+        Ok(value) if crate::secrets::is_reference(&value) && !value.starts_with("enc:") => {
+            crate::secrets::resolve(&value).map_err(|e| format!("{DB_PASSPHRASE_ENV}: {e}"))?
+        }
+        Ok(value) => value,
+        Err(_) if std::io::stdin().is_terminal() => {
+            crate::config_crypt::prompt_hidden("Database passphrase: ")?
+        }
+        Err(_) => {
+            return Err(format!(
+                "db_encryption is on; set {DB_PASSPHRASE_ENV} or start from a terminal"
+            ))
+        }
+    };
+    if passphrase.is_empty() {
+        return Err("the database passphrase is empty".into());
+    }
+    Ok(passphrase)
+}
+
+/// Read the passphrase when `db_encryption` is on. Call before the database
+/// is first opened; later calls are no-ops.
+pub fn init(config: &Config) -> Result<(), MicroClawError> {
+    if !config.db_encryption || KEY.get().is_some() {
+        return Ok(());
+    }
+    if !is_supported() {
+        return Err(MicroClawError::Config(
+            "db_encryption needs a MicroClaw build with the sqlcipher feature (cargo build --release --features sqlcipher)".into(),
+        ));
+    }
+    let passphrase = read_passphrase().map_err(MicroClawError::Config)?;
+    let _ = KEY.set(passphrase);
+    Ok(())
+}
+
+fn not_readable(path: &Path, e: rusqlite::Error, keyed: bool) -> MicroClawError {
+    let hint = if keyed {
+        "wrong passphrase, or the database is not encrypted yet (run `microclaw db encrypt`)"
+    } else {
+        "if it is encrypted, set db_encryption: true"
+    };
+    MicroClawError::Config(format!("cannot read {}: {e} ({hint})", path.display()))
+}
+
+/// Open `path` keyed with `key` and check that it can be read.
+fn open_with_key(
+    path: &Path,
+    flags: OpenFlags,
+    key: Option<&str>,
+) -> Result<Connection, MicroClawError> {
+    let conn = Connection::open_with_flags(path, flags)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|e| not_readable(path, e, key.is_some()))?;
+    Ok(conn)
+}
+
+/// Open a database file, keyed with the startup passphrase when
+/// `db_encryption` is on.
+pub fn open(path: &Path, flags: OpenFlags) -> Result<Connection, MicroClawError> {
+    open_with_key(path, flags, KEY.get().map(String::as_str))
+}
+
+/// Rewrite the database at `path` encrypted with `key` (`encrypt`) or in
+/// plaintext, keyed with `key` before. The original is replaced.
+fn convert(path: &Path, key: &str, encrypt: bool) -> Result<(), MicroClawError> {
+    let converted = PathBuf::from(format!("{}.converting", path.display()));
+    let _ = std::fs::remove_file(&converted);
+    {
+        let conn = open_with_key(path, OpenFlags::default(), (!encrypt).then_some(key))?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS converted KEY ?2",
+            params![converted.to_string_lossy(), if encrypt { key } else { "" }],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))?;
+        conn.execute_batch("DETACH DATABASE converted")?;
+    }
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+    std::fs::rename(&converted, path)?;
+    Ok(())
+}
+
+/// `microclaw db encrypt|decrypt`: convert the database in `data_dir`.
+pub fn run_convert_cli(data_dir: &Path, encrypt: bool) -> anyhow::Result<()> {
+    if !is_supported() {
+        anyhow::bail!("this build has no SQLCipher; rebuild with --features sqlcipher");
+    }
+    let path = data_dir.join(crate::db::DATABASE_FILE);
+    if !path.is_file() {
+        anyhow::bail!("no database at {}", path.display());
+    }
+    let key = match std::env::var(DB_PASSPHRASE_ENV) {
+        Err(_) if encrypt && std::io::stdin().is_terminal() => {
+            let first = crate::config_crypt::prompt_hidden("Database passphrase: ")
+                .map_err(anyhow::Error::msg)?;
+            let second = crate::config_crypt::prompt_hidden("Repeat passphrase: ")
+                .map_err(anyhow::Error::msg)?;
+            if first != second {
+                anyhow::bail!("the passphrases don't match");
+            }
+            if first.is_empty() {
+                anyhow::bail!("the database passphrase is empty");
+            }
+            first
+        }
+        _ => read_passphrase().map_err(anyhow::Error::msg)?,
+    };
+    convert(&path, &key, encrypt)?;
+    if encrypt {
+        println!(
+            "Encrypted {}. Set db_encryption: true and provide {DB_PASSPHRASE_ENV} when starting MicroClaw. Existing backups are still plaintext.",
+            path.display()
+        );
+    } else {
+        println!(
+            "Decrypted {}. Remove db_encryption from the config.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plaintext_open_without_key() {
+        let dir = std::env::temp_dir().join(format!("microclaw_dbcrypt_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("plain.db");
+        let conn = open_with_key(&path, OpenFlags::default(), None).unwrap();
+        conn.execute_batch("CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('secret');")
+            .unwrap();
+        drop(conn);
+        assert!(open_with_key(&path, OpenFlags::default(), None).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(any(feature = "sqlcipher", feature = "sqlcipher-vendored"))]
+    #[test]
+    fn test_encrypt_and_decrypt_round_trip() {
+        assert!(is_supported());
+        let dir = std::env::temp_dir().join(format!("microclaw_dbcrypt_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chat.db");
+        let conn = open_with_key(&path, OpenFlags::default(), None).unwrap();
+        conn.execute_batch(
+            "PRAGMA journal_mode=WAL; CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('top secret');",
+        )
+        .unwrap();
+        drop(conn);
+
+        convert(&path, "hunter2", true).unwrap();
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!raw.windows(10).any(|w| w == b"top secret"));
+        let err = open_with_key(&path, OpenFlags::default(), None).unwrap_err();
+        assert!(err.to_string().contains("db_encryption"), "{err}");
+        let err = open_with_key(&path, OpenFlags::default(), Some("wrong")).unwrap_err();
+        assert!(err.to_string().contains("wrong passphrase"), "{err}");
+        let conn = open_with_key(&path, OpenFlags::default(), Some("hunter2")).unwrap();
+        let v: String = conn.query_row("SELECT v FROM t", [], |r| r.get(0)).unwrap();
+        assert_eq!(v, "top secret");
+        drop(conn);
+
+        convert(&path, "hunter2", false).unwrap();
+        let conn = open_with_key(&path, OpenFlags::default(), None).unwrap();
+        let v: String = conn.query_row("SELECT v FROM t", [], |r| r.get(0)).unwrap();
+        assert_eq!(v, "top secret");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
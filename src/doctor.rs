@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 
-use crate::config::Config;
+use crate::config::{BashBackend, Config};
 use crate::mcp::McpConfig;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
@@ -41,6 +41,24 @@ pub struct DoctorCheck {
     pub fix: Option<String>,
 }
 
+impl DoctorCheck {
+    pub fn new(
+        id: impl Into<String>,
+        title: impl Into<String>,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        fix: Option<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            title: title.into(),
+            status,
+            detail: detail.into(),
+            fix,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct DoctorReport {
     pub platform: String,
@@ -67,28 +85,28 @@ impl DoctorReport {
         detail: impl Into<String>,
         fix: Option<String>,
     ) {
-        self.checks.push(DoctorCheck {
-            id: id.into(),
-            title: title.into(),
-            status,
-            detail: detail.into(),
-            fix,
-        });
+        self.checks
+            .push(DoctorCheck::new(id, title, status, detail, fix));
     }
 
     fn summary(&self) -> (usize, usize, usize) {
-        let mut pass = 0usize;
-        let mut warn = 0usize;
-        let mut fail = 0usize;
-        for check in &self.checks {
-            match check.status {
-                CheckStatus::Pass => pass += 1,
-                CheckStatus::Warn => warn += 1,
-                CheckStatus::Fail => fail += 1,
-            }
+        summarize(&self.checks)
+    }
+}
+
+/// Number of passed, warned and failed checks.
+pub fn summarize(checks: &[DoctorCheck]) -> (usize, usize, usize) {
+    let mut pass = 0usize;
+    let mut warn = 0usize;
+    let mut fail = 0usize;
+    for check in checks {
+        match check.status {
+            CheckStatus::Pass => pass += 1,
+            CheckStatus::Warn => warn += 1,
+            CheckStatus::Fail => fail += 1,
         }
-        (pass, warn, fail)
     }
+    (pass, warn, fail)
 }
 
 pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
@@ -131,8 +149,10 @@ fn build_report() -> DoctorReport {
     );
 
     check_config(&mut report);
+    check_model_capabilities(&mut report);
     check_path(&mut report);
     check_shell(&mut report);
+    check_bash_backend(&mut report);
     check_node_and_browser(&mut report);
     check_mcp_dependencies(&mut report);
 
@@ -165,6 +185,51 @@ fn check_config(report: &mut DoctorReport) {
     }
 }
 
+fn check_model_capabilities(report: &mut DoctorReport) {
+    let Ok(config) = Config::load() else {
+        return;
+    };
+    let caps = crate::model_caps::for_config(&config);
+    let known = crate::model_caps::builtin(&config.model).is_some()
+        || config.model_capabilities.contains_key(config.model.trim());
+    let notes = crate::model_caps::limitations(&config.model, &caps);
+    let (status, detail, fix) = if !notes.is_empty() {
+        (
+            CheckStatus::Warn,
+            notes.join("; "),
+            Some("Pick a model with these features, or correct `model_capabilities` if the registry is wrong.".to_string()),
+        )
+    } else if !known {
+        (
+            CheckStatus::Warn,
+            format!(
+                "{} is not in the capability registry; assuming vision, tools and streaming",
+                config.model
+            ),
+            Some(format!(
+                "Add `model_capabilities.{}` to the config if the model lacks any of these.",
+                config.model
+            )),
+        )
+    } else {
+        (
+            CheckStatus::Pass,
+            format!(
+                "{}: vision, tools, streaming; {} token context",
+                config.model, caps.max_context_tokens
+            ),
+            None,
+        )
+    };
+    report.push(
+        "llm.capabilities",
+        "Model capabilities",
+        status,
+        detail,
+        fix,
+    );
+}
+
 fn check_path(report: &mut DoctorReport) {
     let target = if cfg!(target_os = "windows") {
         user_home_dir().map(|h| h.join(".local").join("bin"))
@@ -282,6 +347,38 @@ fn check_shell(report: &mut DoctorReport) {
     }
 }
 
+fn check_bash_backend(report: &mut DoctorReport) {
+    let Ok(config) = Config::load() else {
+        return;
+    };
+    if config.bash_backend != BashBackend::Container {
+        return;
+    }
+    match crate::tools::bash_container::resolve_runtime(&config.bash_container) {
+        Ok(runtime) if command_exists(&runtime) || Path::new(&runtime).is_file() => report.push(
+            "bash.container",
+            "Bash container runtime",
+            CheckStatus::Pass,
+            format!("{runtime}, image {}", config.bash_container.image),
+            None,
+        ),
+        Ok(runtime) => report.push(
+            "bash.container",
+            "Bash container runtime",
+            CheckStatus::Fail,
+            format!("{runtime} not found"),
+            Some("Install podman or docker, or fix bash_container.runtime.".to_string()),
+        ),
+        Err(err) => report.push(
+            "bash.container",
+            "Bash container runtime",
+            CheckStatus::Fail,
+            err,
+            Some("Install podman or docker, or set bash_backend: host.".to_string()),
+        ),
+    }
+}
+
 fn check_node_and_browser(report: &mut DoctorReport) {
     let node_ok = command_exists("node");
     report.push(
@@ -472,8 +569,19 @@ fn print_report(report: &DoctorReport) {
         report.platform, report.arch, report.in_wsl
     );
     println!();
+    print_checks(&report.checks);
+
+    let (_, _, fail) = report.summary();
+    if fail > 0 {
+        println!("Doctor exit code: 2 (hard failures present)");
+    } else {
+        println!("Doctor exit code: 0");
+    }
+}
 
-    for check in &report.checks {
+/// One line per check, then a pass/warn/fail summary line.
+pub fn print_checks(checks: &[DoctorCheck]) {
+    for check in checks {
         println!(
             "[{} {:<4}] {:<28} ({}) {}",
             check.status.as_emoji(),
@@ -487,14 +595,9 @@ fn print_report(report: &DoctorReport) {
         }
     }
 
-    let (pass, warn, fail) = report.summary();
+    let (pass, warn, fail) = summarize(checks);
     println!();
     println!("Summary: pass={} warn={} fail={}", pass, warn, fail);
-    if fail > 0 {
-        println!("Doctor exit code: 2 (hard failures present)");
-    } else {
-        println!("Doctor exit code: 0");
-    }
 }
 
 fn current_platform() -> &'static str {
@@ -55,16 +55,10 @@ struct OllamaEmbeddingResponse {
     embedding: Vec<f32>,
 }
 
-#[cfg(feature = "sqlite-vec")]
 fn infer_default_dim(provider: &str, model: &str) -> usize {
     match provider {
-        "openai" => {
-            if model.contains("3-large") {
-                3072
-            } else {
-                1536
-            }
-        }
+        "openai" if model.contains("3-large") => 3072,
+        "openai" => 1536,
         "ollama" => 1024,
         _ => 1536,
     }
@@ -141,6 +135,7 @@ impl EmbeddingProvider for OllamaEmbeddingProvider {
     }
 }
 
+/// Embedding client for semantic memory, which needs the `sqlite-vec` index.
 pub fn create_provider(config: &Config) -> Option<Arc<dyn EmbeddingProvider>> {
     #[cfg(not(feature = "sqlite-vec"))]
     {
@@ -150,61 +145,67 @@ pub fn create_provider(config: &Config) -> Option<Arc<dyn EmbeddingProvider>> {
 
     #[cfg(feature = "sqlite-vec")]
     {
-        let provider = config
-            .embedding_provider
-            .as_deref()
-            .unwrap_or("")
-            .trim()
-            .to_lowercase();
-        if provider.is_empty() {
-            return None;
-        }
+        build_provider(config)
+    }
+}
 
-        let model = config
-            .embedding_model
-            .clone()
-            .unwrap_or_else(|| match provider.as_str() {
-                "openai" => "text-embedding-3-small".to_string(),
-                "ollama" => "nomic-embed-text".to_string(),
-                _ => "text-embedding-3-small".to_string(),
-            });
-        let dim = config
-            .embedding_dim
-            .unwrap_or_else(|| infer_default_dim(&provider, &model));
-        let client = reqwest::Client::new();
+/// The configured embedding client, with or without `sqlite-vec` (knowledge
+/// chunks keep their vectors in a plain table).
+pub fn build_provider(config: &Config) -> Option<Arc<dyn EmbeddingProvider>> {
+    let provider = config
+        .embedding_provider
+        .as_deref()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    if provider.is_empty() {
+        return None;
+    }
 
-        match provider.as_str() {
-            "openai" => {
-                let api_key = config.embedding_api_key.clone().unwrap_or_default();
-                if api_key.trim().is_empty() {
-                    return None;
-                }
-                let base_url = config
-                    .embedding_base_url
-                    .clone()
-                    .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
-                Some(Arc::new(OpenAIEmbeddingProvider {
-                    client,
-                    base_url,
-                    api_key,
-                    model,
-                    dim,
-                }))
-            }
-            "ollama" => {
-                let base_url = config
-                    .embedding_base_url
-                    .clone()
-                    .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
-                Some(Arc::new(OllamaEmbeddingProvider {
-                    client,
-                    base_url,
-                    model,
-                    dim,
-                }))
+    let model = config
+        .embedding_model
+        .clone()
+        .unwrap_or_else(|| match provider.as_str() {
+            "openai" => "text-embedding-3-small".to_string(),
+            "ollama" => "nomic-embed-text".to_string(),
+            _ => "text-embedding-3-small".to_string(),
+        });
+    let dim = config
+        .embedding_dim
+        .unwrap_or_else(|| infer_default_dim(&provider, &model));
+    let client = reqwest::Client::new();
+
+    match provider.as_str() {
+        "openai" => {
+            let api_key = config.embedding_api_key.clone().unwrap_or_default();
+            if api_key.trim().is_empty() {
+                return None;
             }
-            _ => None,
+            let base_url = config
+                .embedding_base_url
+                .clone()
+                .unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+            Some(Arc::new(OpenAIEmbeddingProvider {
+                client,
+                base_url,
+                api_key,
+                model,
+                dim,
+            }))
+        }
+        "ollama" => {
+            let base_url = config
+                .embedding_base_url
+                .clone()
+                .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
+            Some(Arc::new(OllamaEmbeddingProvider {
+                client,
+                base_url,
+                model,
+                dim,
+            }))
         }
+        _ => None,
     }
 }
 
@@ -242,6 +243,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 10961,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -257,6 +260,71 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         }
     }
@@ -9,6 +9,10 @@ pub enum MicroClawError {
     #[error("Rate limited, retry after backoff")]
     RateLimited,
 
+    /// Non-success HTTP status from an LLM API.
+    #[error("LLM API error: {message}")]
+    LlmHttp { status: u16, message: String },
+
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
 
@@ -31,6 +35,63 @@ pub enum MicroClawError {
     MaxIterations(usize),
 }
 
+/// Coarse class of an LLM failure, used for retries and provider health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmErrorKind {
+    /// 429 or an explicit rate limit.
+    RateLimit,
+    /// 401/403: the key or token was rejected.
+    Auth,
+    /// Server errors, timeouts and connection failures.
+    Transient,
+    /// Everything else, e.g. an invalid request.
+    Other,
+}
+
+impl LlmErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LlmErrorKind::RateLimit => "rate_limit",
+            LlmErrorKind::Auth => "auth",
+            LlmErrorKind::Transient => "transient",
+            LlmErrorKind::Other => "other",
+        }
+    }
+}
+
+fn kind_for_status(status: u16) -> LlmErrorKind {
+    match status {
+        429 => LlmErrorKind::RateLimit,
+        401 | 403 => LlmErrorKind::Auth,
+        408 | 500..=599 => LlmErrorKind::Transient,
+        _ => LlmErrorKind::Other,
+    }
+}
+
+impl MicroClawError {
+    pub fn llm_error_kind(&self) -> LlmErrorKind {
+        match self {
+            MicroClawError::RateLimited => LlmErrorKind::RateLimit,
+            MicroClawError::LlmHttp { status, .. } => kind_for_status(*status),
+            MicroClawError::Http(e) => match e.status() {
+                Some(status) => kind_for_status(status.as_u16()),
+                None if e.is_timeout() || e.is_connect() => LlmErrorKind::Transient,
+                None => LlmErrorKind::Other,
+            },
+            _ => LlmErrorKind::Other,
+        }
+    }
+
+    /// Rate limits, server errors, timeouts and connection failures: the
+    /// request itself was fine, so a retry or another model may answer it.
+    pub fn is_transient_llm_failure(&self) -> bool {
+        matches!(
+            self.llm_error_kind(),
+            LlmErrorKind::RateLimit | LlmErrorKind::Transient
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,6 +112,39 @@ mod tests {
 
         let e = MicroClawError::MaxIterations(25);
         assert_eq!(e.to_string(), "Max tool iterations reached (25)");
+
+        let e = MicroClawError::LlmHttp {
+            status: 529,
+            message: "overloaded_error: Overloaded".into(),
+        };
+        assert_eq!(e.to_string(), "LLM API error: overloaded_error: Overloaded");
+    }
+
+    #[test]
+    fn test_transient_llm_failures() {
+        let http = |status| MicroClawError::LlmHttp {
+            status,
+            message: String::new(),
+        };
+        assert!(http(429).is_transient_llm_failure());
+        assert!(http(503).is_transient_llm_failure());
+        assert!(!http(400).is_transient_llm_failure());
+        assert!(MicroClawError::RateLimited.is_transient_llm_failure());
+        assert!(!MicroClawError::LlmApi("bad".into()).is_transient_llm_failure());
+    }
+
+    #[test]
+    fn test_llm_error_kinds() {
+        let http = |status| MicroClawError::LlmHttp {
+            status,
+            message: String::new(),
+        };
+        assert_eq!(http(429).llm_error_kind(), LlmErrorKind::RateLimit);
+        assert_eq!(http(401).llm_error_kind(), LlmErrorKind::Auth);
+        assert_eq!(http(403).llm_error_kind(), LlmErrorKind::Auth);
+        assert_eq!(http(529).llm_error_kind(), LlmErrorKind::Transient);
+        assert_eq!(http(400).llm_error_kind(), LlmErrorKind::Other);
+        assert!(!http(401).is_transient_llm_failure());
     }
 
     #[test]
@@ -0,0 +1,541 @@
+//! RSS and Atom feed watching.
+//!
+//! Chats subscribe to feed URLs with the `subscribe_feed` tool. Every
+//! `feeds.poll_interval_mins` the watcher fetches each subscribed feed
+//! through the network policy, drops items whose key (guid, id or link) is
+//! already in `feed_seen_items`, applies the subscription's keyword filter,
+//! and posts what is left to the chat, summarized by the model unless
+//! `feeds.summarize` is off.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::channel::deliver_and_store_bot_message;
+use crate::config::NetworkPolicyConfig;
+use crate::db::{call_blocking, Database, FeedSubscription};
+use crate::llm_types::{Message, MessageContent, ResponseContentBlock};
+use crate::runtime::AppState;
+use crate::tools::web_html::{decode_html_entities, html_to_text};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+const USER_AGENT: &str = "MicroClaw/1.0 (feed watcher)";
+/// Longest item summary kept from the feed, in characters.
+const MAX_SUMMARY_CHARS: usize = 400;
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You summarize new items from an RSS/Atom feed for a chat. For each item write one or two sentences on what it is about, then its link on its own line. Plain text, no preamble, keep the item order.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    /// Dedup key: guid, Atom id, link, or title as a last resort.
+    pub key: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub summary: String,
+    pub published: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub title: Option<String>,
+    pub items: Vec<FeedItem>,
+}
+
+// ── Parsing ───────────────────────────────────────────────────────────────────
+
+/// `(attributes, inner text)` of each `<name ...>...</name>` element in
+/// `xml`, in document order. Self-closing elements have empty inner text.
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<(&'a str, &'a str)> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(pos) = rest.find(&open) {
+        let after = &rest[pos + open.len()..];
+        // `<link` must not match `<linkage`.
+        if !after.starts_with(['>', '/', ' ', '\t', '\r', '\n']) {
+            rest = after;
+            continue;
+        }
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let attrs = &after[..tag_end];
+        let body = &after[tag_end + 1..];
+        if attrs.ends_with('/') {
+            found.push((attrs.trim_end_matches('/'), ""));
+            rest = body;
+            continue;
+        }
+        match body.find(&close) {
+            Some(end) => {
+                found.push((attrs, &body[..end]));
+                rest = &body[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+fn first_text(xml: &str, names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        elements(xml, name)
+            .into_iter()
+            .map(|(_, inner)| clean_text(inner))
+            .find(|text| !text.is_empty())
+    })
+}
+
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let needle = format!("{name}={quote}");
+        let mut rest = attrs;
+        while let Some(pos) = rest.find(&needle) {
+            // Skip `xml:href=` style matches on a longer attribute name.
+            let boundary = rest[..pos]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace);
+            let value = &rest[pos + needle.len()..];
+            if boundary {
+                let end = value.find(quote)?;
+                return Some(decode_entities(&value[..end]));
+            }
+            rest = value;
+        }
+    }
+    None
+}
+
+/// Decode named and numeric character references.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find("&#") {
+        out.push_str(&rest[..pos]);
+        let candidate = &rest[pos + 2..];
+        let decoded = candidate.find(';').and_then(|end| {
+            let number = &candidate[..end];
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse().ok(),
+            };
+            code.and_then(char::from_u32).map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &candidate[end + 1..];
+            }
+            None => {
+                out.push_str("&#");
+                rest = candidate;
+            }
+        }
+    }
+    out.push_str(rest);
+    decode_html_entities(&out).into_owned()
+}
+
+/// Plain text of an element: CDATA unwrapped, entities decoded, and any
+/// (escaped) HTML markup removed. Escaped HTML (`type="html"`, RSS
+/// descriptions) is decoded twice: once as XML, once as HTML.
+fn clean_text(raw: &str) -> String {
+    let raw = raw.trim();
+    let unwrapped = match raw
+        .strip_prefix("<![CDATA[")
+        .and_then(|r| r.strip_suffix("]]>"))
+    {
+        Some(inner) => inner.to_string(),
+        None => decode_entities(raw),
+    };
+    if unwrapped.contains(['<', '&']) {
+        html_to_text(&unwrapped)
+    } else {
+        crate::tools::web_html::collapse_whitespace(&unwrapped)
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((cut, _)) => format!("{}…", text[..cut].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+fn parse_item(xml: &str) -> Option<FeedItem> {
+    let link = elements(xml, "link")
+        .into_iter()
+        .find_map(|(attrs, inner)| {
+            let text = clean_text(inner);
+            if !text.is_empty() {
+                return Some(text);
+            }
+            let rel = attribute(attrs, "rel");
+            if rel.is_none() || rel.as_deref() == Some("alternate") {
+                attribute(attrs, "href")
+            } else {
+                None
+            }
+        });
+    let title = first_text(xml, &["title"]);
+    let summary = first_text(
+        xml,
+        &["description", "summary", "content:encoded", "content"],
+    )
+    .unwrap_or_default();
+    let key = first_text(xml, &["guid", "id"])
+        .or_else(|| link.clone())
+        .or_else(|| title.clone())?;
+    Some(FeedItem {
+        key,
+        title: title.unwrap_or_else(|| "(untitled)".into()),
+        link,
+        summary: truncate_chars(&summary, MAX_SUMMARY_CHARS),
+        published: first_text(xml, &["pubDate", "published", "updated", "dc:date"]),
+    })
+}
+
+/// Parse an RSS 2.0, RSS 1.0 (RDF) or Atom document.
+pub fn parse_feed(xml: &str) -> Result<Feed, String> {
+    let is_feed = ["<rss", "<feed", "<rdf:RDF"]
+        .iter()
+        .any(|root| xml.contains(root));
+    if !is_feed {
+        return Err("not an RSS or Atom feed".into());
+    }
+    let mut items: Vec<FeedItem> = elements(xml, "item")
+        .into_iter()
+        .chain(elements(xml, "entry"))
+        .filter_map(|(_, inner)| parse_item(inner))
+        .collect();
+    let mut seen = HashSet::new();
+    items.retain(|item| seen.insert(item.key.clone()));
+    // The feed's own title precedes its first item.
+    let head_end = ["<item", "<entry"]
+        .iter()
+        .filter_map(|tag| xml.find(tag))
+        .min()
+        .unwrap_or(xml.len());
+    Ok(Feed {
+        title: first_text(&xml[..head_end], &["title"]),
+        items,
+    })
+}
+
+// ── Filters ───────────────────────────────────────────────────────────────────
+
+/// Comma-separated keywords matched against item titles and summaries,
+/// case-insensitively. Items must contain one of the plain terms (if any) and
+/// none of the `-excluded` ones: `rust, tokio, -hiring`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl FeedFilter {
+    pub fn parse(filter: &str) -> Self {
+        let mut parsed = FeedFilter::default();
+        for term in filter.split(',').map(|t| t.trim().to_lowercase()) {
+            match term.strip_prefix('-') {
+                Some(excluded) if !excluded.trim().is_empty() => {
+                    parsed.exclude.push(excluded.trim().to_string())
+                }
+                Some(_) => {}
+                None if !term.is_empty() => parsed.include.push(term),
+                None => {}
+            }
+        }
+        parsed
+    }
+
+    pub fn matches(&self, item: &FeedItem) -> bool {
+        let text = format!("{}\n{}", item.title, item.summary).to_lowercase();
+        (self.include.is_empty() || self.include.iter().any(|t| text.contains(t)))
+            && !self.exclude.iter().any(|t| text.contains(t))
+    }
+}
+
+// ── Fetching and polling ──────────────────────────────────────────────────────
+
+pub async fn fetch_feed(
+    policy: &NetworkPolicyConfig,
+    chat_id: Option<i64>,
+    url: &str,
+) -> Result<Feed, String> {
+    let response = crate::network_policy::guarded_get(
+        policy,
+        chat_id,
+        "feeds",
+        url,
+        FETCH_TIMEOUT,
+        USER_AGENT,
+    )
+    .await?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    parse_feed(&body)
+}
+
+/// Record `items` as seen for a subscription and return those not seen
+/// before.
+pub async fn take_new_items(
+    db: Arc<Database>,
+    subscription_id: i64,
+    items: Vec<FeedItem>,
+) -> Result<Vec<FeedItem>, String> {
+    let keys: Vec<String> = items.iter().map(|item| item.key.clone()).collect();
+    let new_keys: HashSet<String> =
+        call_blocking(db, move |db| db.record_feed_items(subscription_id, &keys))
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect();
+    Ok(items
+        .into_iter()
+        .filter(|item| new_keys.contains(&item.key))
+        .collect())
+}
+
+fn item_list(items: &[FeedItem]) -> String {
+    items
+        .iter()
+        .map(|item| match &item.link {
+            Some(link) => format!("- {}\n  {link}", item.title),
+            None => format!("- {}", item.title),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn summarize(state: &AppState, feed_title: &str, items: &[FeedItem]) -> Option<String> {
+    let listing = items
+        .iter()
+        .map(|item| {
+            format!(
+                "Title: {}\nLink: {}\nPublished: {}\n{}",
+                item.title,
+                item.link.as_deref().unwrap_or("-"),
+                item.published.as_deref().unwrap_or("-"),
+                item.summary
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let message = Message {
+        role: "user".into(),
+        content: MessageContent::Text(format!("New items in {feed_title}:\n\n{listing}")),
+    };
+    match state
+        .llm
+        .send_message(SUMMARY_SYSTEM_PROMPT, vec![message], None)
+        .await
+    {
+        Ok(response) => {
+            let text: String = response
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ResponseContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        }
+        Err(e) => {
+            warn!("Feeds: summary failed for {feed_title}: {e}");
+            None
+        }
+    }
+}
+
+async fn check_subscription(
+    state: &AppState,
+    subscription: &FeedSubscription,
+) -> Result<(), String> {
+    let feed = fetch_feed(
+        &state.config.network_policy,
+        Some(subscription.chat_id),
+        &subscription.url,
+    )
+    .await?;
+    let filter = FeedFilter::parse(subscription.filter.as_deref().unwrap_or_default());
+    let mut items: Vec<FeedItem> = take_new_items(state.db.clone(), subscription.id, feed.items)
+        .await?
+        .into_iter()
+        .filter(|item| filter.matches(item))
+        .collect();
+    if items.is_empty() {
+        return Ok(());
+    }
+    let more = items.len().saturating_sub(state.config.feeds.max_items);
+    items.truncate(state.config.feeds.max_items);
+
+    let feed_title = feed
+        .title
+        .or_else(|| subscription.title.clone())
+        .unwrap_or_else(|| subscription.url.clone());
+    let body = if state.config.feeds.summarize {
+        summarize(state, &feed_title, &items).await
+    } else {
+        None
+    }
+    .unwrap_or_else(|| item_list(&items));
+    let mut text = format!("New in {feed_title}:\n\n{body}");
+    if more > 0 {
+        text.push_str(&format!("\n\n(+{more} more)"));
+    }
+    info!(
+        "Feeds: posting {} new item(s) from {} to chat {}",
+        items.len(),
+        subscription.url,
+        subscription.chat_id
+    );
+    deliver_and_store_bot_message(
+        &state.channel_registry,
+        state.db.clone(),
+        &state.config.bot_username,
+        subscription.chat_id,
+        &text,
+    )
+    .await
+}
+
+async fn poll_feeds(state: &AppState) {
+    let subscriptions =
+        match call_blocking(state.db.clone(), |db| db.list_feed_subscriptions(None)).await {
+            Ok(subscriptions) => subscriptions,
+            Err(e) => {
+                warn!("Feeds: failed to list subscriptions: {e}");
+                return;
+            }
+        };
+    for subscription in subscriptions {
+        let error = check_subscription(state, &subscription).await.err();
+        if let Some(e) = &error {
+            warn!("Feeds: {} failed: {e}", subscription.url);
+        }
+        let id = subscription.id;
+        let _ = call_blocking(state.db.clone(), move |db| {
+            db.update_feed_check(id, error.as_deref())
+        })
+        .await;
+    }
+}
+
+pub fn spawn_feed_watcher(state: Arc<AppState>) {
+    let interval = Duration::from_secs(state.config.feeds.poll_interval_mins * 60);
+    tokio::spawn(async move {
+        info!(
+            "Feed watcher started (interval: {}min)",
+            state.config.feeds.poll_interval_mins
+        );
+        loop {
+            tokio::time::sleep(interval).await;
+            poll_feeds(&state).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+<channel>
+  <title>Example &amp; Co Blog</title>
+  <link>https://example.com/</link>
+  <item>
+    <title><![CDATA[Tokio 2.0 released]]></title>
+    <link>https://example.com/tokio-2</link>
+    <guid isPermaLink="false">post-2</guid>
+    <description>&lt;p&gt;The async runtime gets &lt;b&gt;faster&lt;/b&gt; &#8212; again.&lt;/p&gt;</description>
+    <pubDate>Thu, 15 Oct 2026 08:00:00 GMT</pubDate>
+  </item>
+  <item>
+    <title>We are hiring Rust engineers</title>
+    <link>https://example.com/jobs</link>
+  </item>
+</channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title type="text">Release notes</title>
+  <link rel="self" href="https://example.org/feed.atom"/>
+  <entry>
+    <title>v1.2</title>
+    <link rel="alternate" href="https://example.org/v1.2"/>
+    <id>tag:example.org,2026:v1.2</id>
+    <updated>2026-10-14T10:00:00Z</updated>
+    <summary type="html">Fixes &amp;amp; improvements</summary>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss_and_atom() {
+        let feed = parse_feed(RSS).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Example & Co Blog"));
+        assert_eq!(feed.items.len(), 2);
+        let first = &feed.items[0];
+        assert_eq!(first.key, "post-2");
+        assert_eq!(first.title, "Tokio 2.0 released");
+        assert_eq!(first.link.as_deref(), Some("https://example.com/tokio-2"));
+        assert_eq!(first.summary, "The async runtime gets faster — again.");
+        // No guid: the link is the key.
+        assert_eq!(feed.items[1].key, "https://example.com/jobs");
+
+        let feed = parse_feed(ATOM).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Release notes"));
+        let entry = &feed.items[0];
+        assert_eq!(entry.key, "tag:example.org,2026:v1.2");
+        assert_eq!(entry.link.as_deref(), Some("https://example.org/v1.2"));
+        assert_eq!(entry.summary, "Fixes & improvements");
+        assert_eq!(entry.published.as_deref(), Some("2026-10-14T10:00:00Z"));
+
+        assert!(parse_feed("<html><body>nope</body></html>").is_err());
+    }
+
+    #[test]
+    fn test_feed_filter() {
+        let items = parse_feed(RSS).unwrap().items;
+        let filter = FeedFilter::parse("Rust, tokio, -hiring");
+        assert!(filter.matches(&items[0]));
+        assert!(!filter.matches(&items[1]));
+        assert!(FeedFilter::parse("").matches(&items[1]));
+        assert!(!FeedFilter::parse("python").matches(&items[0]));
+    }
+
+    #[tokio::test]
+    async fn test_take_new_items_dedups() {
+        let dir = std::env::temp_dir().join(format!("microclaw_feeds_{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let id = db
+            .upsert_feed_subscription(1, "https://example.com/rss", None, None)
+            .unwrap();
+        let items = parse_feed(RSS).unwrap().items;
+
+        let new = take_new_items(db.clone(), id, items[1..].to_vec())
+            .await
+            .unwrap();
+        assert_eq!(new.len(), 1);
+        let new = take_new_items(db.clone(), id, items.clone()).await.unwrap();
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].key, "post-2");
+        assert!(take_new_items(db.clone(), id, items)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert!(db.delete_feed_subscription(1, id).unwrap());
+        assert!(db.list_feed_subscriptions(Some(1)).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,339 @@
+//! Preview cards for files changed by `write_file` / `edit_file`, and the
+//! `/file` command that sends a workspace file to the chat.
+//!
+//! Cards are sent straight to the channel and are not stored in chat history,
+//! so they never reach the model's context.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::Value;
+use tracing::warn;
+
+use crate::channel::get_required_chat_routing;
+use crate::db::call_blocking;
+use crate::runtime::AppState;
+use crate::tools::{path_guard, resolve_tool_path};
+use crate::workspace;
+use crate::workspace_report::fmt_bytes;
+
+/// Files larger than this are not read back for a diff or preview.
+const MAX_PREVIEW_BYTES: u64 = 256 * 1024;
+/// Max characters of a file inlined by `/file` when the channel has no attachments.
+const MAX_INLINE_CHARS: usize = 3500;
+/// Preview buttons kept resolvable; older ones expire.
+const MAX_FILE_TOKENS: usize = 256;
+
+const FILE_USAGE: &str = "Usage: /file <path> — send a file from this chat's workspace";
+
+/// File state captured before a file tool runs, used to render a diff.
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    before: Option<String>,
+}
+
+pub fn is_file_tool(name: &str) -> bool {
+    matches!(name, "write_file" | "edit_file")
+}
+
+fn read_small(path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(path).ok()?;
+    if !meta.is_file() || meta.len() > MAX_PREVIEW_BYTES {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// Capture the target file of a `write_file` / `edit_file` call.
+pub fn snapshot_before(working_dir: &Path, input: &Value) -> Option<FileSnapshot> {
+    let path = input.get("path").and_then(|v| v.as_str())?;
+    let path = resolve_tool_path(working_dir, path);
+    let before = read_small(&path);
+    Some(FileSnapshot { path, before })
+}
+
+/// Lines that differ between two versions after trimming the common prefix
+/// and suffix. Returns (first changed line index, removed, added).
+fn changed_lines<'a>(before: &'a str, after: &'a str) -> (usize, Vec<&'a str>, Vec<&'a str>) {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    (
+        prefix,
+        old[prefix..old.len() - suffix].to_vec(),
+        new[prefix..new.len() - suffix].to_vec(),
+    )
+}
+
+/// Render a compact card: name and size, then the first lines of a new file
+/// or the changed lines of an existing one.
+pub fn render_card(
+    display_path: &str,
+    size: u64,
+    before: Option<&str>,
+    after: &str,
+    max_lines: usize,
+) -> String {
+    let max_lines = max_lines.max(1);
+    match before {
+        Some(before) if before == after => {
+            format!("`{display_path}` ({}, unchanged)", fmt_bytes(size))
+        }
+        Some(before) => {
+            let (start, removed, added) = changed_lines(before, after);
+            let mut lines: Vec<String> = removed
+                .iter()
+                .map(|l| format!("-{l}"))
+                .chain(added.iter().map(|l| format!("+{l}")))
+                .collect();
+            let hidden = lines.len().saturating_sub(max_lines);
+            lines.truncate(max_lines);
+            let mut card = format!(
+                "`{display_path}` ({}, +{} -{} lines)\n```diff\n@@ line {} @@\n{}\n```",
+                fmt_bytes(size),
+                added.len(),
+                removed.len(),
+                start + 1,
+                lines.join("\n")
+            );
+            if hidden > 0 {
+                card.push_str(&format!("\n… {hidden} more changed lines"));
+            }
+            card
+        }
+        None => {
+            let total = after.lines().count();
+            let head: Vec<&str> = after.lines().take(max_lines).collect();
+            let mut card = format!(
+                "`{display_path}` ({}, new file, {total} lines)\n```\n{}\n```",
+                fmt_bytes(size),
+                head.join("\n")
+            );
+            if total > head.len() {
+                card.push_str(&format!("\n… {} more lines", total - head.len()));
+            }
+            card
+        }
+    }
+}
+
+fn display_path(working_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(working_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Send a preview card for a file a tool has just written. Errors are logged.
+pub async fn send_preview_card(
+    state: &AppState,
+    chat_id: i64,
+    working_dir: &Path,
+    snapshot: FileSnapshot,
+) {
+    let Ok(meta) = std::fs::metadata(&snapshot.path) else {
+        return;
+    };
+    let shown = display_path(working_dir, &snapshot.path);
+    let card = match read_small(&snapshot.path) {
+        Some(after) => render_card(
+            &shown,
+            meta.len(),
+            snapshot.before.as_deref(),
+            &after,
+            state.config.file_preview_lines,
+        ),
+        None => format!("`{shown}` ({})", fmt_bytes(meta.len())),
+    };
+    let result = async {
+        let routing =
+            get_required_chat_routing(&state.channel_registry, state.db.clone(), chat_id).await?;
+        let Some(adapter) = state.channel_registry.get(&routing.channel_name) else {
+            return Ok(());
+        };
+        if adapter.is_local_only() {
+            return Ok(());
+        }
+        let external_chat_id = external_chat_id(state, chat_id).await?;
+        adapter
+            .send_file_preview(&external_chat_id, &card, &shown, &snapshot.path)
+            .await
+    }
+    .await;
+    if let Err(e) = result {
+        warn!("File preview for chat {chat_id} failed: {e}");
+    }
+}
+
+async fn external_chat_id(state: &AppState, chat_id: i64) -> Result<String, String> {
+    Ok(
+        call_blocking(state.db.clone(), move |db| db.get_chat_external_id(chat_id))
+            .await
+            .map_err(|e| format!("Failed to read external chat id for chat {chat_id}: {e}"))?
+            .unwrap_or_else(|| chat_id.to_string()),
+    )
+}
+
+fn file_tokens() -> &'static Mutex<VecDeque<(String, String, PathBuf)>> {
+    static TOKENS: OnceLock<Mutex<VecDeque<(String, String, PathBuf)>>> = OnceLock::new();
+    TOKENS.get_or_init(|| Mutex::new(VecDeque::new()))
+}
+
+/// Register a file behind a short token for channel buttons whose payload is
+/// size-limited (Telegram callback data is 64 bytes).
+pub fn register_file_token(external_chat_id: &str, path: &Path) -> String {
+    let token = uuid::Uuid::new_v4().simple().to_string()[..16].to_string();
+    let mut tokens = file_tokens().lock().unwrap_or_else(|e| e.into_inner());
+    if tokens.len() >= MAX_FILE_TOKENS {
+        tokens.pop_front();
+    }
+    tokens.push_back((
+        token.clone(),
+        external_chat_id.to_string(),
+        path.to_path_buf(),
+    ));
+    token
+}
+
+/// Resolve a button token; only the chat it was issued to can use it.
+pub fn lookup_file_token(token: &str, external_chat_id: &str) -> Option<PathBuf> {
+    let tokens = file_tokens().lock().unwrap_or_else(|e| e.into_inner());
+    tokens
+        .iter()
+        .find(|(t, chat, _)| t == token && chat == external_chat_id)
+        .map(|(_, _, path)| path.clone())
+}
+
+/// Resolve `/file` arguments inside the chat workspace. Paths outside the
+/// configured `working_dir` and sensitive paths are rejected.
+fn resolve_workspace_file(base: &Path, working_dir: &Path, arg: &str) -> Result<PathBuf, String> {
+    let path = resolve_tool_path(working_dir, arg);
+    path_guard::check_path(&path.to_string_lossy())?;
+    let canonical = path
+        .canonicalize()
+        .map_err(|_| format!("File not found: {arg}"))?;
+    let root = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+    if !canonical.starts_with(&root) {
+        return Err(format!("Access denied: {arg} is outside the workspace."));
+    }
+    if !canonical.is_file() {
+        return Err(format!("Not a file: {arg}"));
+    }
+    Ok(canonical)
+}
+
+/// Handle `/file <path>`. Returns `None` when the text is not this command.
+pub async fn handle_file_command(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    sender_id: Option<&str>,
+    text: &str,
+) -> Option<String> {
+    let rest = text.trim().strip_prefix("/file")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let arg = rest.trim();
+    if arg.is_empty() {
+        return Some(FILE_USAGE.to_string());
+    }
+    let working_dir = workspace::turn_working_dir(state, caller_channel, chat_id, sender_id).await;
+    let path = match resolve_workspace_file(
+        Path::new(state.config.working_dir_for_channel(caller_channel)),
+        &working_dir,
+        arg,
+    ) {
+        Ok(p) => p,
+        Err(e) => return Some(e),
+    };
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+    let adapter = state.channel_registry.get(caller_channel);
+    if let (Some(adapter), Ok(external)) = (adapter, external_chat_id(state, chat_id).await) {
+        match adapter.send_attachment(&external, &path, None).await {
+            Ok(_) => return Some(format!("Sent `{arg}` ({}).", fmt_bytes(size))),
+            Err(e) => warn!("/file attachment failed on {caller_channel}: {e}"),
+        }
+    }
+    match read_small(&path) {
+        Some(content) => {
+            let shown: String = content.chars().take(MAX_INLINE_CHARS).collect();
+            let more = if shown.len() < content.len() {
+                "\n… (truncated)"
+            } else {
+                ""
+            };
+            Some(format!(
+                "`{arg}` ({})\n```\n{shown}\n```{more}",
+                fmt_bytes(size)
+            ))
+        }
+        None => Some(format!(
+            "`{arg}` ({}) is too large or not text, and this channel cannot send attachments.",
+            fmt_bytes(size)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_card_new_file_and_diff() {
+        let new_file = render_card("notes.md", 40, None, "a\nb\nc\nd", 2);
+        assert!(new_file.starts_with("`notes.md` (40 B, new file, 4 lines)"));
+        assert!(new_file.contains("```\na\nb\n```"));
+        assert!(new_file.ends_with("… 2 more lines"));
+
+        let diff = render_card(
+            "src/lib.rs",
+            100,
+            Some("one\ntwo\nthree\nfour"),
+            "one\n2\nthree\nfour",
+            12,
+        );
+        assert!(diff.contains("(100 B, +1 -1 lines)"));
+        assert!(diff.contains("@@ line 2 @@\n-two\n+2\n```"));
+
+        let same = render_card("x", 1, Some("a"), "a", 12);
+        assert!(same.contains("unchanged"));
+    }
+
+    #[test]
+    fn test_resolve_workspace_file_stays_inside_working_dir() {
+        let base = std::env::temp_dir().join(format!("mc_file_preview_{}", uuid::Uuid::new_v4()));
+        let chat_dir = base.join("chat");
+        std::fs::create_dir_all(&chat_dir).unwrap();
+        std::fs::write(chat_dir.join("out.txt"), "hi").unwrap();
+        let outside = std::env::temp_dir().join(format!("mc_outside_{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&outside, "secret").unwrap();
+
+        assert!(resolve_workspace_file(&base, &chat_dir, "out.txt").is_ok());
+        assert!(resolve_workspace_file(&base, &chat_dir, "../chat/out.txt").is_ok());
+        assert!(resolve_workspace_file(&base, &chat_dir, "missing.txt").is_err());
+        assert!(
+            resolve_workspace_file(&base, &chat_dir, outside.to_str().unwrap())
+                .unwrap_err()
+                .contains("outside the workspace")
+        );
+        assert!(resolve_workspace_file(&base, &chat_dir, ".").is_err());
+
+        let token = register_file_token("42", &chat_dir.join("out.txt"));
+        assert!(lookup_file_token(&token, "42").is_some());
+        assert!(lookup_file_token(&token, "43").is_none());
+
+        let _ = std::fs::remove_dir_all(&base);
+        let _ = std::fs::remove_file(&outside);
+    }
+}
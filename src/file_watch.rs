@@ -0,0 +1,331 @@
+//! Workspace file watching.
+//!
+//! Chats register globs relative to their workspace with the `watch_path`
+//! tool. One notify watcher follows every workspace that has a watch
+//! (recursively), and the set is reconciled with `path_watches` every few
+//! seconds. Matching events are collected per watch and, once
+//! `file_watch.debounce_secs` pass without new ones, posted to the chat as a
+//! single message.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use notify::event::ModifyKind;
+use notify::{EventKind, RecursiveMode, Watcher};
+use tracing::{info, warn};
+
+use crate::channel::deliver_and_store_bot_message;
+use crate::db::{call_blocking, PathWatch};
+use crate::runtime::AppState;
+use crate::tools::path_guard;
+
+/// How often the watched workspaces are synced with `path_watches`.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(10);
+/// Changed paths listed per message; the rest are counted.
+const MAX_LISTED_CHANGES: usize = 20;
+
+/// Normalize a user-supplied glob: trimmed, relative, no `..`, and valid.
+pub fn normalize_pattern(pattern: &str) -> Result<String, String> {
+    let pattern = pattern
+        .trim()
+        .trim_start_matches("./")
+        .trim_end_matches('/');
+    if pattern.is_empty() {
+        return Err("pattern is empty".into());
+    }
+    let path = Path::new(pattern);
+    if path.is_absolute()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!(
+            "pattern must be relative to the working directory, without '..': {pattern}"
+        ));
+    }
+    glob::Pattern::new(pattern).map_err(|e| format!("invalid pattern {pattern:?}: {e}"))?;
+    Ok(pattern.to_string())
+}
+
+/// Whether `rel` (relative to the workspace) is covered by `pattern`. A
+/// pattern without glob characters also covers everything below it.
+pub fn pattern_matches(pattern: &str, rel: &Path) -> bool {
+    if !pattern.contains(['*', '?', '[']) {
+        return rel.starts_with(pattern);
+    }
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    glob::Pattern::new(pattern)
+        .map(|p| p.matches_path_with(rel, options))
+        .unwrap_or(false)
+}
+
+fn change_label(kind: &EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Create(_) => Some("created"),
+        EventKind::Remove(_) => Some("removed"),
+        EventKind::Modify(ModifyKind::Name(_)) => Some("renamed"),
+        EventKind::Modify(ModifyKind::Metadata(_)) => None,
+        EventKind::Modify(_) => Some("modified"),
+        _ => None,
+    }
+}
+
+struct Pending {
+    watch: PathWatch,
+    /// Relative path -> latest change.
+    changes: BTreeMap<String, &'static str>,
+    last_event: Instant,
+}
+
+/// Changes per watch, waiting for their quiet period to end.
+#[derive(Default)]
+pub struct ChangeCollector {
+    pending: HashMap<i64, Pending>,
+}
+
+impl ChangeCollector {
+    /// Add an event's paths to every watch they match.
+    pub fn record(
+        &mut self,
+        watches: &[PathWatch],
+        kind: &EventKind,
+        paths: &[PathBuf],
+        now: Instant,
+    ) {
+        let Some(label) = change_label(kind) else {
+            return;
+        };
+        for path in paths {
+            if path_guard::is_blocked(path) {
+                continue;
+            }
+            for watch in watches {
+                let Ok(rel) = path.strip_prefix(&watch.root) else {
+                    continue;
+                };
+                if !pattern_matches(&watch.pattern, rel) {
+                    continue;
+                }
+                let pending = self.pending.entry(watch.id).or_insert_with(|| Pending {
+                    watch: watch.clone(),
+                    changes: BTreeMap::new(),
+                    last_event: now,
+                });
+                pending
+                    .changes
+                    .insert(rel.to_string_lossy().replace('\\', "/"), label);
+                pending.last_event = now;
+            }
+        }
+    }
+
+    /// Watches that have been quiet for `debounce`, with their messages.
+    pub fn take_ready(&mut self, now: Instant, debounce: Duration) -> Vec<(i64, String)> {
+        let ready: Vec<i64> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| now.duration_since(p.last_event) >= debounce)
+            .map(|(id, _)| *id)
+            .collect();
+        ready
+            .into_iter()
+            .filter_map(|id| self.pending.remove(&id))
+            .map(|p| (p.watch.chat_id, change_message(&p.watch, &p.changes)))
+            .collect()
+    }
+}
+
+fn change_message(watch: &PathWatch, changes: &BTreeMap<String, &'static str>) -> String {
+    let mut text = format!(
+        "👀 Files matching `{}` changed (watch #{}):\n",
+        watch.pattern, watch.id
+    );
+    for (path, label) in changes.iter().take(MAX_LISTED_CHANGES) {
+        text.push_str(&format!("- {label}: {path}\n"));
+    }
+    if changes.len() > MAX_LISTED_CHANGES {
+        text.push_str(&format!("(+{} more)\n", changes.len() - MAX_LISTED_CHANGES));
+    }
+    text.trim_end().to_string()
+}
+
+/// Point the watcher at exactly the roots of `watches`.
+fn sync_roots(
+    watcher: &mut notify::RecommendedWatcher,
+    watched: &mut HashSet<PathBuf>,
+    watches: &[PathWatch],
+) {
+    let wanted: HashSet<PathBuf> = watches.iter().map(|w| PathBuf::from(&w.root)).collect();
+    for root in watched.difference(&wanted).cloned().collect::<Vec<_>>() {
+        let _ = watcher.unwatch(&root);
+        watched.remove(&root);
+    }
+    for root in wanted {
+        if watched.contains(&root) {
+            continue;
+        }
+        let _ = std::fs::create_dir_all(&root);
+        match watcher.watch(&root, RecursiveMode::Recursive) {
+            Ok(()) => {
+                watched.insert(root);
+            }
+            Err(e) => warn!("File watch: cannot watch {}: {e}", root.display()),
+        }
+    }
+}
+
+pub fn spawn_file_watcher(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = match notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        }) {
+            Ok(w) => w,
+            Err(e) => {
+                warn!("File watch: failed to start watcher: {e}");
+                return;
+            }
+        };
+        info!("File watcher started");
+        let debounce = Duration::from_secs(state.config.file_watch.debounce_secs);
+        let mut watched = HashSet::new();
+        let mut watches: Vec<PathWatch> = Vec::new();
+        let mut collector = ChangeCollector::default();
+        let mut reconcile = tokio::time::interval(RECONCILE_INTERVAL);
+        let mut flush = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = reconcile.tick() => {
+                    match call_blocking(state.db.clone(), |db| db.list_path_watches(None)).await {
+                        Ok(current) => {
+                            watches = current;
+                            sync_roots(&mut watcher, &mut watched, &watches);
+                        }
+                        Err(e) => warn!("File watch: failed to list watches: {e}"),
+                    }
+                }
+                Some(res) = rx.recv() => match res {
+                    Ok(event) => collector.record(&watches, &event.kind, &event.paths, Instant::now()),
+                    Err(e) => warn!("File watch: {e}"),
+                },
+                _ = flush.tick() => {
+                    for (chat_id, text) in collector.take_ready(Instant::now(), debounce) {
+                        if let Err(e) = deliver_and_store_bot_message(
+                            &state.channel_registry,
+                            state.db.clone(),
+                            &state.config.bot_username,
+                            chat_id,
+                            &text,
+                        )
+                        .await
+                        {
+                            warn!("File watch: failed to notify chat {chat_id}: {e}");
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, MetadataKind, RemoveKind};
+
+    fn watch(id: i64, root: &str, pattern: &str) -> PathWatch {
+        PathWatch {
+            id,
+            chat_id: id * 10,
+            root: root.into(),
+            pattern: pattern.into(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_pattern() {
+        assert_eq!(
+            normalize_pattern(" ./target/release/ ").unwrap(),
+            "target/release"
+        );
+        assert_eq!(normalize_pattern("dist/**/*.js").unwrap(), "dist/**/*.js");
+        assert!(normalize_pattern("").is_err());
+        assert!(normalize_pattern("/etc/*").is_err());
+        assert!(normalize_pattern("../other/*").is_err());
+        assert!(normalize_pattern("a/[").is_err());
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("dist/*.js", Path::new("dist/app.js")));
+        assert!(!pattern_matches("dist/*.js", Path::new("dist/sub/app.js")));
+        assert!(pattern_matches(
+            "dist/**/*.js",
+            Path::new("dist/sub/app.js")
+        ));
+        assert!(pattern_matches("build", Path::new("build/out/bin")));
+        assert!(!pattern_matches("build", Path::new("builder/x")));
+    }
+
+    #[test]
+    fn test_collector_debounces_per_watch() {
+        let watches = vec![watch(1, "/w/a", "dist/*"), watch(2, "/w/b", "logs")];
+        let mut collector = ChangeCollector::default();
+        let start = Instant::now();
+        let create = EventKind::Create(CreateKind::File);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        collector.record(
+            &watches,
+            &create,
+            &[PathBuf::from("/w/a/dist/app.js")],
+            start,
+        );
+        collector.record(
+            &watches,
+            &modify,
+            &[
+                PathBuf::from("/w/a/dist/app.js"),
+                PathBuf::from("/w/a/src/main.rs"),
+            ],
+            start + Duration::from_secs(2),
+        );
+        collector.record(
+            &watches,
+            &EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+            &[PathBuf::from("/w/b/logs/run.log")],
+            start,
+        );
+        collector.record(
+            &watches,
+            &EventKind::Remove(RemoveKind::File),
+            &[PathBuf::from("/w/b/logs/old.log")],
+            start + Duration::from_secs(4),
+        );
+
+        let debounce = Duration::from_secs(5);
+        assert!(collector
+            .take_ready(start + Duration::from_secs(6), debounce)
+            .is_empty());
+        let ready = collector.take_ready(start + Duration::from_secs(7), debounce);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].0, 10);
+        assert!(ready[0].1.contains("`dist/*`"));
+        assert!(ready[0].1.contains("- modified: dist/app.js"));
+        assert!(!ready[0].1.contains("main.rs"));
+
+        let ready = collector.take_ready(start + Duration::from_secs(9), debounce);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].0, 20);
+        assert!(ready[0].1.contains("- removed: logs/old.log"));
+        assert!(!ready[0].1.contains("run.log"));
+        assert!(collector
+            .take_ready(start + Duration::from_secs(60), debounce)
+            .is_empty());
+    }
+}
@@ -15,6 +15,8 @@ struct ServiceContext {
     exe_path: PathBuf,
     working_dir: PathBuf,
     config_path: Option<PathBuf>,
+    /// Config profile the service starts with.
+    profile: Option<String>,
     runtime_logs_dir: PathBuf,
 }
 
@@ -154,12 +156,14 @@ fn build_context() -> Result<ServiceContext> {
     let exe_path = std::env::current_exe().context("Failed to resolve current binary path")?;
     let working_dir = std::env::current_dir().context("Failed to resolve current directory")?;
     let config_path = resolve_config_path(&working_dir);
+    let profile = Config::active_profile()?;
     let runtime_logs_dir = resolve_runtime_logs_dir(&working_dir);
 
     Ok(ServiceContext {
         exe_path,
         working_dir,
         config_path,
+        profile,
         runtime_logs_dir,
     })
 }
@@ -238,6 +242,9 @@ fn render_linux_unit(ctx: &ServiceContext) -> String {
             config_path.display()
         ));
     }
+    if let Some(profile) = &ctx.profile {
+        unit.push_str(&format!("Environment=MICROCLAW_PROFILE={profile}\n"));
+    }
     unit.push_str("Restart=always\n");
     unit.push_str("RestartSec=5\n\n");
     unit.push_str("[Install]\n");
@@ -392,6 +399,10 @@ fn render_macos_plist(ctx: &ServiceContext) -> String {
             xml_escape(&config_path.to_string_lossy())
         ));
     }
+    if let Some(profile) = &ctx.profile {
+        items.push("    <key>MICROCLAW_PROFILE</key>".to_string());
+        items.push(format!("    <string>{}</string>", xml_escape(profile)));
+    }
     items.push("  </dict>".to_string());
 
     items.push("</dict>".to_string());
@@ -525,6 +536,7 @@ mod tests {
             exe_path: PathBuf::from("/usr/local/bin/microclaw"),
             working_dir: PathBuf::from("/tmp/microclaw"),
             config_path: Some(PathBuf::from("/tmp/microclaw/microclaw.config.yaml")),
+            profile: Some("prod".into()),
             runtime_logs_dir: PathBuf::from("/tmp/microclaw/runtime/logs"),
         };
 
@@ -533,6 +545,7 @@ mod tests {
         assert!(unit.contains("Restart=always"));
         assert!(unit.contains("MICROCLAW_GATEWAY=1"));
         assert!(unit.contains("MICROCLAW_CONFIG=/tmp/microclaw/microclaw.config.yaml"));
+        assert!(unit.contains("MICROCLAW_PROFILE=prod"));
     }
 
     #[test]
@@ -541,6 +554,7 @@ mod tests {
             exe_path: PathBuf::from("/usr/local/bin/microclaw"),
             working_dir: PathBuf::from("/tmp/microclaw"),
             config_path: Some(PathBuf::from("/tmp/microclaw/microclaw.config.yaml")),
+            profile: Some("prod".into()),
             runtime_logs_dir: PathBuf::from("/tmp/microclaw/runtime/logs"),
         };
 
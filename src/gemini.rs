@@ -0,0 +1,610 @@
+//! Native Gemini provider (`llm_provider: gemini`).
+//!
+//! Talks to the `generateContent` / `streamGenerateContent` API directly
+//! instead of the OpenAI compatibility endpoint, which loses images in tool
+//! loops and some function-call shapes. Tool calls map to `functionCall` /
+//! `functionResponse` parts, images to `inlineData`, and `gemini_safety_threshold`
+//! to `safetySettings`.
+//!
+//! Gemini does not always return ids for function calls, so ids are generated
+//! here and tool results are matched back to the call's name. Thought
+//! signatures that come with a function call are kept as a signature-only
+//! `Thinking` block right before the `ToolUse` block and sent back with it.
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::config::Config;
+use crate::error::MicroClawError;
+use crate::llm::{sanitize_messages, LlmProvider, SseEventParser};
+use crate::llm_types::{
+    ContentBlock, Message, MessageContent, MessagesResponse, ResponseContentBlock, ResponseSchema,
+    ToolDefinition, Usage,
+};
+use crate::wire_log::WireExchange;
+
+pub const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Harm categories `gemini_safety_threshold` applies to.
+const SAFETY_CATEGORIES: &[&str] = &[
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+/// Accepted values for `gemini_safety_threshold`.
+pub const SAFETY_THRESHOLDS: &[&str] = &[
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+    "OFF",
+];
+
+pub struct GeminiProvider {
+    http: reqwest::Client,
+    api_key: String,
+    model: String,
+    max_tokens: u32,
+    thinking_budget: u32,
+    temperature: Option<f64>,
+    safety_threshold: Option<String>,
+    base_url: String,
+    /// Token from `microclaw login gemini`, used instead of `api_key`.
+    oauth: Option<crate::provider_oauth::OAuthCredential>,
+}
+
+/// API root for `configured_base`; accepts the OpenAI-compat URL too.
+fn resolve_gemini_base(configured_base: &str) -> String {
+    let trimmed = configured_base.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return DEFAULT_GEMINI_BASE_URL.to_string();
+    }
+    trimmed.trim_end_matches("/openai").to_string()
+}
+
+impl GeminiProvider {
+    pub fn new(config: &Config) -> Self {
+        GeminiProvider {
+            http: reqwest::Client::new(),
+            api_key: config.api_key.clone(),
+            model: config
+                .model
+                .trim()
+                .trim_start_matches("models/")
+                .to_string(),
+            max_tokens: config.max_tokens,
+            thinking_budget: config.thinking.budget_tokens,
+            temperature: config.temperature,
+            safety_threshold: config.gemini_safety_threshold.clone(),
+            base_url: resolve_gemini_base(config.llm_base_url.as_deref().unwrap_or("")),
+            oauth: crate::provider_oauth::OAuthCredential::from_config(config),
+        }
+    }
+
+    fn build_body(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Value {
+        let mut generation_config = json!({ "maxOutputTokens": self.max_tokens });
+        if let Some(temperature) = self.temperature {
+            generation_config["temperature"] = json!(temperature);
+        }
+        if self.thinking_budget > 0 {
+            generation_config["maxOutputTokens"] =
+                json!(self.max_tokens.saturating_add(self.thinking_budget));
+            generation_config["thinkingConfig"] = json!({
+                "thinkingBudget": self.thinking_budget,
+                "includeThoughts": true,
+            });
+        }
+        let mut body = json!({
+            "contents": translate_messages(sanitize_messages(messages)),
+            "generationConfig": generation_config,
+        });
+        if !system.trim().is_empty() {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        if let Some(tools) = tools.filter(|t| !t.is_empty()) {
+            body["tools"] = json!([{ "functionDeclarations": translate_tools(&tools) }]);
+        }
+        if let Some(threshold) = &self.safety_threshold {
+            body["safetySettings"] = Value::Array(
+                SAFETY_CATEGORIES
+                    .iter()
+                    .map(|category| json!({ "category": category, "threshold": threshold }))
+                    .collect(),
+            );
+        }
+        body
+    }
+
+    /// POST to `method`, returning the response and its wire log entry.
+    async fn post(
+        &self,
+        method: &str,
+        body: &Value,
+    ) -> Result<(reqwest::Response, WireExchange), MicroClawError> {
+        let url = format!("{}/models/{}:{method}", self.base_url, self.model);
+        let wire = crate::wire_log::begin("gemini", &url, body);
+        let req = self
+            .http
+            .post(url)
+            .header("content-type", "application/json")
+            .json(body);
+        let req = match &self.oauth {
+            Some(oauth) => req.bearer_auth(oauth.bearer_token().await?),
+            None => req.header("x-goog-api-key", &self.api_key),
+        };
+        let response = req.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok((response, wire));
+        }
+        let text = response.text().await.unwrap_or_default();
+        wire.finish(status.as_u16(), &text);
+        let message = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|v| {
+                v.pointer("/error/message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| format!("HTTP {status}: {text}"));
+        Err(MicroClawError::LlmHttp {
+            status: status.as_u16(),
+            message,
+        })
+    }
+
+    async fn generate(&self, body: &Value) -> Result<MessagesResponse, MicroClawError> {
+        let (response, wire) = self.post("generateContent", body).await?;
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        wire.finish(status, &text);
+        let value: Value = serde_json::from_str(&text).map_err(|e| {
+            MicroClawError::LlmApi(format!(
+                "Failed to parse Gemini response: {e}\nBody: {text}"
+            ))
+        })?;
+        let mut reply = GeminiReply::default();
+        reply.push_chunk(&value, None);
+        Ok(reply.into_response())
+    }
+}
+
+/// JSON schema for a tool, passed through as `parametersJsonSchema`.
+fn translate_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "parametersJsonSchema": tool.input_schema,
+            })
+        })
+        .collect()
+}
+
+/// Convert the conversation into Gemini `contents`.
+fn translate_messages(messages: Vec<Message>) -> Vec<Value> {
+    // Tool results only carry the call id; Gemini wants the function name.
+    let mut call_names: HashMap<String, String> = HashMap::new();
+    for msg in &messages {
+        if let MessageContent::Blocks(blocks) = &msg.content {
+            for block in blocks {
+                if let ContentBlock::ToolUse { id, name, .. } = block {
+                    call_names.insert(id.clone(), name.clone());
+                }
+            }
+        }
+    }
+
+    let mut contents: Vec<Value> = Vec::new();
+    for msg in messages {
+        let role = if msg.role == "assistant" {
+            "model"
+        } else {
+            "user"
+        };
+        let mut parts = Vec::new();
+        match msg.content {
+            MessageContent::Text(text) => {
+                if !text.is_empty() {
+                    parts.push(json!({ "text": text }));
+                }
+            }
+            MessageContent::Blocks(blocks) => {
+                let mut signature: Option<String> = None;
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text } => {
+                            if !text.is_empty() {
+                                parts.push(json!({ "text": text }));
+                            }
+                        }
+                        ContentBlock::Image { source } => parts.push(json!({
+                            "inlineData": { "mimeType": source.media_type, "data": source.data }
+                        })),
+                        ContentBlock::Thinking { signature: sig, .. } if !sig.is_empty() => {
+                            signature = Some(sig)
+                        }
+                        ContentBlock::ToolUse { name, input, .. } => {
+                            let mut part =
+                                json!({ "functionCall": { "name": name, "args": input } });
+                            if let Some(sig) = signature.take() {
+                                part["thoughtSignature"] = json!(sig);
+                            }
+                            parts.push(part);
+                        }
+                        ContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                            is_error,
+                        } => {
+                            let name = call_names
+                                .get(&tool_use_id)
+                                .cloned()
+                                .unwrap_or_else(|| tool_use_id.clone());
+                            let key = if is_error == Some(true) {
+                                "error"
+                            } else {
+                                "result"
+                            };
+                            parts.push(json!({
+                                "functionResponse": { "name": name, "response": { key: content } }
+                            }));
+                        }
+                        ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. } => {}
+                    }
+                }
+            }
+        }
+        if parts.is_empty() {
+            continue;
+        }
+        match contents.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["parts"].as_array_mut() {
+                    existing.extend(parts);
+                }
+            }
+            _ => contents.push(json!({ "role": role, "parts": parts })),
+        }
+    }
+    contents
+}
+
+/// Reply assembled from one response or a stream of chunks.
+#[derive(Default)]
+struct GeminiReply {
+    thoughts: String,
+    text: String,
+    /// Function calls in order, with the thought signature sent with each.
+    calls: Vec<(String, Value, Option<String>)>,
+    finish_reason: Option<String>,
+    block_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl GeminiReply {
+    /// Add one `GenerateContentResponse`, forwarding text (and thoughts in
+    /// `<think>` tags) to `text_tx`.
+    fn push_chunk(&mut self, chunk: &Value, text_tx: Option<&UnboundedSender<String>>) {
+        let send = |piece: &str| {
+            if let Some(tx) = text_tx {
+                let _ = tx.send(piece.to_string());
+            }
+        };
+        if let Some(reason) = chunk
+            .pointer("/promptFeedback/blockReason")
+            .and_then(Value::as_str)
+        {
+            self.block_reason = Some(reason.to_string());
+        }
+        if let Some(meta) = chunk.get("usageMetadata") {
+            let count = |key: &str| {
+                meta.get(key)
+                    .and_then(Value::as_u64)
+                    .map(|n| u32::try_from(n).unwrap_or(u32::MAX))
+                    .unwrap_or(0)
+            };
+            self.usage = Some(Usage {
+                input_tokens: count("promptTokenCount"),
+                output_tokens: count("candidatesTokenCount")
+                    .saturating_add(count("thoughtsTokenCount")),
+            });
+        }
+        let Some(candidate) = chunk
+            .get("candidates")
+            .and_then(Value::as_array)
+            .and_then(|c| c.first())
+        else {
+            return;
+        };
+        if let Some(reason) = candidate.get("finishReason").and_then(Value::as_str) {
+            self.finish_reason = Some(reason.to_string());
+        }
+        let parts = candidate
+            .pointer("/content/parts")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for part in parts {
+            let signature = part
+                .get("thoughtSignature")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if let Some(call) = part.get("functionCall") {
+                let name = call
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+                self.calls.push((name, args, signature));
+                continue;
+            }
+            let Some(text) = part.get("text").and_then(Value::as_str) else {
+                continue;
+            };
+            if part.get("thought").and_then(Value::as_bool) == Some(true) {
+                if self.thoughts.is_empty() {
+                    send("<think>");
+                }
+                self.thoughts.push_str(text);
+                send(text);
+            } else if !text.is_empty() {
+                if self.text.is_empty() && !self.thoughts.is_empty() {
+                    send("</think>");
+                }
+                self.text.push_str(text);
+                send(text);
+            }
+        }
+    }
+
+    fn into_response(self) -> MessagesResponse {
+        let mut content = Vec::new();
+        if !self.thoughts.trim().is_empty() {
+            content.push(ResponseContentBlock::Thinking {
+                thinking: self.thoughts,
+                signature: String::new(),
+            });
+        }
+        let text = match (&self.block_reason, self.finish_reason.as_deref()) {
+            (Some(reason), _) => format!("(Gemini blocked this request: {reason})"),
+            (None, Some("SAFETY" | "PROHIBITED_CONTENT" | "BLOCKLIST" | "SPII"))
+                if self.text.is_empty() =>
+            {
+                "(Gemini's safety filters blocked this reply)".to_string()
+            }
+            _ => self.text,
+        };
+        if !text.is_empty() {
+            content.push(ResponseContentBlock::Text { text });
+        }
+        let has_calls = !self.calls.is_empty();
+        for (name, input, signature) in self.calls {
+            if let Some(signature) = signature {
+                content.push(ResponseContentBlock::Thinking {
+                    thinking: String::new(),
+                    signature,
+                });
+            }
+            content.push(ResponseContentBlock::ToolUse {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                name,
+                input,
+            });
+        }
+        if content.is_empty() {
+            content.push(ResponseContentBlock::Text {
+                text: String::new(),
+            });
+        }
+        let stop_reason = if has_calls {
+            "tool_use"
+        } else if self.finish_reason.as_deref() == Some("MAX_TOKENS") {
+            "max_tokens"
+        } else {
+            "end_turn"
+        };
+        MessagesResponse {
+            content,
+            stop_reason: Some(stop_reason.into()),
+            usage: self.usage,
+            served_by: None,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let body = self.build_body(system, messages, tools);
+        self.generate(&body).await
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let body = self.build_body(system, messages, tools);
+        let (response, mut wire) = self.post("streamGenerateContent?alt=sse", &body).await?;
+        let status = response.status().as_u16();
+        let mut byte_stream = response.bytes_stream();
+        let mut sse = SseEventParser::default();
+        let mut reply = GeminiReply::default();
+        while let Some(chunk) = byte_stream.next().await {
+            let Ok(chunk) = chunk else { break };
+            for data in sse.push_chunk(&String::from_utf8_lossy(&chunk)) {
+                wire.event(&data);
+                if let Ok(value) = serde_json::from_str::<Value>(&data) {
+                    reply.push_chunk(&value, text_tx);
+                }
+            }
+        }
+        for data in sse.finish() {
+            wire.event(&data);
+            if let Ok(value) = serde_json::from_str::<Value>(&data) {
+                reply.push_chunk(&value, text_tx);
+            }
+        }
+        wire.finish_stream(status);
+        if !reply.thoughts.is_empty() && reply.text.is_empty() {
+            if let Some(tx) = text_tx {
+                let _ = tx.send("</think>".into());
+            }
+        }
+        Ok(reply.into_response())
+    }
+
+    async fn send_message_json(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        schema: &ResponseSchema,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let mut body = self.build_body(system, messages, None);
+        body["generationConfig"]["responseMimeType"] = json!("application/json");
+        body["generationConfig"]["responseJsonSchema"] = schema.schema.clone();
+        self.generate(&body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::ImageSource;
+
+    #[test]
+    fn test_translate_messages_maps_tools_images_and_signatures() {
+        let messages = vec![
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![
+                    ContentBlock::Image {
+                        source: ImageSource {
+                            source_type: "base64".into(),
+                            media_type: "image/png".into(),
+                            data: "AAAA".into(),
+                        },
+                    },
+                    ContentBlock::Text {
+                        text: "what is this?".into(),
+                    },
+                ]),
+            },
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![
+                    ContentBlock::Thinking {
+                        thinking: String::new(),
+                        signature: "sig-1".into(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "call_1".into(),
+                        name: "read_file".into(),
+                        input: json!({"path": "a.txt"}),
+                    },
+                ]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "call_1".into(),
+                    content: "hello".into(),
+                    is_error: None,
+                }]),
+            },
+        ];
+        let contents = translate_messages(messages);
+        assert_eq!(contents.len(), 3);
+        assert_eq!(
+            contents[0]["parts"][0]["inlineData"]["mimeType"],
+            "image/png"
+        );
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["functionCall"]["name"], "read_file");
+        assert_eq!(contents[1]["parts"][0]["thoughtSignature"], "sig-1");
+        assert_eq!(
+            contents[2]["parts"][0]["functionResponse"],
+            json!({"name": "read_file", "response": {"result": "hello"}})
+        );
+    }
+
+    #[test]
+    fn test_reply_from_chunks() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let mut reply = GeminiReply::default();
+        reply.push_chunk(
+            &json!({"candidates": [{"content": {"parts": [
+                {"text": "Look it up.", "thought": true}
+            ]}}]}),
+            Some(&tx),
+        );
+        reply.push_chunk(
+            &json!({
+                "candidates": [{"content": {"parts": [
+                    {"text": "Checking."},
+                    {"functionCall": {"name": "web_search", "args": {"q": "rust"}}, "thoughtSignature": "s"}
+                ]}, "finishReason": "STOP"}],
+                "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 5, "thoughtsTokenCount": 3}
+            }),
+            Some(&tx),
+        );
+        drop(tx);
+        let mut streamed = String::new();
+        while let Ok(piece) = rx.try_recv() {
+            streamed.push_str(&piece);
+        }
+        assert_eq!(streamed, "<think>Look it up.</think>Checking.");
+
+        let response = reply.into_response();
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.usage.as_ref().unwrap().output_tokens, 8);
+        assert!(matches!(
+            &response.content[0],
+            ResponseContentBlock::Thinking { thinking, .. } if thinking == "Look it up."
+        ));
+        assert!(matches!(
+            &response.content[2],
+            ResponseContentBlock::Thinking { thinking, signature } if thinking.is_empty() && signature == "s"
+        ));
+        assert!(matches!(
+            &response.content[3],
+            ResponseContentBlock::ToolUse { name, .. } if name == "web_search"
+        ));
+    }
+
+    #[test]
+    fn test_blocked_reply_is_explained() {
+        let mut reply = GeminiReply::default();
+        reply.push_chunk(&json!({"candidates": [{"finishReason": "SAFETY"}]}), None);
+        let response = reply.into_response();
+        assert!(matches!(
+            &response.content[0],
+            ResponseContentBlock::Text { text } if text.contains("safety filters")
+        ));
+        assert_eq!(
+            resolve_gemini_base("https://generativelanguage.googleapis.com/v1beta/openai/"),
+            DEFAULT_GEMINI_BASE_URL
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Dependency checks behind `GET /api/health`.
+//!
+//! Long-running loops record heartbeats here: each Telegram bot when its
+//! update listener delivers an update or fails, and the scheduler after every
+//! tick. [`report`] combines them with live checks (a database round trip and
+//! free disk space under `data_dir`) and the per-model LLM health from
+//! `provider_health`. Any failing check marks the whole report degraded.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use crate::db::call_blocking;
+use crate::runtime::AppState;
+
+/// The scheduler ticks every minute; three missed ticks mean it is stuck.
+const SCHEDULER_STALE_AFTER: Duration = Duration::minutes(3);
+/// A listener error this recent, with no update since, means polling fails.
+const POLL_ERROR_WINDOW: Duration = Duration::minutes(2);
+/// Below this much free space under `data_dir` the disk check fails.
+const MIN_FREE_DISK_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default)]
+struct Heartbeat {
+    last_ok: Option<DateTime<Utc>>,
+    last_error: Option<(String, DateTime<Utc>)>,
+}
+
+fn heartbeats() -> std::sync::MutexGuard<'static, BTreeMap<String, Heartbeat>> {
+    static HEARTBEATS: OnceLock<Mutex<BTreeMap<String, Heartbeat>>> = OnceLock::new();
+    HEARTBEATS
+        .get_or_init(|| Mutex::new(BTreeMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Record that `component` (e.g. `scheduler`, `telegram`) just did its work.
+pub fn beat(component: &str) {
+    heartbeats()
+        .entry(component.to_string())
+        .or_default()
+        .last_ok = Some(Utc::now());
+}
+
+/// Record that `component` just failed.
+pub fn fail(component: &str, error: impl Into<String>) {
+    heartbeats()
+        .entry(component.to_string())
+        .or_default()
+        .last_error = Some((error.into(), Utc::now()));
+}
+
+fn heartbeat(component: &str) -> Option<Heartbeat> {
+    heartbeats().get(component).cloned()
+}
+
+fn check(ok: bool, mut details: serde_json::Value) -> serde_json::Value {
+    details["ok"] = json!(ok);
+    details
+}
+
+fn scheduler_check(now: DateTime<Utc>) -> serde_json::Value {
+    // No heartbeat: the scheduler does not run in this process (tests, `chat`).
+    let Some(last) = heartbeat("scheduler").and_then(|h| h.last_ok) else {
+        return check(true, json!({ "running": false }));
+    };
+    check(
+        now - last <= SCHEDULER_STALE_AFTER,
+        json!({ "running": true, "last_heartbeat": last.to_rfc3339() }),
+    )
+}
+
+fn telegram_check(channel: &str, now: DateTime<Utc>) -> serde_json::Value {
+    let beat = heartbeat(channel).unwrap_or_default();
+    let failing = beat.last_error.as_ref().is_some_and(|(_, at)| {
+        now - *at <= POLL_ERROR_WINDOW && beat.last_ok.is_none_or(|ok| ok < *at)
+    });
+    let mut details = json!({
+        "last_update": beat.last_ok.map(|at| at.to_rfc3339()),
+    });
+    if let Some((error, at)) = &beat.last_error {
+        details["last_error"] = json!(error);
+        details["last_error_at"] = json!(at.to_rfc3339());
+    }
+    check(!failing, details)
+}
+
+fn llm_check() -> serde_json::Value {
+    let models = crate::provider_health::snapshot();
+    // Healthy while some model in the chain can still answer.
+    let ok = models.is_empty()
+        || models
+            .iter()
+            .any(|h| !crate::provider_health::is_cooling_down(&h.provider, &h.model));
+    let models: Vec<serde_json::Value> = models
+        .iter()
+        .map(|h| {
+            json!({
+                "provider": h.provider,
+                "model": h.model,
+                "score": h.score,
+                "consecutive_failures": h.consecutive_failures,
+                "last_success": h.last_success.map(|at| at.to_rfc3339()),
+                "last_error": h.last_error.as_ref().map(|(_, message, _)| message),
+            })
+        })
+        .collect();
+    check(ok, json!({ "models": models }))
+}
+
+#[cfg(unix)]
+fn free_disk_bytes(path: &str) -> std::io::Result<u64> {
+    let c_path = std::ffi::CString::new(path)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_disk_bytes(_path: &str) -> std::io::Result<u64> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "free disk space is only checked on unix",
+    ))
+}
+
+fn disk_check(data_dir: &str) -> serde_json::Value {
+    match free_disk_bytes(data_dir) {
+        Ok(free) => check(
+            free >= MIN_FREE_DISK_BYTES,
+            json!({ "path": data_dir, "free_bytes": free, "min_free_bytes": MIN_FREE_DISK_BYTES }),
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+            check(true, json!({ "path": data_dir, "skipped": e.to_string() }))
+        }
+        Err(e) => check(false, json!({ "path": data_dir, "error": e.to_string() })),
+    }
+}
+
+/// Run every check. Returns whether all passed, and the per-check details.
+pub async fn report(state: &AppState) -> (bool, serde_json::Value) {
+    let now = Utc::now();
+    let mut checks = serde_json::Map::new();
+
+    let database = match call_blocking(state.db.clone(), |db| db.ping()).await {
+        Ok(()) => check(true, json!({})),
+        Err(e) => check(false, json!({ "error": e.to_string() })),
+    };
+    checks.insert("database".into(), database);
+    for channel in state.channel_registry.names() {
+        if channel == "telegram" || channel.starts_with("telegram:") {
+            checks.insert(channel.to_string(), telegram_check(channel, now));
+        }
+    }
+    checks.insert("llm".into(), llm_check());
+    checks.insert("scheduler".into(), scheduler_check(now));
+    checks.insert("disk".into(), disk_check(&state.config.data_dir));
+
+    let ok = checks.values().all(|c| c["ok"] == json!(true));
+    (ok, serde_json::Value::Object(checks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telegram_check_tracks_listener_errors() {
+        let channel = "telegram:health-test";
+        let now = Utc::now();
+        assert_eq!(telegram_check(channel, now)["ok"], true);
+
+        fail(channel, "network down");
+        let failing = telegram_check(channel, Utc::now());
+        assert_eq!(failing["ok"], false);
+        assert_eq!(failing["last_error"], "network down");
+        // Old errors stop counting.
+        assert_eq!(
+            telegram_check(channel, Utc::now() + Duration::minutes(5))["ok"],
+            true
+        );
+
+        beat(channel);
+        assert_eq!(telegram_check(channel, Utc::now())["ok"], true);
+    }
+
+    #[test]
+    fn test_disk_check_reports_free_space() {
+        let dir = std::env::temp_dir();
+        let result = disk_check(&dir.to_string_lossy());
+        assert!(result.get("free_bytes").is_some() || result.get("skipped").is_some());
+        #[cfg(unix)]
+        assert_eq!(disk_check("/nonexistent/microclaw-health")["ok"], false);
+    }
+}
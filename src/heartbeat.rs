@@ -0,0 +1,297 @@
+//! Proactive check-ins (`heartbeat.enabled`).
+//!
+//! Every `heartbeat.interval_mins` the agent reviews each configured chat:
+//! its memory, open todos and recent conversation. The model either answers
+//! [`NOTHING_TO_SAY`] or writes a short message about something that needs
+//! attention (an overdue todo, a question left unanswered), which is posted
+//! to the chat. Quiet hours, a per-day cap and an idle window keep it from
+//! interrupting or nagging: a chat that ignored the last check-in is not
+//! reviewed again until something new happens in it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use tracing::{info, warn};
+
+use crate::channel::deliver_and_store_bot_message;
+use crate::db::call_blocking;
+use crate::llm_types::{Message, MessageContent, ResponseContentBlock};
+use crate::runtime::AppState;
+use crate::tools::todo::read_todos;
+
+/// Reply meaning "nothing needs attention".
+const NOTHING_TO_SAY: &str = "HEARTBEAT_OK";
+/// Recent messages shown to the model.
+const RECENT_MESSAGES: usize = 30;
+
+const HEARTBEAT_SYSTEM_PROMPT: &str = r#"You are reviewing a chat to decide whether to proactively message the user.
+
+Message them only when something clearly needs attention now, for example:
+- an open todo that is overdue or due soon
+- a question the user asked that was never answered, or a follow-up you promised
+- a date or commitment from memory that is coming up
+
+Do not repeat a reminder that is already in the recent conversation, do not make small talk, and do not message just to say nothing is happening.
+
+If nothing needs attention, reply with exactly HEARTBEAT_OK. Otherwise reply with only the message to send: one to three short sentences, addressed to the user."#;
+
+/// Daily window without check-ins; may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Parse `HH:MM-HH:MM`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("heartbeat.quiet_hours must look like 22:00-08:00, got {value:?}");
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| invalid());
+        Ok(QuietHours {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// What the watcher remembers about a chat between reviews.
+#[derive(Debug, Default)]
+struct ChatState {
+    /// Check-ins sent on the given local day.
+    sent: Option<(NaiveDate, u32)>,
+    /// Newest message at the last review that produced a check-in.
+    unanswered_at: Option<String>,
+}
+
+impl ChatState {
+    fn sent_on(&self, day: NaiveDate) -> u32 {
+        match self.sent {
+            Some((d, count)) if d == day => count,
+            _ => 0,
+        }
+    }
+}
+
+fn chats(state: &AppState) -> Vec<i64> {
+    if state.config.heartbeat.chat_ids.is_empty() {
+        state.config.control_chat_ids.clone()
+    } else {
+        state.config.heartbeat.chat_ids.clone()
+    }
+}
+
+/// The model's reply as a message to send, or `None` for nothing to say.
+fn check_in_message(reply: &str) -> Option<String> {
+    let reply = reply.trim();
+    if reply.is_empty() || reply.contains(NOTHING_TO_SAY) {
+        None
+    } else {
+        Some(reply.to_string())
+    }
+}
+
+async fn review_chat(
+    state: &AppState,
+    chat_id: i64,
+    now: DateTime<Utc>,
+    chat: &mut ChatState,
+    today: NaiveDate,
+) {
+    let messages = match call_blocking(state.db.clone(), move |db| {
+        db.get_recent_messages(chat_id, RECENT_MESSAGES)
+    })
+    .await
+    {
+        Ok(messages) => messages,
+        Err(e) => {
+            warn!("Heartbeat: failed to load messages for chat {chat_id}: {e}");
+            return;
+        }
+    };
+    let latest = messages.last().map(|m| m.timestamp.clone());
+    if let Some(ts) = &latest {
+        let active = DateTime::parse_from_rfc3339(ts).is_ok_and(|ts| {
+            now - ts.with_timezone(&Utc)
+                < chrono::Duration::minutes(state.config.heartbeat.idle_mins as i64)
+        });
+        if active {
+            return;
+        }
+    }
+    // The last check-in is still unanswered; don't follow up on it.
+    if chat.unanswered_at.is_some() && chat.unanswered_at == latest {
+        return;
+    }
+
+    let memory_chat_id = crate::identity::identity_chat_id(state.db.clone(), chat_id).await;
+    let groups_dir = std::path::Path::new(&state.config.data_dir).join("groups");
+    let open_todos: Vec<String> = read_todos(&groups_dir, memory_chat_id)
+        .into_iter()
+        .filter(|t| t.status != "completed")
+        .map(|t| format!("- [{}] {}", t.status, t.task))
+        .collect();
+    if messages.is_empty() && open_todos.is_empty() {
+        return;
+    }
+    let memory = state.memory.build_memory_context(memory_chat_id);
+    let structured = call_blocking(state.db.clone(), move |db| {
+        db.get_all_memories_for_chat(Some(memory_chat_id))
+    })
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .filter(|m| !m.is_archived)
+    .map(|m| format!("- [{}] {}", m.category, m.content))
+    .collect::<Vec<_>>();
+    let conversation = messages
+        .iter()
+        .map(|m| format!("[{}] {}: {}", m.timestamp, m.sender_name, m.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let tz: chrono_tz::Tz = state.config.timezone.parse().unwrap_or(chrono_tz::Tz::UTC);
+    let prompt = format!(
+        "Current time: {} ({tz})\n\n{memory}Structured memories:\n{}\n\nOpen todos:\n{}\n\nRecent conversation (oldest first):\n{}",
+        now.with_timezone(&tz).format("%a %Y-%m-%d %H:%M"),
+        if structured.is_empty() { "(none)".to_string() } else { structured.join("\n") },
+        if open_todos.is_empty() { "(none)".to_string() } else { open_todos.join("\n") },
+        if conversation.is_empty() { "(none)" } else { &conversation },
+    );
+    let user_msg = Message {
+        role: "user".into(),
+        content: MessageContent::Text(prompt),
+    };
+    let response = match state
+        .llm
+        .send_message(HEARTBEAT_SYSTEM_PROMPT, vec![user_msg], None)
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Heartbeat: LLM call failed for chat {chat_id}: {e}");
+            return;
+        }
+    };
+    let reply: String = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ResponseContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    let Some(text) = check_in_message(&reply) else {
+        return;
+    };
+
+    info!("Heartbeat: checking in with chat {chat_id}");
+    match deliver_and_store_bot_message(
+        &state.channel_registry,
+        state.db.clone(),
+        &state.config.bot_username,
+        chat_id,
+        &text,
+    )
+    .await
+    {
+        Ok(()) => {
+            chat.sent = Some((today, chat.sent_on(today) + 1));
+            // The check-in itself is now the newest message.
+            chat.unanswered_at = call_blocking(state.db.clone(), move |db| {
+                db.get_recent_messages(chat_id, 1)
+            })
+            .await
+            .ok()
+            .and_then(|m| m.last().map(|m| m.timestamp.clone()));
+        }
+        Err(e) => warn!("Heartbeat: failed to deliver to chat {chat_id}: {e}"),
+    }
+}
+
+pub fn spawn_heartbeat(state: Arc<AppState>) {
+    let config = &state.config.heartbeat;
+    if !config.enabled {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(config.interval_mins * 60);
+    let quiet = config
+        .quiet_hours
+        .as_deref()
+        .and_then(|q| QuietHours::parse(q).ok());
+    tokio::spawn(async move {
+        info!(
+            "Heartbeat started (interval: {}min, chats: {:?})",
+            state.config.heartbeat.interval_mins,
+            chats(&state)
+        );
+        let tz: chrono_tz::Tz = state.config.timezone.parse().unwrap_or(chrono_tz::Tz::UTC);
+        let mut chat_states: HashMap<i64, ChatState> = HashMap::new();
+        loop {
+            tokio::time::sleep(interval).await;
+            let now = Utc::now();
+            let local = now.with_timezone(&tz);
+            if quiet.is_some_and(|q| q.contains(local.time())) {
+                continue;
+            }
+            for chat_id in chats(&state) {
+                let chat = chat_states.entry(chat_id).or_default();
+                if chat.sent_on(local.date_naive()) >= state.config.heartbeat.max_per_day {
+                    continue;
+                }
+                review_chat(&state, chat_id, now, chat, local.date_naive()).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let night = QuietHours::parse("22:00-08:00").unwrap();
+        assert!(night.contains(at(23, 30)));
+        assert!(night.contains(at(7, 59)));
+        assert!(!night.contains(at(8, 0)));
+        assert!(!night.contains(at(12, 0)));
+
+        let lunch = QuietHours::parse(" 12:00 - 13:30 ").unwrap();
+        assert!(lunch.contains(at(12, 45)));
+        assert!(!lunch.contains(at(13, 30)));
+
+        assert!(QuietHours::parse("late").is_err());
+        assert!(QuietHours::parse("25:00-08:00").is_err());
+    }
+
+    #[test]
+    fn test_check_in_message() {
+        assert_eq!(check_in_message(" HEARTBEAT_OK\n"), None);
+        assert_eq!(check_in_message(""), None);
+        assert_eq!(
+            check_in_message("Your passport renewal todo is due tomorrow.\n").as_deref(),
+            Some("Your passport renewal todo is due tomorrow.")
+        );
+
+        let mut chat = ChatState::default();
+        let day = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        assert_eq!(chat.sent_on(day), 0);
+        chat.sent = Some((day, 2));
+        assert_eq!(chat.sent_on(day), 2);
+        assert_eq!(chat.sent_on(day.succ_opt().unwrap()), 0);
+    }
+}
@@ -0,0 +1,436 @@
+//! Bot-facing strings in the chat's language.
+//!
+//! `language` in the config sets the default; `/language en|zh` overrides
+//! it for a chat (stored in `chat_settings`). Strings the bot itself writes
+//! — errors, stop and approval notices, budget refusals, `/usage` reports,
+//! setup hints — go through [`t`] / [`tf`]; the model's replies are not
+//! translated.
+
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::db::{call_blocking, Database};
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding the chat's `/language`.
+pub const LANGUAGE_SETTING_KEY: &str = "language";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    En,
+    Zh,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::En, Language::Zh];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Zh => "zh",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::Zh => "中文",
+        }
+    }
+
+    /// Accepts codes (`zh`, `zh-CN`, `en_US`) and names (`Chinese`, `中文`).
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase().replace('_', "-");
+        let primary = value.split('-').next().unwrap_or_default();
+        match primary {
+            "en" | "english" => Some(Language::En),
+            "zh" | "chinese" | "中文" | "简体中文" => Some(Language::Zh),
+            _ => None,
+        }
+    }
+}
+
+/// A bot-facing string. `{name}` placeholders are filled by [`tf`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    NoVisibleReply,
+    OutputLimit,
+    Done,
+    Error,
+    DocumentTooLarge,
+    VoiceNotConfigured,
+    VoiceFailed,
+    Stopped,
+    CancelledList,
+    CancelledReply,
+    CancelledRunning,
+    CancelledPending,
+    ListSeparator,
+    ToolsFinished,
+    NothingToStop,
+    Stopping,
+    ApprovalRequired,
+    ApprovalInvalid,
+    ReadOnlyBlocked,
+    BudgetGlobalReached,
+    BudgetChatReached,
+    UserRateLimited,
+    UserMuted,
+    PeriodDaily,
+    PeriodMonthly,
+    UsageTitle,
+    UsageUpdated,
+    UsageThisChat,
+    UsageGlobal,
+    UsageAllTime,
+    UsageLast24h,
+    UsageLast7d,
+    UsageTopModels24h,
+    UsageTopModels7d,
+    UsageNoData,
+    UsageByUser7d,
+    UsageLinked,
+    UsageRouter,
+    UsageChartTitle,
+    UsageChartCaption,
+    UsageChartCaptionPriced,
+    UsageLabelThisChat,
+    UsageLabelGlobal,
+    UsageMemoryTitle,
+    LanguageUsage,
+    LanguageChat,
+    LanguageDefault,
+    LanguageAvailable,
+    LanguageFailed,
+    TaskFailureAlert,
+    ContextCleared,
+    NoSessionToArchive,
+    Archived,
+}
+
+impl Msg {
+    #[cfg(test)]
+    const ALL: &'static [Msg] = &[
+        Msg::NoVisibleReply,
+        Msg::OutputLimit,
+        Msg::Done,
+        Msg::Error,
+        Msg::DocumentTooLarge,
+        Msg::VoiceNotConfigured,
+        Msg::VoiceFailed,
+        Msg::Stopped,
+        Msg::CancelledList,
+        Msg::CancelledReply,
+        Msg::CancelledRunning,
+        Msg::CancelledPending,
+        Msg::ListSeparator,
+        Msg::ToolsFinished,
+        Msg::NothingToStop,
+        Msg::Stopping,
+        Msg::ApprovalRequired,
+        Msg::ApprovalInvalid,
+        Msg::ReadOnlyBlocked,
+        Msg::BudgetGlobalReached,
+        Msg::BudgetChatReached,
+        Msg::UserRateLimited,
+        Msg::UserMuted,
+        Msg::PeriodDaily,
+        Msg::PeriodMonthly,
+        Msg::UsageTitle,
+        Msg::UsageUpdated,
+        Msg::UsageThisChat,
+        Msg::UsageGlobal,
+        Msg::UsageAllTime,
+        Msg::UsageLast24h,
+        Msg::UsageLast7d,
+        Msg::UsageTopModels24h,
+        Msg::UsageTopModels7d,
+        Msg::UsageNoData,
+        Msg::UsageByUser7d,
+        Msg::UsageLinked,
+        Msg::UsageRouter,
+        Msg::UsageChartTitle,
+        Msg::UsageChartCaption,
+        Msg::UsageChartCaptionPriced,
+        Msg::UsageLabelThisChat,
+        Msg::UsageLabelGlobal,
+        Msg::UsageMemoryTitle,
+        Msg::LanguageUsage,
+        Msg::LanguageChat,
+        Msg::LanguageDefault,
+        Msg::LanguageAvailable,
+        Msg::LanguageFailed,
+        Msg::TaskFailureAlert,
+        Msg::ContextCleared,
+        Msg::NoSessionToArchive,
+        Msg::Archived,
+    ];
+
+    /// `[English, Chinese]`.
+    fn texts(self) -> [&'static str; 2] {
+        match self {
+            Msg::NoVisibleReply => [
+                "I couldn't produce a visible reply after an automatic retry. Please try again.",
+                "自动重试后仍未能生成可见的回复，请再试一次。",
+            ],
+            Msg::OutputLimit => [
+                "I reached the model output limit before producing a visible reply. Please ask me to continue.",
+                "还没写出可见的回复就达到了模型输出上限，请让我继续。",
+            ],
+            Msg::Done => ["Done.", "完成。"],
+            Msg::Error => ["Error: {error}", "出错了：{error}"],
+            Msg::DocumentTooLarge => [
+                "Document is too large ({bytes} bytes). Max allowed is {max_mb} MB.",
+                "文件过大（{bytes} 字节），最大允许 {max_mb} MB。",
+            ],
+            Msg::VoiceNotConfigured => [
+                "Voice messages not supported (no transcription backend configured)",
+                "暂不支持语音消息（未配置语音转写服务）",
+            ],
+            Msg::VoiceFailed => [
+                "Couldn't transcribe that voice message: {error}",
+                "无法转写这条语音消息：{error}",
+            ],
+            Msg::Stopped => ["⏹ Stopped.", "⏹ 已停止。"],
+            Msg::CancelledList => [" Cancelled {items}.", "已取消：{items}。"],
+            Msg::CancelledReply => ["the reply being generated", "正在生成的回复"],
+            Msg::CancelledRunning => ["running {tools}", "运行中的 {tools}"],
+            Msg::CancelledPending => ["pending {tools} (not started)", "尚未开始的 {tools}"],
+            Msg::ListSeparator => ["; ", "；"],
+            Msg::ToolsFinished => [
+                " {count} tool call(s) had already finished.",
+                "已有 {count} 个工具调用完成。",
+            ],
+            Msg::NothingToStop => ["Nothing to stop.", "没有正在运行的任务。"],
+            Msg::Stopping => ["Stopping the current run...", "正在停止当前任务……"],
+            Msg::ApprovalRequired => [
+                "Approval required for high-risk tool '{tool}' (risk: {risk}). Re-run the same tool with __microclaw_approval.token=\"{token}\" to confirm.",
+                "高风险工具 '{tool}'（风险：{risk}）需要确认。请带上 __microclaw_approval.token=\"{token}\" 重新调用同一工具以确认。",
+            ],
+            Msg::ApprovalInvalid => [
+                "Approval token invalid or expired for high-risk tool '{tool}' (risk: {risk}). Re-run with __microclaw_approval.token=\"{token}\".",
+                "高风险工具 '{tool}'（风险：{risk}）的确认令牌无效或已过期。请带上 __microclaw_approval.token=\"{token}\" 重新调用。",
+            ],
+            Msg::ReadOnlyBlocked => [
+                "Tool '{tool}' (risk: {risk}) is disabled because this chat is in read-only mode. Only low-risk tools such as reading and searching are available.",
+                "本聊天处于只读模式，工具 '{tool}'（风险：{risk}）已禁用。只能使用读取、搜索等低风险工具。",
+            ],
+            Msg::BudgetGlobalReached => [
+                "The bot has reached its global {period} budget, so I can't answer until it resets at {resets_at} ({tz}). An operator can lift the limit from a control chat with /budget override global.",
+                "机器人已用完全局{period}预算，在 {resets_at}（{tz}）重置前无法回复。管理员可在控制聊天中发送 /budget override global 解除限制。",
+            ],
+            Msg::BudgetChatReached => [
+                "This chat has reached its {period} budget ({spent}), so I can't answer until it resets at {resets_at} ({tz}). An operator can lift the limit from a control chat with /budget override {chat_id}.",
+                "本聊天已用完{period}预算（{spent}），在 {resets_at}（{tz}）重置前无法回复。管理员可在控制聊天中发送 /budget override {chat_id} 解除限制。",
+            ],
+            Msg::UserRateLimited => [
+                "You're sending messages faster than this bot allows, so I'll ignore your messages for the next {minutes} minute(s).",
+                "你发送消息的频率超过了限制，接下来 {minutes} 分钟内我会忽略你的消息。",
+            ],
+            Msg::UserMuted => [
+                "Too many of your recent requests made tools fail, so I'm pausing replies to you for {minutes} minute(s).",
+                "你最近的请求导致多次工具调用失败，接下来 {minutes} 分钟内我将暂停回复你。",
+            ],
+            Msg::PeriodDaily => ["daily", "每日"],
+            Msg::PeriodMonthly => ["monthly", "每月"],
+            Msg::UsageTitle => ["📊 Token Usage", "📊 Token 用量"],
+            Msg::UsageUpdated => ["🕒 Updated: {time}", "🕒 更新于：{time}"],
+            Msg::UsageThisChat => ["🔹 This chat", "🔹 本聊天"],
+            Msg::UsageGlobal => ["🌍 Global", "🌍 全局"],
+            Msg::UsageAllTime => ["All-time", "累计"],
+            Msg::UsageLast24h => ["Last 24h", "近24小时"],
+            Msg::UsageLast7d => ["Last 7d", "近7天"],
+            Msg::UsageTopModels24h => ["🤖 Top models (24h)", "🤖 常用模型（24小时）"],
+            Msg::UsageTopModels7d => ["🤖 Top models (7d)", "🤖 常用模型（7天）"],
+            Msg::UsageNoData => ["(no data)", "（暂无数据）"],
+            Msg::UsageByUser7d => ["👥 By user (7d)", "👥 按用户（7天）"],
+            Msg::UsageLinked => [
+                "👤 You ({count} linked chats)",
+                "👤 你（{count} 个已关联的聊天）",
+            ],
+            Msg::UsageRouter => [
+                "🧭 Model router (7d): {small} small, {large} large",
+                "🧭 模型路由（7天）：小模型 {small} 次，大模型 {large} 次",
+            ],
+            Msg::UsageChartTitle => [
+                "📈 Last {days} days (UTC, oldest → today)",
+                "📈 近 {days} 天（UTC，最早 → 今天）",
+            ],
+            Msg::UsageChartCaption => [
+                "📈 Tokens per day, last {days} days. Dark: this chat; light: all chats.",
+                "📈 近 {days} 天每日 token 用量。深色：本聊天；浅色：全部聊天。",
+            ],
+            Msg::UsageChartCaptionPriced => [
+                "📈 Tokens (top) and estimated cost (bottom) per day, last {days} days. Dark: this chat; light: all chats.",
+                "📈 近 {days} 天每日 token 用量（上）与估算费用（下）。深色：本聊天；浅色：全部聊天。",
+            ],
+            Msg::UsageLabelThisChat => ["This chat", "本聊天"],
+            Msg::UsageLabelGlobal => ["Global", "全局"],
+            Msg::UsageMemoryTitle => ["🧠 Memory Observability", "🧠 记忆观测"],
+            Msg::LanguageUsage => [
+                "Usage: /language — show this chat's language\n/language en|zh — set it for this chat\n/language default — go back to the configured language",
+                "用法：/language — 查看本聊天的语言\n/language en|zh — 为本聊天设置语言\n/language default — 恢复为配置的默认语言",
+            ],
+            Msg::LanguageChat => [
+                "This chat's language is {language}.",
+                "本聊天的语言为{language}。",
+            ],
+            Msg::LanguageDefault => [
+                "This chat uses the default language, {language}.",
+                "本聊天使用默认语言：{language}。",
+            ],
+            Msg::LanguageAvailable => ["Available: {languages}", "可选：{languages}"],
+            Msg::LanguageFailed => [
+                "Failed to update the language: {error}",
+                "更新语言失败：{error}",
+            ],
+            Msg::TaskFailureAlert => [
+                "⚠️ Scheduled task #{id} in chat {chat_id} has failed {count} times in a row.\nTask: {prompt}\nLast error: {error}\nPause it with pause_scheduled_task or check get_task_history.",
+                "⚠️ 聊天 {chat_id} 中的定时任务 #{id} 已连续失败 {count} 次。\n任务：{prompt}\n最近错误：{error}\n可用 pause_scheduled_task 暂停，或用 get_task_history 查看记录。",
+            ],
+            Msg::ContextCleared => [
+                "Context cleared (session + chat history).",
+                "上下文已清空（会话和聊天记录）。",
+            ],
+            Msg::NoSessionToArchive => ["No session to archive.", "没有可归档的会话。"],
+            Msg::Archived => ["Archived {count} messages.", "已归档 {count} 条消息。"],
+        }
+    }
+}
+
+/// `msg` in `lang`.
+pub fn t(lang: Language, msg: Msg) -> &'static str {
+    let [en, zh] = msg.texts();
+    match lang {
+        Language::En => en,
+        Language::Zh => zh,
+    }
+}
+
+/// `msg` in `lang` with its `{name}` placeholders filled from `args`.
+pub fn tf(lang: Language, msg: Msg, args: &[(&str, &(dyn std::fmt::Display + Sync))]) -> String {
+    let mut text = t(lang, msg).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    text
+}
+
+/// The configured default language.
+pub fn default_language(config: &Config) -> Language {
+    Language::parse(&config.language).unwrap_or_default()
+}
+
+/// The chat's language: its `/language`, else the configured default.
+pub async fn language_for_chat(db: Arc<Database>, config: &Config, chat_id: i64) -> Language {
+    call_blocking(db, move |db| {
+        db.get_chat_setting(chat_id, LANGUAGE_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten()
+    .and_then(|code| Language::parse(&code))
+    .unwrap_or_else(|| default_language(config))
+}
+
+/// [`language_for_chat`] from the app state.
+pub async fn chat_language(state: &AppState, chat_id: i64) -> Language {
+    language_for_chat(state.db.clone(), &state.config, chat_id).await
+}
+
+/// Handle `/language`. Returns `None` when the text is not this command.
+pub async fn handle_language_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let text = text.trim();
+    let arg = match text.split_once(char::is_whitespace) {
+        Some(("/language", rest)) => rest.trim().to_string(),
+        None if text == "/language" => String::new(),
+        _ => return None,
+    };
+    if !arg.is_empty() {
+        let stored = if arg.eq_ignore_ascii_case("default") {
+            None
+        } else {
+            match Language::parse(&arg) {
+                Some(lang) => Some(lang.code().to_string()),
+                None => {
+                    let lang = chat_language(state, chat_id).await;
+                    return Some(t(lang, Msg::LanguageUsage).to_string());
+                }
+            }
+        };
+        if let Err(e) = call_blocking(state.db.clone(), move |db| {
+            db.set_chat_setting(chat_id, LANGUAGE_SETTING_KEY, stored.as_deref())
+        })
+        .await
+        {
+            let lang = chat_language(state, chat_id).await;
+            return Some(tf(lang, Msg::LanguageFailed, &[("error", &e)]));
+        }
+    }
+    let chosen = call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, LANGUAGE_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten()
+    .and_then(|code| Language::parse(&code));
+    let lang = chosen.unwrap_or_else(|| default_language(&state.config));
+    let current = match chosen {
+        Some(_) => tf(lang, Msg::LanguageChat, &[("language", &lang.name())]),
+        None => tf(lang, Msg::LanguageDefault, &[("language", &lang.name())]),
+    };
+    let available = Language::ALL
+        .iter()
+        .map(|l| format!("{} ({})", l.code(), l.name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!(
+        "{current}\n{}",
+        tf(lang, Msg::LanguageAvailable, &[("languages", &available)])
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_translations_are_complete() {
+        for msg in Msg::ALL {
+            let [en, zh] = msg.texts();
+            assert!(!en.is_empty() && !zh.is_empty(), "{msg:?}");
+            assert_eq!(placeholders(en), placeholders(zh), "{msg:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_and_fill() {
+        assert_eq!(Language::parse("zh-CN"), Some(Language::Zh));
+        assert_eq!(Language::parse("en_US"), Some(Language::En));
+        assert_eq!(Language::parse("中文"), Some(Language::Zh));
+        assert_eq!(Language::parse("fr"), None);
+        assert_eq!(
+            tf(Language::Zh, Msg::Error, &[("error", &"timeout")]),
+            "出错了：timeout"
+        );
+        assert_eq!(
+            tf(
+                Language::En,
+                Msg::DocumentTooLarge,
+                &[("bytes", &2048), ("max_mb", &1)]
+            ),
+            "Document is too large (2048 bytes). Max allowed is 1 MB."
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Cross-channel user identity.
+//!
+//! A person can join their private chats on different channels (Telegram DM,
+//! Discord DM, a web session, ...) into one identity: `/link` in one chat
+//! prints a short-lived code, `/link <code>` in another chat joins it. The
+//! identity is keyed by its home chat (where the first code was issued).
+//! Linked chats read and write the home chat's memory, preferences and todos,
+//! and `/usage` adds a total across all linked chats. History, sessions and
+//! working directories stay per chat.
+//!
+//! Codes are guessable only by brute force, so each sender gets a few wrong
+//! codes per window, and outstanding codes are dropped after a few wrong
+//! guesses from anyone.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::channel::{get_chat_routing, ConversationKind};
+use crate::db::{call_blocking, Database, LinkedChat};
+use crate::runtime::AppState;
+
+const LINK_CODE_TTL_MINUTES: i64 = 10;
+/// No 0/O or 1/I, so codes survive being read aloud or retyped.
+const LINK_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const LINK_CODE_LEN: usize = 10;
+/// Wrong codes one sender may try per [`LINK_ATTEMPT_WINDOW`].
+const LINK_MAX_FAILED_ATTEMPTS: usize = 5;
+const LINK_ATTEMPT_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Wrong guesses, from any chat, after which an outstanding code is dropped.
+const LINK_CODE_MAX_FAILURES: i64 = 5;
+
+const LINK_USAGE: &str = "Usage:
+/link — show linked chats and get a code to link another chat
+/link <code> — link this chat to the chats that issued <code>
+/unlink — remove this chat from its linked identity";
+
+/// Home chat whose memory, preferences and todos `chat_id` uses.
+pub async fn identity_chat_id(db: Arc<Database>, chat_id: i64) -> i64 {
+    call_blocking(db, move |db| db.get_identity_chat_id(chat_id))
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(chat_id)
+}
+
+fn generate_link_code() -> String {
+    uuid::Uuid::new_v4()
+        .as_bytes()
+        .iter()
+        .take(LINK_CODE_LEN)
+        .map(|b| LINK_CODE_ALPHABET[*b as usize % LINK_CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Show a code in two dash-separated halves; the dash is ignored on input.
+fn format_link_code(code: &str) -> String {
+    let (head, tail) = code.split_at(code.len() / 2);
+    format!("{head}-{tail}")
+}
+
+fn normalize_link_code(raw: &str) -> String {
+    raw.trim()
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+fn format_linked_chats(chats: &[LinkedChat], current: i64) -> String {
+    chats
+        .iter()
+        .map(|c| {
+            let channel = c.channel.as_deref().unwrap_or("unknown");
+            let title = c.chat_title.as_deref().unwrap_or("untitled");
+            let marker = if c.chat_id == current {
+                " (this chat)"
+            } else {
+                ""
+            };
+            format!("- {channel}: {title} [{}]{marker}", c.chat_id)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn failed_attempts() -> std::sync::MutexGuard<'static, HashMap<String, VecDeque<Instant>>> {
+    static FAILED: OnceLock<Mutex<HashMap<String, VecDeque<Instant>>>> = OnceLock::new();
+    FAILED
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Recent wrong codes from `sender`, pruning those outside the window.
+fn recent_failures(sender: &str, now: Instant) -> usize {
+    let mut failed = failed_attempts();
+    let Some(times) = failed.get_mut(sender) else {
+        return 0;
+    };
+    while times
+        .front()
+        .is_some_and(|t| now.duration_since(*t) >= LINK_ATTEMPT_WINDOW)
+    {
+        times.pop_front();
+    }
+    if times.is_empty() {
+        failed.remove(sender);
+        return 0;
+    }
+    times.len()
+}
+
+fn record_failure(sender: &str, now: Instant) {
+    failed_attempts()
+        .entry(sender.to_string())
+        .or_default()
+        .push_back(now);
+}
+
+async fn is_group_chat(state: &AppState, chat_id: i64) -> bool {
+    matches!(
+        get_chat_routing(&state.channel_registry, state.db.clone(), chat_id).await,
+        Ok(Some(routing)) if routing.conversation == ConversationKind::Group
+    )
+}
+
+/// Handle `/link` and `/unlink`. Returns `None` when the text is not one of
+/// these commands. Only private chats can be linked. Wrong codes count
+/// against the sender's platform user id, or the chat when there is none.
+pub async fn handle_link_command(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    sender_id: Option<&str>,
+    text: &str,
+) -> Option<String> {
+    let text = text.trim();
+    let (command, rest) = match text.split_once(char::is_whitespace) {
+        Some((command, rest)) => (command, rest.trim()),
+        None => (text, ""),
+    };
+    if command != "/link" && command != "/unlink" {
+        return None;
+    }
+    if is_group_chat(state, chat_id).await {
+        return Some("Identity linking only works in private chats.".into());
+    }
+
+    let db = state.db.clone();
+    let reply = match (command, rest) {
+        ("/unlink", "") => call_blocking(db, move |db| db.unlink_chat_identity(chat_id))
+            .await
+            .map(|n| {
+                if n > 0 {
+                    "Unlinked. This chat now keeps its own memory, preferences and todos."
+                        .to_string()
+                } else {
+                    "This chat is not linked to any other chat.".to_string()
+                }
+            }),
+        ("/link", "") => {
+            let identity = identity_chat_id(db.clone(), chat_id).await;
+            let code = generate_link_code();
+            let expires_at = (chrono::Utc::now()
+                + chrono::Duration::minutes(LINK_CODE_TTL_MINUTES))
+            .to_rfc3339();
+            let code_for_db = code.clone();
+            call_blocking(db, move |db| {
+                db.create_identity_link_code(identity, &code_for_db, &expires_at)?;
+                db.get_identity_chats(identity)
+            })
+            .await
+            .map(|chats| {
+                let code = format_link_code(&code);
+                let mut reply = format!(
+                    "Link code: {code}\nSend \"/link {code}\" from your other chat (Telegram, Discord, web, ...) within {LINK_CODE_TTL_MINUTES} minutes. Both chats will then share memory, preferences and todos."
+                );
+                if chats.len() > 1 {
+                    reply.push_str("\n\nLinked chats:\n");
+                    reply.push_str(&format_linked_chats(&chats, chat_id));
+                }
+                reply
+            })
+        }
+        ("/link", code) => {
+            let sender = match sender_id.map(str::trim).filter(|id| !id.is_empty()) {
+                Some(id) => format!("{caller_channel}:{id}"),
+                None => format!("chat:{chat_id}"),
+            };
+            if recent_failures(&sender, Instant::now()) >= LINK_MAX_FAILED_ATTEMPTS {
+                return Some(
+                    "Too many wrong link codes. Wait a few minutes and run /link in your other chat for a new code."
+                        .into(),
+                );
+            }
+            let code = normalize_link_code(code);
+            let now = chrono::Utc::now().to_rfc3339();
+            let current = identity_chat_id(db.clone(), chat_id).await;
+            let taken = call_blocking(db.clone(), move |db| {
+                db.take_identity_link_code(&code, &now, LINK_CODE_MAX_FAILURES)
+            })
+            .await;
+            let identity = match taken {
+                Ok(Some(identity)) => identity,
+                Ok(None) => {
+                    record_failure(&sender, Instant::now());
+                    return Some("That code is invalid or has expired. Run /link in your other chat for a new one.".into());
+                }
+                Err(e) => return Some(format!("Failed to update linked chats: {e}")),
+            };
+            call_blocking(db, move |db| {
+                if identity == current {
+                    return Ok("This chat is already linked.".to_string());
+                }
+                db.link_chat_identity(current, identity)?;
+                let chats = db.get_identity_chats(identity)?;
+                Ok(format!(
+                    "Linked. This chat now uses the memory, preferences and todos of your other chats; what was stored only here stays in this chat.\n\nLinked chats:\n{}",
+                    format_linked_chats(&chats, chat_id)
+                ))
+            })
+            .await
+        }
+        _ => return Some(LINK_USAGE.to_string()),
+    };
+    Some(reply.unwrap_or_else(|e| format!("Failed to update linked chats: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_codes() {
+        let code = generate_link_code();
+        assert_eq!(code.len(), LINK_CODE_LEN);
+        assert!(code.bytes().all(|b| LINK_CODE_ALPHABET.contains(&b)));
+        assert_eq!(normalize_link_code(" abc-123 "), "ABC123");
+        assert_eq!(normalize_link_code(&format_link_code(&code)), code);
+    }
+
+    #[test]
+    fn test_failed_link_attempts_expire() {
+        let sender = format!("test:{}", uuid::Uuid::new_v4());
+        let start = Instant::now();
+        for _ in 0..LINK_MAX_FAILED_ATTEMPTS {
+            record_failure(&sender, start);
+        }
+        assert_eq!(recent_failures(&sender, start), LINK_MAX_FAILED_ATTEMPTS);
+        assert_eq!(recent_failures(&sender, start + LINK_ATTEMPT_WINDOW), 0);
+    }
+
+    #[test]
+    fn test_format_linked_chats_marks_current() {
+        let chats = vec![
+            LinkedChat {
+                chat_id: 1,
+                channel: Some("telegram".into()),
+                chat_title: Some("alice".into()),
+            },
+            LinkedChat {
+                chat_id: 2,
+                channel: Some("web".into()),
+                chat_title: None,
+            },
+        ];
+        let text = format_linked_chats(&chats, 2);
+        assert!(text.contains("- telegram: alice [1]\n"));
+        assert!(text.ends_with("- web: untitled [2] (this chat)"));
+    }
+}
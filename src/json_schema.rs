@@ -0,0 +1,142 @@
+//! JSON Schema validation for tool inputs and structured replies.
+//!
+//! A thin wrapper over the `jsonschema` crate, so every keyword of the
+//! schema's draft (2020-12 unless `$schema` says otherwise) is enforced. A
+//! schema that does not compile is reported as a violation at the root
+//! rather than being skipped.
+
+use serde_json::Value;
+
+/// First place where a value does not match its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value (`""` for the root).
+    pub pointer: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{pointer}: {}", self.message)
+    }
+}
+
+/// Check `value` against `schema`.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), SchemaViolation> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| SchemaViolation {
+        pointer: String::new(),
+        message: format!("invalid schema: {e}"),
+    })?;
+    validator.validate(value).map_err(|e| SchemaViolation {
+        pointer: e.instance_path.to_string(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": "string", "minLength": 1},
+                "priority": {"type": "integer", "minimum": 1, "maximum": 5},
+                "kind": {"enum": ["once", "cron"]},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2}
+            },
+            "required": ["title", "kind"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_validate_accepts_matching_value() {
+        let value = json!({"title": "backup", "priority": 2, "kind": "cron", "tags": ["ops"]});
+        assert_eq!(validate(&task_schema(), &value), Ok(()));
+        assert_eq!(validate(&json!({}), &json!([1, "x"])), Ok(()));
+        assert_eq!(
+            validate(&json!({"type": ["string", "null"]}), &Value::Null),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_pointer_of_first_violation() {
+        let schema = task_schema();
+        let cases = [
+            (json!("x"), "/: \"x\" is not of type \"object\""),
+            (
+                json!({"kind": "once"}),
+                "/: \"title\" is a required property",
+            ),
+            (
+                json!({"title": "a", "kind": "weekly"}),
+                "/kind: \"weekly\" is not one of [\"once\",\"cron\"]",
+            ),
+            (
+                json!({"title": "a", "kind": "once", "priority": 9}),
+                "/priority: 9 is greater than the maximum of 5",
+            ),
+            (
+                json!({"title": "a", "kind": "once", "priority": 1.5}),
+                "/priority: 1.5 is not of type \"integer\"",
+            ),
+            (
+                json!({"title": "a", "kind": "once", "tags": ["x", 3]}),
+                "/tags/1: 3 is not of type \"string\"",
+            ),
+            (
+                json!({"title": "a", "kind": "once", "extra": true}),
+                "/: Additional properties are not allowed ('extra' was unexpected)",
+            ),
+        ];
+        for (value, expected) in cases {
+            let err = validate(&schema, &value).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_validate_any_of_and_one_of() {
+        let schema = json!({"anyOf": [{"type": "string"}, {"type": "integer"}]});
+        assert!(validate(&schema, &json!(3)).is_ok());
+        assert!(validate(&schema, &json!(true)).is_err());
+
+        let schema = json!({"oneOf": [{"type": "number"}, {"type": "integer"}]});
+        assert!(validate(&schema, &json!(1.5)).is_ok());
+        assert!(validate(&schema, &json!(2)).is_err());
+    }
+
+    #[test]
+    fn test_validate_enforces_every_keyword() {
+        let schema = json!({
+            "$defs": {"id": {"type": "string", "pattern": "^[a-z]+-[0-9]+$"}},
+            "type": "object",
+            "properties": {
+                "id": {"$ref": "#/$defs/id"},
+                "ids": {"type": "array", "items": {"$ref": "#/$defs/id"}, "uniqueItems": true}
+            },
+            "allOf": [{"required": ["id"]}]
+        });
+        assert!(validate(&schema, &json!({"id": "job-1", "ids": ["a-1", "b-2"]})).is_ok());
+        assert_eq!(
+            validate(&schema, &json!({"id": "JOB"}))
+                .unwrap_err()
+                .pointer,
+            "/id"
+        );
+        assert!(validate(&schema, &json!({"id": "a-1", "ids": ["a-1", "a-1"]})).is_err());
+        assert!(validate(&schema, &json!({})).is_err());
+
+        let err = validate(&json!({"type": "no-such-type"}), &json!(1)).unwrap_err();
+        assert!(err.message.starts_with("invalid schema"), "{err}");
+    }
+}
@@ -0,0 +1,479 @@
+//! Document ingestion and retrieval (`knowledge:` in the config).
+//!
+//! Files under the knowledge folder (default `<data_dir>/knowledge`) are
+//! split into overlapping chunks and stored in SQLite, each with an embedding
+//! vector when an embedding provider is configured. A background pass every
+//! `scan_interval_secs` indexes new and changed files and drops deleted ones;
+//! the `ingest` tool adds a file or text right away. The `retrieve` tool
+//! ranks chunks by cosine similarity to the query, or by keyword overlap when
+//! there are no vectors, and returns the top ones into the conversation.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::db::{call_blocking, Database, KnowledgeChunk};
+use crate::embedding::EmbeddingProvider;
+use crate::error::MicroClawError;
+use crate::text::floor_char_boundary;
+
+/// Larger files are skipped.
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Extensions read as plain text; `.html`/`.htm` are converted to text.
+const TEXT_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "txt", "text", "rst", "org", "adoc", "csv", "tsv", "json", "yaml", "yml",
+    "toml", "ini", "log", "xml", "html", "htm", "rs", "py", "js", "ts", "go", "java", "c", "h",
+    "cpp", "hpp", "rb", "sh", "sql",
+];
+
+/// One retrieved chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub source: String,
+    pub chunk_index: usize,
+    pub content: String,
+    pub score: f32,
+}
+
+/// Result of one folder scan.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+pub struct KnowledgeBase {
+    dir: PathBuf,
+    db: Arc<Database>,
+    embedding: Option<Arc<dyn EmbeddingProvider>>,
+    chunk_chars: usize,
+    chunk_overlap: usize,
+    top_k: usize,
+}
+
+impl KnowledgeBase {
+    /// `None` unless `knowledge.enabled`.
+    pub fn from_config(config: &Config, db: Arc<Database>) -> Option<Arc<Self>> {
+        if !config.knowledge.enabled {
+            return None;
+        }
+        Some(Arc::new(KnowledgeBase {
+            dir: config.knowledge_dir(),
+            db,
+            embedding: crate::embedding::build_provider(config),
+            chunk_chars: config.knowledge.chunk_chars,
+            chunk_overlap: config.knowledge.chunk_overlap,
+            top_k: config.knowledge.top_k,
+        }))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn default_top_k(&self) -> usize {
+        self.top_k
+    }
+
+    fn embedding_model(&self) -> Option<String> {
+        self.embedding.as_ref().map(|e| e.model().to_string())
+    }
+
+    fn source_name(&self, path: &Path) -> String {
+        path.strip_prefix(&self.dir)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Chunk, embed and store one file under the folder. Returns the chunk
+    /// count, or `None` when the file is unchanged since it was indexed.
+    pub async fn index_file(&self, path: &Path) -> Result<Option<usize>, MicroClawError> {
+        let source = self.source_name(path);
+        let bytes = tokio::fs::read(path).await?;
+        let text = extract_text(path, &bytes).ok_or_else(|| {
+            MicroClawError::ToolExecution(format!("{source} is not a supported text document"))
+        })?;
+        let hash = hex_digest(&bytes);
+        let model = self.embedding_model();
+
+        let lookup = source.clone();
+        let existing = call_blocking(self.db.clone(), move |db| {
+            Ok(db
+                .list_knowledge_documents()?
+                .into_iter()
+                .find(|d| d.source == lookup))
+        })
+        .await?;
+        if existing.is_some_and(|d| d.content_hash == hash && d.embedding_model == model) {
+            return Ok(None);
+        }
+
+        let mut chunks = Vec::new();
+        for chunk in chunk_text(&text, self.chunk_chars, self.chunk_overlap) {
+            let vector = match &self.embedding {
+                Some(provider) => Some(provider.embed(&chunk).await.map_err(|e| {
+                    MicroClawError::ToolExecution(format!("embedding {source} failed: {e}"))
+                })?),
+                None => None,
+            };
+            chunks.push((chunk, vector));
+        }
+        let count = chunks.len();
+        call_blocking(self.db.clone(), move |db| {
+            db.replace_knowledge_document(&source, &hash, model.as_deref(), &chunks)
+        })
+        .await?;
+        Ok(Some(count))
+    }
+
+    /// Index new and changed files and forget deleted ones.
+    pub async fn sync(&self) -> Result<SyncReport, MicroClawError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let dir = self.dir.clone();
+        let files = tokio::task::spawn_blocking(move || list_files(&dir))
+            .await
+            .map_err(|e| MicroClawError::ToolExecution(format!("knowledge scan failed: {e}")))?;
+
+        let mut report = SyncReport::default();
+        let mut seen = HashSet::new();
+        for path in files {
+            seen.insert(self.source_name(&path));
+            match self.index_file(&path).await {
+                Ok(Some(count)) => {
+                    info!(
+                        "Knowledge: indexed {} ({count} chunks)",
+                        self.source_name(&path)
+                    );
+                    report.indexed += 1;
+                }
+                Ok(None) => report.unchanged += 1,
+                Err(e) => {
+                    warn!("Knowledge: {e}");
+                    report.failed += 1;
+                }
+            }
+        }
+
+        let stale: Vec<String> = call_blocking(self.db.clone(), |db| db.list_knowledge_documents())
+            .await?
+            .into_iter()
+            .map(|d| d.source)
+            .filter(|source| !seen.contains(source))
+            .collect();
+        for source in stale {
+            info!("Knowledge: removed {source}");
+            call_blocking(self.db.clone(), move |db| {
+                db.delete_knowledge_document(&source)
+            })
+            .await?;
+            report.removed += 1;
+        }
+        Ok(report)
+    }
+
+    /// The `k` chunks most relevant to `query`.
+    pub async fn search(&self, query: &str, k: usize) -> Result<Vec<Hit>, MicroClawError> {
+        let chunks = call_blocking(self.db.clone(), |db| db.knowledge_chunks()).await?;
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query_vector = match &self.embedding {
+            Some(provider) if chunks.iter().any(|c| c.embedding.is_some()) => Some(
+                provider
+                    .embed(query)
+                    .await
+                    .map_err(|e| MicroClawError::ToolExecution(format!("embedding failed: {e}")))?,
+            ),
+            _ => None,
+        };
+        Ok(rank(&chunks, query, query_vector.as_deref(), k))
+    }
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| TEXT_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Text of a supported document, or `None` for binary or unknown files.
+fn extract_text(path: &Path, bytes: &[u8]) -> Option<String> {
+    if !is_supported(path) || bytes.contains(&0) {
+        return None;
+    }
+    let text = String::from_utf8_lossy(bytes);
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(if ext == "html" || ext == "htm" {
+        crate::tools::web_html::html_to_text(&text)
+    } else {
+        text.into_owned()
+    })
+}
+
+/// Supported files under `dir`, skipping hidden entries and large files.
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(path);
+            } else if meta.is_file() && meta.len() <= MAX_FILE_BYTES && is_supported(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Split `text` into chunks of about `size` bytes, breaking at paragraph,
+/// line, sentence or word boundaries where possible. Consecutive chunks share
+/// about `overlap` bytes.
+pub fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let text = text.trim();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = floor_char_boundary(text, start + size);
+        if end < text.len() {
+            let window = &text[start..end];
+            let min = window.len() / 2;
+            if let Some(cut) = ["\n\n", "\n", ". ", " "].iter().find_map(|sep| {
+                window
+                    .rfind(sep)
+                    .filter(|i| *i > min)
+                    .map(|i| i + sep.len())
+            }) {
+                end = start + cut;
+            }
+        }
+        let chunk = text[start..end].trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+        if end >= text.len() {
+            break;
+        }
+        // Back up by `overlap`, then forward to the next word.
+        let mut next = floor_char_boundary(text, end.saturating_sub(overlap).max(start + 1));
+        if let Some(space) = text[next..end].find(char::is_whitespace) {
+            next += space;
+        }
+        start = if next > start { next } else { end };
+        while start < text.len() && !text.is_char_boundary(start) {
+            start += 1;
+        }
+    }
+    chunks
+}
+
+fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    (denom > 0.0).then(|| dot / denom)
+}
+
+/// Score chunks by cosine similarity when the query has a vector, else by
+/// TF-IDF keyword overlap. Returns the best `k` with a positive score.
+fn rank(
+    chunks: &[KnowledgeChunk],
+    query: &str,
+    query_vector: Option<&[f32]>,
+    k: usize,
+) -> Vec<Hit> {
+    let scores: Vec<f32> = match query_vector {
+        Some(q) => chunks
+            .iter()
+            .map(|c| {
+                c.embedding
+                    .as_deref()
+                    .and_then(|v| cosine(q, v))
+                    .unwrap_or(0.0)
+            })
+            .collect(),
+        None => {
+            let query_terms: HashSet<String> = terms(query).into_iter().collect();
+            let chunk_terms: Vec<HashMap<String, usize>> = chunks
+                .iter()
+                .map(|c| {
+                    let mut counts = HashMap::new();
+                    for term in terms(&c.content) {
+                        if query_terms.contains(&term) {
+                            *counts.entry(term).or_insert(0) += 1;
+                        }
+                    }
+                    counts
+                })
+                .collect();
+            let n = chunks.len() as f32;
+            chunk_terms
+                .iter()
+                .map(|counts| {
+                    counts
+                        .iter()
+                        .map(|(term, tf)| {
+                            let df = chunk_terms.iter().filter(|c| c.contains_key(term)).count();
+                            (1.0 + *tf as f32).ln() * (1.0 + n / df as f32).ln()
+                        })
+                        .sum()
+                })
+                .collect()
+        }
+    };
+    let mut hits: Vec<Hit> = chunks
+        .iter()
+        .zip(scores)
+        .filter(|(_, score)| *score > 0.0)
+        .map(|(c, score)| Hit {
+            source: c.source.clone(),
+            chunk_index: c.chunk_index,
+            content: c.content.clone(),
+            score,
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(k);
+    hits
+}
+
+pub fn spawn_indexer(knowledge: Arc<KnowledgeBase>, interval: Duration) {
+    tokio::spawn(async move {
+        info!("Knowledge indexer watching {}", knowledge.dir().display());
+        loop {
+            match knowledge.sync().await {
+                Ok(report) if report.indexed + report.removed + report.failed > 0 => info!(
+                    "Knowledge: {} indexed, {} removed, {} failed",
+                    report.indexed, report.removed, report.failed
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Knowledge scan failed: {e}"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(source: &str, content: &str, embedding: Option<Vec<f32>>) -> KnowledgeChunk {
+        KnowledgeChunk {
+            source: source.into(),
+            chunk_index: 0,
+            content: content.into(),
+            embedding,
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_breaks_at_boundaries_with_overlap() {
+        let text = "First paragraph about cats.\n\nSecond paragraph about dogs and more dogs. \
+                    Third sentence here.\n\nFinal words.";
+        let chunks = chunk_text(text, 60, 15);
+        assert_eq!(
+            chunks,
+            vec![
+                "First paragraph about cats.\n\nSecond paragraph about dogs",
+                // Overlaps the previous chunk and ends at a sentence.
+                "about dogs and more dogs. Third sentence here.",
+                "here.\n\nFinal words.",
+            ]
+        );
+
+        assert!(chunk_text("   ", 100, 10).is_empty());
+        let unicode = "é".repeat(300);
+        let chunks = chunk_text(&unicode, 101, 20);
+        assert!(chunks.len() > 1);
+    }
+
+    #[test]
+    fn test_rank_by_keywords_and_vectors() {
+        let chunks = vec![
+            chunk(
+                "a.md",
+                "The deploy script pushes to production.",
+                Some(vec![1.0, 0.0]),
+            ),
+            chunk("b.md", "Lunch menu: soup and bread.", Some(vec![0.0, 1.0])),
+            chunk("c.md", "Deploy deploy deploy checklist.", None),
+        ];
+        let hits = rank(&chunks, "deploy checklist", None, 5);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].source, "c.md");
+        assert_eq!(hits[1].source, "a.md");
+
+        let hits = rank(&chunks, "ignored", Some(&[0.1, 0.9]), 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].source, "b.md");
+    }
+
+    #[tokio::test]
+    async fn test_sync_indexes_and_forgets_files() {
+        let root = std::env::temp_dir().join(format!("microclaw_kb_{}", uuid::Uuid::new_v4()));
+        let dir = root.join("knowledge");
+        std::fs::create_dir_all(dir.join("guides")).unwrap();
+        std::fs::write(dir.join("guides/deploy.md"), "Run make deploy to ship.").unwrap();
+        std::fs::write(dir.join("image.png"), [0u8, 1, 2]).unwrap();
+        let db = Arc::new(Database::new(root.to_str().unwrap()).unwrap());
+        let kb = KnowledgeBase {
+            dir: dir.clone(),
+            db: db.clone(),
+            embedding: None,
+            chunk_chars: 500,
+            chunk_overlap: 50,
+            top_k: 3,
+        };
+
+        let report = kb.sync().await.unwrap();
+        assert_eq!(report.indexed, 1);
+        assert_eq!(kb.sync().await.unwrap().unchanged, 1);
+        let hits = kb.search("deploy", 3).await.unwrap();
+        assert_eq!(hits[0].source, "guides/deploy.md");
+
+        std::fs::remove_file(dir.join("guides/deploy.md")).unwrap();
+        assert_eq!(kb.sync().await.unwrap().removed, 1);
+        assert!(kb.search("deploy", 3).await.unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
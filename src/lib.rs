@@ -1,29 +1,79 @@
 pub mod agent_engine;
+pub mod azure;
+pub mod backup;
+pub mod bedrock;
+pub mod branches;
+pub mod budget;
 pub mod builtin_skills;
+pub mod calendar;
 pub mod channel;
 pub mod channel_adapter;
 pub mod channels;
+pub mod chat;
+pub mod chat_prompt;
+pub mod claude_auth;
+pub mod clawhooks;
 pub mod codex_auth;
+pub mod compare;
 pub mod config;
+pub mod config_crypt;
 pub mod db;
+pub mod db_crypt;
 pub mod doctor;
 pub mod embedding;
 pub mod error;
+pub mod feeds;
+pub mod file_preview;
+pub mod file_watch;
 pub mod gateway;
+pub mod gemini;
+pub mod health;
+pub mod heartbeat;
+pub mod i18n;
+pub mod identity;
+pub mod inline_mode;
+pub mod json_schema;
+pub mod knowledge;
 pub mod llm;
 pub mod llm_types;
 pub mod logging;
 pub mod mcp;
 pub mod memory;
+pub mod memory_consolidation;
 pub mod memory_quality;
+pub mod metrics;
+pub mod model_caps;
+pub mod network_policy;
+pub mod otel;
+pub mod persona;
+pub mod preferences;
+pub mod pricing;
+pub mod provider_health;
+pub mod provider_oauth;
+pub mod reactions;
+pub mod read_only;
+pub mod retention;
+pub mod router;
+pub mod run_control;
 pub mod runtime;
 pub mod scheduler;
+pub mod secrets;
 pub mod setup;
 pub mod skills;
+pub mod streaming;
+pub mod structured;
 pub(crate) mod text;
+pub mod thinking;
+pub mod tokens;
+pub mod tool_runner;
 pub mod tools;
 pub mod transcribe;
+pub mod turn_queue;
 pub mod usage;
+pub mod user_limits;
 pub mod web;
+pub mod wire_log;
+pub mod workspace;
+pub mod workspace_report;
 pub use channels::discord;
 pub use channels::telegram;
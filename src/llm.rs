@@ -6,6 +6,7 @@ use tokio::sync::mpsc::UnboundedSender;
 use tracing::warn;
 
 use std::collections::HashSet;
+use std::time::Duration;
 
 use crate::codex_auth::{
     codex_config_default_openai_base_url, is_openai_codex_provider,
@@ -17,7 +18,7 @@ use crate::config::WorkingDirIsolation;
 use crate::error::MicroClawError;
 use crate::llm_types::{
     ContentBlock, ImageSource, Message, MessageContent, MessagesRequest, MessagesResponse,
-    ResponseContentBlock, ToolDefinition, Usage,
+    ResponseContentBlock, ResponseSchema, ServedBy, ToolDefinition, Usage,
 };
 
 /// Convert a `MessageContent` into a `Vec<ContentBlock>`, wrapping plain text
@@ -43,7 +44,7 @@ fn merge_message_content(prev: &mut Message, other: Message) {
 /// Remove orphaned `ToolResult` blocks whose `tool_use_id` does not match any
 /// `ToolUse` block in the conversation.  This can happen after session
 /// compaction splits a tool_use / tool_result pair.
-fn sanitize_messages(messages: Vec<Message>) -> Vec<Message> {
+pub(crate) fn sanitize_messages(messages: Vec<Message>) -> Vec<Message> {
     // Collect all tool_use IDs from assistant messages (owned to avoid borrow conflicts).
     let known_ids: HashSet<String> = messages
         .iter()
@@ -112,14 +113,32 @@ fn sanitize_messages(messages: Vec<Message>) -> Vec<Message> {
     merged
 }
 
+/// Drop thinking blocks kept from turns that ran with extended thinking, for
+/// requests without it.
+fn strip_thinking_blocks(messages: &mut [Message]) {
+    for msg in messages.iter_mut() {
+        if let MessageContent::Blocks(blocks) = &mut msg.content {
+            blocks.retain(|b| {
+                !matches!(
+                    b,
+                    ContentBlock::Thinking { .. } | ContentBlock::RedactedThinking { .. }
+                )
+            });
+            if blocks.is_empty() {
+                msg.content = MessageContent::Text("(thinking)".into());
+            }
+        }
+    }
+}
+
 #[derive(Default)]
-struct SseEventParser {
+pub(crate) struct SseEventParser {
     pending: String,
     data_lines: Vec<String>,
 }
 
 impl SseEventParser {
-    fn push_chunk(&mut self, chunk: &str) -> Vec<String> {
+    pub(crate) fn push_chunk(&mut self, chunk: &str) -> Vec<String> {
         self.pending.push_str(chunk);
         let mut events = Vec::new();
 
@@ -137,7 +156,7 @@ impl SseEventParser {
         events
     }
 
-    fn finish(&mut self) -> Vec<String> {
+    pub(crate) fn finish(&mut self) -> Vec<String> {
         let mut events = Vec::new();
         if !self.pending.is_empty() {
             let mut line = std::mem::take(&mut self.pending);
@@ -216,12 +235,203 @@ pub trait LlmProvider: Send + Sync {
         }
         Ok(response)
     }
+
+    /// Ask for a reply that is a JSON object matching `schema`, using the
+    /// provider's native JSON schema mode when the model has one. The default
+    /// sends a plain request; `crate::structured` puts the schema into the
+    /// prompt and validates the reply either way.
+    async fn send_message_json(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        _schema: &ResponseSchema,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.send_message(system, messages, None).await
+    }
+
+    /// Embedding vectors for `inputs`, in the same order, from the
+    /// provider's embedding model. Providers without an embeddings API
+    /// return an error.
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, MicroClawError> {
+        let _ = inputs;
+        Err(MicroClawError::LlmApi(
+            "This LLM provider does not offer embeddings".into(),
+        ))
+    }
 }
 
 pub fn create_provider(config: &Config) -> Box<dyn LlmProvider> {
-    match config.llm_provider.trim().to_lowercase().as_str() {
-        "anthropic" => Box::new(AnthropicProvider::new(config)),
+    let primary = create_single_provider(config);
+    if config.llm_fallbacks.is_empty() {
+        return primary;
+    }
+    let mut chain = vec![(
+        ServedBy {
+            provider: config.llm_provider.clone(),
+            model: config.model.clone(),
+        },
+        primary,
+    )];
+    for fallback in &config.llm_fallbacks {
+        let fallback_config = config.with_fallback(fallback);
+        chain.push((
+            ServedBy {
+                provider: fallback_config.llm_provider.clone(),
+                model: fallback_config.model.clone(),
+            },
+            create_single_provider(&fallback_config),
+        ));
+    }
+    Box::new(FallbackProvider {
+        chain,
+        timeout: (config.llm_fallback_timeout_secs > 0)
+            .then(|| Duration::from_secs(config.llm_fallback_timeout_secs)),
+    })
+}
+
+fn create_single_provider(config: &Config) -> Box<dyn LlmProvider> {
+    let provider: Box<dyn LlmProvider> = match config.llm_provider.trim().to_lowercase().as_str() {
+        "anthropic" | "claude-oauth" => Box::new(AnthropicProvider::new(config)),
+        "gemini" => Box::new(crate::gemini::GeminiProvider::new(config)),
+        "bedrock" if crate::bedrock::uses_native_api(config) => {
+            Box::new(crate::bedrock::BedrockProvider::new(config))
+        }
         _ => Box::new(OpenAiProvider::new(config)),
+    };
+    let provider = Box::new(crate::provider_health::RetryingProvider::new(
+        provider, config,
+    ));
+    crate::model_caps::adapt_provider(provider, config)
+}
+
+// ---------------------------------------------------------------------------
+// Fallback chain
+// ---------------------------------------------------------------------------
+
+/// Tries each provider in order until one answers. Only transient failures
+/// (rate limits, 5xx, timeouts) move on to the next entry; other errors are
+/// returned as is. Entries that keep failing (see `provider_health`) are
+/// skipped for a while unless they are the last one. Responses from a
+/// fallback carry `served_by`.
+struct FallbackProvider {
+    chain: Vec<(ServedBy, Box<dyn LlmProvider>)>,
+    timeout: Option<Duration>,
+}
+
+impl FallbackProvider {
+    async fn run<'a, F>(&'a self, call: F) -> Result<MessagesResponse, MicroClawError>
+    where
+        F: Fn(
+            &'a dyn LlmProvider,
+        )
+            -> futures_util::future::BoxFuture<'a, Result<MessagesResponse, MicroClawError>>,
+    {
+        let mut last_err = None;
+        for (index, (served_by, provider)) in self.chain.iter().enumerate() {
+            if index + 1 < self.chain.len()
+                && crate::provider_health::is_cooling_down(&served_by.provider, &served_by.model)
+            {
+                warn!(
+                    "LLM {}/{} is failing repeatedly; skipping to {}/{}",
+                    served_by.provider,
+                    served_by.model,
+                    self.chain[index + 1].0.provider,
+                    self.chain[index + 1].0.model
+                );
+                continue;
+            }
+            let attempt = call(provider.as_ref());
+            let (result, transient) = match self.timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, attempt).await {
+                    Ok(result) => (result, false),
+                    Err(_) => {
+                        let e = MicroClawError::LlmApi(format!(
+                            "{} timed out after {}s",
+                            served_by.model,
+                            timeout.as_secs()
+                        ));
+                        crate::provider_health::record_failure(
+                            &served_by.provider,
+                            &served_by.model,
+                            &e,
+                        );
+                        (Err(e), true)
+                    }
+                },
+                None => (attempt.await, false),
+            };
+            match result {
+                Ok(mut response) => {
+                    if index > 0 {
+                        response.served_by = Some(served_by.clone());
+                    }
+                    return Ok(response);
+                }
+                Err(e)
+                    if index + 1 < self.chain.len()
+                        && (transient || e.is_transient_llm_failure()) =>
+                {
+                    let score =
+                        crate::provider_health::health(&served_by.provider, &served_by.model)
+                            .map_or(1.0, |h| h.score);
+                    warn!(
+                        "LLM {}/{} failed ({e}, health {:.0}%); falling back to {}/{}",
+                        served_by.provider,
+                        served_by.model,
+                        score * 100.0,
+                        self.chain[index + 1].0.provider,
+                        self.chain[index + 1].0.model
+                    );
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| MicroClawError::LlmApi("no LLM configured".into())))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for FallbackProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.run(|provider| {
+            Box::pin(provider.send_message(system, messages.clone(), tools.clone()))
+        })
+        .await
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.run(|provider| {
+            Box::pin(provider.send_message_stream(system, messages.clone(), tools.clone(), text_tx))
+        })
+        .await
+    }
+
+    async fn send_message_json(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        schema: &ResponseSchema,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.run(|provider| Box::pin(provider.send_message_json(system, messages.clone(), schema)))
+            .await
+    }
+
+    /// Always the primary model: vectors from different embedding models
+    /// cannot be compared, so falling back would corrupt stored embeddings.
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, MicroClawError> {
+        self.chain[0].1.embed(inputs).await
     }
 }
 
@@ -229,12 +439,22 @@ pub fn create_provider(config: &Config) -> Box<dyn LlmProvider> {
 // Anthropic provider
 // ---------------------------------------------------------------------------
 
+/// Beta header Anthropic requires on requests authorized with OAuth tokens.
+const ANTHROPIC_OAUTH_BETA: &str = "oauth-2025-04-20";
+
 pub struct AnthropicProvider {
     http: reqwest::Client,
     api_key: String,
     model: String,
     max_tokens: u32,
+    /// Extended thinking budget (0 = off).
+    thinking_budget: u32,
+    temperature: Option<f64>,
     base_url: String,
+    /// Token from `microclaw login anthropic`, used instead of `api_key`.
+    oauth: Option<crate::provider_oauth::OAuthCredential>,
+    /// `claude-oauth`: authorize with the `claude login` credentials.
+    claude_oauth: bool,
 }
 
 impl AnthropicProvider {
@@ -244,7 +464,57 @@ impl AnthropicProvider {
             api_key: config.api_key.clone(),
             model: config.model.clone(),
             max_tokens: config.max_tokens,
+            thinking_budget: config.thinking.budget_tokens,
+            temperature: config.temperature,
             base_url: resolve_anthropic_messages_url(config.llm_base_url.as_deref().unwrap_or("")),
+            oauth: crate::provider_oauth::OAuthCredential::from_config(config),
+            claude_oauth: crate::claude_auth::is_claude_oauth_provider(&config.llm_provider),
+        }
+    }
+
+    /// Add the OAuth bearer token, else the `x-api-key` header.
+    async fn authorize(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder, MicroClawError> {
+        if self.claude_oauth {
+            let token = crate::claude_auth::claude_oauth_bearer_token().await?;
+            return Ok(req
+                .bearer_auth(token)
+                .header("anthropic-beta", ANTHROPIC_OAUTH_BETA));
+        }
+        match &self.oauth {
+            Some(oauth) => Ok(req
+                .bearer_auth(oauth.bearer_token().await?)
+                .header("anthropic-beta", ANTHROPIC_OAUTH_BETA)),
+            None => Ok(req.header("x-api-key", &self.api_key)),
+        }
+    }
+
+    fn build_request(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        stream: Option<bool>,
+    ) -> MessagesRequest {
+        let mut messages = sanitize_messages(messages);
+        if self.thinking_budget == 0 {
+            strip_thinking_blocks(&mut messages);
+        }
+        MessagesRequest {
+            model: self.model.clone(),
+            // The thinking budget must fit inside max_tokens; keep the
+            // configured room for the visible answer.
+            max_tokens: self.max_tokens.saturating_add(self.thinking_budget),
+            system: system.to_string(),
+            messages,
+            tools,
+            stream,
+            thinking: (self.thinking_budget > 0)
+                .then(|| json!({"type": "enabled", "budget_tokens": self.thinking_budget})),
+            // Extended thinking only runs at the default temperature.
+            temperature: self.temperature.filter(|_| self.thinking_budget == 0),
         }
     }
 
@@ -256,37 +526,34 @@ impl AnthropicProvider {
         let mut streamed_request = request.clone();
         streamed_request.stream = Some(true);
 
-        let response = self
+        let mut wire = crate::wire_log::begin("anthropic", &self.base_url, &streamed_request);
+        let req = self
             .http
             .post(&self.base_url)
-            .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&streamed_request)
-            .send()
-            .await?;
+            .json(&streamed_request);
+        let response = self.authorize(req).await?.send().await?;
 
         let status = response.status();
         if !status.is_success() {
             let body = response.text().await.unwrap_or_default();
+            wire.finish(status.as_u16(), &body);
             if let Ok(api_err) = serde_json::from_str::<AnthropicApiError>(&body) {
-                return Err(MicroClawError::LlmApi(format!(
-                    "{}: {}",
-                    api_err.error.error_type, api_err.error.message
-                )));
+                return Err(MicroClawError::LlmHttp {
+                    status: status.as_u16(),
+                    message: format!("{}: {}", api_err.error.error_type, api_err.error.message),
+                });
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {body}")));
+            return Err(MicroClawError::LlmHttp {
+                status: status.as_u16(),
+                message: format!("HTTP {status}: {body}"),
+            });
         }
 
         let mut byte_stream = response.bytes_stream();
         let mut sse = SseEventParser::default();
-        let mut stop_reason: Option<String> = None;
-        let mut usage: Option<Usage> = None;
-        let mut text_blocks: std::collections::HashMap<usize, String> =
-            std::collections::HashMap::new();
-        let mut tool_blocks: std::collections::HashMap<usize, StreamToolUseBlock> =
-            std::collections::HashMap::new();
-        let mut ordered_indexes: Vec<usize> = Vec::new();
+        let mut stream = AnthropicStream::default();
 
         'outer: while let Some(chunk_res) = byte_stream.next().await {
             let chunk = match chunk_res {
@@ -297,39 +564,20 @@ impl AnthropicProvider {
                 if data == "[DONE]" {
                     break 'outer;
                 }
-                process_anthropic_stream_event(
-                    &data,
-                    text_tx,
-                    &mut stop_reason,
-                    &mut usage,
-                    &mut text_blocks,
-                    &mut tool_blocks,
-                    &mut ordered_indexes,
-                );
+                wire.event(&data);
+                process_anthropic_stream_event(&data, text_tx, &mut stream);
             }
         }
         for data in sse.finish() {
             if data == "[DONE]" {
                 break;
             }
-            process_anthropic_stream_event(
-                &data,
-                text_tx,
-                &mut stop_reason,
-                &mut usage,
-                &mut text_blocks,
-                &mut tool_blocks,
-                &mut ordered_indexes,
-            );
+            wire.event(&data);
+            process_anthropic_stream_event(&data, text_tx, &mut stream);
         }
+        wire.finish_stream(status.as_u16());
 
-        Ok(build_stream_response(
-            ordered_indexes,
-            text_blocks,
-            tool_blocks,
-            stop_reason,
-            usage,
-        ))
+        Ok(stream.into_response())
     }
 }
 
@@ -351,6 +599,56 @@ struct StreamToolUseBlock {
     input_json: String,
 }
 
+#[derive(Default)]
+struct StreamThinkingBlock {
+    thinking: String,
+    signature: String,
+    /// Set for `redacted_thinking` blocks.
+    redacted: Option<String>,
+}
+
+/// Content blocks and metadata collected from an Anthropic event stream.
+#[derive(Default)]
+struct AnthropicStream {
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+    text_blocks: std::collections::HashMap<usize, String>,
+    tool_blocks: std::collections::HashMap<usize, StreamToolUseBlock>,
+    thinking_blocks: std::collections::BTreeMap<usize, StreamThinkingBlock>,
+    ordered_indexes: Vec<usize>,
+}
+
+impl AnthropicStream {
+    fn into_response(self) -> MessagesResponse {
+        let mut response = build_stream_response(
+            self.ordered_indexes,
+            self.text_blocks,
+            self.tool_blocks,
+            self.stop_reason,
+            self.usage,
+        );
+        // Thinking always comes before the answer and tool calls.
+        let thinking: Vec<ResponseContentBlock> = self
+            .thinking_blocks
+            .into_values()
+            .map(|block| match block.redacted {
+                Some(data) => ResponseContentBlock::RedactedThinking { data },
+                None => ResponseContentBlock::Thinking {
+                    thinking: block.thinking,
+                    signature: block.signature,
+                },
+            })
+            .collect();
+        if !thinking.is_empty() {
+            response
+                .content
+                .retain(|b| !matches!(b, ResponseContentBlock::Text { text } if text.is_empty()));
+            response.content.splice(0..0, thinking);
+        }
+        response
+    }
+}
+
 fn usage_from_json(v: &serde_json::Value) -> Option<Usage> {
     let input = v.get("input_tokens").and_then(|n| n.as_u64())?;
     let output = v
@@ -364,18 +662,29 @@ fn usage_from_json(v: &serde_json::Value) -> Option<Usage> {
     })
 }
 
+/// Apply one stream event. Thinking is forwarded to `text_tx` inside
+/// `<think>` tags so drafts can hide or show it like inline thinking.
 fn process_anthropic_stream_event(
     data: &str,
     text_tx: Option<&UnboundedSender<String>>,
-    stop_reason: &mut Option<String>,
-    usage: &mut Option<Usage>,
-    text_blocks: &mut std::collections::HashMap<usize, String>,
-    tool_blocks: &mut std::collections::HashMap<usize, StreamToolUseBlock>,
-    ordered_indexes: &mut Vec<usize>,
+    stream: &mut AnthropicStream,
 ) {
     let Ok(v) = serde_json::from_str::<serde_json::Value>(data) else {
         return;
     };
+    let AnthropicStream {
+        stop_reason,
+        usage,
+        text_blocks,
+        tool_blocks,
+        thinking_blocks,
+        ordered_indexes,
+    } = stream;
+    let send = |piece: &str| {
+        if let Some(tx) = text_tx {
+            let _ = tx.send(piece.to_string());
+        }
+    };
 
     let event_type = v.get("type").and_then(|t| t.as_str()).unwrap_or_default();
     match event_type {
@@ -426,6 +735,36 @@ fn process_anthropic_stream_event(
                                 },
                             );
                         }
+                        Some("thinking") => {
+                            let thinking = block
+                                .get("thinking")
+                                .and_then(|t| t.as_str())
+                                .unwrap_or_default()
+                                .to_string();
+                            send("<think>");
+                            send(&thinking);
+                            thinking_blocks.insert(
+                                index,
+                                StreamThinkingBlock {
+                                    thinking,
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        Some("redacted_thinking") => {
+                            let data = block
+                                .get("data")
+                                .and_then(|t| t.as_str())
+                                .unwrap_or_default()
+                                .to_string();
+                            thinking_blocks.insert(
+                                index,
+                                StreamThinkingBlock {
+                                    redacted: Some(data),
+                                    ..Default::default()
+                                },
+                            );
+                        }
                         _ => {}
                     }
                 }
@@ -454,9 +793,27 @@ fn process_anthropic_stream_event(
                         .unwrap_or_default();
                     if !piece.is_empty() {
                         text_blocks.entry(index).or_default().push_str(piece);
-                        if let Some(tx) = text_tx {
-                            let _ = tx.send(piece.to_string());
-                        }
+                        send(piece);
+                    }
+                }
+                Some("thinking_delta") => {
+                    let piece = delta
+                        .get("thinking")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default();
+                    if let Some(block) = thinking_blocks.get_mut(&index) {
+                        block.thinking.push_str(piece);
+                        send(piece);
+                    }
+                }
+                Some("signature_delta") => {
+                    if let Some(block) = thinking_blocks.get_mut(&index) {
+                        block.signature.push_str(
+                            delta
+                                .get("signature")
+                                .and_then(|t| t.as_str())
+                                .unwrap_or_default(),
+                        );
                     }
                 }
                 Some("input_json_delta") => {
@@ -475,6 +832,18 @@ fn process_anthropic_stream_event(
                 _ => {}
             }
         }
+        "content_block_stop" => {
+            let index = v
+                .get("index")
+                .and_then(|i| i.as_u64())
+                .and_then(|i| usize::try_from(i).ok());
+            if index
+                .and_then(|i| thinking_blocks.get(&i))
+                .is_some_and(|b| b.redacted.is_none())
+            {
+                send("</think>");
+            }
+        }
         "message_delta" => {
             if let Some(reason) = v
                 .get("delta")
@@ -496,10 +865,13 @@ fn process_anthropic_stream_event(
     }
 }
 
+/// Apply one stream chunk. Reasoning deltas are collected in `reasoning` and
+/// forwarded inside `<think>` tags.
 fn process_openai_stream_event(
     data: &str,
     text_tx: Option<&UnboundedSender<String>>,
     text: &mut String,
+    reasoning: &mut String,
     stop_reason: &mut Option<String>,
     usage: &mut Option<Usage>,
     tool_calls: &mut std::collections::BTreeMap<usize, StreamToolUseBlock>,
@@ -528,12 +900,31 @@ fn process_openai_stream_event(
         return;
     };
 
+    let send = |piece: &str| {
+        if let Some(tx) = text_tx {
+            let _ = tx.send(piece.to_string());
+        }
+    };
+    if let Some(piece) = delta
+        .get("reasoning_content")
+        .or_else(|| delta.get("reasoning"))
+        .and_then(|t| t.as_str())
+    {
+        if !piece.is_empty() && text.is_empty() {
+            if reasoning.is_empty() {
+                send("<think>");
+            }
+            reasoning.push_str(piece);
+            send(piece);
+        }
+    }
     if let Some(piece) = delta.get("content").and_then(|t| t.as_str()) {
         if !piece.is_empty() {
-            text.push_str(piece);
-            if let Some(tx) = text_tx {
-                let _ = tx.send(piece.to_string());
+            if text.is_empty() && !reasoning.is_empty() {
+                send("</think>");
             }
+            text.push_str(piece);
+            send(piece);
         }
     }
 
@@ -612,6 +1003,7 @@ fn build_stream_response(
         content,
         stop_reason: normalize_stop_reason(stop_reason),
         usage,
+        served_by: None,
     }
 }
 
@@ -635,61 +1027,40 @@ impl LlmProvider for AnthropicProvider {
         messages: Vec<Message>,
         tools: Option<Vec<ToolDefinition>>,
     ) -> Result<MessagesResponse, MicroClawError> {
-        let messages = sanitize_messages(messages);
-
-        let request = MessagesRequest {
-            model: self.model.clone(),
-            max_tokens: self.max_tokens,
-            system: system.to_string(),
-            messages,
-            tools,
-            stream: None,
-        };
-
-        let mut retries = 0u32;
-        let max_retries = 3;
-
-        loop {
-            let response = self
-                .http
-                .post(&self.base_url)
-                .header("x-api-key", &self.api_key)
-                .header("anthropic-version", "2023-06-01")
-                .header("content-type", "application/json")
-                .json(&request)
-                .send()
-                .await?;
-
-            let status = response.status();
+        let request = self.build_request(system, messages, tools, None);
 
-            if status.is_success() {
-                let body = response.text().await?;
-                let parsed: MessagesResponse = serde_json::from_str(&body).map_err(|e| {
-                    MicroClawError::LlmApi(format!("Failed to parse response: {e}\nBody: {body}"))
-                })?;
-                return Ok(parsed);
-            }
+        let wire = crate::wire_log::begin("anthropic", &self.base_url, &request);
+        let req = self
+            .http
+            .post(&self.base_url)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&request);
+        let response = self.authorize(req).await?.send().await?;
 
-            if status.as_u16() == 429 && retries < max_retries {
-                retries += 1;
-                let delay = std::time::Duration::from_secs(2u64.pow(retries));
-                warn!(
-                    "Rate limited, retrying in {:?} (attempt {retries}/{max_retries})",
-                    delay
-                );
-                tokio::time::sleep(delay).await;
-                continue;
-            }
+        let status = response.status();
 
-            let body = response.text().await.unwrap_or_default();
-            if let Ok(api_err) = serde_json::from_str::<AnthropicApiError>(&body) {
-                return Err(MicroClawError::LlmApi(format!(
-                    "{}: {}",
-                    api_err.error.error_type, api_err.error.message
-                )));
-            }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {body}")));
+        if status.is_success() {
+            let body = response.text().await?;
+            wire.finish(status.as_u16(), &body);
+            let parsed: MessagesResponse = serde_json::from_str(&body).map_err(|e| {
+                MicroClawError::LlmApi(format!("Failed to parse response: {e}\nBody: {body}"))
+            })?;
+            return Ok(parsed);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        wire.finish(status.as_u16(), &body);
+        if let Ok(api_err) = serde_json::from_str::<AnthropicApiError>(&body) {
+            return Err(MicroClawError::LlmHttp {
+                status: status.as_u16(),
+                message: format!("{}: {}", api_err.error.error_type, api_err.error.message),
+            });
         }
+        Err(MicroClawError::LlmHttp {
+            status: status.as_u16(),
+            message: format!("HTTP {status}: {body}"),
+        })
     }
 
     async fn send_message_stream(
@@ -699,15 +1070,7 @@ impl LlmProvider for AnthropicProvider {
         tools: Option<Vec<ToolDefinition>>,
         text_tx: Option<&UnboundedSender<String>>,
     ) -> Result<MessagesResponse, MicroClawError> {
-        let messages = sanitize_messages(messages);
-        let request = MessagesRequest {
-            model: self.model.clone(),
-            max_tokens: self.max_tokens,
-            system: system.to_string(),
-            messages,
-            tools,
-            stream: Some(true),
-        };
+        let request = self.build_request(system, messages, tools, Some(true));
 
         self.send_message_stream_single_pass(&request, text_tx)
             .await
@@ -725,8 +1088,76 @@ pub struct OpenAiProvider {
     model: String,
     max_tokens: u32,
     is_openai_codex: bool,
+    /// Model accepts `response_format: json_schema` (`structured_output`).
+    json_schema_mode: bool,
+    /// `reasoning_effort` for reasoning models (`thinking.reasoning_effort`).
+    reasoning_effort: Option<String>,
+    /// Not sent with `reasoning_effort`; reasoning models reject it.
+    temperature: Option<f64>,
     chat_url: String,
     responses_url: String,
+    embeddings_url: String,
+    /// Model for `embed`; see `default_embedding_model`.
+    embedding_model: String,
+    /// Azure OpenAI auth (`llm_provider: azure`).
+    azure: Option<crate::azure::AzureCredential>,
+    /// Token from `microclaw login <provider>`, used instead of `api_key`.
+    oauth: Option<crate::provider_oauth::OAuthCredential>,
+}
+
+/// Embedding model for `embed`: `embedding_model` when it belongs to this
+/// provider (no other `embedding_provider` is set), else the provider's
+/// usual default.
+fn default_embedding_model(config: &Config) -> String {
+    let provider = config.llm_provider.trim().to_lowercase();
+    let same_provider = config
+        .embedding_provider
+        .as_deref()
+        .is_none_or(|p| p.trim().eq_ignore_ascii_case(&provider));
+    if let Some(model) = config
+        .embedding_model
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| same_provider && !m.is_empty())
+    {
+        return model.to_string();
+    }
+    match provider.as_str() {
+        "ollama" => "nomic-embed-text".into(),
+        _ => "text-embedding-3-small".into(),
+    }
+}
+
+/// Inputs sent per embeddings request.
+const EMBED_BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Deserialize)]
+struct OaiEmbeddingResponse {
+    data: Vec<OaiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OaiEmbedding {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Vectors from an embeddings response, in input order.
+fn parse_embeddings(text: &str, expected: usize) -> Result<Vec<Vec<f32>>, MicroClawError> {
+    let mut response: OaiEmbeddingResponse = serde_json::from_str(text).map_err(|e| {
+        MicroClawError::LlmApi(format!(
+            "Failed to parse embeddings response: {e}\nBody: {text}"
+        ))
+    })?;
+    if response.data.len() != expected {
+        return Err(MicroClawError::LlmApi(format!(
+            "Embeddings response has {} vectors for {expected} inputs",
+            response.data.len()
+        )));
+    }
+    response.data.sort_by_key(|e| e.index);
+    Ok(response.data.into_iter().map(|e| e.embedding).collect())
 }
 
 fn resolve_openai_compat_base(provider: &str, configured_base: &str) -> String {
@@ -752,7 +1183,9 @@ impl OpenAiProvider {
         let base = resolve_openai_compat_base(&config.llm_provider, configured_base);
 
         let (api_key, codex_account_id) = if is_openai_codex {
-            let _ = refresh_openai_codex_auth_if_needed();
+            if let Err(e) = refresh_openai_codex_auth_if_needed() {
+                warn!("{e}");
+            }
             match resolve_openai_codex_auth("") {
                 Ok(auth) => (auth.bearer_token, auth.account_id),
                 Err(e) => {
@@ -764,6 +1197,15 @@ impl OpenAiProvider {
             (config.api_key.clone(), None)
         };
 
+        let mut chat_url = format!("{}/chat/completions", base.trim_end_matches('/'));
+        let mut embeddings_url = format!("{}/embeddings", base.trim_end_matches('/'));
+        let azure = crate::azure::is_azure_provider(&config.llm_provider).then(|| {
+            let api_version = config.azure.api_version.as_deref();
+            chat_url = crate::azure::with_api_version(&chat_url, api_version);
+            embeddings_url = crate::azure::with_api_version(&embeddings_url, api_version);
+            crate::azure::AzureCredential::new(config)
+        });
+
         OpenAiProvider {
             http: reqwest::Client::new(),
             api_key,
@@ -771,8 +1213,15 @@ impl OpenAiProvider {
             model: config.model.clone(),
             max_tokens: config.max_tokens,
             is_openai_codex,
-            chat_url: format!("{}/chat/completions", base.trim_end_matches('/')),
+            json_schema_mode: crate::model_caps::for_config(config).structured_output,
+            reasoning_effort: config.thinking.reasoning_effort.clone(),
+            temperature: config.temperature,
+            chat_url,
             responses_url: format!("{}/responses", base.trim_end_matches('/')),
+            embeddings_url,
+            embedding_model: default_embedding_model(config),
+            azure,
+            oauth: crate::provider_oauth::OAuthCredential::from_config(config),
         }
     }
 }
@@ -794,6 +1243,10 @@ struct OaiChoice {
 #[derive(Debug, Deserialize)]
 struct OaiMessage {
     content: Option<String>,
+    /// Reasoning text from DeepSeek-style (`reasoning_content`) and
+    /// OpenRouter (`reasoning`) reasoning models.
+    #[serde(default, alias = "reasoning")]
+    reasoning_content: Option<String>,
     tool_calls: Option<Vec<OaiToolCall>>,
 }
 
@@ -883,6 +1336,11 @@ impl LlmProvider for OpenAiProvider {
             "max_tokens": self.max_tokens,
             "messages": oai_messages,
         });
+        if let Some(effort) = &self.reasoning_effort {
+            body["reasoning_effort"] = json!(effort);
+        } else if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
 
         if let Some(ref tool_defs) = tools {
             if !tool_defs.is_empty() {
@@ -890,49 +1348,61 @@ impl LlmProvider for OpenAiProvider {
             }
         }
 
-        let mut retries = 0u32;
-        let max_retries = 3;
+        self.post_chat_completion(&body).await
+    }
 
-        loop {
-            let mut req = self
-                .http
-                .post(&self.chat_url)
-                .header("Content-Type", "application/json")
-                .json(&body);
-            if !self.api_key.trim().is_empty() {
-                req = req.header("Authorization", format!("Bearer {}", self.api_key));
-            }
-            let response = req.send().await?;
+    async fn send_message_json(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        schema: &ResponseSchema,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        if self.is_openai_codex || !self.json_schema_mode {
+            return self.send_message(system, messages, None).await;
+        }
+        let body = json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens,
+            "messages": translate_messages_to_oai(system, &messages),
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": schema.name,
+                    "schema": schema.schema,
+                    "strict": false,
+                },
+            },
+        });
+        self.post_chat_completion(&body).await
+    }
 
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, MicroClawError> {
+        if self.is_openai_codex {
+            return Err(MicroClawError::LlmApi(
+                "The ChatGPT Codex backend does not offer embeddings".into(),
+            ));
+        }
+        let mut vectors = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(EMBED_BATCH_SIZE) {
+            let body = json!({ "model": self.embedding_model, "input": batch });
+            let wire = crate::wire_log::begin("openai", &self.embeddings_url, &body);
+            let req = self.http.post(&self.embeddings_url).json(&body);
+            let response = self.authorize(req).await?.send().await?;
             let status = response.status();
-
-            if status.is_success() {
-                let text = response.text().await?;
-                let oai: OaiResponse = serde_json::from_str(&text).map_err(|e| {
-                    MicroClawError::LlmApi(format!(
-                        "Failed to parse OpenAI response: {e}\nBody: {text}"
-                    ))
-                })?;
-                return Ok(translate_oai_response(oai));
-            }
-
-            if status.as_u16() == 429 && retries < max_retries {
-                retries += 1;
-                let delay = std::time::Duration::from_secs(2u64.pow(retries));
-                warn!(
-                    "Rate limited, retrying in {:?} (attempt {retries}/{max_retries})",
-                    delay
-                );
-                tokio::time::sleep(delay).await;
-                continue;
-            }
-
             let text = response.text().await.unwrap_or_default();
-            if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
-                return Err(MicroClawError::LlmApi(err.error.message));
+            wire.finish(status.as_u16(), &text);
+            if !status.is_success() {
+                let message = serde_json::from_str::<OaiErrorResponse>(&text)
+                    .map(|err| err.error.message)
+                    .unwrap_or_else(|_| format!("HTTP {status}: {text}"));
+                return Err(MicroClawError::LlmHttp {
+                    status: status.as_u16(),
+                    message,
+                });
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {text}")));
+            vectors.extend(parse_embeddings(&text, batch.len())?);
         }
+        Ok(vectors)
     }
 
     async fn send_message_stream(
@@ -969,6 +1439,11 @@ impl LlmProvider for OpenAiProvider {
             "messages": oai_messages,
             "stream": true,
         });
+        if let Some(effort) = &self.reasoning_effort {
+            body["reasoning_effort"] = json!(effort);
+        } else if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
 
         if let Some(ref tool_defs) = tools {
             if !tool_defs.is_empty() {
@@ -976,27 +1451,33 @@ impl LlmProvider for OpenAiProvider {
             }
         }
 
-        let mut req = self
+        let mut wire = crate::wire_log::begin("openai", &self.chat_url, &body);
+        let req = self
             .http
             .post(&self.chat_url)
             .header("Content-Type", "application/json")
             .json(&body);
-        if !self.api_key.trim().is_empty() {
-            req = req.header("Authorization", format!("Bearer {}", self.api_key));
-        }
-        let response = req.send().await?;
+        let response = self.authorize(req).await?.send().await?;
         let status = response.status();
         if !status.is_success() {
             let text = response.text().await.unwrap_or_default();
+            wire.finish(status.as_u16(), &text);
             if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
-                return Err(MicroClawError::LlmApi(err.error.message));
+                return Err(MicroClawError::LlmHttp {
+                    status: status.as_u16(),
+                    message: err.error.message,
+                });
             }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {text}")));
+            return Err(MicroClawError::LlmHttp {
+                status: status.as_u16(),
+                message: format!("HTTP {status}: {text}"),
+            });
         }
 
         let mut byte_stream = response.bytes_stream();
         let mut sse = SseEventParser::default();
         let mut text = String::new();
+        let mut reasoning = String::new();
         let mut stop_reason: Option<String> = None;
         let mut usage: Option<Usage> = None;
         let mut tool_calls: std::collections::BTreeMap<usize, StreamToolUseBlock> =
@@ -1011,10 +1492,12 @@ impl LlmProvider for OpenAiProvider {
                 if data == "[DONE]" {
                     break 'outer;
                 }
+                wire.event(&data);
                 process_openai_stream_event(
                     &data,
                     text_tx,
                     &mut text,
+                    &mut reasoning,
                     &mut stop_reason,
                     &mut usage,
                     &mut tool_calls,
@@ -1025,17 +1508,31 @@ impl LlmProvider for OpenAiProvider {
             if data == "[DONE]" {
                 break;
             }
+            wire.event(&data);
             process_openai_stream_event(
                 &data,
                 text_tx,
                 &mut text,
+                &mut reasoning,
                 &mut stop_reason,
                 &mut usage,
                 &mut tool_calls,
             );
         }
+        wire.finish_stream(status.as_u16());
 
         let mut content = Vec::new();
+        if !reasoning.is_empty() && text.is_empty() {
+            if let Some(tx) = text_tx {
+                let _ = tx.send("</think>".into());
+            }
+        }
+        if !reasoning.trim().is_empty() {
+            content.push(ResponseContentBlock::Thinking {
+                thinking: reasoning,
+                signature: String::new(),
+            });
+        }
         if !text.is_empty() {
             content.push(ResponseContentBlock::Text { text });
         }
@@ -1056,11 +1553,69 @@ impl LlmProvider for OpenAiProvider {
             content,
             stop_reason: normalize_stop_reason(stop_reason),
             usage,
+            served_by: None,
         })
     }
 }
 
 impl OpenAiProvider {
+    /// Add credentials: Azure `api-key` / Entra ID token, else the OAuth
+    /// login's token, else the bearer key.
+    async fn authorize(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder, MicroClawError> {
+        if let Some(azure) = &self.azure {
+            return azure.authorize(req).await;
+        }
+        if let Some(oauth) = &self.oauth {
+            return Ok(req.bearer_auth(oauth.bearer_token().await?));
+        }
+        if self.api_key.trim().is_empty() {
+            return Ok(req);
+        }
+        Ok(req.header("Authorization", format!("Bearer {}", self.api_key)))
+    }
+
+    /// POST a chat completions request. Retries happen in `RetryingProvider`.
+    async fn post_chat_completion(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let wire = crate::wire_log::begin("openai", &self.chat_url, body);
+        let req = self
+            .http
+            .post(&self.chat_url)
+            .header("Content-Type", "application/json")
+            .json(body);
+        let response = self.authorize(req).await?.send().await?;
+
+        let status = response.status();
+
+        if status.is_success() {
+            let text = response.text().await?;
+            wire.finish(status.as_u16(), &text);
+            let oai: OaiResponse = serde_json::from_str(&text).map_err(|e| {
+                MicroClawError::LlmApi(format!(
+                    "Failed to parse OpenAI response: {e}\nBody: {text}"
+                ))
+            })?;
+            return Ok(translate_oai_response(oai));
+        }
+
+        let text = response.text().await.unwrap_or_default();
+        wire.finish(status.as_u16(), &text);
+        if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
+            return Err(MicroClawError::LlmHttp {
+                status: status.as_u16(),
+                message: err.error.message,
+            });
+        }
+        Err(MicroClawError::LlmHttp {
+            status: status.as_u16(),
+            message: format!("HTTP {status}: {text}"),
+        })
+    }
     async fn send_codex_message(
         &self,
         system: &str,
@@ -1087,6 +1642,9 @@ impl OpenAiProvider {
             "store": false,
             "stream": true,
         });
+        if let Some(effort) = &self.reasoning_effort {
+            body["reasoning"] = json!({ "effort": effort });
+        }
         if let Some(ref tool_defs) = tools {
             if !tool_defs.is_empty() {
                 body["tools"] = json!(translate_tools_to_oai_responses(tool_defs));
@@ -1094,49 +1652,42 @@ impl OpenAiProvider {
             }
         }
 
-        let mut retries = 0u32;
-        let max_retries = 3;
-
-        loop {
-            let mut req = self
-                .http
-                .post(&self.responses_url)
-                .header("Content-Type", "application/json")
-                .json(&body);
-            if !self.api_key.trim().is_empty() {
-                req = req.header("Authorization", format!("Bearer {}", self.api_key));
-            }
-            if let Some(account_id) = self.codex_account_id.as_deref() {
-                if !account_id.trim().is_empty() {
-                    req = req.header("ChatGPT-Account-ID", account_id);
-                }
-            }
-            let response = req.send().await?;
-            let status = response.status();
-
-            if status.is_success() {
-                let text = response.text().await?;
-                let parsed = parse_openai_codex_response_payload(&text)?;
-                return Ok(translate_oai_responses_response(parsed));
+        let wire = crate::wire_log::begin("openai-codex", &self.responses_url, &body);
+        let mut req = self
+            .http
+            .post(&self.responses_url)
+            .header("Content-Type", "application/json")
+            .json(&body);
+        if !self.api_key.trim().is_empty() {
+            req = req.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+        if let Some(account_id) = self.codex_account_id.as_deref() {
+            if !account_id.trim().is_empty() {
+                req = req.header("ChatGPT-Account-ID", account_id);
             }
+        }
+        let response = req.send().await?;
+        let status = response.status();
 
-            if status.as_u16() == 429 && retries < max_retries {
-                retries += 1;
-                let delay = std::time::Duration::from_secs(2u64.pow(retries));
-                warn!(
-                    "Rate limited, retrying in {:?} (attempt {retries}/{max_retries})",
-                    delay
-                );
-                tokio::time::sleep(delay).await;
-                continue;
-            }
+        if status.is_success() {
+            let text = response.text().await?;
+            wire.finish(status.as_u16(), &text);
+            let parsed = parse_openai_codex_response_payload(&text)?;
+            return Ok(translate_oai_responses_response(parsed));
+        }
 
-            let text = response.text().await.unwrap_or_default();
-            if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
-                return Err(MicroClawError::LlmApi(err.error.message));
-            }
-            return Err(MicroClawError::LlmApi(format!("HTTP {status}: {text}")));
+        let text = response.text().await.unwrap_or_default();
+        wire.finish(status.as_u16(), &text);
+        if let Ok(err) = serde_json::from_str::<OaiErrorResponse>(&text) {
+            return Err(MicroClawError::LlmHttp {
+                status: status.as_u16(),
+                message: err.error.message,
+            });
         }
+        Err(MicroClawError::LlmHttp {
+            status: status.as_u16(),
+            message: format!("HTTP {status}: {text}"),
+        })
     }
 }
 
@@ -1551,6 +2102,7 @@ fn translate_oai_responses_response(resp: OaiResponsesResponse) -> MessagesRespo
             input_tokens: usage.input_tokens,
             output_tokens: usage.output_tokens,
         }),
+        served_by: None,
     }
 }
 
@@ -1564,12 +2116,21 @@ fn translate_oai_response(oai: OaiResponse) -> MessagesResponse {
                 }],
                 stop_reason: Some("end_turn".into()),
                 usage: None,
+                served_by: None,
             };
         }
     };
 
     let mut content = Vec::new();
 
+    if let Some(thinking) = choice.message.reasoning_content {
+        if !thinking.trim().is_empty() {
+            content.push(ResponseContentBlock::Thinking {
+                thinking,
+                signature: String::new(),
+            });
+        }
+    }
     if let Some(text) = choice.message.content {
         if !text.is_empty() {
             content.push(ResponseContentBlock::Text { text });
@@ -1609,6 +2170,7 @@ fn translate_oai_response(oai: OaiResponse) -> MessagesResponse {
         content,
         stop_reason,
         usage,
+        served_by: None,
     }
 }
 
@@ -1830,6 +2392,23 @@ mod tests {
     // translate_oai_response
     // -----------------------------------------------------------------------
 
+    #[test]
+    fn test_translate_oai_response_reasoning_content() {
+        let oai: OaiResponse = serde_json::from_value(json!({
+            "choices": [{
+                "message": {"content": "42", "reasoning_content": "6 * 7"},
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap();
+        let resp = translate_oai_response(oai);
+        assert!(matches!(
+            &resp.content[0],
+            ResponseContentBlock::Thinking { thinking, .. } if thinking == "6 * 7"
+        ));
+        assert!(matches!(&resp.content[1], ResponseContentBlock::Text { text } if text == "42"));
+    }
+
     #[test]
     fn test_translate_oai_response_text() {
         let oai = OaiResponse {
@@ -1837,6 +2416,7 @@ mod tests {
                 message: OaiMessage {
                     content: Some("Hello!".into()),
                     tool_calls: None,
+                    reasoning_content: None,
                 },
                 finish_reason: Some("stop".into()),
             }],
@@ -1870,6 +2450,7 @@ mod tests {
                             arguments: r#"{"command":"ls"}"#.into(),
                         },
                     }]),
+                    reasoning_content: None,
                 },
                 finish_reason: Some("tool_calls".into()),
             }],
@@ -1908,6 +2489,7 @@ mod tests {
                 message: OaiMessage {
                     content: Some("partial".into()),
                     tool_calls: None,
+                    reasoning_content: None,
                 },
                 finish_reason: Some("length".into()),
             }],
@@ -1930,6 +2512,7 @@ mod tests {
                             arguments: r#"{"path":"/tmp/x"}"#.into(),
                         },
                     }]),
+                    reasoning_content: None,
                 },
                 finish_reason: Some("tool_calls".into()),
             }],
@@ -1963,6 +2546,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_anthropic_stream_collects_thinking() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let mut stream = AnthropicStream::default();
+        for event in [
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Check the date."}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Friday"}}"#,
+        ] {
+            process_anthropic_stream_event(event, Some(&tx), &mut stream);
+        }
+        drop(tx);
+        let mut streamed = String::new();
+        while let Ok(piece) = rx.try_recv() {
+            streamed.push_str(&piece);
+        }
+        assert_eq!(streamed, "<think>Check the date.</think>Friday");
+
+        let response = stream.into_response();
+        assert!(matches!(
+            &response.content[0],
+            ResponseContentBlock::Thinking { thinking, signature }
+                if thinking == "Check the date." && signature == "sig"
+        ));
+        assert!(
+            matches!(&response.content[1], ResponseContentBlock::Text { text } if text == "Friday")
+        );
+    }
+
+    #[test]
+    fn test_anthropic_request_thinking_budget() {
+        let mut config: Config =
+            serde_yaml::from_str("api_key: key\nllm_provider: anthropic\nmax_tokens: 4096\n")
+                .unwrap();
+        let history = vec![Message {
+            role: "assistant".into(),
+            content: MessageContent::Blocks(vec![
+                ContentBlock::Thinking {
+                    thinking: "old".into(),
+                    signature: "sig".into(),
+                },
+                ContentBlock::Text { text: "hi".into() },
+            ]),
+        }];
+
+        let plain = AnthropicProvider::new(&config).build_request("", history.clone(), None, None);
+        assert!(plain.thinking.is_none());
+        assert_eq!(plain.max_tokens, 4096);
+        assert!(matches!(
+            &plain.messages[0].content,
+            MessageContent::Blocks(blocks) if blocks.len() == 1
+        ));
+
+        config.thinking.budget_tokens = 2048;
+        let thinking = AnthropicProvider::new(&config).build_request("", history, None, None);
+        assert_eq!(thinking.max_tokens, 6144);
+        assert_eq!(thinking.thinking.unwrap()["budget_tokens"], 2048);
+        assert!(matches!(
+            &thinking.messages[0].content,
+            MessageContent::Blocks(blocks) if blocks.len() == 2
+        ));
+    }
+
     #[test]
     fn test_build_stream_response_tool_json_parsing() {
         let mut tool_blocks = std::collections::HashMap::new();
@@ -2027,6 +2676,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 3900,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -2042,6 +2693,71 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         };
         // Should not panic
@@ -2078,6 +2794,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 3900,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -2093,11 +2811,151 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         };
         let _provider = create_provider(&config);
     }
 
+    struct StubProvider {
+        fail_status: Option<u16>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for StubProvider {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            match self.fail_status {
+                Some(status) => Err(MicroClawError::LlmHttp {
+                    status,
+                    message: format!("HTTP {status}"),
+                }),
+                None => Ok(MessagesResponse {
+                    content: vec![ResponseContentBlock::Text { text: "ok".into() }],
+                    stop_reason: Some("end_turn".into()),
+                    usage: None,
+                    served_by: None,
+                }),
+            }
+        }
+    }
+
+    fn stub_chain(statuses: &[Option<u16>]) -> FallbackProvider {
+        FallbackProvider {
+            chain: statuses
+                .iter()
+                .enumerate()
+                .map(|(i, status)| {
+                    (
+                        ServedBy {
+                            provider: "stub".into(),
+                            model: format!("m{i}"),
+                        },
+                        Box::new(StubProvider {
+                            fail_status: *status,
+                        }) as Box<dyn LlmProvider>,
+                    )
+                })
+                .collect(),
+            timeout: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fallback_provider_moves_on_after_transient_errors() {
+        let response = stub_chain(&[Some(529), Some(429), None])
+            .send_message("", vec![], None)
+            .await
+            .unwrap();
+        assert_eq!(response.served_by.map(|s| s.model).as_deref(), Some("m2"));
+
+        let primary = stub_chain(&[None, None])
+            .send_message("", vec![], None)
+            .await
+            .unwrap();
+        assert!(primary.served_by.is_none());
+
+        let err = stub_chain(&[Some(400), None])
+            .send_message("", vec![], None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicroClawError::LlmHttp { status: 400, .. }));
+
+        let err = stub_chain(&[Some(503), Some(503)])
+            .send_message("", vec![], None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicroClawError::LlmHttp { status: 503, .. }));
+    }
+
     #[tokio::test]
     #[allow(clippy::await_holding_lock)]
     async fn test_openai_codex_stream_uses_responses_endpoint() {
@@ -2194,6 +3052,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 3900,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -2209,6 +3069,71 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         };
         let provider = OpenAiProvider::new(&config);
@@ -2349,6 +3274,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 3900,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -2364,6 +3291,71 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         };
         let provider = OpenAiProvider::new(&config);
@@ -2631,4 +3623,24 @@ data: [DONE]
             _ => panic!("Expected text block"),
         }
     }
+
+    #[test]
+    fn test_parse_embeddings_orders_by_index() {
+        let body =
+            r#"{"data":[{"index":1,"embedding":[0.5,0.5]},{"index":0,"embedding":[1.0,0.0]}]}"#;
+        let vectors = parse_embeddings(body, 2).unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.5, 0.5]]);
+        assert!(parse_embeddings(body, 3).is_err());
+    }
+
+    #[test]
+    fn test_default_embedding_model() {
+        let mut config: Config =
+            serde_yaml::from_str("api_key: key\nllm_provider: ollama\n").unwrap();
+        assert_eq!(default_embedding_model(&config), "nomic-embed-text");
+        config.embedding_model = Some("mxbai-embed-large".into());
+        assert_eq!(default_embedding_model(&config), "mxbai-embed-large");
+        config.embedding_provider = Some("openai".into());
+        assert_eq!(default_embedding_model(&config), "nomic-embed-text");
+    }
 }
@@ -35,6 +35,12 @@ pub enum ContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    /// Anthropic extended thinking. Sent back unchanged during a tool loop,
+    /// dropped for other providers.
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +66,11 @@ pub struct MessagesRequest {
     pub tools: Option<Vec<ToolDefinition>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// Anthropic extended thinking, e.g. `{"type": "enabled", "budget_tokens": 4096}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,6 +79,35 @@ pub struct MessagesResponse {
     pub content: Vec<ResponseContentBlock>,
     pub stop_reason: Option<String>,
     pub usage: Option<Usage>,
+    /// Set when a fallback model answered instead of the configured one.
+    #[serde(skip)]
+    pub served_by: Option<ServedBy>,
+}
+
+impl MessagesResponse {
+    /// Provider and model to record usage under: the fallback that served
+    /// the request, or the configured ones passed in.
+    pub fn usage_source(&self, provider: &str, model: &str) -> (String, String) {
+        match &self.served_by {
+            Some(s) => (s.provider.clone(), s.model.clone()),
+            None => (provider.to_string(), model.to_string()),
+        }
+    }
+}
+
+/// Provider and model that answered a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServedBy {
+    pub provider: String,
+    pub model: String,
+}
+
+/// JSON schema a structured reply must match (see `crate::structured`).
+#[derive(Debug, Clone)]
+pub struct ResponseSchema {
+    /// Short identifier, sent as the schema name in native JSON modes.
+    pub name: String,
+    pub schema: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -81,6 +121,14 @@ pub enum ResponseContentBlock {
         name: String,
         input: serde_json::Value,
     },
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
 #[derive(Debug, Deserialize)]
@@ -210,6 +258,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_response_content_block_thinking_deserialization() {
+        let json = json!({"type": "thinking", "thinking": "Let me see", "signature": "sig"});
+        let block: ResponseContentBlock = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            block,
+            ResponseContentBlock::Thinking { ref thinking, ref signature }
+                if thinking == "Let me see" && signature == "sig"
+        ));
+        let json = json!({"type": "redacted_thinking", "data": "abc"});
+        let block: ResponseContentBlock = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            block,
+            ResponseContentBlock::RedactedThinking { .. }
+        ));
+    }
+
     #[test]
     fn test_messages_request_serialization() {
         let req = MessagesRequest {
@@ -222,6 +287,8 @@ mod tests {
             }],
             tools: None,
             stream: None,
+            thinking: None,
+            temperature: None,
         };
         let json = serde_json::to_value(&req).unwrap();
         assert_eq!(json["model"], "claude-sonnet-4-5-20250929");
@@ -242,6 +309,8 @@ mod tests {
                 input_schema: json!({"type": "object"}),
             }]),
             stream: None,
+            thinking: None,
+            temperature: None,
         };
         let json = serde_json::to_value(&req).unwrap();
         assert!(json["tools"].is_array());
@@ -5,38 +5,166 @@ use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
 use tracing_subscriber::fmt::writer::MakeWriter;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
 
 pub const LOG_FILE_PREFIX: &str = "microclaw-";
 pub const LOG_FILE_SUFFIX: &str = ".log";
 pub const LOG_RETENTION_DAYS: i64 = 30;
 
-pub fn init_logging(runtime_data_dir: &str) -> Result<()> {
+fn env_filter() -> tracing_subscriber::EnvFilter {
+    tracing_subscriber::EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into())
+}
+
+fn init_with<W>(writer: W, ansi: bool, json: bool)
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(env_filter())
+        .with_writer(writer);
+    if json {
+        builder
+            .with_ansi(false)
+            .event_format(JsonFormat)
+            .fmt_fields(JsonFields)
+            .init();
+    } else {
+        builder.with_ansi(ansi).init();
+    }
+}
+
+/// `json` selects `log_format: json`.
+pub fn init_logging(runtime_data_dir: &str, json: bool) -> Result<()> {
     let log_dir = PathBuf::from(runtime_data_dir).join("logs");
     fs::create_dir_all(&log_dir)
         .with_context(|| format!("Failed to create log directory: {}", log_dir.display()))?;
     cleanup_old_logs(&log_dir, Utc::now(), LOG_RETENTION_DAYS)?;
 
     let writer = HourlyLogWriter::new(log_dir, LOG_RETENTION_DAYS)?;
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .with_ansi(false)
-        .with_writer(writer)
-        .init();
+    init_with(writer, false, json);
 
     Ok(())
 }
 
-pub fn init_console_logging() {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .init();
+pub fn init_console_logging(json: bool) {
+    init_with(io::stdout, true, json);
+}
+
+/// Collects event or span fields into a JSON object. Numbers and booleans
+/// keep their type; everything else is recorded as a string.
+#[derive(Default)]
+struct JsonVisitor(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+/// Stores span fields as a JSON object, so `JsonFormat` can merge them into
+/// each event.
+struct JsonFields;
+
+impl<'writer> FormatFields<'writer> for JsonFields {
+    fn format_fields<R: tracing_subscriber::field::RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> std::fmt::Result {
+        let mut visitor = JsonVisitor::default();
+        fields.record(&mut visitor);
+        write!(writer, "{}", serde_json::Value::Object(visitor.0))
+    }
+
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &tracing::span::Record<'_>,
+    ) -> std::fmt::Result {
+        let mut visitor = JsonVisitor(
+            serde_json::from_str(&current.fields)
+                .ok()
+                .and_then(|v: serde_json::Value| v.as_object().cloned())
+                .unwrap_or_default(),
+        );
+        fields.record(&mut visitor);
+        current.fields = serde_json::Value::Object(visitor.0).to_string();
+        Ok(())
+    }
+}
+
+/// One JSON object per line: `timestamp`, `level`, `target`, the fields of
+/// the enclosing spans (a turn's `chat_id`, `channel` and `model`), then the
+/// event's own fields (`message`, `tool`, `duration_ms`, ...).
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut line = serde_json::Map::new();
+        line.insert("timestamp".into(), Utc::now().to_rfc3339().into());
+        line.insert(
+            "level".into(),
+            event
+                .metadata()
+                .level()
+                .as_str()
+                .to_ascii_lowercase()
+                .into(),
+        );
+        line.insert("target".into(), event.metadata().target().into());
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                let Some(fields) = extensions.get::<FormattedFields<N>>() else {
+                    continue;
+                };
+                if let Ok(serde_json::Value::Object(fields)) =
+                    serde_json::from_str::<serde_json::Value>(fields)
+                {
+                    line.extend(fields);
+                }
+            }
+        }
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        line.extend(visitor.0);
+        writeln!(writer, "{}", serde_json::Value::Object(line))
+    }
 }
 
 #[derive(Debug)]
@@ -212,6 +340,50 @@ mod tests {
         std::env::temp_dir().join(format!("microclaw_logging_test_{}", Uuid::new_v4()))
     }
 
+    #[test]
+    fn test_json_format_merges_span_fields() {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        let sink = buf.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || SharedBuf(sink.clone()))
+            .event_format(JsonFormat)
+            .fmt_fields(JsonFields)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "turn",
+                chat_id = 42i64,
+                channel = "web",
+                model = tracing::field::Empty
+            );
+            let _guard = span.enter();
+            span.record("model", "claude");
+            tracing::info!(tool = "bash", duration_ms = 12u64, "Tool finished");
+        });
+        let out = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+        assert_eq!(line["level"], "info");
+        assert_eq!(line["chat_id"], 42);
+        assert_eq!(line["channel"], "web");
+        assert_eq!(line["model"], "claude");
+        assert_eq!(line["tool"], "bash");
+        assert_eq!(line["duration_ms"], 12);
+        assert_eq!(line["message"], "Tool finished");
+    }
+
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_parse_log_filename_time() {
         assert!(parse_log_filename_time("microclaw-2026-02-08-10.log").is_some());
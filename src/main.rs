@@ -1,7 +1,8 @@
-use microclaw::config::Config;
+use microclaw::config::{Config, LogFormat};
 use microclaw::error::MicroClawError;
 use microclaw::{
-    builtin_skills, db, doctor, gateway, logging, mcp, memory, runtime, setup, skills,
+    backup, builtin_skills, chat, db, db_crypt, doctor, gateway, logging, mcp, memory,
+    provider_oauth, runtime, setup, skills, tool_runner,
 };
 use std::path::Path;
 use tracing::info;
@@ -13,16 +14,26 @@ fn print_help() {
         r#"MicroClaw v{VERSION}
 
 Usage:
-  microclaw <command>
+  microclaw [--profile <name>] <command>
 
 Commands:
   start      Start runtime (enabled channels)
+  chat       Chat with the agent in the terminal (no channel needed)
+  run        Run one prompt headlessly and print the answer (run "<prompt>")
   setup      Full-screen setup wizard
   doctor     Preflight diagnostics
+  config     Validate the config or encrypt a secret (config validate|encrypt|hash-password)
+  tool       List tools or run one directly (tool list|run <name> --input '<json>')
+  db         Back up, restore, prune or encrypt the database (db backup|restore <dir>, db prune, db encrypt|decrypt)
+  login      Sign in to an LLM provider with OAuth instead of an API key (login <provider> [--logout])
   gateway    Manage service (install/start/stop/status/logs)
   version    Show version
   help       Show this help
 
+Options:
+  --profile <name>  Layer microclaw.config.<name>.yaml over the base config
+                    (same as MICROCLAW_PROFILE=<name>)
+
 Quick Start:
   1) microclaw setup
   2) microclaw doctor
@@ -105,13 +116,46 @@ fn migrate_legacy_runtime_layout(data_root: &Path, runtime_dir: &Path) {
     }
 }
 
+/// Remove `--profile <name>` / `--profile=<name>` from `args`.
+fn take_profile_arg(args: &mut Vec<String>) -> anyhow::Result<Option<String>> {
+    let Some(idx) = args
+        .iter()
+        .position(|a| a == "--profile" || a.starts_with("--profile="))
+    else {
+        return Ok(None);
+    };
+    let arg = args.remove(idx);
+    let profile = match arg.strip_prefix("--profile=") {
+        Some(value) => value.to_string(),
+        None if idx < args.len() => args.remove(idx),
+        None => return Err(anyhow::anyhow!("--profile needs a profile name")),
+    };
+    Ok(Some(profile))
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // Set before anything reads the config; child processes inherit it.
+    if let Some(profile) = take_profile_arg(&mut args)? {
+        std::env::set_var(microclaw::config::PROFILE_ENV, profile);
+    }
     let command = args.get(1).map(|s| s.as_str());
 
+    let mut one_shot = None;
     match command {
-        Some("start") => {}
+        Some("start" | "chat") => {}
+        Some("run") => match chat::OneShot::from_args(&args[2..]) {
+            Ok(Some(parsed)) => one_shot = Some(parsed),
+            Ok(None) => {
+                println!("{}", chat::RUN_USAGE);
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{e}\n\n{}", chat::RUN_USAGE);
+                std::process::exit(2);
+            }
+        },
         Some("gateway") => {
             gateway::handle_gateway_cli(&args[2..])?;
             return Ok(());
@@ -129,6 +173,22 @@ async fn main() -> anyhow::Result<()> {
             doctor::run_cli(&args[2..])?;
             return Ok(());
         }
+        Some("config") => {
+            setup::run_config_cli(&args[2..])?;
+            return Ok(());
+        }
+        Some("tool") => {
+            tool_runner::run_cli(&args[2..]).await?;
+            return Ok(());
+        }
+        Some("db") => {
+            backup::run_cli(&args[2..])?;
+            return Ok(());
+        }
+        Some("login") => {
+            provider_oauth::run_login_cli(&args[2..]).await?;
+            return Ok(());
+        }
         Some("version" | "--version" | "-V") => {
             print_version();
             return Ok(());
@@ -144,8 +204,11 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    // `chat` and `run` work without a messaging channel.
+    let local_mode = matches!(command, Some("chat" | "run"));
     let config = match Config::load() {
         Ok(c) => c,
+        Err(MicroClawError::Config(_)) if local_mode => Config::load_without_channels()?,
         Err(MicroClawError::Config(e)) => {
             eprintln!("Config missing/invalid: {e}");
             eprintln!("Launching setup wizard...");
@@ -159,7 +222,9 @@ async fn main() -> anyhow::Result<()> {
         }
         Err(e) => return Err(e.into()),
     };
-    info!("Starting MicroClaw bot...");
+    if !local_mode {
+        info!("Starting MicroClaw bot...");
+    }
 
     let data_root_dir = config.data_root_dir();
     let runtime_data_dir = config.runtime_data_dir();
@@ -167,12 +232,16 @@ async fn main() -> anyhow::Result<()> {
     migrate_legacy_runtime_layout(&data_root_dir, Path::new(&runtime_data_dir));
     builtin_skills::ensure_builtin_skills(&data_root_dir)?;
 
-    if std::env::var("MICROCLAW_GATEWAY").is_ok() {
-        logging::init_logging(&runtime_data_dir)?;
+    let json_logs = config.log_format == LogFormat::Json;
+    // The chat TUI owns the terminal and `run` owns stdout, so they log to
+    // files.
+    if local_mode || std::env::var("MICROCLAW_GATEWAY").is_ok() {
+        logging::init_logging(&runtime_data_dir, json_logs)?;
     } else {
-        logging::init_console_logging();
+        logging::init_console_logging(json_logs);
     }
 
+    db_crypt::init(&config)?;
     let db = db::Database::new(&runtime_data_dir)?;
     info!("Database initialized");
 
@@ -197,14 +266,35 @@ async fn main() -> anyhow::Result<()> {
     let mut runtime_config = config.clone();
     runtime_config.data_dir = runtime_data_dir;
 
-    runtime::run(
-        runtime_config,
-        db,
-        memory_manager,
-        skill_manager,
-        mcp_manager,
-    )
-    .await?;
+    if let Some(one_shot) = one_shot {
+        chat::run_once(
+            runtime_config,
+            db,
+            memory_manager,
+            skill_manager,
+            mcp_manager,
+            one_shot,
+        )
+        .await?;
+    } else if command == Some("chat") {
+        chat::run(
+            runtime_config,
+            db,
+            memory_manager,
+            skill_manager,
+            mcp_manager,
+        )
+        .await?;
+    } else {
+        runtime::run(
+            runtime_config,
+            db,
+            memory_manager,
+            skill_manager,
+            mcp_manager,
+        )
+        .await?;
+    }
 
     Ok(())
 }
@@ -38,7 +38,6 @@ impl MemoryManager {
         std::fs::write(path, content)
     }
 
-    #[allow(dead_code)]
     pub fn write_chat_memory(&self, chat_id: i64, content: &str) -> std::io::Result<()> {
         let path = self.chat_memory_path(chat_id);
         if let Some(parent) = path.parent() {
@@ -47,6 +46,16 @@ impl MemoryManager {
         std::fs::write(path, content)
     }
 
+    /// Overwrite a chat's memory file, keeping the previous one as
+    /// `AGENTS.md.bak`.
+    pub fn replace_chat_memory(&self, chat_id: i64, content: &str) -> std::io::Result<()> {
+        let path = self.chat_memory_path(chat_id);
+        if path.exists() {
+            std::fs::copy(&path, path.with_extension("md.bak"))?;
+        }
+        self.write_chat_memory(chat_id, content)
+    }
+
     pub fn build_memory_context(&self, chat_id: i64) -> String {
         let mut context = String::new();
 
@@ -69,7 +78,6 @@ impl MemoryManager {
         context
     }
 
-    #[allow(dead_code)]
     pub fn groups_dir(&self) -> &Path {
         &self.data_dir
     }
@@ -138,6 +146,18 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[test]
+    fn test_replace_chat_memory_keeps_backup() {
+        let (mm, dir) = test_memory_manager();
+        mm.replace_chat_memory(42, "first").unwrap();
+        assert!(!mm.chat_memory_path(42).with_extension("md.bak").exists());
+        mm.replace_chat_memory(42, "second").unwrap();
+        assert_eq!(mm.read_chat_memory(42).unwrap(), "second");
+        let backup = mm.chat_memory_path(42).with_extension("md.bak");
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), "first");
+        cleanup(&dir);
+    }
+
     #[test]
     fn test_build_memory_context_empty() {
         let (mm, dir) = test_memory_manager();
@@ -0,0 +1,474 @@
+//! Nightly memory consolidation (`memory_consolidation.enabled`).
+//!
+//! Once a day at `memory_consolidation.hour` (local `timezone`), every chat
+//! with enough structured memories, or with a large memory file, is reviewed
+//! by a cheap model. It merges duplicates and overlapping entries, drops facts
+//! that are stale or contradicted, and rewrites the chat's `AGENTS.md` more
+//! compactly. Merged entries supersede their sources and dropped ones are
+//! archived, so nothing is hard-deleted; the previous memory file is kept as
+//! `AGENTS.md.bak`.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeZone, Utc};
+use tracing::{info, warn};
+
+use crate::db::{call_blocking, Memory};
+use crate::llm::LlmProvider;
+use crate::llm_types::{Message, MessageContent, ResponseSchema};
+use crate::runtime::AppState;
+use crate::structured::request_json;
+
+/// A memory file at least this long is consolidated even when the chat has
+/// few structured memories.
+const LARGE_MEMORY_FILE_CHARS: usize = 4000;
+/// Refuse a result that would drop more than this share of the entries.
+const MAX_EXPIRED_SHARE: f64 = 0.5;
+const REQUEST_TIMEOUT_SECS: u64 = 120;
+
+const CONSOLIDATION_SYSTEM_PROMPT: &str = r#"You maintain an assistant's long-term memory about one chat. Consolidate it so it stays small and relevant.
+
+You get the chat's memory file (markdown notes) and its structured memory entries, each with an id, category, confidence and the date it was last confirmed.
+
+For the entries:
+- Return every entry that is still true and useful, each in exactly one item, with its id in source_ids.
+- Merge duplicates and entries that overlap into one item listing all their ids. Rewrite the content only as much as merging needs.
+- Leave out entries that are outdated, contradicted by a newer entry, or no longer useful (past one-off events, finished tasks).
+- Category must be exactly one of: PROFILE, KNOWLEDGE, EVENT.
+- Never add facts that are not in the input.
+
+For memory_file: return the memory file rewritten with duplicates merged and stale items removed, in the same markdown style. Keep everything else as it is and add nothing new. Return an empty string if there is no memory file."#;
+
+fn consolidation_schema() -> ResponseSchema {
+    ResponseSchema {
+        name: "memory_consolidation".into(),
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "memories": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "content": {"type": "string"},
+                            "category": {"type": "string"},
+                            "source_ids": {"type": "array", "items": {"type": "integer"}},
+                        },
+                        "required": ["content", "category", "source_ids"],
+                    },
+                },
+                "memory_file": {"type": "string"},
+            },
+            "required": ["memories", "memory_file"],
+        }),
+    }
+}
+
+/// Entries replacing one or more existing memories.
+#[derive(Debug, PartialEq)]
+struct Merged {
+    sources: Vec<i64>,
+    content: String,
+    category: String,
+}
+
+/// Changes to a chat's structured memories.
+#[derive(Debug, Default, PartialEq)]
+struct Plan {
+    merged: Vec<Merged>,
+    /// Entries the model dropped as stale.
+    expired: Vec<i64>,
+}
+
+/// Turn the model's reply into changes to `memories` (the chat's active
+/// entries). Ids the model made up or already used are ignored, and items
+/// without a known source are skipped rather than inserted as new facts.
+fn plan(memories: &[Memory], value: &serde_json::Value) -> Result<Plan, String> {
+    let by_id: HashMap<i64, &Memory> = memories.iter().map(|m| (m.id, m)).collect();
+    let mut claimed = HashSet::new();
+    let mut plan = Plan::default();
+    for item in value["memories"].as_array().into_iter().flatten() {
+        let content = item["content"].as_str().unwrap_or("").trim();
+        if content.is_empty() {
+            continue;
+        }
+        let sources: Vec<i64> = item["source_ids"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| id.as_i64())
+            .filter(|id| by_id.contains_key(id) && claimed.insert(*id))
+            .collect();
+        let Some(first) = sources.first().map(|id| by_id[id]) else {
+            continue;
+        };
+        let category = item["category"].as_str().unwrap_or("").trim();
+        let category = if matches!(category, "PROFILE" | "KNOWLEDGE" | "EVENT") {
+            category
+        } else {
+            first.category.as_str()
+        };
+        if sources.len() == 1 && content == first.content.trim() && category == first.category {
+            continue;
+        }
+        plan.merged.push(Merged {
+            sources,
+            content: content.to_string(),
+            category: category.to_string(),
+        });
+    }
+    if claimed.is_empty() && !memories.is_empty() {
+        return Err("reply kept none of the memories".into());
+    }
+    plan.expired = memories
+        .iter()
+        .map(|m| m.id)
+        .filter(|id| !claimed.contains(id))
+        .collect();
+    if plan.expired.len() as f64 > memories.len() as f64 * MAX_EXPIRED_SHARE {
+        return Err(format!(
+            "reply would drop {} of {} memories",
+            plan.expired.len(),
+            memories.len()
+        ));
+    }
+    Ok(plan)
+}
+
+/// The rewritten memory file, if it should replace `current`.
+fn compacted_file(current: &str, reply: &serde_json::Value) -> Option<String> {
+    let rewritten = reply["memory_file"].as_str()?.trim();
+    let current = current.trim();
+    // Never create, empty or grow a memory file.
+    if current.is_empty() || rewritten.is_empty() || rewritten.len() >= current.len() {
+        return None;
+    }
+    Some(format!("{rewritten}\n"))
+}
+
+fn render_memories(memories: &[Memory]) -> String {
+    memories
+        .iter()
+        .map(|m| {
+            format!(
+                "#{} [{}] (confidence {:.2}, last seen {}) {}",
+                m.id,
+                m.category,
+                m.confidence,
+                m.last_seen_at.get(..10).unwrap_or(&m.last_seen_at),
+                m.content
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Next time the local clock in `tz` reads `hour`:00, strictly after `now`.
+fn next_run(now: DateTime<Utc>, tz: chrono_tz::Tz, hour: u32) -> DateTime<Utc> {
+    let local = now.with_timezone(&tz);
+    let mut day = local.date_naive();
+    loop {
+        let at = day
+            .and_hms_opt(hour, 0, 0)
+            .map(|t| tz.from_local_datetime(&t));
+        // A DST gap skips that day's run.
+        if let Some(at) = at.and_then(|t| t.earliest()) {
+            if at > local {
+                return at.with_timezone(&Utc);
+            }
+        }
+        day = day.succ_opt().unwrap_or(day);
+    }
+}
+
+/// Chats with enough memories to consolidate.
+async fn candidate_chats(state: &AppState) -> Vec<i64> {
+    let min = state.config.memory_consolidation.min_memories;
+    let mut chats = call_blocking(state.db.clone(), move |db| {
+        db.get_chat_ids_with_memories(min)
+    })
+    .await
+    .unwrap_or_else(|e| {
+        warn!("Memory consolidation: failed to list chats: {e}");
+        Vec::new()
+    });
+    let groups = std::fs::read_dir(state.memory.groups_dir())
+        .into_iter()
+        .flatten()
+        .flatten();
+    for entry in groups {
+        let Some(chat_id) = entry.file_name().to_str().and_then(|n| n.parse().ok()) else {
+            continue;
+        };
+        let large = state
+            .memory
+            .read_chat_memory(chat_id)
+            .is_some_and(|m| m.len() >= LARGE_MEMORY_FILE_CHARS);
+        if large && !chats.contains(&chat_id) {
+            chats.push(chat_id);
+        }
+    }
+    chats.sort_unstable();
+    chats
+}
+
+async fn consolidate_chat(
+    state: &AppState,
+    llm: &dyn LlmProvider,
+    model: &str,
+    chat_id: i64,
+) -> Result<(), String> {
+    let memories: Vec<Memory> = call_blocking(state.db.clone(), move |db| {
+        db.get_all_memories_for_chat(Some(chat_id))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .into_iter()
+    .filter(|m| !m.is_archived)
+    .collect();
+    let file = state.memory.read_chat_memory(chat_id).unwrap_or_default();
+    if memories.is_empty() && file.trim().is_empty() {
+        return Ok(());
+    }
+
+    let prompt = format!(
+        "Today: {}\n\nMemory file:\n{}\n\nStructured memories:\n{}",
+        Utc::now().format("%Y-%m-%d"),
+        if file.trim().is_empty() {
+            "(none)"
+        } else {
+            file.trim()
+        },
+        if memories.is_empty() {
+            "(none)".to_string()
+        } else {
+            render_memories(&memories)
+        },
+    );
+    let messages = vec![Message {
+        role: "user".into(),
+        content: MessageContent::Text(prompt),
+    }];
+    let reply = tokio::time::timeout(
+        std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS),
+        request_json(
+            llm,
+            CONSOLIDATION_SYSTEM_PROMPT,
+            messages,
+            &consolidation_schema(),
+        ),
+    )
+    .await
+    .map_err(|_| format!("timed out after {REQUEST_TIMEOUT_SECS}s"))?
+    .map_err(|e| e.to_string())?;
+
+    let provider = state.config.llm_provider.clone();
+    let model = model.to_string();
+    let input_tokens = i64::from(reply.usage.input_tokens);
+    let output_tokens = i64::from(reply.usage.output_tokens);
+    let _ = call_blocking(state.db.clone(), move |db| {
+        db.log_llm_usage(
+            chat_id,
+            "memory_consolidation",
+            &provider,
+            &model,
+            input_tokens,
+            output_tokens,
+            "memory_consolidation",
+        )
+        .map(|_| ())
+    })
+    .await;
+
+    let plan = plan(&memories, &reply.value)?;
+    let confidence: HashMap<i64, f64> = memories.iter().map(|m| (m.id, m.confidence)).collect();
+    let (merged, expired) = (plan.merged.len(), plan.expired.len());
+    call_blocking(state.db.clone(), move |db| {
+        for entry in &plan.merged {
+            let confidence = entry
+                .sources
+                .iter()
+                .map(|id| confidence[id])
+                .fold(0.0, f64::max);
+            db.supersede_memory(
+                entry.sources[0],
+                &entry.content,
+                &entry.category,
+                "consolidation",
+                confidence,
+                Some("consolidated"),
+            )?;
+            for id in &entry.sources[1..] {
+                db.archive_memory(*id)?;
+            }
+        }
+        for id in &plan.expired {
+            db.archive_memory(*id)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let rewritten = compacted_file(&file, &reply.value);
+    if let Some(content) = &rewritten {
+        state
+            .memory
+            .replace_chat_memory(chat_id, content)
+            .map_err(|e| format!("failed to write memory file: {e}"))?;
+    }
+    info!(
+        "Memory consolidation: chat {chat_id}: {} memories, {merged} merged, {expired} expired{}",
+        memories.len(),
+        if rewritten.is_some() {
+            ", memory file compacted"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+async fn run_consolidation(state: &AppState) {
+    let model = state
+        .config
+        .memory_consolidation
+        .model
+        .as_deref()
+        .or(state.config.compaction_model.as_deref())
+        .map(str::trim)
+        .filter(|m| !m.is_empty());
+    let cheap = model.map(|model| {
+        crate::llm::create_provider(&crate::router::with_model(&state.config, model, None))
+    });
+    let llm = cheap.as_deref().unwrap_or(state.llm.as_ref());
+    let model = model.unwrap_or(&state.config.model);
+    for chat_id in candidate_chats(state).await {
+        if let Err(e) = consolidate_chat(state, llm, model, chat_id).await {
+            warn!("Memory consolidation: skipped chat {chat_id}: {e}");
+        }
+    }
+}
+
+pub fn spawn_memory_consolidation(state: Arc<AppState>) {
+    if !state.config.memory_consolidation.enabled {
+        return;
+    }
+    tokio::spawn(async move {
+        let tz: chrono_tz::Tz = state.config.timezone.parse().unwrap_or(chrono_tz::Tz::UTC);
+        let hour = state.config.memory_consolidation.hour;
+        info!("Memory consolidation started (daily at {hour:02}:00 {tz})");
+        loop {
+            let wait = (next_run(Utc::now(), tz, hour) - Utc::now())
+                .to_std()
+                .unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            run_consolidation(&state).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(id: i64, content: &str, category: &str) -> Memory {
+        Memory {
+            id,
+            chat_id: Some(100),
+            content: content.into(),
+            category: category.into(),
+            created_at: "2026-01-01T00:00:00Z".into(),
+            updated_at: "2026-01-01T00:00:00Z".into(),
+            embedding_model: None,
+            confidence: 0.7,
+            source: "reflector".into(),
+            last_seen_at: "2026-09-01T00:00:00Z".into(),
+            is_archived: false,
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_merges_and_expires() {
+        let memories = vec![
+            memory(1, "Lives in Berlin", "PROFILE"),
+            memory(2, "User lives in Berlin", "PROFILE"),
+            memory(3, "Prefers Rust", "PROFILE"),
+            memory(4, "Dentist appointment on 2026-03-02", "EVENT"),
+            memory(5, "Works at Acme", "KNOWLEDGE"),
+        ];
+        let reply = serde_json::json!({
+            "memories": [
+                {"content": "User lives in Berlin", "category": "PROFILE", "source_ids": [1, 2]},
+                {"content": "Prefers Rust", "category": "PROFILE", "source_ids": [3]},
+                {"content": "Works at Acme", "category": "PROFILE", "source_ids": [5, 99]},
+                {"content": "Has a cat", "category": "PROFILE", "source_ids": []},
+                {"content": "Again", "category": "PROFILE", "source_ids": [3]},
+            ],
+            "memory_file": "",
+        });
+        let plan = plan(&memories, &reply).unwrap();
+        assert_eq!(
+            plan.merged,
+            vec![
+                Merged {
+                    sources: vec![1, 2],
+                    content: "User lives in Berlin".into(),
+                    category: "PROFILE".into(),
+                },
+                Merged {
+                    sources: vec![5],
+                    content: "Works at Acme".into(),
+                    category: "PROFILE".into(),
+                },
+            ]
+        );
+        assert_eq!(plan.expired, vec![4]);
+    }
+
+    #[test]
+    fn test_plan_refuses_dropping_most_memories() {
+        let memories = vec![
+            memory(1, "a", "KNOWLEDGE"),
+            memory(2, "b", "KNOWLEDGE"),
+            memory(3, "c", "KNOWLEDGE"),
+        ];
+        let keep_one = serde_json::json!({
+            "memories": [{"content": "a", "category": "KNOWLEDGE", "source_ids": [1]}],
+        });
+        assert!(plan(&memories, &keep_one).is_err());
+        assert!(plan(&memories, &serde_json::json!({"memories": []})).is_err());
+        assert_eq!(plan(&[], &serde_json::json!({})).unwrap(), Plan::default());
+    }
+
+    #[test]
+    fn test_compacted_file() {
+        let current = "# Notes\n- likes tea\n- likes tea\n- owns a bike\n";
+        let reply = |file: &str| serde_json::json!({ "memory_file": file });
+        assert_eq!(
+            compacted_file(current, &reply("# Notes\n- likes tea\n- owns a bike")).as_deref(),
+            Some("# Notes\n- likes tea\n- owns a bike\n")
+        );
+        assert_eq!(compacted_file(current, &reply("")), None);
+        assert_eq!(compacted_file(current, &reply(current)), None);
+        assert_eq!(compacted_file("", &reply("# Notes")), None);
+    }
+
+    #[test]
+    fn test_next_run() {
+        let tz: chrono_tz::Tz = "Europe/Berlin".parse().unwrap();
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 0, 30, 0).unwrap();
+        // 02:30 local; 03:00 local is 01:00 UTC the same day.
+        assert_eq!(
+            next_run(now, tz, 3),
+            Utc.with_ymd_and_hms(2026, 10, 15, 1, 0, 0).unwrap()
+        );
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 1, 0, 0).unwrap();
+        assert_eq!(
+            next_run(now, tz, 3),
+            Utc.with_ymd_and_hms(2026, 10, 16, 1, 0, 0).unwrap()
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Prometheus metrics (`metrics:` in the config).
+//!
+//! Counters and histograms live in one process-wide registry that the agent
+//! loop, tool registry, scheduler and usage log record into whether or not
+//! the endpoint is enabled. With `metrics.enabled`, `GET /metrics` on
+//! `metrics.listen` serves them in the Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+
+use tracing::{error, info};
+
+use crate::config::MetricsConfig;
+
+/// Histogram bucket bounds, in seconds.
+const BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+enum Kind {
+    Counter,
+    Histogram,
+}
+
+/// Every metric, in output order: name, kind, help.
+const METRICS: &[(&str, Kind, &str)] = &[
+    (
+        "microclaw_messages_total",
+        Kind::Counter,
+        "Inbound messages handled by the agent, by channel.",
+    ),
+    (
+        "microclaw_llm_requests_total",
+        Kind::Counter,
+        "Agent loop LLM requests, by model and status.",
+    ),
+    (
+        "microclaw_llm_request_duration_seconds",
+        Kind::Histogram,
+        "Agent loop LLM request latency, by model.",
+    ),
+    (
+        "microclaw_llm_tokens_total",
+        Kind::Counter,
+        "LLM tokens used, by model and direction.",
+    ),
+    (
+        "microclaw_tool_calls_total",
+        Kind::Counter,
+        "Tool executions, by tool and status.",
+    ),
+    (
+        "microclaw_tool_duration_seconds",
+        Kind::Histogram,
+        "Tool execution time, by tool.",
+    ),
+    (
+        "microclaw_scheduler_runs_total",
+        Kind::Counter,
+        "Scheduled task runs, by task and status.",
+    ),
+    (
+        "microclaw_scheduler_run_duration_seconds",
+        Kind::Histogram,
+        "Scheduled task run time, by task.",
+    ),
+    (
+        "microclaw_scheduler_failure_alerts_total",
+        Kind::Counter,
+        "Alerts sent for tasks that kept failing, by task.",
+    ),
+    (
+        "microclaw_approvals_total",
+        Kind::Counter,
+        "High-risk tool approval events (requested, granted).",
+    ),
+    (
+        "microclaw_clawhook_deliveries_total",
+        Kind::Counter,
+        "Outbound clawhook deliveries, by event and status.",
+    ),
+    (
+        "microclaw_user_limit_refusals_total",
+        Kind::Counter,
+        "Messages dropped by user_limits, by reason (cooldown, muted).",
+    ),
+];
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Clone, Default)]
+struct Histogram {
+    /// Per-bucket (non-cumulative) counts; the last slot is +Inf.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; BUCKETS.len() + 1];
+        }
+        let slot = BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(BUCKETS.len());
+        self.counts[slot] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    counters: BTreeMap<&'static str, BTreeMap<Labels, u64>>,
+    histograms: BTreeMap<&'static str, BTreeMap<Labels, Histogram>>,
+}
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(Registry::default()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+fn add(name: &'static str, labels: Labels, value: u64) {
+    *registry()
+        .counters
+        .entry(name)
+        .or_default()
+        .entry(labels)
+        .or_default() += value;
+}
+
+fn observe(name: &'static str, labels: Labels, seconds: f64) {
+    registry()
+        .histograms
+        .entry(name)
+        .or_default()
+        .entry(labels)
+        .or_default()
+        .observe(seconds);
+}
+
+fn status(ok: bool) -> String {
+    if ok { "ok" } else { "error" }.to_string()
+}
+
+pub fn message_handled(channel: &str) {
+    add(
+        "microclaw_messages_total",
+        vec![("channel", channel.to_string())],
+        1,
+    );
+}
+
+pub fn llm_request(model: &str, seconds: f64, ok: bool) {
+    add(
+        "microclaw_llm_requests_total",
+        vec![("model", model.to_string()), ("status", status(ok))],
+        1,
+    );
+    observe(
+        "microclaw_llm_request_duration_seconds",
+        vec![("model", model.to_string())],
+        seconds,
+    );
+}
+
+pub fn llm_tokens(model: &str, input_tokens: i64, output_tokens: i64) {
+    for (direction, tokens) in [("input", input_tokens), ("output", output_tokens)] {
+        add(
+            "microclaw_llm_tokens_total",
+            vec![
+                ("model", model.to_string()),
+                ("direction", direction.to_string()),
+            ],
+            tokens.max(0) as u64,
+        );
+    }
+}
+
+pub fn tool_call(tool: &str, seconds: f64, ok: bool) {
+    add(
+        "microclaw_tool_calls_total",
+        vec![("tool", tool.to_string()), ("status", status(ok))],
+        1,
+    );
+    observe(
+        "microclaw_tool_duration_seconds",
+        vec![("tool", tool.to_string())],
+        seconds,
+    );
+}
+
+pub fn scheduler_run(task_id: i64, seconds: f64, success: bool) {
+    add(
+        "microclaw_scheduler_runs_total",
+        vec![("task", task_id.to_string()), ("status", status(success))],
+        1,
+    );
+    observe(
+        "microclaw_scheduler_run_duration_seconds",
+        vec![("task", task_id.to_string())],
+        seconds,
+    );
+}
+
+pub fn scheduler_failure_alert(task_id: i64) {
+    add(
+        "microclaw_scheduler_failure_alerts_total",
+        vec![("task", task_id.to_string())],
+        1,
+    );
+}
+
+/// `event` is `requested` or `granted`.
+pub fn approval(event: &'static str) {
+    add(
+        "microclaw_approvals_total",
+        vec![("event", event.to_string())],
+        1,
+    );
+}
+
+pub fn clawhook_delivery(event: &'static str, ok: bool) {
+    add(
+        "microclaw_clawhook_deliveries_total",
+        vec![("event", event.to_string()), ("status", status(ok))],
+        1,
+    );
+}
+
+/// `reason` is `cooldown` or `muted`.
+pub fn user_limit_refusal(reason: &'static str) {
+    add(
+        "microclaw_user_limit_refusals_total",
+        vec![("reason", reason.to_string())],
+        1,
+    );
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_labels(labels: &[(&str, String)], extra: Option<(&str, String)>) -> String {
+    let parts: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| (*k, v.clone()))
+        .chain(extra)
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(&v)))
+        .collect();
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+/// The registry in the Prometheus text exposition format.
+pub fn render() -> String {
+    let registry = registry();
+    let mut out = String::new();
+    for (name, kind, help) in METRICS {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        match kind {
+            Kind::Counter => {
+                let _ = writeln!(out, "# TYPE {name} counter");
+                for (labels, value) in registry.counters.get(name).into_iter().flatten() {
+                    let _ = writeln!(out, "{name}{} {value}", format_labels(labels, None));
+                }
+            }
+            Kind::Histogram => {
+                let _ = writeln!(out, "# TYPE {name} histogram");
+                for (labels, hist) in registry.histograms.get(name).into_iter().flatten() {
+                    let mut cumulative = 0;
+                    for (i, count) in hist.counts.iter().enumerate() {
+                        cumulative += count;
+                        let le = BUCKETS
+                            .get(i)
+                            .map(|b| b.to_string())
+                            .unwrap_or_else(|| "+Inf".to_string());
+                        let _ = writeln!(
+                            out,
+                            "{name}_bucket{} {cumulative}",
+                            format_labels(labels, Some(("le", le)))
+                        );
+                    }
+                    let labels = format_labels(labels, None);
+                    let _ = writeln!(out, "{name}_sum{labels} {}", hist.sum);
+                    let _ = writeln!(out, "{name}_count{labels} {}", hist.count);
+                }
+            }
+        }
+    }
+    out
+}
+
+async fn metrics_handler() -> impl axum::response::IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        render(),
+    )
+}
+
+pub fn spawn_metrics_server(config: &MetricsConfig) {
+    if !config.enabled {
+        return;
+    }
+    let listen = config.listen.clone();
+    tokio::spawn(async move {
+        let app = axum::Router::new().route("/metrics", axum::routing::get(metrics_handler));
+        let listener = match tokio::net::TcpListener::bind(&listen).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Metrics: cannot listen on {listen}: {e}");
+                return;
+            }
+        };
+        info!("Serving Prometheus metrics on http://{listen}/metrics");
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Metrics server stopped: {e}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counters_and_histograms() {
+        message_handled("metrics-test");
+        message_handled("metrics-test");
+        tool_call("metrics_\"tool\"", 0.02, false);
+        let text = render();
+        assert!(text.contains("# TYPE microclaw_messages_total counter"));
+        assert!(text.contains("microclaw_messages_total{channel=\"metrics-test\"} 2"));
+        assert!(text.contains(
+            "microclaw_tool_calls_total{tool=\"metrics_\\\"tool\\\"\",status=\"error\"} 1"
+        ));
+        assert!(text.contains(
+            "microclaw_tool_duration_seconds_bucket{tool=\"metrics_\\\"tool\\\"\",le=\"0.01\"} 0"
+        ));
+        assert!(text.contains(
+            "microclaw_tool_duration_seconds_bucket{tool=\"metrics_\\\"tool\\\"\",le=\"0.025\"} 1"
+        ));
+        assert!(text.contains(
+            "microclaw_tool_duration_seconds_bucket{tool=\"metrics_\\\"tool\\\"\",le=\"+Inf\"} 1"
+        ));
+        assert!(
+            text.contains("microclaw_tool_duration_seconds_count{tool=\"metrics_\\\"tool\\\"\"} 1")
+        );
+    }
+}
@@ -0,0 +1,494 @@
+//! Per-model capability registry.
+//!
+//! Built-in entries match on model-name prefixes (the longest match wins);
+//! `model_capabilities` in config overrides individual fields per model name.
+//! Unknown models are assumed to support vision, tools and streaming so that
+//! custom endpoints keep working as before.
+//!
+//! [`adapt_provider`] wraps a provider so that turns degrade instead of
+//! failing: images are replaced by a text note, native tool calls fall back to
+//! a ReAct-style `<tool_call>` text protocol, and streaming becomes a single
+//! final chunk.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::config::{Config, ModelCapabilityOverride};
+use crate::error::MicroClawError;
+use crate::llm::LlmProvider;
+use crate::llm_types::{
+    ContentBlock, Message, MessageContent, MessagesResponse, ResponseContentBlock, ResponseSchema,
+    ToolDefinition,
+};
+
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ModelCapabilities {
+    pub vision: bool,
+    pub tool_use: bool,
+    pub streaming: bool,
+    pub prompt_caching: bool,
+    pub structured_output: bool,
+    pub max_context_tokens: u32,
+}
+
+const fn caps(
+    vision: bool,
+    tool_use: bool,
+    prompt_caching: bool,
+    structured_output: bool,
+    max_context_tokens: u32,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        vision,
+        tool_use,
+        streaming: true,
+        prompt_caching,
+        structured_output,
+        max_context_tokens,
+    }
+}
+
+/// Assumed for models missing from the registry.
+pub const UNKNOWN_MODEL: ModelCapabilities = caps(true, true, false, false, 128_000);
+
+const BUILTIN: &[(&str, ModelCapabilities)] = &[
+    ("claude-", caps(true, true, true, false, 200_000)),
+    ("claude-2", caps(false, false, false, false, 100_000)),
+    ("claude-instant", caps(false, false, false, false, 100_000)),
+    ("gpt-5", caps(true, true, false, true, 400_000)),
+    ("gpt-4.1", caps(true, true, false, true, 1_047_576)),
+    ("gpt-4o", caps(true, true, false, true, 128_000)),
+    ("gpt-4-turbo", caps(true, true, false, false, 128_000)),
+    ("gpt-4", caps(false, true, false, false, 8_192)),
+    ("gpt-3.5", caps(false, true, false, false, 16_385)),
+    ("o1", caps(true, true, false, true, 200_000)),
+    ("o1-mini", caps(false, false, false, false, 128_000)),
+    ("o3", caps(true, true, false, true, 200_000)),
+    ("o3-mini", caps(false, true, false, true, 200_000)),
+    ("o4-mini", caps(true, true, false, true, 200_000)),
+    ("codex-", caps(true, true, false, true, 400_000)),
+    ("gemini-", caps(true, true, false, true, 1_048_576)),
+    ("deepseek-", caps(false, true, false, false, 128_000)),
+    ("grok-", caps(true, true, false, true, 131_072)),
+    ("kimi-", caps(false, true, false, false, 128_000)),
+    ("moonshot-", caps(false, true, false, false, 128_000)),
+    ("glm-", caps(false, true, false, false, 128_000)),
+    ("glm-4v", caps(true, false, false, false, 8_192)),
+    ("qwen", caps(false, true, false, false, 128_000)),
+    ("qwen-vl", caps(true, false, false, false, 32_768)),
+    ("qwen2.5-vl", caps(true, true, false, false, 128_000)),
+    ("mistral-", caps(false, true, false, false, 128_000)),
+    ("pixtral", caps(true, true, false, false, 128_000)),
+    ("llama3", caps(false, true, false, false, 128_000)),
+    ("llama3.2-vision", caps(true, false, false, false, 128_000)),
+    ("llava", caps(true, false, false, false, 4_096)),
+    ("gemma", caps(false, false, false, false, 8_192)),
+    ("phi", caps(false, false, false, false, 128_000)),
+];
+
+/// Names to try against the table: the full id, then every suffix after a
+/// `/` or `.` so that `anthropic/claude-sonnet-4` (OpenRouter) and
+/// `us.anthropic.claude-sonnet-4` (Bedrock) resolve like `claude-sonnet-4`.
+fn candidates(model: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(model).chain(
+        model
+            .char_indices()
+            .filter(|(_, c)| *c == '/' || *c == '.')
+            .map(move |(i, _)| &model[i + 1..]),
+    )
+}
+
+/// Built-in capabilities for a model id, `None` when the registry has no entry.
+pub fn builtin(model: &str) -> Option<ModelCapabilities> {
+    let model = model.trim().to_ascii_lowercase();
+    candidates(&model)
+        .flat_map(|name| {
+            BUILTIN
+                .iter()
+                .filter(move |(prefix, _)| name.starts_with(prefix))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, caps)| *caps)
+}
+
+/// Capabilities for `model` with config overrides applied.
+pub fn lookup(
+    model: &str,
+    overrides: &HashMap<String, ModelCapabilityOverride>,
+) -> ModelCapabilities {
+    let mut caps = builtin(model).unwrap_or(UNKNOWN_MODEL);
+    if let Some(o) = overrides.get(model.trim()) {
+        caps.vision = o.vision.unwrap_or(caps.vision);
+        caps.tool_use = o.tool_use.unwrap_or(caps.tool_use);
+        caps.streaming = o.streaming.unwrap_or(caps.streaming);
+        caps.prompt_caching = o.prompt_caching.unwrap_or(caps.prompt_caching);
+        caps.structured_output = o.structured_output.unwrap_or(caps.structured_output);
+        caps.max_context_tokens = o.max_context_tokens.unwrap_or(caps.max_context_tokens);
+    }
+    caps
+}
+
+/// Capabilities of the configured chat model.
+pub fn for_config(config: &Config) -> ModelCapabilities {
+    lookup(&config.model, &config.model_capabilities)
+}
+
+/// Human-readable notes on features the model lacks and how they degrade.
+pub fn limitations(model: &str, caps: &ModelCapabilities) -> Vec<String> {
+    let mut notes = Vec::new();
+    if !caps.vision {
+        notes.push(format!(
+            "{model} cannot view images; attached images are replaced by a text note"
+        ));
+    }
+    if !caps.tool_use {
+        notes.push(format!(
+            "{model} has no native tool calling; tools run through a text-based <tool_call> fallback"
+        ));
+    }
+    if !caps.streaming {
+        notes.push(format!(
+            "{model} does not stream; replies arrive in one piece"
+        ));
+    }
+    notes
+}
+
+/// Note prepended to a reply when the user sent an image the model cannot see.
+pub fn image_ignored_notice(model: &str) -> String {
+    format!(
+        "Note: the current model ({model}) can't view images, so the attached image was ignored."
+    )
+}
+
+/// Wrap `inner` so requests fit what the model supports. Returns `inner`
+/// unchanged for fully capable models.
+pub fn adapt_provider(inner: Box<dyn LlmProvider>, config: &Config) -> Box<dyn LlmProvider> {
+    let caps = for_config(config);
+    if caps.vision && caps.tool_use && caps.streaming {
+        return inner;
+    }
+    Box::new(CapabilityAdapter {
+        inner,
+        model: config.model.clone(),
+        caps,
+    })
+}
+
+struct CapabilityAdapter {
+    inner: Box<dyn LlmProvider>,
+    model: String,
+    caps: ModelCapabilities,
+}
+
+impl CapabilityAdapter {
+    fn prepare(&self, messages: Vec<Message>, react: bool) -> Vec<Message> {
+        messages
+            .into_iter()
+            .map(|mut msg| {
+                if let MessageContent::Blocks(blocks) = msg.content {
+                    msg.content = MessageContent::Blocks(
+                        blocks
+                            .into_iter()
+                            .map(|block| self.convert_block(block, react))
+                            .collect(),
+                    );
+                }
+                msg
+            })
+            .collect()
+    }
+
+    fn convert_block(&self, block: ContentBlock, react: bool) -> ContentBlock {
+        match block {
+            ContentBlock::Image { .. } if !self.caps.vision => ContentBlock::Text {
+                text: format!("[image omitted: {} cannot view images]", self.model),
+            },
+            ContentBlock::ToolUse { name, input, .. } if react => ContentBlock::Text {
+                text: format!(
+                    "{TOOL_CALL_OPEN}{}{TOOL_CALL_CLOSE}",
+                    serde_json::json!({"name": name, "input": input})
+                ),
+            },
+            ContentBlock::ToolResult {
+                content, is_error, ..
+            } if react => {
+                let status = if is_error == Some(true) {
+                    " error=\"true\""
+                } else {
+                    ""
+                };
+                ContentBlock::Text {
+                    text: format!("<tool_result{status}>\n{content}\n</tool_result>"),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for CapabilityAdapter {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let react = !self.caps.tool_use && tools.as_ref().is_some_and(|t| !t.is_empty());
+        let messages = self.prepare(messages, react);
+        if !react {
+            return self.inner.send_message(system, messages, tools).await;
+        }
+        let system = format!(
+            "{system}\n\n{}",
+            react_tools_section(tools.as_deref().unwrap_or_default())
+        );
+        let response = self.inner.send_message(&system, messages, None).await?;
+        Ok(parse_react_response(response))
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        // Text-protocol tool calls must be parsed out before anything is shown,
+        // so the fallback and non-streaming models both reply in one chunk.
+        if self.caps.streaming && self.caps.tool_use {
+            let messages = self.prepare(messages, false);
+            return self
+                .inner
+                .send_message_stream(system, messages, tools, text_tx)
+                .await;
+        }
+        let response = self.send_message(system, messages, tools).await?;
+        if let Some(tx) = text_tx {
+            for block in &response.content {
+                if let ResponseContentBlock::Text { text } = block {
+                    let _ = tx.send(text.clone());
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    async fn send_message_json(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        schema: &ResponseSchema,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        let messages = self.prepare(messages, false);
+        self.inner.send_message_json(system, messages, schema).await
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, MicroClawError> {
+        self.inner.embed(inputs).await
+    }
+}
+
+/// System prompt section describing the text tool-call protocol.
+fn react_tools_section(tools: &[ToolDefinition]) -> String {
+    let mut out = format!(
+        "# Tools\n\nThis model has no native tool calling. To use a tool, end your reply with one block per call:\n{TOOL_CALL_OPEN}{{\"name\": \"<tool name>\", \"input\": {{...}}}}{TOOL_CALL_CLOSE}\nThe input must be JSON matching the tool's schema. Results come back in the next message inside <tool_result> tags. Reply normally when no tool is needed.\n\nAvailable tools:\n"
+    );
+    for tool in tools {
+        out.push_str(&format!(
+            "\n- {}: {}\n  input schema: {}\n",
+            tool.name, tool.description, tool.input_schema
+        ));
+    }
+    out
+}
+
+/// Turn `<tool_call>` blocks in the response text into tool-use blocks.
+fn parse_react_response(mut response: MessagesResponse) -> MessagesResponse {
+    let mut content = Vec::new();
+    let mut found = false;
+    for block in response.content {
+        let ResponseContentBlock::Text { text } = block else {
+            content.push(block);
+            continue;
+        };
+        let mut rest = text.as_str();
+        let mut prose = String::new();
+        let mut calls = Vec::new();
+        while let Some(start) = rest.find(TOOL_CALL_OPEN) {
+            let body_start = start + TOOL_CALL_OPEN.len();
+            let (body, next) = match rest[body_start..].find(TOOL_CALL_CLOSE) {
+                Some(end) => (
+                    &rest[body_start..body_start + end],
+                    &rest[body_start + end + TOOL_CALL_CLOSE.len()..],
+                ),
+                None => (&rest[body_start..], ""),
+            };
+            let parsed = serde_json::from_str::<serde_json::Value>(body.trim())
+                .ok()
+                .and_then(|v| {
+                    let name = v.get("name")?.as_str()?.to_string();
+                    let input = v
+                        .get("input")
+                        .cloned()
+                        .unwrap_or_else(|| serde_json::json!({}));
+                    Some((name, input))
+                });
+            prose.push_str(&rest[..start]);
+            match parsed {
+                Some((name, input)) => calls.push(ResponseContentBlock::ToolUse {
+                    id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                    name,
+                    input,
+                }),
+                // Leave malformed calls visible rather than silently dropping them.
+                None => prose.push_str(&rest[start..rest.len() - next.len()]),
+            }
+            rest = next;
+        }
+        prose.push_str(rest);
+        if !prose.trim().is_empty() {
+            content.push(ResponseContentBlock::Text {
+                text: prose.trim().to_string(),
+            });
+        }
+        found |= !calls.is_empty();
+        content.extend(calls);
+    }
+    if found {
+        response.stop_reason = Some("tool_use".into());
+    }
+    response.content = content;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup_prefers_longest_prefix() {
+        assert!(builtin("claude-sonnet-4-5-20250929").unwrap().vision);
+        assert!(builtin("anthropic/claude-opus-4").unwrap().prompt_caching);
+        assert!(builtin("us.anthropic.claude-3-5-haiku-20241022-v1:0").is_some());
+        assert!(builtin("gpt-4o-mini").unwrap().vision);
+        assert!(!builtin("gpt-4").unwrap().vision);
+        assert!(!builtin("o1-mini").unwrap().tool_use);
+        assert!(builtin("o1-preview").unwrap().tool_use);
+        assert!(builtin("llama3.2-vision:11b").unwrap().vision);
+        assert!(!builtin("llama3.1:8b").unwrap().vision);
+        assert_eq!(builtin("my-finetune"), None);
+    }
+
+    #[test]
+    fn test_lookup_applies_overrides() {
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "my-finetune".to_string(),
+            ModelCapabilityOverride {
+                tool_use: Some(false),
+                max_context_tokens: Some(32_000),
+                ..Default::default()
+            },
+        );
+        let caps = lookup("my-finetune", &overrides);
+        assert!(caps.vision);
+        assert!(!caps.tool_use);
+        assert_eq!(caps.max_context_tokens, 32_000);
+        assert_eq!(lookup("other", &overrides), UNKNOWN_MODEL);
+    }
+
+    #[test]
+    fn test_parse_react_response_extracts_tool_calls() {
+        let response = MessagesResponse {
+            content: vec![ResponseContentBlock::Text {
+                text: "Let me check.\n<tool_call>{\"name\": \"bash\", \"input\": {\"command\": \"ls\"}}</tool_call>".into(),
+            }],
+            stop_reason: Some("end_turn".into()),
+            usage: None,
+            served_by: None,
+        };
+        let parsed = parse_react_response(response);
+        assert_eq!(parsed.stop_reason.as_deref(), Some("tool_use"));
+        assert!(matches!(
+            &parsed.content[0],
+            ResponseContentBlock::Text { text } if text == "Let me check."
+        ));
+        assert!(matches!(
+            &parsed.content[1],
+            ResponseContentBlock::ToolUse { name, input, .. }
+                if name == "bash" && input["command"] == "ls"
+        ));
+
+        let malformed = parse_react_response(MessagesResponse {
+            content: vec![ResponseContentBlock::Text {
+                text: "<tool_call>not json</tool_call>".into(),
+            }],
+            stop_reason: Some("end_turn".into()),
+            usage: None,
+            served_by: None,
+        });
+        assert_eq!(malformed.stop_reason.as_deref(), Some("end_turn"));
+        assert!(matches!(
+            &malformed.content[0],
+            ResponseContentBlock::Text { text } if text.contains("not json")
+        ));
+    }
+
+    #[test]
+    fn test_prepare_flattens_tool_blocks_and_images() {
+        let adapter = CapabilityAdapter {
+            inner: Box::new(NoopProvider),
+            model: "gemma2".into(),
+            caps: builtin("gemma2").unwrap(),
+        };
+        let messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::Blocks(vec![
+                ContentBlock::Image {
+                    source: crate::llm_types::ImageSource {
+                        source_type: "base64".into(),
+                        media_type: "image/png".into(),
+                        data: "AAAA".into(),
+                    },
+                },
+                ContentBlock::ToolResult {
+                    tool_use_id: "t1".into(),
+                    content: "done".into(),
+                    is_error: None,
+                },
+            ]),
+        }];
+        let prepared = adapter.prepare(messages, true);
+        let MessageContent::Blocks(blocks) = &prepared[0].content else {
+            panic!("expected blocks");
+        };
+        assert!(
+            matches!(&blocks[0], ContentBlock::Text { text } if text.contains("image omitted"))
+        );
+        assert!(
+            matches!(&blocks[1], ContentBlock::Text { text } if text.contains("<tool_result>\ndone"))
+        );
+    }
+
+    struct NoopProvider;
+
+    #[async_trait]
+    impl LlmProvider for NoopProvider {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            Err(MicroClawError::LlmApi("noop".into()))
+        }
+    }
+}
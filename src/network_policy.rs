@@ -0,0 +1,630 @@
+//! Outbound network policy shared by the tools that reach the network.
+//!
+//! `web_fetch` and `browser` check their target URLs directly. `bash` cannot be
+//! inspected, so when `bash_proxy` is on it gets `HTTP(S)_PROXY` pointing at a
+//! local filtering proxy. The proxy credentials carry the chat id and a random
+//! token minted for that chat, so per-chat postures apply there too and a
+//! command cannot claim another chat's posture by naming its id.
+//!
+//! Domain entries match the host and its subdomains. The SSRF guard resolves
+//! hostnames up front and refuses any name with a loopback, private (RFC 1918),
+//! link-local, CGNAT, site-local or unique-local address, including IPv4
+//! addresses embedded in IPv6 (mapped, compatible, 6to4, NAT64, Teredo);
+//! callers then connect to the checked addresses so a second lookup cannot
+//! rebind the name.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use base64::Engine;
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+use crate::config::{NetworkPolicyConfig, NetworkPosture};
+
+const MAX_REDIRECTS: usize = 5;
+const MAX_PROXY_HEAD_BYTES: usize = 16 * 1024;
+
+/// Posture for a chat: its override, else the configured default.
+pub fn posture_for(policy: &NetworkPolicyConfig, chat_id: Option<i64>) -> NetworkPosture {
+    chat_id
+        .and_then(|id| policy.chat_postures.get(&id).copied())
+        .unwrap_or(policy.posture)
+}
+
+fn normalize_domain(entry: &str) -> String {
+    entry
+        .trim()
+        .trim_start_matches("*.")
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+fn domain_matches(host: &str, entries: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    entries.iter().map(|e| normalize_domain(e)).any(|d| {
+        !d.is_empty()
+            && (host == d
+                || host
+                    .strip_suffix(d.as_str())
+                    .is_some_and(|rest| rest.ends_with('.')))
+    })
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b == 18 || b == 19))
+}
+
+/// IPv4 address carried inside an IPv6 one: mapped (`::ffff:a.b.c.d`),
+/// compatible (`::a.b.c.d`), 6to4 (`2002::/16`), NAT64 (`64:ff9b::/96`) or the
+/// Teredo client (`2001::/32`).
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let seg = ip.segments();
+    let v4 = |hi: u16, lo: u16| Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo));
+    match seg {
+        [0, 0, 0, 0, 0, 0xffff, hi, lo] | [0, 0, 0, 0, 0, 0, hi, lo] => Some(v4(hi, lo)),
+        [0x2002, hi, lo, ..] => Some(v4(hi, lo)),
+        [0x64, 0xff9b, 0, 0, 0, 0, hi, lo] => Some(v4(hi, lo)),
+        [0x2001, 0, .., hi, lo] => Some(v4(!hi, !lo)),
+        _ => None,
+    }
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = embedded_v4(ip) {
+        return is_private_v4(v4);
+    }
+    let [first, second, ..] = ip.segments();
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || (first & 0xffc0) == 0xfec0
+        || (first == 0x64 && second == 0xff9b)
+        || (first == 0x2001 && second == 0x0db8)
+}
+
+/// Loopback, private, link-local and other addresses that are not on the
+/// public internet.
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_private_v4(v4),
+        IpAddr::V6(v6) => is_private_v6(v6),
+    }
+}
+
+/// Apply the domain lists for a posture. DNS is not consulted.
+fn check_host(
+    policy: &NetworkPolicyConfig,
+    posture: NetworkPosture,
+    host: &str,
+) -> Result<(), String> {
+    if domain_matches(host, &policy.deny_domains) {
+        return Err(format!("{host} is on the network deny list"));
+    }
+    let allowed = domain_matches(host, &policy.allow_domains);
+    match posture {
+        NetworkPosture::Open => Ok(()),
+        NetworkPosture::Strict if !allowed => Err(format!(
+            "{host} is not on the network allow list (strict posture)"
+        )),
+        NetworkPosture::Standard if !allowed && !policy.allow_domains.is_empty() => {
+            Err(format!("{host} is not on the network allow list"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn guard_private(policy: &NetworkPolicyConfig, posture: NetworkPosture) -> bool {
+    match posture {
+        NetworkPosture::Open => false,
+        NetworkPosture::Standard => policy.block_private_networks,
+        NetworkPosture::Strict => true,
+    }
+}
+
+fn blocked(source: &str, chat_id: Option<i64>, reason: String) -> String {
+    let chat = chat_id.map_or_else(|| "-".to_string(), |id| id.to_string());
+    warn!("Network policy blocked {source} request (chat {chat}): {reason}");
+    format!("Blocked by network policy: {reason}")
+}
+
+/// Check `host:port` for a chat. Returns the addresses to connect to, or an
+/// empty list when no guard applies and the caller may resolve normally.
+pub async fn check_target(
+    policy: &NetworkPolicyConfig,
+    chat_id: Option<i64>,
+    source: &str,
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>, String> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let posture = posture_for(policy, chat_id);
+    check_host(policy, posture, host).map_err(|r| blocked(source, chat_id, r))?;
+    let guard = guard_private(policy, posture);
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if guard && is_private_ip(ip) {
+            return Err(blocked(
+                source,
+                chat_id,
+                format!("{ip} is a private or local address"),
+            ));
+        }
+        return Ok(vec![SocketAddr::new(ip, port)]);
+    }
+    if !guard {
+        return Ok(Vec::new());
+    }
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("Failed to resolve {host}: {e}"))?
+        .collect();
+    if let Some(private) = addrs.iter().find(|a| is_private_ip(a.ip())) {
+        return Err(blocked(
+            source,
+            chat_id,
+            format!("{host} resolves to private address {}", private.ip()),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Check an http(s) URL. Returns the parsed URL and the addresses to pin.
+pub async fn check_url(
+    policy: &NetworkPolicyConfig,
+    chat_id: Option<i64>,
+    source: &str,
+    url: &str,
+) -> Result<(reqwest::Url, Vec<SocketAddr>), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {url}: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme: {}", parsed.scheme()));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| format!("URL has no host: {url}"))?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let addrs = check_target(policy, chat_id, source, host, port).await?;
+    Ok((parsed, addrs))
+}
+
+/// GET a URL under the policy, checking every redirect hop and pinning each
+/// connection to the addresses that passed the check.
+pub async fn guarded_get(
+    policy: &NetworkPolicyConfig,
+    chat_id: Option<i64>,
+    source: &str,
+    url: &str,
+    timeout: Duration,
+    user_agent: &str,
+) -> Result<reqwest::Response, String> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let (parsed, addrs) = check_url(policy, chat_id, source, &url).await?;
+        let mut builder = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(user_agent);
+        if let Some(domain) = parsed.domain() {
+            if !addrs.is_empty() {
+                builder = builder.resolve_to_addrs(domain, &addrs);
+            }
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        let resp = client
+            .get(parsed.clone())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if resp.status().is_redirection() {
+            if let Some(location) = resp
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+            {
+                url = parsed
+                    .join(location)
+                    .map_err(|e| format!("Invalid redirect location: {e}"))?
+                    .to_string();
+                continue;
+            }
+        }
+        return Ok(resp);
+    }
+    Err(format!("Too many redirects (max {MAX_REDIRECTS})"))
+}
+
+fn proxies() -> &'static Mutex<Vec<(NetworkPolicyConfig, SocketAddr)>> {
+    static PROXIES: OnceLock<Mutex<Vec<(NetworkPolicyConfig, SocketAddr)>>> = OnceLock::new();
+    PROXIES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Start (once per policy) the loopback filtering proxy and return its address.
+/// Must be called from within a Tokio runtime.
+pub fn ensure_proxy(policy: &NetworkPolicyConfig) -> Result<SocketAddr, String> {
+    let mut running = proxies().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, addr)) = running.iter().find(|(p, _)| p == policy) {
+        return Ok(*addr);
+    }
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.set_nonblocking(true).map(|_| l))
+        .map_err(|e| format!("Failed to start network policy proxy: {e}"))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let listener = TcpListener::from_std(listener).map_err(|e| e.to_string())?;
+    let shared = Arc::new(policy.clone());
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let policy = shared.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_proxy_connection(&policy, stream).await {
+                    debug!("Network policy proxy connection ended: {e}");
+                }
+            });
+        }
+    });
+    running.push((policy.clone(), addr));
+    Ok(addr)
+}
+
+fn chat_tokens() -> &'static Mutex<HashMap<i64, String>> {
+    static TOKENS: OnceLock<Mutex<HashMap<i64, String>>> = OnceLock::new();
+    TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Proxy password for a chat, minted on first use and kept for the process.
+fn chat_token(chat_id: i64) -> Option<String> {
+    let mut tokens = chat_tokens().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(token) = tokens.get(&chat_id) {
+        return Some(token.clone());
+    }
+    let mut bytes = [0u8; 24];
+    SystemRandom::new().fill(&mut bytes).ok()?;
+    let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    tokens.insert(chat_id, token.clone());
+    Some(token)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Proxy URL for a chat's shell commands, or `None` when `bash_proxy` is off.
+/// Errors when the proxy can't be started, so callers don't run unfiltered.
+pub fn proxy_url_for_chat(
+    policy: &NetworkPolicyConfig,
+    chat_id: Option<i64>,
+) -> Result<Option<String>, String> {
+    if !policy.bash_proxy {
+        return Ok(None);
+    }
+    let addr = ensure_proxy(policy)?;
+    Ok(Some(match chat_id {
+        Some(id) => {
+            let token = chat_token(id).ok_or("Failed to mint a network policy proxy token")?;
+            format!("http://chat_{id}:{token}@{addr}")
+        }
+        None => format!("http://{addr}"),
+    }))
+}
+
+fn chat_from_proxy_auth(value: &str) -> Option<i64> {
+    let encoded = value.trim().strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    let chat_id: i64 = user.strip_prefix("chat_")?.parse().ok()?;
+    let tokens = chat_tokens().lock().unwrap_or_else(|e| e.into_inner());
+    let expected = tokens.get(&chat_id)?;
+    constant_time_eq(password.as_bytes(), expected.as_bytes()).then_some(chat_id)
+}
+
+fn split_host_port(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (host, tail) = rest.split_once(']')?;
+        let port = match tail.strip_prefix(':') {
+            Some(p) => p.parse().ok()?,
+            None => default_port,
+        };
+        return Some((host.to_string(), port));
+    }
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None => Some((authority.to_string(), default_port)),
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    let resp = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(resp.as_bytes()).await
+}
+
+async fn connect(addrs: &[SocketAddr], host: &str, port: u16) -> std::io::Result<TcpStream> {
+    if addrs.is_empty() {
+        TcpStream::connect((host, port)).await
+    } else {
+        TcpStream::connect(addrs).await
+    }
+}
+
+async fn handle_proxy_connection(
+    policy: &NetworkPolicyConfig,
+    mut client: TcpStream,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let head_end = loop {
+        let mut chunk = [0u8; 4096];
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_PROXY_HEAD_BYTES {
+            return respond(&mut client, "431 Request Header Fields Too Large", "").await;
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.split("\r\n").filter(|l| !l.is_empty());
+    let request_line = lines.next().unwrap_or_default();
+    let headers: Vec<&str> = lines.collect();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return respond(&mut client, "400 Bad Request", "malformed request line").await;
+    };
+    let chat_id = headers.iter().find_map(|h| {
+        let (name, value) = h.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("proxy-authorization")
+            .then(|| chat_from_proxy_auth(value))
+            .flatten()
+    });
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        let Some((host, port)) = split_host_port(target, 443) else {
+            return respond(&mut client, "400 Bad Request", "invalid CONNECT target").await;
+        };
+        let addrs = match check_target(policy, chat_id, "bash", &host, port).await {
+            Ok(addrs) => addrs,
+            Err(reason) => return respond(&mut client, "403 Forbidden", &reason).await,
+        };
+        let mut upstream = match connect(&addrs, &host, port).await {
+            Ok(s) => s,
+            Err(e) => return respond(&mut client, "502 Bad Gateway", &e.to_string()).await,
+        };
+        client
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await?;
+        upstream.write_all(&buf[head_end..]).await?;
+        tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+        return Ok(());
+    }
+
+    let url = match reqwest::Url::parse(target) {
+        Ok(url) if url.scheme() == "http" && url.host_str().is_some() => url,
+        _ => {
+            return respond(
+                &mut client,
+                "400 Bad Request",
+                "expected an absolute http URL",
+            )
+            .await
+        }
+    };
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs = match check_target(policy, chat_id, "bash", &host, port).await {
+        Ok(addrs) => addrs,
+        Err(reason) => return respond(&mut client, "403 Forbidden", &reason).await,
+    };
+    let mut upstream = match connect(&addrs, host.trim_matches(['[', ']']), port).await {
+        Ok(s) => s,
+        Err(e) => return respond(&mut client, "502 Bad Gateway", &e.to_string()).await,
+    };
+    // One request per connection, so a kept-alive client cannot switch hosts
+    // without another policy check.
+    let mut forwarded = format!(
+        "{method} {}{} {version}\r\n",
+        url.path(),
+        url.query().map(|q| format!("?{q}")).unwrap_or_default()
+    );
+    for header in &headers {
+        let name = header.split(':').next().unwrap_or_default().trim();
+        if name.to_ascii_lowercase().starts_with("proxy-")
+            || name.eq_ignore_ascii_case("connection")
+            || name.eq_ignore_ascii_case("keep-alive")
+        {
+            continue;
+        }
+        forwarded.push_str(header);
+        forwarded.push_str("\r\n");
+    }
+    forwarded.push_str("Connection: close\r\n\r\n");
+    upstream.write_all(forwarded.as_bytes()).await?;
+    upstream.write_all(&buf[head_end..]).await?;
+    tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> NetworkPolicyConfig {
+        NetworkPolicyConfig::default()
+    }
+
+    #[test]
+    fn test_domain_matching_and_private_ips() {
+        let entries = vec!["example.com".to_string(), "*.corp.internal".to_string()];
+        assert!(domain_matches("example.com", &entries));
+        assert!(domain_matches("API.Example.com.", &entries));
+        assert!(domain_matches("git.corp.internal", &entries));
+        assert!(!domain_matches("badexample.com", &entries));
+        assert!(!domain_matches("example.org", &entries));
+
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "::127.0.0.1",
+            "::a9fe:a9fe",
+            "2002:7f00:1::",
+            "2002:c0a8:101::1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::1",
+            "2001:0:4136:e378:8000:63bf:f5ff:fffe",
+            "fec0::1",
+            "ff02::1",
+        ] {
+            assert!(is_private_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "1.1.1.1",
+            "93.184.216.34",
+            "2606:4700:4700::1111",
+            "2002:0808:0808::1",
+            "64:ff9b::808:808",
+        ] {
+            assert!(!is_private_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_target_postures_and_lists() {
+        let mut p = policy();
+        assert!(check_target(&p, None, "test", "8.8.8.8", 443).await.is_ok());
+        let err = check_target(&p, None, "test", "169.254.169.254", 80)
+            .await
+            .unwrap_err();
+        assert!(err.contains("Blocked by network policy"));
+
+        p.deny_domains = vec!["8.8.4.4".into()];
+        assert!(check_target(&p, None, "test", "8.8.4.4", 443)
+            .await
+            .is_err());
+
+        p.chat_postures.insert(7, NetworkPosture::Open);
+        assert!(check_target(&p, Some(7), "test", "127.0.0.1", 80)
+            .await
+            .is_ok());
+        assert!(check_target(&p, Some(7), "test", "8.8.4.4", 443)
+            .await
+            .is_err());
+
+        p.chat_postures.insert(8, NetworkPosture::Strict);
+        p.allow_domains = vec!["1.1.1.1".into()];
+        assert!(check_target(&p, Some(8), "test", "1.1.1.1", 443)
+            .await
+            .is_ok());
+        assert!(check_target(&p, Some(8), "test", "8.8.8.8", 443)
+            .await
+            .is_err());
+
+        assert!(check_url(&p, None, "test", "file:///etc/passwd")
+            .await
+            .is_err());
+        assert!(check_url(&p, None, "test", "http://[::1]:8080/")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_filters_connect_by_chat_posture() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut s, _)) = upstream.accept().await {
+                let _ = s.write_all(b"hello").await;
+            }
+        });
+
+        let mut p = policy();
+        p.bash_proxy = true;
+        p.chat_postures.insert(5, NetworkPosture::Open);
+        let proxy = ensure_proxy(&p).unwrap();
+        assert_eq!(ensure_proxy(&p).unwrap(), proxy);
+        let proxy_url =
+            reqwest::Url::parse(&proxy_url_for_chat(&p, Some(5)).unwrap().unwrap()).unwrap();
+        assert_eq!(proxy_url.username(), "chat_5");
+        let token = proxy_url.password().unwrap().to_string();
+        assert!(token.len() >= 32);
+        assert_eq!(
+            proxy_url_for_chat(&p, Some(5)).unwrap().unwrap(),
+            proxy_url.to_string().trim_end_matches('/')
+        );
+
+        let request = |auth: &str| {
+            format!("CONNECT {upstream_addr} HTTP/1.1\r\nHost: {upstream_addr}\r\n{auth}\r\n")
+        };
+        let mut denied = TcpStream::connect(proxy).await.unwrap();
+        denied.write_all(request("").as_bytes()).await.unwrap();
+        let mut out = String::new();
+        denied.read_to_string(&mut out).await.unwrap();
+        assert!(out.starts_with("HTTP/1.1 403"));
+        assert!(out.contains("private or local address"));
+
+        // Naming the chat without its token gets the default posture.
+        let forged = base64::engine::general_purpose::STANDARD.encode("chat_5:x");
+        let mut spoofed = TcpStream::connect(proxy).await.unwrap();
+        spoofed
+            .write_all(request(&format!("Proxy-Authorization: Basic {forged}\r\n")).as_bytes())
+            .await
+            .unwrap();
+        let mut out = String::new();
+        spoofed.read_to_string(&mut out).await.unwrap();
+        assert!(out.starts_with("HTTP/1.1 403"));
+
+        let creds = base64::engine::general_purpose::STANDARD.encode(format!("chat_5:{token}"));
+        let mut allowed = TcpStream::connect(proxy).await.unwrap();
+        allowed
+            .write_all(request(&format!("Proxy-Authorization: Basic {creds}\r\n")).as_bytes())
+            .await
+            .unwrap();
+        let mut buf = vec![0u8; 64];
+        let mut got = Vec::new();
+        while !got.ends_with(b"hello") {
+            let n = allowed.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            got.extend_from_slice(&buf[..n]);
+        }
+        assert!(String::from_utf8_lossy(&got).starts_with("HTTP/1.1 200"));
+    }
+}
@@ -0,0 +1,288 @@
+//! OpenTelemetry tracing (`otel:` in the config).
+//!
+//! The agent loop opens a `turn` span per turn, with an `llm_call` child for
+//! every model request and a `tool_call` child for every tool execution.
+//! Finished spans are batched and exported as OTLP/HTTP JSON to
+//! `otel.endpoint`, which Jaeger, Tempo and the OpenTelemetry Collector all
+//! accept. When tracing is off, spans are inert and cost nothing.
+
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::warn;
+
+use crate::config::OtelConfig;
+
+const BATCH_SIZE: usize = 256;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// OTLP span kinds and status codes.
+const KIND_INTERNAL: u8 = 1;
+const KIND_CLIENT: u8 = 3;
+const STATUS_ERROR: u8 = 2;
+
+static EXPORTER: OnceLock<UnboundedSender<FinishedSpan>> = OnceLock::new();
+
+/// Identifies a span, for parenting children.
+#[derive(Clone, Copy, Debug)]
+pub struct SpanContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+}
+
+#[derive(Debug)]
+struct FinishedSpan {
+    name: &'static str,
+    kind: u8,
+    context: SpanContext,
+    parent_span_id: Option<[u8; 8]>,
+    start_ns: u128,
+    end_ns: u128,
+    attributes: Vec<(&'static str, serde_json::Value)>,
+    error: Option<String>,
+}
+
+/// An open span; it ends (and is queued for export) when dropped.
+pub struct Span(Option<FinishedSpan>);
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&uuid::Uuid::new_v4().as_bytes()[..8]);
+    id
+}
+
+impl Span {
+    fn start(name: &'static str, kind: u8, parent: Option<SpanContext>) -> Self {
+        if EXPORTER.get().is_none() {
+            return Span(None);
+        }
+        let trace_id = parent
+            .map(|p| p.trace_id)
+            .unwrap_or_else(|| *uuid::Uuid::new_v4().as_bytes());
+        Span(Some(FinishedSpan {
+            name,
+            kind,
+            context: SpanContext {
+                trace_id,
+                span_id: new_span_id(),
+            },
+            parent_span_id: parent.map(|p| p.span_id),
+            start_ns: now_ns(),
+            end_ns: 0,
+            attributes: Vec::new(),
+            error: None,
+        }))
+    }
+
+    /// A new trace (one agent turn).
+    pub fn root(name: &'static str) -> Self {
+        Self::start(name, KIND_INTERNAL, None)
+    }
+
+    /// A child of `parent`; `client` marks outbound calls such as LLM
+    /// requests.
+    pub fn child(name: &'static str, parent: Option<SpanContext>, client: bool) -> Self {
+        let kind = if client { KIND_CLIENT } else { KIND_INTERNAL };
+        Self::start(name, kind, parent)
+    }
+
+    pub fn context(&self) -> Option<SpanContext> {
+        self.0.as_ref().map(|s| s.context)
+    }
+
+    pub fn set(&mut self, key: &'static str, value: impl Into<serde_json::Value>) {
+        if let Some(span) = &mut self.0 {
+            span.attributes.push((key, value.into()));
+        }
+    }
+
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        if let Some(span) = &mut self.0 {
+            span.error = Some(message.into());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let (Some(mut span), Some(exporter)) = (self.0.take(), EXPORTER.get()) {
+            span.end_ns = now_ns();
+            let _ = exporter.send(span);
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn attribute(key: &str, value: &serde_json::Value) -> serde_json::Value {
+    let value = match value {
+        serde_json::Value::Bool(b) => json!({ "boolValue": b }),
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => {
+            json!({ "intValue": n.to_string() })
+        }
+        serde_json::Value::Number(n) => json!({ "doubleValue": n.as_f64() }),
+        serde_json::Value::String(s) => json!({ "stringValue": s }),
+        other => json!({ "stringValue": other.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+fn span_json(span: &FinishedSpan) -> serde_json::Value {
+    let mut value = json!({
+        "traceId": hex(&span.context.trace_id),
+        "spanId": hex(&span.context.span_id),
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start_ns.to_string(),
+        "endTimeUnixNano": span.end_ns.to_string(),
+        "attributes": span
+            .attributes
+            .iter()
+            .map(|(k, v)| attribute(k, v))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(parent) = span.parent_span_id {
+        value["parentSpanId"] = json!(hex(&parent));
+    }
+    if let Some(message) = &span.error {
+        value["status"] = json!({ "code": STATUS_ERROR, "message": message });
+    }
+    value
+}
+
+/// An OTLP `ExportTraceServiceRequest` body.
+fn export_body(service_name: &str, spans: &[FinishedSpan]) -> serde_json::Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", &json!(service_name)),
+                    attribute("service.version", &json!(env!("CARGO_PKG_VERSION"))),
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "microclaw", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans.iter().map(span_json).collect::<Vec<_>>(),
+            }]
+        }]
+    })
+}
+
+async fn export_loop(config: OtelConfig, mut rx: UnboundedReceiver<FinishedSpan>) {
+    let client = reqwest::Client::new();
+    let mut batch = Vec::new();
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        let closed = tokio::select! {
+            span = rx.recv() => match span {
+                Some(span) => {
+                    batch.push(span);
+                    if batch.len() < BATCH_SIZE {
+                        continue;
+                    }
+                    false
+                }
+                None => true,
+            },
+            _ = ticker.tick() => false,
+        };
+        if !batch.is_empty() {
+            let mut request = client
+                .post(&config.endpoint)
+                .timeout(Duration::from_secs(10))
+                .json(&export_body(&config.service_name, &batch));
+            for (name, value) in &config.headers {
+                request = request.header(name, value);
+            }
+            match request.send().await {
+                Ok(resp) if !resp.status().is_success() => {
+                    warn!(
+                        "OTLP export to {} failed: {}",
+                        config.endpoint,
+                        resp.status()
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => warn!("OTLP export to {} failed: {e}", config.endpoint),
+            }
+            batch.clear();
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+/// Start the exporter when `otel.enabled`; until then spans are inert.
+pub fn init(config: &OtelConfig) {
+    if !config.enabled || EXPORTER.get().is_some() {
+        return;
+    }
+    let (tx, rx) = unbounded_channel();
+    if EXPORTER.set(tx).is_ok() {
+        tokio::spawn(export_loop(config.clone(), rx));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_body_shape() {
+        let root = FinishedSpan {
+            name: "turn",
+            kind: KIND_INTERNAL,
+            context: SpanContext {
+                trace_id: [0xab; 16],
+                span_id: [1; 8],
+            },
+            parent_span_id: None,
+            start_ns: 10,
+            end_ns: 20,
+            attributes: vec![("chat_id", json!(42)), ("channel", json!("web"))],
+            error: None,
+        };
+        let child = FinishedSpan {
+            name: "tool_call",
+            kind: KIND_INTERNAL,
+            context: SpanContext {
+                trace_id: [0xab; 16],
+                span_id: [2; 8],
+            },
+            parent_span_id: Some([1; 8]),
+            start_ns: 12,
+            end_ns: 15,
+            attributes: vec![("tool", json!("bash"))],
+            error: Some("exit 1".into()),
+        };
+        let body = export_body("microclaw", &[root, child]);
+        let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans[0]["traceId"], "ab".repeat(16));
+        assert_eq!(spans[0]["spanId"], "0101010101010101");
+        assert!(spans[0].get("parentSpanId").is_none());
+        assert_eq!(spans[0]["attributes"][0]["value"]["intValue"], "42");
+        assert_eq!(spans[0]["startTimeUnixNano"], "10");
+        assert_eq!(spans[1]["parentSpanId"], "0101010101010101");
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_eq!(
+            body["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "microclaw"
+        );
+
+        // Without an exporter, spans are inert.
+        let span = Span::root("turn");
+        assert!(span.context().is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Named personas (`/persona`).
+//!
+//! Personas are defined under `personas:` in the config; each may carry its
+//! own system prompt, model, temperature, tool allow-list and greeting. A chat
+//! picks one with `/persona <name>`, stored in `chat_settings`, and every turn
+//! records the active persona on the chat's session row.
+
+use crate::config::PersonaConfig;
+use crate::db::call_blocking;
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding the chat's persona name.
+pub const PERSONA_SETTING_KEY: &str = "persona";
+
+const PERSONA_USAGE: &str = "Usage: /persona — show the active persona\n/persona <name> — switch this chat to a persona\n/persona default — go back to the default";
+
+#[derive(Debug, PartialEq, Eq)]
+enum PersonaCommand<'a> {
+    Show,
+    Switch(&'a str),
+    Reset,
+    Usage,
+}
+
+fn parse(text: &str) -> Option<PersonaCommand<'_>> {
+    let text = text.trim();
+    let rest = match text.split_once(char::is_whitespace) {
+        Some(("/persona", rest)) => rest.trim(),
+        None if text == "/persona" => "",
+        _ => return None,
+    };
+    Some(match rest {
+        "" | "show" => PersonaCommand::Show,
+        _ if rest.contains(char::is_whitespace) => PersonaCommand::Usage,
+        _ if rest.eq_ignore_ascii_case("default") || rest.eq_ignore_ascii_case("off") => {
+            PersonaCommand::Reset
+        }
+        name => PersonaCommand::Switch(name),
+    })
+}
+
+fn available(state: &AppState) -> String {
+    let mut names: Vec<&str> = state.config.personas.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    format!("Available: {}", names.join(", "))
+}
+
+/// The chat's active persona, if it picked one that is still configured.
+pub async fn chat_persona(state: &AppState, chat_id: i64) -> Option<(String, PersonaConfig)> {
+    if state.config.personas.is_empty() {
+        return None;
+    }
+    let name = call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, PERSONA_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten()?;
+    let persona = state.config.personas.get(&name)?.clone();
+    Some((name, persona))
+}
+
+/// Handle `/persona`. Returns `None` when the text is not this command.
+pub async fn handle_persona_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let command = parse(text)?;
+    if state.config.personas.is_empty() {
+        return Some("No personas are configured.".to_string());
+    }
+    let name = match command {
+        PersonaCommand::Usage => return Some(format!("{PERSONA_USAGE}\n{}", available(state))),
+        PersonaCommand::Show => {
+            let current = match chat_persona(state, chat_id).await {
+                Some((name, _)) => format!("Active persona: {name}"),
+                None => "Active persona: default".to_string(),
+            };
+            return Some(format!("{current}\n{}", available(state)));
+        }
+        PersonaCommand::Switch(name) => {
+            if !state.config.personas.contains_key(name) {
+                return Some(format!("Unknown persona '{name}'. {}", available(state)));
+            }
+            Some(name.to_string())
+        }
+        PersonaCommand::Reset => None,
+    };
+    let stored = name.clone();
+    if let Err(e) = call_blocking(state.db.clone(), move |db| {
+        db.set_chat_setting(chat_id, PERSONA_SETTING_KEY, stored.as_deref())?;
+        db.set_session_persona(chat_id, stored.as_deref())
+    })
+    .await
+    {
+        return Some(format!("Failed to switch persona: {e}"));
+    }
+    Some(match name {
+        Some(name) => state.config.personas[&name]
+            .greeting
+            .clone()
+            .filter(|g| !g.trim().is_empty())
+            .unwrap_or_else(|| format!("Switched to persona '{name}'.")),
+        None => "Switched back to the default persona.".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_persona_command() {
+        assert_eq!(parse("/persona"), Some(PersonaCommand::Show));
+        assert_eq!(parse("/persona show"), Some(PersonaCommand::Show));
+        assert_eq!(
+            parse("/persona reviewer"),
+            Some(PersonaCommand::Switch("reviewer"))
+        );
+        assert_eq!(parse("/persona Default"), Some(PersonaCommand::Reset));
+        assert_eq!(parse("/persona off"), Some(PersonaCommand::Reset));
+        assert_eq!(parse("/persona two words"), Some(PersonaCommand::Usage));
+        assert_eq!(parse("/personas"), None);
+        assert_eq!(parse("be a /persona"), None);
+    }
+}
@@ -0,0 +1,239 @@
+//! Structured per-chat preference profile.
+//!
+//! Unlike free-form memories, preferences are a small fixed set of keys the
+//! reflector infers from conversation and the user can audit or override with
+//! `/preferences`. Values set by the user are never replaced by inference.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::db::{call_blocking, Database, UserPreference};
+use crate::memory_quality;
+use crate::runtime::AppState;
+
+/// Keys the reflector may infer, with a short description used in its prompt.
+pub const PREFERENCE_KEYS: &[(&str, &str)] = &[
+    (
+        "formatting",
+        "preferred answer layout (bullets, tables, code blocks, plain prose)",
+    ),
+    ("verbosity", "how long answers should be"),
+    ("tone", "register and style (casual, formal, playful)"),
+    ("language", "language to reply in"),
+    ("favorite_tools", "tools or services the user reaches for"),
+    (
+        "schedule",
+        "working hours, routines and when to send reminders",
+    ),
+];
+
+const MAX_VALUE_CHARS: usize = 120;
+
+const PREFERENCES_USAGE: &str = "Usage:
+/preferences — show what I have learned about your preferences
+/preferences set <key> <value> — set a preference (kept until you change it)
+/preferences forget <key> — remove one preference
+/preferences clear — remove all preferences
+Keys: formatting, verbosity, tone, language, favorite_tools, schedule (or any custom key)";
+
+fn is_known_key(key: &str) -> bool {
+    PREFERENCE_KEYS.iter().any(|(k, _)| *k == key)
+}
+
+/// Lowercase a key and restrict it to `[a-z0-9_]`, max 32 chars.
+fn normalize_key(raw: &str) -> Option<String> {
+    let key = raw.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+    if key.is_empty()
+        || key.len() > 32
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return None;
+    }
+    Some(key)
+}
+
+/// Reflector prompt section describing the preference output format.
+pub fn reflector_instructions() -> String {
+    let keys = PREFERENCE_KEYS
+        .iter()
+        .map(|(k, d)| format!("  - {k}: {d}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Preferences:\n- When the user clearly shows a lasting preference, also add an item {{\"preference\":\"<key>\",\"value\":\"...\"}} to the same array\n- Allowed keys:\n{keys}\n- Values < 100 characters; only include a key when the conversation gives real evidence"
+    )
+}
+
+/// Store inferred preferences from reflector output items. Returns how many
+/// rows changed; user-set values are left untouched.
+pub async fn apply_inferred_preferences(db: Arc<Database>, chat_id: i64, items: &[Value]) -> usize {
+    let mut updates = Vec::new();
+    for item in items {
+        let (Some(key), Some(value)) = (
+            item.get("preference").and_then(|v| v.as_str()),
+            item.get("value").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let Some(key) = normalize_key(key).filter(|k| is_known_key(k)) else {
+            continue;
+        };
+        let Some(value) = memory_quality::normalize_memory_content(value, MAX_VALUE_CHARS) else {
+            continue;
+        };
+        updates.push((key, value));
+    }
+    if updates.is_empty() {
+        return 0;
+    }
+    let changed = call_blocking(db, move |db| {
+        let mut changed = 0usize;
+        for (key, value) in &updates {
+            if db.upsert_user_preference(chat_id, key, value, "inferred")? {
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    })
+    .await
+    .unwrap_or(0);
+    if changed > 0 {
+        info!("Preferences: chat {chat_id} -> {changed} inferred preference(s) updated");
+    }
+    changed
+}
+
+fn format_preference_lines(prefs: &[UserPreference]) -> String {
+    prefs
+        .iter()
+        .map(|p| {
+            let origin = if p.source == "user" {
+                "set by you"
+            } else {
+                "inferred"
+            };
+            format!("- {}: {} ({origin})", p.key, p.value)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// System prompt block with the chat's preferences, or empty when none exist.
+pub async fn build_preferences_context(db: &Arc<Database>, chat_id: i64) -> String {
+    let prefs = match call_blocking(db.clone(), move |db| db.get_user_preferences(chat_id)).await {
+        Ok(p) if !p.is_empty() => p,
+        _ => return String::new(),
+    };
+    let lines = prefs
+        .iter()
+        .map(|p| format!("{}: {}", p.key, p.value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "<user_preferences>\n{lines}\n</user_preferences>\nFollow these preferences unless the current request asks otherwise.\n"
+    )
+}
+
+/// Handle `/preferences`. Returns `None` when the text is not this command.
+pub async fn handle_preferences_command(
+    state: &AppState,
+    chat_id: i64,
+    text: &str,
+) -> Option<String> {
+    let rest = text.trim().strip_prefix("/preferences")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut parts = rest.split_whitespace();
+    let action = parts.next().unwrap_or("");
+    let key = parts.next().and_then(normalize_key);
+    let value = parts.collect::<Vec<_>>().join(" ");
+
+    let db = state.db.clone();
+    let chat_id = crate::identity::identity_chat_id(db.clone(), chat_id).await;
+    let reply = match (action, key) {
+        ("", _) => call_blocking(db, move |db| db.get_user_preferences(chat_id))
+            .await
+            .map(|prefs| {
+                if prefs.is_empty() {
+                    "No preferences recorded yet. I pick them up as we talk, or use /preferences set <key> <value>.".to_string()
+                } else {
+                    format!("Your preferences:\n{}", format_preference_lines(&prefs))
+                }
+            }),
+        ("set", Some(key)) if !value.trim().is_empty() => {
+            let value: String = value.trim().chars().take(MAX_VALUE_CHARS).collect();
+            let reply = format!("Saved {key}: {value}");
+            call_blocking(db, move |db| {
+                db.upsert_user_preference(chat_id, &key, &value, "user")
+            })
+            .await
+            .map(|_| reply)
+        }
+        ("forget", Some(key)) => {
+            let label = key.clone();
+            call_blocking(db, move |db| db.delete_user_preferences(chat_id, Some(&key)))
+                .await
+                .map(|n| {
+                    if n > 0 {
+                        format!("Forgot {label}.")
+                    } else {
+                        format!("No preference named {label}.")
+                    }
+                })
+        }
+        ("clear", _) => call_blocking(db, move |db| db.delete_user_preferences(chat_id, None))
+            .await
+            .map(|n| format!("Cleared {n} preference(s).")),
+        _ => return Some(PREFERENCES_USAGE.to_string()),
+    };
+    Some(reply.unwrap_or_else(|e| format!("Failed to update preferences: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_db() -> (Arc<Database>, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("microclaw_prefs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        (db, dir)
+    }
+
+    #[test]
+    fn test_normalize_key() {
+        assert_eq!(
+            normalize_key("Favorite-Tools").as_deref(),
+            Some("favorite_tools")
+        );
+        assert_eq!(normalize_key(" tone ").as_deref(), Some("tone"));
+        assert!(normalize_key("").is_none());
+        assert!(normalize_key("bad/key").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_apply_inferred_preferences_filters_and_builds_context() {
+        let (db, dir) = test_db();
+        let items = vec![
+            json!({"content": "User lives in Berlin", "category": "PROFILE"}),
+            json!({"preference": "Verbosity", "value": "short answers, no preamble"}),
+            json!({"preference": "shoe_size", "value": "42"}),
+            json!({"preference": "language", "value": ""}),
+        ];
+        assert_eq!(apply_inferred_preferences(db.clone(), 7, &items).await, 1);
+
+        let ctx = build_preferences_context(&db, 7).await;
+        assert!(ctx.contains("<user_preferences>"));
+        assert!(ctx.contains("verbosity: short answers, no preamble"));
+        assert!(!ctx.contains("shoe_size"));
+        assert!(build_preferences_context(&db, 8).await.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
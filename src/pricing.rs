@@ -0,0 +1,201 @@
+//! Model price lists beyond the inline `model_prices` table.
+//!
+//! `pricing_file` points at a YAML price list loaded at startup, and
+//! `pricing_url` at one fetched at startup and every `pricing_refresh_hours`,
+//! so new model releases get priced without editing the config. A fetched
+//! list replaces the loaded one and is written to `pricing_file` for the next
+//! start. Inline `model_prices` entries always win.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::config::{Config, ModelPrice};
+
+/// A price list is either a bare list of entries or a document with a
+/// `model_prices:` (or `models:`) key, so a config snippet can be reused.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PriceList {
+    Entries(Vec<ModelPrice>),
+    Document {
+        #[serde(alias = "models")]
+        model_prices: Vec<ModelPrice>,
+    },
+}
+
+fn loaded() -> &'static Mutex<Vec<ModelPrice>> {
+    static LOADED: OnceLock<Mutex<Vec<ModelPrice>>> = OnceLock::new();
+    LOADED.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// The price list loaded from `pricing_file` / `pricing_url`.
+pub fn loaded_prices() -> Vec<ModelPrice> {
+    loaded().lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn set_loaded_prices(prices: Vec<ModelPrice>) {
+    *loaded().lock().unwrap_or_else(|e| e.into_inner()) = prices;
+}
+
+pub fn parse_price_list(text: &str) -> Result<Vec<ModelPrice>, String> {
+    let list: PriceList =
+        serde_yaml::from_str(text).map_err(|e| format!("invalid price list: {e}"))?;
+    let mut prices = match list {
+        PriceList::Entries(prices)
+        | PriceList::Document {
+            model_prices: prices,
+        } => prices,
+    };
+    for price in &mut prices {
+        price.normalize()?;
+    }
+    Ok(prices)
+}
+
+/// Exact (case-insensitive) match in `inline`, then in `loaded`, then a `*`
+/// row from either.
+pub fn find_price(inline: &[ModelPrice], loaded: &[ModelPrice], model: &str) -> Option<ModelPrice> {
+    let needle = model.trim();
+    let exact = |p: &&ModelPrice| p.model.eq_ignore_ascii_case(needle);
+    let wildcard = |p: &&ModelPrice| p.model == "*";
+    inline
+        .iter()
+        .find(exact)
+        .or_else(|| loaded.iter().find(exact))
+        .or_else(|| inline.iter().find(wildcard))
+        .or_else(|| loaded.iter().find(wildcard))
+        .cloned()
+}
+
+/// Load `pricing_file`, if set. A missing file is not an error when
+/// `pricing_url` will fill it in.
+pub fn load_pricing_file(config: &Config) {
+    let Some(path) = config.pricing_file.as_deref() else {
+        return;
+    };
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && config.pricing_url.is_some() => {
+            return;
+        }
+        Err(e) => {
+            warn!("Could not read pricing_file {path}: {e}");
+            return;
+        }
+    };
+    match parse_price_list(&text) {
+        Ok(prices) => {
+            info!("Loaded {} model prices from {path}", prices.len());
+            set_loaded_prices(prices);
+        }
+        Err(e) => warn!("Ignoring pricing_file {path}: {e}"),
+    }
+}
+
+async fn fetch_price_list(url: &str) -> Result<(String, Vec<ModelPrice>), String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client
+        .get(url)
+        .header("User-Agent", "MicroClaw/1.0")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    let prices = parse_price_list(&text)?;
+    Ok((text, prices))
+}
+
+async fn refresh_from_url(config: &Config, url: &str) {
+    match fetch_price_list(url).await {
+        Ok((text, prices)) => {
+            info!("Fetched {} model prices from {url}", prices.len());
+            set_loaded_prices(prices);
+            if let Some(path) = config.pricing_file.as_deref() {
+                if let Err(e) = std::fs::write(path, text) {
+                    warn!("Could not save fetched prices to {path}: {e}");
+                }
+            }
+        }
+        // Keep whatever was loaded before.
+        Err(e) => warn!("Failed to refresh prices from {url}: {e}"),
+    }
+}
+
+/// Load `pricing_file` and start refreshing from `pricing_url`.
+pub fn spawn_pricing_refresh(config: Config) {
+    load_pricing_file(&config);
+    let Some(url) = config.pricing_url.clone() else {
+        return;
+    };
+    tokio::spawn(async move {
+        loop {
+            refresh_from_url(&config, &url).await;
+            if config.pricing_refresh_hours == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(config.pricing_refresh_hours * 3600)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(model: &str, input: f64) -> ModelPrice {
+        ModelPrice {
+            model: model.to_string(),
+            input_per_million_usd: input,
+            output_per_million_usd: input,
+            cache_read_per_million_usd: None,
+            cache_write_per_million_usd: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_price_list() {
+        let bare = "- model: gpt-5\n  input_per_million_usd: 1.25\n  output_per_million_usd: 10\n  cache_read_per_million_usd: 0.125\n";
+        let prices = parse_price_list(bare).unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].cache_read_per_million_usd, Some(0.125));
+
+        let doc = "model_prices:\n  - model: ' claude-opus-4-1 '\n    input_per_million_usd: 15\n    output_per_million_usd: 75\n";
+        assert_eq!(parse_price_list(doc).unwrap()[0].model, "claude-opus-4-1");
+
+        let negative = "- model: x\n  input_per_million_usd: 1\n  output_per_million_usd: 1\n  cache_write_per_million_usd: -1\n";
+        assert!(parse_price_list(negative)
+            .unwrap_err()
+            .contains("cache_write_per_million_usd must be >= 0"));
+        assert!(parse_price_list("not: [a price list").is_err());
+    }
+
+    #[test]
+    fn test_find_price_prefers_inline() {
+        let inline = vec![price("gpt-5", 2.0), price("*", 0.0)];
+        let loaded = vec![
+            price("GPT-5", 1.0),
+            price("gpt-5-mini", 0.25),
+            price("*", 9.0),
+        ];
+        let pick = |model| find_price(&inline, &loaded, model).map(|p| p.input_per_million_usd);
+        assert_eq!(pick("gpt-5"), Some(2.0));
+        assert_eq!(pick("gpt-5-mini"), Some(0.25));
+        assert_eq!(pick("unknown"), Some(0.0));
+        assert_eq!(
+            find_price(&[], &loaded, "unknown")
+                .unwrap()
+                .input_per_million_usd,
+            9.0
+        );
+        assert!(find_price(&[], &[], "gpt-5").is_none());
+    }
+}
@@ -0,0 +1,493 @@
+//! Retry policy and per-model health (`llm_max_retries`, `/status`).
+//!
+//! Every model client built by `llm::create_provider` is wrapped in a
+//! [`RetryingProvider`]. Rate limits and transient failures (5xx, timeouts,
+//! connection errors) are retried with exponential backoff and jitter; auth
+//! and request errors are returned at once. Each attempt is recorded in a
+//! process-wide registry keyed by provider and model, which keeps a rolling
+//! success rate and the latest error. The fallback chain skips models that
+//! keep failing, and `/status` reports the scores.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::error::{LlmErrorKind, MicroClawError};
+use crate::llm::LlmProvider;
+use crate::llm_types::{Message, MessagesResponse, ResponseSchema, ToolDefinition};
+use crate::runtime::AppState;
+
+/// Attempts kept per model for the health score.
+const HEALTH_WINDOW: usize = 20;
+/// Consecutive failures after which a model is skipped by the fallback chain.
+const COOLDOWN_FAILURES: u32 = 3;
+/// How long a failing model is skipped after its last failure.
+const COOLDOWN: Duration = Duration::from_secs(60);
+const MAX_ERROR_CHARS: usize = 160;
+
+/// Exponential backoff with equal jitter: the delay before retry `n` is
+/// between half and all of `base_delay * 2^n`, capped at `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        RetryPolicy {
+            max_retries: config.llm_max_retries,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// Delay before retry `attempt` (0-based). `jitter` is in `[0, 1]`.
+    pub fn delay(&self, attempt: u32, jitter: f64) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        half + half.mul_f64(jitter.clamp(0.0, 1.0))
+    }
+}
+
+fn random_jitter() -> f64 {
+    // 53 random bits from a v4 UUID, scaled to [0, 1).
+    (uuid::Uuid::new_v4().as_u128() >> 75) as f64 / (1u64 << 53) as f64
+}
+
+/// Health of one provider/model pair as seen by this process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub model: String,
+    /// Share of successful attempts in the rolling window (1.0 when unused).
+    pub score: f64,
+    /// Attempts in the rolling window.
+    pub recent_attempts: usize,
+    pub consecutive_failures: u32,
+    pub total_attempts: u64,
+    pub total_failures: u64,
+    pub last_error: Option<(LlmErrorKind, String, DateTime<Utc>)>,
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct HealthRecord {
+    window: VecDeque<bool>,
+    consecutive_failures: u32,
+    total_attempts: u64,
+    total_failures: u64,
+    last_error: Option<(LlmErrorKind, String, DateTime<Utc>)>,
+    last_failure_at: Option<Instant>,
+    last_success: Option<DateTime<Utc>>,
+}
+
+impl HealthRecord {
+    fn push(&mut self, ok: bool) {
+        if self.window.len() == HEALTH_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(ok);
+        self.total_attempts += 1;
+    }
+
+    fn score(&self) -> f64 {
+        if self.window.is_empty() {
+            return 1.0;
+        }
+        self.window.iter().filter(|ok| **ok).count() as f64 / self.window.len() as f64
+    }
+}
+
+type HealthMap = HashMap<(String, String), HealthRecord>;
+
+fn registry() -> &'static Mutex<HealthMap> {
+    static HEALTH: OnceLock<Mutex<HealthMap>> = OnceLock::new();
+    HEALTH.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn with_record<T>(provider: &str, model: &str, f: impl FnOnce(&mut HealthRecord) -> T) -> T {
+    let mut map = registry().lock().unwrap_or_else(|e| e.into_inner());
+    let record = map
+        .entry((provider.to_string(), model.to_string()))
+        .or_default();
+    f(record)
+}
+
+pub fn record_success(provider: &str, model: &str) {
+    with_record(provider, model, |record| {
+        record.push(true);
+        record.consecutive_failures = 0;
+        record.last_success = Some(Utc::now());
+    });
+}
+
+pub fn record_failure(provider: &str, model: &str, error: &MicroClawError) {
+    let kind = error.llm_error_kind();
+    let mut message = error.to_string();
+    if message.chars().count() > MAX_ERROR_CHARS {
+        message = message.chars().take(MAX_ERROR_CHARS).collect::<String>() + "…";
+    }
+    with_record(provider, model, |record| {
+        record.push(false);
+        record.total_failures += 1;
+        record.consecutive_failures += 1;
+        record.last_error = Some((kind, message, Utc::now()));
+        record.last_failure_at = Some(Instant::now());
+    });
+}
+
+/// Health of `provider`/`model`, if it has been used.
+pub fn health(provider: &str, model: &str) -> Option<ProviderHealth> {
+    let map = registry().lock().unwrap_or_else(|e| e.into_inner());
+    map.get(&(provider.to_string(), model.to_string()))
+        .map(|record| to_health(provider, model, record))
+}
+
+/// Health of every model used so far, sorted by provider and model.
+pub fn snapshot() -> Vec<ProviderHealth> {
+    let map = registry().lock().unwrap_or_else(|e| e.into_inner());
+    let mut all: Vec<ProviderHealth> = map
+        .iter()
+        .map(|((provider, model), record)| to_health(provider, model, record))
+        .collect();
+    all.sort_by(|a, b| (&a.provider, &a.model).cmp(&(&b.provider, &b.model)));
+    all
+}
+
+fn to_health(provider: &str, model: &str, record: &HealthRecord) -> ProviderHealth {
+    ProviderHealth {
+        provider: provider.to_string(),
+        model: model.to_string(),
+        score: record.score(),
+        recent_attempts: record.window.len(),
+        consecutive_failures: record.consecutive_failures,
+        total_attempts: record.total_attempts,
+        total_failures: record.total_failures,
+        last_error: record.last_error.clone(),
+        last_success: record.last_success,
+    }
+}
+
+/// True when the model failed [`COOLDOWN_FAILURES`] times in a row, the last
+/// time within [`COOLDOWN`]. The fallback chain skips such models.
+pub fn is_cooling_down(provider: &str, model: &str) -> bool {
+    let map = registry().lock().unwrap_or_else(|e| e.into_inner());
+    map.get(&(provider.to_string(), model.to_string()))
+        .is_some_and(|record| {
+            record.consecutive_failures >= COOLDOWN_FAILURES
+                && record
+                    .last_failure_at
+                    .is_some_and(|at| at.elapsed() < COOLDOWN)
+        })
+}
+
+// ---------------------------------------------------------------------------
+// Retrying provider
+// ---------------------------------------------------------------------------
+
+/// Retries one model's requests per [`RetryPolicy`] and records every attempt
+/// in the health registry.
+pub struct RetryingProvider {
+    inner: Box<dyn LlmProvider>,
+    provider: String,
+    model: String,
+    policy: RetryPolicy,
+}
+
+impl RetryingProvider {
+    pub fn new(inner: Box<dyn LlmProvider>, config: &Config) -> Self {
+        RetryingProvider {
+            inner,
+            provider: config.llm_provider.clone(),
+            model: config.model.clone(),
+            policy: RetryPolicy::from_config(config),
+        }
+    }
+
+    /// `retryable` decides which failures may be retried.
+    async fn run<'a, T, F>(
+        &'a self,
+        retryable: fn(&MicroClawError) -> bool,
+        call: F,
+    ) -> Result<T, MicroClawError>
+    where
+        F: Fn(
+            &'a dyn LlmProvider,
+        ) -> futures_util::future::BoxFuture<'a, Result<T, MicroClawError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match call(self.inner.as_ref()).await {
+                Ok(response) => {
+                    record_success(&self.provider, &self.model);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    record_failure(&self.provider, &self.model, &e);
+                    if attempt >= self.policy.max_retries || !retryable(&e) {
+                        return Err(e);
+                    }
+                    let delay = self.policy.delay(attempt, random_jitter());
+                    attempt += 1;
+                    warn!(
+                        "LLM {}/{} failed ({}: {e}); retry {attempt}/{} in {}ms",
+                        self.provider,
+                        self.model,
+                        e.llm_error_kind().as_str(),
+                        self.policy.max_retries,
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+fn retryable(error: &MicroClawError) -> bool {
+    error.is_transient_llm_failure()
+}
+
+/// Streams are only retried on failures reported before any text was sent
+/// (an HTTP status), so a retry never repeats partial output.
+fn retryable_stream(error: &MicroClawError) -> bool {
+    matches!(
+        error,
+        MicroClawError::RateLimited | MicroClawError::LlmHttp { .. }
+    ) && error.is_transient_llm_failure()
+}
+
+#[async_trait]
+impl LlmProvider for RetryingProvider {
+    async fn send_message(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.run(retryable, |provider| {
+            Box::pin(provider.send_message(system, messages.clone(), tools.clone()))
+        })
+        .await
+    }
+
+    async fn send_message_stream(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolDefinition>>,
+        text_tx: Option<&UnboundedSender<String>>,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.run(retryable_stream, |provider| {
+            Box::pin(provider.send_message_stream(system, messages.clone(), tools.clone(), text_tx))
+        })
+        .await
+    }
+
+    async fn send_message_json(
+        &self,
+        system: &str,
+        messages: Vec<Message>,
+        schema: &ResponseSchema,
+    ) -> Result<MessagesResponse, MicroClawError> {
+        self.run(retryable, |provider| {
+            Box::pin(provider.send_message_json(system, messages.clone(), schema))
+        })
+        .await
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, MicroClawError> {
+        self.run(retryable, |provider| Box::pin(provider.embed(inputs)))
+            .await
+    }
+}
+
+// ---------------------------------------------------------------------------
+// /status
+// ---------------------------------------------------------------------------
+
+fn ago(at: DateTime<Utc>) -> String {
+    let secs = (Utc::now() - at).num_seconds().max(0);
+    match secs {
+        0..=59 => format!("{secs}s ago"),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+fn health_line(role: &str, provider: &str, model: &str) -> String {
+    let Some(h) = health(provider, model) else {
+        return format!("{role} {provider}/{model}: no requests yet");
+    };
+    let mut line = format!(
+        "{role} {provider}/{model}: {:.0}% ok over the last {} attempts ({} total)",
+        h.score * 100.0,
+        h.recent_attempts,
+        h.total_attempts
+    );
+    if is_cooling_down(provider, model) {
+        line.push_str(&format!(
+            ", skipped after {} failures in a row",
+            h.consecutive_failures
+        ));
+    }
+    if let Some((kind, message, at)) = &h.last_error {
+        line.push_str(&format!(
+            "\n  last error {} ({}): {message}",
+            ago(*at),
+            kind.as_str()
+        ));
+    }
+    line
+}
+
+/// Handle `/status`. Returns `None` when the text is not this command.
+pub async fn handle_status_command(state: &AppState, text: &str) -> Option<String> {
+    if text.trim() != "/status" {
+        return None;
+    }
+    let config = &state.config;
+    let mut listed = vec![(config.llm_provider.clone(), config.model.clone())];
+    let mut lines = vec![
+        "🩺 LLM health".to_string(),
+        health_line("Primary", &config.llm_provider, &config.model),
+    ];
+    for (index, fallback) in config.llm_fallbacks.iter().enumerate() {
+        let fallback = config.with_fallback(fallback);
+        lines.push(health_line(
+            &format!("Fallback {}", index + 1),
+            &fallback.llm_provider,
+            &fallback.model,
+        ));
+        listed.push((fallback.llm_provider, fallback.model));
+    }
+    for h in snapshot() {
+        if !listed.contains(&(h.provider.clone(), h.model.clone())) {
+            lines.push(health_line("Other", &h.provider, &h.model));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::ResponseContentBlock;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_retry_delay_is_bounded() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        };
+        assert_eq!(policy.delay(0, 0.0), Duration::from_millis(500));
+        assert_eq!(policy.delay(0, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.delay(2, 0.5), Duration::from_secs(3));
+        assert_eq!(policy.delay(10, 1.0), Duration::from_secs(30));
+        assert_eq!(policy.delay(40, 0.0), Duration::from_secs(15));
+        let jitter = random_jitter();
+        assert!((0.0..1.0).contains(&jitter));
+    }
+
+    #[test]
+    fn test_health_score_and_cooldown() {
+        let (provider, model) = ("health-test", "score-model");
+        assert!(health(provider, model).is_none());
+        record_success(provider, model);
+        let err = MicroClawError::LlmHttp {
+            status: 503,
+            message: "overloaded".into(),
+        };
+        for _ in 0..COOLDOWN_FAILURES {
+            record_failure(provider, model, &err);
+        }
+        let h = health(provider, model).unwrap();
+        assert_eq!(h.recent_attempts, 4);
+        assert!((h.score - 0.25).abs() < f64::EPSILON);
+        assert_eq!(h.last_error.as_ref().unwrap().0, LlmErrorKind::Transient);
+        assert!(is_cooling_down(provider, model));
+
+        record_success(provider, model);
+        assert!(!is_cooling_down(provider, model));
+        assert_eq!(health(provider, model).unwrap().consecutive_failures, 0);
+    }
+
+    struct FlakyProvider {
+        calls: AtomicU32,
+        failures: u32,
+        status: u16,
+    }
+
+    #[async_trait]
+    impl LlmProvider for FlakyProvider {
+        async fn send_message(
+            &self,
+            _system: &str,
+            _messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(MicroClawError::LlmHttp {
+                    status: self.status,
+                    message: format!("HTTP {}", self.status),
+                });
+            }
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text { text: "ok".into() }],
+                stop_reason: Some("end_turn".into()),
+                usage: None,
+                served_by: None,
+            })
+        }
+    }
+
+    fn retrying(model: &str, failures: u32, status: u16) -> RetryingProvider {
+        RetryingProvider {
+            inner: Box::new(FlakyProvider {
+                calls: AtomicU32::new(0),
+                failures,
+                status,
+            }),
+            provider: "retry-test".into(),
+            model: model.into(),
+            policy: RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(2),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retrying_provider_retries_transient_errors_only() {
+        let provider = retrying("transient", 2, 429);
+        assert!(provider.send_message("", vec![], None).await.is_ok());
+        let h = health("retry-test", "transient").unwrap();
+        assert_eq!((h.total_attempts, h.total_failures), (3, 2));
+
+        let provider = retrying("exhausted", 5, 502);
+        let err = provider.send_message("", vec![], None).await.unwrap_err();
+        assert!(matches!(err, MicroClawError::LlmHttp { status: 502, .. }));
+        assert_eq!(health("retry-test", "exhausted").unwrap().total_attempts, 3);
+
+        let provider = retrying("auth", 5, 401);
+        assert!(provider.send_message("", vec![], None).await.is_err());
+        let h = health("retry-test", "auth").unwrap();
+        assert_eq!(h.total_attempts, 1);
+        assert_eq!(h.last_error.unwrap().0, LlmErrorKind::Auth);
+    }
+}
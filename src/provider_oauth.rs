@@ -0,0 +1,425 @@
+//! OAuth device-code login for LLM providers (`microclaw login <provider>`).
+//!
+//! The login asks the provider's device authorization endpoint for a user
+//! code, waits while the user approves it in a browser, and stores the
+//! tokens in `data_dir/auth/<provider>.json`. Providers built for a provider
+//! with a stored token send it as a bearer token instead of `api_key`,
+//! refreshing it shortly before it expires. Clients are configured under
+//! `oauth.<provider>`; Google's endpoints are built in.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::config::{Config, OAuthClientConfig};
+use crate::error::MicroClawError;
+
+pub const LOGIN_USAGE: &str = "Usage: microclaw login <provider> — sign in with the device-code flow (needs oauth.<provider> in the config)\n       microclaw login <provider> --logout — delete the stored token";
+
+const GOOGLE_DEVICE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Refresh tokens this long before they expire.
+const EXPIRY_MARGIN_SECS: i64 = 300;
+
+/// Endpoints and scope of a provider's OAuth client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct OAuthClient {
+    client_id: String,
+    client_secret: Option<String>,
+    scope: String,
+    device_authorization_url: String,
+    token_url: String,
+}
+
+impl OAuthClient {
+    /// `oauth.<provider>` filled in with the provider's known endpoints.
+    fn resolve(provider: &str, config: &OAuthClientConfig) -> Result<Self, String> {
+        let google = matches!(provider, "google" | "gemini");
+        let preset = |value: &Option<String>, default: &str| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .or_else(|| google.then(|| default.to_string()))
+        };
+        let missing = |field: &str| format!("oauth.{provider}.{field} is required for {provider}");
+        Ok(OAuthClient {
+            client_id: config.client_id.trim().to_string(),
+            client_secret: config
+                .client_secret
+                .clone()
+                .filter(|s| !s.trim().is_empty()),
+            scope: preset(&config.scope, GOOGLE_SCOPE).unwrap_or_default(),
+            device_authorization_url: preset(&config.device_authorization_url, GOOGLE_DEVICE_URL)
+                .ok_or_else(|| missing("device_authorization_url"))?,
+            token_url: preset(&config.token_url, GOOGLE_TOKEN_URL)
+                .ok_or_else(|| missing("token_url"))?,
+        })
+    }
+
+    fn form<'a>(&'a self, extra: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        let mut form = vec![("client_id", self.client_id.as_str())];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        form.extend_from_slice(extra);
+        form
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredToken {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+impl StoredToken {
+    /// A token endpoint response. A refresh that returns no new refresh
+    /// token keeps `previous_refresh`.
+    fn from_response(
+        value: &Value,
+        previous_refresh: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        let access_token = value.get("access_token")?.as_str()?.to_string();
+        let refresh_token = value
+            .get("refresh_token")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or(previous_refresh);
+        let expires_at = value
+            .get("expires_in")
+            .and_then(Value::as_i64)
+            .map(|secs| now + chrono::Duration::seconds(secs));
+        Some(StoredToken {
+            access_token,
+            refresh_token,
+            expires_at,
+            scope: value
+                .get("scope")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .is_none_or(|at| at - chrono::Duration::seconds(EXPIRY_MARGIN_SECS) > now)
+    }
+}
+
+fn token_path(config: &Config, provider: &str) -> PathBuf {
+    config
+        .data_root_dir()
+        .join("auth")
+        .join(format!("{provider}.json"))
+}
+
+fn load_token(path: &Path) -> Option<StoredToken> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_token(path: &Path, token: &StoredToken) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(token).map_err(std::io::Error::other)?;
+    std::fs::write(path, json)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// The error message of a failed token endpoint response.
+fn oauth_error(value: &Value, status: reqwest::StatusCode, text: &str) -> String {
+    value
+        .get("error_description")
+        .or_else(|| value.get("error"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("HTTP {status}: {text}"))
+}
+
+async fn post_form(
+    http: &reqwest::Client,
+    url: &str,
+    form: &[(&str, &str)],
+) -> Result<(reqwest::StatusCode, Value, String), String> {
+    let response = http
+        .post(url)
+        .form(form)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    let value = serde_json::from_str(&text).unwrap_or(Value::Null);
+    Ok((status, value, text))
+}
+
+/// Bearer token from `microclaw login`, refreshed when it is about to expire.
+pub struct OAuthCredential {
+    http: reqwest::Client,
+    provider: String,
+    path: PathBuf,
+    client: Option<OAuthClient>,
+    token: Mutex<StoredToken>,
+}
+
+impl OAuthCredential {
+    /// The stored login for `config.llm_provider`, if there is one.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let provider = config.llm_provider.trim().to_lowercase();
+        let path = token_path(config, &provider);
+        let token = load_token(&path)?;
+        let client = config
+            .oauth
+            .get(&provider)
+            .and_then(|c| OAuthClient::resolve(&provider, c).ok());
+        Some(OAuthCredential {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            provider,
+            path,
+            client,
+            token: Mutex::new(token),
+        })
+    }
+
+    /// The current access token, refreshed first when it expires soon.
+    pub async fn bearer_token(&self) -> Result<String, MicroClawError> {
+        let mut token = self.token.lock().await;
+        let now = Utc::now();
+        if token.is_fresh(now) {
+            return Ok(token.access_token.clone());
+        }
+        // Another process (or a new `microclaw login`) may have refreshed it.
+        if let Some(on_disk) = load_token(&self.path).filter(|t| t.is_fresh(now)) {
+            *token = on_disk;
+            return Ok(token.access_token.clone());
+        }
+        let relogin = |reason: String| {
+            MicroClawError::LlmApi(format!(
+                "{} OAuth token expired and could not be refreshed ({reason}); run `microclaw login {}`",
+                self.provider, self.provider
+            ))
+        };
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| relogin(format!("no oauth.{} in the config", self.provider)))?;
+        let refresh_token = token
+            .refresh_token
+            .clone()
+            .ok_or_else(|| relogin("no refresh token".into()))?;
+        let form = client.form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ]);
+        let (status, value, text) = post_form(&self.http, &client.token_url, &form)
+            .await
+            .map_err(relogin)?;
+        if !status.is_success() {
+            return Err(relogin(oauth_error(&value, status, &text)));
+        }
+        let refreshed = StoredToken::from_response(&value, Some(refresh_token), now)
+            .ok_or_else(|| relogin("the response has no access_token".into()))?;
+        if let Err(e) = save_token(&self.path, &refreshed) {
+            tracing::warn!("Failed to save refreshed {} token: {e}", self.provider);
+        }
+        *token = refreshed;
+        Ok(token.access_token.clone())
+    }
+}
+
+async fn device_login(config: &Config, provider: &str) -> anyhow::Result<PathBuf> {
+    let client_config = config.oauth.get(provider).ok_or_else(|| {
+        anyhow::anyhow!("add oauth.{provider} (at least client_id) to the config first")
+    })?;
+    let client = OAuthClient::resolve(provider, client_config).map_err(anyhow::Error::msg)?;
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()?;
+
+    let mut request = vec![];
+    if !client.scope.is_empty() {
+        request.push(("scope", client.scope.as_str()));
+    }
+    let (status, device, text) = post_form(
+        &http,
+        &client.device_authorization_url,
+        &client.form(&request),
+    )
+    .await
+    .map_err(anyhow::Error::msg)?;
+    if !status.is_success() {
+        anyhow::bail!(
+            "device authorization failed: {}",
+            oauth_error(&device, status, &text)
+        );
+    }
+    let field = |key: &str| device.get(key).and_then(Value::as_str);
+    let device_code = field("device_code")
+        .ok_or_else(|| anyhow::anyhow!("the device authorization response has no device_code"))?;
+    let user_code = field("user_code").unwrap_or_default();
+    // Google says `verification_url`; RFC 8628 says `verification_uri`.
+    let verification = field("verification_uri_complete")
+        .or_else(|| field("verification_uri"))
+        .or_else(|| field("verification_url"))
+        .unwrap_or_default();
+    let expires_in = device
+        .get("expires_in")
+        .and_then(Value::as_u64)
+        .unwrap_or(900);
+    let mut interval = device.get("interval").and_then(Value::as_u64).unwrap_or(5);
+    println!("Open {verification} and enter the code: {user_code}");
+    println!(
+        "Waiting for approval (expires in {} min)...",
+        expires_in / 60
+    );
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(expires_in);
+    loop {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        if std::time::Instant::now() >= deadline {
+            anyhow::bail!("the code expired before it was approved; run the login again");
+        }
+        let form = client.form(&[
+            ("grant_type", DEVICE_CODE_GRANT),
+            ("device_code", device_code),
+        ]);
+        let (status, value, text) = post_form(&http, &client.token_url, &form)
+            .await
+            .map_err(anyhow::Error::msg)?;
+        if status.is_success() {
+            let token = StoredToken::from_response(&value, None, Utc::now())
+                .ok_or_else(|| anyhow::anyhow!("the token response has no access_token"))?;
+            let path = token_path(config, provider);
+            save_token(&path, &token)?;
+            return Ok(path);
+        }
+        match value.get("error").and_then(Value::as_str) {
+            Some("authorization_pending") => {}
+            Some("slow_down") => interval += 5,
+            Some("access_denied") => anyhow::bail!("the login was denied"),
+            Some("expired_token") => {
+                anyhow::bail!("the code expired before it was approved; run the login again")
+            }
+            _ => anyhow::bail!("login failed: {}", oauth_error(&value, status, &text)),
+        }
+    }
+}
+
+/// `microclaw login <provider> [--logout]`.
+pub async fn run_login_cli(args: &[String]) -> anyhow::Result<()> {
+    let logout = args.iter().any(|a| a == "--logout");
+    let positional: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !a.starts_with('-'))
+        .collect();
+    let [provider] = positional.as_slice() else {
+        println!("{LOGIN_USAGE}");
+        return Ok(());
+    };
+    let provider = provider.trim().to_lowercase();
+    let config = Config::load_without_channels()?;
+    if logout {
+        let path = token_path(&config, &provider);
+        match std::fs::remove_file(&path) {
+            Ok(()) => println!("Removed the {provider} login ({}).", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!("No {provider} login is stored.")
+            }
+            Err(e) => return Err(e.into()),
+        }
+        return Ok(());
+    }
+    let path = device_login(&config, &provider).await?;
+    println!(
+        "Logged in to {provider}; the token is stored in {}. It is used instead of api_key while llm_provider is {provider}.",
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_client_uses_google_presets() {
+        let config = OAuthClientConfig {
+            client_id: " id ".into(),
+            client_secret: Some("secret".into()),
+            ..Default::default()
+        };
+        let google = OAuthClient::resolve("google", &config).unwrap();
+        assert_eq!(google.client_id, "id");
+        assert_eq!(google.token_url, GOOGLE_TOKEN_URL);
+        assert_eq!(google.scope, GOOGLE_SCOPE);
+        assert_eq!(
+            google.form(&[("grant_type", "x")]),
+            vec![
+                ("client_id", "id"),
+                ("client_secret", "secret"),
+                ("grant_type", "x")
+            ]
+        );
+
+        let err = OAuthClient::resolve("anthropic", &config).unwrap_err();
+        assert!(
+            err.contains("oauth.anthropic.device_authorization_url"),
+            "{err}"
+        );
+        let anthropic = OAuthClient::resolve(
+            "anthropic",
+            &OAuthClientConfig {
+                device_authorization_url: Some("https://auth.example/device".into()),
+                token_url: Some("https://auth.example/token".into()),
+                ..config
+            },
+        )
+        .unwrap();
+        assert_eq!(anthropic.scope, "");
+    }
+
+    #[test]
+    fn test_stored_token_refresh_keeps_refresh_token() {
+        let now = Utc::now();
+        let token = StoredToken::from_response(
+            &json!({"access_token": "a2", "expires_in": 3600}),
+            Some("r1".into()),
+            now,
+        )
+        .unwrap();
+        assert_eq!(token.refresh_token.as_deref(), Some("r1"));
+        assert!(token.is_fresh(now));
+        assert!(!token.is_fresh(now + chrono::Duration::seconds(3400)));
+        assert!(StoredToken::from_response(&json!({"error": "x"}), None, now).is_none());
+
+        let dir = std::env::temp_dir().join(format!("microclaw_oauth_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("auth").join("google.json");
+        save_token(&path, &token).unwrap();
+        assert_eq!(load_token(&path), Some(token));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
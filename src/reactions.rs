@@ -0,0 +1,178 @@
+//! Reaction-based controls for chat channels that deliver emoji reactions
+//! (Telegram, Discord).
+//!
+//! - 👍 approves the high-risk tool calls waiting for approval in the chat
+//!   and lets the agent continue.
+//! - 🔁 (or 👎) rewinds the session to the last user message and answers it
+//!   again.
+//! - ❌ (or 🙈) cancels the in-flight agent run, like `/stop`.
+//!
+//! Telegram only offers a fixed reaction set without 🔁 and ❌, hence the
+//! alternatives.
+
+use crate::db::{call_blocking, StoredMessage};
+use crate::llm_types::Message;
+use crate::run_control;
+use crate::runtime::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionAction {
+    Approve,
+    Retry,
+    Cancel,
+}
+
+/// What the channel should do after a reaction was handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReactionOutcome {
+    /// Nothing to do; the reaction was not a control or had no effect.
+    Ignore,
+    /// Send this text to the chat.
+    Reply(String),
+    /// Run an agent turn for the chat and deliver its response.
+    RunTurn,
+}
+
+/// Map a reaction emoji to a control action.
+pub fn reaction_action(emoji: &str) -> Option<ReactionAction> {
+    // Skin tone modifiers and variation selectors don't change the meaning.
+    let base: String = emoji
+        .chars()
+        .filter(|c| !matches!(*c, '\u{1F3FB}'..='\u{1F3FF}' | '\u{FE0F}'))
+        .collect();
+    match base.as_str() {
+        "👍" | "👌" => Some(ReactionAction::Approve),
+        "🔁" | "🔄" | "👎" => Some(ReactionAction::Retry),
+        "❌" | "✖" | "🛑" | "🙈" => Some(ReactionAction::Cancel),
+        _ => None,
+    }
+}
+
+/// Drop everything after the last user message. Returns `false` when there is
+/// no user message to answer again.
+fn rewind_messages(messages: &mut Vec<Message>) -> bool {
+    let Some(idx) = messages.iter().rposition(crate::compare::is_user_turn) else {
+        return false;
+    };
+    messages.truncate(idx + 1);
+    true
+}
+
+async fn rewind_last_turn(state: &AppState, chat_id: i64) -> bool {
+    let Ok(Some((json, _))) =
+        call_blocking(state.db.clone(), move |db| db.load_session(chat_id)).await
+    else {
+        return false;
+    };
+    let mut messages: Vec<Message> = serde_json::from_str(&json).unwrap_or_default();
+    if !rewind_messages(&mut messages) {
+        return false;
+    }
+    let Ok(json) = serde_json::to_string(&messages) else {
+        return false;
+    };
+    call_blocking(state.db.clone(), move |db| db.save_session(chat_id, &json))
+        .await
+        .is_ok()
+}
+
+/// Apply a reaction control to a chat. `sender_name` is who reacted.
+pub async fn handle_reaction(
+    state: &AppState,
+    channel: &str,
+    chat_id: i64,
+    sender_name: &str,
+    action: ReactionAction,
+) -> ReactionOutcome {
+    match action {
+        ReactionAction::Cancel => {
+            run_control::cancel_chat_runs(chat_id);
+            // The cancelled run posts its own stop notice.
+            ReactionOutcome::Ignore
+        }
+        ReactionAction::Approve => {
+            if crate::tools::approve_pending_calls(channel, chat_id) == 0 {
+                return ReactionOutcome::Ignore;
+            }
+            let stored = StoredMessage {
+                id: uuid::Uuid::new_v4().to_string(),
+                chat_id,
+                sender_name: sender_name.to_string(),
+                content: "👍 Approved. Go ahead with the pending tool call.".into(),
+                is_from_bot: false,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            };
+            let _ = call_blocking(state.db.clone(), move |db| db.store_message(&stored)).await;
+            ReactionOutcome::RunTurn
+        }
+        ReactionAction::Retry => {
+            if run_control::has_active_run(chat_id) {
+                return ReactionOutcome::Reply(
+                    "Still working on it. React ❌ to stop the current run first.".into(),
+                );
+            }
+            if rewind_last_turn(state, chat_id).await {
+                ReactionOutcome::RunTurn
+            } else {
+                ReactionOutcome::Reply("Nothing to retry yet.".into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::{ContentBlock, MessageContent};
+
+    fn text(role: &str, t: &str) -> Message {
+        Message {
+            role: role.into(),
+            content: MessageContent::Text(t.into()),
+        }
+    }
+
+    #[test]
+    fn test_reaction_action_mapping() {
+        assert_eq!(reaction_action("👍"), Some(ReactionAction::Approve));
+        assert_eq!(reaction_action("👍🏽"), Some(ReactionAction::Approve));
+        assert_eq!(reaction_action("🔁"), Some(ReactionAction::Retry));
+        assert_eq!(reaction_action("👎"), Some(ReactionAction::Retry));
+        assert_eq!(reaction_action("❌"), Some(ReactionAction::Cancel));
+        assert_eq!(reaction_action("✖️"), Some(ReactionAction::Cancel));
+        assert_eq!(reaction_action("❤"), None);
+        assert_eq!(reaction_action(""), None);
+    }
+
+    #[test]
+    fn test_rewind_messages_keeps_last_user_turn() {
+        let mut messages = vec![
+            text("user", "first"),
+            text("assistant", "one"),
+            text("user", "second"),
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "t1".into(),
+                    name: "bash".into(),
+                    input: serde_json::json!({}),
+                }]),
+            },
+            Message {
+                role: "user".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                    tool_use_id: "t1".into(),
+                    content: "ok".into(),
+                    is_error: None,
+                }]),
+            },
+            text("assistant", "two"),
+        ];
+        assert!(rewind_messages(&mut messages));
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(&messages[2].content, MessageContent::Text(t) if t == "second"));
+
+        let mut empty = vec![text("assistant", "hello")];
+        assert!(!rewind_messages(&mut empty));
+    }
+}
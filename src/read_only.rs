@@ -0,0 +1,148 @@
+//! Read-only mode (`read_only` in the config, `/readonly` per chat).
+//!
+//! A read-only turn only sees and may only run low-risk tools; the registry
+//! refuses Medium/High-risk calls (see `ToolAuthContext::read_only`). The
+//! switch is on for every chat when `read_only: true` is configured or a
+//! control chat ran `/readonly on global`, and for single chats set with
+//! `/readonly on`. Any chat can lock itself down, but only control chats can
+//! lift a lock, so it also works as an incident switch.
+
+use crate::db::call_blocking;
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding `/readonly on`.
+pub const READ_ONLY_SETTING_KEY: &str = "read_only";
+/// `chat_settings` row of the runtime global switch.
+const GLOBAL_CHAT_ID: i64 = 0;
+
+const READ_ONLY_USAGE: &str = "Usage: /readonly — show whether tools are read-only here\n/readonly on|off — lock or unlock this chat\n/readonly on|off <chat_id|global> — lock or unlock another chat or every chat (control chats)";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Chat(i64),
+    Global,
+}
+
+/// `Ok(None)` asks for the status, `Ok(Some((on, target)))` switches, and
+/// `Err(())` is a malformed command.
+type Parsed = Result<Option<(bool, Option<Target>)>, ()>;
+
+fn parse(text: &str) -> Option<Parsed> {
+    let mut parts = text.split_whitespace();
+    if parts.next() != Some("/readonly") {
+        return None;
+    }
+    let args: Vec<&str> = parts.collect();
+    let on = match args.first().map(|a| a.to_ascii_lowercase()).as_deref() {
+        None => return Some(Ok(None)),
+        Some("on") => true,
+        Some("off") => false,
+        Some(_) => return Some(Err(())),
+    };
+    let target = match args.get(1..) {
+        Some([]) => None,
+        Some([t]) if t.eq_ignore_ascii_case("global") => Some(Target::Global),
+        Some([t]) => match t.parse() {
+            Ok(id) => Some(Target::Chat(id)),
+            Err(_) => return Some(Err(())),
+        },
+        _ => return Some(Err(())),
+    };
+    Some(Ok(Some((on, target))))
+}
+
+async fn setting_on(state: &AppState, chat_id: i64) -> bool {
+    call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, READ_ONLY_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten()
+    .is_some()
+}
+
+/// Whether `chat_id`'s turns are read-only.
+pub async fn is_read_only(state: &AppState, chat_id: i64) -> bool {
+    state.config.read_only
+        || setting_on(state, GLOBAL_CHAT_ID).await
+        || setting_on(state, chat_id).await
+}
+
+async fn status(state: &AppState, chat_id: i64) -> String {
+    if state.config.read_only {
+        "Read-only mode is on for every chat (read_only in the config): only low-risk tools are available.".into()
+    } else if setting_on(state, GLOBAL_CHAT_ID).await {
+        "Read-only mode is on for every chat (/readonly on global): only low-risk tools are available.".into()
+    } else if setting_on(state, chat_id).await {
+        "Read-only mode is on for this chat: only low-risk tools are available.".into()
+    } else {
+        "Read-only mode is off for this chat.".into()
+    }
+}
+
+/// Handle `/readonly`. Returns `None` when the text is not this command.
+pub async fn handle_read_only_command(
+    state: &AppState,
+    chat_id: i64,
+    text: &str,
+) -> Option<String> {
+    let (on, target) = match parse(text)? {
+        Err(()) => return Some(READ_ONLY_USAGE.to_string()),
+        Ok(None) => return Some(status(state, chat_id).await),
+        Ok(Some(command)) => command,
+    };
+    let is_control = state.config.control_chat_ids.contains(&chat_id);
+    let own_chat = target.is_none() || target == Some(Target::Chat(chat_id));
+    if !(is_control || on && own_chat) {
+        return Some(if own_chat {
+            "Only control chats can turn read-only mode off.".to_string()
+        } else {
+            "Only control chats can change read-only mode for other chats.".to_string()
+        });
+    }
+    let (key_chat, label) = match target {
+        None => (chat_id, "this chat".to_string()),
+        Some(Target::Chat(id)) if id == chat_id => (chat_id, "this chat".to_string()),
+        Some(Target::Chat(id)) => (id, format!("chat {id}")),
+        Some(Target::Global) => (GLOBAL_CHAT_ID, "every chat".to_string()),
+    };
+    if let Err(e) = call_blocking(state.db.clone(), move |db| {
+        db.set_chat_setting(key_chat, READ_ONLY_SETTING_KEY, on.then_some("on"))
+    })
+    .await
+    {
+        return Some(format!("Failed to update read-only mode: {e}"));
+    }
+    let mut reply = if on {
+        format!("Read-only mode is on for {label}: only low-risk tools are available.")
+    } else {
+        format!("Read-only mode is off for {label}.")
+    };
+    if !on && state.config.read_only {
+        reply.push_str(" It stays on everywhere while read_only is set in the config.");
+    }
+    Some(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_read_only_command() {
+        assert_eq!(parse("/read"), None);
+        assert_eq!(parse("/readonly"), Some(Ok(None)));
+        assert_eq!(parse("/readonly ON"), Some(Ok(Some((true, None)))));
+        assert_eq!(
+            parse("/readonly off -100"),
+            Some(Ok(Some((false, Some(Target::Chat(-100))))))
+        );
+        assert_eq!(
+            parse("/readonly on global"),
+            Some(Ok(Some((true, Some(Target::Global)))))
+        );
+        assert_eq!(parse("/readonly maybe"), Some(Err(())));
+        assert_eq!(parse("/readonly on x"), Some(Err(())));
+        assert_eq!(parse("/readonly on 1 2"), Some(Err(())));
+    }
+}
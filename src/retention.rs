@@ -0,0 +1,112 @@
+//! Data retention (`retention:` in the config).
+//!
+//! A background pass every `interval_hours` deletes chat messages, task run
+//! history, finished scheduled tasks and memory audit log rows past their
+//! configured limits, then optionally runs `VACUUM`. `microclaw db prune
+//! --dry-run` reports what a pass would remove without deleting anything.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::config::{Config, RetentionConfig};
+use crate::db::{call_blocking, Database, RetentionCutoffs, RetentionReport};
+use crate::runtime::AppState;
+
+/// Delay before the first pass, so startup isn't slowed by a large delete.
+const FIRST_PASS_DELAY: Duration = Duration::from_secs(300);
+
+pub fn cutoffs(config: &RetentionConfig, now: DateTime<Utc>) -> RetentionCutoffs {
+    let before =
+        |days: u64| (days > 0).then(|| (now - chrono::Duration::days(days as i64)).to_rfc3339());
+    RetentionCutoffs {
+        messages_before: before(config.messages_days),
+        task_runs_before: before(config.task_runs_days),
+        finished_tasks_before: before(config.finished_tasks_days),
+        audit_log_max_rows: (config.audit_log_max_rows > 0)
+            .then_some(config.audit_log_max_rows as i64),
+    }
+}
+
+pub fn format_report(report: &RetentionReport, dry_run: bool) -> String {
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    format!(
+        "{verb} {} messages, {} task runs, {} finished tasks and {} audit log rows.",
+        report.messages, report.task_runs, report.finished_tasks, report.audit_logs
+    )
+}
+
+/// One retention pass. `VACUUM` runs only when something was deleted.
+pub fn run_pass(
+    db: &Database,
+    config: &RetentionConfig,
+    dry_run: bool,
+) -> Result<RetentionReport, crate::error::MicroClawError> {
+    let report = db.apply_retention(&cutoffs(config, Utc::now()), dry_run)?;
+    if !dry_run && config.vacuum && report.total() > 0 {
+        db.vacuum()?;
+    }
+    Ok(report)
+}
+
+pub fn spawn_retention(state: Arc<AppState>) {
+    if !state.config.retention.is_enabled() {
+        return;
+    }
+    let interval = Duration::from_secs(state.config.retention.interval_hours * 3600);
+    tokio::spawn(async move {
+        tokio::time::sleep(FIRST_PASS_DELAY).await;
+        loop {
+            let config = state.config.retention.clone();
+            match call_blocking(state.db.clone(), move |db| run_pass(db, &config, false)).await {
+                Ok(report) if report.total() > 0 => {
+                    info!("Retention: {}", format_report(&report, false))
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Retention pass failed: {e}"),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+/// `microclaw db prune [--dry-run]`: run one pass now.
+pub fn run_cli(dry_run: bool) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    if !config.retention.is_enabled() {
+        println!("No retention limits are configured (see `retention:` in the config).");
+        return Ok(());
+    }
+    crate::db_crypt::init(&config)?;
+    let db = Database::new(&config.runtime_data_dir())?;
+    let report = run_pass(&db, &config.retention, dry_run)?;
+    println!("{}", format_report(&report, dry_run));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cutoffs_skip_disabled_limits() {
+        let now = DateTime::parse_from_rfc3339("2026-03-31T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let config = RetentionConfig {
+            messages_days: 30,
+            audit_log_max_rows: 500,
+            ..Default::default()
+        };
+        let cutoffs = cutoffs(&config, now);
+        assert_eq!(
+            cutoffs.messages_before.as_deref(),
+            Some("2026-03-01T12:00:00+00:00")
+        );
+        assert!(cutoffs.task_runs_before.is_none());
+        assert!(cutoffs.finished_tasks_before.is_none());
+        assert_eq!(cutoffs.audit_log_max_rows, Some(500));
+    }
+}
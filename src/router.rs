@@ -0,0 +1,276 @@
+//! Complexity-based model routing (`model_router`).
+//!
+//! Before a turn, a cheap classifier model labels the latest user message as
+//! simple (short factual answer) or complex (multi-step, tool use, code). The
+//! turn then runs on `small_model` or on the large model. Each decision is
+//! logged to `llm_usage_logs` as `router_small` / `router_large` with the
+//! classifier's tokens, so `/usage` can show the split. Chats opt out with
+//! `/router off`. Channels with their own `model` override are never routed.
+
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::db::call_blocking;
+use crate::llm::{create_provider, LlmProvider};
+use crate::llm_types::{Message, MessageContent, ResponseContentBlock};
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding the per-chat opt-out.
+pub const ROUTER_SETTING_KEY: &str = "router";
+const CLASSIFIER_MAX_TOKENS: u32 = 64;
+const CLASSIFIER_TIMEOUT: Duration = Duration::from_secs(15);
+
+const CLASSIFIER_PROMPT: &str = "You route requests for an AI assistant that has tools (shell, files, web search, scheduling, memory). Read the user's message and reply with exactly one word:
+SIMPLE - a short direct answer from general knowledge or the conversation is enough (greetings, quick facts, definitions, small rewrites).
+COMPLEX - it needs tools, several steps, code, long-form writing or careful reasoning.
+If unsure, reply COMPLEX.";
+
+const ROUTER_USAGE: &str = "Usage: /router — show model routing for this chat\n/router on|off — route this chat's turns by complexity, or always use the large model";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Complexity {
+    Simple,
+    Complex,
+}
+
+impl Complexity {
+    fn request_kind(self) -> &'static str {
+        match self {
+            Complexity::Simple => "router_small",
+            Complexity::Complex => "router_large",
+        }
+    }
+}
+
+/// LLM clients used by the router. Built once at startup.
+pub struct ModelRouter {
+    classifier: Box<dyn LlmProvider>,
+    classifier_model: String,
+    small: Box<dyn LlmProvider>,
+    small_model: String,
+    /// `None` when the large model is the configured `model`.
+    large: Option<(Box<dyn LlmProvider>, String)>,
+}
+
+pub(crate) fn with_model(config: &Config, model: &str, max_tokens: Option<u32>) -> Config {
+    let mut config = config.clone();
+    config.model = model.trim().to_string();
+    if let Some(max_tokens) = max_tokens {
+        config.max_tokens = max_tokens;
+    }
+    config
+}
+
+impl ModelRouter {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let router = &config.model_router;
+        if !router.enabled {
+            return None;
+        }
+        let large = router
+            .large_model
+            .as_deref()
+            .map(str::trim)
+            .filter(|m| !m.is_empty() && *m != config.model)
+            .map(|m| (create_provider(&with_model(config, m, None)), m.to_string()));
+        Some(ModelRouter {
+            classifier: create_provider(&with_model(
+                config,
+                &router.classifier_model,
+                Some(CLASSIFIER_MAX_TOKENS),
+            )),
+            classifier_model: router.classifier_model.trim().to_string(),
+            small: create_provider(&with_model(config, &router.small_model, None)),
+            small_model: router.small_model.trim().to_string(),
+            large,
+        })
+    }
+
+    fn large_model<'a>(&'a self, config: &'a Config) -> &'a str {
+        self.large
+            .as_ref()
+            .map(|(_, model)| model.as_str())
+            .unwrap_or(&config.model)
+    }
+}
+
+fn parse_complexity(answer: &str) -> Complexity {
+    let answer = answer.to_ascii_uppercase();
+    if answer.contains("SIMPLE") && !answer.contains("COMPLEX") {
+        Complexity::Simple
+    } else {
+        Complexity::Complex
+    }
+}
+
+async fn routing_enabled_for_chat(state: &AppState, chat_id: i64) -> bool {
+    let setting = call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, ROUTER_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten();
+    setting.as_deref() != Some("off")
+}
+
+/// Pick the LLM and model for a turn. Returns `None` when routing does not
+/// apply, in which case the channel's usual model is used.
+pub async fn route_turn<'a>(
+    state: &'a AppState,
+    channel: &str,
+    chat_id: i64,
+    query: &str,
+) -> Option<(&'a dyn LlmProvider, String)> {
+    let router = state.router.as_ref()?;
+    if query.trim().is_empty()
+        || state.config.channel_overrides(channel).model.is_some()
+        || !routing_enabled_for_chat(state, chat_id).await
+    {
+        return None;
+    }
+
+    let messages = vec![Message {
+        role: "user".into(),
+        content: MessageContent::Text(query.to_string()),
+    }];
+    let response = tokio::time::timeout(
+        CLASSIFIER_TIMEOUT,
+        router
+            .classifier
+            .send_message(CLASSIFIER_PROMPT, messages, None),
+    )
+    .await;
+    let response = match response {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            warn!("Model router: classifier failed for chat {chat_id}: {e}");
+            return None;
+        }
+        Err(_) => {
+            warn!("Model router: classifier timed out for chat {chat_id}");
+            return None;
+        }
+    };
+    let answer: String = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ResponseContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    let complexity = parse_complexity(&answer);
+
+    let (provider, model) =
+        response.usage_source(&state.config.llm_provider, &router.classifier_model);
+    let (input_tokens, output_tokens) = response
+        .usage
+        .as_ref()
+        .map(|u| (i64::from(u.input_tokens), i64::from(u.output_tokens)))
+        .unwrap_or((0, 0));
+    let channel_name = channel.to_string();
+    let _ = call_blocking(state.db.clone(), move |db| {
+        db.log_llm_usage(
+            chat_id,
+            &channel_name,
+            &provider,
+            &model,
+            input_tokens,
+            output_tokens,
+            complexity.request_kind(),
+        )
+        .map(|_| ())
+    })
+    .await;
+
+    let routed = match complexity {
+        Complexity::Simple => (router.small.as_ref(), router.small_model.clone()),
+        Complexity::Complex => match &router.large {
+            Some((llm, model)) => (llm.as_ref(), model.clone()),
+            None => (state.llm_for(channel), state.config.model.clone()),
+        },
+    };
+    info!(
+        "Model router: chat {chat_id} {complexity:?} -> {}",
+        routed.1
+    );
+    Some(routed)
+}
+
+/// Handle `/router`. Returns `None` when the text is not this command.
+pub async fn handle_router_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let text = text.trim();
+    let arg = match text.split_once(char::is_whitespace) {
+        Some(("/router", rest)) => rest.trim(),
+        None if text == "/router" => "",
+        _ => return None,
+    };
+    let Some(router) = state.router.as_ref() else {
+        return Some(
+            "Model routing is not enabled (set model_router.enabled in the config).".into(),
+        );
+    };
+    let value = match arg {
+        "" => None,
+        "on" => Some(None),
+        "off" => Some(Some("off")),
+        _ => return Some(ROUTER_USAGE.to_string()),
+    };
+    if let Some(value) = value {
+        let stored = value.map(str::to_string);
+        if let Err(e) = call_blocking(state.db.clone(), move |db| {
+            db.set_chat_setting(chat_id, ROUTER_SETTING_KEY, stored.as_deref())
+        })
+        .await
+        {
+            return Some(format!("Failed to update model routing: {e}"));
+        }
+    }
+    let large = router.large_model(&state.config);
+    Some(if routing_enabled_for_chat(state, chat_id).await {
+        format!(
+            "Model routing is on for this chat: {} decides per message; short factual turns use {}, multi-step turns use {large}.",
+            router.classifier_model, router.small_model
+        )
+    } else {
+        format!("Model routing is off for this chat; every turn uses {large}.")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_complexity_defaults_to_complex() {
+        assert_eq!(parse_complexity("SIMPLE"), Complexity::Simple);
+        assert_eq!(parse_complexity(" simple.\n"), Complexity::Simple);
+        assert_eq!(parse_complexity("COMPLEX"), Complexity::Complex);
+        assert_eq!(parse_complexity("SIMPLE or COMPLEX?"), Complexity::Complex);
+        assert_eq!(parse_complexity(""), Complexity::Complex);
+    }
+
+    #[test]
+    fn test_router_from_config() {
+        let mut config: Config = serde_yaml::from_str(
+            "api_key: key\nmodel: big\nmodel_router:\n  enabled: true\n  classifier_model: tiny\n  small_model: small\n",
+        )
+        .unwrap();
+        config.post_deserialize().unwrap();
+        let router = ModelRouter::from_config(&config).unwrap();
+        assert_eq!(router.classifier_model, "tiny");
+        assert_eq!(router.small_model, "small");
+        assert_eq!(router.large_model(&config), "big");
+
+        config.model_router.enabled = false;
+        assert!(ModelRouter::from_config(&config).is_none());
+
+        let mut missing: Config = serde_yaml::from_str(
+            "api_key: key\nmodel_router:\n  enabled: true\n  small_model: small\n",
+        )
+        .unwrap();
+        assert!(missing.post_deserialize().is_err());
+    }
+}
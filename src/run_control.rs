@@ -0,0 +1,254 @@
+//! Per-chat registry of in-flight agent runs, used by `/stop` (and, with
+//! `cancel_on_new_message`, a follow-up message) to cancel them.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::i18n::{t, tf, Language, Msg};
+
+/// Start of the reply returned (and stored in history) when a run is
+/// cancelled, in every language; [`CancelSummary::reply`] adds what was cut
+/// short.
+pub const CANCELLED_PREFIX: &str = "⏹";
+/// Marker appended to the partial assistant turn saved in the session.
+pub const CANCELLED_MARKER: &str = "[turn cancelled by user]";
+
+struct ActiveRun {
+    run_id: u64,
+    sender: Option<String>,
+    token: CancellationToken,
+}
+
+type RunMap = HashMap<i64, Vec<ActiveRun>>;
+
+fn active_runs() -> &'static Mutex<RunMap> {
+    static RUNS: OnceLock<Mutex<RunMap>> = OnceLock::new();
+    RUNS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Registration of one agent run. Dropping it unregisters the run.
+pub struct RunGuard {
+    chat_id: i64,
+    run_id: u64,
+    token: CancellationToken,
+}
+
+impl RunGuard {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        if let Ok(mut runs) = active_runs().lock() {
+            if let Some(list) = runs.get_mut(&self.chat_id) {
+                list.retain(|run| run.run_id != self.run_id);
+                if list.is_empty() {
+                    runs.remove(&self.chat_id);
+                }
+            }
+        }
+    }
+}
+
+/// Register a run for `chat_id`, started by `sender` when it answers a
+/// message (`None` for scheduled and internal runs).
+pub fn begin_run(chat_id: i64, sender: Option<&str>) -> RunGuard {
+    let run_id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+    let token = CancellationToken::new();
+    if let Ok(mut runs) = active_runs().lock() {
+        runs.entry(chat_id).or_default().push(ActiveRun {
+            run_id,
+            sender: sender.map(str::to_string),
+            token: token.clone(),
+        });
+    }
+    RunGuard {
+        chat_id,
+        run_id,
+        token,
+    }
+}
+
+/// Whether a chat has an agent run in flight.
+pub fn has_active_run(chat_id: i64) -> bool {
+    active_runs()
+        .lock()
+        .map(|runs| runs.contains_key(&chat_id))
+        .unwrap_or(false)
+}
+
+/// Cancel every in-flight run for a chat. Returns how many were signalled.
+pub fn cancel_chat_runs(chat_id: i64) -> usize {
+    let Ok(runs) = active_runs().lock() else {
+        return 0;
+    };
+    let Some(list) = runs.get(&chat_id) else {
+        return 0;
+    };
+    for run in list {
+        run.token.cancel();
+    }
+    list.len()
+}
+
+/// Cancel the chat's runs started by `sender`, because they sent a new
+/// message (`cancel_on_new_message`). Returns how many were signalled.
+pub fn cancel_runs_from(chat_id: i64, sender: &str) -> usize {
+    let Ok(runs) = active_runs().lock() else {
+        return 0;
+    };
+    let Some(list) = runs.get(&chat_id) else {
+        return 0;
+    };
+    let mut cancelled = 0;
+    for run in list.iter().filter(|r| r.sender.as_deref() == Some(sender)) {
+        run.token.cancel();
+        cancelled += 1;
+    }
+    cancelled
+}
+
+fn has_run_from(chat_id: i64, sender: &str) -> bool {
+    active_runs()
+        .lock()
+        .map(|runs| {
+            runs.get(&chat_id)
+                .is_some_and(|list| list.iter().any(|r| r.sender.as_deref() == Some(sender)))
+        })
+        .unwrap_or(false)
+}
+
+/// Cancel `sender`'s in-flight runs in the chat and wait (up to 5 seconds)
+/// for them to save their partial turn, so the next run builds on it.
+pub async fn supersede_runs_from(chat_id: i64, sender: &str) -> usize {
+    let cancelled = cancel_runs_from(chat_id, sender);
+    if cancelled > 0 {
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+        while has_run_from(chat_id, sender) && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+    cancelled
+}
+
+/// What a cancelled run was doing, for the reply.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CancelSummary {
+    /// The model was still generating a reply.
+    pub model_call: bool,
+    /// Tools that were running and got aborted.
+    pub interrupted_tools: Vec<String>,
+    /// Tool calls the model asked for that never started.
+    pub skipped_tools: Vec<String>,
+    /// Tool calls that finished earlier in the turn.
+    pub completed_tools: usize,
+}
+
+impl CancelSummary {
+    pub fn reply(&self, lang: Language) -> String {
+        let mut cancelled = Vec::new();
+        if self.model_call {
+            cancelled.push(t(lang, Msg::CancelledReply).to_string());
+        }
+        if !self.interrupted_tools.is_empty() {
+            let tools = self.interrupted_tools.join(", ");
+            cancelled.push(tf(lang, Msg::CancelledRunning, &[("tools", &tools)]));
+        }
+        if !self.skipped_tools.is_empty() {
+            let tools = self.skipped_tools.join(", ");
+            cancelled.push(tf(lang, Msg::CancelledPending, &[("tools", &tools)]));
+        }
+        let mut reply = t(lang, Msg::Stopped).to_string();
+        if !cancelled.is_empty() {
+            let items = cancelled.join(t(lang, Msg::ListSeparator));
+            reply.push_str(&tf(lang, Msg::CancelledList, &[("items", &items)]));
+        }
+        if self.completed_tools > 0 {
+            reply.push_str(&tf(
+                lang,
+                Msg::ToolsFinished,
+                &[("count", &self.completed_tools)],
+            ));
+        }
+        reply
+    }
+}
+
+/// Reply text for a `/stop` command.
+pub fn stop_command_reply(lang: Language, cancelled: usize) -> &'static str {
+    if cancelled == 0 {
+        t(lang, Msg::NothingToStop)
+    } else {
+        t(lang, Msg::Stopping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_chat_runs_signals_only_that_chat() {
+        let a = begin_run(-9001, None);
+        let b = begin_run(-9002, None);
+        assert_eq!(cancel_chat_runs(-9001), 1);
+        assert!(a.token().is_cancelled());
+        assert!(!b.token().is_cancelled());
+    }
+
+    #[test]
+    fn test_dropped_run_is_unregistered() {
+        let run = begin_run(-9003, None);
+        assert!(has_active_run(-9003));
+        drop(run);
+        assert!(!has_active_run(-9003));
+        assert_eq!(cancel_chat_runs(-9003), 0);
+    }
+
+    #[test]
+    fn test_cancel_runs_from_only_hits_that_sender() {
+        let alice = begin_run(-9004, Some("alice"));
+        let bob = begin_run(-9004, Some("bob"));
+        let scheduled = begin_run(-9004, None);
+        assert_eq!(cancel_runs_from(-9004, "alice"), 1);
+        assert!(alice.token().is_cancelled());
+        assert!(!bob.token().is_cancelled());
+        assert!(!scheduled.token().is_cancelled());
+    }
+
+    #[test]
+    fn test_cancel_summary_reply() {
+        assert_eq!(CancelSummary::default().reply(Language::En), "⏹ Stopped.");
+        let summary = CancelSummary {
+            model_call: false,
+            interrupted_tools: vec!["bash".into()],
+            skipped_tools: vec!["write_file".into(), "send_message".into()],
+            completed_tools: 2,
+        };
+        assert_eq!(
+            summary.reply(Language::En),
+            "⏹ Stopped. Cancelled running bash; pending write_file, send_message (not started). 2 tool call(s) had already finished."
+        );
+        let generating = CancelSummary {
+            model_call: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generating.reply(Language::En),
+            "⏹ Stopped. Cancelled the reply being generated."
+        );
+        let reply = summary.reply(Language::Zh);
+        assert!(reply.starts_with(CANCELLED_PREFIX));
+        assert_eq!(
+            reply,
+            "⏹ 已停止。已取消：运行中的 bash；尚未开始的 write_file, send_message。已有 2 个工具调用完成。"
+        );
+    }
+}
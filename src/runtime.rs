@@ -1,19 +1,16 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::anyhow;
-use tracing::info;
-#[cfg(feature = "sqlite-vec")]
-use tracing::warn;
+use tracing::{error, info, warn};
 
 /// Wait for any termination signal: SIGTERM, SIGHUP, or Ctrl-C.
 /// Returns a human-readable label of which signal was received.
 async fn shutdown_signal() -> &'static str {
     use tokio::signal::unix::{signal, SignalKind};
 
-    let mut sigterm =
-        signal(SignalKind::terminate()).expect("failed to register SIGTERM handler");
-    let mut sighup =
-        signal(SignalKind::hangup()).expect("failed to register SIGHUP handler");
+    let mut sigterm = signal(SignalKind::terminate()).expect("failed to register SIGTERM handler");
+    let mut sighup = signal(SignalKind::hangup()).expect("failed to register SIGHUP handler");
 
     tokio::select! {
         _ = tokio::signal::ctrl_c() => "SIGINT (Ctrl-C)",
@@ -23,8 +20,10 @@ async fn shutdown_signal() -> &'static str {
 }
 
 use crate::channel_adapter::ChannelRegistry;
-use crate::channels::telegram::TelegramChannelConfig;
-use crate::channels::{DiscordAdapter, FeishuAdapter, SlackAdapter, TelegramAdapter};
+use crate::channels::telegram::{TelegramChannelConfig, TelegramIdentity};
+use crate::channels::{
+    DiscordAdapter, EmailAdapter, FeishuAdapter, SignalAdapter, SlackAdapter, TelegramAdapter,
+};
 use crate::config::Config;
 use crate::db::Database;
 use crate::embedding::EmbeddingProvider;
@@ -41,19 +40,39 @@ pub struct AppState {
     pub memory: MemoryManager,
     pub skills: SkillManager,
     pub llm: Box<dyn LlmProvider>,
+    /// Clients for channels whose `channels.<name>` section overrides the
+    /// model or `max_tokens`.
+    pub channel_llms: HashMap<String, Box<dyn LlmProvider>>,
+    /// Small/large model router, when `model_router.enabled`.
+    pub router: Option<crate::router::ModelRouter>,
     pub embedding: Option<Arc<dyn EmbeddingProvider>>,
     pub tools: ToolRegistry,
 }
 
-pub async fn run(
+impl AppState {
+    /// LLM client for turns on `channel`.
+    pub fn llm_for(&self, channel: &str) -> &dyn LlmProvider {
+        self.channel_llms.get(channel).unwrap_or(&self.llm).as_ref()
+    }
+}
+
+/// Build the shared state (LLM clients, tools, router) around a channel
+/// registry. Used by [`run`] and by `microclaw chat`.
+pub fn build_state(
     config: Config,
-    db: Database,
+    db: Arc<Database>,
     memory: MemoryManager,
     skills: SkillManager,
     mcp_manager: crate::mcp::McpManager,
-) -> anyhow::Result<()> {
-    let db = Arc::new(db);
+    registry: ChannelRegistry,
+) -> AppState {
+    crate::wire_log::init(&config, &config.data_dir);
     let llm = crate::llm::create_provider(&config);
+    for note in
+        crate::model_caps::limitations(&config.model, &crate::model_caps::for_config(&config))
+    {
+        warn!("Model capability: {note}");
+    }
     let embedding = crate::embedding::create_provider(&config);
     #[cfg(feature = "sqlite-vec")]
     {
@@ -67,6 +86,53 @@ pub async fn run(
         }
     }
 
+    let channel_registry = Arc::new(registry);
+
+    let mut tools = ToolRegistry::new(&config, channel_registry.clone(), db.clone());
+
+    for (server, tool_info) in mcp_manager.all_tools() {
+        tools.add_tool(Box::new(crate::tools::mcp::McpTool::new(server, tool_info)));
+    }
+
+    let mut channel_llms = HashMap::new();
+    for name in config.channels.keys() {
+        if config.channel_overrides(name).changes_llm() {
+            let channel_config = config.for_channel(name);
+            info!("Channel {name} uses model {}", channel_config.model);
+            channel_llms.insert(name.clone(), crate::llm::create_provider(&channel_config));
+        }
+    }
+
+    let router = crate::router::ModelRouter::from_config(&config);
+    if router.is_some() {
+        info!(
+            "Model router enabled: {} classifies, {} answers simple turns",
+            config.model_router.classifier_model, config.model_router.small_model
+        );
+    }
+
+    AppState {
+        config,
+        channel_registry,
+        db,
+        memory,
+        skills,
+        llm,
+        channel_llms,
+        router,
+        embedding,
+        tools,
+    }
+}
+
+pub async fn run(
+    config: Config,
+    db: Database,
+    memory: MemoryManager,
+    skills: SkillManager,
+    mcp_manager: crate::mcp::McpManager,
+) -> anyhow::Result<()> {
+    let db = Arc::new(db);
     // Build channel registry from config
     let mut registry = ChannelRegistry::new();
     let mut telegram_bot: Option<teloxide::Bot> = None;
@@ -81,6 +147,13 @@ pub async fn run(
         }
     }
 
+    let mut extra_telegram_bots = Vec::new();
+    for bot_cfg in &config.telegram_bots {
+        let bot = teloxide::Bot::new(&bot_cfg.bot_token);
+        registry.register(Arc::new(TelegramAdapter::for_bot(bot.clone(), bot_cfg)));
+        extra_telegram_bots.push((bot, TelegramIdentity::extra(bot_cfg)));
+    }
+
     if let Some(dc_cfg) =
         config.channel_config::<crate::channels::discord::DiscordChannelConfig>("discord")
     {
@@ -113,31 +186,58 @@ pub async fn run(
         }
     }
 
-    if config.web_enabled {
-        registry.register(Arc::new(WebAdapter));
+    let mut has_email = false;
+    if let Some(email_cfg) =
+        config.channel_config::<crate::channels::email::EmailChannelConfig>("email")
+    {
+        if !email_cfg.imap_host.trim().is_empty() && !email_cfg.smtp_host.trim().is_empty() {
+            has_email = true;
+            registry.register(Arc::new(EmailAdapter::new(email_cfg, db.clone())));
+        }
     }
 
-    let channel_registry = Arc::new(registry);
-
-    let mut tools = ToolRegistry::new(&config, channel_registry.clone(), db.clone());
+    let mut has_signal = false;
+    if let Some(signal_cfg) =
+        config.channel_config::<crate::channels::signal::SignalChannelConfig>("signal")
+    {
+        if !signal_cfg.account.trim().is_empty() {
+            has_signal = true;
+            registry.register(Arc::new(SignalAdapter::new(signal_cfg)));
+        }
+    }
 
-    for (server, tool_info) in mcp_manager.all_tools() {
-        tools.add_tool(Box::new(crate::tools::mcp::McpTool::new(server, tool_info)));
+    if config.web_enabled {
+        registry.register(Arc::new(WebAdapter));
     }
 
-    let state = Arc::new(AppState {
+    let state = Arc::new(build_state(
         config,
-        channel_registry,
         db,
         memory,
         skills,
-        llm,
-        embedding,
-        tools,
-    });
+        mcp_manager,
+        registry,
+    ));
 
     crate::scheduler::spawn_scheduler(state.clone());
     crate::scheduler::spawn_reflector(state.clone());
+    crate::feeds::spawn_feed_watcher(state.clone());
+    crate::file_watch::spawn_file_watcher(state.clone());
+    crate::heartbeat::spawn_heartbeat(state.clone());
+    crate::memory_consolidation::spawn_memory_consolidation(state.clone());
+    crate::pricing::spawn_pricing_refresh(state.config.clone());
+    crate::codex_auth::spawn_codex_refresh(state.clone());
+    crate::retention::spawn_retention(state.clone());
+    crate::metrics::spawn_metrics_server(&state.config.metrics);
+    crate::otel::init(&state.config.otel);
+    if let Some(knowledge) =
+        crate::knowledge::KnowledgeBase::from_config(&state.config, state.db.clone())
+    {
+        crate::knowledge::spawn_indexer(
+            knowledge,
+            std::time::Duration::from_secs(state.config.knowledge.scan_interval_secs),
+        );
+    }
 
     if let Some(ref token) = discord_token {
         let discord_state = state.clone();
@@ -164,6 +264,22 @@ pub async fn run(
         });
     }
 
+    if has_email {
+        let email_state = state.clone();
+        info!("Starting email channel (IMAP polling)");
+        tokio::spawn(async move {
+            crate::channels::email::start_email_bot(email_state).await;
+        });
+    }
+
+    if has_signal {
+        let signal_state = state.clone();
+        info!("Starting Signal bot (signal-cli)");
+        tokio::spawn(async move {
+            crate::channels::signal::start_signal_bot(signal_state).await;
+        });
+    }
+
     if state.config.web_enabled {
         let web_state = state.clone();
         info!(
@@ -175,9 +291,29 @@ pub async fn run(
         });
     }
 
+    let has_extra_telegram = !extra_telegram_bots.is_empty();
+    for (bot, identity) in extra_telegram_bots {
+        let bot_state = state.clone();
+        info!("Starting Telegram bot {}", identity.channel);
+        tokio::spawn(async move {
+            let channel = identity.channel.clone();
+            if let Err(e) = crate::telegram::start_telegram_bot(bot_state, bot, identity).await {
+                error!("Telegram bot {channel} stopped: {e}");
+            }
+        });
+    }
+
     if let Some(bot) = telegram_bot {
-        crate::telegram::start_telegram_bot(state, bot).await
-    } else if state.config.web_enabled || discord_token.is_some() || has_slack || has_feishu {
+        let identity = TelegramIdentity::primary(&state.config);
+        crate::telegram::start_telegram_bot(state, bot, identity).await
+    } else if state.config.web_enabled
+        || has_extra_telegram
+        || discord_token.is_some()
+        || has_slack
+        || has_feishu
+        || has_email
+        || has_signal
+    {
         info!("Running without Telegram adapter; waiting for other channels");
         let sig = shutdown_signal().await;
         info!("Received {sig}, starting graceful shutdown...");
@@ -192,7 +328,7 @@ pub async fn run(
         Ok(())
     } else {
         Err(anyhow!(
-            "No channel is enabled. Configure Telegram, Discord, Slack, Feishu, or web_enabled=true."
+            "No channel is enabled. Configure Telegram, Discord, Slack, Feishu, Email, Signal, or web_enabled=true."
         ))
     }
 }
@@ -9,18 +9,22 @@ use crate::agent_engine::AgentRequestContext;
 use crate::channel::{
     deliver_and_store_bot_message, get_chat_routing, ChatRouting, ConversationKind,
 };
-use crate::db::call_blocking;
+use crate::db::{call_blocking, ScheduledTask};
+use crate::i18n::{self, Msg};
 use crate::llm_types::{Message, MessageContent, ResponseContentBlock};
 use crate::runtime::AppState;
 use crate::text::floor_char_boundary;
-use crate::{db::Memory, memory_quality};
+use crate::workspace_report::{build_workspace_report, is_workspace_report_task};
+use crate::{db::Memory, memory_quality, preferences};
 
 pub fn spawn_scheduler(state: Arc<AppState>) {
     tokio::spawn(async move {
         info!("Scheduler started");
+        crate::health::beat("scheduler");
         loop {
             tokio::time::sleep(std::time::Duration::from_secs(60)).await;
             run_due_tasks(&state).await;
+            crate::health::beat("scheduler");
         }
     });
 }
@@ -36,92 +40,7 @@ async fn run_due_tasks(state: &Arc<AppState>) {
     };
 
     for task in tasks {
-        info!(
-            "Scheduler: executing task #{} for chat {}",
-            task.id, task.chat_id
-        );
-
-        let started_at = Utc::now();
-        let started_at_str = started_at.to_rfc3339();
-        let routing = get_chat_routing(&state.channel_registry, state.db.clone(), task.chat_id)
-            .await
-            .ok()
-            .flatten()
-            .unwrap_or(ChatRouting {
-                channel_name: "telegram".to_string(),
-                conversation: ConversationKind::Private,
-            });
-
-        // Run agent loop with the task prompt
-        let (success, result_summary) = match process_with_agent(
-            state,
-            AgentRequestContext {
-                caller_channel: &routing.channel_name,
-                chat_id: task.chat_id,
-                chat_type: routing.conversation.as_agent_chat_type(),
-            },
-            Some(&task.prompt),
-            None,
-        )
-        .await
-        {
-            Ok(response) => {
-                if !response.is_empty() {
-                    let _ = deliver_and_store_bot_message(
-                        &state.channel_registry,
-                        state.db.clone(),
-                        &state.config.bot_username,
-                        task.chat_id,
-                        &response,
-                    )
-                    .await;
-                }
-                let summary = if response.len() > 200 {
-                    format!("{}...", &response[..floor_char_boundary(&response, 200)])
-                } else {
-                    response
-                };
-                (true, Some(summary))
-            }
-            Err(e) => {
-                error!("Scheduler: task #{} failed: {e}", task.id);
-                let err_text = format!("Scheduled task #{} failed: {e}", task.id);
-                let _ = deliver_and_store_bot_message(
-                    &state.channel_registry,
-                    state.db.clone(),
-                    &state.config.bot_username,
-                    task.chat_id,
-                    &err_text,
-                )
-                .await;
-                (false, Some(format!("Error: {e}")))
-            }
-        };
-
-        let finished_at = Utc::now();
-        let finished_at_str = finished_at.to_rfc3339();
-        let duration_ms = (finished_at - started_at).num_milliseconds();
-
-        // Log the task run
-        let log_summary = result_summary.clone();
-        let started_for_log = started_at_str.clone();
-        let finished_for_log = finished_at_str.clone();
-        if let Err(e) = call_blocking(state.db.clone(), move |db| {
-            db.log_task_run(
-                task.id,
-                task.chat_id,
-                &started_for_log,
-                &finished_for_log,
-                duration_ms,
-                success,
-                log_summary.as_deref(),
-            )?;
-            Ok(())
-        })
-        .await
-        {
-            error!("Scheduler: failed to log task run for #{}: {e}", task.id);
-        }
+        let started_at_str = run_task(state, &task, &task.prompt).await;
 
         // Compute next run
         let tz: chrono_tz::Tz = state.config.timezone.parse().unwrap_or(chrono_tz::Tz::UTC);
@@ -141,7 +60,7 @@ async fn run_due_tasks(state: &Arc<AppState>) {
             None // one-shot
         };
 
-        let started_for_update = started_at_str.clone();
+        let started_for_update = started_at_str;
         if let Err(e) = call_blocking(state.db.clone(), move |db| {
             db.update_task_after_run(task.id, &started_for_update, next_run.as_deref())?;
             Ok(())
@@ -153,6 +72,192 @@ async fn run_due_tasks(state: &Arc<AppState>) {
     }
 }
 
+/// Run a webhook-triggered task now (see `channels::webhook`); `prompt` is the
+/// task prompt with the request payload appended. The task stays active.
+pub async fn run_webhook_task(state: &Arc<AppState>, task: &ScheduledTask, prompt: &str) {
+    let started_at = run_task(state, task, prompt).await;
+    let task_id = task.id;
+    let next_run = task.next_run.clone();
+    if let Err(e) = call_blocking(state.db.clone(), move |db| {
+        db.update_task_after_run(task_id, &started_at, Some(&next_run))
+    })
+    .await
+    {
+        error!("Scheduler: failed to update task #{task_id}: {e}");
+    }
+}
+
+/// Run a task's prompt for its chat, deliver the result and log the run.
+/// Returns when it started.
+async fn run_task(state: &Arc<AppState>, task: &ScheduledTask, prompt: &str) -> String {
+    info!(
+        "Scheduler: executing task #{} for chat {}",
+        task.id, task.chat_id
+    );
+
+    let started_at = Utc::now();
+    let started_at_str = started_at.to_rfc3339();
+    let routing = get_chat_routing(&state.channel_registry, state.db.clone(), task.chat_id)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(ChatRouting {
+            channel_name: "telegram".to_string(),
+            conversation: ConversationKind::Private,
+        });
+
+    // Built-in templates render without the agent loop; everything else
+    // runs the task prompt through the agent.
+    let outcome = if is_workspace_report_task(&task.prompt) {
+        Ok(build_workspace_report(
+            state.db.clone(),
+            &state.config,
+            &routing.channel_name,
+            task.chat_id,
+        )
+        .await)
+    } else {
+        process_with_agent(
+            state,
+            AgentRequestContext {
+                caller_channel: &routing.channel_name,
+                chat_id: task.chat_id,
+                chat_type: routing.conversation.as_agent_chat_type(),
+                sender: None,
+                sender_id: None,
+            },
+            Some(prompt),
+            None,
+        )
+        .await
+    };
+    let (success, result_summary) = match outcome {
+        Ok(response) => {
+            if !response.is_empty() {
+                let _ = deliver_and_store_bot_message(
+                    &state.channel_registry,
+                    state.db.clone(),
+                    &state.config.bot_username,
+                    task.chat_id,
+                    &response,
+                )
+                .await;
+            }
+            let summary = if response.len() > 200 {
+                format!("{}...", &response[..floor_char_boundary(&response, 200)])
+            } else {
+                response
+            };
+            (true, Some(summary))
+        }
+        Err(e) => {
+            error!("Scheduler: task #{} failed: {e}", task.id);
+            let err_text = format!("Scheduled task #{} failed: {e}", task.id);
+            let _ = deliver_and_store_bot_message(
+                &state.channel_registry,
+                state.db.clone(),
+                &state.config.bot_username,
+                task.chat_id,
+                &err_text,
+            )
+            .await;
+            (false, Some(format!("Error: {e}")))
+        }
+    };
+
+    let finished_at = Utc::now();
+    let finished_at_str = finished_at.to_rfc3339();
+    let duration_ms = (finished_at - started_at).num_milliseconds();
+    crate::metrics::scheduler_run(task.id, duration_ms as f64 / 1000.0, success);
+
+    // Log the task run
+    let log_summary = result_summary.clone();
+    let started_for_log = started_at_str.clone();
+    let finished_for_log = finished_at_str.clone();
+    let (task_id, chat_id) = (task.id, task.chat_id);
+    if let Err(e) = call_blocking(state.db.clone(), move |db| {
+        db.log_task_run(
+            task_id,
+            chat_id,
+            &started_for_log,
+            &finished_for_log,
+            duration_ms,
+            success,
+            log_summary.as_deref(),
+        )?;
+        Ok(())
+    })
+    .await
+    {
+        error!("Scheduler: failed to log task run for #{}: {e}", task.id);
+    }
+    if !success {
+        alert_if_failing(state, task).await;
+    }
+    started_at_str
+}
+
+/// Alert `task_failure_alerts.chat_id` (default: the task's chat) when the
+/// task has just reached `after_failures` failed runs in a row. Later failures
+/// in the same streak stay quiet; a success resets it.
+async fn alert_if_failing(state: &Arc<AppState>, task: &ScheduledTask) {
+    let config = &state.config.task_failure_alerts;
+    if config.after_failures == 0 {
+        return;
+    }
+    let task_id = task.id;
+    let stats =
+        match call_blocking(state.db.clone(), move |db| db.get_task_run_stats(task_id)).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("Scheduler: failed to load run stats for task #{task_id}: {e}");
+                return;
+            }
+        };
+    if stats.consecutive_failures != i64::from(config.after_failures) {
+        return;
+    }
+
+    let alert_chat_id = config.chat_id.unwrap_or(task.chat_id);
+    let lang = i18n::chat_language(state, alert_chat_id).await;
+    let prompt = if task.prompt.len() > 200 {
+        format!(
+            "{}...",
+            &task.prompt[..floor_char_boundary(&task.prompt, 200)]
+        )
+    } else {
+        task.prompt.clone()
+    };
+    let error = stats.last_error.unwrap_or_default();
+    let text = i18n::tf(
+        lang,
+        Msg::TaskFailureAlert,
+        &[
+            ("id", &task_id),
+            ("chat_id", &task.chat_id),
+            ("count", &stats.consecutive_failures),
+            ("prompt", &prompt),
+            ("error", &error.strip_prefix("Error: ").unwrap_or(&error)),
+        ],
+    );
+    info!(
+        "Scheduler: task #{task_id} failed {} times in a row; alerting chat {alert_chat_id}",
+        stats.consecutive_failures
+    );
+    crate::metrics::scheduler_failure_alert(task_id);
+    if let Err(e) = deliver_and_store_bot_message(
+        &state.channel_registry,
+        state.db.clone(),
+        &state.config.bot_username,
+        alert_chat_id,
+        &text,
+    )
+    .await
+    {
+        error!("Scheduler: failed to send failure alert for task #{task_id}: {e}");
+    }
+}
+
 const REFLECTOR_SYSTEM_PROMPT: &str = r#"You are a memory extraction specialist. Extract durable, factual information from conversations.
 
 Rules:
@@ -353,6 +458,8 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
         return;
     }
     let latest_message_ts = messages.last().map(|m| m.timestamp.clone());
+    // Memories and preferences belong to the chat's linked identity, if any.
+    let memory_chat_id = crate::identity::identity_chat_id(state.db.clone(), chat_id).await;
 
     // 3. Format conversation for the LLM
     let conversation = messages
@@ -363,7 +470,7 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
 
     // 4. Load existing memories (needed for dedup and to pass to LLM for merge)
     let existing = match call_blocking(state.db.clone(), move |db| {
-        db.get_all_memories_for_chat(Some(chat_id))
+        db.get_all_memories_for_chat(Some(memory_chat_id))
     })
     .await
     {
@@ -382,16 +489,36 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
         format!("\n\nExisting memories (use supersedes_id to replace stale ones):\n{lines}")
     };
 
+    let preferences = call_blocking(state.db.clone(), move |db| {
+        db.get_user_preferences(memory_chat_id)
+    })
+    .await
+    .unwrap_or_default();
+    let preferences_hint = if preferences.is_empty() {
+        String::new()
+    } else {
+        let lines = preferences
+            .iter()
+            .map(|p| format!("  {}: {}", p.key, p.value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("\n\nCurrent preferences (only output a preference when it changed):\n{lines}")
+    };
+
     // 5. Call LLM directly (no tools, no session)
     let user_msg = Message {
         role: "user".into(),
         content: MessageContent::Text(format!(
-            "Extract memories from this conversation (chat_id={chat_id}):{existing_hint}\n\nConversation:\n{conversation}"
+            "Extract memories from this conversation (chat_id={chat_id}):{existing_hint}{preferences_hint}\n\nConversation:\n{conversation}"
         )),
     };
+    let system_prompt = format!(
+        "{REFLECTOR_SYSTEM_PROMPT}\n\n{}",
+        preferences::reflector_instructions()
+    );
     let response = match state
         .llm
-        .send_message(REFLECTOR_SYSTEM_PROMPT, vec![user_msg], None)
+        .send_message(&system_prompt, vec![user_msg], None)
         .await
     {
         Ok(r) => r,
@@ -488,6 +615,8 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
         }
     };
 
+    preferences::apply_inferred_preferences(state.db.clone(), memory_chat_id, &extracted).await;
+
     if extracted.is_empty() {
         if let Some(ts) = latest_message_ts {
             let _ = call_blocking(state.db.clone(), move |db| {
@@ -608,7 +737,7 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
                 if let Some(provider) = &state.embedding {
                     if let Ok(query_vec) = provider.embed(&content).await {
                         let nearest = call_blocking(state.db.clone(), move |db| {
-                            db.knn_memories(chat_id, &query_vec, 1)
+                            db.knn_memories(memory_chat_id, &query_vec, 1)
                         })
                         .await
                         .ok()
@@ -673,7 +802,13 @@ async fn reflect_for_chat(state: &Arc<AppState>, chat_id: i64) {
         let db_content = content.clone();
         let category = category.to_string();
         let inserted_id = call_blocking(state.db.clone(), move |db| {
-            db.insert_memory_with_metadata(Some(chat_id), &db_content, &category, "reflector", 0.68)
+            db.insert_memory_with_metadata(
+                Some(memory_chat_id),
+                &db_content,
+                &category,
+                "reflector",
+                0.68,
+            )
         })
         .await
         .ok();
@@ -0,0 +1,134 @@
+//! Secret references in config values.
+//!
+//! Credential fields may hold a reference instead of the secret itself:
+//!
+//! - `env:VAR` reads an environment variable
+//! - `file:/path` reads a file (trailing newline removed)
+//! - `exec:command` runs a shell command and takes its stdout
+//! - `keychain:service/account` reads the OS keychain (macOS `security`,
+//!   Linux `secret-tool`)
+//! - `enc:v1:...` is decrypted with the config passphrase
+//!   (see `crate::config_crypt`)
+//!
+//! References are resolved once when the config is loaded.
+
+use std::process::Command;
+
+const PREFIXES: &[&str] = &["env:", "file:", "exec:", "keychain:", "enc:"];
+
+pub fn is_reference(value: &str) -> bool {
+    PREFIXES.iter().any(|p| value.starts_with(p))
+}
+
+fn trim_newline(s: &str) -> &str {
+    s.trim_end_matches(['\r', '\n'])
+}
+
+fn run(mut command: Command, what: &str) -> Result<String, String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to run {what}: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{what} exited with {}: {}",
+            output.status,
+            trim_newline(&stderr)
+        ));
+    }
+    String::from_utf8(output.stdout)
+        .map(|out| trim_newline(&out).to_string())
+        .map_err(|_| format!("{what} printed non-UTF-8 output"))
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+fn keychain(spec: &str) -> Result<String, String> {
+    let (service, account) = spec
+        .split_once('/')
+        .filter(|(s, a)| !s.is_empty() && !a.is_empty())
+        .ok_or("keychain references look like keychain:service/account")?;
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("security");
+        cmd.args(["find-generic-password", "-s", service, "-a", account, "-w"]);
+        cmd
+    } else if cfg!(target_os = "linux") {
+        let mut cmd = Command::new("secret-tool");
+        cmd.args(["lookup", "service", service, "account", account]);
+        cmd
+    } else {
+        return Err("keychain references are supported on macOS and Linux only".into());
+    };
+    cmd.stdin(std::process::Stdio::null());
+    run(cmd, "the keychain lookup")
+}
+
+/// Resolve a reference to the secret it points at. Errors never include the
+/// secret.
+pub fn resolve(reference: &str) -> Result<String, String> {
+    let secret = if let Some(var) = reference.strip_prefix("env:") {
+        std::env::var(var.trim())
+            .map_err(|_| format!("environment variable {} is not set", var.trim()))?
+    } else if let Some(path) = reference.strip_prefix("file:") {
+        let path = path.trim();
+        std::fs::read_to_string(path)
+            .map(|s| trim_newline(&s).to_string())
+            .map_err(|e| format!("cannot read {path}: {e}"))?
+    } else if let Some(command) = reference.strip_prefix("exec:") {
+        run(shell(command.trim()), "the exec: command")?
+    } else if let Some(spec) = reference.strip_prefix("keychain:") {
+        keychain(spec.trim())?
+    } else if reference.starts_with("enc:") {
+        crate::config_crypt::decrypt(reference.trim())?
+    } else {
+        return Ok(reference.to_string());
+    };
+    if secret.is_empty() {
+        return Err(format!("{reference} resolved to an empty value"));
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_references() {
+        assert!(is_reference("env:API_KEY"));
+        assert!(!is_reference("sk-plain"));
+        assert_eq!(resolve("sk-plain").unwrap(), "sk-plain");
+
+        let path = std::env::temp_dir().join(format!("mc_secret_{}", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+        assert_eq!(
+            resolve(&format!("file:{}", path.display())).unwrap(),
+            "from-file"
+        );
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(resolve("env:PATH").unwrap(), std::env::var("PATH").unwrap());
+        assert!(resolve("env:MICROCLAW_SURELY_UNSET_VAR")
+            .unwrap_err()
+            .contains("is not set"));
+        assert!(resolve("keychain:no-account").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_exec() {
+        assert_eq!(resolve("exec:echo from-exec").unwrap(), "from-exec");
+        assert!(resolve("exec:exit 3").unwrap_err().contains("exited"));
+        assert!(resolve("exec:true").unwrap_err().contains("empty value"));
+    }
+}
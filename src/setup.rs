@@ -17,10 +17,12 @@ use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
 use ratatui::DefaultTerminal;
 
+use crate::claude_auth::{is_claude_oauth_provider, resolve_claude_oauth_token};
 use crate::codex_auth::{
     codex_config_default_openai_base_url, is_openai_codex_provider, provider_allows_empty_api_key,
     resolve_openai_codex_auth,
 };
+use crate::doctor::{CheckStatus, DoctorCheck};
 use crate::error::MicroClawError;
 use crate::text::floor_char_boundary;
 
@@ -106,6 +108,60 @@ const DYNAMIC_CHANNELS: &[DynamicChannelDef] = &[
             },
         ],
     },
+    DynamicChannelDef {
+        name: "email",
+        presence_keys: &["imap_host", "smtp_host"],
+        fields: &[
+            ChannelFieldDef {
+                yaml_key: "imap_host",
+                label: "Email IMAP host (TLS, port 993)",
+                default: "",
+                secret: false,
+                required: true,
+            },
+            ChannelFieldDef {
+                yaml_key: "smtp_host",
+                label: "Email SMTP host (TLS, port 465)",
+                default: "",
+                secret: false,
+                required: true,
+            },
+            ChannelFieldDef {
+                yaml_key: "username",
+                label: "Email account username",
+                default: "",
+                secret: false,
+                required: true,
+            },
+            ChannelFieldDef {
+                yaml_key: "password",
+                label: "Email account password",
+                default: "",
+                secret: true,
+                required: true,
+            },
+        ],
+    },
+    DynamicChannelDef {
+        name: "signal",
+        presence_keys: &["account"],
+        fields: &[
+            ChannelFieldDef {
+                yaml_key: "account",
+                label: "Signal bot number registered with signal-cli (+E.164)",
+                default: "",
+                secret: false,
+                required: true,
+            },
+            ChannelFieldDef {
+                yaml_key: "http_url",
+                label: "signal-cli daemon HTTP URL",
+                default: "http://127.0.0.1:8080",
+                secret: false,
+                required: false,
+            },
+        ],
+    },
 ];
 
 /// Build the setup-wizard field key from channel name + yaml key.
@@ -116,6 +172,8 @@ fn dynamic_field_key(channel: &str, yaml_key: &str) -> String {
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum ProviderProtocol {
     Anthropic,
+    Gemini,
+    Bedrock,
     OpenAiCompat,
 }
 
@@ -161,6 +219,13 @@ const PROVIDER_PRESETS: &[ProviderPreset] = &[
         default_base_url: "",
         models: &["claude-sonnet-4-5-20250929", "claude-opus-4-6-20260205"],
     },
+    ProviderPreset {
+        id: "claude-oauth",
+        label: "Claude Pro/Max (claude login)",
+        protocol: ProviderProtocol::Anthropic,
+        default_base_url: "",
+        models: &["claude-sonnet-4-5-20250929", "claude-opus-4-6-20260205"],
+    },
     ProviderPreset {
         id: "ollama",
         label: "Ollama (local)",
@@ -175,6 +240,13 @@ const PROVIDER_PRESETS: &[ProviderPreset] = &[
         default_base_url: "https://generativelanguage.googleapis.com/v1beta/openai",
         models: &["gemini-2.5-pro", "gemini-2.5-flash"],
     },
+    ProviderPreset {
+        id: "gemini",
+        label: "Google Gemini (native API)",
+        protocol: ProviderProtocol::Gemini,
+        default_base_url: "",
+        models: &["gemini-2.5-flash", "gemini-2.5-pro"],
+    },
     ProviderPreset {
         id: "alibaba",
         label: "Alibaba Cloud (Qwen / DashScope)",
@@ -214,11 +286,11 @@ const PROVIDER_PRESETS: &[ProviderPreset] = &[
     ProviderPreset {
         id: "bedrock",
         label: "Amazon AWS Bedrock",
-        protocol: ProviderProtocol::OpenAiCompat,
-        default_base_url: "https://bedrock-runtime.YOUR-REGION.amazonaws.com/openai/v1",
+        protocol: ProviderProtocol::Bedrock,
+        default_base_url: "https://bedrock-runtime.us-east-1.amazonaws.com",
         models: &[
-            "anthropic.claude-opus-4-6-v1",
-            "anthropic.claude-sonnet-4-5-v2",
+            "us.anthropic.claude-sonnet-4-5-20250929-v1:0",
+            "us.anthropic.claude-opus-4-6-v1",
         ],
     },
     ProviderPreset {
@@ -340,6 +412,9 @@ struct SetupApp {
     completed: bool,
     backup_path: Option<String>,
     completion_summary: Vec<String>,
+    /// Chat being typed for the post-save test message.
+    test_target: Option<String>,
+    test_result: Vec<String>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -367,7 +442,15 @@ impl SetupApp {
 
     fn new() -> Self {
         // Try loading from existing config file first, then fall back to env vars
-        let existing = Self::load_existing_config();
+        Self::with_existing(Self::load_existing_config())
+    }
+
+    /// Wizard state for a config that was already loaded.
+    fn from_config(config: crate::config::Config) -> Self {
+        Self::with_existing(Self::values_from_config(config))
+    }
+
+    fn with_existing(existing: HashMap<String, String>) -> Self {
         let provider = existing
             .get("LLM_PROVIDER")
             .cloned()
@@ -548,6 +631,8 @@ impl SetupApp {
             completed: false,
             backup_path: None,
             completion_summary: Vec::new(),
+            test_target: None,
+            test_result: Vec::new(),
         };
 
         // Generate fields for dynamic channels (slack, feishu, etc.)
@@ -586,81 +671,7 @@ impl SetupApp {
         if let Some(path) = yaml_path {
             if let Ok(content) = fs::read_to_string(path) {
                 if let Ok(config) = serde_yaml::from_str::<crate::config::Config>(&content) {
-                    let mut map = HashMap::new();
-                    let mut enabled = Vec::new();
-                    if !config.telegram_bot_token.trim().is_empty() {
-                        enabled.push("telegram");
-                    }
-                    if config
-                        .discord_bot_token
-                        .as_deref()
-                        .map(|v| !v.trim().is_empty())
-                        .unwrap_or(false)
-                    {
-                        enabled.push("discord");
-                    }
-                    for ch in DYNAMIC_CHANNELS {
-                        if config.channels.contains_key(ch.name) {
-                            enabled.push(ch.name);
-                        }
-                    }
-                    map.insert("ENABLED_CHANNELS".into(), enabled.join(","));
-                    map.insert("TELEGRAM_BOT_TOKEN".into(), config.telegram_bot_token);
-                    map.insert("BOT_USERNAME".into(), config.bot_username);
-                    map.insert(
-                        "DISCORD_BOT_TOKEN".into(),
-                        config.discord_bot_token.unwrap_or_default(),
-                    );
-                    // Extract dynamic channel configs
-                    for ch in DYNAMIC_CHANNELS {
-                        if let Some(ch_map) = config.channels.get(ch.name) {
-                            for f in ch.fields {
-                                if let Some(v) = ch_map.get(f.yaml_key).and_then(|v| v.as_str()) {
-                                    let key = dynamic_field_key(ch.name, f.yaml_key);
-                                    map.insert(key, v.to_string());
-                                }
-                            }
-                        }
-                    }
-                    map.insert("LLM_PROVIDER".into(), config.llm_provider);
-                    map.insert("LLM_API_KEY".into(), config.api_key);
-                    if !config.model.is_empty() {
-                        map.insert("LLM_MODEL".into(), config.model);
-                    }
-                    if let Some(url) = config.llm_base_url {
-                        map.insert("LLM_BASE_URL".into(), url);
-                    }
-                    map.insert("DATA_DIR".into(), config.data_dir);
-                    map.insert("TIMEZONE".into(), config.timezone);
-                    map.insert("WORKING_DIR".into(), config.working_dir);
-                    map.insert(
-                        "REFLECTOR_ENABLED".into(),
-                        config.reflector_enabled.to_string(),
-                    );
-                    map.insert(
-                        "REFLECTOR_INTERVAL_MINS".into(),
-                        config.reflector_interval_mins.to_string(),
-                    );
-                    map.insert(
-                        "MEMORY_TOKEN_BUDGET".into(),
-                        config.memory_token_budget.to_string(),
-                    );
-                    if let Some(v) = config.embedding_provider {
-                        map.insert("EMBEDDING_PROVIDER".into(), v);
-                    }
-                    if let Some(v) = config.embedding_api_key {
-                        map.insert("EMBEDDING_API_KEY".into(), v);
-                    }
-                    if let Some(v) = config.embedding_base_url {
-                        map.insert("EMBEDDING_BASE_URL".into(), v);
-                    }
-                    if let Some(v) = config.embedding_model {
-                        map.insert("EMBEDDING_MODEL".into(), v);
-                    }
-                    if let Some(v) = config.embedding_dim {
-                        map.insert("EMBEDDING_DIM".into(), v.to_string());
-                    }
-                    return map;
+                    return Self::values_from_config(config);
                 }
             }
         }
@@ -668,6 +679,85 @@ impl SetupApp {
         HashMap::new()
     }
 
+    /// Wizard field values for `config`.
+    fn values_from_config(config: crate::config::Config) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        let mut enabled = Vec::new();
+        if !config.telegram_bot_token.trim().is_empty() {
+            enabled.push("telegram");
+        }
+        if config
+            .discord_bot_token
+            .as_deref()
+            .map(|v| !v.trim().is_empty())
+            .unwrap_or(false)
+        {
+            enabled.push("discord");
+        }
+        for ch in DYNAMIC_CHANNELS {
+            if config.channels.contains_key(ch.name) {
+                enabled.push(ch.name);
+            }
+        }
+        map.insert("ENABLED_CHANNELS".into(), enabled.join(","));
+        map.insert("TELEGRAM_BOT_TOKEN".into(), config.telegram_bot_token);
+        map.insert("BOT_USERNAME".into(), config.bot_username);
+        map.insert(
+            "DISCORD_BOT_TOKEN".into(),
+            config.discord_bot_token.unwrap_or_default(),
+        );
+        // Extract dynamic channel configs
+        for ch in DYNAMIC_CHANNELS {
+            if let Some(ch_map) = config.channels.get(ch.name) {
+                for f in ch.fields {
+                    if let Some(v) = ch_map.get(f.yaml_key).and_then(|v| v.as_str()) {
+                        let key = dynamic_field_key(ch.name, f.yaml_key);
+                        map.insert(key, v.to_string());
+                    }
+                }
+            }
+        }
+        map.insert("LLM_PROVIDER".into(), config.llm_provider);
+        map.insert("LLM_API_KEY".into(), config.api_key);
+        if !config.model.is_empty() {
+            map.insert("LLM_MODEL".into(), config.model);
+        }
+        if let Some(url) = config.llm_base_url {
+            map.insert("LLM_BASE_URL".into(), url);
+        }
+        map.insert("DATA_DIR".into(), config.data_dir);
+        map.insert("TIMEZONE".into(), config.timezone);
+        map.insert("WORKING_DIR".into(), config.working_dir);
+        map.insert(
+            "REFLECTOR_ENABLED".into(),
+            config.reflector_enabled.to_string(),
+        );
+        map.insert(
+            "REFLECTOR_INTERVAL_MINS".into(),
+            config.reflector_interval_mins.to_string(),
+        );
+        map.insert(
+            "MEMORY_TOKEN_BUDGET".into(),
+            config.memory_token_budget.to_string(),
+        );
+        if let Some(v) = config.embedding_provider {
+            map.insert("EMBEDDING_PROVIDER".into(), v);
+        }
+        if let Some(v) = config.embedding_api_key {
+            map.insert("EMBEDDING_API_KEY".into(), v);
+        }
+        if let Some(v) = config.embedding_base_url {
+            map.insert("EMBEDDING_BASE_URL".into(), v);
+        }
+        if let Some(v) = config.embedding_model {
+            map.insert("EMBEDDING_MODEL".into(), v);
+        }
+        if let Some(v) = config.embedding_dim {
+            map.insert("EMBEDDING_DIM".into(), v.to_string());
+        }
+        map
+    }
+
     fn next(&mut self) {
         if self.selected + 1 < self.fields.len() {
             self.selected += 1;
@@ -797,6 +887,12 @@ impl SetupApp {
                 ));
             }
         }
+        if is_claude_oauth_provider(&provider) && !self.field_value("LLM_API_KEY").trim().is_empty()
+        {
+            return Err(MicroClawError::Config(
+                "claude-oauth ignores LLM_API_KEY here. Run `claude login` instead.".into(),
+            ));
+        }
 
         let timezone = self.field_value("TIMEZONE");
         let tz = if timezone.is_empty() {
@@ -855,7 +951,16 @@ impl SetupApp {
 
     fn validate_online(&self) -> Result<Vec<String>, MicroClawError> {
         let tg_enabled = self.channel_enabled("telegram");
-        let tg_token = self.field_value("TELEGRAM_BOT_TOKEN");
+        // Fields may hold secret references (`env:`, `file:`, ...).
+        let resolve = |key: &str| {
+            crate::secrets::resolve(&self.field_value(key))
+                .map_err(|e| MicroClawError::Config(format!("{key}: {e}")))
+        };
+        let tg_token = if tg_enabled {
+            resolve("TELEGRAM_BOT_TOKEN")?
+        } else {
+            String::new()
+        };
         let env_username = self
             .field_value("BOT_USERNAME")
             .trim_start_matches('@')
@@ -864,8 +969,10 @@ impl SetupApp {
         let (api_key, codex_account_id) = if is_openai_codex_provider(&provider) {
             let auth = resolve_openai_codex_auth("")?;
             (auth.bearer_token, auth.account_id)
+        } else if is_claude_oauth_provider(&provider) {
+            (resolve_claude_oauth_token()?, None)
         } else {
-            (self.field_value("LLM_API_KEY"), None)
+            (resolve("LLM_API_KEY")?, None)
         };
         let base_url = self.field_value("LLM_BASE_URL");
         let model = self.field_value("LLM_MODEL");
@@ -1226,6 +1333,9 @@ impl SetupApp {
     }
 }
 
+/// Prefix of online check results that could not run.
+const LLM_CHECK_SKIPPED: &str = "LLM check skipped";
+
 #[allow(clippy::too_many_arguments)]
 fn perform_online_validation(
     telegram_enabled: bool,
@@ -1237,44 +1347,71 @@ fn perform_online_validation(
     model: &str,
     codex_account_id: Option<&str>,
 ) -> Result<Vec<String>, MicroClawError> {
+    let client = validation_client()?;
     let mut checks = Vec::new();
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .build()?;
-
-    // --- Telegram validation (optional) ---
     if telegram_enabled {
-        let tg_resp: serde_json::Value = client
-            .get(format!("https://api.telegram.org/bot{tg_token}/getMe"))
-            .send()?
-            .json()?;
-        let ok = tg_resp.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
-        if !ok {
-            return Err(MicroClawError::Config(
-                "Telegram getMe failed (check TELEGRAM_BOT_TOKEN)".into(),
-            ));
-        }
-        let actual_username = tg_resp
-            .get("result")
-            .and_then(|r| r.get("username"))
-            .and_then(|u| u.as_str())
-            .unwrap_or_default()
-            .to_string();
-        if !env_username.is_empty()
-            && !actual_username.is_empty()
-            && env_username != actual_username
-        {
-            checks.push(format!(
-                "Telegram OK (token user={actual_username}, configured={env_username})"
-            ));
-        } else {
-            checks.push(format!("Telegram OK ({actual_username})"));
-        }
+        checks.push(check_telegram_online(&client, tg_token, env_username)?);
     } else {
         checks.push("Telegram skipped (disabled)".into());
     }
+    checks.push(check_llm_online(
+        &client,
+        provider,
+        api_key,
+        base_url,
+        model,
+        codex_account_id,
+    )?);
+    Ok(checks)
+}
+
+fn validation_client() -> Result<reqwest::blocking::Client, MicroClawError> {
+    Ok(reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?)
+}
+
+/// Call Telegram `getMe` with the bot token.
+fn check_telegram_online(
+    client: &reqwest::blocking::Client,
+    tg_token: &str,
+    env_username: &str,
+) -> Result<String, MicroClawError> {
+    let tg_resp: serde_json::Value = client
+        .get(format!("https://api.telegram.org/bot{tg_token}/getMe"))
+        .send()?
+        .json()?;
+    let ok = tg_resp.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
+    if !ok {
+        return Err(MicroClawError::Config(
+            "Telegram getMe failed (check TELEGRAM_BOT_TOKEN)".into(),
+        ));
+    }
+    let actual_username = tg_resp
+        .get("result")
+        .and_then(|r| r.get("username"))
+        .and_then(|u| u.as_str())
+        .unwrap_or_default()
+        .to_string();
+    if !env_username.is_empty() && !actual_username.is_empty() && env_username != actual_username {
+        Ok(format!(
+            "Telegram OK (token user={actual_username}, configured={env_username})"
+        ))
+    } else {
+        Ok(format!("Telegram OK ({actual_username})"))
+    }
+}
 
-    // --- LLM validation: send a minimal "hi" message ---
+/// Send a minimal "hi" message to the provider. A line starting with
+/// [`LLM_CHECK_SKIPPED`] means the check can't run from here.
+fn check_llm_online(
+    client: &reqwest::blocking::Client,
+    provider: &str,
+    api_key: &str,
+    base_url: &str,
+    model: &str,
+    codex_account_id: Option<&str>,
+) -> Result<String, MicroClawError> {
     let preset = find_provider_preset(provider);
     let protocol = provider_protocol(provider);
     let model = if model.is_empty() {
@@ -1297,13 +1434,19 @@ fn perform_online_validation(
             "max_tokens": 1,
             "messages": [{"role": "user", "content": "hi"}]
         });
-        let resp = client
+        let req = client
             .post(format!("{base}/v1/messages"))
-            .header("x-api-key", api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .body(body.to_string())
-            .send()?;
+            .body(body.to_string());
+        // Subscription tokens are bearer tokens behind the OAuth beta flag.
+        let req = if is_claude_oauth_provider(provider) {
+            req.bearer_auth(api_key)
+                .header("anthropic-beta", "oauth-2025-04-20")
+        } else {
+            req.header("x-api-key", api_key)
+        };
+        let resp = req.send()?;
         let status = resp.status();
         if !status.is_success() {
             let text = resp.text().unwrap_or_default();
@@ -1320,7 +1463,82 @@ fn perform_online_validation(
                 "LLM validation failed: {detail}"
             )));
         }
-        checks.push(format!("LLM OK (anthropic, model={model})"));
+        Ok(format!("LLM OK ({provider}, model={model})"))
+    } else if protocol == ProviderProtocol::Gemini {
+        let base = if base_url.is_empty() {
+            crate::gemini::DEFAULT_GEMINI_BASE_URL.to_string()
+        } else {
+            base_url.trim_end_matches('/').to_string()
+        };
+        let body = serde_json::json!({
+            "contents": [{"role": "user", "parts": [{"text": "hi"}]}],
+            "generationConfig": {"maxOutputTokens": 1}
+        });
+        let resp = client
+            .post(format!("{base}/models/{model}:generateContent"))
+            .header("x-goog-api-key", api_key)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().unwrap_or_default();
+            let detail = serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .and_then(|v| {
+                    v.pointer("/error/message")
+                        .and_then(|m| m.as_str())
+                        .map(|s| s.to_string())
+                })
+                .unwrap_or_else(|| format!("HTTP {status}"));
+            return Err(MicroClawError::Config(format!(
+                "LLM validation failed: {detail}"
+            )));
+        }
+        Ok(format!("LLM OK (gemini, model={model})"))
+    } else if protocol == ProviderProtocol::Bedrock && !base_url.contains("/openai") {
+        let base = if base_url.is_empty() {
+            preset.map(|p| p.default_base_url).unwrap_or_default()
+        } else {
+            base_url.trim_end_matches('/')
+        };
+        let Some((creds, source)) = crate::bedrock::static_credentials(None) else {
+            return Ok(format!(
+                "{LLM_CHECK_SKIPPED} (bedrock: no AWS credentials in the environment or ~/.aws; an instance role is tried at runtime)"
+            ));
+        };
+        let region = crate::bedrock::resolve_region(None, Some(base), None).ok_or_else(|| {
+            MicroClawError::Config(
+                "LLM validation failed: set AWS_REGION or use a https://bedrock-runtime.<region>.amazonaws.com base URL".into(),
+            )
+        })?;
+        let url = reqwest::Url::parse(&crate::bedrock::converse_url(base, &model))
+            .map_err(|e| MicroClawError::Config(format!("Invalid Bedrock base URL: {e}")))?;
+        let body = serde_json::json!({
+            "messages": [{"role": "user", "content": [{"text": "hi"}]}],
+            "inferenceConfig": {"maxTokens": 1}
+        })
+        .to_string();
+        let mut request = client
+            .post(url.clone())
+            .header("content-type", "application/json");
+        for (name, value) in
+            crate::bedrock::sign_post(&creds, &url, body.as_bytes(), &region, chrono::Utc::now())
+        {
+            request = request.header(name, value);
+        }
+        let resp = request.body(body).send()?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().unwrap_or_default();
+            return Err(MicroClawError::Config(format!(
+                "LLM validation failed: {}",
+                crate::bedrock::error_message(status, &text)
+            )));
+        }
+        Ok(format!(
+            "LLM OK (bedrock, model={model}, region={region}, credentials={source})"
+        ))
     } else {
         let base = resolve_openai_compat_validation_base(provider, base_url, preset);
         let resp = if is_openai_codex_provider(provider) {
@@ -1344,6 +1562,26 @@ fn perform_online_validation(
                 }
             }
             req.send()?
+        } else if crate::azure::is_azure_provider(provider) {
+            if api_key.trim().is_empty() {
+                return Ok(format!(
+                    "{LLM_CHECK_SKIPPED} (azure: Entra ID token is requested at runtime)"
+                ));
+            }
+            let body = serde_json::json!({
+                "max_tokens": 1,
+                "messages": [{"role": "user", "content": "hi"}]
+            });
+            let url = crate::azure::with_api_version(
+                &format!("{}/chat/completions", base.trim_end_matches('/')),
+                None,
+            );
+            client
+                .post(url)
+                .header("content-type", "application/json")
+                .header("api-key", api_key)
+                .body(body.to_string())
+                .send()?
         } else {
             let body = serde_json::json!({
                 "model": model,
@@ -1375,10 +1613,8 @@ fn perform_online_validation(
                 "LLM validation failed: {detail}"
             )));
         }
-        checks.push(format!("LLM OK (openai-compatible, model={model})"));
+        Ok(format!("LLM OK (openai-compatible, model={model})"))
     }
-
-    Ok(checks)
 }
 
 fn resolve_openai_compat_validation_base(
@@ -1532,7 +1768,7 @@ fn save_config_yaml(
     }
 
     yaml.push_str(
-        "# LLM provider (anthropic, openai-codex, ollama, openai, openrouter, deepseek, google, etc.)\n",
+        "# LLM provider (anthropic, claude-oauth, openai-codex, ollama, openai, openrouter, deepseek, google, etc.)\n",
     );
     yaml.push_str(&format!("llm_provider: \"{}\"\n", get("LLM_PROVIDER")));
     yaml.push_str("# API key for LLM provider\n");
@@ -1642,7 +1878,7 @@ fn save_config_yaml(
 
 fn draw_ui(frame: &mut ratatui::Frame<'_>, app: &SetupApp) {
     if app.completed {
-        let done = Paragraph::new(vec![
+        let mut lines = vec![
             Line::from(Span::styled(
                 "✅ Setup saved successfully",
                 Style::default()
@@ -1664,12 +1900,27 @@ fn draw_ui(frame: &mut ratatui::Frame<'_>, app: &SetupApp) {
                 app.backup_path.as_deref().unwrap_or("none")
             )),
             Line::from(""),
+        ];
+        if let Some(target) = &app.test_target {
+            lines.push(Line::from(format!(
+                "Test chat (telegram:<chat_id>, discord:<channel_id> or slack:<channel_id>): {target}_"
+            )));
+            lines.push(Line::from("Enter to send, Esc to cancel."));
+            lines.push(Line::from(""));
+        }
+        for result in &app.test_result {
+            lines.push(Line::from(result.clone()));
+        }
+        if !app.test_result.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.extend([
             Line::from("Next:"),
             Line::from("  1) microclaw start"),
             Line::from(""),
-            Line::from("Press Enter to finish."),
-        ])
-        .block(
+            Line::from("Press t to send a test message, Enter to finish."),
+        ]);
+        let done = Paragraph::new(lines).block(
             Block::default()
                 .borders(Borders::ALL)
                 .title("Setup Complete"),
@@ -1957,6 +2208,173 @@ fn try_save(terminal: &mut DefaultTerminal, app: &mut SetupApp) -> Result<(), Mi
     Ok(())
 }
 
+/// Channels the post-save test message can be sent on.
+const TEST_CHANNELS: &[&str] = &["telegram", "discord", "slack"];
+
+fn slack_bot_token(config: &crate::config::Config) -> Option<String> {
+    config
+        .channels
+        .get("slack")
+        .and_then(|slack| slack.get("bot_token"))
+        .and_then(|token| token.as_str())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Split `channel:chat` into a configured test channel and chat id. The
+/// prefix may be left out when only one test channel is configured.
+fn parse_test_target(
+    config: &crate::config::Config,
+    target: &str,
+) -> Result<(&'static str, String), MicroClawError> {
+    let configured: Vec<&'static str> = TEST_CHANNELS
+        .iter()
+        .copied()
+        .filter(|channel| match *channel {
+            "telegram" => !config.telegram_bot_token.trim().is_empty(),
+            "discord" => config
+                .discord_bot_token
+                .as_deref()
+                .is_some_and(|t| !t.trim().is_empty()),
+            _ => slack_bot_token(config).is_some(),
+        })
+        .collect();
+    let prefixed = target.split_once(':').and_then(|(prefix, chat)| {
+        TEST_CHANNELS
+            .iter()
+            .find(|c| **c == prefix)
+            .map(|c| (*c, chat.trim()))
+    });
+    let (channel, chat) = match prefixed {
+        Some(parsed) => parsed,
+        None if configured.len() == 1 => (configured[0], target.trim()),
+        None => {
+            return Err(MicroClawError::Config(
+                "prefix the chat with telegram:, discord: or slack:".into(),
+            ))
+        }
+    };
+    if !configured.contains(&channel) {
+        return Err(MicroClawError::Config(format!(
+            "{channel} is not configured"
+        )));
+    }
+    if chat.is_empty() {
+        return Err(MicroClawError::Config("enter a chat id".into()));
+    }
+    Ok((channel, chat.to_string()))
+}
+
+fn post_test_message(
+    config: &crate::config::Config,
+    channel: &str,
+    chat: &str,
+    text: &str,
+) -> Result<(), MicroClawError> {
+    let client = validation_client()?;
+    let request = match channel {
+        "telegram" => client
+            .post(format!(
+                "https://api.telegram.org/bot{}/sendMessage",
+                config.telegram_bot_token.trim()
+            ))
+            .json(&serde_json::json!({"chat_id": chat, "text": text})),
+        "discord" => client
+            .post(format!(
+                "https://discord.com/api/v10/channels/{chat}/messages"
+            ))
+            .header(
+                "Authorization",
+                format!(
+                    "Bot {}",
+                    config
+                        .discord_bot_token
+                        .as_deref()
+                        .unwrap_or_default()
+                        .trim()
+                ),
+            )
+            .json(&serde_json::json!({"content": text})),
+        _ => client
+            .post("https://slack.com/api/chat.postMessage")
+            .bearer_auth(slack_bot_token(config).unwrap_or_default())
+            .json(&serde_json::json!({"channel": chat, "text": text})),
+    };
+    let resp = request.send()?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().unwrap_or_default();
+    // Telegram and Slack report failures in an `ok` field.
+    let ok = status.is_success() && body.get("ok").and_then(|v| v.as_bool()) != Some(false);
+    if !ok {
+        let detail = body
+            .get("description")
+            .or_else(|| body.get("error"))
+            .or_else(|| body.get("message"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("HTTP {status}"));
+        return Err(MicroClawError::Config(format!(
+            "sending to {channel}:{chat} failed: {detail}"
+        )));
+    }
+    Ok(())
+}
+
+/// Load the saved config, ask the model for a one-line greeting and send it
+/// to `target`, exercising the LLM and the channel before `microclaw start`.
+fn send_test_message(target: &str) -> Result<Vec<String>, MicroClawError> {
+    let content = fs::read_to_string("microclaw.config.yaml")?;
+    let mut config: crate::config::Config = serde_yaml::from_str(&content)
+        .map_err(|e| MicroClawError::Config(format!("Failed to parse config: {e}")))?;
+    config.post_deserialize()?;
+    let (channel, chat) = parse_test_target(&config, target)?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let response = runtime.block_on(async {
+        crate::llm::create_provider(&config)
+            .send_message(
+                "You are MicroClaw, a chat assistant that was just set up.",
+                vec![crate::llm_types::Message {
+                    role: "user".into(),
+                    content: crate::llm_types::MessageContent::Text(
+                        "Greet the user in one short sentence to confirm you are working.".into(),
+                    ),
+                }],
+                None,
+            )
+            .await
+    })?;
+    let greeting: String = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            crate::llm_types::ResponseContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("")
+        .trim()
+        .to_string();
+    if greeting.is_empty() {
+        return Err(MicroClawError::Config(
+            "the model returned an empty reply".into(),
+        ));
+    }
+
+    post_test_message(
+        &config,
+        channel,
+        &chat,
+        &format!("👋 MicroClaw test message\n\n{greeting}"),
+    )?;
+    Ok(vec![
+        format!("✅ LLM replied ({}): {greeting}", config.model),
+        format!("✅ Sent test message to {channel}:{chat}"),
+    ])
+}
+
 fn run_wizard(mut terminal: DefaultTerminal) -> Result<bool, MicroClawError> {
     let mut app = SetupApp::new();
 
@@ -1971,7 +2389,32 @@ fn run_wizard(mut terminal: DefaultTerminal) -> Result<bool, MicroClawError> {
             }
 
             if app.completed {
+                if let Some(target) = app.test_target.as_mut() {
+                    match key.code {
+                        KeyCode::Esc => app.test_target = None,
+                        KeyCode::Backspace => {
+                            target.pop();
+                        }
+                        KeyCode::Char(c) => target.push(c),
+                        KeyCode::Enter => {
+                            let target = target.trim().to_string();
+                            app.test_target = None;
+                            app.test_result = match run_with_spinner(
+                                &mut terminal,
+                                &mut app,
+                                "Sending test message",
+                                move || send_test_message(&target),
+                            ) {
+                                Ok(lines) => lines,
+                                Err(e) => vec![format!("Test failed: {e}")],
+                            };
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
                 match key.code {
+                    KeyCode::Char('t') => app.test_target = Some(String::new()),
                     KeyCode::Enter | KeyCode::Char('q') => return Ok(true),
                     _ => continue,
                 }
@@ -2091,6 +2534,196 @@ pub fn run_setup_wizard() -> Result<bool, MicroClawError> {
     result
 }
 
+const CONFIG_USAGE: &str = "Usage: microclaw config validate [--offline] [--json]
+       microclaw config encrypt
+       microclaw config hash-password
+
+validate  Loads the config and runs the setup wizard's checks. Unless
+          --offline is given, also checks the Telegram token and the LLM
+          credentials online. Exits with code 2 when a check fails.
+encrypt   Reads a secret (prompt or stdin) and prints an enc:v1: value to
+          paste into a credential field. The passphrase comes from
+          MICROCLAW_CONFIG_PASSPHRASE or is prompted for.
+hash-password
+          Reads a password (prompt or stdin) and prints the hash for
+          web_auth.users[].password_hash.";
+
+/// `microclaw config <subcommand>`.
+pub fn run_config_cli(args: &[String]) -> anyhow::Result<()> {
+    match args.first().map(String::as_str) {
+        Some("encrypt") => return crate::config_crypt::run_encrypt_cli(),
+        Some("hash-password") => return crate::web::auth::run_hash_password_cli(),
+        Some("validate") if !args.iter().any(|a| a == "--help" || a == "-h") => {}
+        Some("validate" | "help" | "--help" | "-h") | None => {
+            println!("{CONFIG_USAGE}");
+            return Ok(());
+        }
+        Some(other) => {
+            eprintln!("Unknown config command: {other}\n\n{CONFIG_USAGE}");
+            std::process::exit(1);
+        }
+    }
+    let offline = args.iter().any(|a| a == "--offline");
+    let json_output = args.iter().any(|a| a == "--json");
+
+    // The online checks use a blocking HTTP client, which must not run on
+    // the async runtime's thread.
+    let checks = std::thread::spawn(move || config_validation_checks(offline))
+        .join()
+        .map_err(|_| anyhow::anyhow!("validation thread panicked"))?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        println!("MicroClaw config validate");
+        println!();
+        crate::doctor::print_checks(&checks);
+    }
+    let (_, _, fail) = crate::doctor::summarize(&checks);
+    if fail > 0 {
+        std::process::exit(2);
+    }
+    Ok(())
+}
+
+fn config_validation_checks(offline: bool) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    let config = match crate::config::Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(DoctorCheck::new(
+                "config.load",
+                "Config file",
+                CheckStatus::Fail,
+                e.to_string(),
+                Some("Run `microclaw setup` or fix the reported field.".into()),
+            ));
+            return checks;
+        }
+    };
+    let base = crate::config::Config::resolve_config_path().ok().flatten();
+    let mut sources: Vec<String> = base.iter().map(|p| p.display().to_string()).collect();
+    if let Ok(Some(profile)) = crate::config::Config::active_profile() {
+        if let Ok(path) = crate::config::Config::resolve_profile_path(base.as_deref(), &profile) {
+            sources.push(path.display().to_string());
+        }
+    }
+    checks.push(DoctorCheck::new(
+        "config.load",
+        "Config file",
+        CheckStatus::Pass,
+        format!("loaded {}", sources.join(" + ")),
+        None,
+    ));
+
+    let app = SetupApp::from_config(config.clone());
+    checks.push(match app.validate_local() {
+        Ok(()) => DoctorCheck::new(
+            "config.local",
+            "Setup checks",
+            CheckStatus::Pass,
+            "required fields, channels, timezone, data and working dirs",
+            None,
+        ),
+        Err(e) => DoctorCheck::new(
+            "config.local",
+            "Setup checks",
+            CheckStatus::Fail,
+            e.to_string(),
+            Some("Run `microclaw setup` to fix it.".into()),
+        ),
+    });
+    if offline {
+        return checks;
+    }
+
+    let client = match validation_client() {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(DoctorCheck::new(
+                "online.client",
+                "HTTP client",
+                CheckStatus::Fail,
+                e.to_string(),
+                None,
+            ));
+            return checks;
+        }
+    };
+
+    let tg_token = config.telegram_bot_token.trim();
+    checks.push(if tg_token.is_empty() {
+        DoctorCheck::new(
+            "online.telegram",
+            "Telegram token",
+            CheckStatus::Pass,
+            "skipped (telegram disabled)",
+            None,
+        )
+    } else {
+        let username = config.bot_username.trim_start_matches('@');
+        match check_telegram_online(&client, tg_token, username) {
+            Ok(detail) => DoctorCheck::new(
+                "online.telegram",
+                "Telegram token",
+                CheckStatus::Pass,
+                detail,
+                None,
+            ),
+            Err(e) => DoctorCheck::new(
+                "online.telegram",
+                "Telegram token",
+                CheckStatus::Fail,
+                e.to_string(),
+                Some("Check telegram_bot_token with @BotFather.".into()),
+            ),
+        }
+    });
+
+    let provider = config.llm_provider.to_lowercase();
+    let credentials = if is_openai_codex_provider(&provider) {
+        resolve_openai_codex_auth("").map(|auth| (auth.bearer_token, auth.account_id))
+    } else if is_claude_oauth_provider(&provider) {
+        resolve_claude_oauth_token().map(|token| (token, None))
+    } else {
+        Ok((config.api_key.clone(), None))
+    };
+    let llm = credentials.and_then(|(api_key, account_id)| {
+        check_llm_online(
+            &client,
+            &provider,
+            &api_key,
+            config.llm_base_url.as_deref().unwrap_or_default(),
+            &config.model,
+            account_id.as_deref(),
+        )
+    });
+    checks.push(match llm {
+        Ok(detail) if detail.starts_with(LLM_CHECK_SKIPPED) => DoctorCheck::new(
+            "online.llm",
+            "LLM provider",
+            CheckStatus::Warn,
+            detail,
+            None,
+        ),
+        Ok(detail) => DoctorCheck::new(
+            "online.llm",
+            "LLM provider",
+            CheckStatus::Pass,
+            detail,
+            None,
+        ),
+        Err(e) => DoctorCheck::new(
+            "online.llm",
+            "LLM provider",
+            CheckStatus::Fail,
+            e.to_string(),
+            Some("Check llm_provider, api_key, model and llm_base_url.".into()),
+        ),
+    });
+    checks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2214,4 +2847,47 @@ mod tests {
             .expect("LLM_API_KEY field missing");
         assert!(app.is_field_required(api_key_field));
     }
+
+    #[test]
+    fn test_parse_test_target() {
+        let config: crate::config::Config =
+            serde_yaml::from_str("telegram_bot_token: tok\napi_key: key\n").unwrap();
+        assert_eq!(
+            parse_test_target(&config, "-100123").unwrap(),
+            ("telegram", "-100123".to_string())
+        );
+        assert_eq!(
+            parse_test_target(&config, "telegram: 42").unwrap(),
+            ("telegram", "42".to_string())
+        );
+        assert!(parse_test_target(&config, "discord:1")
+            .unwrap_err()
+            .to_string()
+            .contains("discord is not configured"));
+        assert!(parse_test_target(&config, "telegram:").is_err());
+
+        let config: crate::config::Config = serde_yaml::from_str(
+            "telegram_bot_token: tok\ndiscord_bot_token: dtok\napi_key: key\n",
+        )
+        .unwrap();
+        assert!(parse_test_target(&config, "42")
+            .unwrap_err()
+            .to_string()
+            .contains("prefix the chat"));
+        assert_eq!(
+            parse_test_target(&config, "discord:99").unwrap(),
+            ("discord", "99".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_config_runs_wizard_checks() {
+        let config: crate::config::Config =
+            serde_yaml::from_str("telegram_bot_token: tok\nbot_username: \"@bot\"\napi_key: key\n")
+                .unwrap();
+        let app = SetupApp::from_config(config);
+        assert_eq!(app.field_value("ENABLED_CHANNELS"), "telegram");
+        let err = app.validate_local().unwrap_err().to_string();
+        assert!(err.contains("BOT_USERNAME should not include '@'"));
+    }
 }
@@ -0,0 +1,327 @@
+//! Progressive message edits while the model is still writing.
+//!
+//! Channels that can edit sent messages (Telegram, Discord) show the streamed
+//! text in one message and edit it about once a second, then replace it with
+//! the final, formatted response. Text streamed before a tool call is replaced
+//! by the next iteration's text, so only the answer that would have been sent
+//! anyway remains.
+//!
+//! With `progress_status`, the same message shows what the agent is doing
+//! once it starts calling tools ("Running bash… (12s)"), even when the text
+//! itself is not streamed, including the latest progress note of tools that
+//! report one (a sub-agent's current step).
+
+use std::time::{Duration, Instant};
+
+use crate::agent_engine::{strip_thinking, AgentEvent};
+use crate::text::floor_char_boundary;
+use crate::thinking::format_thinking;
+
+/// Minimum time between two edits of the streamed message.
+pub const STREAM_EDIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the progress status is refreshed while no events arrive, so the
+/// elapsed time keeps moving during a long tool call.
+pub const STATUS_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a call to tool `name` is doing, for the progress status.
+pub fn tool_status_label(name: &str) -> String {
+    let label = match name {
+        "web_search" => "Searching the web",
+        "web_fetch" => "Reading a web page",
+        "browser" => "Using the browser",
+        "read_file" => "Reading files",
+        "write_file" | "edit_file" => "Editing files",
+        "glob" | "grep" => "Searching files",
+        "sub_agent" => "Working with a sub-agent",
+        "retrieve" => "Searching the knowledge base",
+        "read_memory" | "structured_memory_search" => "Checking memory",
+        "write_memory" | "structured_memory_update" | "structured_memory_delete" => {
+            "Updating memory"
+        }
+        "send_message" => "Sending a message",
+        "send_email" => "Sending an email",
+        "calendar" => "Checking the calendar",
+        _ => return format!("Running {name}"),
+    };
+    label.to_string()
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// A tool call in progress and its latest progress note.
+#[derive(Debug)]
+struct RunningTool {
+    tool_use_id: String,
+    name: String,
+    note: Option<String>,
+}
+
+/// Streamed text of the current iteration and when it was last shown.
+#[derive(Debug)]
+pub struct StreamingDraft {
+    text: String,
+    shown: String,
+    last_edit: Option<Instant>,
+    max_len: usize,
+    show_thinking: bool,
+    show_text: bool,
+    progress: bool,
+    started: Instant,
+    /// Tools currently running, in start order.
+    running: Vec<RunningTool>,
+    /// Whether the turn has called a tool yet; the status only appears then.
+    used_tools: bool,
+}
+
+impl StreamingDraft {
+    /// `max_len` is the channel's message size limit in bytes.
+    pub fn new(max_len: usize) -> Self {
+        StreamingDraft {
+            text: String::new(),
+            shown: String::new(),
+            last_edit: None,
+            max_len,
+            show_thinking: false,
+            show_text: true,
+            progress: false,
+            started: Instant::now(),
+            running: Vec::new(),
+            used_tools: false,
+        }
+    }
+
+    /// Show the streamed text (`stream_replies`). Without it, only the
+    /// progress status is shown.
+    pub fn with_text(mut self, show_text: bool) -> Self {
+        self.show_text = show_text;
+        self
+    }
+
+    /// Show the running tool and elapsed time (`progress_status`).
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Whether anything will be shown at all.
+    pub fn is_active(&self) -> bool {
+        self.show_text || self.progress
+    }
+
+    /// Show streamed thinking as a quoted block instead of hiding it
+    /// (`show_thinking`).
+    pub fn with_thinking(mut self, show_thinking: bool) -> Self {
+        self.show_thinking = show_thinking;
+        self
+    }
+
+    pub fn on_event(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::Iteration { .. } => {
+                self.text.clear();
+                self.running.clear();
+            }
+            AgentEvent::TextDelta { delta } => self.text.push_str(delta),
+            AgentEvent::ToolStart {
+                tool_use_id, name, ..
+            } => {
+                self.running.push(RunningTool {
+                    tool_use_id: tool_use_id.clone(),
+                    name: name.clone(),
+                    note: None,
+                });
+                self.used_tools = true;
+            }
+            AgentEvent::ToolProgress {
+                tool_use_id, note, ..
+            } => {
+                if let Some(tool) = self
+                    .running
+                    .iter_mut()
+                    .find(|t| &t.tool_use_id == tool_use_id)
+                {
+                    tool.note = Some(note.clone());
+                }
+            }
+            AgentEvent::ToolResult { tool_use_id, .. } => {
+                if let Some(i) = self
+                    .running
+                    .iter()
+                    .position(|t| &t.tool_use_id == tool_use_id)
+                {
+                    self.running.remove(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Text to show now, if the interval has passed and it changed since the
+    /// last edit. Call `mark_shown` after the edit went out.
+    pub fn due(&self, now: Instant) -> Option<String> {
+        if self
+            .last_edit
+            .is_some_and(|t| now.duration_since(t) < STREAM_EDIT_INTERVAL)
+        {
+            return None;
+        }
+        let preview = self.preview(now);
+        (!preview.is_empty() && preview != self.shown).then_some(preview)
+    }
+
+    pub fn mark_shown(&mut self, text: String, now: Instant) {
+        self.shown = text;
+        self.last_edit = Some(now);
+    }
+
+    /// Status line once the turn has used tools: the latest running tool and
+    /// its progress note, or "Thinking" between tool calls, with the time
+    /// since the turn began.
+    fn status(&self, now: Instant) -> Option<String> {
+        if !self.progress || !self.used_tools {
+            return None;
+        }
+        let activity = match self.running.last() {
+            Some(RunningTool {
+                name,
+                note: Some(note),
+                ..
+            }) => format!("{}: {note}", tool_status_label(name)),
+            Some(tool) => tool_status_label(&tool.name),
+            None => "Thinking".to_string(),
+        };
+        let elapsed = now.saturating_duration_since(self.started);
+        Some(format!("⏳ {activity}… ({})", format_elapsed(elapsed)))
+    }
+
+    /// Visible part of the streamed text: thinking removed (or quoted with
+    /// `show_thinking`), cut to the message limit with a trailing ellipsis,
+    /// followed by the progress status.
+    fn preview(&self, now: Instant) -> String {
+        let mut text = if !self.show_text {
+            String::new()
+        } else if self.show_thinking {
+            format_thinking(&self.text)
+        } else {
+            strip_thinking(&self.text)
+        };
+        let status = self.status(now);
+        let reserved = status.as_ref().map_or(0, |s| s.len() + 2);
+        let max_len = self.max_len.saturating_sub(reserved);
+        if text.len() > max_len {
+            let cut = floor_char_boundary(&text, max_len.saturating_sub('…'.len_utf8()));
+            text = format!("{}…", &text[..cut]);
+        }
+        match status {
+            Some(status) if text.is_empty() => status,
+            Some(status) => format!("{text}\n\n{status}"),
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delta(text: &str) -> AgentEvent {
+        AgentEvent::TextDelta { delta: text.into() }
+    }
+
+    #[test]
+    fn test_draft_throttles_and_skips_unchanged_text() {
+        let mut draft = StreamingDraft::new(100);
+        let start = Instant::now();
+        assert_eq!(draft.due(start), None);
+
+        draft.on_event(&delta("Hello"));
+        assert_eq!(draft.due(start).as_deref(), Some("Hello"));
+        draft.mark_shown("Hello".into(), start);
+
+        draft.on_event(&delta(" world"));
+        assert_eq!(draft.due(start + Duration::from_millis(200)), None);
+        let later = start + STREAM_EDIT_INTERVAL;
+        assert_eq!(draft.due(later).as_deref(), Some("Hello world"));
+        draft.mark_shown("Hello world".into(), later);
+        assert_eq!(draft.due(later + STREAM_EDIT_INTERVAL), None);
+    }
+
+    #[test]
+    fn test_draft_resets_per_iteration_and_hides_thinking() {
+        let mut draft = StreamingDraft::new(100);
+        draft.on_event(&delta("Let me check."));
+        draft.on_event(&AgentEvent::Iteration { iteration: 2 });
+        draft.on_event(&delta("<think>plan</think>Done"));
+        assert_eq!(draft.due(Instant::now()).as_deref(), Some("Done"));
+
+        let mut draft = StreamingDraft::new(100).with_thinking(true);
+        draft.on_event(&delta("<think>plan"));
+        assert_eq!(
+            draft.due(Instant::now()).as_deref(),
+            Some("💭 Thinking\n> plan")
+        );
+    }
+
+    #[test]
+    fn test_draft_progress_status() {
+        let tool_start = |name: &str| AgentEvent::ToolStart {
+            tool_use_id: "t1".into(),
+            name: name.into(),
+            input: serde_json::json!({}),
+        };
+        let mut draft = StreamingDraft::new(100)
+            .with_text(false)
+            .with_progress(true);
+        let start = Instant::now();
+        draft.on_event(&delta("Let me check."));
+        assert_eq!(draft.due(start), None);
+
+        draft.on_event(&tool_start("bash"));
+        let status = draft.due(start + Duration::from_secs(7)).unwrap();
+        assert!(status.starts_with("⏳ Running bash… ("), "{status}");
+        draft.on_event(&tool_start("web_search"));
+        let status = draft.due(start + Duration::from_secs(75)).unwrap();
+        assert!(
+            status.starts_with("⏳ Searching the web… (1m 1"),
+            "{status}"
+        );
+        draft.on_event(&AgentEvent::Iteration { iteration: 2 });
+        let status = draft.due(start).unwrap();
+        assert!(status.starts_with("⏳ Thinking…"), "{status}");
+
+        draft.on_event(&tool_start("sub_agent"));
+        draft.on_event(&AgentEvent::ToolProgress {
+            tool_use_id: "t1".into(),
+            name: "sub_agent".into(),
+            note: "step 2/10: Searching files".into(),
+        });
+        let status = draft.due(start).unwrap();
+        assert!(
+            status.starts_with("⏳ Working with a sub-agent: step 2/10: Searching files…"),
+            "{status}"
+        );
+
+        let mut draft = StreamingDraft::new(100).with_progress(true);
+        draft.on_event(&delta("Checking the logs."));
+        draft.on_event(&tool_start("grep"));
+        let preview = draft.due(start).unwrap();
+        assert!(preview.starts_with("Checking the logs.\n\n⏳ Searching files…"));
+    }
+
+    #[test]
+    fn test_draft_preview_is_cut_to_limit() {
+        let mut draft = StreamingDraft::new(10);
+        draft.on_event(&delta("ééééééééé"));
+        let preview = draft.due(Instant::now()).unwrap();
+        assert!(preview.len() <= 10);
+        assert!(preview.ends_with('…'));
+    }
+}
@@ -0,0 +1,226 @@
+//! Schema-constrained JSON replies from the LLM.
+//!
+//! [`request_json`] asks for a JSON object matching a [`ResponseSchema`]. It
+//! uses the provider's native JSON schema mode when the model supports it
+//! (OpenAI-compatible models with `structured_output`), and always puts the
+//! schema in the system prompt and validates the reply. Invalid replies are
+//! sent back with the validation error for another attempt.
+
+use crate::error::MicroClawError;
+use crate::json_schema;
+use crate::llm::LlmProvider;
+use crate::llm_types::{Message, MessageContent, ResponseContentBlock, ResponseSchema, Usage};
+
+/// Attempts before giving up on a reply that does not match the schema.
+pub const MAX_JSON_ATTEMPTS: usize = 3;
+
+/// A validated JSON reply and the tokens spent on all attempts.
+#[derive(Debug)]
+pub struct JsonReply {
+    pub value: serde_json::Value,
+    pub usage: Usage,
+    pub attempts: usize,
+}
+
+fn json_system_prompt(system: &str, schema: &ResponseSchema) -> String {
+    let schema_text =
+        serde_json::to_string_pretty(&schema.schema).unwrap_or_else(|_| schema.schema.to_string());
+    let instructions = format!(
+        "Reply with only a JSON object that matches this JSON schema, without any other text or code fences:\n{schema_text}"
+    );
+    if system.trim().is_empty() {
+        instructions
+    } else {
+        format!("{system}\n\n{instructions}")
+    }
+}
+
+/// Parse the JSON object out of a reply, tolerating code fences and text
+/// around it.
+fn extract_json(text: &str) -> Result<serde_json::Value, String> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+    match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&text[start..=end])
+            .map_err(|e| format!("the reply is not valid JSON ({e})")),
+        _ => Err("the reply does not contain a JSON object".into()),
+    }
+}
+
+/// Parse and validate one reply.
+pub fn parse_reply(text: &str, schema: &ResponseSchema) -> Result<serde_json::Value, String> {
+    let value = extract_json(text)?;
+    json_schema::validate(&schema.schema, &value)
+        .map_err(|e| format!("the JSON does not match the schema at {e}"))?;
+    Ok(value)
+}
+
+/// Ask `llm` for a JSON object matching `schema`, retrying invalid replies up
+/// to [`MAX_JSON_ATTEMPTS`] times.
+pub async fn request_json(
+    llm: &dyn LlmProvider,
+    system: &str,
+    mut messages: Vec<Message>,
+    schema: &ResponseSchema,
+) -> Result<JsonReply, MicroClawError> {
+    let system = json_system_prompt(system, schema);
+    let mut usage = Usage {
+        input_tokens: 0,
+        output_tokens: 0,
+    };
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_JSON_ATTEMPTS {
+        let response = llm
+            .send_message_json(&system, messages.clone(), schema)
+            .await?;
+        if let Some(u) = &response.usage {
+            usage.input_tokens += u.input_tokens;
+            usage.output_tokens += u.output_tokens;
+        }
+        let text: String = response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ResponseContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        match parse_reply(&text, schema) {
+            Ok(value) => {
+                return Ok(JsonReply {
+                    value,
+                    usage,
+                    attempts: attempt,
+                })
+            }
+            Err(e) => {
+                tracing::debug!(
+                    "Structured reply for {} rejected (attempt {attempt}): {e}",
+                    schema.name
+                );
+                messages.push(Message {
+                    role: "assistant".into(),
+                    content: MessageContent::Text(text),
+                });
+                messages.push(Message {
+                    role: "user".into(),
+                    content: MessageContent::Text(format!(
+                        "That reply was rejected: {e}. Reply again with only the corrected JSON object."
+                    )),
+                });
+                last_error = e;
+            }
+        }
+    }
+    Err(MicroClawError::LlmApi(format!(
+        "no valid {} JSON after {MAX_JSON_ATTEMPTS} attempts: {last_error}",
+        schema.name
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_types::{MessagesResponse, ToolDefinition};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    struct ScriptedLlm {
+        replies: Mutex<Vec<&'static str>>,
+        seen: Mutex<Vec<(String, usize)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ScriptedLlm {
+        async fn send_message(
+            &self,
+            system: &str,
+            messages: Vec<Message>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<MessagesResponse, MicroClawError> {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((system.to_string(), messages.len()));
+            let text = self.replies.lock().unwrap().remove(0);
+            Ok(MessagesResponse {
+                content: vec![ResponseContentBlock::Text { text: text.into() }],
+                stop_reason: Some("end_turn".into()),
+                usage: Some(Usage {
+                    input_tokens: 10,
+                    output_tokens: 5,
+                }),
+                served_by: None,
+            })
+        }
+    }
+
+    fn schema() -> ResponseSchema {
+        ResponseSchema {
+            name: "task".into(),
+            schema: json!({
+                "type": "object",
+                "properties": {"title": {"type": "string"}, "minutes": {"type": "integer"}},
+                "required": ["title", "minutes"]
+            }),
+        }
+    }
+
+    fn question() -> Vec<Message> {
+        vec![Message {
+            role: "user".into(),
+            content: MessageContent::Text("remind me to stretch in 20 minutes".into()),
+        }]
+    }
+
+    #[test]
+    fn test_parse_reply_tolerates_fences() {
+        let value = parse_reply(
+            "```json\n{\"title\": \"stretch\", \"minutes\": 20}\n```",
+            &schema(),
+        )
+        .unwrap();
+        assert_eq!(value["minutes"], 20);
+        assert!(parse_reply("no json here", &schema()).is_err());
+        let err = parse_reply("{\"title\": \"stretch\"}", &schema()).unwrap_err();
+        assert!(err.contains("\"minutes\" is a required property"));
+    }
+
+    #[tokio::test]
+    async fn test_request_json_retries_invalid_reply() {
+        let llm = ScriptedLlm {
+            replies: Mutex::new(vec![
+                "Sure! {\"title\": \"stretch\", \"minutes\": \"20\"}",
+                "{\"title\": \"stretch\", \"minutes\": 20}",
+            ]),
+            seen: Mutex::new(Vec::new()),
+        };
+        let reply = request_json(&llm, "Parse tasks.", question(), &schema())
+            .await
+            .unwrap();
+        assert_eq!(reply.value, json!({"title": "stretch", "minutes": 20}));
+        assert_eq!(reply.attempts, 2);
+        assert_eq!(reply.usage.input_tokens, 20);
+
+        let seen = llm.seen.lock().unwrap();
+        assert!(seen[0]
+            .0
+            .starts_with("Parse tasks.\n\nReply with only a JSON object"));
+        // The rejected reply and the correction request were appended.
+        assert_eq!(seen[1].1, 3);
+    }
+
+    #[tokio::test]
+    async fn test_request_json_gives_up() {
+        let llm = ScriptedLlm {
+            replies: Mutex::new(vec!["nope"; MAX_JSON_ATTEMPTS]),
+            seen: Mutex::new(Vec::new()),
+        };
+        let err = request_json(&llm, "", question(), &schema())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no valid task JSON"));
+    }
+}
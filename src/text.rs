@@ -33,3 +33,261 @@ pub fn split_text(text: &str, max_len: usize) -> Vec<String> {
     }
     chunks
 }
+
+/// Replies that would need more messages than this are sent as a file
+/// attachment instead, after the first chunk.
+pub const MAX_REPLY_CHUNKS: usize = 4;
+
+/// File name used when a reply is sent as an attachment.
+pub const REPLY_FILE_NAME: &str = "reply.md";
+
+/// Split Markdown into chunks of at most `max_chars` characters for chat
+/// platforms with a message length limit.
+///
+/// Chunks end at paragraph breaks where possible, then at line breaks, then
+/// at spaces outside inline code. A fenced code block that has to be split is
+/// closed at the end of one chunk and reopened, with its language tag, at the
+/// start of the next, so every chunk renders on its own. Splitting happens on
+/// the raw Markdown, before any channel-specific escaping.
+pub fn split_markdown(text: &str, max_chars: usize) -> Vec<String> {
+    if text.chars().count() <= max_chars {
+        return vec![text.to_string()];
+    }
+    let mut splitter = MarkdownSplitter {
+        max_chars: max_chars.max(FENCE_CLOSE.len() + 8),
+        chunks: Vec::new(),
+        lines: Vec::new(),
+        len: 0,
+        fence: None,
+        paragraph_break: None,
+    };
+    for line in text.split('\n') {
+        splitter.push_line(line);
+    }
+    splitter.finish()
+}
+
+const FENCE_CLOSE: &str = "```";
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with(FENCE_CLOSE)
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+struct MarkdownSplitter {
+    max_chars: usize,
+    chunks: Vec<String>,
+    /// Lines of the chunk being built.
+    lines: Vec<String>,
+    /// Characters in `lines` joined with newlines.
+    len: usize,
+    /// Opening line of the code fence still open at the end of `lines`.
+    fence: Option<String>,
+    /// Index in `lines` of the last blank line outside a code fence.
+    paragraph_break: Option<usize>,
+}
+
+impl MarkdownSplitter {
+    /// Characters the current chunk would have with `line` appended and any
+    /// fence left open by it closed.
+    fn len_with(&self, line: &str, fence_after: bool) -> usize {
+        let separator = usize::from(!self.lines.is_empty());
+        let close = if fence_after {
+            FENCE_CLOSE.len() + 1
+        } else {
+            0
+        };
+        self.len + separator + char_len(line) + close
+    }
+
+    fn append(&mut self, line: &str) {
+        if !self.lines.is_empty() {
+            self.len += 1;
+        }
+        self.len += char_len(line);
+        self.lines.push(line.to_string());
+    }
+
+    fn push_line(&mut self, line: &str) {
+        let fence_after = if is_fence(line) {
+            match self.fence {
+                Some(_) => None,
+                None => Some(line.trim_start().to_string()),
+            }
+        } else {
+            self.fence.clone()
+        };
+
+        // A carried-over paragraph may still leave no room, so flush twice.
+        for _ in 0..2 {
+            if self.lines.is_empty() || self.len_with(line, fence_after.is_some()) <= self.max_chars
+            {
+                break;
+            }
+            self.flush();
+        }
+
+        if self.len_with(line, fence_after.is_some()) <= self.max_chars {
+            if line.trim().is_empty() && self.fence.is_none() {
+                self.paragraph_break = Some(self.lines.len());
+            }
+            self.append(line);
+        } else {
+            self.push_long_line(line);
+        }
+        self.fence = fence_after;
+    }
+
+    /// Append a line too long for any chunk, flushing as each chunk fills.
+    fn push_long_line(&mut self, line: &str) {
+        let mut rest = line;
+        loop {
+            let close = if self.fence.is_some() {
+                FENCE_CLOSE.len() + 1
+            } else {
+                0
+            };
+            let separator = usize::from(!self.lines.is_empty());
+            let room = self
+                .max_chars
+                .saturating_sub(self.len + separator + close)
+                .max(1);
+            if char_len(rest) <= room {
+                self.append(rest);
+                return;
+            }
+            let limit = rest
+                .char_indices()
+                .nth(room)
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            let (cut, skip) = match break_at_space(&rest[..limit]) {
+                Some(i) => (i, 1),
+                None => (limit, 0),
+            };
+            self.append(&rest[..cut]);
+            rest = &rest[cut + skip..];
+            self.paragraph_break = None;
+            self.flush();
+        }
+    }
+
+    /// End the current chunk and start the next one.
+    fn flush(&mut self) {
+        let lines = std::mem::take(&mut self.lines);
+        let carry_from = self.paragraph_break.take().filter(|&i| {
+            let kept: usize = lines[..i].iter().map(|l| char_len(l) + 1).sum();
+            kept >= self.max_chars / 2
+        });
+        self.len = 0;
+        match carry_from {
+            // End the chunk at the paragraph break; the fence state there was
+            // closed, so the carried lines reopen any fence themselves.
+            Some(i) => {
+                self.emit(lines[..i].join("\n"));
+                for line in &lines[i + 1..] {
+                    self.append(line);
+                }
+            }
+            None => {
+                let mut chunk = lines.join("\n");
+                if let Some(open) = self.fence.clone() {
+                    chunk.push('\n');
+                    chunk.push_str(FENCE_CLOSE);
+                    self.append(&open);
+                }
+                self.emit(chunk);
+            }
+        }
+    }
+
+    fn emit(&mut self, chunk: String) {
+        if !chunk.trim().is_empty() {
+            self.chunks.push(chunk);
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        let chunk = self.lines.join("\n");
+        self.emit(chunk);
+        if self.chunks.is_empty() {
+            self.chunks.push(String::new());
+        }
+        self.chunks
+    }
+}
+
+/// Byte index of the last space in the second half of `s`, preferring one
+/// outside inline code.
+fn break_at_space(s: &str) -> Option<usize> {
+    let spaces: Vec<usize> = s
+        .match_indices(' ')
+        .map(|(i, _)| i)
+        .filter(|&i| i > 0 && i >= s.len() / 2)
+        .collect();
+    spaces
+        .iter()
+        .rev()
+        .find(|&&i| s[..i].matches('`').count().is_multiple_of(2))
+        .or(spaces.last())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fences_balanced(chunk: &str) -> bool {
+        chunk
+            .lines()
+            .filter(|l| is_fence(l))
+            .count()
+            .is_multiple_of(2)
+    }
+
+    #[test]
+    fn test_split_markdown_prefers_paragraphs() {
+        let paragraph = "word ".repeat(30);
+        let text = [paragraph.trim(), paragraph.trim(), paragraph.trim()].join("\n\n");
+        let chunks = split_markdown(&text, 320);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], [paragraph.trim(), paragraph.trim()].join("\n\n"));
+        assert_eq!(chunks[1], paragraph.trim());
+    }
+
+    #[test]
+    fn test_split_markdown_reopens_code_fence() {
+        let mut text = String::from("Intro\n```rust\n");
+        for i in 0..40 {
+            text.push_str(&format!("let value_{i} = compute({i});\n"));
+        }
+        text.push_str("```\nDone.");
+        let chunks = split_markdown(&text, 200);
+        assert!(chunks.len() > 2);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.chars().count() <= 200, "chunk {i} too long");
+            assert!(fences_balanced(chunk), "unbalanced chunk {i}: {chunk}");
+            if i > 0 && i < chunks.len() - 1 {
+                assert!(chunk.starts_with("```rust\n"), "chunk {i}: {chunk}");
+            }
+        }
+        assert!(chunks.last().unwrap().ends_with("```\nDone."));
+    }
+
+    #[test]
+    fn test_split_markdown_long_line_breaks_at_space_outside_code() {
+        let text = format!("{} `keep this together` tail", "x".repeat(80));
+        let chunks = split_markdown(&text, 100);
+        assert_eq!(chunks[0], "x".repeat(80));
+        assert_eq!(chunks[1], "`keep this together` tail");
+
+        let text = "é".repeat(250);
+        let chunks = split_markdown(&text, 100);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 100));
+        assert_eq!(chunks.concat(), text);
+    }
+}
@@ -0,0 +1,198 @@
+//! Extended thinking levels and how thinking is shown.
+//!
+//! `thinking` in the config sets the default budget / reasoning effort;
+//! `/thinking off|low|medium|high` overrides it for a chat (stored in
+//! `chat_settings`). With `show_thinking`, the model's thinking — Anthropic
+//! thinking blocks, `reasoning_content`, or inline `<think>` tags — is shown
+//! above the answer as a quoted block; otherwise it is stripped.
+
+use crate::config::{ThinkingConfig, REASONING_EFFORTS};
+use crate::db::call_blocking;
+use crate::llm_types::ResponseContentBlock;
+use crate::runtime::AppState;
+
+/// `chat_settings` key holding the chat's `/thinking` level.
+pub const THINKING_SETTING_KEY: &str = "thinking";
+const THINKING_HEADER: &str = "💭 Thinking";
+
+const THINKING_USAGE: &str = "Usage: /thinking — show this chat's thinking level\n/thinking off|low|medium|high — set it for this chat\n/thinking default — go back to the configured level";
+
+/// The chat's `/thinking` level, if it set one.
+pub async fn chat_thinking_level(state: &AppState, chat_id: i64) -> Option<String> {
+    call_blocking(state.db.clone(), move |db| {
+        db.get_chat_setting(chat_id, THINKING_SETTING_KEY)
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Thinking settings for a chat's turns when they differ from the config.
+pub async fn chat_thinking(state: &AppState, chat_id: i64) -> Option<ThinkingConfig> {
+    let level = chat_thinking_level(state, chat_id).await?;
+    ThinkingConfig::for_level(&level).filter(|t| *t != state.config.thinking)
+}
+
+fn describe(thinking: &ThinkingConfig) -> String {
+    if !thinking.is_enabled() {
+        return "off".into();
+    }
+    let mut parts = Vec::new();
+    if thinking.budget_tokens > 0 {
+        parts.push(format!("{} token budget", thinking.budget_tokens));
+    }
+    if let Some(effort) = &thinking.reasoning_effort {
+        parts.push(format!("{effort} reasoning effort"));
+    }
+    parts.join(", ")
+}
+
+/// Handle `/thinking`. Returns `None` when the text is not this command.
+pub async fn handle_thinking_command(state: &AppState, chat_id: i64, text: &str) -> Option<String> {
+    let text = text.trim();
+    let arg = match text.split_once(char::is_whitespace) {
+        Some(("/thinking", rest)) => rest.trim().to_ascii_lowercase(),
+        None if text == "/thinking" => String::new(),
+        _ => return None,
+    };
+    if !arg.is_empty() {
+        let stored = match arg.as_str() {
+            "default" => None,
+            level if ThinkingConfig::for_level(level).is_some() => Some(arg.clone()),
+            _ => return Some(THINKING_USAGE.to_string()),
+        };
+        if let Err(e) = call_blocking(state.db.clone(), move |db| {
+            db.set_chat_setting(chat_id, THINKING_SETTING_KEY, stored.as_deref())
+        })
+        .await
+        {
+            return Some(format!("Failed to update the thinking level: {e}"));
+        }
+    }
+    let (source, thinking) = match chat_thinking_level(state, chat_id).await {
+        Some(level) => (
+            format!("set to {level} for this chat"),
+            ThinkingConfig::for_level(&level).unwrap_or_default(),
+        ),
+        None => ("the default".to_string(), state.config.thinking.clone()),
+    };
+    let shown = if state.config.show_thinking {
+        "shown above replies"
+    } else {
+        "hidden (show_thinking is off)"
+    };
+    Some(format!(
+        "Thinking is {source}: {}. Thinking is {shown}.\nLevels: off, low, medium, high (reasoning effort: {}).",
+        describe(&thinking),
+        REASONING_EFFORTS.join(", ")
+    ))
+}
+
+/// The reply text with the response's thinking blocks in front of it as
+/// `<think>` tags, for [`format_thinking`].
+pub fn with_thinking_blocks(content: &[ResponseContentBlock], text: &str) -> String {
+    let mut out = String::new();
+    for block in content {
+        if let ResponseContentBlock::Thinking { thinking, .. } = block {
+            if !thinking.trim().is_empty() {
+                out.push_str("<think>");
+                out.push_str(thinking);
+                out.push_str("</think>");
+            }
+        }
+    }
+    out.push_str(text);
+    out
+}
+
+fn quote(thinking: &str) -> String {
+    let lines: Vec<String> = thinking
+        .trim()
+        .lines()
+        .map(|line| format!("> {line}").trim_end().to_string())
+        .collect();
+    format!("{THINKING_HEADER}\n{}", lines.join("\n"))
+}
+
+/// Render `<think>` blocks as a quoted, labelled block before the answer.
+/// An unclosed block (still streaming) is shown up to the end of the text.
+pub fn format_thinking(text: &str) -> String {
+    let mut thinking = Vec::new();
+    let mut answer = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<think>") {
+        answer.push_str(&rest[..start]);
+        let after = &rest[start + "<think>".len()..];
+        match after.find("</think>") {
+            Some(end) => {
+                thinking.push(&after[..end]);
+                rest = &after[end + "</think>".len()..];
+            }
+            None => {
+                thinking.push(after);
+                rest = "";
+            }
+        }
+    }
+    answer.push_str(rest);
+    let thinking: Vec<&str> = thinking
+        .into_iter()
+        .filter(|t| !t.trim().is_empty())
+        .collect();
+    let answer = answer.trim();
+    if thinking.is_empty() {
+        return answer.to_string();
+    }
+    let block = quote(&thinking.join("\n\n"));
+    if answer.is_empty() {
+        block
+    } else {
+        format!("{block}\n\n{answer}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_thinking_quotes_thinking_before_answer() {
+        assert_eq!(
+            format_thinking("<think>step one\nstep two</think>The answer"),
+            "💭 Thinking\n> step one\n> step two\n\nThe answer"
+        );
+        assert_eq!(format_thinking("plain"), "plain");
+        assert_eq!(
+            format_thinking("<think>still going"),
+            "💭 Thinking\n> still going"
+        );
+        assert_eq!(format_thinking("<think> </think>hi"), "hi");
+    }
+
+    #[test]
+    fn test_with_thinking_blocks() {
+        let content = vec![
+            ResponseContentBlock::Thinking {
+                thinking: "hmm".into(),
+                signature: "sig".into(),
+            },
+            ResponseContentBlock::Text {
+                text: "Done".into(),
+            },
+        ];
+        assert_eq!(
+            format_thinking(&with_thinking_blocks(&content, "Done")),
+            "💭 Thinking\n> hmm\n\nDone"
+        );
+    }
+
+    #[test]
+    fn test_thinking_levels() {
+        assert_eq!(ThinkingConfig::for_level("off"), Some(Default::default()));
+        let high = ThinkingConfig::for_level("high").unwrap();
+        assert_eq!(high.budget_tokens, 24576);
+        assert_eq!(high.reasoning_effort.as_deref(), Some("high"));
+        assert!(ThinkingConfig::for_level("max").is_none());
+        assert_eq!(describe(&ThinkingConfig::default()), "off");
+    }
+}
@@ -0,0 +1,535 @@
+//! Token counting for context-window budgeting.
+//!
+//! OpenAI models, and models we don't recognise, are counted exactly with the
+//! `o200k_base` / `cl100k_base` BPE vocabularies from `tiktoken-rs`. Claude,
+//! Gemini and Llama-style models have no bundled vocabulary (Anthropic's is
+//! not public), so their counts follow the family's pre-tokenization (how
+//! text is split into words, digit groups, CJK runs and punctuation before
+//! byte-pair merges) with its typical piece lengths. After every LLM call the
+//! count is compared with the provider-reported `input_tokens` and later
+//! counts for that model are scaled by the running ratio, so estimates
+//! converge on the provider's own tokenizer after the first turn.
+//!
+//! [`fit_to_window`] keeps a request inside the model's context window by
+//! truncating the largest tool results first and dropping the oldest
+//! messages only if that is not enough.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use tiktoken_rs::CoreBPE;
+
+use crate::llm_types::{ContentBlock, Message, MessageContent, ToolDefinition};
+use crate::text::floor_char_boundary;
+
+/// Tool results are never truncated below this many tokens.
+const TOOL_RESULT_FLOOR_TOKENS: usize = 500;
+/// Weight of the newest observation in the calibration average.
+const CALIBRATION_WEIGHT: f64 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenizerFamily {
+    /// Anthropic Claude.
+    Claude,
+    /// OpenAI `o200k_base` (GPT-4o, GPT-4.1, GPT-5, o-series, Codex).
+    O200k,
+    /// OpenAI `cl100k_base` (GPT-4, GPT-3.5) and unknown models.
+    Cl100k,
+    /// SentencePiece models (Gemini, Gemma).
+    Gemini,
+    /// Llama 3 style 128k vocabularies (Llama, Qwen, DeepSeek, Mistral, ...).
+    Llama,
+}
+
+struct FamilyParams {
+    /// Letters per token for words longer than one token.
+    word_chars: usize,
+    /// Tokens per 10 CJK characters.
+    cjk_per_10: usize,
+    /// Digits merged into one token.
+    digit_group: usize,
+    /// Role and framing tokens per message.
+    message_overhead: usize,
+    image_tokens: usize,
+}
+
+impl TokenizerFamily {
+    pub fn for_model(model: &str) -> Self {
+        let model = model.trim().to_ascii_lowercase();
+        let name = model.rsplit(['/', '.']).next().unwrap_or(&model);
+        let starts = |prefixes: &[&str]| {
+            prefixes
+                .iter()
+                .any(|p| name.starts_with(p) || model.starts_with(p))
+        };
+        if model.contains("claude") {
+            TokenizerFamily::Claude
+        } else if model.contains("gemini") || starts(&["gemma"]) {
+            TokenizerFamily::Gemini
+        } else if starts(&[
+            "gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4", "codex", "chatgpt",
+        ]) {
+            TokenizerFamily::O200k
+        } else if starts(&["gpt-4", "gpt-3.5"]) {
+            TokenizerFamily::Cl100k
+        } else if starts(&[
+            "llama", "qwen", "deepseek", "mistral", "mixtral", "kimi", "moonshot", "glm", "phi",
+        ]) {
+            TokenizerFamily::Llama
+        } else {
+            TokenizerFamily::Cl100k
+        }
+    }
+
+    fn params(self) -> FamilyParams {
+        match self {
+            TokenizerFamily::Claude => FamilyParams {
+                word_chars: 6,
+                cjk_per_10: 12,
+                digit_group: 3,
+                message_overhead: 5,
+                image_tokens: 1600,
+            },
+            TokenizerFamily::O200k => FamilyParams {
+                word_chars: 8,
+                cjk_per_10: 8,
+                digit_group: 3,
+                message_overhead: 4,
+                image_tokens: 765,
+            },
+            TokenizerFamily::Cl100k => FamilyParams {
+                word_chars: 7,
+                cjk_per_10: 13,
+                digit_group: 3,
+                message_overhead: 4,
+                image_tokens: 765,
+            },
+            TokenizerFamily::Gemini => FamilyParams {
+                word_chars: 8,
+                cjk_per_10: 7,
+                digit_group: 1,
+                message_overhead: 4,
+                image_tokens: 258,
+            },
+            TokenizerFamily::Llama => FamilyParams {
+                word_chars: 7,
+                cjk_per_10: 10,
+                digit_group: 3,
+                message_overhead: 5,
+                image_tokens: 1600,
+            },
+        }
+    }
+
+    /// The family's BPE vocabulary, loaded on first use. `None` for families
+    /// without a bundled vocabulary, or if it fails to load.
+    fn bpe(self) -> Option<&'static CoreBPE> {
+        static O200K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+        static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+        match self {
+            TokenizerFamily::O200k => O200K.get_or_init(|| tiktoken_rs::o200k_base().ok()),
+            TokenizerFamily::Cl100k => CL100K.get_or_init(|| tiktoken_rs::cl100k_base().ok()),
+            _ => return None,
+        }
+        .as_ref()
+    }
+
+    /// Uncalibrated token count of `text`: exact for the BPE families, an
+    /// [`estimate`](Self::estimate) otherwise.
+    pub fn count(self, text: &str) -> usize {
+        match self.bpe() {
+            Some(bpe) => bpe.encode_ordinary(text).len(),
+            None => self.estimate(text),
+        }
+    }
+
+    /// Token count from the family's pre-tokenization and piece lengths.
+    fn estimate(self, text: &str) -> usize {
+        let p = self.params();
+        let mut tokens = 0;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let class = CharClass::of(c);
+            let mut run: usize = 1;
+            while chars.peek().is_some_and(|n| CharClass::of(*n) == class) {
+                chars.next();
+                run += 1;
+            }
+            tokens += match class {
+                CharClass::Letter => run.div_ceil(p.word_chars),
+                CharClass::Cjk => (run * p.cjk_per_10).div_ceil(10),
+                CharClass::Digit => run.div_ceil(p.digit_group),
+                // A single space before a word is part of the word's token.
+                CharClass::Space
+                    if run == 1
+                        && c == ' '
+                        && chars
+                            .peek()
+                            .is_some_and(|n| CharClass::of(*n) == CharClass::Letter) =>
+                {
+                    0
+                }
+                CharClass::Space => run.div_ceil(16),
+                CharClass::Other => run.div_ceil(2),
+            };
+        }
+        tokens
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Letter,
+    Cjk,
+    Digit,
+    Space,
+    Other,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            CharClass::Space
+        } else if c.is_ascii_digit() {
+            CharClass::Digit
+        } else if is_cjk(c) {
+            CharClass::Cjk
+        } else if c.is_alphabetic() {
+            CharClass::Letter
+        } else {
+            CharClass::Other
+        }
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0x20000..=0x2CEAF
+    )
+}
+
+fn calibration() -> &'static Mutex<HashMap<String, f64>> {
+    static CALIBRATION: OnceLock<Mutex<HashMap<String, f64>>> = OnceLock::new();
+    CALIBRATION.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Record the provider-reported input tokens for a request estimated at
+/// `estimated` tokens.
+pub fn calibrate(model: &str, estimated: usize, actual: u32) {
+    if estimated < 100 || actual == 0 {
+        return;
+    }
+    let ratio = (f64::from(actual) / estimated as f64).clamp(0.5, 2.0);
+    let mut map = calibration().lock().unwrap_or_else(|e| e.into_inner());
+    let scale = map.entry(model.trim().to_string()).or_insert(ratio);
+    *scale = *scale * (1.0 - CALIBRATION_WEIGHT) + ratio * CALIBRATION_WEIGHT;
+}
+
+/// Counts tokens for one model, with its calibration applied.
+#[derive(Clone, Copy, Debug)]
+pub struct TokenCounter {
+    family: TokenizerFamily,
+    scale: f64,
+}
+
+impl TokenCounter {
+    pub fn for_model(model: &str) -> Self {
+        let scale = calibration()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(model.trim())
+            .copied()
+            .unwrap_or(1.0);
+        TokenCounter {
+            family: TokenizerFamily::for_model(model),
+            scale,
+        }
+    }
+
+    fn scaled(&self, tokens: usize) -> usize {
+        (tokens as f64 * self.scale).ceil() as usize
+    }
+
+    fn raw_message(&self, message: &Message) -> usize {
+        let p = self.family.params();
+        let content = match &message.content {
+            MessageContent::Text(text) => self.family.count(text),
+            MessageContent::Blocks(blocks) => blocks
+                .iter()
+                .map(|block| match block {
+                    ContentBlock::Text { text } => self.family.count(text),
+                    ContentBlock::Image { .. } => p.image_tokens,
+                    ContentBlock::ToolUse { name, input, .. } => {
+                        self.family.count(name) + self.family.count(&input.to_string()) + 3
+                    }
+                    ContentBlock::ToolResult { content, .. } => self.family.count(content) + 3,
+                    ContentBlock::Thinking { thinking, .. } => self.family.count(thinking),
+                    ContentBlock::RedactedThinking { data } => data.len() / 4,
+                })
+                .sum(),
+        };
+        content + p.message_overhead
+    }
+
+    pub fn text(&self, text: &str) -> usize {
+        self.scaled(self.family.count(text))
+    }
+
+    pub fn message(&self, message: &Message) -> usize {
+        self.scaled(self.raw_message(message))
+    }
+
+    pub fn messages(&self, messages: &[Message]) -> usize {
+        self.scaled(messages.iter().map(|m| self.raw_message(m)).sum())
+    }
+
+    pub fn tools(&self, tools: &[ToolDefinition]) -> usize {
+        self.scaled(
+            tools
+                .iter()
+                .map(|t| {
+                    self.family.count(&t.name)
+                        + self.family.count(&t.description)
+                        + self.family.count(&t.input_schema.to_string())
+                        + 8
+                })
+                .sum(),
+        )
+    }
+
+    /// Input tokens of a whole request.
+    pub fn request(&self, system: &str, messages: &[Message], tools: &[ToolDefinition]) -> usize {
+        self.text(system) + self.messages(messages) + self.tools(tools)
+    }
+
+    /// Uncalibrated request count, the figure to pass to [`calibrate`].
+    pub fn raw_request(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> usize {
+        TokenCounter {
+            family: self.family,
+            scale: 1.0,
+        }
+        .request(system, messages, tools)
+    }
+}
+
+/// How many of the newest messages to keep verbatim: at most `max_messages`
+/// and `budget` tokens, but always at least one.
+pub fn recent_within(
+    counter: &TokenCounter,
+    messages: &[Message],
+    max_messages: usize,
+    budget: usize,
+) -> usize {
+    let mut used = 0;
+    let mut kept = 0;
+    for message in messages.iter().rev().take(max_messages.max(1)) {
+        used += counter.message(message);
+        if kept > 0 && used > budget {
+            break;
+        }
+        kept += 1;
+    }
+    kept
+}
+
+/// What [`fit_to_window`] had to do.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FitOutcome {
+    pub truncated_tool_results: usize,
+    pub dropped_messages: usize,
+}
+
+/// Shrink `messages` until the request fits in `limit` tokens: the largest
+/// tool results are truncated (down to a floor) first, then the oldest
+/// messages are dropped. The last message is always kept.
+pub fn fit_to_window(
+    counter: &TokenCounter,
+    system: &str,
+    messages: &mut Vec<Message>,
+    tools: &[ToolDefinition],
+    limit: usize,
+) -> FitOutcome {
+    let mut outcome = FitOutcome::default();
+    let mut total = counter.request(system, messages, tools);
+    if total <= limit {
+        return outcome;
+    }
+
+    let mut results: Vec<(usize, usize, usize)> = Vec::new();
+    for (mi, message) in messages.iter().enumerate() {
+        if let MessageContent::Blocks(blocks) = &message.content {
+            for (bi, block) in blocks.iter().enumerate() {
+                if let ContentBlock::ToolResult { content, .. } = block {
+                    results.push((mi, bi, counter.text(content)));
+                }
+            }
+        }
+    }
+    results.sort_by_key(|(_, _, tokens)| std::cmp::Reverse(*tokens));
+    for (mi, bi, tokens) in results {
+        if total <= limit {
+            break;
+        }
+        if tokens <= TOOL_RESULT_FLOOR_TOKENS {
+            break;
+        }
+        // Leave room for the truncation note.
+        let keep = tokens
+            .saturating_sub(total - limit + 32)
+            .max(TOOL_RESULT_FLOOR_TOKENS);
+        if let MessageContent::Blocks(blocks) = &mut messages[mi].content {
+            if let ContentBlock::ToolResult { content, .. } = &mut blocks[bi] {
+                let cut = floor_char_boundary(content, content.len() * keep / tokens);
+                content.truncate(cut);
+                content.push_str(&format!(
+                    "\n[... truncated {} tokens to fit the context window]",
+                    tokens - keep
+                ));
+                outcome.truncated_tool_results += 1;
+            }
+        }
+        total = counter.request(system, messages, tools);
+    }
+
+    while total > limit && messages.len() > 1 {
+        messages.remove(0);
+        outcome.dropped_messages += 1;
+        // The history must start with a user turn that answers no dropped call.
+        while messages.len() > 1 && !starts_conversation(&messages[0]) {
+            messages.remove(0);
+            outcome.dropped_messages += 1;
+        }
+        total = counter.request(system, messages, tools);
+    }
+    outcome
+}
+
+fn starts_conversation(message: &Message) -> bool {
+    message.role == "user"
+        && match &message.content {
+            MessageContent::Text(_) => true,
+            MessageContent::Blocks(blocks) => !blocks
+                .iter()
+                .any(|b| matches!(b, ContentBlock::ToolResult { .. })),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(role: &str, text: &str) -> Message {
+        Message {
+            role: role.into(),
+            content: MessageContent::Text(text.into()),
+        }
+    }
+
+    fn tool_result(content: String) -> Message {
+        Message {
+            role: "user".into(),
+            content: MessageContent::Blocks(vec![ContentBlock::ToolResult {
+                tool_use_id: "t1".into(),
+                content,
+                is_error: None,
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_family_for_model() {
+        use TokenizerFamily::*;
+        assert_eq!(
+            TokenizerFamily::for_model("claude-sonnet-4-5-20250929"),
+            Claude
+        );
+        assert_eq!(
+            TokenizerFamily::for_model("us.anthropic.claude-haiku-4-5-20251001-v1:0"),
+            Claude
+        );
+        assert_eq!(TokenizerFamily::for_model("gpt-5.2"), O200k);
+        assert_eq!(TokenizerFamily::for_model("openai/gpt-4o-mini"), O200k);
+        assert_eq!(TokenizerFamily::for_model("gpt-4-turbo"), Cl100k);
+        assert_eq!(TokenizerFamily::for_model("gemini-2.5-pro"), Gemini);
+        assert_eq!(TokenizerFamily::for_model("qwen2.5:14b"), Llama);
+        assert_eq!(TokenizerFamily::for_model("my-local-model"), Cl100k);
+    }
+
+    #[test]
+    fn test_estimate_pieces() {
+        let o200k = TokenizerFamily::O200k;
+        assert_eq!(o200k.estimate("hello world"), 2);
+        assert_eq!(o200k.estimate("internationalization"), 3);
+        assert_eq!(o200k.estimate("1234567"), 3);
+        assert_eq!(o200k.estimate("你好世界"), 4);
+        assert_eq!(TokenizerFamily::Claude.count("你好世界"), 5);
+        assert_eq!(TokenizerFamily::Gemini.count("1234567"), 7);
+        assert_eq!(o200k.estimate(""), 0);
+    }
+
+    #[test]
+    fn test_count_uses_bpe_for_openai_families() {
+        let o200k = TokenizerFamily::O200k;
+        assert_eq!(o200k.count("hello world"), 2);
+        assert_eq!(o200k.count("1234567"), 3);
+        assert_eq!(o200k.count(""), 0);
+        assert_eq!(TokenizerFamily::Cl100k.count("hello world"), 2);
+        let text = "fn main() { println!(\"{}\", 42); }";
+        assert_eq!(
+            o200k.count(text),
+            o200k.bpe().unwrap().encode_ordinary(text).len()
+        );
+    }
+
+    #[test]
+    fn test_recent_within() {
+        let counter = TokenCounter::for_model("gpt-4o-recent-test");
+        let messages = vec![
+            text("user", &"long ".repeat(1000)),
+            text("assistant", "short answer"),
+            text("user", "short question"),
+        ];
+        assert_eq!(recent_within(&counter, &messages, 20, 100), 2);
+        assert_eq!(recent_within(&counter, &messages, 1, 100_000), 1);
+        assert_eq!(recent_within(&counter, &messages[..1], 20, 10), 1);
+    }
+
+    #[test]
+    fn test_fit_truncates_tool_results_before_dropping_messages() {
+        let counter = TokenCounter::for_model("gpt-4o-fit-test");
+        let big = "word ".repeat(5000);
+        let mut messages = vec![
+            text("user", "read the log"),
+            Message {
+                role: "assistant".into(),
+                content: MessageContent::Blocks(vec![ContentBlock::ToolUse {
+                    id: "t1".into(),
+                    name: "read_file".into(),
+                    input: serde_json::json!({"path": "app.log"}),
+                }]),
+            },
+            tool_result(big),
+        ];
+        let outcome = fit_to_window(&counter, "system", &mut messages, &[], 2000);
+        assert_eq!(outcome.truncated_tool_results, 1);
+        assert_eq!(outcome.dropped_messages, 0);
+        assert!(counter.request("system", &messages, &[]) <= 2000);
+
+        // When truncation cannot help, the oldest turns go first.
+        let mut messages = vec![
+            text("user", &"old ".repeat(3000)),
+            text("assistant", "ok"),
+            text("user", "latest question"),
+        ];
+        let outcome = fit_to_window(&counter, "system", &mut messages, &[], 500);
+        assert_eq!(outcome.dropped_messages, 2);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, "user");
+    }
+}
@@ -0,0 +1,220 @@
+//! `microclaw tool list` / `microclaw tool run`: call registered tools
+//! directly, without the LLM.
+//!
+//! Calls run as the local `cli` chat (the one `microclaw chat` uses, or
+//! `--chat <id>`), which is treated as a control chat. High-risk tools still
+//! need approval; `--approve` grants it for the call.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::channel_adapter::ChannelRegistry;
+use crate::chat::CliAdapter;
+use crate::config::Config;
+use crate::db::Database;
+use crate::tools::{pre_approve_call, tool_risk, ToolAuthContext, ToolRegistry};
+
+const TOOL_USAGE: &str = "Usage: microclaw tool list
+       microclaw tool run <name> [--input '<json>'] [--chat <id>] [--approve]
+
+list  Show the registered tools (built-in, skills and MCP) and their risk.
+run   Execute one tool and print its output. --input takes a JSON object
+      (or - to read it from stdin; default {}). The call runs as the local
+      cli chat, or --chat <id>, with control-chat permissions. --approve
+      confirms high-risk tools such as bash. Exits with 1 when the tool
+      reports an error.";
+
+#[derive(Debug, PartialEq)]
+struct RunArgs {
+    name: String,
+    input: Option<String>,
+    chat_id: Option<i64>,
+    approve: bool,
+}
+
+fn parse_run_args(args: &[String]) -> anyhow::Result<RunArgs> {
+    let mut name = None;
+    let mut input = None;
+    let mut chat_id = None;
+    let mut approve = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--input" => {
+                input = Some(
+                    iter.next()
+                        .ok_or_else(|| anyhow!("--input needs a value"))?,
+                )
+            }
+            "--chat" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| anyhow!("--chat needs a chat id"))?;
+                chat_id = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("invalid chat id: {value}"))?,
+                );
+            }
+            "--approve" => approve = true,
+            flag if flag.starts_with('-') => bail!("unknown option {flag}"),
+            _ if name.is_none() => name = Some(arg.clone()),
+            other => bail!("unexpected argument {other}"),
+        }
+    }
+    Ok(RunArgs {
+        name: name.ok_or_else(|| anyhow!("missing tool name"))?,
+        input: input.cloned(),
+        chat_id,
+        approve,
+    })
+}
+
+fn parse_input(raw: Option<&str>) -> anyhow::Result<serde_json::Value> {
+    let raw = match raw {
+        None => return Ok(serde_json::json!({})),
+        Some("-") => {
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        }
+        Some(raw) => raw.to_string(),
+    };
+    let value: serde_json::Value =
+        serde_json::from_str(&raw).context("--input is not valid JSON")?;
+    if !value.is_object() {
+        bail!("--input must be a JSON object");
+    }
+    Ok(value)
+}
+
+async fn build_registry(config: &Config, db: Arc<Database>) -> ToolRegistry {
+    let mut channels = ChannelRegistry::new();
+    channels.register(Arc::new(CliAdapter));
+    let mut tools = ToolRegistry::new(config, Arc::new(channels), db);
+    let mcp_config_path = config.data_root_dir().join("mcp.json");
+    let mcp_manager =
+        crate::mcp::McpManager::from_config_file(&mcp_config_path.to_string_lossy()).await;
+    for (server, tool_info) in mcp_manager.all_tools() {
+        tools.add_tool(Box::new(crate::tools::mcp::McpTool::new(server, tool_info)));
+    }
+    tools
+}
+
+pub async fn run_cli(args: &[String]) -> anyhow::Result<()> {
+    // `None` lists the tools.
+    let run = match args.first().map(String::as_str) {
+        Some("list") => None,
+        Some("run") if !args.iter().any(|a| a == "--help" || a == "-h") => {
+            Some(parse_run_args(&args[1..])?)
+        }
+        Some("help" | "--help" | "-h" | "run") | None => {
+            println!("{TOOL_USAGE}");
+            return Ok(());
+        }
+        Some(other) => {
+            eprintln!("Unknown tool command: {other}\n\n{TOOL_USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = Config::load_without_channels()?;
+    config.data_dir = config.runtime_data_dir();
+    crate::db_crypt::init(&config)?;
+    let db = Arc::new(Database::new(&config.data_dir)?);
+    let tools = build_registry(&config, db.clone()).await;
+
+    let Some(run) = run else {
+        for def in tools.definitions() {
+            let summary = def.description.lines().next().unwrap_or_default();
+            println!(
+                "{:<24} {:<6} {summary}",
+                def.name,
+                tool_risk(&def.name).as_str()
+            );
+        }
+        return Ok(());
+    };
+    let input = parse_input(run.input.as_deref())?;
+    let chat_id = match run.chat_id {
+        Some(id) => id,
+        None => db.resolve_or_create_chat_id("cli", "local", Some("Terminal"), "cli")?,
+    };
+    let mut control_chat_ids = config.control_chat_ids.clone();
+    control_chat_ids.push(chat_id);
+    let auth = ToolAuthContext {
+        caller_channel: "cli".to_string(),
+        caller_chat_id: chat_id,
+        control_chat_ids,
+        ..Default::default()
+    };
+    if run.approve {
+        pre_approve_call(&auth, &run.name);
+    }
+
+    let result = tools.execute_with_auth(&run.name, input, &auth).await;
+    println!("{}", result.content);
+    eprintln!(
+        "[{}{}, {} ms, {} bytes]",
+        if result.is_error { "error" } else { "ok" },
+        result
+            .error_type
+            .as_deref()
+            .map(|t| format!(": {t}"))
+            .unwrap_or_default(),
+        result.duration_ms.unwrap_or(0),
+        result.bytes
+    );
+    if result.error_type.as_deref() == Some("approval_required") {
+        // Approval tokens don't outlive this process.
+        eprintln!("Re-run with --approve to confirm.");
+    }
+    if result.is_error {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_run_args() {
+        let parsed = parse_run_args(&args(&[
+            "bash",
+            "--input",
+            "{\"command\":\"ls\"}",
+            "--approve",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed,
+            RunArgs {
+                name: "bash".into(),
+                input: Some("{\"command\":\"ls\"}".into()),
+                chat_id: None,
+                approve: true,
+            }
+        );
+        assert_eq!(
+            parse_run_args(&args(&["--chat", "-5", "read_file"]))
+                .unwrap()
+                .chat_id,
+            Some(-5)
+        );
+        assert!(parse_run_args(&args(&["--input"])).is_err());
+        assert!(parse_run_args(&args(&["--approve"])).is_err());
+        assert!(parse_run_args(&args(&["a", "b"])).is_err());
+
+        assert_eq!(parse_input(None).unwrap(), serde_json::json!({}));
+        assert!(parse_input(Some("[1]")).is_err());
+        assert!(parse_input(Some("{nope")).is_err());
+    }
+}
@@ -3,16 +3,20 @@ use serde_json::json;
 use std::path::PathBuf;
 use tracing::info;
 
-use crate::config::WorkingDirIsolation;
+use crate::config::{BashBackend, BashContainerConfig, NetworkPolicyConfig, WorkingDirIsolation};
 use crate::llm_types::ToolDefinition;
-use crate::text::floor_char_boundary;
-use crate::tools::command_runner::{build_command, shell_command};
+use crate::tools::bash_container::{self, ContainerSandbox};
+use crate::tools::command_runner::{
+    build_command, isolate_process_group, shell_command, ProcessGroupGuard,
+};
 
 use super::{schema_object, Tool, ToolResult};
 
 pub struct BashTool {
     working_dir: PathBuf,
     working_dir_isolation: WorkingDirIsolation,
+    network_policy: NetworkPolicyConfig,
+    container: Option<ContainerSandbox>,
 }
 
 impl BashTool {
@@ -27,8 +31,23 @@ impl BashTool {
         Self {
             working_dir: PathBuf::from(working_dir),
             working_dir_isolation,
+            network_policy: NetworkPolicyConfig::default(),
+            container: None,
         }
     }
+
+    /// Route commands through the network policy proxy when `bash_proxy` is on.
+    pub fn with_network_policy(mut self, network_policy: NetworkPolicyConfig) -> Self {
+        self.network_policy = network_policy;
+        self
+    }
+
+    /// Run commands in a per-workspace container for `BashBackend::Container`.
+    pub fn with_backend(mut self, backend: BashBackend, container: BashContainerConfig) -> Self {
+        self.container =
+            (backend == BashBackend::Container).then(|| ContainerSandbox::new(container));
+        self
+    }
 }
 
 #[async_trait]
@@ -78,12 +97,64 @@ impl Tool for BashTool {
 
         info!("Executing bash: {}", command);
 
-        let spec = shell_command(command);
+        let container = match &self.container {
+            Some(sandbox) => match sandbox.ensure_running(&working_dir).await {
+                Ok(container) => Some(container),
+                Err(e) => return ToolResult::error(e).with_error_type("spawn_error"),
+            },
+            None => None,
+        };
+        let spec = match &container {
+            Some((runtime, name)) => bash_container::exec_command(runtime, name, command),
+            None => shell_command(command),
+        };
+        let mut cmd = build_command(&spec, Some(&working_dir));
+        isolate_process_group(&mut cmd);
+        let chat_id = super::auth_context_from_input(&input).map(|auth| auth.caller_chat_id);
+        // The container's network is governed by `bash_container.network` instead
+        let proxy = match &container {
+            Some(_) => None,
+            None => {
+                match crate::network_policy::proxy_url_for_chat(&self.network_policy, chat_id) {
+                    Ok(proxy) => proxy,
+                    Err(e) => {
+                        return ToolResult::error(format!(
+                            "Network policy proxy unavailable, refusing to run the command: {e}"
+                        ))
+                        .with_error_type("network_policy")
+                    }
+                }
+            }
+        };
+        if let Some(proxy) = proxy {
+            for var in ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"] {
+                cmd.env(var, &proxy);
+            }
+            // Local targets must go through the proxy too, so the SSRF guard sees them.
+            for var in ["NO_PROXY", "no_proxy", "ALL_PROXY", "all_proxy"] {
+                cmd.env_remove(var);
+            }
+        }
+        cmd.stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        let child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                return ToolResult::error(format!("Failed to execute command: {e}"))
+                    .with_error_type("spawn_error")
+            }
+        };
+        // Kills the command's process tree on timeout or when this future is dropped
+        let mut group_guard = ProcessGroupGuard::new(child.id());
         let result = tokio::time::timeout(
             std::time::Duration::from_secs(timeout_secs),
-            build_command(&spec, Some(&working_dir)).output(),
+            child.wait_with_output(),
         )
         .await;
+        if matches!(result, Ok(Ok(_))) {
+            // Leave intentionally backgrounded processes alone after a normal exit
+            group_guard.disarm();
+        }
 
         match result {
             Ok(Ok(output)) => {
@@ -106,13 +177,6 @@ impl Tool for BashTool {
                     result_text = format!("Command completed with exit code {exit_code}");
                 }
 
-                // Truncate very long output
-                if result_text.len() > 30000 {
-                    let cutoff = floor_char_boundary(&result_text, 30000);
-                    result_text.truncate(cutoff);
-                    result_text.push_str("\n... (output truncated)");
-                }
-
                 if exit_code == 0 {
                     ToolResult::success(result_text).with_status_code(exit_code)
                 } else {
@@ -123,8 +187,13 @@ impl Tool for BashTool {
             }
             Ok(Err(e)) => ToolResult::error(format!("Failed to execute command: {e}"))
                 .with_error_type("spawn_error"),
-            Err(_) => ToolResult::error(format!("Command timed out after {timeout_secs} seconds"))
-                .with_error_type("timeout"),
+            Err(_) => {
+                if let (Some(sandbox), Some((runtime, name))) = (&self.container, &container) {
+                    sandbox.remove(runtime, name).await;
+                }
+                ToolResult::error(format!("Command timed out after {timeout_secs} seconds"))
+                    .with_error_type("timeout")
+            }
         }
     }
 }
@@ -0,0 +1,265 @@
+//! `bash_backend: container`: `bash` commands run via `<runtime> exec` in one
+//! long-lived podman/docker container per workspace. The workspace is
+//! bind-mounted at `/workspace`, and containers get no network unless
+//! `bash_container.network` is set.
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::config::BashContainerConfig;
+use crate::tools::command_runner::CommandSpec;
+
+/// Mount point of the workspace inside the container.
+pub const CONTAINER_WORKDIR: &str = "/workspace";
+
+const RUNTIMES: [&str; 2] = ["podman", "docker"];
+
+pub struct ContainerSandbox {
+    config: BashContainerConfig,
+}
+
+impl ContainerSandbox {
+    pub fn new(config: BashContainerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Container for `working_dir`, started (or created) if it is not running.
+    /// Returns the runtime program and container name.
+    pub async fn ensure_running(&self, working_dir: &Path) -> Result<(String, String), String> {
+        let runtime = resolve_runtime(&self.config)?;
+        let working_dir = std::fs::canonicalize(working_dir)
+            .map_err(|e| format!("Failed to resolve {}: {e}", working_dir.display()))?;
+        let name = container_name(&self.config, &working_dir);
+
+        match is_running(&runtime, &name).await {
+            Some(true) => return Ok((runtime, name)),
+            Some(false) => {
+                run(&runtime, &["start".to_string(), name.clone()]).await?;
+                return Ok((runtime, name));
+            }
+            None => {}
+        }
+
+        info!(
+            "Creating bash container {name} for {} from {}",
+            working_dir.display(),
+            self.config.image
+        );
+        let args = run_args(&self.config, &runtime, &name, &working_dir);
+        if let Err(e) = run(&runtime, &args).await {
+            // Another call for the same workspace may have created it first
+            if is_running(&runtime, &name).await != Some(true) {
+                return Err(e);
+            }
+        }
+        Ok((runtime, name))
+    }
+
+    /// Remove a container whose command timed out, so its leftover processes
+    /// die with it. The next command creates a fresh one.
+    pub async fn remove(&self, runtime: &str, name: &str) {
+        if let Err(e) = run(runtime, &["rm".into(), "-f".into(), name.into()]).await {
+            warn!("Failed to remove bash container {name}: {e}");
+        }
+    }
+}
+
+/// `<runtime> exec` invocation for `command` in container `name`.
+pub fn exec_command(runtime: &str, name: &str, command: &str) -> CommandSpec {
+    CommandSpec {
+        program: runtime.to_string(),
+        args: vec![
+            "exec".into(),
+            "-w".into(),
+            CONTAINER_WORKDIR.into(),
+            name.into(),
+            "bash".into(),
+            "-c".into(),
+            command.into(),
+        ],
+    }
+}
+
+/// The configured runtime, or podman/docker from PATH.
+pub fn resolve_runtime(config: &BashContainerConfig) -> Result<String, String> {
+    if !config.runtime.is_empty() {
+        return Ok(config.runtime.clone());
+    }
+    RUNTIMES
+        .into_iter()
+        .find(|name| find_on_path(name).is_some())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            "bash_backend is container but neither podman nor docker is on PATH; \
+             install one or set bash_container.runtime"
+                .to_string()
+        })
+}
+
+/// Stable per workspace and container settings, so a config change gets
+/// fresh containers instead of reusing ones created with the old settings.
+fn container_name(config: &BashContainerConfig, working_dir: &Path) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(working_dir.to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(config.image.as_bytes());
+    hasher.update([u8::from(config.network)]);
+    for arg in &config.run_args {
+        hasher.update([0]);
+        hasher.update(arg.as_bytes());
+    }
+    let digest = hasher.finalize();
+    let hex: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    format!("microclaw-bash-{hex}")
+}
+
+fn run_args(
+    config: &BashContainerConfig,
+    runtime: &str,
+    name: &str,
+    working_dir: &Path,
+) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "run".into(),
+        "-d".into(),
+        "--init".into(),
+        "--name".into(),
+        name.into(),
+        "--label".into(),
+        format!("microclaw.workspace={}", working_dir.display()),
+        "-v".into(),
+        format!("{}:{CONTAINER_WORKDIR}", working_dir.display()),
+        "-w".into(),
+        CONTAINER_WORKDIR.into(),
+    ];
+    if !config.network {
+        args.extend(["--network".into(), "none".into()]);
+    }
+    // Rootful docker would leave root-owned files in the workspace; rootless
+    // podman already maps container root to the invoking user.
+    #[cfg(unix)]
+    if !runtime_is_podman(runtime) {
+        // SAFETY: getuid/getgid cannot fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        args.extend(["--user".into(), format!("{uid}:{gid}")]);
+    }
+    args.extend(config.run_args.iter().cloned());
+    args.extend([config.image.clone(), "sleep".into(), "infinity".into()]);
+    args
+}
+
+fn runtime_is_podman(runtime: &str) -> bool {
+    Path::new(runtime)
+        .file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().starts_with("podman"))
+}
+
+/// `Some(running)` if the container exists.
+async fn is_running(runtime: &str, name: &str) -> Option<bool> {
+    let output = tokio::process::Command::new(runtime)
+        .args([
+            "container",
+            "inspect",
+            "--format",
+            "{{.State.Running}}",
+            name,
+        ])
+        .output()
+        .await
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+async fn run(runtime: &str, args: &[String]) -> Result<(), String> {
+    let output = tokio::process::Command::new(runtime)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {runtime}: {e}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(format!(
+        "{runtime} {} failed: {}",
+        args.first().map(String::as_str).unwrap_or_default(),
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var).find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let exe = dir.join(format!("{program}.exe"));
+        (cfg!(target_os = "windows") && exe.is_file()).then_some(exe)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_name_tracks_workspace_and_settings() {
+        let config = BashContainerConfig::default();
+        let a = container_name(&config, Path::new("/data/chat/1"));
+        assert!(a.starts_with("microclaw-bash-"));
+        assert_eq!(a, container_name(&config, Path::new("/data/chat/1")));
+        assert_ne!(a, container_name(&config, Path::new("/data/chat/2")));
+        let networked = BashContainerConfig {
+            network: true,
+            ..Default::default()
+        };
+        assert_ne!(a, container_name(&networked, Path::new("/data/chat/1")));
+    }
+
+    #[test]
+    fn test_run_args_mount_workspace_without_network() {
+        let config = BashContainerConfig {
+            run_args: vec!["--memory".into(), "1g".into()],
+            ..Default::default()
+        };
+        let args = run_args(&config, "podman", "box", Path::new("/data/chat/1"));
+        let joined = args.join(" ");
+        assert!(joined.contains("-v /data/chat/1:/workspace"));
+        assert!(joined.contains("--network none"));
+        assert!(!joined.contains("--user"));
+        assert!(joined.ends_with("--memory 1g debian:bookworm-slim sleep infinity"));
+
+        let config = BashContainerConfig {
+            network: true,
+            ..Default::default()
+        };
+        let args = run_args(&config, "/usr/bin/docker", "box", Path::new("/w"));
+        assert!(!args.iter().any(|a| a == "--network"));
+        #[cfg(unix)]
+        assert!(args.iter().any(|a| a == "--user"));
+    }
+
+    #[test]
+    fn test_exec_command_runs_bash_in_workspace() {
+        let spec = exec_command("docker", "box", "ls -la");
+        assert_eq!(spec.program, "docker");
+        assert_eq!(
+            spec.args,
+            ["exec", "-w", "/workspace", "box", "bash", "-c", "ls -la"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_runtime_prefers_configured() {
+        let config = BashContainerConfig {
+            runtime: "/opt/bin/podman".into(),
+            ..Default::default()
+        };
+        assert_eq!(resolve_runtime(&config).unwrap(), "/opt/bin/podman");
+    }
+}
@@ -4,14 +4,16 @@ use async_trait::async_trait;
 use serde_json::json;
 use tracing::info;
 
+use crate::config::NetworkPolicyConfig;
 use crate::llm_types::ToolDefinition;
-use crate::text::floor_char_boundary;
+use crate::network_policy::check_url;
 use crate::tools::command_runner::agent_browser_program;
 
 use super::{auth_context_from_input, schema_object, Tool, ToolResult};
 
 pub struct BrowserTool {
     data_dir: PathBuf,
+    network_policy: NetworkPolicyConfig,
 }
 
 fn split_browser_command(command: &str) -> Result<Vec<String>, String> {
@@ -69,13 +71,24 @@ fn split_browser_command(command: &str) -> Result<Vec<String>, String> {
     Ok(args)
 }
 
+fn is_http_url(arg: &str) -> bool {
+    let lower = arg.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
 impl BrowserTool {
     pub fn new(data_dir: &str) -> Self {
         BrowserTool {
             data_dir: PathBuf::from(data_dir).join("groups"),
+            network_policy: NetworkPolicyConfig::default(),
         }
     }
 
+    pub fn with_network_policy(mut self, network_policy: NetworkPolicyConfig) -> Self {
+        self.network_policy = network_policy;
+        self
+    }
+
     fn profile_path(&self, chat_id: i64) -> PathBuf {
         self.data_dir
             .join(chat_id.to_string())
@@ -177,6 +190,13 @@ impl Tool for BrowserTool {
                 ));
             }
         };
+        // Navigation targets (open, tab new, ...) are checked against the network policy.
+        let chat_id = auth.as_ref().map(|auth| auth.caller_chat_id);
+        for url in command_args.iter().filter(|a| is_http_url(a)) {
+            if let Err(e) = check_url(&self.network_policy, chat_id, "browser", url).await {
+                return ToolResult::error(e).with_error_type("network_policy");
+            }
+        }
         args.extend(command_args);
 
         let program = agent_browser_program();
@@ -209,13 +229,6 @@ impl Tool for BrowserTool {
                     result_text = format!("Command completed with exit code {exit_code}");
                 }
 
-                // Truncate very long output
-                if result_text.len() > 30000 {
-                    let cutoff = floor_char_boundary(&result_text, 30000);
-                    result_text.truncate(cutoff);
-                    result_text.push_str("\n... (output truncated)");
-                }
-
                 if exit_code == 0 {
                     ToolResult::success(result_text).with_status_code(exit_code)
                 } else {
@@ -294,4 +307,15 @@ mod tests {
         assert!(result.is_error);
         assert!(result.content.contains("Missing 'command'"));
     }
+
+    #[tokio::test]
+    async fn test_browser_blocks_private_navigation() {
+        let tool = BrowserTool::new("/tmp/test-data");
+        let result = tool
+            .execute(json!({"command": "open http://127.0.0.1:8080/admin"}))
+            .await;
+        assert!(result.is_error);
+        assert_eq!(result.error_type.as_deref(), Some("network_policy"));
+        assert!(result.content.contains("Blocked by network policy"));
+    }
 }
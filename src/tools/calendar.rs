@@ -0,0 +1,419 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::json;
+
+use super::{auth_context_from_input, authorize_chat_access, schema_object, Tool, ToolResult};
+use crate::calendar::{CalendarClient, CalendarEvent, NewEvent};
+use crate::channel::enforce_channel_policy;
+use crate::channel_adapter::ChannelRegistry;
+use crate::db::{call_blocking, Database};
+use crate::llm_types::ToolDefinition;
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 90;
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+const DEFAULT_REMIND_MINUTES: i64 = 15;
+/// How far ahead `remind` looks for the next event.
+const REMIND_LOOKAHEAD_DAYS: i64 = 14;
+
+pub struct CalendarTool {
+    client: Arc<CalendarClient>,
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+    timezone: Tz,
+}
+
+impl CalendarTool {
+    pub fn new(
+        client: Arc<CalendarClient>,
+        registry: Arc<ChannelRegistry>,
+        db: Arc<Database>,
+        timezone: &str,
+    ) -> Self {
+        CalendarTool {
+            client,
+            registry,
+            db,
+            timezone: Tz::from_str(timezone).unwrap_or(Tz::UTC),
+        }
+    }
+
+    /// RFC 3339, or a local `YYYY-MM-DDTHH:MM[:SS]` in the configured zone.
+    fn parse_time(&self, value: &str) -> Result<DateTime<Utc>, String> {
+        let value = value.trim();
+        if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+            return Ok(dt.with_timezone(&Utc));
+        }
+        ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+            .and_then(|naive| self.timezone.from_local_datetime(&naive).earliest())
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok_or_else(|| format!("Invalid time {value:?}; use ISO 8601, e.g. 2026-10-16T14:00"))
+    }
+
+    fn format_event(&self, event: &CalendarEvent) -> String {
+        let start = event.start.with_timezone(&self.timezone);
+        let mut line = if event.all_day {
+            format!(
+                "{} (all day) {}",
+                start.format("%a %Y-%m-%d"),
+                event.summary
+            )
+        } else {
+            let end = event
+                .end
+                .map(|end| format!("–{}", end.with_timezone(&self.timezone).format("%H:%M")))
+                .unwrap_or_default();
+            format!(
+                "{}{end} {}",
+                start.format("%a %Y-%m-%d %H:%M"),
+                event.summary
+            )
+        };
+        if let Some(location) = &event.location {
+            line.push_str(&format!(" @ {location}"));
+        }
+        if !event.id.is_empty() {
+            line.push_str(&format!(" [id: {}]", event.id));
+        }
+        line
+    }
+
+    async fn list(&self, input: &serde_json::Value) -> ToolResult {
+        let days = input
+            .get("days")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(DEFAULT_DAYS)
+            .clamp(1, MAX_DAYS);
+        let limit = input
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_LIMIT))
+            .unwrap_or(DEFAULT_LIMIT);
+        let now = Utc::now();
+        match self
+            .client
+            .upcoming(now, now + Duration::days(days), limit)
+            .await
+        {
+            Ok(events) if events.is_empty() => {
+                ToolResult::success(format!("No events in the next {days} days."))
+            }
+            Ok(events) => {
+                let lines: Vec<String> = events
+                    .iter()
+                    .map(|e| format!("- {}", self.format_event(e)))
+                    .collect();
+                ToolResult::success(format!(
+                    "Upcoming events (tz: {}):\n{}",
+                    self.timezone,
+                    lines.join("\n")
+                ))
+            }
+            Err(e) => ToolResult::error(format!("Failed to list events: {e}")),
+        }
+    }
+
+    async fn create(&self, input: &serde_json::Value) -> ToolResult {
+        let summary = match input.get("summary").and_then(|v| v.as_str()) {
+            Some(s) if !s.trim().is_empty() => s.trim().to_string(),
+            _ => return ToolResult::error("Missing required parameter: summary".into()),
+        };
+        let start = match input.get("start").and_then(|v| v.as_str()) {
+            Some(s) => match self.parse_time(s) {
+                Ok(start) => start,
+                Err(e) => return ToolResult::error(e),
+            },
+            None => return ToolResult::error("Missing required parameter: start".into()),
+        };
+        let end = match input.get("end").and_then(|v| v.as_str()) {
+            Some(s) => match self.parse_time(s) {
+                Ok(end) => end,
+                Err(e) => return ToolResult::error(e),
+            },
+            None => {
+                let minutes = input
+                    .get("duration_minutes")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(60);
+                start + Duration::minutes(minutes)
+            }
+        };
+        if end <= start {
+            return ToolResult::error("Event end must be after its start".into());
+        }
+        let text = |key: &str| {
+            input
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let event = NewEvent {
+            summary,
+            start,
+            end,
+            location: text("location"),
+            description: text("description"),
+        };
+        match self.client.create(&event).await {
+            Ok(created) => ToolResult::success(format!("Created: {}", self.format_event(&created))),
+            Err(e) => ToolResult::error(format!("Failed to create event: {e}")),
+        }
+    }
+
+    async fn remind(&self, input: &serde_json::Value) -> ToolResult {
+        let chat_id = match input.get("chat_id").and_then(|v| v.as_i64()) {
+            Some(id) => id,
+            None => return ToolResult::error("Missing required parameter: chat_id".into()),
+        };
+        if let Err(e) = authorize_chat_access(input, chat_id) {
+            return ToolResult::error(e);
+        }
+        if let Err(e) =
+            enforce_channel_policy(&self.registry, self.db.clone(), input, chat_id).await
+        {
+            return ToolResult::error(e);
+        }
+        let minutes = input
+            .get("minutes_before")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(DEFAULT_REMIND_MINUTES)
+            .max(0);
+        let event_id = input.get("event_id").and_then(|v| v.as_str());
+
+        let now = Utc::now();
+        let events = match self
+            .client
+            .upcoming(now, now + Duration::days(REMIND_LOOKAHEAD_DAYS), MAX_LIMIT)
+            .await
+        {
+            Ok(events) => events,
+            Err(e) => return ToolResult::error(format!("Failed to list events: {e}")),
+        };
+        let Some(event) = pick_event(&events, event_id, now) else {
+            return ToolResult::error(match event_id {
+                Some(id) => format!("No upcoming event with id {id}"),
+                None => format!("No upcoming events in the next {REMIND_LOOKAHEAD_DAYS} days"),
+            });
+        };
+        let remind_at = event.start - Duration::minutes(minutes);
+        if remind_at <= now {
+            return ToolResult::error(format!(
+                "Too late: {} starts at {}, less than {minutes} minutes from now",
+                event.summary,
+                event.start.with_timezone(&self.timezone).format("%H:%M")
+            ));
+        }
+        let prompt = input
+            .get("prompt")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| reminder_prompt(&self.format_event(event), minutes));
+
+        let next_run = remind_at.to_rfc3339();
+        let value = next_run.clone();
+        match call_blocking(self.db.clone(), move |db| {
+            db.create_scheduled_task(chat_id, &prompt, "once", &value, &value)
+        })
+        .await
+        {
+            Ok(id) => ToolResult::success(format!(
+                "Task #{id} scheduled: reminder for {} at {} ({minutes} minutes before).",
+                event.summary,
+                remind_at
+                    .with_timezone(&self.timezone)
+                    .format("%a %Y-%m-%d %H:%M %Z")
+            )),
+            Err(e) => ToolResult::error(format!("Failed to create task: {e}")),
+        }
+    }
+}
+
+/// The event `event_id` names, or the next timed event that has not started.
+fn pick_event<'a>(
+    events: &'a [CalendarEvent],
+    event_id: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<&'a CalendarEvent> {
+    match event_id {
+        Some(id) => events.iter().find(|e| e.id == id && e.start > now),
+        None => events.iter().find(|e| !e.all_day && e.start > now),
+    }
+}
+
+fn reminder_prompt(event: &str, minutes: i64) -> String {
+    format!("Remind the user that this calendar event starts in {minutes} minutes: {event}")
+}
+
+#[async_trait]
+impl Tool for CalendarTool {
+    fn name(&self) -> &str {
+        "calendar"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "calendar".into(),
+            description: format!(
+                "Read and add events in the user's calendar. Times without an offset are in {}. Actions: 'list' shows upcoming events; 'create' adds an event; 'remind' schedules a one-time reminder in this chat `minutes_before` the next meeting (or the event with `event_id`), e.g. for \"remind me 30 minutes before my next meeting\".",
+                self.timezone
+            ),
+            input_schema: schema_object(
+                json!({
+                    "action": {
+                        "type": "string",
+                        "enum": ["list", "create", "remind"]
+                    },
+                    "days": {
+                        "type": "integer",
+                        "description": format!("list: how many days ahead to look (default {DEFAULT_DAYS}, max {MAX_DAYS})")
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("list: maximum number of events (default {DEFAULT_LIMIT})")
+                    },
+                    "summary": {
+                        "type": "string",
+                        "description": "create: event title"
+                    },
+                    "start": {
+                        "type": "string",
+                        "description": "create: start time, ISO 8601 (e.g. 2026-10-16T14:00)"
+                    },
+                    "end": {
+                        "type": "string",
+                        "description": "create: end time; defaults to start + duration_minutes"
+                    },
+                    "duration_minutes": {
+                        "type": "integer",
+                        "description": "create: length when 'end' is omitted (default 60)"
+                    },
+                    "location": {
+                        "type": "string",
+                        "description": "create: where the event takes place"
+                    },
+                    "description": {
+                        "type": "string",
+                        "description": "create: notes for the event"
+                    },
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "remind: chat to send the reminder to (the current chat)"
+                    },
+                    "minutes_before": {
+                        "type": "integer",
+                        "description": format!("remind: minutes before the event start (default {DEFAULT_REMIND_MINUTES})")
+                    },
+                    "event_id": {
+                        "type": "string",
+                        "description": "remind: id from 'list'; default is the next timed event"
+                    },
+                    "prompt": {
+                        "type": "string",
+                        "description": "remind: custom prompt to run at reminder time"
+                    }
+                }),
+                &["action"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        if let Some(auth) = auth_context_from_input(&input) {
+            if !auth.is_control_chat() {
+                return ToolResult::error(format!(
+                    "Permission denied: chat {} cannot access the calendar",
+                    auth.caller_chat_id
+                ));
+            }
+        }
+        match input.get("action").and_then(|v| v.as_str()) {
+            Some("list") => self.list(&input).await,
+            Some("create") => self.create(&input).await,
+            Some("remind") => self.remind(&input).await,
+            Some(other) => ToolResult::error(format!("Unknown action: {other}")),
+            None => ToolResult::error("Missing required parameter: action".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: &str, start: DateTime<Utc>, all_day: bool) -> CalendarEvent {
+        CalendarEvent {
+            id: id.into(),
+            summary: format!("event {id}"),
+            start,
+            end: None,
+            all_day,
+            location: None,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_pick_event_skips_started_and_all_day() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap();
+        let events = vec![
+            event("started", now - Duration::minutes(10), false),
+            event("holiday", now + Duration::hours(1), true),
+            event("sync", now + Duration::hours(2), false),
+            event("later", now + Duration::hours(5), false),
+        ];
+        assert_eq!(pick_event(&events, None, now).unwrap().id, "sync");
+        assert_eq!(pick_event(&events, Some("later"), now).unwrap().id, "later");
+        assert!(pick_event(&events, Some("started"), now).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_parse_time_and_permissions() {
+        let root = std::env::temp_dir().join(format!("microclaw_cal_{}", uuid::Uuid::new_v4()));
+        let yaml = format!(
+            "api_key: key\ndata_dir: {}\ntimezone: Europe/Berlin\ncalendar:\n  provider: caldav\n  url: http://127.0.0.1:9/cal/\n",
+            root.display()
+        );
+        let config: crate::config::Config = serde_yaml::from_str(&yaml).unwrap();
+        let db = Arc::new(Database::new(root.to_str().unwrap()).unwrap());
+        let tool = CalendarTool::new(
+            CalendarClient::from_config(&config).unwrap(),
+            Arc::new(ChannelRegistry::new()),
+            db,
+            &config.timezone,
+        );
+
+        assert_eq!(
+            tool.parse_time("2026-10-16T14:00").unwrap().to_rfc3339(),
+            "2026-10-16T12:00:00+00:00"
+        );
+        assert_eq!(
+            tool.parse_time("2026-10-16T14:00:00Z")
+                .unwrap()
+                .to_rfc3339(),
+            "2026-10-16T14:00:00+00:00"
+        );
+        assert!(tool.parse_time("tomorrow").is_err());
+
+        let result = tool
+            .execute(json!({
+                "action": "list",
+                "__microclaw_auth": {"caller_channel": "telegram", "caller_chat_id": 5, "control_chat_ids": []}
+            }))
+            .await;
+        assert!(result.content.contains("Permission denied"));
+        let result = tool
+            .execute(json!({"action": "create", "summary": "x", "start": "2026-10-16T14:00", "duration_minutes": 0}))
+            .await;
+        assert!(result.content.contains("end must be after"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
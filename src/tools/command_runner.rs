@@ -45,6 +45,43 @@ pub fn build_command(spec: &CommandSpec, working_dir: Option<&Path>) -> tokio::p
     cmd
 }
 
+/// Put the command in its own process group (unix) so the whole tree it
+/// spawns can be killed together via [`ProcessGroupGuard`].
+pub fn isolate_process_group(cmd: &mut tokio::process::Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.kill_on_drop(true);
+}
+
+/// Kills the process group led by `pid` when dropped, unless disarmed.
+/// Covers timeouts and cancelled tool calls, where the awaiting future is
+/// dropped while the shell and its children are still running.
+pub struct ProcessGroupGuard {
+    pid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    pub fn new(pid: Option<u32>) -> Self {
+        Self { pid }
+    }
+
+    pub fn disarm(&mut self) {
+        self.pid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid.take() {
+            // SAFETY: killpg only sends a signal; a stale pgid fails with ESRCH.
+            unsafe {
+                libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,6 +93,22 @@ mod tests {
         assert!(!spec.args.is_empty());
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_group_guard_kills_group_on_drop() {
+        let spec = shell_command("sleep 30 & sleep 30; wait");
+        let mut cmd = build_command(&spec, None);
+        isolate_process_group(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let guard = ProcessGroupGuard::new(child.id());
+        drop(guard);
+        let status = tokio::time::timeout(std::time::Duration::from_secs(5), child.wait())
+            .await
+            .expect("process group was not killed")
+            .unwrap();
+        assert!(!status.success());
+    }
+
     #[test]
     fn test_agent_browser_program_not_empty() {
         let p = agent_browser_program();
@@ -0,0 +1,395 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+use tracing::{info, warn};
+use zip::write::SimpleFileOptions;
+
+use super::{auth_context_from_input, path_guard, schema_object, Tool, ToolResult};
+use crate::channel::get_required_chat_routing;
+use crate::channel_adapter::ChannelRegistry;
+use crate::config::WorkingDirIsolation;
+use crate::db::{call_blocking, Database, StoredMessage};
+use crate::llm_types::ToolDefinition;
+
+pub struct ExportWorkspaceTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+    data_dir: PathBuf,
+    bot_username: String,
+    max_bytes: u64,
+}
+
+impl ExportWorkspaceTool {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        registry: Arc<ChannelRegistry>,
+        db: Arc<Database>,
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+        data_dir: &str,
+        bot_username: String,
+        max_mb: u64,
+    ) -> Self {
+        ExportWorkspaceTool {
+            registry,
+            db,
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+            data_dir: PathBuf::from(data_dir),
+            bot_username,
+            max_bytes: max_mb.saturating_mul(1024 * 1024),
+        }
+    }
+
+    /// Send the archive to the caller's chat and record it in the history.
+    async fn send_archive(&self, chat_id: i64, archive: &Path) -> Result<(), String> {
+        let routing = get_required_chat_routing(&self.registry, self.db.clone(), chat_id).await?;
+        let adapter = self.registry.get(&routing.channel_name).ok_or_else(|| {
+            format!(
+                "No adapter registered for channel '{}'",
+                routing.channel_name
+            )
+        })?;
+        let external_chat_id =
+            call_blocking(self.db.clone(), move |db| db.get_chat_external_id(chat_id))
+                .await
+                .map_err(|e| format!("Failed to resolve external chat id: {e}"))?
+                .unwrap_or_else(|| chat_id.to_string());
+        let content = adapter
+            .send_attachment(&external_chat_id, archive, Some("Workspace export"))
+            .await?;
+        let msg = StoredMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            chat_id,
+            sender_name: self.bot_username.clone(),
+            content,
+            is_from_bot: true,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = call_blocking(self.db.clone(), move |db| db.store_message(&msg)).await {
+            warn!("export_workspace: failed to store sent message: {e}");
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Tool for ExportWorkspaceTool {
+    fn name(&self) -> &str {
+        "export_workspace"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "export_workspace".into(),
+            description: "Zip this chat's working directory (or a subdirectory of it) so the user can download everything produced there. Sends the archive to the chat as an attachment where the channel supports it, and always keeps a copy under the data directory's exports/. Sensitive files (.env, keys, credentials) and symlinks are left out.".into(),
+            input_schema: schema_object(
+                json!({
+                    "path": {
+                        "type": "string",
+                        "description": "Optional subdirectory of the working directory to export (default: all of it)"
+                    },
+                    "send": {
+                        "type": "boolean",
+                        "description": "Send the archive to this chat (default: true). false only stores it"
+                    }
+                }),
+                &[],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let workspace =
+            super::resolve_tool_working_dir(&self.working_dir, self.working_dir_isolation, &input);
+        let root = match input.get("path").and_then(|v| v.as_str()).map(str::trim) {
+            Some(sub) if !sub.is_empty() && sub != "." => {
+                let sub_path = Path::new(sub);
+                if sub_path.is_absolute()
+                    || sub_path
+                        .components()
+                        .any(|c| matches!(c, std::path::Component::ParentDir))
+                {
+                    return ToolResult::error(format!(
+                        "path must be a subdirectory of the working directory: {sub}"
+                    ));
+                }
+                workspace.join(sub_path)
+            }
+            _ => workspace.clone(),
+        };
+        if !root.is_dir() {
+            return ToolResult::error(format!("Not a directory: {}", root.display()));
+        }
+        // A symlinked subdirectory would otherwise export whatever it points at.
+        let root = match (workspace.canonicalize(), root.canonicalize()) {
+            (Ok(workspace), Ok(resolved)) if resolved.starts_with(&workspace) => resolved,
+            (Ok(_), Ok(_)) => {
+                return ToolResult::error(format!(
+                    "path must be a subdirectory of the working directory: {}",
+                    root.display()
+                ))
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                return ToolResult::error(format!("Failed to resolve {}: {e}", root.display()))
+            }
+        };
+
+        let auth = auth_context_from_input(&input);
+        let chat_label = auth
+            .as_ref()
+            .map(|a| a.caller_chat_id.to_string())
+            .unwrap_or_else(|| "shared".into());
+        let archive = self.data_dir.join("exports").join(format!(
+            "workspace_{chat_label}_{}.zip",
+            chrono::Utc::now().format("%Y%m%d_%H%M%S")
+        ));
+        let max_bytes = self.max_bytes;
+        let archive_for_zip = archive.clone();
+        let summary = match tokio::task::spawn_blocking(move || {
+            zip_directory(&root, &archive_for_zip, max_bytes)
+        })
+        .await
+        {
+            Ok(Ok(summary)) => summary,
+            Ok(Err(e)) => return ToolResult::error(e),
+            Err(e) => return ToolResult::error(format!("Export failed: {e}")),
+        };
+        info!(
+            "export_workspace: {} files ({} bytes) into {}",
+            summary.files,
+            summary.bytes,
+            archive.display()
+        );
+
+        let mut text = format!(
+            "Exported {} files ({}) to {}",
+            summary.files,
+            fmt_size(summary.bytes),
+            archive.display()
+        );
+        if summary.skipped > 0 {
+            text.push_str(&format!(
+                "\nSkipped {} sensitive or linked entries.",
+                summary.skipped
+            ));
+        }
+        let send = input.get("send").and_then(|v| v.as_bool()).unwrap_or(true);
+        if let Some(auth) = auth.filter(|_| send) {
+            match self.send_archive(auth.caller_chat_id, &archive).await {
+                Ok(()) => text.push_str("\nSent the archive to this chat."),
+                Err(e) => text.push_str(&format!(
+                    "\nCould not send it as an attachment ({e}); it is kept at the path above."
+                )),
+            }
+        }
+        ToolResult::success(text)
+    }
+}
+
+struct ZipSummary {
+    files: usize,
+    bytes: u64,
+    skipped: usize,
+}
+
+/// Files under `root` to archive, as (relative path, absolute path, size).
+/// Symlinks and `path_guard`-blocked paths are skipped and counted.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    out: &mut Vec<(String, PathBuf, u64)>,
+    skipped: &mut usize,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let meta = std::fs::symlink_metadata(&path)?;
+        if meta.file_type().is_symlink() || path_guard::is_blocked(&path) {
+            *skipped += 1;
+            continue;
+        }
+        if meta.is_dir() {
+            collect_files(root, &path, out, skipped)?;
+        } else if meta.is_file() {
+            let rel = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.push((rel, path, meta.len()));
+        }
+    }
+    Ok(())
+}
+
+fn zip_directory(root: &Path, archive: &Path, max_bytes: u64) -> Result<ZipSummary, String> {
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    collect_files(root, root, &mut files, &mut skipped)
+        .map_err(|e| format!("Failed to read {}: {e}", root.display()))?;
+    if files.is_empty() {
+        return Err(format!("Nothing to export in {}", root.display()));
+    }
+    files.sort();
+    let bytes: u64 = files.iter().map(|(_, _, size)| size).sum();
+    if max_bytes > 0 && bytes > max_bytes {
+        return Err(format!(
+            "The workspace holds {} of files, over the {} export limit (workspace_export_max_mb). Export a subdirectory with path instead.",
+            fmt_size(bytes),
+            fmt_size(max_bytes)
+        ));
+    }
+
+    if let Some(parent) = archive.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {e}"))?;
+    }
+    let write = || -> zip::result::ZipResult<()> {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(archive)?);
+        for (name, path, size) in &files {
+            let options = SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated)
+                .large_file(*size >= u64::from(u32::MAX));
+            zip.start_file(name.as_str(), options)?;
+            let mut file = std::fs::File::open(path)?;
+            std::io::copy(&mut file, &mut zip)?;
+        }
+        zip.finish()?.flush()?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(archive);
+        return Err(format!("Failed to write {}: {e}", archive.display()));
+    }
+    Ok(ZipSummary {
+        files: files.len(),
+        bytes,
+        skipped,
+    })
+}
+
+fn fmt_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_tool(root: &Path, max_mb: u64) -> ExportWorkspaceTool {
+        let db = Arc::new(Database::new(root.join("db").to_str().unwrap()).unwrap());
+        ExportWorkspaceTool::new(
+            Arc::new(ChannelRegistry::new()),
+            db,
+            root.join("work").to_str().unwrap(),
+            WorkingDirIsolation::Chat,
+            root.join("data").to_str().unwrap(),
+            "bot".into(),
+            max_mb,
+        )
+    }
+
+    fn input(extra: serde_json::Value) -> serde_json::Value {
+        let mut input = json!({
+            "__microclaw_auth": {"caller_channel": "web", "caller_chat_id": 7, "control_chat_ids": []},
+            "send": false
+        });
+        input
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        input
+    }
+
+    #[tokio::test]
+    async fn test_export_workspace_zips_files_and_skips_sensitive() {
+        let root =
+            std::env::temp_dir().join(format!("microclaw_wsexport_{}", uuid::Uuid::new_v4()));
+        let workspace = root.join("work").join("chat").join("web").join("7");
+        std::fs::create_dir_all(workspace.join("out")).unwrap();
+        std::fs::write(workspace.join("report.md"), "# Report").unwrap();
+        std::fs::write(workspace.join("out").join("data.csv"), "a,b\n1,2\n").unwrap();
+        std::fs::write(workspace.join(".env"), "SECRET=1").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/etc/hostname", workspace.join("link")).unwrap();
+
+        let tool = test_tool(&root, 50);
+        let result = tool.execute(input(json!({}))).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("Exported 2 files"));
+        assert!(result.content.contains("Skipped"));
+
+        let archive = std::fs::read_dir(root.join("data").join("exports"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert!(archive
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("workspace_7_"));
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        let names: Vec<String> = zip.file_names().map(|n| n.unwrap().to_string()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"report.md".to_string()));
+        assert!(names.contains(&"out/data.csv".to_string()));
+        let mut csv = String::new();
+        std::io::Read::read_to_string(&mut zip.by_name("out/data.csv").unwrap(), &mut csv).unwrap();
+        assert_eq!(csv, "a,b\n1,2\n");
+
+        let result = tool.execute(input(json!({"path": "out"}))).await;
+        assert!(result.content.contains("Exported 1 files"));
+        let result = tool.execute(input(json!({"path": "../other"}))).await;
+        assert!(result.is_error);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_workspace_rejects_symlinked_subdir() {
+        let root =
+            std::env::temp_dir().join(format!("microclaw_wsexport_{}", uuid::Uuid::new_v4()));
+        let workspace = root.join("work").join("chat").join("web").join("7");
+        let outside = root.join("outside");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "host data").unwrap();
+        std::os::unix::fs::symlink(&outside, workspace.join("escape")).unwrap();
+
+        let result = test_tool(&root, 50)
+            .execute(input(json!({"path": "escape"})))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("must be a subdirectory"));
+        assert!(!root.join("data").join("exports").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_export_workspace_enforces_size_limit() {
+        let root =
+            std::env::temp_dir().join(format!("microclaw_wsexport_{}", uuid::Uuid::new_v4()));
+        let workspace = root.join("work").join("chat").join("web").join("7");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(workspace.join("big.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+
+        let result = test_tool(&root, 1).execute(input(json!({}))).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("over the 1.0 MB export limit"));
+        assert!(!root.join("data").join("exports").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
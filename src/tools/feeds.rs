@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{authorized_chat_id, schema_object, Tool, ToolResult};
+use crate::channel_adapter::ChannelRegistry;
+use crate::config::NetworkPolicyConfig;
+use crate::db::{call_blocking, Database};
+use crate::feeds::{fetch_feed, take_new_items, FeedFilter};
+use crate::llm_types::ToolDefinition;
+
+// --- subscribe_feed ---
+
+pub struct SubscribeFeedTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+    network_policy: NetworkPolicyConfig,
+    poll_interval_mins: u64,
+}
+
+impl SubscribeFeedTool {
+    pub fn new(
+        registry: Arc<ChannelRegistry>,
+        db: Arc<Database>,
+        network_policy: NetworkPolicyConfig,
+        poll_interval_mins: u64,
+    ) -> Self {
+        SubscribeFeedTool {
+            registry,
+            db,
+            network_policy,
+            poll_interval_mins,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for SubscribeFeedTool {
+    fn name(&self) -> &str {
+        "subscribe_feed"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "subscribe_feed".into(),
+            description: format!(
+                "Subscribe a chat to an RSS or Atom feed. The feed is checked every {} minutes and new items are summarized and posted to the chat; items already in the feed when subscribing are skipped. Subscribing again to the same URL updates its filter.",
+                self.poll_interval_mins
+            ),
+            input_schema: schema_object(
+                json!({
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat ID to post new items to"
+                    },
+                    "url": {
+                        "type": "string",
+                        "description": "Feed URL (RSS or Atom)"
+                    },
+                    "filter": {
+                        "type": "string",
+                        "description": "Comma-separated keywords; only items mentioning one are posted. Prefix a keyword with '-' to drop items mentioning it, e.g. 'rust, tokio, -hiring'"
+                    }
+                }),
+                &["chat_id", "url"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        let url = match input.get("url").and_then(|v| v.as_str()) {
+            Some(u) if !u.trim().is_empty() => u.trim().to_string(),
+            _ => return ToolResult::error("Missing required parameter: url".into()),
+        };
+        let filter = input
+            .get("filter")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string);
+
+        let feed = match fetch_feed(&self.network_policy, Some(chat_id), &url).await {
+            Ok(feed) => feed,
+            Err(e) => return ToolResult::error(format!("Failed to read feed {url}: {e}")),
+        };
+        let title = feed.title.clone();
+        let url_for_db = url.clone();
+        let filter_for_db = filter.clone();
+        let id = match call_blocking(self.db.clone(), move |db| {
+            db.upsert_feed_subscription(
+                chat_id,
+                &url_for_db,
+                title.as_deref(),
+                filter_for_db.as_deref(),
+            )
+        })
+        .await
+        {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(format!("Failed to subscribe: {e}")),
+        };
+        // Only items published from now on are posted.
+        let current = feed.items.len();
+        if let Err(e) = take_new_items(self.db.clone(), id, feed.items).await {
+            return ToolResult::error(format!("Failed to subscribe: {e}"));
+        }
+
+        let mut message = format!(
+            "Feed #{id} subscribed: {} ({current} current items skipped).",
+            feed.title.as_deref().unwrap_or(&url)
+        );
+        if let Some(filter) = &filter {
+            if FeedFilter::parse(filter) != FeedFilter::default() {
+                message.push_str(&format!(" Filter: {filter}"));
+            }
+        }
+        ToolResult::success(message)
+    }
+}
+
+// --- list_feeds ---
+
+pub struct ListFeedsTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+}
+
+impl ListFeedsTool {
+    pub fn new(registry: Arc<ChannelRegistry>, db: Arc<Database>) -> Self {
+        ListFeedsTool { registry, db }
+    }
+}
+
+#[async_trait]
+impl Tool for ListFeedsTool {
+    fn name(&self) -> &str {
+        "list_feeds"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_feeds".into(),
+            description: "List the RSS/Atom feeds a chat is subscribed to, with their filters and last check.".into(),
+            input_schema: schema_object(
+                json!({
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat ID to list feeds for"
+                    }
+                }),
+                &["chat_id"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        match call_blocking(self.db.clone(), move |db| {
+            db.list_feed_subscriptions(Some(chat_id))
+        })
+        .await
+        {
+            Ok(feeds) if feeds.is_empty() => {
+                ToolResult::success("No feed subscriptions for this chat.".into())
+            }
+            Ok(feeds) => {
+                let mut output = String::new();
+                for feed in feeds {
+                    output.push_str(&format!(
+                        "#{} {} | {}",
+                        feed.id,
+                        feed.title.as_deref().unwrap_or("(untitled)"),
+                        feed.url
+                    ));
+                    if let Some(filter) = &feed.filter {
+                        output.push_str(&format!(" | filter: {filter}"));
+                    }
+                    output.push_str(&format!(
+                        " | last check: {}",
+                        feed.last_checked_at.as_deref().unwrap_or("never")
+                    ));
+                    if let Some(error) = &feed.last_error {
+                        output.push_str(&format!(" (error: {error})"));
+                    }
+                    output.push('\n');
+                }
+                ToolResult::success(output)
+            }
+            Err(e) => ToolResult::error(format!("Failed to list feeds: {e}")),
+        }
+    }
+}
+
+// --- unsubscribe_feed ---
+
+pub struct UnsubscribeFeedTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+}
+
+impl UnsubscribeFeedTool {
+    pub fn new(registry: Arc<ChannelRegistry>, db: Arc<Database>) -> Self {
+        UnsubscribeFeedTool { registry, db }
+    }
+}
+
+#[async_trait]
+impl Tool for UnsubscribeFeedTool {
+    fn name(&self) -> &str {
+        "unsubscribe_feed"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "unsubscribe_feed".into(),
+            description: "Stop posting a feed to a chat.".into(),
+            input_schema: schema_object(
+                json!({
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat ID the feed is posted to"
+                    },
+                    "feed_id": {
+                        "type": "integer",
+                        "description": "The feed ID from list_feeds"
+                    }
+                }),
+                &["chat_id", "feed_id"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        let feed_id = match input.get("feed_id").and_then(|v| v.as_i64()) {
+            Some(id) => id,
+            None => return ToolResult::error("Missing required parameter: feed_id".into()),
+        };
+        match call_blocking(self.db.clone(), move |db| {
+            db.delete_feed_subscription(chat_id, feed_id)
+        })
+        .await
+        {
+            Ok(true) => ToolResult::success(format!("Feed #{feed_id} unsubscribed.")),
+            Ok(false) => ToolResult::error(format!("Feed #{feed_id} not found in this chat.")),
+            Err(e) => ToolResult::error(format!("Failed to unsubscribe: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_and_unsubscribe() {
+        let dir = std::env::temp_dir().join(format!("microclaw_feedt_{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let registry = Arc::new(ChannelRegistry::new());
+        let id = db
+            .upsert_feed_subscription(7, "https://example.com/rss", Some("Example"), Some("rust"))
+            .unwrap();
+        let list = ListFeedsTool::new(registry.clone(), db.clone());
+        let unsubscribe = UnsubscribeFeedTool::new(registry.clone(), db.clone());
+
+        let result = list.execute(json!({"chat_id": 7})).await;
+        assert!(result.content.contains(&format!(
+            "#{id} Example | https://example.com/rss | filter: rust | last check: never"
+        )));
+
+        let denied = json!({
+            "chat_id": 7,
+            "feed_id": id,
+            "__microclaw_auth": {"caller_channel": "telegram", "caller_chat_id": 8, "control_chat_ids": []}
+        });
+        assert!(unsubscribe.execute(denied).await.is_error);
+        let result = unsubscribe
+            .execute(json!({"chat_id": 7, "feed_id": id}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        let result = list.execute(json!({"chat_id": 7})).await;
+        assert!(result.content.contains("No feed subscriptions"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::WorkingDirIsolation;
+use crate::knowledge::KnowledgeBase;
+use crate::llm_types::ToolDefinition;
+
+use super::{auth_context_from_input, schema_object, Tool, ToolResult};
+
+/// Longest `retrieve` answer the caller can ask for.
+const MAX_TOP_K: usize = 20;
+
+/// Keep only characters that are safe in a file name.
+fn safe_file_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+// ── Ingest ────────────────────────────────────────────────────────────────────
+
+pub struct IngestTool {
+    knowledge: Arc<KnowledgeBase>,
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+}
+
+impl IngestTool {
+    pub fn new(
+        knowledge: Arc<KnowledgeBase>,
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+    ) -> Self {
+        Self {
+            knowledge,
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for IngestTool {
+    fn name(&self) -> &str {
+        "ingest"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "ingest".into(),
+            description: "Add a document to the shared knowledge base so `retrieve` can find it later. Pass `path` for a text file (e.g. one the user uploaded), or `name` and `content` to save text directly. The document is copied into the knowledge folder and indexed right away; re-ingesting the same name replaces it.".into(),
+            input_schema: schema_object(
+                json!({
+                    "path": {
+                        "type": "string",
+                        "description": "Text document to add (markdown, txt, html, csv, code, ...)"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "File name to store it under, e.g. 'onboarding.md' (default: the file's own name)"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Text to store instead of a file; requires 'name'"
+                    }
+                }),
+                &[],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        if let Some(auth) = auth_context_from_input(&input) {
+            if !auth.is_control_chat() {
+                return ToolResult::error(format!(
+                    "Permission denied: chat {} cannot add to the shared knowledge base",
+                    auth.caller_chat_id
+                ));
+            }
+        }
+        let path = input.get("path").and_then(|v| v.as_str());
+        let content = input.get("content").and_then(|v| v.as_str());
+        let name = input
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(safe_file_name);
+
+        let (name, bytes) = match (path, content) {
+            (Some(path), None) => {
+                let working_dir = super::resolve_tool_working_dir(
+                    &self.working_dir,
+                    self.working_dir_isolation,
+                    &input,
+                );
+                let resolved = super::resolve_tool_path(&working_dir, path);
+                if let Err(msg) = crate::tools::path_guard::check_path(&resolved.to_string_lossy())
+                {
+                    return ToolResult::error(msg);
+                }
+                let bytes = match tokio::fs::read(&resolved).await {
+                    Ok(bytes) => bytes,
+                    Err(e) => return ToolResult::error(format!("Failed to read file: {e}")),
+                };
+                let own_name = resolved
+                    .file_name()
+                    .map(|n| safe_file_name(&n.to_string_lossy()))
+                    .unwrap_or_default();
+                (name.unwrap_or(own_name), bytes)
+            }
+            (None, Some(content)) => match name {
+                Some(name) => (name, content.as_bytes().to_vec()),
+                None => return ToolResult::error("'content' requires a 'name'".into()),
+            },
+            _ => return ToolResult::error("Pass either 'path' or 'content'".into()),
+        };
+        if name.is_empty() {
+            return ToolResult::error("Invalid document name".into());
+        }
+        let name = if name.contains('.') {
+            name
+        } else {
+            format!("{name}.md")
+        };
+
+        let target = self.knowledge.dir().join(&name);
+        if let Err(e) = tokio::fs::create_dir_all(self.knowledge.dir()).await {
+            return ToolResult::error(format!("Failed to create knowledge folder: {e}"));
+        }
+        if let Err(e) = tokio::fs::write(&target, &bytes).await {
+            return ToolResult::error(format!("Failed to save document: {e}"));
+        }
+        info!("Ingesting {} into the knowledge base", target.display());
+        match self.knowledge.index_file(&target).await {
+            Ok(Some(chunks)) => ToolResult::success(format!("Ingested {name} ({chunks} chunks).")),
+            Ok(None) => ToolResult::success(format!("{name} is already up to date.")),
+            Err(e) => {
+                // Don't leave a file the indexer would keep failing on.
+                let _ = tokio::fs::remove_file(&target).await;
+                ToolResult::error(format!("Failed to ingest {name}: {e}"))
+            }
+        }
+    }
+}
+
+// ── Retrieve ──────────────────────────────────────────────────────────────────
+
+pub struct RetrieveTool {
+    knowledge: Arc<KnowledgeBase>,
+}
+
+impl RetrieveTool {
+    pub fn new(knowledge: Arc<KnowledgeBase>) -> Self {
+        Self { knowledge }
+    }
+}
+
+#[async_trait]
+impl Tool for RetrieveTool {
+    fn name(&self) -> &str {
+        "retrieve"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "retrieve".into(),
+            description: "Search the knowledge base (documents in the knowledge folder or added with `ingest`) and return the most relevant passages with their source. Use it before answering questions about the user's own documents, manuals or notes.".into(),
+            input_schema: schema_object(
+                json!({
+                    "query": {
+                        "type": "string",
+                        "description": "What to look for, as a question or keywords"
+                    },
+                    "top_k": {
+                        "type": "integer",
+                        "description": format!("Number of passages to return (default {}, max {MAX_TOP_K})", self.knowledge.default_top_k())
+                    }
+                }),
+                &["query"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let query = match input.get("query").and_then(|v| v.as_str()) {
+            Some(q) if !q.trim().is_empty() => q.trim(),
+            _ => return ToolResult::error("Missing or empty 'query' parameter".into()),
+        };
+        let top_k = input
+            .get("top_k")
+            .and_then(|v| v.as_u64())
+            .map(|k| (k as usize).clamp(1, MAX_TOP_K))
+            .unwrap_or_else(|| self.knowledge.default_top_k());
+
+        match self.knowledge.search(query, top_k).await {
+            Ok(hits) if hits.is_empty() => {
+                ToolResult::success("No matching passages in the knowledge base.".into())
+            }
+            Ok(hits) => {
+                let passages: Vec<String> = hits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, hit)| {
+                        format!(
+                            "[{}] {} #{} (score {:.2})\n{}",
+                            i + 1,
+                            hit.source,
+                            hit.chunk_index + 1,
+                            hit.score,
+                            hit.content
+                        )
+                    })
+                    .collect();
+                ToolResult::success(passages.join("\n\n"))
+            }
+            Err(e) => ToolResult::error(format!("Retrieval failed: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn knowledge(root: &std::path::Path) -> Arc<KnowledgeBase> {
+        let yaml = format!(
+            "api_key: key\ndata_dir: {}\nknowledge:\n  enabled: true\n",
+            root.display()
+        );
+        let config: crate::config::Config = serde_yaml::from_str(&yaml).unwrap();
+        let db = Arc::new(Database::new(root.to_str().unwrap()).unwrap());
+        KnowledgeBase::from_config(&config, db).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ingest_then_retrieve() {
+        let root = std::env::temp_dir().join(format!("microclaw_kt_{}", uuid::Uuid::new_v4()));
+        let kb = knowledge(&root);
+        let ingest = IngestTool::new(
+            kb.clone(),
+            root.to_str().unwrap(),
+            WorkingDirIsolation::Shared,
+        );
+        let retrieve = RetrieveTool::new(kb.clone());
+
+        let result = ingest
+            .execute(json!({"name": "vpn guide", "content": "Connect to the VPN with wg-quick up office."}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(root.join("knowledge/vpn_guide.md").exists());
+
+        let result = retrieve
+            .execute(json!({"query": "how do I connect to the vpn"}))
+            .await;
+        assert!(result.content.contains("vpn_guide.md #1"));
+        assert!(result.content.contains("wg-quick up office"));
+
+        let result = ingest.execute(json!({"content": "no name"})).await;
+        assert!(result.is_error);
+        let result = ingest
+            .execute(json!({
+                "content": "x",
+                "name": "x.md",
+                "__microclaw_auth": {"caller_channel": "telegram", "caller_chat_id": 5, "control_chat_ids": []}
+            }))
+            .await;
+        assert!(result.content.contains("Permission denied"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -8,7 +8,10 @@ use crate::db::{call_blocking, Database};
 use crate::llm_types::ToolDefinition;
 use crate::memory_quality;
 
-use super::{auth_context_from_input, authorize_chat_access, schema_object, Tool, ToolResult};
+use super::{
+    auth_context_from_input, authorize_chat_access, personal_chat_id, schema_object, Tool,
+    ToolResult,
+};
 
 pub struct ReadMemoryTool {
     groups_dir: PathBuf,
@@ -65,6 +68,7 @@ impl Tool for ReadMemoryTool {
                 if let Err(e) = authorize_chat_access(&input, chat_id) {
                     return ToolResult::error(e);
                 }
+                let chat_id = personal_chat_id(&input, chat_id);
                 self.groups_dir.join(chat_id.to_string()).join("AGENTS.md")
             }
             _ => return ToolResult::error("scope must be 'global' or 'chat'".into()),
@@ -160,6 +164,7 @@ impl Tool for WriteMemoryTool {
                 if let Err(e) = authorize_chat_access(&input, chat_id) {
                     return ToolResult::error(e);
                 }
+                let chat_id = personal_chat_id(&input, chat_id);
                 (
                     self.groups_dir.join(chat_id.to_string()).join("AGENTS.md"),
                     Some(chat_id),
@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+use super::{auth_context_from_input, schema_object, Tool, ToolResult};
+use crate::config::{Config, OcrBackend};
+use crate::db::{call_blocking, Database};
+use crate::llm_types::{
+    ContentBlock, ImageSource, Message, MessageContent, ResponseContentBlock, ToolDefinition,
+};
+
+const OCR_PROMPT: &str = "Transcribe all text in this image exactly as written, keeping line breaks and reading order. Render tables as Markdown tables. Output only the transcription; if there is no text, output exactly: (no text found)";
+
+/// Extracts text from an image in the workspace with a vision model or
+/// tesseract (`ocr:` in the config), so screenshots can be used on models
+/// that cannot view images.
+pub struct OcrTool {
+    config: Config,
+    db: Arc<Database>,
+    working_dir: PathBuf,
+}
+
+impl OcrTool {
+    pub fn new(config: &Config, db: Arc<Database>) -> Self {
+        OcrTool {
+            config: config.clone(),
+            db,
+            working_dir: PathBuf::from(&config.working_dir),
+        }
+    }
+
+    async fn vision_ocr(
+        &self,
+        input: &serde_json::Value,
+        config: &Config,
+        bytes: &[u8],
+    ) -> ToolResult {
+        let Some((data, media_type)) = crate::channel::image_input(bytes) else {
+            return ToolResult::error(format!(
+                "Not a PNG, JPEG, GIF or WebP image up to {} MB",
+                crate::channel::MAX_IMAGE_INPUT_BYTES / (1024 * 1024)
+            ));
+        };
+        let llm = crate::llm::create_provider(config);
+        let messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::Blocks(vec![
+                ContentBlock::Image {
+                    source: ImageSource {
+                        source_type: "base64".into(),
+                        media_type,
+                        data,
+                    },
+                },
+                ContentBlock::Text {
+                    text: "Transcribe this image.".into(),
+                },
+            ]),
+        }];
+        let response = match llm.send_message(OCR_PROMPT, messages, None).await {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(format!("OCR model error: {e}")),
+        };
+        if let Some(usage) = &response.usage {
+            let auth = auth_context_from_input(input);
+            let chat_id = auth.as_ref().map(|a| a.caller_chat_id).unwrap_or(0);
+            let caller_channel = auth
+                .map(|a| a.caller_channel)
+                .unwrap_or_else(|| "ocr".to_string());
+            let (provider, model) = response.usage_source(&config.llm_provider, &config.model);
+            let input_tokens = i64::from(usage.input_tokens);
+            let output_tokens = i64::from(usage.output_tokens);
+            let _ = call_blocking(self.db.clone(), move |db| {
+                db.log_llm_usage(
+                    chat_id,
+                    &caller_channel,
+                    &provider,
+                    &model,
+                    input_tokens,
+                    output_tokens,
+                    "ocr",
+                )
+                .map(|_| ())
+            })
+            .await;
+        }
+        let text = response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ResponseContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        ToolResult::success(text.trim().to_string())
+    }
+
+    async fn tesseract_ocr(&self, path: &Path) -> ToolResult {
+        let output = tokio::process::Command::new("tesseract")
+            .arg(path)
+            .arg("stdout")
+            .arg("-l")
+            .arg(&self.config.ocr.languages)
+            .kill_on_drop(true)
+            .output()
+            .await;
+        match output {
+            Ok(out) if out.status.success() => {
+                let text = String::from_utf8_lossy(&out.stdout).trim().to_string();
+                ToolResult::success(if text.is_empty() {
+                    "(no text found)".into()
+                } else {
+                    text
+                })
+            }
+            Ok(out) => ToolResult::error(format!(
+                "tesseract failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ToolResult::error(
+                "tesseract is not installed; install it or set ocr.model to a vision model".into(),
+            ),
+            Err(e) => ToolResult::error(format!("Failed to run tesseract: {e}")),
+        }
+    }
+}
+
+/// Whether OCR asks `config.model` rather than tesseract.
+fn use_vision(config: &Config, backend: OcrBackend) -> bool {
+    match backend {
+        OcrBackend::Vision => true,
+        OcrBackend::Tesseract => false,
+        OcrBackend::Auto => {
+            crate::model_caps::lookup(&config.model, &config.model_capabilities).vision
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for OcrTool {
+    fn name(&self) -> &str {
+        "ocr"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "ocr".into(),
+            description: "Extract the text from an image file in the workspace (a screenshot, photo of a document, receipt, etc.), e.g. the saved_path of a photo the user sent. Use it when you cannot view the image yourself or need its exact text.".into(),
+            input_schema: schema_object(
+                json!({
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the image file (PNG, JPEG, GIF or WebP)"
+                    }
+                }),
+                &["path"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let path = match input.get("path").and_then(|v| v.as_str()) {
+            Some(p) => p,
+            None => return ToolResult::error("Missing 'path' parameter".into()),
+        };
+        let working_dir = super::resolve_tool_working_dir(
+            &self.working_dir,
+            self.config.working_dir_isolation,
+            &input,
+        );
+        let resolved_path = super::resolve_tool_path(&working_dir, path);
+        if let Err(msg) = super::path_guard::check_path(&resolved_path.to_string_lossy()) {
+            return ToolResult::error(msg);
+        }
+        let bytes = match tokio::fs::read(&resolved_path).await {
+            Ok(b) => b,
+            Err(e) => return ToolResult::error(format!("Failed to read image: {e}")),
+        };
+        if crate::channel::sniff_image_media_type(&bytes).is_none() {
+            return ToolResult::error("Not a PNG, JPEG, GIF or WebP image".into());
+        }
+
+        // OCR runs on the caller channel's model unless `ocr.model` is set.
+        let mut config = match auth_context_from_input(&input) {
+            Some(auth) => self.config.for_channel(&auth.caller_channel),
+            None => self.config.clone(),
+        };
+        if !self.config.ocr.model.is_empty() {
+            config.model = self.config.ocr.model.clone();
+        }
+        if use_vision(&config, self.config.ocr.backend) {
+            info!("OCR with {}: {}", config.model, resolved_path.display());
+            self.vision_ocr(&input, &config, &bytes).await
+        } else {
+            info!("OCR with tesseract: {}", resolved_path.display());
+            self.tesseract_ocr(&resolved_path).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OcrConfig;
+
+    fn test_config() -> Config {
+        let mut config: Config = serde_yaml::from_str("bot_username: bot\n").unwrap();
+        config.working_dir = std::env::temp_dir().to_string_lossy().to_string();
+        config
+    }
+
+    #[test]
+    fn test_use_vision_follows_backend_and_model_caps() {
+        let mut config = test_config();
+        config.model = "gpt-4o-mini".into();
+        assert!(use_vision(&config, OcrBackend::Auto));
+        assert!(!use_vision(&config, OcrBackend::Tesseract));
+        config.model = "gpt-4".into();
+        assert!(!use_vision(&config, OcrBackend::Auto));
+        assert!(use_vision(&config, OcrBackend::Vision));
+        assert_eq!(OcrConfig::default().languages, "eng");
+    }
+
+    #[tokio::test]
+    async fn test_ocr_rejects_non_images() {
+        let dir = std::env::temp_dir().join(format!("microclaw_ocr_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "plain text").unwrap();
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let tool = OcrTool::new(&test_config(), db);
+
+        let result = tool.execute(json!({"path": file.to_str().unwrap()})).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Not a PNG"), "{}", result.content);
+        let result = tool.execute(json!({})).await;
+        assert!(result.is_error);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
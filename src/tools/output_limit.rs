@@ -0,0 +1,152 @@
+//! Per-tool output limits (`tool_output_limits`). Output over a tool's limit
+//! is cut for the model, and the full text is saved under `.tool-output/` in
+//! the caller's workspace so it can still be read with `read_file`/`grep`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tracing::warn;
+
+use crate::config::{Config, WorkingDirIsolation};
+use crate::text::floor_char_boundary;
+
+use super::ToolResult;
+
+/// Built-in limits in bytes; `tool_output_limits` overrides them.
+pub const DEFAULT_TOOL_OUTPUT_LIMITS: [(&str, usize); 3] =
+    [("bash", 30_000), ("browser", 30_000), ("web_fetch", 20_000)];
+
+/// Workspace-relative directory for overflow files.
+pub const OVERFLOW_DIR: &str = ".tool-output";
+
+#[derive(Default)]
+pub struct OutputLimits {
+    limits: HashMap<String, usize>,
+    working_dir: PathBuf,
+    working_dir_isolation: Option<WorkingDirIsolation>,
+}
+
+impl OutputLimits {
+    pub fn from_config(config: &Config) -> Self {
+        let mut limits: HashMap<String, usize> = DEFAULT_TOOL_OUTPUT_LIMITS
+            .into_iter()
+            .map(|(tool, limit)| (tool.to_string(), limit))
+            .collect();
+        limits.extend(config.tool_output_limits.clone());
+        Self {
+            limits,
+            working_dir: PathBuf::from(&config.working_dir),
+            working_dir_isolation: Some(config.working_dir_isolation),
+        }
+    }
+
+    /// Limit for `tool` in bytes, if any (`0` in config means unlimited).
+    pub fn limit_for(&self, tool: &str) -> Option<usize> {
+        self.limits.get(tool).copied().filter(|limit| *limit > 0)
+    }
+
+    /// Cut `result` to the tool's limit, saving the full output to a file.
+    pub fn apply(&self, tool: &str, input: &serde_json::Value, result: &mut ToolResult) {
+        let Some(limit) = self.limit_for(tool) else {
+            return;
+        };
+        if result.content.len() <= limit {
+            return;
+        }
+        let total = result.content.len();
+        let saved = self.save_overflow(tool, input, &result.content);
+        result
+            .content
+            .truncate(floor_char_boundary(&result.content, limit));
+        match saved {
+            Some(path) => result.content.push_str(&format!(
+                "\n... (output truncated at {limit} of {total} bytes; full output saved to {path} in the working directory)"
+            )),
+            None => result.content.push_str("\n... (output truncated)"),
+        }
+    }
+
+    fn save_overflow(
+        &self,
+        tool: &str,
+        input: &serde_json::Value,
+        content: &str,
+    ) -> Option<String> {
+        let isolation = self.working_dir_isolation?;
+        let workspace = super::resolve_tool_working_dir(&self.working_dir, isolation, input);
+        let file_name = format!(
+            "{tool}-{}-{}.txt",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+        let dir = workspace.join(OVERFLOW_DIR);
+        let written = std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(dir.join(&file_name), content));
+        if let Err(e) = written {
+            warn!("Failed to save {tool} output to {}: {e}", dir.display());
+            return None;
+        }
+        Some(format!("{OVERFLOW_DIR}/{file_name}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limits_in(dir: &std::path::Path, limits: &[(&str, usize)]) -> OutputLimits {
+        OutputLimits {
+            limits: limits.iter().map(|(t, l)| (t.to_string(), *l)).collect(),
+            working_dir: dir.to_path_buf(),
+            working_dir_isolation: Some(WorkingDirIsolation::Shared),
+        }
+    }
+
+    #[test]
+    fn test_overflow_saved_to_workspace_file() {
+        let dir = std::env::temp_dir().join(format!("microclaw_outlimit_{}", uuid::Uuid::new_v4()));
+        let limits = limits_in(&dir, &[("bash", 10)]);
+        let output = "é".repeat(20);
+        let mut result = ToolResult::success(output.clone());
+        limits.apply("bash", &json!({}), &mut result);
+
+        let (head, note) = result.content.split_once("\n... ").unwrap();
+        assert_eq!(head, "é".repeat(5));
+        assert!(note.contains("truncated at 10 of 40 bytes"));
+        let rel = note
+            .split("saved to ")
+            .nth(1)
+            .and_then(|s| s.split(' ').next())
+            .unwrap();
+        assert!(rel.starts_with(".tool-output/bash-"));
+        let saved = std::fs::read_to_string(dir.join("shared").join(rel)).unwrap();
+        assert_eq!(saved, output);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_limits_only_apply_to_configured_tools() {
+        let dir = std::env::temp_dir().join(format!("microclaw_outlimit_{}", uuid::Uuid::new_v4()));
+        let limits = limits_in(&dir, &[("bash", 0), ("browser", 5)]);
+        assert_eq!(limits.limit_for("bash"), None);
+        assert_eq!(limits.limit_for("read_file"), None);
+        let mut result = ToolResult::error("long error output".into());
+        limits.apply("bash", &json!({}), &mut result);
+        assert_eq!(result.content, "long error output");
+        limits.apply("browser", &json!({}), &mut result);
+        assert!(result
+            .content
+            .starts_with("long \n... (output truncated at 5"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_default_limits_without_workspace_just_truncate() {
+        let mut limits = OutputLimits::default();
+        limits.limits.insert("bash".into(), 3);
+        let mut result = ToolResult::success("abcdef".into());
+        limits.apply("bash", &json!({}), &mut result);
+        assert_eq!(result.content, "abc\n... (output truncated)");
+    }
+}
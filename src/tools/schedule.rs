@@ -2,13 +2,19 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use base64::Engine;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde_json::json;
 
 use super::{authorize_chat_access, schema_object, Tool, ToolResult};
 use crate::channel::enforce_channel_policy;
 use crate::channel_adapter::ChannelRegistry;
-use crate::db::{call_blocking, Database};
+use crate::channels::webhook::TASK_WEBHOOK_TRIGGER;
+use crate::db::{call_blocking, Database, ScheduledTask};
 use crate::llm_types::ToolDefinition;
+use crate::workspace_report::{
+    WORKSPACE_REPORT_DEFAULT_CRON, WORKSPACE_REPORT_PROMPT, WORKSPACE_REPORT_TEMPLATE,
+};
 
 fn compute_next_run(cron_expr: &str, tz_name: &str) -> Result<String, String> {
     let tz: chrono_tz::Tz = tz_name
@@ -30,6 +36,8 @@ pub struct ScheduleTaskTool {
     registry: Arc<ChannelRegistry>,
     db: Arc<Database>,
     default_timezone: String,
+    /// Web server base URL for `trigger: webhook` tasks; `None` disables them.
+    webhook_base_url: Option<String>,
 }
 
 impl ScheduleTaskTool {
@@ -42,6 +50,39 @@ impl ScheduleTaskTool {
             registry,
             db,
             default_timezone,
+            webhook_base_url: None,
+        }
+    }
+
+    pub fn with_webhook_base_url(mut self, base_url: Option<String>) -> Self {
+        self.webhook_base_url = base_url;
+        self
+    }
+
+    async fn create_webhook_task(&self, chat_id: i64, prompt: &str) -> ToolResult {
+        let Some(base_url) = self.webhook_base_url.as_deref() else {
+            return ToolResult::error(
+                "Webhook triggers need the web server; set web_enabled: true".into(),
+            );
+        };
+        let mut secret = [0u8; 24];
+        if SystemRandom::new().fill(&mut secret).is_err() {
+            return ToolResult::error("No secure randomness available".into());
+        }
+        let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret);
+        let prompt_owned = prompt.to_string();
+        let token_owned = token.clone();
+        match call_blocking(self.db.clone(), move |db| {
+            db.create_scheduled_task(chat_id, &prompt_owned, TASK_WEBHOOK_TRIGGER, &token_owned, "")
+        })
+        .await
+        {
+            Ok(id) => ToolResult::success(format!(
+                "Task #{id} created. It runs whenever this URL receives a POST (the body, if any, is passed along with the prompt):\n\
+                 {base_url}/webhook/task/{id}?token={token}\n\
+                 Keep the URL secret. Services that sign their requests (GitHub) can use the token as their webhook secret instead of ?token=."
+            )),
+            Err(e) => ToolResult::error(format!("Failed to create task: {e}")),
         }
     }
 }
@@ -55,7 +96,7 @@ impl Tool for ScheduleTaskTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "schedule_task".into(),
-            description: "Schedule a recurring or one-time task. For recurring tasks, provide a 6-field cron expression (sec min hour dom month dow). For one-time tasks, provide an ISO 8601 timestamp. The bot will execute the prompt at the scheduled time and send the result to this chat.".into(),
+            description: "Schedule a recurring or one-time task. For recurring tasks, provide a 6-field cron expression (sec min hour dom month dow). For one-time tasks, provide an ISO 8601 timestamp. The bot will execute the prompt at the scheduled time and send the result to this chat. Set trigger='webhook' instead of a schedule to get a secret URL that runs the prompt whenever something posts to it (e.g. on deploy, summarize the changelog). Set template='workspace_report' to schedule the built-in workspace report (disk usage, changed files, stale todos, memory growth) instead of a prompt; it defaults to weekly and runs without LLM calls.".into(),
             input_schema: schema_object(
                 json!({
                     "chat_id": {
@@ -66,6 +107,11 @@ impl Tool for ScheduleTaskTool {
                         "type": "string",
                         "description": "The prompt/instruction to execute at the scheduled time"
                     },
+                    "trigger": {
+                        "type": "string",
+                        "enum": ["time", "webhook"],
+                        "description": "What runs the task: 'time' (default) follows schedule_type/schedule_value; 'webhook' runs it when its URL receives a POST, and needs no schedule"
+                    },
                     "schedule_type": {
                         "type": "string",
                         "enum": ["cron", "once"],
@@ -78,9 +124,14 @@ impl Tool for ScheduleTaskTool {
                     "timezone": {
                         "type": "string",
                         "description": "Optional IANA timezone name (e.g. 'US/Eastern', 'Europe/London'). Defaults to server timezone setting."
+                    },
+                    "template": {
+                        "type": "string",
+                        "enum": [WORKSPACE_REPORT_TEMPLATE],
+                        "description": "Optional built-in task template. When set, prompt is not needed and the schedule defaults to weekly (Mondays 09:00)."
                     }
                 }),
-                &["chat_id", "prompt", "schedule_type", "schedule_value"],
+                &["chat_id"],
             ),
         }
     }
@@ -98,15 +149,46 @@ impl Tool for ScheduleTaskTool {
         {
             return ToolResult::error(e);
         }
-        let prompt = match input.get("prompt").and_then(|v| v.as_str()) {
+        let template = input.get("template").and_then(|v| v.as_str());
+        let (prompt, default_type, default_value) = match template {
+            Some(WORKSPACE_REPORT_TEMPLATE) => (
+                Some(WORKSPACE_REPORT_PROMPT),
+                Some("cron"),
+                Some(WORKSPACE_REPORT_DEFAULT_CRON),
+            ),
+            Some(other) => return ToolResult::error(format!("Unknown template: {other}")),
+            None => (input.get("prompt").and_then(|v| v.as_str()), None, None),
+        };
+        let prompt = match prompt {
             Some(p) => p,
             None => return ToolResult::error("Missing required parameter: prompt".into()),
         };
-        let schedule_type = match input.get("schedule_type").and_then(|v| v.as_str()) {
+        match input
+            .get("trigger")
+            .and_then(|v| v.as_str())
+            .unwrap_or("time")
+        {
+            "time" => {}
+            "webhook" => return self.create_webhook_task(chat_id, prompt).await,
+            other => {
+                return ToolResult::error(format!(
+                    "trigger must be 'time' or 'webhook', not '{other}'"
+                ))
+            }
+        }
+        let schedule_type = match input
+            .get("schedule_type")
+            .and_then(|v| v.as_str())
+            .or(default_type)
+        {
             Some(t) => t,
             None => return ToolResult::error("Missing required parameter: schedule_type".into()),
         };
-        let schedule_value = match input.get("schedule_value").and_then(|v| v.as_str()) {
+        let schedule_value = match input
+            .get("schedule_value")
+            .and_then(|v| v.as_str())
+            .or(default_value)
+        {
             Some(v) => v,
             None => return ToolResult::error("Missing required parameter: schedule_value".into()),
         };
@@ -159,6 +241,25 @@ impl Tool for ScheduleTaskTool {
 
 // --- list_tasks ---
 
+/// One line per task: id, status, prompt, schedule and next run.
+pub fn format_task_list(tasks: &[ScheduledTask]) -> String {
+    let mut output = String::new();
+    for t in tasks {
+        if t.schedule_type == TASK_WEBHOOK_TRIGGER {
+            output.push_str(&format!(
+                "#{} [{}] {} | on POST to /webhook/task/{}\n",
+                t.id, t.status, t.prompt, t.id
+            ));
+            continue;
+        }
+        output.push_str(&format!(
+            "#{} [{}] {} | {} '{}' | next: {}\n",
+            t.id, t.status, t.prompt, t.schedule_type, t.schedule_value, t.next_run
+        ));
+    }
+    output
+}
+
 pub struct ListTasksTool {
     registry: Arc<ChannelRegistry>,
     db: Arc<Database>,
@@ -211,14 +312,7 @@ impl Tool for ListTasksTool {
                 if tasks.is_empty() {
                     return ToolResult::success("No scheduled tasks found for this chat.".into());
                 }
-                let mut output = String::new();
-                for t in &tasks {
-                    output.push_str(&format!(
-                        "#{} [{}] {} | {} '{}' | next: {}\n",
-                        t.id, t.status, t.prompt, t.schedule_type, t.schedule_value, t.next_run
-                    ));
-                }
-                ToolResult::success(output)
+                ToolResult::success(format_task_list(&tasks))
             }
             Err(e) => ToolResult::error(format!("Failed to list tasks: {e}")),
         }
@@ -448,7 +542,7 @@ impl Tool for GetTaskHistoryTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "get_task_history".into(),
-            description: "Get the execution history/run logs for a scheduled task.".into(),
+            description: "Get the execution history/run logs for a scheduled task, with run counts, failure streak and durations.".into(),
             input_schema: schema_object(
                 json!({
                     "task_id": {
@@ -487,18 +581,29 @@ impl Tool for GetTaskHistoryTool {
         let limit = input.get("limit").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
 
         match call_blocking(self.db.clone(), move |db| {
-            db.get_task_run_logs(task_id, limit)
+            Ok((
+                db.get_task_run_logs(task_id, limit)?,
+                db.get_task_run_stats(task_id)?,
+            ))
         })
         .await
         {
-            Ok(logs) => {
+            Ok((logs, stats)) => {
                 if logs.is_empty() {
                     return ToolResult::success(format!(
                         "No run history found for task #{task_id}."
                     ));
                 }
-                let mut output =
-                    format!("Run history for task #{task_id} (most recent first):\n\n");
+                let mut output = format!(
+                    "Task #{task_id}: {} runs, {} failed ({} in a row), avg {}ms, max {}ms, last success: {}\n\n",
+                    stats.runs,
+                    stats.failures,
+                    stats.consecutive_failures,
+                    stats.avg_duration_ms,
+                    stats.max_duration_ms,
+                    stats.last_success_at.as_deref().unwrap_or("never"),
+                );
+                output.push_str("Run history (most recent first):\n\n");
                 for log in &logs {
                     let status = if log.success { "OK" } else { "FAIL" };
                     output.push_str(&format!(
@@ -648,6 +753,58 @@ mod tests {
         cleanup(&dir);
     }
 
+    #[tokio::test]
+    async fn test_schedule_task_workspace_report_template() {
+        let (db, dir) = test_db();
+        let tool = ScheduleTaskTool::new(test_registry(), db.clone(), "UTC".into());
+        let result = tool
+            .execute(json!({
+                "chat_id": 100,
+                "template": "workspace_report"
+            }))
+            .await;
+        assert!(!result.is_error, "Error: {}", result.content);
+        let tasks = db.get_tasks_for_chat(100).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].prompt, WORKSPACE_REPORT_PROMPT);
+        assert_eq!(tasks[0].schedule_type, "cron");
+        assert_eq!(tasks[0].schedule_value, WORKSPACE_REPORT_DEFAULT_CRON);
+
+        let result = tool
+            .execute(json!({"chat_id": 100, "template": "nope"}))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Unknown template"));
+        cleanup(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_task_webhook_trigger() {
+        let (db, dir) = test_db();
+        let tool = ScheduleTaskTool::new(test_registry(), db.clone(), "UTC".into());
+        let input = json!({"chat_id": 100, "prompt": "summarize the deploy", "trigger": "webhook"});
+        let result = tool.execute(input.clone()).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("web_enabled"));
+
+        let tool = tool.with_webhook_base_url(Some("https://bot.example.com".into()));
+        let result = tool.execute(input).await;
+        assert!(!result.is_error, "Error: {}", result.content);
+        assert!(result
+            .content
+            .contains("https://bot.example.com/webhook/task/1?token="));
+        let task = db.get_task_by_id(1).unwrap().unwrap();
+        assert_eq!(task.schedule_type, "webhook");
+        assert!(result.content.contains(&task.schedule_value));
+
+        let list = ListTasksTool::new(test_registry(), db)
+            .execute(json!({"chat_id": 100}))
+            .await;
+        assert!(list.content.contains("on POST to /webhook/task/1"));
+        assert!(!list.content.contains(&task.schedule_value));
+        cleanup(&dir);
+    }
+
     #[tokio::test]
     async fn test_list_tasks_empty() {
         let (db, dir) = test_db();
@@ -795,6 +952,12 @@ mod tests {
         assert!(result.content.contains("FAIL"));
         assert!(result.content.contains("All good"));
         assert!(result.content.contains("Error: timeout"));
+        assert!(result
+            .content
+            .contains("2 runs, 1 failed (1 in a row), avg 3500ms, max 5000ms"));
+        assert!(result
+            .content
+            .contains("last success: 2024-01-01T00:00:00Z"));
         cleanup(&dir);
     }
 
@@ -0,0 +1,425 @@
+//! `scratchpad`: named pads of text that several chats can read and write,
+//! e.g. a planning group keeping a task list that an execution group works
+//! through. A pad belongs to the chat that first writes it; control chats can
+//! share it with other chats and see every pad.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{auth_context_from_input, authorized_chat_id, schema_object, Tool, ToolResult};
+use crate::channel_adapter::ChannelRegistry;
+use crate::db::{call_blocking, Database, Scratchpad};
+use crate::llm_types::ToolDefinition;
+
+/// Largest pad content in bytes.
+const MAX_SCRATCHPAD_BYTES: usize = 64 * 1024;
+const MAX_NAME_LEN: usize = 64;
+
+pub struct ScratchpadTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+}
+
+impl ScratchpadTool {
+    pub fn new(registry: Arc<ChannelRegistry>, db: Arc<Database>) -> Self {
+        ScratchpadTool { registry, db }
+    }
+
+    async fn load(&self, name: &str) -> Result<Option<Scratchpad>, String> {
+        let name = name.to_string();
+        call_blocking(self.db.clone(), move |db| db.get_scratchpad(&name))
+            .await
+            .map_err(|e| format!("Failed to read scratchpad: {e}"))
+    }
+
+    /// The existing pad `name`, if `chat_id` may use it.
+    async fn accessible(
+        &self,
+        name: &str,
+        chat_id: i64,
+        control: bool,
+    ) -> Result<Option<Scratchpad>, String> {
+        match self.load(name).await? {
+            Some(pad) if !control && !can_use(&pad, chat_id) => Err(format!(
+                "Permission denied: scratchpad '{name}' is not shared with chat {chat_id}"
+            )),
+            pad => Ok(pad),
+        }
+    }
+
+    async fn list(&self, chat_id: i64, control: bool) -> ToolResult {
+        let scope = (!control).then_some(chat_id);
+        match call_blocking(self.db.clone(), move |db| db.list_scratchpads(scope)).await {
+            Ok(pads) if pads.is_empty() => ToolResult::success("No scratchpads.".into()),
+            Ok(pads) => {
+                let mut output = String::new();
+                for pad in pads {
+                    output.push_str(&format!("{} | {}\n", pad.name, describe(&pad)));
+                }
+                ToolResult::success(output)
+            }
+            Err(e) => ToolResult::error(format!("Failed to list scratchpads: {e}")),
+        }
+    }
+
+    async fn read(&self, name: &str, chat_id: i64, control: bool) -> ToolResult {
+        match self.accessible(name, chat_id, control).await {
+            Ok(Some(pad)) => ToolResult::success(format!(
+                "Scratchpad '{name}' ({}):\n\n{}",
+                describe(&pad),
+                pad.content
+            )),
+            Ok(None) => ToolResult::error(format!("Scratchpad '{name}' not found.")),
+            Err(e) => ToolResult::error(e),
+        }
+    }
+
+    async fn write(
+        &self,
+        name: &str,
+        chat_id: i64,
+        control: bool,
+        content: String,
+        append: bool,
+    ) -> ToolResult {
+        let existing = match self.accessible(name, chat_id, control).await {
+            Ok(pad) => pad,
+            Err(e) => return ToolResult::error(e),
+        };
+        let size = match (&existing, append) {
+            (Some(pad), true) => pad.content.len() + content.len(),
+            _ => content.len(),
+        };
+        if size > MAX_SCRATCHPAD_BYTES {
+            return ToolResult::error(format!(
+                "Scratchpad '{name}' would be {size} bytes; the limit is {MAX_SCRATCHPAD_BYTES}."
+            ));
+        }
+        let name_for_db = name.to_string();
+        match call_blocking(self.db.clone(), move |db| {
+            db.write_scratchpad(&name_for_db, chat_id, &content, append)
+        })
+        .await
+        {
+            Ok(pad) if existing.is_none() => ToolResult::success(format!(
+                "Scratchpad '{name}' created ({} bytes).",
+                pad.content.len()
+            )),
+            Ok(pad) => ToolResult::success(format!(
+                "Scratchpad '{name}' updated to v{} ({} bytes).",
+                pad.version,
+                pad.content.len()
+            )),
+            Err(e) => ToolResult::error(format!("Failed to write scratchpad: {e}")),
+        }
+    }
+
+    async fn share(&self, name: &str, input: &serde_json::Value, share: bool) -> ToolResult {
+        match auth_context_from_input(input) {
+            Some(auth) if auth.is_control_chat() => {}
+            Some(auth) => {
+                return ToolResult::error(format!(
+                    "Permission denied: only control chats can change who a scratchpad is shared with (chat {} is not one)",
+                    auth.caller_chat_id
+                ));
+            }
+            None => {
+                return ToolResult::error(
+                    "Permission denied: sharing a scratchpad needs a caller context".into(),
+                );
+            }
+        }
+        let Some(target) = input.get("target_chat_id").and_then(|v| v.as_i64()) else {
+            return ToolResult::error("Missing required parameter: target_chat_id".into());
+        };
+        let name_for_db = name.to_string();
+        let result = call_blocking(self.db.clone(), move |db| {
+            if share {
+                db.share_scratchpad(&name_for_db, target)
+            } else {
+                db.unshare_scratchpad(&name_for_db, target)
+            }
+        })
+        .await;
+        match (result, share) {
+            (Ok(true), true) => {
+                ToolResult::success(format!("Scratchpad '{name}' shared with chat {target}."))
+            }
+            (Ok(true), false) => ToolResult::success(format!(
+                "Scratchpad '{name}' is no longer shared with chat {target}."
+            )),
+            (Ok(false), true) => ToolResult::error(format!("Scratchpad '{name}' not found.")),
+            (Ok(false), false) => ToolResult::error(format!(
+                "Scratchpad '{name}' is not shared with chat {target}."
+            )),
+            (Err(e), _) => ToolResult::error(format!("Failed to update sharing: {e}")),
+        }
+    }
+
+    async fn delete(&self, name: &str, chat_id: i64, control: bool) -> ToolResult {
+        match self.load(name).await {
+            Ok(Some(pad)) if !control && pad.owner_chat_id != chat_id => {
+                return ToolResult::error(format!(
+                    "Permission denied: only chat {} or a control chat can delete scratchpad '{name}'",
+                    pad.owner_chat_id
+                ))
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => return ToolResult::error(format!("Scratchpad '{name}' not found.")),
+            Err(e) => return ToolResult::error(e),
+        }
+        let name_for_db = name.to_string();
+        match call_blocking(self.db.clone(), move |db| {
+            db.delete_scratchpad(&name_for_db)
+        })
+        .await
+        {
+            Ok(_) => ToolResult::success(format!("Scratchpad '{name}' deleted.")),
+            Err(e) => ToolResult::error(format!("Failed to delete scratchpad: {e}")),
+        }
+    }
+}
+
+fn can_use(pad: &Scratchpad, chat_id: i64) -> bool {
+    pad.owner_chat_id == chat_id || pad.shared_with.contains(&chat_id)
+}
+
+fn describe(pad: &Scratchpad) -> String {
+    let mut text = format!(
+        "v{}, {} bytes, owner chat {}, last written by chat {} at {}",
+        pad.version,
+        pad.content.len(),
+        pad.owner_chat_id,
+        pad.updated_by_chat_id,
+        pad.updated_at
+    );
+    if !pad.shared_with.is_empty() {
+        let chats: Vec<String> = pad.shared_with.iter().map(i64::to_string).collect();
+        text.push_str(&format!(", shared with {}", chats.join(", ")));
+    }
+    text
+}
+
+fn normalize_name(name: &str) -> Result<String, String> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty()
+        || name.len() > MAX_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Invalid scratchpad name {name:?}: use up to {MAX_NAME_LEN} letters, digits, '-', '_' or '.'"
+        ));
+    }
+    Ok(name)
+}
+
+#[async_trait]
+impl Tool for ScratchpadTool {
+    fn name(&self) -> &str {
+        "scratchpad"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "scratchpad".into(),
+            description: "Named text pads shared between chats, for handing structured state (plans, task lists, status) from one chat to another. A pad belongs to the chat that first writes it and can be used by chats it is shared with; only control chats can share pads and see all of them. Actions: list, read, write (replace or create), append, share, unshare, delete.".into(),
+            input_schema: schema_object(
+                json!({
+                    "action": {
+                        "type": "string",
+                        "enum": ["list", "read", "write", "append", "share", "unshare", "delete"]
+                    },
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat acting on the pad (normally the current chat)"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Pad name, e.g. 'release-plan' (every action except list)"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": format!("write/append: text to store (a pad holds up to {MAX_SCRATCHPAD_BYTES} bytes)")
+                    },
+                    "target_chat_id": {
+                        "type": "integer",
+                        "description": "share/unshare: the chat to grant or revoke access"
+                    }
+                }),
+                &["action", "chat_id"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        let control = auth_context_from_input(&input)
+            .map(|auth| auth.is_control_chat())
+            .unwrap_or(true);
+        let action = match input.get("action").and_then(|v| v.as_str()) {
+            Some(action) => action,
+            None => return ToolResult::error("Missing required parameter: action".into()),
+        };
+        if action == "list" {
+            return self.list(chat_id, control).await;
+        }
+        let name = match input.get("name").and_then(|v| v.as_str()) {
+            Some(name) => match normalize_name(name) {
+                Ok(name) => name,
+                Err(e) => return ToolResult::error(e),
+            },
+            None => return ToolResult::error("Missing required parameter: name".into()),
+        };
+        match action {
+            "read" => self.read(&name, chat_id, control).await,
+            "write" | "append" => {
+                let Some(content) = input.get("content").and_then(|v| v.as_str()) else {
+                    return ToolResult::error("Missing required parameter: content".into());
+                };
+                self.write(
+                    &name,
+                    chat_id,
+                    control,
+                    content.to_string(),
+                    action == "append",
+                )
+                .await
+            }
+            "share" => self.share(&name, &input, true).await,
+            "unshare" => self.share(&name, &input, false).await,
+            "delete" => self.delete(&name, chat_id, control).await,
+            other => ToolResult::error(format!("Unknown action: {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn as_chat(chat_id: i64, mut input: serde_json::Value) -> serde_json::Value {
+        input["chat_id"] = json!(chat_id);
+        input["__microclaw_auth"] = json!({
+            "caller_channel": "telegram",
+            "caller_chat_id": chat_id,
+            "control_chat_ids": [1]
+        });
+        input
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_sharing() {
+        let dir = std::env::temp_dir().join(format!("microclaw_padt_{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let tool = ScratchpadTool::new(Arc::new(ChannelRegistry::new()), db.clone());
+
+        let result = tool
+            .execute(as_chat(
+                1,
+                json!({"action": "write", "name": "Plan", "content": "- build\n"}),
+            ))
+            .await;
+        assert!(
+            result.content.contains("'plan' created"),
+            "{}",
+            result.content
+        );
+
+        // Chat 2 can neither use nor share the pad until a control chat shares it.
+        let read = as_chat(2, json!({"action": "read", "name": "plan"}));
+        assert!(tool
+            .execute(read.clone())
+            .await
+            .content
+            .contains("Permission denied"));
+        let share_self = as_chat(
+            2,
+            json!({"action": "share", "name": "plan", "target_chat_id": 2}),
+        );
+        assert!(tool.execute(share_self).await.is_error);
+        let share = as_chat(
+            1,
+            json!({"action": "share", "name": "plan", "target_chat_id": 2}),
+        );
+        assert!(!tool.execute(share).await.is_error);
+
+        let result = tool
+            .execute(as_chat(
+                2,
+                json!({"action": "append", "name": "plan", "content": "- ship\n"}),
+            ))
+            .await;
+        assert!(
+            result.content.contains("updated to v2"),
+            "{}",
+            result.content
+        );
+        let result = tool.execute(read).await;
+        assert!(result
+            .content
+            .contains("owner chat 1, last written by chat 2"));
+        assert!(result.content.ends_with("- build\n- ship\n"));
+        let list = tool.execute(as_chat(3, json!({"action": "list"}))).await;
+        assert_eq!(list.content, "No scratchpads.");
+
+        let delete = as_chat(2, json!({"action": "delete", "name": "plan"}));
+        assert!(tool
+            .execute(delete)
+            .await
+            .content
+            .contains("Permission denied"));
+        let delete = as_chat(1, json!({"action": "delete", "name": "plan"}));
+        assert!(!tool.execute(delete).await.is_error);
+        assert!(db.get_scratchpad("plan").unwrap().is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_rejects_bad_names_and_oversized_content() {
+        let dir = std::env::temp_dir().join(format!("microclaw_padt_{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let tool = ScratchpadTool::new(Arc::new(ChannelRegistry::new()), db);
+
+        let result = tool
+            .execute(json!({"action": "write", "chat_id": 1, "name": "../x", "content": "x"}))
+            .await;
+        assert!(result.content.contains("Invalid scratchpad name"));
+        let big = "x".repeat(MAX_SCRATCHPAD_BYTES + 1);
+        let result = tool
+            .execute(json!({"action": "write", "chat_id": 1, "name": "big", "content": big}))
+            .await;
+        assert!(result.content.contains("the limit is"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_share_requires_caller_context() {
+        let dir = std::env::temp_dir().join(format!("microclaw_padt_{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let tool = ScratchpadTool::new(Arc::new(ChannelRegistry::new()), db.clone());
+
+        let write = as_chat(
+            1,
+            json!({"action": "write", "name": "plan", "content": "x"}),
+        );
+        assert!(!tool.execute(write).await.is_error);
+        let result = tool
+            .execute(json!({"action": "share", "chat_id": 1, "name": "plan", "target_chat_id": 2}))
+            .await;
+        assert!(
+            result.content.contains("Permission denied"),
+            "{}",
+            result.content
+        );
+        let read = as_chat(2, json!({"action": "read", "name": "plan"}));
+        assert!(tool.execute(read).await.is_error);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,314 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::AsyncTransport;
+use serde_json::json;
+use tracing::info;
+
+use crate::channels::email::build_smtp_transport;
+use crate::config::{SmtpConfig, WorkingDirIsolation};
+use crate::llm_types::ToolDefinition;
+
+use super::{schema_object, Tool, ToolResult};
+
+/// Recipients across `to` and `cc`.
+const MAX_RECIPIENTS: usize = 20;
+/// Combined size of all attachments.
+const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+pub struct SendEmailTool {
+    smtp: SmtpConfig,
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+}
+
+impl SendEmailTool {
+    pub fn new(
+        smtp: SmtpConfig,
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+    ) -> Self {
+        SendEmailTool {
+            smtp,
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+        }
+    }
+}
+
+/// `to`/`cc` as a list, accepting one string or an array of strings.
+fn addresses(input: &serde_json::Value, key: &str) -> Vec<String> {
+    match input.get(key) {
+        Some(serde_json::Value::String(s)) => s
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse `address` and check its domain is in `allowed_domains` (already
+/// lower-cased by config normalization).
+fn allowed_mailbox(address: &str, allowed_domains: &[String]) -> Result<Mailbox, String> {
+    let mailbox: Mailbox = address
+        .parse()
+        .map_err(|e| format!("Invalid email address {address:?}: {e}"))?;
+    let domain = mailbox.email.domain().to_ascii_lowercase();
+    if allowed_domains.contains(&domain) {
+        Ok(mailbox)
+    } else {
+        Err(format!(
+            "Recipient domain {domain} is not allowed (smtp.allowed_domains: {})",
+            allowed_domains.join(", ")
+        ))
+    }
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        _ => "application/octet-stream",
+    }
+}
+
+#[async_trait]
+impl Tool for SendEmailTool {
+    fn name(&self) -> &str {
+        "send_email"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "send_email".into(),
+            description: format!(
+                "Send an email from {} with optional file attachments, e.g. to deliver a scheduled report. Recipients must be in an allowed domain: {}.",
+                self.smtp.sender_address(),
+                self.smtp.allowed_domains.join(", ")
+            ),
+            input_schema: schema_object(
+                json!({
+                    "to": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Recipient addresses"
+                    },
+                    "cc": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Carbon-copy addresses"
+                    },
+                    "subject": {
+                        "type": "string",
+                        "description": "Subject line"
+                    },
+                    "body": {
+                        "type": "string",
+                        "description": "Plain-text message body"
+                    },
+                    "attachments": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Paths of files to attach"
+                    }
+                }),
+                &["to", "subject", "body"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let to = addresses(&input, "to");
+        let cc = addresses(&input, "cc");
+        if to.is_empty() {
+            return ToolResult::error("Missing required parameter: to".into());
+        }
+        if to.len() + cc.len() > MAX_RECIPIENTS {
+            return ToolResult::error(format!("At most {MAX_RECIPIENTS} recipients per email"));
+        }
+        let subject = match input.get("subject").and_then(|v| v.as_str()) {
+            Some(s) if !s.trim().is_empty() => s.trim().to_string(),
+            _ => return ToolResult::error("Missing required parameter: subject".into()),
+        };
+        let body = match input.get("body").and_then(|v| v.as_str()) {
+            Some(b) => b.to_string(),
+            None => return ToolResult::error("Missing required parameter: body".into()),
+        };
+
+        let from: Mailbox = match self.smtp.sender_address().parse() {
+            Ok(m) => m,
+            Err(e) => return ToolResult::error(format!("Invalid smtp.from_address: {e}")),
+        };
+        let mut builder = lettre::Message::builder().from(from).subject(&subject);
+        for address in &to {
+            match allowed_mailbox(address, &self.smtp.allowed_domains) {
+                Ok(m) => builder = builder.to(m),
+                Err(e) => return ToolResult::error(e),
+            }
+        }
+        for address in &cc {
+            match allowed_mailbox(address, &self.smtp.allowed_domains) {
+                Ok(m) => builder = builder.cc(m),
+                Err(e) => return ToolResult::error(e),
+            }
+        }
+
+        let paths: Vec<&str> = input
+            .get("attachments")
+            .and_then(|v| v.as_array())
+            .map(|items| items.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        let working_dir =
+            super::resolve_tool_working_dir(&self.working_dir, self.working_dir_isolation, &input);
+        let mut attachments = Vec::new();
+        let mut total_bytes = 0u64;
+        for path in &paths {
+            let resolved = super::resolve_tool_path(&working_dir, path);
+            if let Err(msg) = crate::tools::path_guard::check_path(&resolved.to_string_lossy()) {
+                return ToolResult::error(msg);
+            }
+            let bytes = match tokio::fs::read(&resolved).await {
+                Ok(bytes) => bytes,
+                Err(e) => return ToolResult::error(format!("Failed to read {path}: {e}")),
+            };
+            total_bytes += bytes.len() as u64;
+            if total_bytes > MAX_ATTACHMENT_BYTES {
+                return ToolResult::error(format!(
+                    "Attachments exceed {} MB in total",
+                    MAX_ATTACHMENT_BYTES / (1024 * 1024)
+                ));
+            }
+            let filename = resolved
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "attachment.bin".into());
+            let content_type =
+                ContentType::parse(content_type_for(&resolved)).unwrap_or(ContentType::TEXT_PLAIN);
+            attachments.push(Attachment::new(filename).body(bytes, content_type));
+        }
+
+        let message = if attachments.is_empty() {
+            builder.header(ContentType::TEXT_PLAIN).body(body)
+        } else {
+            let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(body));
+            for attachment in attachments {
+                parts = parts.singlepart(attachment);
+            }
+            builder.multipart(parts)
+        };
+        let message = match message {
+            Ok(m) => m,
+            Err(e) => return ToolResult::error(format!("Failed to build email: {e}")),
+        };
+
+        let transport = match build_smtp_transport(
+            self.smtp.host.as_deref().unwrap_or_default(),
+            self.smtp.port,
+            self.smtp.starttls,
+            &self.smtp.username,
+            self.smtp.password.as_deref().unwrap_or_default(),
+        ) {
+            Ok(t) => t,
+            Err(e) => return ToolResult::error(e),
+        };
+        match transport.send(message).await {
+            Ok(_) => {
+                info!(
+                    "Sent email {subject:?} to {} recipient(s)",
+                    to.len() + cc.len()
+                );
+                let mut summary = format!("Email sent to {}", to.join(", "));
+                if !cc.is_empty() {
+                    summary.push_str(&format!(" (cc {})", cc.join(", ")));
+                }
+                if !paths.is_empty() {
+                    summary.push_str(&format!(" with {} attachment(s)", paths.len()));
+                }
+                ToolResult::success(summary)
+            }
+            Err(e) => ToolResult::error(format!("Failed to send email: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool() -> SendEmailTool {
+        let smtp = SmtpConfig {
+            host: Some("127.0.0.1".into()),
+            port: 9,
+            username: "bot@example.com".into(),
+            allowed_domains: vec!["example.com".into()],
+            ..SmtpConfig::default()
+        };
+        SendEmailTool::new(smtp, "/tmp", WorkingDirIsolation::Shared)
+    }
+
+    #[test]
+    fn test_recipient_domain_allowlist() {
+        let allowed = vec!["example.com".to_string()];
+        assert!(allowed_mailbox("Ann <ann@Example.COM>", &allowed).is_ok());
+        let err = allowed_mailbox("eve@evil.test", &allowed).unwrap_err();
+        assert!(err.contains("evil.test is not allowed"));
+        // Subdomains are separate domains.
+        assert!(allowed_mailbox("bob@mail.example.com", &allowed).is_err());
+        assert!(allowed_mailbox("not an address", &allowed).is_err());
+        assert_eq!(
+            addresses(&json!({"to": "a@example.com, b@example.com"}), "to"),
+            vec!["a@example.com", "b@example.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_before_sending() {
+        let tool = tool();
+        let result = tool
+            .execute(json!({"to": ["ann@example.com", "eve@evil.test"], "subject": "Report", "body": "hi"}))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("evil.test"));
+
+        let result = tool
+            .execute(json!({"to": ["ann@example.com"], "subject": " ", "body": "hi"}))
+            .await;
+        assert!(result.content.contains("subject"));
+
+        let result = tool
+            .execute(json!({
+                "to": "ann@example.com",
+                "subject": "Report",
+                "body": "hi",
+                "attachments": ["/nonexistent/microclaw-report.pdf"]
+            }))
+            .await;
+        assert!(result.content.contains("Failed to read"));
+    }
+}
@@ -10,9 +10,13 @@ use crate::channel::{
     deliver_and_store_bot_message, enforce_channel_policy, get_required_chat_routing,
 };
 use crate::channel_adapter::ChannelRegistry;
+use crate::channels::telegram::{parse_external_chat_id, topic_external_chat_id};
 use crate::db::{call_blocking, Database, StoredMessage};
 use crate::llm_types::ToolDefinition;
 
+/// Files per `send_message` call; one Telegram album or Discord message.
+const MAX_ATTACHMENTS: usize = 10;
+
 pub struct SendMessageTool {
     registry: Arc<ChannelRegistry>,
     db: Arc<Database>,
@@ -48,6 +52,31 @@ impl SendMessageTool {
             .map_err(|e| format!("Failed to resolve external chat id: {e}"))?;
         Ok(external.unwrap_or_else(|| chat_id.to_string()))
     }
+
+    /// Chat for a Telegram forum topic in the same group as `chat_id`.
+    async fn resolve_topic_chat_id(&self, chat_id: i64, thread_id: i64) -> Result<i64, String> {
+        let routing = get_required_chat_routing(&self.registry, self.db.clone(), chat_id).await?;
+        let channel = routing.channel_name.clone();
+        if channel != "telegram" && !channel.starts_with("telegram:") {
+            return Err(format!(
+                "message_thread_id is only supported for Telegram forum groups, not {}",
+                routing.channel_name
+            ));
+        }
+        let thread_id = i32::try_from(thread_id)
+            .ok()
+            .filter(|t| *t > 0)
+            .ok_or_else(|| format!("Invalid message_thread_id: {thread_id}"))?;
+        let external = self.resolve_external_chat_id(chat_id).await?;
+        let (group, _) = parse_external_chat_id(&external)?;
+        let topic_external = topic_external_chat_id(group.0, Some(thread_id));
+        call_blocking(self.db.clone(), move |db| {
+            let chat_type = format!("{channel}_supergroup");
+            db.resolve_or_create_chat_id(&channel, &topic_external, None, &chat_type)
+        })
+        .await
+        .map_err(|e| format!("Failed to resolve forum topic chat: {e}"))
+    }
 }
 
 #[async_trait]
@@ -59,7 +88,7 @@ impl Tool for SendMessageTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "send_message".into(),
-            description: "Send a message mid-conversation. Supports text for all channels, and attachments for Telegram/Discord/Slack via attachment_path.".into(),
+            description: "Send a message mid-conversation. Supports text for all channels, and attachments for Telegram/Discord/Slack via attachment_path. Use attachment_paths to send several files (e.g. report charts) together: one album on Telegram, one message on Discord.".into(),
             input_schema: schema_object(
                 json!({
                     "chat_id": {
@@ -74,9 +103,18 @@ impl Tool for SendMessageTool {
                         "type": "string",
                         "description": "Optional local file path to send as an attachment"
                     },
+                    "attachment_paths": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Optional local file paths to send together as one message (up to 10)"
+                    },
                     "caption": {
                         "type": "string",
                         "description": "Optional caption used when sending attachment"
+                    },
+                    "message_thread_id": {
+                        "type": "integer",
+                        "description": "Telegram forum groups only: topic (message_thread_id) in the chat's group to post to"
                     }
                 }),
                 &["chat_id"],
@@ -95,25 +133,37 @@ impl Tool for SendMessageTool {
             .unwrap_or("")
             .trim()
             .to_string();
-        let attachment_path = input
+        let attachment_paths: Vec<String> = input
             .get("attachment_path")
-            .and_then(|v| v.as_str())
+            .into_iter()
+            .chain(
+                input
+                    .get("attachment_paths")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten(),
+            )
+            .filter_map(|v| v.as_str())
             .map(|v| v.trim().to_string())
-            .filter(|v| !v.is_empty());
+            .filter(|v| !v.is_empty())
+            .collect();
         let caption = input
             .get("caption")
             .and_then(|v| v.as_str())
             .map(|v| v.trim().to_string())
             .filter(|v| !v.is_empty());
 
-        if text.is_empty() && attachment_path.is_none() {
+        if text.is_empty() && attachment_paths.is_empty() {
             return ToolResult::error("Provide text and/or attachment_path".into());
         }
+        if attachment_paths.len() > MAX_ATTACHMENTS {
+            return ToolResult::error(format!("At most {MAX_ATTACHMENTS} attachments per message"));
+        }
         info!(
-            "send_message start: chat_id={}, has_text={}, has_attachment={}",
+            "send_message start: chat_id={}, has_text={}, attachments={}",
             chat_id,
             !text.is_empty(),
-            attachment_path.is_some()
+            attachment_paths.len()
         );
 
         if let Err(e) = authorize_chat_access(&input, chat_id) {
@@ -126,30 +176,42 @@ impl Tool for SendMessageTool {
             return ToolResult::error(e);
         }
 
-        if let Some(path) = attachment_path {
+        let chat_id = match input.get("message_thread_id").and_then(|v| v.as_i64()) {
+            Some(thread_id) => match self.resolve_topic_chat_id(chat_id, thread_id).await {
+                Ok(topic_chat_id) => topic_chat_id,
+                Err(e) => return ToolResult::error(e),
+            },
+            None => chat_id,
+        };
+
+        if !attachment_paths.is_empty() {
             let routing =
                 match get_required_chat_routing(&self.registry, self.db.clone(), chat_id).await {
                     Ok(v) => v,
                     Err(e) => return ToolResult::error(e),
                 };
             info!(
-                "send_message attachment routing: chat_id={}, channel={}, path={}",
-                chat_id, routing.channel_name, path
+                "send_message attachment routing: chat_id={}, channel={}, paths={:?}",
+                chat_id, routing.channel_name, attachment_paths
             );
 
-            let file_path = PathBuf::from(&path);
-            if !file_path.is_file() {
-                warn!(
-                    "send_message attachment missing: chat_id={}, path={}, current_dir={}",
-                    chat_id,
-                    file_path.display(),
-                    std::env::current_dir()
-                        .map(|p| p.display().to_string())
-                        .unwrap_or_else(|_| "<unknown>".to_string())
-                );
-                return ToolResult::error(format!(
-                    "attachment_path not found or not a file: {path}"
-                ));
+            let mut file_paths = Vec::new();
+            for path in &attachment_paths {
+                let file_path = PathBuf::from(path);
+                if !file_path.is_file() {
+                    warn!(
+                        "send_message attachment missing: chat_id={}, path={}, current_dir={}",
+                        chat_id,
+                        file_path.display(),
+                        std::env::current_dir()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_else(|_| "<unknown>".to_string())
+                    );
+                    return ToolResult::error(format!(
+                        "attachment_path not found or not a file: {path}"
+                    ));
+                }
+                file_paths.push(file_path);
             }
 
             let used_caption = caption.or_else(|| {
@@ -175,16 +237,22 @@ impl Tool for SendMessageTool {
                 Err(e) => return ToolResult::error(e),
             };
 
-            let send_result = adapter
-                .send_attachment(&external_chat_id, &file_path, used_caption.as_deref())
-                .await;
+            let send_result = if let [file_path] = file_paths.as_slice() {
+                adapter
+                    .send_attachment(&external_chat_id, file_path, used_caption.as_deref())
+                    .await
+            } else {
+                adapter
+                    .send_attachments(&external_chat_id, &file_paths, used_caption.as_deref())
+                    .await
+            };
 
             match send_result {
                 Ok(content) => {
                     info!(
-                        "send_message attachment sent: chat_id={}, path={}",
+                        "send_message attachments sent: chat_id={}, count={}",
                         chat_id,
-                        file_path.display()
+                        file_paths.len()
                     );
                     if let Err(e) = self.store_bot_message(chat_id, content).await {
                         warn!(
@@ -193,14 +261,19 @@ impl Tool for SendMessageTool {
                         );
                         return ToolResult::error(e);
                     }
-                    ToolResult::success("Attachment sent successfully.".into())
+                    if file_paths.len() == 1 {
+                        ToolResult::success("Attachment sent successfully.".into())
+                    } else {
+                        ToolResult::success(format!(
+                            "{} attachments sent successfully.",
+                            file_paths.len()
+                        ))
+                    }
                 }
                 Err(e) => {
                     warn!(
-                        "send_message attachment delivery failed: chat_id={}, path={}, error={}",
-                        chat_id,
-                        file_path.display(),
-                        e
+                        "send_message attachment delivery failed: chat_id={}, paths={:?}, error={}",
+                        chat_id, attachment_paths, e
                     );
                     ToolResult::error(e)
                 }
@@ -376,4 +449,59 @@ mod tests {
         assert!(result.content.contains("not supported for web"));
         cleanup(&dir);
     }
+
+    #[tokio::test]
+    async fn test_send_multiple_attachments_validated() {
+        let (db, dir) = test_db();
+        db.upsert_chat(999, Some("web-main"), "web").unwrap();
+        let chart = dir.join("chart1.png");
+        let table = dir.join("table.csv");
+        std::fs::write(&chart, "png").unwrap();
+        std::fs::write(&table, "a,b").unwrap();
+        let tool = SendMessageTool::new(test_registry(), db, "bot".into());
+
+        let too_many: Vec<String> = (0..11).map(|i| format!("/tmp/chart{i}.png")).collect();
+        let result = tool
+            .execute(json!({"chat_id": 999, "attachment_paths": too_many}))
+            .await;
+        assert!(result.content.contains("At most 10 attachments"));
+
+        let result = tool
+            .execute(json!({
+                "chat_id": 999,
+                "attachment_path": chart.to_string_lossy(),
+                "attachment_paths": [dir.join("missing.png").to_string_lossy()]
+            }))
+            .await;
+        assert!(result.content.contains("missing.png"));
+
+        let result = tool
+            .execute(json!({
+                "chat_id": 999,
+                "attachment_paths": [chart.to_string_lossy(), table.to_string_lossy()],
+                "caption": "Weekly report"
+            }))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("not supported for web"));
+        cleanup(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_send_message_thread_id_requires_telegram() {
+        let (db, dir) = test_db();
+        db.upsert_chat(999, Some("web-main"), "web").unwrap();
+
+        let tool = SendMessageTool::new(test_registry(), db, "bot".into());
+        let result = tool
+            .execute(json!({
+                "chat_id": 999,
+                "text": "hi",
+                "message_thread_id": 4
+            }))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("only supported for Telegram"));
+        cleanup(&dir);
+    }
 }
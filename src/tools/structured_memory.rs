@@ -66,7 +66,7 @@ impl Tool for StructuredMemorySearchTool {
             .unwrap_or(false);
 
         let chat_id = auth_context_from_input(&input)
-            .map(|a| a.caller_chat_id)
+            .map(|a| a.personal_chat_id(a.caller_chat_id))
             .unwrap_or(0);
 
         info!(
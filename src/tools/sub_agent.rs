@@ -3,7 +3,10 @@ use serde_json::json;
 use std::sync::Arc;
 use tracing::info;
 
-use super::{auth_context_from_input, schema_object, Tool, ToolRegistry, ToolResult};
+use super::{
+    auth_context_from_input, report_progress, schema_object, untrusted, Tool, ToolRegistry,
+    ToolResult,
+};
 use crate::config::Config;
 #[cfg(test)]
 use crate::config::WorkingDirIsolation;
@@ -13,6 +16,36 @@ use crate::llm_types::{
 };
 
 const MAX_SUB_AGENT_ITERATIONS: usize = 10;
+/// Characters of the sub-agent's interim text kept in a progress note.
+const PROGRESS_FINDINGS_CHARS: usize = 160;
+
+/// Progress note for step `iteration` (0-based) calling `tool_names`, with
+/// the start of the interim text the model wrote alongside, if any.
+fn progress_note(iteration: usize, tool_names: &[&str], text: &str) -> String {
+    let mut activities: Vec<String> = Vec::new();
+    for name in tool_names {
+        let label = crate::streaming::tool_status_label(name);
+        if !activities.contains(&label) {
+            activities.push(label);
+        }
+    }
+    let mut note = format!(
+        "step {}/{MAX_SUB_AGENT_ITERATIONS}: {}",
+        iteration + 1,
+        activities.join(", ")
+    );
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        let clipped: String = text.chars().take(PROGRESS_FINDINGS_CHARS).collect();
+        let ellipsis = if clipped.len() < text.len() {
+            "…"
+        } else {
+            ""
+        };
+        note.push_str(&format!(" — {clipped}{ellipsis}"));
+    }
+    note
+}
 
 pub struct SubAgentTool {
     config: Config,
@@ -65,8 +98,16 @@ impl Tool for SubAgentTool {
 
         info!("Sub-agent starting task: {}", task);
 
-        let llm = crate::llm::create_provider(&self.config);
-        let tools = ToolRegistry::new_sub_agent(&self.config, self.db.clone());
+        // The sub-agent inherits the caller channel's model and tool policy.
+        let config = match &auth_context {
+            Some(auth) => self.config.for_channel(&auth.caller_channel),
+            None => self.config.clone(),
+        };
+        let llm = crate::llm::create_provider(&config);
+        let mut tools = ToolRegistry::new_sub_agent(&config, self.db.clone());
+        if let Some(auth) = &auth_context {
+            tools.restrict(&config.channel_overrides(&auth.caller_channel).tool_policy);
+        }
         let tool_defs = tools.definitions().to_vec();
 
         let system_prompt = "You are a sub-agent assistant. Complete the given task thoroughly and return a clear, concise result. You have access to tools for file operations, search, and web access. Focus on the task and provide actionable output.".to_string();
@@ -81,6 +122,10 @@ impl Tool for SubAgentTool {
             role: "user".into(),
             content: MessageContent::Text(user_content),
         }];
+        let mut untrusted_seen = false;
+        // Interim text of the latest step, returned if the sub-agent runs out
+        // of iterations.
+        let mut findings = String::new();
 
         for iteration in 0..MAX_SUB_AGENT_ITERATIONS {
             let response = match llm
@@ -99,8 +144,8 @@ impl Tool for SubAgentTool {
                     .as_ref()
                     .map(|a| a.caller_channel.clone())
                     .unwrap_or_else(|| "sub_agent".to_string());
-                let provider = self.config.llm_provider.clone();
-                let model = self.config.model.clone();
+                let (provider, model) =
+                    response.usage_source(&self.config.llm_provider, &config.model);
                 let input_tokens = i64::from(usage.input_tokens);
                 let output_tokens = i64::from(usage.output_tokens);
                 let _ = call_blocking(self.db.clone(), move |db| {
@@ -139,6 +184,22 @@ impl Tool for SubAgentTool {
             }
 
             if stop_reason == "tool_use" {
+                let mut tool_names = Vec::new();
+                let mut text = String::new();
+                for block in &response.content {
+                    match block {
+                        ResponseContentBlock::ToolUse { name, .. } => {
+                            tool_names.push(name.as_str())
+                        }
+                        ResponseContentBlock::Text { text: t } => text.push_str(t),
+                        _ => {}
+                    }
+                }
+                report_progress(progress_note(iteration, &tool_names, &text));
+                if !text.trim().is_empty() {
+                    findings = text.trim().to_string();
+                }
+
                 let assistant_content: Vec<ContentBlock> = response
                     .content
                     .iter()
@@ -153,6 +214,16 @@ impl Tool for SubAgentTool {
                                 input: input.clone(),
                             }
                         }
+                        ResponseContentBlock::Thinking {
+                            thinking,
+                            signature,
+                        } => ContentBlock::Thinking {
+                            thinking: thinking.clone(),
+                            signature: signature.clone(),
+                        },
+                        ResponseContentBlock::RedactedThinking { data } => {
+                            ContentBlock::RedactedThinking { data: data.clone() }
+                        }
                     })
                     .collect();
 
@@ -169,11 +240,19 @@ impl Tool for SubAgentTool {
                             name,
                             iteration + 1
                         );
-                        let result = if let Some(ref auth) = auth_context {
+                        let result = if self.config.block_high_risk_after_untrusted
+                            && untrusted_seen
+                            && untrusted::blocked_after_untrusted(name)
+                        {
+                            untrusted::blocked_result(name)
+                        } else if let Some(ref auth) = auth_context {
                             tools.execute_with_auth(name, input.clone(), auth).await
                         } else {
                             tools.execute(name, input.clone()).await
                         };
+                        if !result.is_error && untrusted::is_untrusted_source(name) {
+                            untrusted_seen = true;
+                        }
                         tool_results.push(ContentBlock::ToolResult {
                             tool_use_id: id.clone(),
                             content: result.content,
@@ -207,9 +286,12 @@ impl Tool for SubAgentTool {
             });
         }
 
-        ToolResult::error(
-            "Sub-agent reached maximum iterations without completing the task.".into(),
-        )
+        let mut message =
+            "Sub-agent reached maximum iterations without completing the task.".to_string();
+        if !findings.is_empty() {
+            message.push_str(&format!("\n\nLatest partial findings:\n{findings}"));
+        }
+        ToolResult::error(message)
     }
 }
 
@@ -247,6 +329,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 3900,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -262,6 +346,71 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         }
     }
@@ -295,6 +444,19 @@ mod tests {
         assert!(result.content.contains("Missing required parameter: task"));
     }
 
+    #[test]
+    fn test_progress_note() {
+        assert_eq!(
+            progress_note(0, &["grep", "glob", "bash"], ""),
+            "step 1/10: Searching files, Running bash"
+        );
+        let note = progress_note(2, &["web_fetch"], "  Found the\nchangelog.  ");
+        assert_eq!(note, "step 3/10: Reading a web page — Found the changelog.");
+        let note = progress_note(4, &["read_file"], &"é".repeat(500));
+        assert!(note.ends_with("é…"), "{note}");
+        assert_eq!(note.chars().filter(|c| *c == 'é').count(), 160);
+    }
+
     #[test]
     fn test_sub_agent_restricted_registry_tool_count() {
         let config = test_config();
@@ -333,5 +495,8 @@ mod tests {
         assert!(!names.contains(&"cancel_scheduled_task"));
         assert!(!names.contains(&"get_task_history"));
         assert!(!names.contains(&"export_chat"));
+        assert!(!names.contains(&"export_workspace"));
+        assert!(!names.contains(&"watch_path"));
+        assert!(!names.contains(&"scratchpad"));
     }
 }
@@ -6,7 +6,7 @@ use tracing::info;
 
 use crate::llm_types::ToolDefinition;
 
-use super::{authorize_chat_access, schema_object, Tool, ToolResult};
+use super::{authorize_chat_access, personal_chat_id, schema_object, Tool, ToolResult};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodoItem {
@@ -14,11 +14,11 @@ pub struct TodoItem {
     pub status: String, // "pending", "in_progress", "completed"
 }
 
-fn todo_path(groups_dir: &Path, chat_id: i64) -> PathBuf {
+pub(crate) fn todo_path(groups_dir: &Path, chat_id: i64) -> PathBuf {
     groups_dir.join(chat_id.to_string()).join("TODO.json")
 }
 
-fn read_todos(groups_dir: &Path, chat_id: i64) -> Vec<TodoItem> {
+pub(crate) fn read_todos(groups_dir: &Path, chat_id: i64) -> Vec<TodoItem> {
     let path = todo_path(groups_dir, chat_id);
     match std::fs::read_to_string(&path) {
         Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
@@ -95,6 +95,7 @@ impl Tool for TodoReadTool {
         if let Err(e) = authorize_chat_access(&input, chat_id) {
             return ToolResult::error(e);
         }
+        let chat_id = personal_chat_id(&input, chat_id);
 
         info!("Reading todo list for chat {}", chat_id);
         let todos = read_todos(&self.groups_dir, chat_id);
@@ -165,6 +166,7 @@ impl Tool for TodoWriteTool {
         if let Err(e) = authorize_chat_access(&input, chat_id) {
             return ToolResult::error(e);
         }
+        let chat_id = personal_chat_id(&input, chat_id);
 
         let todos_val = match input.get("todos") {
             Some(v) => v,
@@ -0,0 +1,376 @@
+//! `translate`: translation with the configured LLM and a per-chat glossary
+//! of preferred terms, so repeated translations stay consistent across
+//! sessions. Glossary entries apply to one target language or to all of them;
+//! only the entries whose term occurs in the text go into the prompt.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+use tracing::info;
+
+use super::{auth_context_from_input, authorized_chat_id, schema_object, Tool, ToolResult};
+use crate::channel_adapter::ChannelRegistry;
+use crate::config::Config;
+use crate::db::{call_blocking, Database, GlossaryTerm};
+use crate::llm_types::{Message, MessageContent, ResponseContentBlock, ToolDefinition};
+
+/// Longest text translated in one call, in characters.
+const MAX_TEXT_CHARS: usize = 20_000;
+const MAX_TERM_CHARS: usize = 200;
+
+pub struct TranslateTool {
+    config: Config,
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+}
+
+impl TranslateTool {
+    pub fn new(config: &Config, registry: Arc<ChannelRegistry>, db: Arc<Database>) -> Self {
+        TranslateTool {
+            config: config.clone(),
+            registry,
+            db,
+        }
+    }
+
+    async fn translate(&self, input: &serde_json::Value, chat_id: i64) -> ToolResult {
+        let Some(text) = input.get("text").and_then(|v| v.as_str()) else {
+            return ToolResult::error("Missing required parameter: text".into());
+        };
+        if text.chars().count() > MAX_TEXT_CHARS {
+            return ToolResult::error(format!(
+                "Text is too long; translate it in parts of up to {MAX_TEXT_CHARS} characters."
+            ));
+        }
+        let Some(target) = language_param(input, "target_language") else {
+            return ToolResult::error("Missing required parameter: target_language".into());
+        };
+        let source = language_param(input, "source_language");
+        let language = target.clone();
+        let glossary = match call_blocking(self.db.clone(), move |db| {
+            db.list_glossary_terms(chat_id, Some(&language))
+        })
+        .await
+        {
+            Ok(terms) => relevant_terms(terms, text),
+            Err(e) => return ToolResult::error(format!("Failed to read glossary: {e}")),
+        };
+
+        let config = match auth_context_from_input(input) {
+            Some(auth) => self.config.for_channel(&auth.caller_channel),
+            None => self.config.clone(),
+        };
+        info!(
+            "Translating {} chars to {target} with {} glossary terms",
+            text.chars().count(),
+            glossary.len()
+        );
+        let llm = crate::llm::create_provider(&config);
+        let messages = vec![Message {
+            role: "user".into(),
+            content: MessageContent::Text(text.to_string()),
+        }];
+        let prompt = system_prompt(&target, source.as_deref(), &glossary);
+        let response = match llm.send_message(&prompt, messages, None).await {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(format!("Translation failed: {e}")),
+        };
+        if let Some(usage) = &response.usage {
+            let caller_channel = auth_context_from_input(input)
+                .map(|a| a.caller_channel)
+                .unwrap_or_else(|| "translate".to_string());
+            let (provider, model) = response.usage_source(&config.llm_provider, &config.model);
+            let input_tokens = i64::from(usage.input_tokens);
+            let output_tokens = i64::from(usage.output_tokens);
+            let _ = call_blocking(self.db.clone(), move |db| {
+                db.log_llm_usage(
+                    chat_id,
+                    &caller_channel,
+                    &provider,
+                    &model,
+                    input_tokens,
+                    output_tokens,
+                    "translate",
+                )
+                .map(|_| ())
+            })
+            .await;
+        }
+        let translation = response
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ResponseContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        ToolResult::success(translation.trim().to_string())
+    }
+
+    async fn add_term(&self, input: &serde_json::Value, chat_id: i64) -> ToolResult {
+        let (Some(term), Some(translation)) =
+            (text_param(input, "term"), text_param(input, "translation"))
+        else {
+            return ToolResult::error("Missing required parameters: term and translation".into());
+        };
+        if term.chars().count() > MAX_TERM_CHARS || translation.chars().count() > MAX_TERM_CHARS {
+            return ToolResult::error(format!(
+                "Glossary terms and translations are limited to {MAX_TERM_CHARS} characters."
+            ));
+        }
+        let language = language_param(input, "target_language").unwrap_or_default();
+        let reply = format!(
+            "Glossary: \"{term}\" → \"{translation}\" ({}).",
+            language_label(&language)
+        );
+        match call_blocking(self.db.clone(), move |db| {
+            db.set_glossary_term(chat_id, &language, &term, &translation)
+        })
+        .await
+        {
+            Ok(()) => ToolResult::success(reply),
+            Err(e) => ToolResult::error(format!("Failed to save glossary term: {e}")),
+        }
+    }
+
+    async fn remove_term(&self, input: &serde_json::Value, chat_id: i64) -> ToolResult {
+        let Some(term) = text_param(input, "term") else {
+            return ToolResult::error("Missing required parameter: term".into());
+        };
+        let language = language_param(input, "target_language").unwrap_or_default();
+        let label = language_label(&language).to_string();
+        let term_for_db = term.clone();
+        match call_blocking(self.db.clone(), move |db| {
+            db.delete_glossary_term(chat_id, &language, &term_for_db)
+        })
+        .await
+        {
+            Ok(true) => {
+                ToolResult::success(format!("Removed \"{term}\" ({label}) from the glossary."))
+            }
+            Ok(false) => ToolResult::error(format!("\"{term}\" ({label}) is not in the glossary.")),
+            Err(e) => ToolResult::error(format!("Failed to remove glossary term: {e}")),
+        }
+    }
+
+    async fn list_terms(&self, input: &serde_json::Value, chat_id: i64) -> ToolResult {
+        let language = language_param(input, "target_language");
+        match call_blocking(self.db.clone(), move |db| {
+            db.list_glossary_terms(chat_id, language.as_deref())
+        })
+        .await
+        {
+            Ok(terms) if terms.is_empty() => ToolResult::success("The glossary is empty.".into()),
+            Ok(terms) => ToolResult::success(
+                terms
+                    .iter()
+                    .map(|t| {
+                        format!(
+                            "\"{}\" → \"{}\" ({})",
+                            t.term,
+                            t.translation,
+                            language_label(&t.language)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Err(e) => ToolResult::error(format!("Failed to read glossary: {e}")),
+        }
+    }
+}
+
+fn text_param(input: &serde_json::Value, key: &str) -> Option<String> {
+    input
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Languages are matched case-insensitively, so "DE" and "de" share entries.
+fn language_param(input: &serde_json::Value, key: &str) -> Option<String> {
+    text_param(input, key).map(|s| s.to_lowercase())
+}
+
+fn language_label(language: &str) -> &str {
+    if language.is_empty() {
+        "all languages"
+    } else {
+        language
+    }
+}
+
+/// Glossary entries whose term occurs in `text`. A language-specific entry
+/// overrides an all-languages entry for the same term.
+fn relevant_terms(terms: Vec<GlossaryTerm>, text: &str) -> Vec<GlossaryTerm> {
+    let text = text.to_lowercase();
+    let mut relevant: Vec<GlossaryTerm> = Vec::new();
+    for term in terms {
+        if !text.contains(&term.term.to_lowercase()) {
+            continue;
+        }
+        match relevant
+            .iter_mut()
+            .find(|t| t.term.eq_ignore_ascii_case(&term.term))
+        {
+            Some(existing) if existing.language.is_empty() => *existing = term,
+            Some(_) => {}
+            None => relevant.push(term),
+        }
+    }
+    relevant
+}
+
+fn system_prompt(target: &str, source: Option<&str>, glossary: &[GlossaryTerm]) -> String {
+    let from = source.map(|s| format!(" from {s}")).unwrap_or_default();
+    let mut prompt = format!(
+        "You are a professional translator. Translate the user's message{from} into {target}. Keep the meaning, tone and formatting (Markdown, line breaks, lists, code, URLs and placeholders such as {{name}} unchanged). Output only the translation, without notes or quotes."
+    );
+    if !glossary.is_empty() {
+        prompt.push_str("\n\nGlossary: always translate these terms as given:");
+        for term in glossary {
+            prompt.push_str(&format!("\n- \"{}\" → \"{}\"", term.term, term.translation));
+        }
+    }
+    prompt
+}
+
+#[async_trait]
+impl Tool for TranslateTool {
+    fn name(&self) -> &str {
+        "translate"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "translate".into(),
+            description: "Translate text with the chat's glossary of preferred terminology, and manage that glossary so repeated translations stay consistent. Actions: translate (default), add_term (save or replace a preferred translation), remove_term, list_terms. Use the same language code each time (e.g. 'de', 'zh'); a glossary term without target_language applies to every language.".into(),
+            input_schema: schema_object(
+                json!({
+                    "action": {
+                        "type": "string",
+                        "enum": ["translate", "add_term", "remove_term", "list_terms"]
+                    },
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The current chat ID, whose glossary is used"
+                    },
+                    "text": {
+                        "type": "string",
+                        "description": "translate: the text to translate"
+                    },
+                    "target_language": {
+                        "type": "string",
+                        "description": "Language to translate into, e.g. 'de' (required for translate; optional for the glossary actions)"
+                    },
+                    "source_language": {
+                        "type": "string",
+                        "description": "translate: language of the text, if known"
+                    },
+                    "term": {
+                        "type": "string",
+                        "description": "add_term/remove_term: the source-language term"
+                    },
+                    "translation": {
+                        "type": "string",
+                        "description": "add_term: its preferred translation"
+                    }
+                }),
+                &["chat_id"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        match input
+            .get("action")
+            .and_then(|v| v.as_str())
+            .unwrap_or("translate")
+        {
+            "translate" => self.translate(&input, chat_id).await,
+            "add_term" => self.add_term(&input, chat_id).await,
+            "remove_term" => self.remove_term(&input, chat_id).await,
+            "list_terms" => self.list_terms(&input, chat_id).await,
+            other => ToolResult::error(format!(
+                "Unknown action '{other}'. Use translate, add_term, remove_term or list_terms."
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(term: &str, translation: &str, language: &str) -> GlossaryTerm {
+        GlossaryTerm {
+            term: term.into(),
+            translation: translation.into(),
+            language: language.into(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_relevant_terms_prefer_language_specific_entries() {
+        let terms = vec![
+            term("invoice", "Bill", ""),
+            term("MicroClaw", "MicroClaw", ""),
+            term("Invoice", "Rechnung", "de"),
+            term("receipt", "Quittung", "de"),
+        ];
+        let relevant = relevant_terms(terms, "Please pay the INVOICE for microclaw.");
+        let pairs: Vec<(&str, &str)> = relevant
+            .iter()
+            .map(|t| (t.term.as_str(), t.translation.as_str()))
+            .collect();
+        assert_eq!(pairs, [("Invoice", "Rechnung"), ("MicroClaw", "MicroClaw")]);
+
+        let prompt = system_prompt("de", Some("en"), &relevant);
+        assert!(prompt.contains("from en into de"));
+        assert!(prompt.contains("- \"Invoice\" → \"Rechnung\""));
+        assert!(!system_prompt("de", None, &[]).contains("Glossary"));
+    }
+
+    #[tokio::test]
+    async fn test_glossary_actions() {
+        let dir =
+            std::env::temp_dir().join(format!("microclaw_translate_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let config: Config = serde_yaml::from_str("bot_username: bot\n").unwrap();
+        let tool = TranslateTool::new(&config, Arc::new(ChannelRegistry::new()), db);
+
+        let result = tool
+            .execute(json!({"chat_id": 7, "action": "add_term", "term": " invoice ", "translation": "Rechnung", "target_language": "DE"}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        let result = tool
+            .execute(json!({"chat_id": 7, "action": "list_terms", "target_language": "de"}))
+            .await;
+        assert_eq!(result.content, "\"invoice\" → \"Rechnung\" (de)");
+        let result = tool
+            .execute(json!({"chat_id": 7, "action": "remove_term", "term": "invoice"}))
+            .await;
+        assert!(result.is_error, "the entry is for de only");
+        let result = tool
+            .execute(json!({"chat_id": 7, "action": "remove_term", "term": "invoice", "target_language": "de"}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        let result = tool
+            .execute(json!({"chat_id": 7, "action": "list_terms"}))
+            .await;
+        assert_eq!(result.content, "The glossary is empty.");
+        let result = tool.execute(json!({"chat_id": 7, "text": "hi"})).await;
+        assert!(result.content.contains("target_language"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
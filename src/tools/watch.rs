@@ -0,0 +1,319 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{authorized_chat_id, schema_object, Tool, ToolResult};
+use crate::channel_adapter::ChannelRegistry;
+use crate::config::WorkingDirIsolation;
+use crate::db::{call_blocking, Database};
+use crate::file_watch::normalize_pattern;
+use crate::llm_types::ToolDefinition;
+
+// --- watch_path ---
+
+pub struct WatchPathTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+    working_dir: PathBuf,
+    working_dir_isolation: WorkingDirIsolation,
+    debounce_secs: u64,
+    max_watches_per_chat: usize,
+}
+
+impl WatchPathTool {
+    pub fn new(
+        registry: Arc<ChannelRegistry>,
+        db: Arc<Database>,
+        working_dir: &str,
+        working_dir_isolation: WorkingDirIsolation,
+        debounce_secs: u64,
+        max_watches_per_chat: usize,
+    ) -> Self {
+        WatchPathTool {
+            registry,
+            db,
+            working_dir: PathBuf::from(working_dir),
+            working_dir_isolation,
+            debounce_secs,
+            max_watches_per_chat,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for WatchPathTool {
+    fn name(&self) -> &str {
+        "watch_path"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "watch_path".into(),
+            description: format!(
+                "Watch files in the working directory and post a message to a chat when they are created, modified, removed or renamed. Changes are batched until {}s pass without new ones. Useful for following build output or files written by scheduled jobs.",
+                self.debounce_secs
+            ),
+            input_schema: schema_object(
+                json!({
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat ID to notify"
+                    },
+                    "pattern": {
+                        "type": "string",
+                        "description": "Glob relative to the working directory, e.g. 'dist/**/*.js' or 'reports/*.csv'. A directory path watches everything under it."
+                    }
+                }),
+                &["chat_id", "pattern"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        let pattern = match input.get("pattern").and_then(|v| v.as_str()) {
+            Some(p) => match normalize_pattern(p) {
+                Ok(p) => p,
+                Err(e) => return ToolResult::error(e),
+            },
+            None => return ToolResult::error("Missing required parameter: pattern".into()),
+        };
+        let workspace =
+            super::resolve_tool_working_dir(&self.working_dir, self.working_dir_isolation, &input);
+        if let Err(e) = std::fs::create_dir_all(&workspace) {
+            return ToolResult::error(format!("Failed to create working directory: {e}"));
+        }
+        // Events carry resolved paths, so store the root the same way.
+        let root = match std::fs::canonicalize(&workspace) {
+            Ok(root) => root.to_string_lossy().to_string(),
+            Err(e) => {
+                return ToolResult::error(format!("Failed to resolve working directory: {e}"))
+            }
+        };
+
+        let existing = match call_blocking(self.db.clone(), move |db| {
+            db.list_path_watches(Some(chat_id))
+        })
+        .await
+        {
+            Ok(watches) => watches,
+            Err(e) => return ToolResult::error(format!("Failed to add watch: {e}")),
+        };
+        if let Some(watch) = existing
+            .iter()
+            .find(|w| w.root == root && w.pattern == pattern)
+        {
+            return ToolResult::success(format!("Already watching `{pattern}` (#{}).", watch.id));
+        }
+        if existing.len() >= self.max_watches_per_chat {
+            return ToolResult::error(format!(
+                "This chat already has {} watches (limit {}); remove one with unwatch_path first.",
+                existing.len(),
+                self.max_watches_per_chat
+            ));
+        }
+
+        let pattern_for_db = pattern.clone();
+        match call_blocking(self.db.clone(), move |db| {
+            db.create_path_watch(chat_id, &root, &pattern_for_db)
+        })
+        .await
+        {
+            Ok(id) => ToolResult::success(format!(
+                "Watch #{id} added: changes to `{pattern}` will be posted to this chat (may take a few seconds to start)."
+            )),
+            Err(e) => ToolResult::error(format!("Failed to add watch: {e}")),
+        }
+    }
+}
+
+// --- list_path_watches ---
+
+pub struct ListPathWatchesTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+}
+
+impl ListPathWatchesTool {
+    pub fn new(registry: Arc<ChannelRegistry>, db: Arc<Database>) -> Self {
+        ListPathWatchesTool { registry, db }
+    }
+}
+
+#[async_trait]
+impl Tool for ListPathWatchesTool {
+    fn name(&self) -> &str {
+        "list_path_watches"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_path_watches".into(),
+            description: "List the file watches that notify a chat.".into(),
+            input_schema: schema_object(
+                json!({
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat ID to list watches for"
+                    }
+                }),
+                &["chat_id"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        match call_blocking(self.db.clone(), move |db| {
+            db.list_path_watches(Some(chat_id))
+        })
+        .await
+        {
+            Ok(watches) if watches.is_empty() => {
+                ToolResult::success("No file watches for this chat.".into())
+            }
+            Ok(watches) => {
+                let mut output = String::new();
+                for watch in watches {
+                    output.push_str(&format!(
+                        "#{} {} | in {} | since {}\n",
+                        watch.id, watch.pattern, watch.root, watch.created_at
+                    ));
+                }
+                ToolResult::success(output)
+            }
+            Err(e) => ToolResult::error(format!("Failed to list watches: {e}")),
+        }
+    }
+}
+
+// --- unwatch_path ---
+
+pub struct UnwatchPathTool {
+    registry: Arc<ChannelRegistry>,
+    db: Arc<Database>,
+}
+
+impl UnwatchPathTool {
+    pub fn new(registry: Arc<ChannelRegistry>, db: Arc<Database>) -> Self {
+        UnwatchPathTool { registry, db }
+    }
+}
+
+#[async_trait]
+impl Tool for UnwatchPathTool {
+    fn name(&self) -> &str {
+        "unwatch_path"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "unwatch_path".into(),
+            description: "Stop a file watch.".into(),
+            input_schema: schema_object(
+                json!({
+                    "chat_id": {
+                        "type": "integer",
+                        "description": "The chat ID the watch notifies"
+                    },
+                    "watch_id": {
+                        "type": "integer",
+                        "description": "The watch ID from list_path_watches"
+                    }
+                }),
+                &["chat_id", "watch_id"],
+            ),
+        }
+    }
+
+    async fn execute(&self, input: serde_json::Value) -> ToolResult {
+        let chat_id = match authorized_chat_id(&self.registry, &self.db, &input).await {
+            Ok(id) => id,
+            Err(e) => return ToolResult::error(e),
+        };
+        let watch_id = match input.get("watch_id").and_then(|v| v.as_i64()) {
+            Some(id) => id,
+            None => return ToolResult::error("Missing required parameter: watch_id".into()),
+        };
+        match call_blocking(self.db.clone(), move |db| {
+            db.delete_path_watch(chat_id, watch_id)
+        })
+        .await
+        {
+            Ok(true) => ToolResult::success(format!("Watch #{watch_id} removed.")),
+            Ok(false) => ToolResult::error(format!("Watch #{watch_id} not found in this chat.")),
+            Err(e) => ToolResult::error(format!("Failed to remove watch: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_watch_list_and_unwatch() {
+        let dir = std::env::temp_dir().join(format!("microclaw_watcht_{}", uuid::Uuid::new_v4()));
+        let db = Arc::new(Database::new(dir.to_str().unwrap()).unwrap());
+        let registry = Arc::new(ChannelRegistry::new());
+        let workspace = dir.join("work");
+        let watch = WatchPathTool::new(
+            registry.clone(),
+            db.clone(),
+            workspace.to_str().unwrap(),
+            WorkingDirIsolation::Shared,
+            5,
+            1,
+        );
+        let list = ListPathWatchesTool::new(registry.clone(), db.clone());
+        let unwatch = UnwatchPathTool::new(registry.clone(), db.clone());
+
+        let result = watch
+            .execute(json!({"chat_id": 7, "pattern": "../etc/*"}))
+            .await;
+        assert!(result.is_error);
+        let result = watch
+            .execute(json!({"chat_id": 7, "pattern": "./dist/*.js"}))
+            .await;
+        assert!(!result.is_error, "{}", result.content);
+        let result = watch
+            .execute(json!({"chat_id": 7, "pattern": "dist/*.js"}))
+            .await;
+        assert!(result.content.starts_with("Already watching"));
+        let result = watch
+            .execute(json!({"chat_id": 7, "pattern": "logs"}))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("limit 1"));
+
+        let watches = db.list_path_watches(Some(7)).unwrap();
+        assert_eq!(watches.len(), 1);
+        let root = std::fs::canonicalize(workspace.join("shared")).unwrap();
+        assert_eq!(watches[0].root, root.to_string_lossy());
+        let id = watches[0].id;
+        let result = list.execute(json!({"chat_id": 7})).await;
+        assert!(result
+            .content
+            .starts_with(&format!("#{id} dist/*.js | in ")));
+
+        let denied = json!({
+            "chat_id": 7,
+            "watch_id": id,
+            "__microclaw_auth": {"caller_channel": "telegram", "caller_chat_id": 8, "control_chat_ids": []}
+        });
+        assert!(unwatch.execute(denied).await.is_error);
+        let result = unwatch.execute(json!({"chat_id": 7, "watch_id": id})).await;
+        assert!(!result.is_error, "{}", result.content);
+        let result = list.execute(json!({"chat_id": 7})).await;
+        assert!(result.content.contains("No file watches"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
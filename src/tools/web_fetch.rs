@@ -1,26 +1,24 @@
-use std::sync::OnceLock;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use serde_json::json;
 
 use super::web_html::{extract_primary_html, html_to_text};
-use super::{schema_object, Tool, ToolResult};
+use super::{auth_context_from_input, schema_object, Tool, ToolResult};
+use crate::config::NetworkPolicyConfig;
 use crate::llm_types::ToolDefinition;
-use crate::text::floor_char_boundary;
-
-fn http_client() -> &'static reqwest::Client {
-    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-    CLIENT.get_or_init(|| {
-        reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(15))
-            .redirect(reqwest::redirect::Policy::limited(5))
-            .user_agent("MicroClaw/1.0")
-            .build()
-            .expect("failed to build HTTP client")
-    })
+use crate::network_policy::guarded_get;
+
+#[derive(Default)]
+pub struct WebFetchTool {
+    network_policy: NetworkPolicyConfig,
 }
 
-pub struct WebFetchTool;
+impl WebFetchTool {
+    pub fn new(network_policy: NetworkPolicyConfig) -> Self {
+        WebFetchTool { network_policy }
+    }
+}
 
 #[async_trait]
 impl Tool for WebFetchTool {
@@ -32,7 +30,7 @@ impl Tool for WebFetchTool {
         ToolDefinition {
             name: "web_fetch".into(),
             description:
-                "Fetch a URL and return its text content (HTML parsed, scripts/styles removed). Max 20KB."
+                "Fetch a URL and return its text content (HTML parsed, scripts/styles removed). Long pages are cut to 20KB by default."
                     .into(),
             input_schema: schema_object(
                 json!({
@@ -52,19 +50,28 @@ impl Tool for WebFetchTool {
             None => return ToolResult::error("Missing required parameter: url".into()),
         };
 
-        match fetch_url(url).await {
+        let chat_id = auth_context_from_input(&input).map(|auth| auth.caller_chat_id);
+        match fetch_url(&self.network_policy, chat_id, url).await {
             Ok(text) => ToolResult::success(text),
             Err(e) => ToolResult::error(format!("Failed to fetch URL: {e}")),
         }
     }
 }
 
-async fn fetch_url(url: &str) -> Result<String, String> {
-    let resp = http_client()
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+async fn fetch_url(
+    policy: &NetworkPolicyConfig,
+    chat_id: Option<i64>,
+    url: &str,
+) -> Result<String, String> {
+    let resp = guarded_get(
+        policy,
+        chat_id,
+        "web_fetch",
+        url,
+        Duration::from_secs(15),
+        "MicroClaw/1.0",
+    )
+    .await?;
 
     if !resp.status().is_success() {
         return Err(format!("HTTP {}", resp.status()));
@@ -72,15 +79,8 @@ async fn fetch_url(url: &str) -> Result<String, String> {
 
     let body = resp.text().await.map_err(|e| e.to_string())?;
     let primary = extract_primary_html(&body);
-    let text = html_to_text(primary);
-
-    const MAX_BYTES: usize = 20_000;
-    if text.len() > MAX_BYTES {
-        let truncated = &text[..floor_char_boundary(&text, MAX_BYTES)];
-        Ok(format!("{truncated}\n\n[Truncated at 20KB]"))
-    } else {
-        Ok(text)
-    }
+    // Long pages are cut by the registry's output limit (`tool_output_limits`)
+    Ok(html_to_text(primary))
 }
 
 #[cfg(test)]
@@ -90,7 +90,7 @@ mod tests {
 
     #[test]
     fn test_web_fetch_definition() {
-        let tool = WebFetchTool;
+        let tool = WebFetchTool::default();
         assert_eq!(tool.name(), "web_fetch");
         let def = tool.definition();
         assert_eq!(def.name, "web_fetch");
@@ -102,7 +102,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_web_fetch_missing_url() {
-        let tool = WebFetchTool;
+        let tool = WebFetchTool::default();
         let result = tool.execute(json!({})).await;
         assert!(result.is_error);
         assert!(result.content.contains("Missing required parameter: url"));
@@ -110,7 +110,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_web_fetch_null_url() {
-        let tool = WebFetchTool;
+        let tool = WebFetchTool::default();
         let result = tool.execute(json!({"url": null})).await;
         assert!(result.is_error);
         assert!(result.content.contains("Missing required parameter: url"));
@@ -118,11 +118,21 @@ mod tests {
 
     #[tokio::test]
     async fn test_web_fetch_invalid_url() {
-        let tool = WebFetchTool;
+        let tool = WebFetchTool::default();
         let result = tool
             .execute(json!({"url": "https://this-domain-does-not-exist-12345.example"}))
             .await;
         assert!(result.is_error);
         assert!(result.content.contains("Failed to fetch URL"));
     }
+
+    #[tokio::test]
+    async fn test_web_fetch_blocks_private_targets() {
+        let tool = WebFetchTool::default();
+        let result = tool
+            .execute(json!({"url": "http://169.254.169.254/latest/meta-data/"}))
+            .await;
+        assert!(result.is_error);
+        assert!(result.content.contains("Blocked by network policy"));
+    }
 }
@@ -1,6 +1,93 @@
+//! Speech-to-text for incoming voice messages.
+//!
+//! Two backends: an OpenAI-compatible `/audio/transcriptions` endpoint
+//! (OpenAI Whisper by default, or a self-hosted server via
+//! `voice_transcription_base_url`), and a local command such as whisper.cpp
+//! that receives the audio file path and prints the transcript to stdout.
+
+use std::time::Duration;
+
 use reqwest::multipart;
 
-pub async fn transcribe_audio(api_key: &str, audio_bytes: &[u8]) -> Result<String, String> {
+use crate::config::Config;
+
+const DEFAULT_WHISPER_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_WHISPER_MODEL: &str = "whisper-1";
+const LOCAL_COMMAND_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Transcription backend resolved from config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transcriber {
+    Whisper {
+        base_url: String,
+        model: String,
+        api_key: Option<String>,
+    },
+    Command(String),
+}
+
+impl Transcriber {
+    /// Pick the backend from config. Returns `None` when transcription is off
+    /// or not configured.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let command = config.voice_transcription_command.clone();
+        match config.voice_transcription_provider.as_deref() {
+            Some("off") => None,
+            Some("local") => command.map(Transcriber::Command),
+            None if command.is_some() => command.map(Transcriber::Command),
+            _ => {
+                let api_key = config.openai_api_key.clone();
+                let base_url = config.voice_transcription_base_url.clone();
+                // The default endpoint needs a key; self-hosted servers may not.
+                if api_key.is_none() && base_url.is_none() {
+                    return None;
+                }
+                Some(Transcriber::Whisper {
+                    base_url: base_url
+                        .unwrap_or_else(|| DEFAULT_WHISPER_BASE_URL.to_string())
+                        .trim_end_matches('/')
+                        .to_string(),
+                    model: config
+                        .voice_transcription_model
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_WHISPER_MODEL.to_string()),
+                    api_key,
+                })
+            }
+        }
+    }
+
+    pub async fn transcribe(&self, audio_bytes: &[u8]) -> Result<String, String> {
+        let text = match self {
+            Transcriber::Whisper {
+                base_url,
+                model,
+                api_key,
+            } => transcribe_whisper(base_url, model, api_key.as_deref(), audio_bytes).await?,
+            Transcriber::Command(command) => transcribe_command(command, audio_bytes).await?,
+        };
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return Err("no speech recognized".into());
+        }
+        Ok(text)
+    }
+}
+
+/// Transcribe audio with the configured backend.
+pub async fn transcribe_audio(config: &Config, audio_bytes: &[u8]) -> Result<String, String> {
+    Transcriber::from_config(config)
+        .ok_or_else(|| "voice transcription is not configured".to_string())?
+        .transcribe(audio_bytes)
+        .await
+}
+
+async fn transcribe_whisper(
+    base_url: &str,
+    model: &str,
+    api_key: Option<&str>,
+    audio_bytes: &[u8],
+) -> Result<String, String> {
     let client = reqwest::Client::new();
 
     let part = multipart::Part::bytes(audio_bytes.to_vec())
@@ -9,13 +96,16 @@ pub async fn transcribe_audio(api_key: &str, audio_bytes: &[u8]) -> Result<Strin
         .map_err(|e| e.to_string())?;
 
     let form = multipart::Form::new()
-        .text("model", "whisper-1")
+        .text("model", model.to_string())
         .part("file", part);
 
-    let resp = client
-        .post("https://api.openai.com/v1/audio/transcriptions")
-        .header("Authorization", format!("Bearer {api_key}"))
-        .multipart(form)
+    let mut req = client
+        .post(format!("{base_url}/audio/transcriptions"))
+        .multipart(form);
+    if let Some(key) = api_key {
+        req = req.header("Authorization", format!("Bearer {key}"));
+    }
+    let resp = req
         .send()
         .await
         .map_err(|e| format!("Whisper API request failed: {e}"))?;
@@ -37,10 +127,120 @@ pub async fn transcribe_audio(api_key: &str, audio_bytes: &[u8]) -> Result<Strin
         .ok_or_else(|| "Whisper response missing 'text' field".into())
 }
 
+/// Substitute `{file}` with the shell-quoted audio path. Commands without the
+/// placeholder get the path appended as the last argument.
+fn build_command(template: &str, path: &str) -> String {
+    let quoted = format!("'{}'", path.replace('\'', r"'\''"));
+    if template.contains("{file}") {
+        template.replace("{file}", &quoted)
+    } else {
+        format!("{template} {quoted}")
+    }
+}
+
+async fn transcribe_command(template: &str, audio_bytes: &[u8]) -> Result<String, String> {
+    let path = std::env::temp_dir().join(format!("microclaw_voice_{}.ogg", uuid::Uuid::new_v4()));
+    tokio::fs::write(&path, audio_bytes)
+        .await
+        .map_err(|e| format!("Failed to write audio file: {e}"))?;
+    let command = build_command(template, &path.to_string_lossy());
+    let output = tokio::time::timeout(
+        LOCAL_COMMAND_TIMEOUT,
+        tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+    let _ = tokio::fs::remove_file(&path).await;
+
+    let output = output
+        .map_err(|_| "transcription command timed out".to_string())?
+        .map_err(|e| format!("Failed to run transcription command: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "transcription command exited with {}: {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        serde_yaml::from_str("{}").unwrap()
+    }
+
     #[test]
-    fn test_transcribe_module_exists() {
-        // Basic smoke test that the module compiles
+    fn test_transcriber_from_config() {
+        let mut cfg = config();
+        assert_eq!(Transcriber::from_config(&cfg), None);
+
+        cfg.openai_api_key = Some("sk-test".into());
+        assert_eq!(
+            Transcriber::from_config(&cfg),
+            Some(Transcriber::Whisper {
+                base_url: "https://api.openai.com/v1".into(),
+                model: "whisper-1".into(),
+                api_key: Some("sk-test".into()),
+            })
+        );
+
+        cfg.voice_transcription_command = Some("whisper-cli -f {file}".into());
+        assert_eq!(
+            Transcriber::from_config(&cfg),
+            Some(Transcriber::Command("whisper-cli -f {file}".into()))
+        );
+        cfg.voice_transcription_provider = Some("openai".into());
+        assert!(matches!(
+            Transcriber::from_config(&cfg),
+            Some(Transcriber::Whisper { .. })
+        ));
+        cfg.voice_transcription_provider = Some("off".into());
+        assert_eq!(Transcriber::from_config(&cfg), None);
+
+        let mut local = config();
+        local.voice_transcription_base_url = Some("http://127.0.0.1:8000/v1/".into());
+        assert_eq!(
+            Transcriber::from_config(&local),
+            Some(Transcriber::Whisper {
+                base_url: "http://127.0.0.1:8000/v1".into(),
+                model: "whisper-1".into(),
+                api_key: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_command_quotes_path() {
+        assert_eq!(
+            build_command("whisper -f {file} -nt", "/tmp/a b.ogg"),
+            "whisper -f '/tmp/a b.ogg' -nt"
+        );
+        assert_eq!(
+            build_command("stt", "/tmp/it's.ogg"),
+            r"stt '/tmp/it'\''s.ogg'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_transcriber_reads_stdout() {
+        let stt = Transcriber::Command("printf '  hello from %s\\n' \"$(cat {file})\"".into());
+        assert_eq!(stt.transcribe(b"ogg").await.unwrap(), "hello from ogg");
+
+        let silent = Transcriber::Command("true".into());
+        assert!(silent.transcribe(b"ogg").await.is_err());
+        let failing = Transcriber::Command("echo boom >&2; false".into());
+        assert!(failing
+            .transcribe(b"ogg")
+            .await
+            .unwrap_err()
+            .contains("boom"));
     }
 }
@@ -0,0 +1,223 @@
+//! Per-chat ordering of agent turns and a global cap on turns in flight
+//! (`turn_queue`).
+//!
+//! A turn holds its chat's slot from before it loads the session until it
+//! has saved it, so two turns never build on the same session state. A
+//! message arriving meanwhile waits for the running turn and then reads
+//! everything that came in since that turn loaded its history. With
+//! `turn_queue.merge`, a message arriving while another one is already
+//! waiting is left to that waiting turn instead of getting a turn of its
+//! own. `turn_queue.max_concurrent` bounds the turns running across chats.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tokio::sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::TurnQueueConfig;
+
+#[derive(Default)]
+struct ChatQueue {
+    /// Newest message in the chat when the last turn loaded its history.
+    read_through: Arc<tokio::sync::Mutex<Option<String>>>,
+    /// Turns running or waiting in this chat.
+    turns: usize,
+    /// Mergeable turns still waiting to start.
+    waiting_mergeable: usize,
+}
+
+type QueueMap = HashMap<i64, ChatQueue>;
+
+fn chat_queues() -> &'static Mutex<QueueMap> {
+    static QUEUES: OnceLock<Mutex<QueueMap>> = OnceLock::new();
+    QUEUES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Process-wide turn slots, sized by the first config that asks.
+fn turn_slots(config: &TurnQueueConfig) -> Arc<Semaphore> {
+    static SLOTS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    SLOTS
+        .get_or_init(|| Arc::new(Semaphore::new(config.max_concurrent.max(1))))
+        .clone()
+}
+
+/// A turn counted in its chat's queue; dropping it leaves the queue.
+struct Enrolled {
+    chat_id: i64,
+}
+
+impl Drop for Enrolled {
+    fn drop(&mut self) {
+        if let Ok(mut queues) = chat_queues().lock() {
+            if let Some(queue) = queues.get_mut(&self.chat_id) {
+                queue.turns = queue.turns.saturating_sub(1);
+                // An idle chat starts over from its saved session.
+                if queue.turns == 0 {
+                    queues.remove(&self.chat_id);
+                }
+            }
+        }
+    }
+}
+
+/// A mergeable turn that has not started yet.
+struct Waiting {
+    chat_id: i64,
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if let Ok(mut queues) = chat_queues().lock() {
+            if let Some(queue) = queues.get_mut(&self.chat_id) {
+                queue.waiting_mergeable = queue.waiting_mergeable.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// The right to run a turn in a chat. Held until the turn is done.
+pub struct TurnPermit {
+    read_through: OwnedMutexGuard<Option<String>>,
+    _slot: OwnedSemaphorePermit,
+    waited: bool,
+    _enrolled: Enrolled,
+}
+
+impl TurnPermit {
+    /// Whether the turn had to wait for another one in the same chat.
+    pub fn waited(&self) -> bool {
+        self.waited
+    }
+
+    /// Start of the window of new user messages for a session saved at
+    /// `updated_at`. Messages that arrived while the previous turn ran are
+    /// older than its save, so a queued turn reads from where that turn's
+    /// history ended instead.
+    pub fn messages_since(&self, updated_at: &str) -> String {
+        match self.read_through.as_deref() {
+            Some(read) if read < updated_at => read.to_string(),
+            _ => updated_at.to_string(),
+        }
+    }
+
+    /// Record the newest message visible when this turn loaded its history.
+    pub fn set_read_through(&mut self, timestamp: Option<String>) {
+        *self.read_through = timestamp;
+    }
+}
+
+pub enum Admission {
+    Run(TurnPermit),
+    /// Another waiting turn in the chat will answer this message.
+    Merged,
+}
+
+/// Wait until `chat_id` has no turn running and a global slot is free.
+/// `mergeable` turns answer the chat's stored messages (not a scheduled
+/// prompt or an attached image), so they can be merged under
+/// `turn_queue.merge`.
+pub async fn admit(config: &TurnQueueConfig, chat_id: i64, mergeable: bool) -> Admission {
+    let (lock, waited) = {
+        let mut queues = chat_queues().lock().unwrap_or_else(|e| e.into_inner());
+        let queue = queues.entry(chat_id).or_default();
+        if config.merge && mergeable && queue.waiting_mergeable > 0 {
+            return Admission::Merged;
+        }
+        let waited = queue.turns > 0;
+        queue.turns += 1;
+        if mergeable {
+            queue.waiting_mergeable += 1;
+        }
+        (queue.read_through.clone(), waited)
+    };
+    let enrolled = Enrolled { chat_id };
+    let waiting = mergeable.then_some(Waiting { chat_id });
+
+    let read_through = lock.lock_owned().await;
+    let slot = turn_slots(config)
+        .acquire_owned()
+        .await
+        .expect("turn slots are never closed");
+    drop(waiting);
+    Admission::Run(TurnPermit {
+        read_through,
+        _slot: slot,
+        waited,
+        _enrolled: enrolled,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waiting_mergeable(chat_id: i64) -> usize {
+        chat_queues()
+            .lock()
+            .unwrap()
+            .get(&chat_id)
+            .map_or(0, |q| q.waiting_mergeable)
+    }
+
+    #[tokio::test]
+    async fn test_turns_queue_per_chat_and_merge() {
+        let config = TurnQueueConfig {
+            max_concurrent: 8,
+            merge: true,
+        };
+        let chat_id = -4_887_001;
+        let Admission::Run(mut first) = admit(&config, chat_id, true).await else {
+            panic!("first turn should run");
+        };
+        assert!(!first.waited());
+        first.set_read_through(Some("2026-10-15T10:00:00+00:00".into()));
+
+        let queued_config = config.clone();
+        let second = tokio::spawn(async move {
+            match admit(&queued_config, chat_id, true).await {
+                Admission::Run(permit) => permit,
+                Admission::Merged => panic!("second turn should queue"),
+            }
+        });
+        for _ in 0..100 {
+            if waiting_mergeable(chat_id) > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(waiting_mergeable(chat_id), 1);
+        // A third message is left to the waiting turn.
+        assert!(matches!(
+            admit(&config, chat_id, true).await,
+            Admission::Merged
+        ));
+        assert!(!second.is_finished());
+
+        drop(first);
+        let second = tokio::time::timeout(std::time::Duration::from_secs(2), second)
+            .await
+            .expect("queued turn did not start")
+            .unwrap();
+        assert!(second.waited());
+        assert_eq!(waiting_mergeable(chat_id), 0);
+        assert_eq!(
+            second.messages_since("2026-10-15T10:00:07+00:00"),
+            "2026-10-15T10:00:00+00:00"
+        );
+        assert_eq!(
+            second.messages_since("2026-10-15T09:00:00+00:00"),
+            "2026-10-15T09:00:00+00:00"
+        );
+
+        drop(second);
+        assert!(!chat_queues().lock().unwrap().contains_key(&chat_id));
+        let Admission::Run(third) = admit(&config, chat_id, true).await else {
+            panic!("idle chat should run");
+        };
+        assert!(!third.waited());
+        assert_eq!(
+            third.messages_since("2026-10-15T11:00:00+00:00"),
+            "2026-10-15T11:00:00+00:00"
+        );
+    }
+}
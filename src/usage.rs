@@ -1,11 +1,22 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use chrono::SecondsFormat;
+use chrono::{NaiveDate, SecondsFormat};
+use tracing::warn;
 
 use crate::config::Config;
 use crate::db::{
-    call_blocking, Database, LlmModelUsageSummary, LlmUsageSummary, MemoryObservabilitySummary,
+    call_blocking, Database, LlmDailyUsage, LlmModelUsageSummary, LlmUsageSummary,
+    LlmUserUsageSummary, MemoryObservabilitySummary,
 };
+use crate::i18n::{self, t, tf, Language, Msg};
+use crate::runtime::AppState;
+
+/// Days covered by the usage charts.
+const CHART_DAYS: i64 = 30;
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Size of one panel of the PNG chart; the cost panel stacks below.
+const PNG_PANEL_SIZE: (u32, u32) = (900, 240);
 
 fn fmt_int(v: i64) -> String {
     let neg = v < 0;
@@ -37,9 +48,13 @@ fn fmt_summary_line(name: &str, s: &LlmUsageSummary) -> String {
     )
 }
 
-fn format_model_rows(rows: &[LlmModelUsageSummary], max_rows: usize) -> Vec<String> {
+fn format_model_rows(
+    lang: Language,
+    rows: &[LlmModelUsageSummary],
+    max_rows: usize,
+) -> Vec<String> {
     if rows.is_empty() {
-        return vec!["    - (no data)".to_string()];
+        return vec![format!("    - {}", t(lang, Msg::UsageNoData))];
     }
 
     rows.iter()
@@ -59,7 +74,139 @@ fn format_model_rows(rows: &[LlmModelUsageSummary], max_rows: usize) -> Vec<Stri
         .collect()
 }
 
+fn format_user_rows(rows: &[LlmUserUsageSummary]) -> Vec<String> {
+    let total: i64 = rows.iter().map(|r| r.total_tokens).sum();
+    rows.iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            let share = if total > 0 {
+                row.total_tokens as f64 * 100.0 / total as f64
+            } else {
+                0.0
+            };
+            format!(
+                "    {}. {}  tok={} ({share:.0}%)  req={}",
+                idx + 1,
+                row.user_id,
+                fmt_int(row.total_tokens),
+                fmt_int(row.requests)
+            )
+        })
+        .collect()
+}
+
+/// One bar per value, scaled to the largest.
+fn sparkline(values: &[f64]) -> String {
+    let max = values.iter().cloned().fold(0.0_f64, f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max <= 0.0 {
+                return SPARK_BARS[0];
+            }
+            let idx = ((v / max) * (SPARK_BARS.len() - 1) as f64).round() as usize;
+            SPARK_BARS[idx.min(SPARK_BARS.len() - 1)]
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Default)]
+struct DailyPoint {
+    tokens: i64,
+    usd: f64,
+}
+
+/// Tokens and estimated cost for each of the `CHART_DAYS` days ending
+/// `today`, oldest first; days without usage are zero.
+fn daily_points(rows: &[LlmDailyUsage], config: &Config, today: NaiveDate) -> Vec<DailyPoint> {
+    let first = today - chrono::Duration::days(CHART_DAYS - 1);
+    let mut points = vec![DailyPoint::default(); CHART_DAYS as usize];
+    for row in rows {
+        let Ok(day) = NaiveDate::parse_from_str(&row.day, "%Y-%m-%d") else {
+            continue;
+        };
+        let Some(point) = usize::try_from((day - first).num_days())
+            .ok()
+            .and_then(|idx| points.get_mut(idx))
+        else {
+            continue;
+        };
+        point.tokens += row.total_tokens;
+        point.usd += config
+            .estimate_cost_usd(&row.model, row.input_tokens, row.output_tokens)
+            .unwrap_or(0.0);
+    }
+    points
+}
+
+fn chart_lines(label: &str, points: &[DailyPoint], priced: bool) -> Vec<String> {
+    let tokens: Vec<f64> = points.iter().map(|p| p.tokens as f64).collect();
+    let total: i64 = points.iter().map(|p| p.tokens).sum();
+    let peak = points.iter().map(|p| p.tokens).max().unwrap_or(0);
+    let mut lines = vec![format!(
+        "  {label:<10} tok  {}  total {}  peak {}/day",
+        sparkline(&tokens),
+        fmt_int(total),
+        fmt_int(peak)
+    )];
+    if priced {
+        let usd: Vec<f64> = points.iter().map(|p| p.usd).collect();
+        lines.push(format!(
+            "  {:<10} cost {}  total ${:.2}",
+            "",
+            sparkline(&usd),
+            usd.iter().sum::<f64>()
+        ));
+    }
+    lines
+}
+
+/// Draw per-day bars for all chats with this chat in front: tokens, and
+/// cost below when `priced`. No text is drawn, so no fonts are needed; the
+/// legend goes in the message caption.
+fn render_chart_png(
+    path: &Path,
+    chat: &[DailyPoint],
+    global: &[DailyPoint],
+    priced: bool,
+) -> Result<(), String> {
+    use plotters::prelude::*;
+
+    let panels = if priced { 2 } else { 1 };
+    let size = (PNG_PANEL_SIZE.0, PNG_PANEL_SIZE.1 * panels);
+    let root = BitMapBackend::new(path, size).into_drawing_area();
+    root.fill(&WHITE).map_err(|e| e.to_string())?;
+    let areas = root.split_evenly((panels as usize, 1));
+    let metrics: [fn(&DailyPoint) -> f64; 2] = [|p| p.tokens as f64, |p| p.usd];
+    for (area, metric) in areas.iter().zip(metrics) {
+        let max = global.iter().chain(chat).map(metric).fold(0.0, f64::max);
+        let mut chart = ChartBuilder::on(area)
+            .margin(12)
+            .build_cartesian_2d(0.0..CHART_DAYS as f64, 0.0..max.max(1e-9) * 1.05)
+            .map_err(|e| e.to_string())?;
+        for (points, color) in [
+            (global, RGBColor(190, 200, 220)),
+            (chat, RGBColor(40, 90, 170)),
+        ] {
+            chart
+                .draw_series(points.iter().enumerate().map(|(i, p)| {
+                    let x = i as f64;
+                    Rectangle::new([(x + 0.15, 0.0), (x + 0.85, metric(p))], color.filled())
+                }))
+                .map_err(|e| e.to_string())?;
+        }
+        let baseline = PNG_PANEL_SIZE.1 as i32 - 12;
+        area.draw(&PathElement::new(
+            [(0, baseline), (PNG_PANEL_SIZE.0 as i32, baseline)],
+            BLACK,
+        ))
+        .map_err(|e| e.to_string())?;
+    }
+    root.present().map_err(|e| e.to_string())
+}
+
 fn block_lines(
+    lang: Language,
     title: &str,
     all: &LlmUsageSummary,
     d24: &LlmUsageSummary,
@@ -70,16 +217,16 @@ fn block_lines(
     let mut lines = vec![
         title.to_string(),
         "".to_string(),
-        format!("  🧮 {}", fmt_summary_line("All-time", all)),
-        format!("  🕓 {}", fmt_summary_line("Last 24h", d24)),
-        format!("  📆 {}", fmt_summary_line("Last 7d", d7)),
+        format!("  🧮 {}", fmt_summary_line(t(lang, Msg::UsageAllTime), all)),
+        format!("  🕓 {}", fmt_summary_line(t(lang, Msg::UsageLast24h), d24)),
+        format!("  📆 {}", fmt_summary_line(t(lang, Msg::UsageLast7d), d7)),
         "".to_string(),
-        "  🤖 Top models (24h)".to_string(),
+        format!("  {}", t(lang, Msg::UsageTopModels24h)),
     ];
-    lines.extend(format_model_rows(models_24h, 4));
+    lines.extend(format_model_rows(lang, models_24h, 4));
     lines.push("".to_string());
-    lines.push("  🤖 Top models (7d)".to_string());
-    lines.extend(format_model_rows(models_7d, 4));
+    lines.push(format!("  {}", t(lang, Msg::UsageTopModels7d)));
+    lines.extend(format_model_rows(lang, models_7d, 4));
 
     lines
 }
@@ -108,6 +255,18 @@ async fn query_by_model(
     .map_err(|e| e.to_string())
 }
 
+async fn query_identity_summary(
+    db: Arc<Database>,
+    identity_chat_id: i64,
+    since: Option<String>,
+) -> Result<LlmUsageSummary, String> {
+    call_blocking(db, move |d| {
+        d.get_identity_usage_summary_since(identity_chat_id, since.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
 async fn query_memory_summary(
     db: Arc<Database>,
     chat_id: Option<i64>,
@@ -117,9 +276,80 @@ async fn query_memory_summary(
         .map_err(|e| e.to_string())
 }
 
+/// Daily points for this chat and for all chats over the chart window.
+async fn query_daily_points(
+    db: Arc<Database>,
+    config: &Config,
+    chat_id: i64,
+) -> Result<(Vec<DailyPoint>, Vec<DailyPoint>), String> {
+    let today = chrono::Utc::now().date_naive();
+    let since_chart = (today - chrono::Duration::days(CHART_DAYS - 1))
+        .format("%Y-%m-%d")
+        .to_string();
+    let since = since_chart.clone();
+    let chat_days = call_blocking(db.clone(), move |d| {
+        d.get_llm_usage_by_day(Some(chat_id), &since)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let global_days = call_blocking(db, move |d| d.get_llm_usage_by_day(None, &since_chart))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((
+        daily_points(&chat_days, config, today),
+        daily_points(&global_days, config, today),
+    ))
+}
+
+/// Send the `/usage` trend charts as a PNG on channels that take
+/// attachments. Failures are logged; the text report already has sparklines.
+pub async fn send_usage_chart(state: &AppState, caller_channel: &str, chat_id: i64) {
+    let Some(adapter) = state.channel_registry.get(caller_channel) else {
+        return;
+    };
+    let result = async {
+        let (chat, global) = query_daily_points(state.db.clone(), &state.config, chat_id).await?;
+        let priced = state.config.has_pricing();
+        let dir = PathBuf::from(state.config.runtime_data_dir()).join("usage_charts");
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let path = dir.join(format!("usage-{chat_id}-{}.png", uuid::Uuid::new_v4()));
+        let rendered = render_chart_png(&path, &chat, &global, priced);
+        let sent = match rendered {
+            Ok(()) => {
+                let external =
+                    call_blocking(state.db.clone(), move |db| db.get_chat_external_id(chat_id))
+                        .await
+                        .map_err(|e| e.to_string())?
+                        .unwrap_or_else(|| chat_id.to_string());
+                let lang = i18n::chat_language(state, chat_id).await;
+                let caption = tf(
+                    lang,
+                    if priced {
+                        Msg::UsageChartCaptionPriced
+                    } else {
+                        Msg::UsageChartCaption
+                    },
+                    &[("days", &CHART_DAYS)],
+                );
+                adapter
+                    .send_attachment(&external, &path, Some(&caption))
+                    .await
+                    .map(|_| ())
+            }
+            Err(e) => Err(e),
+        };
+        let _ = std::fs::remove_file(&path);
+        sent
+    }
+    .await;
+    if let Err(e) = result {
+        warn!("Usage chart for chat {chat_id} on {caller_channel} failed: {e}");
+    }
+}
+
 pub async fn build_usage_report(
     db: Arc<Database>,
-    _config: &Config,
+    config: &Config,
     chat_id: i64,
 ) -> Result<String, String> {
     let now = chrono::Utc::now();
@@ -159,18 +389,21 @@ pub async fn build_usage_report(
     .await?;
     let chat_mem = query_memory_summary(db.clone(), Some(chat_id)).await?;
     let global_mem = query_memory_summary(db.clone(), None).await?;
+    let lang = i18n::language_for_chat(db.clone(), config, chat_id).await;
 
     let mut lines = vec![
-        "📊 Token Usage".to_string(),
-        format!(
-            "🕒 Updated: {}",
-            now.to_rfc3339_opts(SecondsFormat::Secs, true)
+        t(lang, Msg::UsageTitle).to_string(),
+        tf(
+            lang,
+            Msg::UsageUpdated,
+            &[("time", &now.to_rfc3339_opts(SecondsFormat::Secs, true))],
         ),
         "".to_string(),
     ];
 
     lines.extend(block_lines(
-        "🔹 This chat",
+        lang,
+        t(lang, Msg::UsageThisChat),
         &chat_all,
         &chat_24h,
         &chat_7d,
@@ -178,10 +411,86 @@ pub async fn build_usage_report(
         &chat_models_7d,
     ));
 
+    // Who triggered the chat's agent turns, for group chats.
+    let since_7d = (now - chrono::Duration::days(7)).to_rfc3339();
+    let users_7d = call_blocking(db.clone(), move |d| {
+        d.get_llm_usage_by_user(chat_id, Some(&since_7d), 10)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if users_7d.len() > 1 {
+        lines.push("".to_string());
+        lines.push(format!("  {}", t(lang, Msg::UsageByUser7d)));
+        lines.extend(format_user_rows(&users_7d));
+    }
+
+    // Totals across every chat linked to this person with /link.
+    let identity = crate::identity::identity_chat_id(db.clone(), chat_id).await;
+    let linked_chats = call_blocking(db.clone(), move |d| d.get_identity_chats(identity))
+        .await
+        .map(|chats| chats.len())
+        .unwrap_or(0);
+    if linked_chats > 1 {
+        let you_all = query_identity_summary(db.clone(), identity, None).await?;
+        let you_24h = query_identity_summary(
+            db.clone(),
+            identity,
+            Some((now - chrono::Duration::hours(24)).to_rfc3339()),
+        )
+        .await?;
+        let you_7d = query_identity_summary(
+            db.clone(),
+            identity,
+            Some((now - chrono::Duration::days(7)).to_rfc3339()),
+        )
+        .await?;
+        lines.push("".to_string());
+        lines.push(tf(lang, Msg::UsageLinked, &[("count", &linked_chats)]));
+        lines.push("".to_string());
+        lines.push(format!(
+            "  🧮 {}",
+            fmt_summary_line(t(lang, Msg::UsageAllTime), &you_all)
+        ));
+        lines.push(format!(
+            "  🕓 {}",
+            fmt_summary_line(t(lang, Msg::UsageLast24h), &you_24h)
+        ));
+        lines.push(format!(
+            "  📆 {}",
+            fmt_summary_line(t(lang, Msg::UsageLast7d), &you_7d)
+        ));
+    }
+
+    // Decisions of the small/large model router, logged by crate::router.
+    let since_7d = (now - chrono::Duration::days(7)).to_rfc3339();
+    let routed = call_blocking(db.clone(), move |d| {
+        d.count_llm_usage_by_kind(Some(chat_id), "router_", Some(&since_7d))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if !routed.is_empty() {
+        let count = |kind: &str| {
+            routed
+                .iter()
+                .find(|(k, _)| k == kind)
+                .map_or(0, |(_, n)| *n)
+        };
+        lines.push("".to_string());
+        lines.push(tf(
+            lang,
+            Msg::UsageRouter,
+            &[
+                ("small", &fmt_int(count("router_small"))),
+                ("large", &fmt_int(count("router_large"))),
+            ],
+        ));
+    }
+
     lines.push("".to_string());
 
     lines.extend(block_lines(
-        "🌍 Global",
+        lang,
+        t(lang, Msg::UsageGlobal),
         &global_all,
         &global_24h,
         &global_7d,
@@ -189,11 +498,29 @@ pub async fn build_usage_report(
         &global_models_7d,
     ));
 
+    // Per-day trend charts.
+    let (chat_days, global_days) = query_daily_points(db.clone(), config, chat_id).await?;
+    let priced = config.has_pricing();
+    lines.push("".to_string());
+    lines.push(tf(lang, Msg::UsageChartTitle, &[("days", &CHART_DAYS)]));
     lines.push("".to_string());
-    lines.push("🧠 Memory Observability".to_string());
+    lines.extend(chart_lines(
+        t(lang, Msg::UsageLabelThisChat),
+        &chat_days,
+        priced,
+    ));
+    lines.extend(chart_lines(
+        t(lang, Msg::UsageLabelGlobal),
+        &global_days,
+        priced,
+    ));
+
+    lines.push("".to_string());
+    lines.push(t(lang, Msg::UsageMemoryTitle).to_string());
     lines.push("".to_string());
     lines.push(format!(
-        "  This chat: total={} active={} archived={} avg_conf={:.2} low_conf={}",
+        "  {}: total={} active={} archived={} avg_conf={:.2} low_conf={}",
+        t(lang, Msg::UsageLabelThisChat),
         fmt_int(chat_mem.total),
         fmt_int(chat_mem.active),
         fmt_int(chat_mem.archived),
@@ -215,7 +542,8 @@ pub async fn build_usage_report(
     ));
     lines.push("".to_string());
     lines.push(format!(
-        "  Global: total={} active={} archived={} avg_conf={:.2} low_conf={}",
+        "  {}: total={} active={} archived={} avg_conf={:.2} low_conf={}",
+        t(lang, Msg::UsageLabelGlobal),
         fmt_int(global_mem.total),
         fmt_int(global_mem.active),
         fmt_int(global_mem.archived),
@@ -238,3 +566,64 @@ pub async fn build_usage_report(
 
     Ok(lines.join("\n"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0.0, 0.0]), "▁▁");
+        assert_eq!(sparkline(&[0.0, 1.0, 2.0, 7.0]), "▁▂▃█");
+    }
+
+    #[test]
+    fn test_render_chart_png() {
+        let dir = std::env::temp_dir().join(format!("mc_usage_chart_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("usage.png");
+        let points: Vec<DailyPoint> = (0..CHART_DAYS)
+            .map(|i| DailyPoint {
+                tokens: i * 100,
+                usd: i as f64 / 10.0,
+            })
+            .collect();
+        render_chart_png(&path, &points[..10], &points, true).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+        // No usage at all still renders.
+        let empty = vec![DailyPoint::default(); CHART_DAYS as usize];
+        render_chart_png(&path, &empty, &empty, false).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_daily_points_fill_gaps_and_price() {
+        let config: Config = serde_yaml::from_str(
+            "model_prices:\n  - model: m\n    input_per_million_usd: 1.0\n    output_per_million_usd: 2.0\n",
+        )
+        .unwrap();
+        let row = |day: &str, tokens: i64| LlmDailyUsage {
+            day: day.to_string(),
+            model: "m".to_string(),
+            input_tokens: tokens,
+            output_tokens: tokens,
+            total_tokens: tokens * 2,
+        };
+        let today = NaiveDate::from_ymd_opt(2026, 3, 31).unwrap();
+        let points = daily_points(
+            &[
+                row("2026-03-02", 500_000),
+                row("2026-03-31", 10),
+                row("2026-03-01", 1),
+            ],
+            &config,
+            today,
+        );
+        assert_eq!(points.len(), CHART_DAYS as usize);
+        assert_eq!(points[0].tokens, 1_000_000);
+        assert!((points[0].usd - 1.5).abs() < 1e-9);
+        assert_eq!(points[1].tokens, 0);
+        assert_eq!(points[29].tokens, 20);
+    }
+}
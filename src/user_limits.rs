@@ -0,0 +1,312 @@
+//! Per-user rate limits and muting (`user_limits`).
+//!
+//! Every message is checked against its sender's limits before the agent
+//! loop starts. Going over `messages_per_minute` or `messages_per_hour`
+//! starts a cooldown of `cooldown_secs`, and a sender whose requests made
+//! `mute_after_tool_errors` tool calls fail within `tool_error_window_secs`
+//! is muted for `mute_secs`. Messages sent meanwhile are dropped; the sender
+//! is told once per cooldown or mute. Senders are keyed by channel and user
+//! id, the state lives in memory, and control chats and scheduled runs are
+//! exempt.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::agent_engine::AgentRequestContext;
+use crate::config::UserLimitsConfig;
+use crate::i18n::{self, Msg};
+use crate::runtime::AppState;
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Reason {
+    Cooldown,
+    Muted,
+}
+
+impl Reason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Reason::Cooldown => "cooldown",
+            Reason::Muted => "muted",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    Allow,
+    /// `notify` is set for the first refused message of a cooldown or mute.
+    Refuse {
+        reason: Reason,
+        remaining: Duration,
+        notify: bool,
+    },
+}
+
+#[derive(Default)]
+struct UserState {
+    messages: VecDeque<Instant>,
+    tool_errors: VecDeque<Instant>,
+    blocked: Option<(Reason, Instant)>,
+    notified: bool,
+}
+
+impl UserState {
+    fn prune(&mut self, config: &UserLimitsConfig, now: Instant) {
+        let window = if config.messages_per_hour > 0 {
+            HOUR
+        } else {
+            MINUTE
+        };
+        while self
+            .messages
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= window)
+        {
+            self.messages.pop_front();
+        }
+        let error_window = Duration::from_secs(config.tool_error_window_secs);
+        while self
+            .tool_errors
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= error_window)
+        {
+            self.tool_errors.pop_front();
+        }
+        if self.blocked.is_some_and(|(_, until)| now >= until) {
+            self.blocked = None;
+            self.notified = false;
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.messages.is_empty() && self.tool_errors.is_empty() && self.blocked.is_none()
+    }
+
+    fn block(&mut self, reason: Reason, until: Instant) {
+        // A mute is never shortened by a cooldown, or the other way round.
+        if self.blocked.is_none_or(|(_, current)| until > current) {
+            self.blocked = Some((reason, until));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Limiter {
+    users: HashMap<String, UserState>,
+}
+
+impl Limiter {
+    fn check(&mut self, config: &UserLimitsConfig, user: &str, now: Instant) -> Verdict {
+        if !self.users.contains_key(user) {
+            self.users.retain(|_, state| {
+                state.prune(config, now);
+                !state.is_idle()
+            });
+        }
+        let state = self.users.entry(user.to_string()).or_default();
+        state.prune(config, now);
+        if let Some((reason, until)) = state.blocked {
+            let notify = !state.notified;
+            state.notified = true;
+            return Verdict::Refuse {
+                reason,
+                remaining: until - now,
+                notify,
+            };
+        }
+        let in_last = |window: Duration| {
+            state
+                .messages
+                .iter()
+                .filter(|t| now.duration_since(**t) < window)
+                .count()
+        };
+        let over_minute = config.messages_per_minute > 0
+            && in_last(MINUTE) >= config.messages_per_minute as usize;
+        let over_hour =
+            config.messages_per_hour > 0 && in_last(HOUR) >= config.messages_per_hour as usize;
+        if over_minute || over_hour {
+            let cooldown = Duration::from_secs(config.cooldown_secs);
+            state.block(Reason::Cooldown, now + cooldown);
+            state.notified = true;
+            return Verdict::Refuse {
+                reason: Reason::Cooldown,
+                remaining: cooldown,
+                notify: true,
+            };
+        }
+        if config.messages_per_minute > 0 || config.messages_per_hour > 0 {
+            state.messages.push_back(now);
+        }
+        Verdict::Allow
+    }
+
+    /// Count a failed tool call; true when it mutes the user.
+    fn record_tool_error(&mut self, config: &UserLimitsConfig, user: &str, now: Instant) -> bool {
+        let state = self.users.entry(user.to_string()).or_default();
+        state.prune(config, now);
+        state.tool_errors.push_back(now);
+        if state.tool_errors.len() < config.mute_after_tool_errors as usize {
+            return false;
+        }
+        state.tool_errors.clear();
+        state.block(Reason::Muted, now + Duration::from_secs(config.mute_secs));
+        state.notified = false;
+        true
+    }
+}
+
+fn limiter() -> std::sync::MutexGuard<'static, Limiter> {
+    static LIMITER: OnceLock<Mutex<Limiter>> = OnceLock::new();
+    LIMITER
+        .get_or_init(|| Mutex::new(Limiter::default()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// The sender's key, or `None` when the turn is not limited.
+fn user_key(state: &AppState, context: &AgentRequestContext<'_>) -> Option<String> {
+    if state.config.control_chat_ids.contains(&context.chat_id) {
+        return None;
+    }
+    let user = context.sender_id.or(context.sender)?;
+    Some(format!("{}:{user}", context.caller_channel))
+}
+
+fn minutes(remaining: Duration) -> String {
+    remaining.as_secs().div_ceil(60).max(1).to_string()
+}
+
+/// Check the sender of a message against `user_limits`. `Some(reply)` means
+/// the message is dropped; the reply is empty after the first notice.
+pub async fn check_user_limits(
+    state: &AppState,
+    context: &AgentRequestContext<'_>,
+) -> Option<String> {
+    let config = &state.config.user_limits;
+    if config.messages_per_minute == 0
+        && config.messages_per_hour == 0
+        && config.mute_after_tool_errors == 0
+    {
+        return None;
+    }
+    let user = user_key(state, context)?;
+    let Verdict::Refuse {
+        reason,
+        remaining,
+        notify,
+    } = limiter().check(config, &user, Instant::now())
+    else {
+        return None;
+    };
+    crate::metrics::user_limit_refusal(reason.as_str());
+    if !notify {
+        return Some(String::new());
+    }
+    tracing::info!(
+        "User {user} is in a {} ({remaining:?} left)",
+        reason.as_str()
+    );
+    let lang = i18n::chat_language(state, context.chat_id).await;
+    let msg = match reason {
+        Reason::Cooldown => Msg::UserRateLimited,
+        Reason::Muted => Msg::UserMuted,
+    };
+    Some(i18n::tf(lang, msg, &[("minutes", &minutes(remaining))]))
+}
+
+/// Count a failed tool call against the sender of the turn.
+pub fn record_tool_error(state: &AppState, context: &AgentRequestContext<'_>) {
+    let config = &state.config.user_limits;
+    if config.mute_after_tool_errors == 0 {
+        return;
+    }
+    let Some(user) = user_key(state, context) else {
+        return;
+    };
+    if limiter().record_tool_error(config, &user, Instant::now()) {
+        tracing::warn!(
+            "Muting {user} for {}s after {} failed tool calls",
+            config.mute_secs,
+            config.mute_after_tool_errors
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> UserLimitsConfig {
+        UserLimitsConfig {
+            messages_per_minute: 3,
+            messages_per_hour: 5,
+            cooldown_secs: 120,
+            mute_after_tool_errors: 2,
+            tool_error_window_secs: 60,
+            mute_secs: 600,
+        }
+    }
+
+    fn refused(verdict: &Verdict) -> Option<(Reason, bool)> {
+        match verdict {
+            Verdict::Allow => None,
+            Verdict::Refuse { reason, notify, .. } => Some((*reason, *notify)),
+        }
+    }
+
+    #[test]
+    fn test_message_limits_start_a_cooldown() {
+        let config = config();
+        let mut limiter = Limiter::default();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check(&config, "tg:1", start), Verdict::Allow);
+        }
+        // Another user is not affected.
+        assert_eq!(limiter.check(&config, "tg:2", start), Verdict::Allow);
+        let fourth = limiter.check(&config, "tg:1", start);
+        assert_eq!(refused(&fourth), Some((Reason::Cooldown, true)));
+        let fifth = limiter.check(&config, "tg:1", start + Duration::from_secs(60));
+        assert_eq!(refused(&fifth), Some((Reason::Cooldown, false)));
+
+        // After the cooldown two more fit under the hourly limit of five.
+        let later = start + Duration::from_secs(121);
+        assert_eq!(limiter.check(&config, "tg:1", later), Verdict::Allow);
+        assert_eq!(limiter.check(&config, "tg:1", later), Verdict::Allow);
+        let over_hour = limiter.check(&config, "tg:1", later + Duration::from_secs(61));
+        assert_eq!(refused(&over_hour), Some((Reason::Cooldown, true)));
+    }
+
+    #[test]
+    fn test_repeated_tool_errors_mute_the_user() {
+        let config = config();
+        let mut limiter = Limiter::default();
+        let start = Instant::now();
+        assert!(!limiter.record_tool_error(&config, "tg:1", start));
+        // The first error has left the window by now.
+        assert!(!limiter.record_tool_error(&config, "tg:1", start + Duration::from_secs(61)));
+        assert!(limiter.record_tool_error(&config, "tg:1", start + Duration::from_secs(62)));
+
+        let Verdict::Refuse {
+            reason,
+            remaining,
+            notify,
+        } = limiter.check(&config, "tg:1", start + Duration::from_secs(62))
+        else {
+            panic!("a muted user should be refused");
+        };
+        assert_eq!((reason, notify), (Reason::Muted, true));
+        assert_eq!(remaining, Duration::from_secs(600));
+        assert_eq!(minutes(remaining), "10");
+        assert_eq!(
+            limiter.check(&config, "tg:1", start + Duration::from_secs(663)),
+            Verdict::Allow
+        );
+    }
+}
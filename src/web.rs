@@ -3,12 +3,14 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::sse::{Event, KeepAlive, Sse};
-use axum::response::{Html, IntoResponse};
+use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
+use futures_util::{SinkExt, StreamExt};
 use include_dir::{include_dir, Dir};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -23,8 +25,13 @@ use crate::channel::{deliver_and_store_bot_message, get_chat_routing, session_so
 use crate::channel_adapter::{ChannelAdapter, ChannelRegistry};
 use crate::config::{Config, WorkingDirIsolation};
 use crate::db::{call_blocking, ChatSummary, StoredMessage};
+use crate::run_control;
 use crate::runtime::AppState;
 use crate::usage::build_usage_report;
+use auth::Caller;
+
+pub(crate) mod auth;
+mod openai;
 
 static WEB_ASSETS: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/web/dist");
 
@@ -57,6 +64,7 @@ impl ChannelAdapter for WebAdapter {
 struct WebState {
     app_state: Arc<AppState>,
     auth_token: Option<String>,
+    oidc_logins: auth::OidcLogins,
     run_hub: RunHub,
     session_hub: SessionHub,
     request_hub: RequestHub,
@@ -326,20 +334,11 @@ fn auth_token_from_headers(headers: &HeaderMap) -> Option<String> {
         .filter(|v| !v.is_empty())
 }
 
-fn require_auth(
-    headers: &HeaderMap,
-    expected_token: Option<&str>,
-) -> Result<(), (StatusCode, String)> {
-    let Some(expected) = expected_token else {
-        return Ok(());
-    };
-
-    let provided = auth_token_from_headers(headers).unwrap_or_default();
-
-    if provided == expected {
+fn require_admin(caller: &Caller) -> Result<(), (StatusCode, String)> {
+    if caller.is_admin() {
         Ok(())
     } else {
-        Err((StatusCode::UNAUTHORIZED, "unauthorized".into()))
+        Err((StatusCode::FORBIDDEN, "admin only".into()))
     }
 }
 
@@ -382,6 +381,18 @@ struct SendRequest {
     session_key: Option<String>,
     sender_name: Option<String>,
     message: String,
+    /// Set by the handler from the request's credentials.
+    #[serde(skip)]
+    caller: Caller,
+}
+
+/// Attach the caller to a message; signed-in users always send under their
+/// own name.
+fn bind_caller(body: &mut SendRequest, caller: Caller) {
+    if let Some(username) = caller.username() {
+        body.sender_name = Some(username.to_string());
+    }
+    body.caller = caller;
 }
 
 #[derive(Debug, Deserialize)]
@@ -492,9 +503,17 @@ fn json_to_yaml_value(v: &serde_json::Value) -> serde_yaml::Value {
 const CHANNEL_SECRET_FIELDS: &[(&str, &[&str])] = &[
     ("slack", &["bot_token", "app_token"]),
     ("feishu", &["app_secret"]),
+    ("email", &["password"]),
 ];
 
 fn config_path_for_save() -> Result<PathBuf, (StatusCode, String)> {
+    // Saving would flatten the base config and the profile into one file.
+    if let Ok(Some(profile)) = Config::active_profile() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Profile '{profile}' is active; edit the config files directly"),
+        ));
+    }
     match Config::resolve_config_path() {
         Ok(Some(path)) => Ok(path),
         Ok(None) => Ok(PathBuf::from("./microclaw.config.yaml")),
@@ -522,6 +541,20 @@ fn redact_config(config: &Config) -> serde_json::Value {
     if cfg.web_auth_token.is_some() {
         cfg.web_auth_token = Some("***".into());
     }
+    if cfg.openai_compat_api_key.is_some() {
+        cfg.openai_compat_api_key = Some("***".into());
+    }
+    for user in &mut cfg.web_auth.users {
+        user.password_hash = "***".into();
+    }
+    for hook in &mut cfg.clawhooks {
+        hook.secret = "***".into();
+    }
+    if let Some(oidc) = &mut cfg.web_auth.oidc {
+        if !oidc.client_secret.is_empty() {
+            oidc.client_secret = "***".into();
+        }
+    }
 
     // Redact secrets in channels map using declarative list
     for (channel_name, secret_fields) in CHANNEL_SECRET_FIELDS {
@@ -536,34 +569,75 @@ fn redact_config(config: &Config) -> serde_json::Value {
             }
         }
     }
+    // Webhook secrets are nested one level deeper, under `hooks.<name>.secret`.
+    if let Some(hooks) = cfg
+        .channels
+        .get_mut("webhook")
+        .and_then(|v| v.get_mut("hooks"))
+        .and_then(|v| v.as_mapping_mut())
+    {
+        for (_, hook) in hooks.iter_mut() {
+            if let Some(map) = hook.as_mapping_mut() {
+                let key = serde_yaml::Value::String("secret".into());
+                if map.contains_key(&key) {
+                    map.insert(key, serde_yaml::Value::String("***".into()));
+                }
+            }
+        }
+    }
 
     json!(cfg)
 }
 
-async fn index() -> impl IntoResponse {
+async fn index(headers: HeaderMap, State(state): State<WebState>) -> impl IntoResponse {
+    if auth::needs_login(&state, &headers).await {
+        return Redirect::to("/login").into_response();
+    }
     match WEB_ASSETS.get_file("index.html") {
         Some(file) => Html(String::from_utf8_lossy(file.contents()).to_string()).into_response(),
         None => (StatusCode::NOT_FOUND, "index.html missing").into_response(),
     }
 }
 
+/// Dependency checks (see `health`); 503 with the failing checks listed when
+/// any of them fails.
 async fn api_health(
     headers: HeaderMap,
     State(state): State<WebState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
-    Ok(Json(json!({
-        "ok": true,
-        "version": env!("CARGO_PKG_VERSION"),
-        "web_enabled": state.app_state.config.web_enabled,
-    })))
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    auth::authenticate(&state, &headers, false).await?;
+    let (ok, checks) = crate::health::report(&state.app_state).await;
+    let failures: Vec<&String> = checks
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, check)| check["ok"] != true)
+        .map(|(name, _)| name)
+        .collect();
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((
+        status,
+        Json(json!({
+            "ok": ok,
+            "status": if ok { "ok" } else { "degraded" },
+            "version": env!("CARGO_PKG_VERSION"),
+            "web_enabled": state.app_state.config.web_enabled,
+            "failures": failures,
+            "checks": checks,
+        })),
+    ))
 }
 
 async fn api_get_config(
     headers: HeaderMap,
     State(state): State<WebState>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, false).await?;
+    require_admin(&caller)?;
 
     let path = config_path_for_save()?;
     Ok(Json(json!({
@@ -579,7 +653,8 @@ async fn api_update_config(
     State(state): State<WebState>,
     Json(body): Json<UpdateConfigRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, true).await?;
+    require_admin(&caller)?;
 
     let mut cfg = state.app_state.config.clone();
 
@@ -713,7 +788,11 @@ async fn api_update_config(
     })))
 }
 
-fn map_chat_to_session(registry: &ChannelRegistry, chat: ChatSummary) -> SessionItem {
+fn map_chat_to_session(
+    registry: &ChannelRegistry,
+    caller: &Caller,
+    chat: ChatSummary,
+) -> SessionItem {
     let source = session_source_for_chat(registry, &chat.chat_type, chat.chat_title.as_deref());
 
     let fallback = format!("{}:{}", source, chat.chat_id);
@@ -730,7 +809,8 @@ fn map_chat_to_session(registry: &ChannelRegistry, chat: ChatSummary) -> Session
     let session_key = if source == "web" {
         chat.chat_title
             .as_deref()
-            .map(|t| normalize_session_key(Some(t)))
+            .and_then(|t| caller.own_session_key(t))
+            .map(|t| normalize_session_key(Some(&t)))
             .unwrap_or_else(|| format!("chat:{}", chat.chat_id))
     } else {
         format!("chat:{}", chat.chat_id)
@@ -752,13 +832,48 @@ fn parse_chat_id_from_session_key(session_key: &str) -> Option<i64> {
         .and_then(|s| s.parse::<i64>().ok())
 }
 
+/// Whether the caller may open a chat by id: admins may open any, users only
+/// their own web chats.
+async fn ensure_chat_visible(
+    state: &WebState,
+    caller: &Caller,
+    chat_id: i64,
+) -> Result<(), (StatusCode, String)> {
+    if caller.is_admin() {
+        return Ok(());
+    }
+    let (chat_type, external_id) = call_blocking(state.app_state.db.clone(), move |db| {
+        Ok((
+            db.get_chat_type(chat_id)?,
+            db.get_chat_external_id(chat_id)?,
+        ))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let own = chat_type.as_deref() == Some("web")
+        && external_id
+            .as_deref()
+            .and_then(|id| caller.own_session_key(id))
+            .is_some();
+    if own {
+        Ok(())
+    } else {
+        Err((StatusCode::NOT_FOUND, "session not found".into()))
+    }
+}
+
 async fn resolve_chat_id_for_session_key(
     state: &WebState,
+    caller: &Caller,
     session_key: &str,
 ) -> Result<i64, (StatusCode, String)> {
     if let Some(parsed) = parse_chat_id_from_session_key(session_key) {
+        ensure_chat_visible(state, caller, parsed).await?;
         return Ok(parsed);
     }
+    if caller.username().is_some() {
+        return resolve_web_chat_id(state, caller, session_key).await;
+    }
 
     let key = session_key.to_string();
     let by_title = call_blocking(state.app_state.db.clone(), move |db| {
@@ -786,7 +901,7 @@ async fn api_sessions(
     headers: HeaderMap,
     State(state): State<WebState>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, false).await?;
 
     let chats = call_blocking(state.app_state.db.clone(), |db| db.get_recent_chats(400))
         .await
@@ -794,7 +909,15 @@ async fn api_sessions(
 
     let sessions = chats
         .into_iter()
-        .map(|c| map_chat_to_session(&state.app_state.channel_registry, c))
+        .filter(|c| {
+            caller.is_admin()
+                || (c.chat_type == "web"
+                    && c.chat_title
+                        .as_deref()
+                        .and_then(|t| caller.own_session_key(t))
+                        .is_some())
+        })
+        .map(|c| map_chat_to_session(&state.app_state.channel_registry, &caller, c))
         .collect::<Vec<_>>();
     Ok(Json(json!({ "ok": true, "sessions": sessions })))
 }
@@ -804,10 +927,10 @@ async fn api_history(
     State(state): State<WebState>,
     Query(query): Query<HistoryQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, false).await?;
 
     let session_key = normalize_session_key(query.session_key.as_deref());
-    let chat_id = resolve_chat_id_for_session_key(&state, &session_key).await?;
+    let chat_id = resolve_chat_id_for_session_key(&state, &caller, &session_key).await?;
 
     let mut messages = call_blocking(state.app_state.db.clone(), move |db| {
         db.get_all_messages(chat_id)
@@ -845,10 +968,10 @@ async fn api_usage(
     State(state): State<WebState>,
     Query(query): Query<UsageQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, false).await?;
 
     let session_key = normalize_session_key(query.session_key.as_deref());
-    let chat_id = resolve_chat_id_for_session_key(&state, &session_key).await?;
+    let chat_id = resolve_chat_id_for_session_key(&state, &caller, &session_key).await?;
     let report = build_usage_report(state.app_state.db.clone(), &state.app_state.config, chat_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
@@ -885,7 +1008,7 @@ async fn api_memory_observability(
     State(state): State<WebState>,
     Query(query): Query<MemoryObservabilityQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, false).await?;
 
     let scope = query
         .scope
@@ -899,10 +1022,11 @@ async fn api_memory_observability(
     let since = (chrono::Utc::now() - chrono::Duration::hours(hours as i64)).to_rfc3339();
 
     let chat_id_filter = if scope == "global" {
+        require_admin(&caller)?;
         None
     } else {
         let session_key = normalize_session_key(query.session_key.as_deref());
-        Some(resolve_chat_id_for_session_key(&state, &session_key).await?)
+        Some(resolve_chat_id_for_session_key(&state, &caller, &session_key).await?)
     };
 
     let summary = call_blocking(state.app_state.db.clone(), move |db| {
@@ -978,11 +1102,12 @@ async fn api_memory_observability(
 async fn api_send(
     headers: HeaderMap,
     State(state): State<WebState>,
-    Json(body): Json<SendRequest>,
+    Json(mut body): Json<SendRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    bind_caller(&mut body, auth::authenticate(&state, &headers, true).await?);
     let start = Instant::now();
     let session_key = normalize_session_key(body.session_key.as_deref());
+    supersede_previous_run(&state, &body, &session_key).await;
     if let Err((status, msg)) = state.request_hub.begin(&session_key, &state.limits).await {
         info!(
             target: "web",
@@ -1010,12 +1135,13 @@ async fn api_send(
     result
 }
 
-async fn api_send_stream(
-    headers: HeaderMap,
-    State(state): State<WebState>,
-    Json(body): Json<SendRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+/// Start an agent run in the background and return its run id. Events are
+/// published to the run hub for `/api/stream` and `/api/chat` subscribers.
+async fn start_stream_run(
+    state: &WebState,
+    body: SendRequest,
+    endpoint: &'static str,
+) -> Result<String, (StatusCode, String)> {
     let start = Instant::now();
 
     let text = body.message.trim().to_string();
@@ -1024,10 +1150,11 @@ async fn api_send_stream(
     }
 
     let session_key = normalize_session_key(body.session_key.as_deref());
+    supersede_previous_run(state, &body, &session_key).await;
     if let Err((status, msg)) = state.request_hub.begin(&session_key, &state.limits).await {
         info!(
             target: "web",
-            endpoint = "/api/send_stream",
+            endpoint = endpoint,
             session_key = %session_key,
             status = status.as_u16(),
             reason = %msg,
@@ -1038,6 +1165,20 @@ async fn api_send_stream(
 
     let run_id = uuid::Uuid::new_v4().to_string();
     state.run_hub.create(&run_id).await;
+    state
+        .run_hub
+        .publish(
+            &run_id,
+            "user_message",
+            json!({
+                "session_key": session_key,
+                "sender_name": body.sender_name,
+                "message": text,
+            })
+            .to_string(),
+            state.limits.run_history_limit,
+        )
+        .await;
     let state_for_task = state.clone();
     let run_id_for_task = run_id.clone();
     let lock = state
@@ -1048,7 +1189,7 @@ async fn api_send_stream(
     let session_key_for_release = session_key.clone();
     info!(
         target: "web",
-        endpoint = "/api/send_stream",
+        endpoint = endpoint,
         session_key = %session_key,
         run_id = %run_id,
         latency_ms = start.elapsed().as_millis(),
@@ -1085,17 +1226,23 @@ async fn api_send_stream(
                             )
                             .await;
                     }
-                    AgentEvent::ToolStart { name } => {
+                    AgentEvent::ToolStart {
+                        tool_use_id,
+                        name,
+                        input,
+                    } => {
                         run_hub
                             .publish(
                                 &run_id_for_events,
                                 "tool_start",
-                                json!({"name": name}).to_string(),
+                                json!({"tool_use_id": tool_use_id, "name": name, "input": input})
+                                    .to_string(),
                                 run_history_limit,
                             )
                             .await;
                     }
                     AgentEvent::ToolResult {
+                        tool_use_id,
                         name,
                         is_error,
                         preview,
@@ -1104,13 +1251,30 @@ async fn api_send_stream(
                         bytes,
                         error_type,
                     } => {
+                        if error_type.as_deref() == Some("approval_required") {
+                            run_hub
+                                .publish(
+                                    &run_id_for_events,
+                                    "approval_required",
+                                    json!({
+                                        "tool_use_id": tool_use_id,
+                                        "name": name,
+                                        "message": preview
+                                    })
+                                    .to_string(),
+                                    run_history_limit,
+                                )
+                                .await;
+                        }
                         run_hub
                             .publish(
                                 &run_id_for_events,
                                 "tool_result",
                                 json!({
+                                    "tool_use_id": tool_use_id,
                                     "name": name,
                                     "is_error": is_error,
+                                    "output": preview,
                                     "preview": preview,
                                     "duration_ms": duration_ms,
                                     "status_code": status_code,
@@ -1122,6 +1286,21 @@ async fn api_send_stream(
                             )
                             .await;
                     }
+                    AgentEvent::ToolProgress {
+                        tool_use_id,
+                        name,
+                        note,
+                    } => {
+                        run_hub
+                            .publish(
+                                &run_id_for_events,
+                                "tool_progress",
+                                json!({"tool_use_id": tool_use_id, "name": name, "note": note})
+                                    .to_string(),
+                                run_history_limit,
+                            )
+                            .await;
+                    }
                     AgentEvent::TextDelta { delta } => {
                         run_hub
                             .publish(
@@ -1132,6 +1311,16 @@ async fn api_send_stream(
                             )
                             .await;
                     }
+                    AgentEvent::Merged => {
+                        run_hub
+                            .publish(
+                                &run_id_for_events,
+                                "status",
+                                json!({"message": "merged into the queued turn"}).to_string(),
+                                run_history_limit,
+                            )
+                            .await;
+                    }
                     AgentEvent::FinalResponse { .. } => {}
                 }
             }
@@ -1177,7 +1366,7 @@ async fn api_send_stream(
             .await;
         info!(
             target: "web",
-            endpoint = "/api/send_stream",
+            endpoint = endpoint,
             session_key = %session_key_for_release,
             run_id = %run_id_for_task,
             latency_ms = run_start.elapsed().as_millis(),
@@ -1190,58 +1379,60 @@ async fn api_send_stream(
             .await;
     });
 
+    Ok(run_id)
+}
+
+async fn api_send_stream(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Json(mut body): Json<SendRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    bind_caller(&mut body, auth::authenticate(&state, &headers, true).await?);
+    let run_id = start_stream_run(&state, body, "/api/send_stream").await?;
     Ok(Json(json!({
         "ok": true,
         "run_id": run_id,
     })))
 }
 
-async fn api_stream(
-    headers: HeaderMap,
-    State(state): State<WebState>,
-    Query(query): Query<StreamQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+/// Subscribe to a run's events: replay metadata, then the buffered history
+/// after `last_event_id` followed by live events until `done`/`error`.
+async fn subscribe_run_events(
+    state: &WebState,
+    run_id: &str,
+    last_event_id: Option<u64>,
+    endpoint: &'static str,
+) -> Option<(
+    serde_json::Value,
+    impl futures_util::Stream<Item = RunEvent> + Send + 'static,
+)> {
     let start = Instant::now();
-
-    let Some((mut rx, replay, done, replay_truncated, oldest_event_id)) = state
+    let (mut rx, replay, done, replay_truncated, oldest_event_id) = state
         .run_hub
-        .subscribe_with_replay(&query.run_id, query.last_event_id)
-        .await
-    else {
-        return Err((StatusCode::NOT_FOUND, "run not found".into()));
-    };
+        .subscribe_with_replay(run_id, last_event_id)
+        .await?;
     info!(
         target: "web",
-        endpoint = "/api/stream",
-        run_id = %query.run_id,
-        last_event_id = ?query.last_event_id,
+        endpoint = endpoint,
+        run_id = %run_id,
+        last_event_id = ?last_event_id,
         replay_count = replay.len(),
         replay_truncated = replay_truncated,
         oldest_event_id = ?oldest_event_id,
         latency_ms = start.elapsed().as_millis(),
         "Stream subscription established"
     );
+    let meta = json!({
+        "replay_truncated": replay_truncated,
+        "oldest_event_id": oldest_event_id,
+        "requested_last_event_id": last_event_id,
+    });
 
-    let stream = async_stream::stream! {
-        let meta = Event::default().event("replay_meta").data(
-            json!({
-                "replay_truncated": replay_truncated,
-                "oldest_event_id": oldest_event_id,
-                "requested_last_event_id": query.last_event_id,
-            })
-            .to_string()
-        );
-        yield Ok::<Event, std::convert::Infallible>(meta);
-
+    let events = async_stream::stream! {
         let mut finished = false;
         for evt in replay {
             let is_done = evt.event == "done" || evt.event == "error";
-            let event = Event::default()
-                .id(evt.id.to_string())
-                .event(evt.event)
-                .data(evt.data);
-            yield Ok::<Event, std::convert::Infallible>(event);
+            yield evt;
             if is_done {
                 finished = true;
                 break;
@@ -1256,11 +1447,7 @@ async fn api_stream(
             match rx.recv().await {
                 Ok(evt) => {
                     let done = evt.event == "done" || evt.event == "error";
-                    let event = Event::default()
-                        .id(evt.id.to_string())
-                        .event(evt.event)
-                        .data(evt.data);
-                    yield Ok::<Event, std::convert::Infallible>(event);
+                    yield evt;
                     if done {
                         break;
                     }
@@ -1274,12 +1461,222 @@ async fn api_stream(
             }
         }
     };
+    Some((meta, events))
+}
+
+/// SSE framing of a run subscription: a `replay_meta` frame, then one frame
+/// per run event with its id.
+fn sse_run_events<S>(
+    meta: serde_json::Value,
+    events: S,
+) -> impl futures_util::Stream<Item = Result<Event, std::convert::Infallible>>
+where
+    S: futures_util::Stream<Item = RunEvent>,
+{
+    let meta = Event::default().event("replay_meta").data(meta.to_string());
+    futures_util::stream::once(async move { meta })
+        .chain(events.map(|evt| {
+            Event::default()
+                .id(evt.id.to_string())
+                .event(evt.event)
+                .data(evt.data)
+        }))
+        .map(Ok::<Event, std::convert::Infallible>)
+}
 
-    Ok(Sse::new(stream).keep_alive(
+fn sse_response<S>(stream: S) -> impl IntoResponse
+where
+    S: futures_util::Stream<Item = Result<Event, std::convert::Infallible>> + Send + 'static,
+{
+    Sse::new(stream).keep_alive(
         KeepAlive::new()
             .interval(std::time::Duration::from_secs(15))
             .text("keepalive"),
-    ))
+    )
+}
+
+async fn api_stream(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Query(query): Query<StreamQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    auth::authenticate(&state, &headers, false).await?;
+    let Some((meta, events)) =
+        subscribe_run_events(&state, &query.run_id, query.last_event_id, "/api/stream").await
+    else {
+        return Err((StatusCode::NOT_FOUND, "run not found".into()));
+    };
+    Ok(sse_response(sse_run_events(meta, events)))
+}
+
+/// Single-request chat: starts a run and answers with its SSE event stream.
+/// The first `run` event carries the run id, so a client that drops the
+/// connection can resume through `/api/stream?run_id=..&last_event_id=..`.
+async fn api_chat(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Json(mut body): Json<SendRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    bind_caller(&mut body, auth::authenticate(&state, &headers, true).await?);
+    let run_id = start_stream_run(&state, body, "/api/chat").await?;
+    let Some((meta, events)) = subscribe_run_events(&state, &run_id, None, "/api/chat").await
+    else {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "run not found".into()));
+    };
+    let first = Event::default()
+        .event("run")
+        .data(json!({ "run_id": run_id }).to_string());
+    let stream =
+        futures_util::stream::once(async move { Ok::<Event, std::convert::Infallible>(first) })
+            .chain(sse_run_events(meta, events));
+    Ok(sse_response(stream))
+}
+
+#[derive(Debug, Deserialize)]
+struct WsQuery {
+    token: Option<String>,
+}
+
+/// Client frames on `/api/ws`, tagged by `type`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsClientMessage {
+    /// Start a run; same fields as `/api/send`.
+    Chat(SendRequest),
+    /// Follow (or resume) an existing run.
+    Subscribe {
+        run_id: String,
+        last_event_id: Option<u64>,
+    },
+    Stop {
+        session_key: Option<String>,
+    },
+    Ping,
+}
+
+/// WebSocket API for programmatic clients. Browsers cannot set headers on a
+/// WebSocket handshake, so the auth token may also be passed as `?token=`;
+/// signed-in browsers use their session cookie instead.
+async fn api_ws(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    Query(query): Query<WsQuery>,
+    State(state): State<WebState>,
+) -> Result<Response, (StatusCode, String)> {
+    let token_ok = state.auth_token.as_deref().is_some_and(|expected| {
+        auth_token_from_headers(&headers).or(query.token).as_deref() == Some(expected)
+    });
+    let caller = if token_ok {
+        Caller::Operator
+    } else {
+        let caller = auth::authenticate(&state, &headers, false).await?;
+        if caller.username().is_some() && !auth::same_origin(&headers) {
+            return Err((StatusCode::FORBIDDEN, "cross-origin WebSocket".into()));
+        }
+        caller
+    };
+    Ok(ws.on_upgrade(move |socket| handle_ws(socket, state, caller)))
+}
+
+/// Forward a run's events to a WebSocket connection as
+/// `{"type":"event","run_id","id","event","data"}` frames.
+async fn forward_run_to_ws(
+    state: &WebState,
+    run_id: String,
+    last_event_id: Option<u64>,
+    out: tokio::sync::mpsc::UnboundedSender<serde_json::Value>,
+) -> bool {
+    let Some((meta, events)) = subscribe_run_events(state, &run_id, last_event_id, "/api/ws").await
+    else {
+        return false;
+    };
+    tokio::spawn(async move {
+        if out
+            .send(json!({"type": "replay_meta", "run_id": run_id, "data": meta}))
+            .is_err()
+        {
+            return;
+        }
+        let mut events = std::pin::pin!(events);
+        while let Some(evt) = events.next().await {
+            let data: serde_json::Value =
+                serde_json::from_str(&evt.data).unwrap_or(serde_json::Value::String(evt.data));
+            let frame = json!({
+                "type": "event",
+                "run_id": run_id,
+                "id": evt.id,
+                "event": evt.event,
+                "data": data,
+            });
+            if out.send(frame).is_err() {
+                break;
+            }
+        }
+    });
+    true
+}
+
+async fn handle_ws(socket: WebSocket, state: WebState, caller: Caller) {
+    let (mut sink, mut incoming) = socket.split();
+    let (out_tx, mut out_rx) = tokio::sync::mpsc::unbounded_channel::<serde_json::Value>();
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = out_rx.recv().await {
+            if sink.send(Message::Text(frame.to_string())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(msg)) = incoming.next().await {
+        let text = match msg {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let reply = match serde_json::from_str::<WsClientMessage>(&text) {
+            Err(e) => json!({"type": "error", "error": format!("invalid message: {e}")}),
+            Ok(WsClientMessage::Ping) => json!({"type": "pong"}),
+            Ok(WsClientMessage::Chat(mut body)) => {
+                bind_caller(&mut body, caller.clone());
+                match start_stream_run(&state, body, "/api/ws").await {
+                    Ok(run_id) => {
+                        let _ = out_tx.send(json!({"type": "run", "run_id": run_id}));
+                        forward_run_to_ws(&state, run_id, None, out_tx.clone()).await;
+                        continue;
+                    }
+                    Err((status, error)) => {
+                        json!({"type": "error", "status": status.as_u16(), "error": error})
+                    }
+                }
+            }
+            Ok(WsClientMessage::Subscribe {
+                run_id,
+                last_event_id,
+            }) => {
+                if forward_run_to_ws(&state, run_id.clone(), last_event_id, out_tx.clone()).await {
+                    continue;
+                }
+                json!({"type": "error", "status": 404, "error": "run not found", "run_id": run_id})
+            }
+            Ok(WsClientMessage::Stop { session_key }) => {
+                let session_key = normalize_session_key(session_key.as_deref());
+                match resolve_chat_id_for_session_key(&state, &caller, &session_key).await {
+                    Ok(chat_id) => json!({
+                        "type": "stopped",
+                        "session_key": session_key,
+                        "cancelled": run_control::cancel_chat_runs(chat_id),
+                    }),
+                    Err((status, error)) => {
+                        json!({"type": "error", "status": status.as_u16(), "error": error})
+                    }
+                }
+            }
+        };
+        if out_tx.send(reply).is_err() {
+            break;
+        }
+    }
+    writer.abort();
 }
 
 async fn api_run_status(
@@ -1287,7 +1684,7 @@ async fn api_run_status(
     State(state): State<WebState>,
     Query(query): Query<RunStatusQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    auth::authenticate(&state, &headers, false).await?;
     let Some((done, last_event_id)) = state.run_hub.status(&query.run_id).await else {
         return Err((StatusCode::NOT_FOUND, "run not found".into()));
     };
@@ -1312,6 +1709,45 @@ async fn send_and_store_response(
     send_and_store_response_with_events(state, body, None).await
 }
 
+/// Reply to a chat command shared with the other channels (`/link`,
+/// `/router`, `/budget`, `/status`, `/thinking`, `/prompt`), or `None` for ordinary
+/// messages.
+async fn resolve_web_chat_id(
+    state: &WebState,
+    caller: &Caller,
+    session_key: &str,
+) -> Result<i64, (StatusCode, String)> {
+    if let Some(explicit_chat_id) = parse_chat_id_from_session_key(session_key) {
+        ensure_chat_visible(state, caller, explicit_chat_id).await?;
+        return Ok(explicit_chat_id);
+    }
+    let chat_key = caller.chat_key(session_key);
+    call_blocking(state.app_state.db.clone(), move |db| {
+        db.resolve_or_create_chat_id("web", &chat_key, Some(&chat_key), "web")
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn web_sender_name(body: &SendRequest) -> &str {
+    body.sender_name
+        .as_deref()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("web-user")
+}
+
+/// With `cancel_on_new_message`, stop the sender's run in this session
+/// before the new message is queued behind it.
+async fn supersede_previous_run(state: &WebState, body: &SendRequest, session_key: &str) {
+    if !state.app_state.config.cancel_on_new_message {
+        return;
+    }
+    if let Ok(chat_id) = resolve_web_chat_id(state, &body.caller, session_key).await {
+        run_control::supersede_runs_from(chat_id, web_sender_name(body)).await;
+    }
+}
+
 async fn send_and_store_response_with_events(
     state: WebState,
     body: SendRequest,
@@ -1324,28 +1760,8 @@ async fn send_and_store_response_with_events(
 
     let session_key = normalize_session_key(body.session_key.as_deref());
     let parsed_chat_id = parse_chat_id_from_session_key(&session_key);
-    let chat_id = if let Some(explicit_chat_id) = parsed_chat_id {
-        explicit_chat_id
-    } else {
-        let session_key_for_lookup = session_key.clone();
-        call_blocking(state.app_state.db.clone(), move |db| {
-            db.resolve_or_create_chat_id(
-                "web",
-                &session_key_for_lookup,
-                Some(&session_key_for_lookup),
-                "web",
-            )
-        })
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    };
-    let sender_name = body
-        .sender_name
-        .as_deref()
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .unwrap_or("web-user")
-        .to_string();
+    let chat_id = resolve_web_chat_id(&state, &body.caller, &session_key).await?;
+    let sender_name = web_sender_name(&body).to_string();
 
     if let Some(explicit_chat_id) = parsed_chat_id {
         let is_web = get_chat_routing(
@@ -1365,6 +1781,8 @@ async fn send_and_store_response_with_events(
         }
     }
 
+    let command_reply =
+        crate::channel::local_command_reply(&state.app_state, "web", chat_id, None, &text).await;
     let user_msg = StoredMessage {
         id: uuid::Uuid::new_v4().to_string(),
         chat_id,
@@ -1379,13 +1797,17 @@ async fn send_and_store_response_with_events(
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let response = if let Some(tx) = event_tx {
+    let response = if let Some(reply) = command_reply {
+        reply
+    } else if let Some(tx) = event_tx {
         process_with_agent_with_events(
             &state.app_state,
             AgentRequestContext {
                 caller_channel: "web",
                 chat_id,
                 chat_type: "web",
+                sender: Some(&sender_name),
+                sender_id: None,
             },
             None,
             None,
@@ -1400,6 +1822,8 @@ async fn send_and_store_response_with_events(
                 caller_channel: "web",
                 chat_id,
                 chat_type: "web",
+                sender: Some(&sender_name),
+                sender_id: None,
             },
             None,
             None,
@@ -1431,10 +1855,10 @@ async fn api_reset(
     State(state): State<WebState>,
     Json(body): Json<ResetRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, true).await?;
 
     let session_key = normalize_session_key(body.session_key.as_deref());
-    let chat_id = resolve_chat_id_for_session_key(&state, &session_key).await?;
+    let chat_id = resolve_chat_id_for_session_key(&state, &caller, &session_key).await?;
 
     let is_web = get_chat_routing(
         &state.app_state.channel_registry,
@@ -1447,6 +1871,11 @@ async fn api_reset(
     .unwrap_or(false);
 
     let deleted = if is_web {
+        let external_id = call_blocking(state.app_state.db.clone(), move |db| {
+            db.get_chat_external_id(chat_id)
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
         let deleted = call_blocking(state.app_state.db.clone(), move |db| {
             db.delete_chat_data(chat_id)
         })
@@ -1454,9 +1883,14 @@ async fn api_reset(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         // Keep the web session entry in the session list after clearing context.
+        // Users' chats are found by external id, so theirs is recreated under it.
         let session_key_for_chat = session_key.clone();
-        call_blocking(state.app_state.db.clone(), move |db| {
-            db.upsert_chat(chat_id, Some(&session_key_for_chat), "web")
+        let user_chat_key = external_id.filter(|_| caller.username().is_some());
+        call_blocking(state.app_state.db.clone(), move |db| match user_chat_key {
+            Some(key) => db
+                .resolve_or_create_chat_id("web", &key, Some(&key), "web")
+                .map(|_| ()),
+            None => db.upsert_chat(chat_id, Some(&session_key_for_chat), "web"),
         })
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -1473,15 +1907,73 @@ async fn api_reset(
     Ok(Json(json!({ "ok": true, "deleted": deleted })))
 }
 
+async fn api_stop(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Json(body): Json<ResetRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let caller = auth::authenticate(&state, &headers, true).await?;
+
+    let session_key = normalize_session_key(body.session_key.as_deref());
+    let chat_id = resolve_chat_id_for_session_key(&state, &caller, &session_key).await?;
+    let cancelled = run_control::cancel_chat_runs(chat_id);
+
+    Ok(Json(json!({ "ok": true, "cancelled": cancelled })))
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookQuery {
+    token: Option<String>,
+}
+
+/// Inbound webhooks authenticate with their own per-hook secret, not the web
+/// auth token, so external services never need the operator's API token.
+async fn webhook_inbound(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Path(name): Path<String>,
+    Query(query): Query<WebhookQuery>,
+    body: axum::body::Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let result = crate::channels::webhook::handle_webhook(
+        state.app_state.clone(),
+        &name,
+        &headers,
+        query.token.as_deref(),
+        &body,
+    )
+    .await?;
+    Ok((StatusCode::ACCEPTED, Json(result)))
+}
+
+/// `POST /webhook/task/:id`: run a `trigger: webhook` scheduled task now.
+async fn webhook_task(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Path(task_id): Path<i64>,
+    Query(query): Query<WebhookQuery>,
+    body: axum::body::Bytes,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    let result = crate::channels::webhook::handle_task_webhook(
+        state.app_state.clone(),
+        task_id,
+        &headers,
+        query.token.as_deref(),
+        &body,
+    )
+    .await?;
+    Ok((StatusCode::ACCEPTED, Json(result)))
+}
+
 async fn api_delete_session(
     headers: HeaderMap,
     State(state): State<WebState>,
     Json(body): Json<ResetRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    require_auth(&headers, state.auth_token.as_deref())?;
+    let caller = auth::authenticate(&state, &headers, true).await?;
 
     let session_key = normalize_session_key(body.session_key.as_deref());
-    let chat_id = resolve_chat_id_for_session_key(&state, &session_key).await?;
+    let chat_id = resolve_chat_id_for_session_key(&state, &caller, &session_key).await?;
 
     let deleted = call_blocking(state.app_state.db.clone(), move |db| {
         db.delete_chat_data(chat_id)
@@ -1496,6 +1988,7 @@ pub async fn start_web_server(state: Arc<AppState>) {
     let limits = WebLimits::from_config(&state.config);
     let web_state = WebState {
         auth_token: state.config.web_auth_token.clone(),
+        oidc_logins: auth::OidcLogins::default(),
         app_state: state.clone(),
         run_hub: RunHub::default(),
         session_hub: SessionHub::default(),
@@ -1560,6 +2053,11 @@ fn build_router(web_state: WebState) -> Router {
         .route("/assets/*file", get(asset_file))
         .route("/icon.png", get(icon_file))
         .route("/favicon.ico", get(favicon_file))
+        .route("/login", get(auth::login_page).post(auth::login_submit))
+        .route("/logout", post(auth::logout))
+        .route("/auth/oidc/login", get(auth::oidc_login))
+        .route("/auth/oidc/callback", get(auth::oidc_callback))
+        .route("/api/me", get(auth::api_me))
         .route("/api/health", get(api_health))
         .route("/api/config", get(api_get_config).put(api_update_config))
         .route("/api/sessions", get(api_sessions))
@@ -1568,10 +2066,17 @@ fn build_router(web_state: WebState) -> Router {
         .route("/api/memory_observability", get(api_memory_observability))
         .route("/api/send", post(api_send))
         .route("/api/send_stream", post(api_send_stream))
+        .route("/api/chat", post(api_chat))
+        .route("/api/ws", get(api_ws))
         .route("/api/stream", get(api_stream))
         .route("/api/run_status", get(api_run_status))
         .route("/api/reset", post(api_reset))
+        .route("/api/stop", post(api_stop))
         .route("/api/delete_session", post(api_delete_session))
+        .route("/webhook/:name", post(webhook_inbound))
+        .route("/webhook/task/:id", post(webhook_task))
+        .route("/v1/chat/completions", post(openai::chat_completions))
+        .route("/v1/models", get(openai::list_models))
         .with_state(web_state)
 }
 
@@ -1627,6 +2132,7 @@ mod tests {
                 }],
                 stop_reason: Some("end_turn".into()),
                 usage: None,
+                served_by: None,
             })
         }
 
@@ -1664,6 +2170,7 @@ mod tests {
                 }],
                 stop_reason: Some("end_turn".into()),
                 usage: None,
+                served_by: None,
             })
         }
     }
@@ -1690,6 +2197,7 @@ mod tests {
                     }],
                     stop_reason: Some("tool_use".into()),
                     usage: None,
+                    served_by: None,
                 });
             }
             Ok(crate::llm_types::MessagesResponse {
@@ -1698,11 +2206,19 @@ mod tests {
                 }],
                 stop_reason: Some("end_turn".into()),
                 usage: None,
+                served_by: None,
             })
         }
     }
 
     fn test_state(llm: Box<dyn LlmProvider>) -> Arc<AppState> {
+        test_state_with(llm, |_| {})
+    }
+
+    fn test_state_with(
+        llm: Box<dyn LlmProvider>,
+        configure: impl FnOnce(&mut Config),
+    ) -> Arc<AppState> {
         let mut cfg = Config {
             telegram_bot_token: "tok".into(),
             bot_username: "bot".into(),
@@ -1731,6 +2247,8 @@ mod tests {
             web_host: "127.0.0.1".into(),
             web_port: 3900,
             web_auth_token: None,
+            web_auth: Default::default(),
+            web_public_url: None,
             web_max_inflight_per_session: 2,
             web_max_requests_per_window: 8,
             web_rate_window_seconds: 10,
@@ -1746,12 +2264,78 @@ mod tests {
             reflector_interval_mins: 15,
             soul_path: None,
             skip_tool_approval: false,
+            workspace_quota_mb: 0,
+            workspace_export_max_mb: 50,
+            file_preview_cards: false,
+            file_preview_lines: 12,
+            openai_compat_api_key: None,
+            voice_transcription_provider: None,
+            voice_transcription_base_url: None,
+            voice_transcription_model: None,
+            voice_transcription_command: None,
+            network_policy: Default::default(),
+            tool_output_limits: Default::default(),
+            bash_backend: Default::default(),
+            bash_container: Default::default(),
+            model_capabilities: Default::default(),
+            telegram_inline_mode: false,
+            telegram_inline_allowed_users: vec![],
+            discord_reply_in_threads: false,
+            telegram_bots: vec![],
+            stream_replies: true,
+            llm_fallbacks: vec![],
+            llm_fallback_timeout_secs: 120,
+            model_router: Default::default(),
+            chat_budget: Default::default(),
+            chat_budgets: Default::default(),
+            llm_max_retries: 3,
+            thinking: Default::default(),
+            gemini_safety_threshold: None,
+            aws_region: None,
+            aws_profile: None,
+            azure: Default::default(),
+            oauth: Default::default(),
+            llm_wire_log: Default::default(),
+            compaction_model: None,
+            context_compact_ratio: 0.7,
+            parallel_tools: Default::default(),
+            max_repeated_tool_failures: 2,
+            temperature: None,
+            personas: Default::default(),
+            block_high_risk_after_untrusted: false,
+            read_only: false,
+            db_encryption: false,
+            cancel_on_new_message: false,
+            global_budget: Default::default(),
+            pricing_file: None,
+            pricing_url: None,
+            pricing_refresh_hours: 24,
+            secret_refs: Vec::new(),
+            retention: Default::default(),
+            metrics: Default::default(),
+            otel: Default::default(),
+            log_format: Default::default(),
+            knowledge: Default::default(),
+            calendar: Default::default(),
+            smtp: Default::default(),
+            feeds: Default::default(),
+            file_watch: Default::default(),
+            ocr: Default::default(),
+            clawhooks: Vec::new(),
+            heartbeat: Default::default(),
+            progress_status: true,
+            turn_queue: Default::default(),
+            user_limits: Default::default(),
+            language: "en".into(),
+            memory_consolidation: Default::default(),
+            task_failure_alerts: Default::default(),
             channels: std::collections::HashMap::new(),
         };
         let dir = std::env::temp_dir().join(format!("microclaw_webtest_{}", uuid::Uuid::new_v4()));
         std::fs::create_dir_all(&dir).unwrap();
         cfg.data_dir = dir.to_string_lossy().to_string();
         cfg.working_dir = dir.join("tmp").to_string_lossy().to_string();
+        configure(&mut cfg);
         let runtime_dir = cfg.runtime_data_dir();
         std::fs::create_dir_all(&runtime_dir).unwrap();
         let db = Arc::new(Database::new(&runtime_dir).unwrap());
@@ -1765,6 +2349,8 @@ mod tests {
             memory: MemoryManager::new(&runtime_dir),
             skills: SkillManager::from_skills_dir(&cfg.skills_data_dir()),
             llm,
+            channel_llms: std::collections::HashMap::new(),
+            router: None,
             embedding: None,
             tools: ToolRegistry::new(&cfg, channel_registry, db),
         };
@@ -1780,6 +2366,7 @@ mod tests {
         WebState {
             app_state: state,
             auth_token,
+            oidc_logins: auth::OidcLogins::default(),
             run_hub: RunHub::default(),
             session_hub: SessionHub::default(),
             request_hub: RequestHub::default(),
@@ -1823,6 +2410,38 @@ mod tests {
         assert!(text.contains("event: done"));
     }
 
+    #[tokio::test]
+    async fn test_chat_streams_run_and_tool_events_in_one_request() {
+        let web_state = test_web_state(
+            Box::new(ToolFlowLlm {
+                calls: AtomicUsize::new(0),
+            }),
+            None,
+            WebLimits::default(),
+        );
+        let app = build_router(web_state);
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/chat")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                r#"{"session_key":"main","sender_name":"u","message":"hi"}"#,
+            ))
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.starts_with("event: run\ndata: {\"run_id\":"));
+        assert!(text.contains(r#""tool_use_id":"tool_1""#));
+        assert!(text.contains("event: tool_result"));
+        assert!(text.contains(r#""pattern":"*.rs""#));
+        assert!(text.contains("event: done"));
+    }
+
     #[tokio::test]
     async fn test_auth_failure_requires_header() {
         let web_state = test_web_state(
@@ -1841,6 +2460,37 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
     }
 
+    #[tokio::test]
+    async fn test_health_reports_checks() {
+        let web_state = test_web_state(Box::new(DummyLlm), None, WebLimits::default());
+        let app = build_router(web_state);
+
+        let req = Request::builder()
+            .method("GET")
+            .uri("/api/health")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        let status = resp.status();
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["checks"]["database"]["ok"], true);
+        assert!(body["checks"]["disk"].get("ok").is_some());
+        assert!(body["checks"]["llm"]["models"].is_array());
+        // Other tests share the process-wide LLM health, so either outcome
+        // is possible here; the status code must match it.
+        if body["ok"] == true {
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["failures"], json!([]));
+        } else {
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(body["status"], "degraded");
+            assert!(!body["failures"].as_array().unwrap().is_empty());
+        }
+    }
+
     #[tokio::test]
     async fn test_same_session_concurrency_limited() {
         let limits = WebLimits {
@@ -2053,6 +2703,224 @@ mod tests {
         assert_eq!(resp3.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_webhook_requires_secret_and_delivers_notification() {
+        let mut web_state = test_web_state(Box::new(DummyLlm), None, WebLimits::default());
+        let hooks: serde_yaml::Value = serde_yaml::from_str(
+            "hooks:\n  grafana:\n    secret: hooksecret\n    chat_id: 77\n    respond: false\n    template: \"Alert {{title}} is {{state}}\"\n",
+        )
+        .unwrap();
+        Arc::get_mut(&mut web_state.app_state)
+            .unwrap()
+            .config
+            .channels
+            .insert("webhook".into(), hooks);
+        let db = web_state.app_state.db.clone();
+        call_blocking(db.clone(), |d| d.upsert_chat(77, Some("alerts"), "web"))
+            .await
+            .unwrap();
+        let app = build_router(web_state);
+        let mk_req = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"title": "CPU high", "state": "alerting"}).to_string(),
+                ))
+                .unwrap()
+        };
+
+        let resp = app
+            .clone()
+            .oneshot(mk_req("/webhook/grafana"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = app
+            .clone()
+            .oneshot(mk_req("/webhook/unknown?token=hooksecret"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = app
+            .oneshot(mk_req("/webhook/grafana?token=hooksecret"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let messages = call_blocking(db, |d| d.get_all_messages(77)).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].is_from_bot);
+        assert_eq!(messages[0].content, "Alert CPU high is alerting");
+    }
+
+    #[tokio::test]
+    async fn test_websocket_chat_streams_events_and_requires_token() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let web_state = test_web_state(
+            Box::new(ToolFlowLlm {
+                calls: AtomicUsize::new(0),
+            }),
+            Some("secret-token".into()),
+            WebLimits::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, build_router(web_state))
+                .await
+                .unwrap();
+        });
+
+        assert!(
+            tokio_tungstenite::connect_async(format!("ws://{addr}/api/ws"))
+                .await
+                .is_err()
+        );
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/api/ws?token=secret-token"))
+                .await
+                .unwrap();
+
+        ws.send(WsMessage::Text(r#"{"type":"ping"}"#.into()))
+            .await
+            .unwrap();
+        ws.send(WsMessage::Text(
+            r#"{"type":"chat","session_key":"main","sender_name":"u","message":"do tool"}"#.into(),
+        ))
+        .await
+        .unwrap();
+
+        let mut frames = Vec::new();
+        while let Some(Ok(msg)) = ws.next().await {
+            let WsMessage::Text(text) = msg else {
+                continue;
+            };
+            let frame: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let done = frame["event"] == "done" || frame["event"] == "error";
+            frames.push(frame);
+            if done {
+                break;
+            }
+        }
+        let kinds: Vec<String> = frames
+            .iter()
+            .map(|f| {
+                f["event"]
+                    .as_str()
+                    .or(f["type"].as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(kinds[0], "pong");
+        assert_eq!(kinds[1], "run");
+        assert!(kinds.contains(&"user_message".to_string()));
+        assert!(kinds.contains(&"tool_start".to_string()));
+        assert_eq!(kinds.last().map(String::as_str), Some("done"));
+
+        let tool_start = frames.iter().find(|f| f["event"] == "tool_start").unwrap();
+        assert_eq!(tool_start["data"]["tool_use_id"], "tool_1");
+        assert_eq!(tool_start["run_id"], frames[1]["run_id"]);
+        let user = frames
+            .iter()
+            .find(|f| f["event"] == "user_message")
+            .unwrap();
+        assert_eq!(user["data"]["message"], "do tool");
+    }
+
+    #[tokio::test]
+    async fn test_openai_chat_completions_requires_key_and_streams() {
+        let mut web_state = test_web_state(Box::new(DummyLlm), None, WebLimits::default());
+        Arc::get_mut(&mut web_state.app_state)
+            .unwrap()
+            .config
+            .openai_compat_api_key = Some("sk-test".into());
+        let app = build_router(web_state);
+        let mk_req = |key: Option<&str>, stream: bool| {
+            let mut builder = Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json");
+            if let Some(key) = key {
+                builder = builder.header("authorization", format!("Bearer {key}"));
+            }
+            builder
+                .body(Body::from(
+                    json!({
+                        "model": "gpt-4o",
+                        "user": "alice",
+                        "stream": stream,
+                        "messages": [
+                            {"role": "system", "content": "be nice"},
+                            {"role": "user", "content": "hi"}
+                        ]
+                    })
+                    .to_string(),
+                ))
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(mk_req(None, false)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = app
+            .clone()
+            .oneshot(mk_req(Some("nope"), false))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = app
+            .clone()
+            .oneshot(mk_req(Some("sk-test"), false))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["object"], "chat.completion");
+        assert_eq!(v["model"], "gpt-4o");
+        assert_eq!(v["choices"][0]["message"]["role"], "assistant");
+        assert!(!v["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap()
+            .is_empty());
+
+        let resp = app.oneshot(mk_req(Some("sk-test"), true)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains(r#""object":"chat.completion.chunk""#));
+        assert!(text.contains(r#""finish_reason":"stop""#));
+        assert!(text.trim_end().ends_with("data: [DONE]"));
+    }
+
+    #[tokio::test]
+    async fn test_api_stop_without_active_run() {
+        let web_state = test_web_state(Box::new(DummyLlm), None, WebLimits::default());
+        let app = build_router(web_state);
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/stop")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({"session_key": "main"}).to_string()))
+            .unwrap();
+
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let v: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v.get("cancelled").and_then(|x| x.as_u64()), Some(0));
+    }
+
     #[tokio::test]
     async fn test_api_usage_returns_report() {
         let web_state = test_web_state(Box::new(DummyLlm), None, WebLimits::default());
@@ -2201,4 +3069,153 @@ mod tests {
         assert_eq!(routing.map(|r| r.channel_name), Some("web".to_string()));
         assert_eq!(external.as_deref(), Some("scoped-main"));
     }
+
+    fn login_cookie(resp: &Response) -> (String, String) {
+        let mut session = String::new();
+        let mut csrf = String::new();
+        for value in resp.headers().get_all("set-cookie") {
+            let pair = value
+                .to_str()
+                .unwrap()
+                .split(';')
+                .next()
+                .unwrap()
+                .to_string();
+            if pair.starts_with("microclaw_session=") {
+                session = pair;
+            } else if let Some(token) = pair.strip_prefix("microclaw_csrf=") {
+                csrf = token.to_string();
+            }
+        }
+        (session, csrf)
+    }
+
+    #[tokio::test]
+    async fn test_web_login_sessions_csrf_and_per_user_chats() {
+        let hash = auth::hash_password("pw").unwrap();
+        let state = test_state_with(Box::new(DummyLlm), |cfg| {
+            cfg.web_auth.users = vec![
+                crate::config::WebUserConfig {
+                    username: "alice".into(),
+                    password_hash: hash.clone(),
+                    admin: false,
+                },
+                crate::config::WebUserConfig {
+                    username: "bob".into(),
+                    password_hash: hash.clone(),
+                    admin: false,
+                },
+            ];
+        });
+        let app = build_router(WebState {
+            app_state: state,
+            auth_token: None,
+            oidc_logins: auth::OidcLogins::default(),
+            run_hub: RunHub::default(),
+            session_hub: SessionHub::default(),
+            request_hub: RequestHub::default(),
+            limits: WebLimits::default(),
+        });
+
+        let get = |uri: &str, cookie: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("cookie", cookie)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let resp = app.clone().oneshot(get("/", "")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        assert_eq!(resp.headers()["location"], "/login");
+        let resp = app.clone().oneshot(get("/api/sessions", "")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let login = |user: &str, password: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/login")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!("username={user}&password={password}")))
+                .unwrap()
+        };
+        let resp = app.clone().oneshot(login("alice", "nope")).await.unwrap();
+        assert_eq!(resp.headers()["location"], "/login?error=invalid");
+        let resp = app.clone().oneshot(login("alice", "pw")).await.unwrap();
+        assert_eq!(resp.headers()["location"], "/");
+        let (alice, alice_csrf) = login_cookie(&resp);
+        let resp = app.clone().oneshot(login("bob", "pw")).await.unwrap();
+        let (bob, _) = login_cookie(&resp);
+
+        let send = |cookie: &str, csrf: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/send")
+                .header("content-type", "application/json")
+                .header("cookie", cookie)
+                .header("x-csrf-token", csrf)
+                .body(Body::from(r#"{"session_key":"main","message":"hello"}"#))
+                .unwrap()
+        };
+        let resp = app.clone().oneshot(send(&alice, "wrong")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = app
+            .clone()
+            .oneshot(send(&alice, &alice_csrf))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let alice_chat = body["chat_id"].as_i64().unwrap();
+
+        let resp = app
+            .clone()
+            .oneshot(get("/api/sessions", &alice))
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["sessions"][0]["session_key"], "main");
+        assert_eq!(body["sessions"][0]["chat_id"], alice_chat);
+
+        // Bob sees neither Alice's session nor her chat by id, and can't
+        // change the config.
+        let resp = app
+            .clone()
+            .oneshot(get("/api/sessions", &bob))
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["sessions"], json!([]));
+        let resp = app
+            .clone()
+            .oneshot(get(
+                &format!("/api/history?session_key=chat:{alice_chat}"),
+                &bob,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = app.clone().oneshot(get("/api/config", &bob)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let logout = Request::builder()
+            .method("POST")
+            .uri("/logout")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .header("cookie", &alice)
+            .body(Body::from(format!("csrf_token={alice_csrf}")))
+            .unwrap();
+        let resp = app.clone().oneshot(logout).await.unwrap();
+        assert_eq!(resp.headers()["location"], "/login");
+        let resp = app.oneshot(get("/api/sessions", &alice)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
 }
@@ -0,0 +1,826 @@
+//! Browser logins for the web channel (`web_auth`).
+//!
+//! With `web_auth.users` or `web_auth.oidc` configured, the UI and `/api/*`
+//! need a session cookie from `/login` (password) or `/auth/oidc/login`
+//! (OpenID Connect authorization code flow). Only a hash of the cookie token
+//! is stored (`web_sessions`). Cookie-authenticated writes must echo the
+//! session's CSRF token, which the UI reads from the `microclaw_csrf` cookie,
+//! in `X-CSRF-Token`. Each user gets their own web chats; admins may also
+//! open everyone else's. `web_auth_token` keeps working as a bearer token for
+//! scripts and acts as the operator.
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Form, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::Json;
+use base64::Engine;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use super::{auth_token_from_headers, WebState};
+use crate::config::{WebAuthConfig, WebOidcConfig};
+use crate::db::{call_blocking, WebSession};
+
+const SESSION_COOKIE: &str = "microclaw_session";
+const CSRF_COOKIE: &str = "microclaw_csrf";
+const CSRF_HEADER: &str = "x-csrf-token";
+const HASH_SCHEME: &str = "pbkdf2-sha256";
+const HASH_ITERATIONS: u32 = 600_000;
+const HASH_LEN: usize = 32;
+const OIDC_LOGIN_TTL: Duration = Duration::from_secs(600);
+
+/// Who is making a web request.
+#[derive(Clone, Debug, Default)]
+pub(super) enum Caller {
+    /// A `web_auth_token` bearer, or anyone when no auth is configured.
+    #[default]
+    Operator,
+    User(WebUser),
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct WebUser {
+    pub username: String,
+    pub admin: bool,
+    csrf_token: String,
+}
+
+impl Caller {
+    /// Config, every chat and the global memory view.
+    pub(super) fn is_admin(&self) -> bool {
+        match self {
+            Caller::Operator => true,
+            Caller::User(user) => user.admin,
+        }
+    }
+
+    pub(super) fn username(&self) -> Option<&str> {
+        match self {
+            Caller::Operator => None,
+            Caller::User(user) => Some(&user.username),
+        }
+    }
+
+    /// External id (and title) of the caller's web chat for a session key.
+    /// Users' chats are prefixed with `<username>:`, which usernames can't
+    /// contain.
+    pub(super) fn chat_key(&self, session_key: &str) -> String {
+        match self {
+            Caller::Operator => session_key.to_string(),
+            Caller::User(user) => format!("{}:{session_key}", user.username),
+        }
+    }
+
+    /// Session key of one of the caller's own web chats, from its title.
+    pub(super) fn own_session_key(&self, chat_title: &str) -> Option<String> {
+        match self {
+            Caller::Operator => Some(chat_title.to_string()),
+            Caller::User(user) => chat_title
+                .strip_prefix(user.username.as_str())
+                .and_then(|rest| rest.strip_prefix(':'))
+                .map(str::to_string),
+        }
+    }
+}
+
+/// Authenticate a request: the bearer token, or a login session whose CSRF
+/// token must come along when `write` is set.
+pub(super) async fn authenticate(
+    state: &WebState,
+    headers: &HeaderMap,
+    write: bool,
+) -> Result<Caller, (StatusCode, String)> {
+    match state.auth_token.as_deref() {
+        Some(expected) if auth_token_from_headers(headers).as_deref() == Some(expected) => {
+            return Ok(Caller::Operator)
+        }
+        None if !state.app_state.config.web_auth.login_enabled() => return Ok(Caller::Operator),
+        _ => {}
+    }
+    let Some(user) = session_user(state, headers).await? else {
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".into()));
+    };
+    if write && header_value(headers, CSRF_HEADER).as_deref() != Some(user.csrf_token.as_str()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "missing or invalid CSRF token".into(),
+        ));
+    }
+    Ok(Caller::User(user))
+}
+
+/// Browsers send cookies on cross-site WebSocket handshakes, so a
+/// cookie-authenticated one must come from this server's own pages.
+pub(super) fn same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = header_value(headers, header::ORIGIN.as_str()) else {
+        return true;
+    };
+    let host = header_value(headers, header::HOST.as_str());
+    origin.split_once("://").map(|(_, rest)| rest) == host.as_deref()
+}
+
+/// Whether `/` should send the browser to `/login` first.
+pub(super) async fn needs_login(state: &WebState, headers: &HeaderMap) -> bool {
+    state.app_state.config.web_auth.login_enabled()
+        && !matches!(session_user(state, headers).await, Ok(Some(_)))
+}
+
+async fn session_user(
+    state: &WebState,
+    headers: &HeaderMap,
+) -> Result<Option<WebUser>, (StatusCode, String)> {
+    let auth = &state.app_state.config.web_auth;
+    if !auth.login_enabled() {
+        return Ok(None);
+    }
+    let Some(token) = cookie(headers, SESSION_COOKIE) else {
+        return Ok(None);
+    };
+    let token_hash = token_hash(&token);
+    let session = call_blocking(state.app_state.db.clone(), move |db| {
+        db.get_web_session(&token_hash)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(session.and_then(|s| user_for_session(auth, s)))
+}
+
+/// The session's user, if they may still sign in; dropping someone from the
+/// config ends their sessions.
+fn user_for_session(auth: &WebAuthConfig, session: WebSession) -> Option<WebUser> {
+    let admin = if let Some(user) = auth.users.iter().find(|u| u.username == session.username) {
+        user.admin
+    } else {
+        let oidc = auth.oidc.as_ref()?;
+        if !email_allowed(&oidc.allowed_emails, &session.username) {
+            return None;
+        }
+        oidc.admin_emails.contains(&session.username)
+    };
+    Some(WebUser {
+        username: session.username,
+        admin,
+        csrf_token: session.csrf_token,
+    })
+}
+
+/// `allowed` holds exact emails and `@domain` entries, already lowercased.
+fn email_allowed(allowed: &[String], email: &str) -> bool {
+    allowed.iter().any(|entry| {
+        if entry.starts_with('@') {
+            email.ends_with(entry.as_str())
+        } else {
+            entry == email
+        }
+    })
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name && !value.is_empty()).then(|| value.to_string())
+        })
+}
+
+fn random_token() -> Result<String, (StatusCode, String)> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "no secure randomness available".to_string(),
+        )
+    })?;
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn token_hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Hash a password for `web_auth.users[].password_hash`.
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| "no secure randomness available".to_string())?;
+    let mut hash = [0u8; HASH_LEN];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(HASH_ITERATIONS).expect("non-zero iterations"),
+        &salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    let b64 = base64::engine::general_purpose::STANDARD_NO_PAD;
+    Ok(format!(
+        "{HASH_SCHEME}${HASH_ITERATIONS}${}${}",
+        b64.encode(salt),
+        b64.encode(hash)
+    ))
+}
+
+struct PasswordHash {
+    iterations: NonZeroU32,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+fn parse_password_hash(value: &str) -> Result<PasswordHash, String> {
+    let parts: Vec<&str> = value.trim().split('$').collect();
+    let [scheme, iterations, salt, hash] = parts[..] else {
+        return Err(format!(
+            "password_hash must look like {HASH_SCHEME}$<iterations>$<salt>$<hash>; \
+             create one with `microclaw config hash-password`"
+        ));
+    };
+    if scheme != HASH_SCHEME {
+        return Err(format!("unsupported password hash scheme {scheme:?}"));
+    }
+    let iterations = iterations
+        .parse::<u32>()
+        .ok()
+        .and_then(NonZeroU32::new)
+        .ok_or("invalid iteration count in password_hash")?;
+    let b64 = base64::engine::general_purpose::STANDARD_NO_PAD;
+    let salt = b64
+        .decode(salt)
+        .map_err(|_| "password_hash salt is not valid base64")?;
+    let hash = b64
+        .decode(hash)
+        .map_err(|_| "password_hash hash is not valid base64")?;
+    if hash.len() != HASH_LEN {
+        return Err("password_hash hash has the wrong length".into());
+    }
+    Ok(PasswordHash {
+        iterations,
+        salt,
+        hash,
+    })
+}
+
+/// Config validation for `web_auth.users[].password_hash`.
+pub fn check_password_hash(value: &str) -> Result<(), String> {
+    parse_password_hash(value).map(|_| ())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    parse_password_hash(password_hash).is_ok_and(|h| {
+        pbkdf2::verify(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            h.iterations,
+            &h.salt,
+            password.as_bytes(),
+            &h.hash,
+        )
+        .is_ok()
+    })
+}
+
+/// `microclaw config hash-password`: read a password and print its hash.
+pub fn run_hash_password_cli() -> anyhow::Result<()> {
+    let password = if std::io::stdin().is_terminal() {
+        let first = crate::config_crypt::prompt_hidden("Password: ").map_err(anyhow::Error::msg)?;
+        let second =
+            crate::config_crypt::prompt_hidden("Repeat password: ").map_err(anyhow::Error::msg)?;
+        if first != second {
+            return Err(anyhow::anyhow!("the passwords don't match"));
+        }
+        first
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if password.is_empty() {
+        return Err(anyhow::anyhow!("the password is empty"));
+    }
+    println!("{}", hash_password(&password).map_err(anyhow::Error::msg)?);
+    Ok(())
+}
+
+fn cookie_attrs(auth: &WebAuthConfig, max_age: u64) -> String {
+    let secure = if auth.secure_cookie { "; Secure" } else { "" };
+    format!("Path=/; SameSite=Lax; Max-Age={max_age}{secure}")
+}
+
+/// Store a session for `username` and send the browser to the UI with its
+/// cookies set.
+async fn start_session(
+    state: &WebState,
+    username: String,
+) -> Result<Response, (StatusCode, String)> {
+    let auth = &state.app_state.config.web_auth;
+    let token = random_token()?;
+    let csrf_token = random_token()?;
+    let session = WebSession {
+        username: username.clone(),
+        csrf_token: csrf_token.clone(),
+        expires_at: (chrono::Utc::now() + chrono::Duration::hours(auth.session_ttl_hours as i64))
+            .to_rfc3339(),
+    };
+    let hash = token_hash(&token);
+    call_blocking(state.app_state.db.clone(), move |db| {
+        db.create_web_session(&hash, &session)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    info!(target: "web", username = %username, "Web login");
+
+    let attrs = cookie_attrs(auth, auth.session_ttl_hours * 3600);
+    let mut resp = Redirect::to("/").into_response();
+    for value in [
+        format!("{SESSION_COOKIE}={token}; HttpOnly; {attrs}"),
+        format!("{CSRF_COOKIE}={csrf_token}; {attrs}"),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            resp.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    Ok(resp)
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LoginQuery {
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LoginForm {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct LogoutForm {
+    #[serde(default)]
+    csrf_token: String,
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn login_html(auth: &WebAuthConfig, user: Option<&WebUser>, error: Option<&str>) -> String {
+    let mut body = String::new();
+    if let Some(message) = match error {
+        Some("invalid") => Some("Wrong username or password."),
+        Some("oidc") => Some("Single sign-on failed. Try again or ask an admin."),
+        Some(_) => Some("Sign-in failed."),
+        None => None,
+    } {
+        body.push_str(&format!("<p class=\"error\">{message}</p>"));
+    }
+    if let Some(user) = user {
+        body.push_str(&format!(
+            "<p>Signed in as <b>{}</b>. <a href=\"/\">Open MicroClaw</a></p>\
+             <form method=\"post\" action=\"/logout\">\
+             <input type=\"hidden\" name=\"csrf_token\" value=\"{}\">\
+             <button type=\"submit\">Sign out</button></form>",
+            escape_html(&user.username),
+            escape_html(&user.csrf_token),
+        ));
+    } else {
+        if !auth.users.is_empty() {
+            body.push_str(
+                "<form method=\"post\" action=\"/login\">\
+                 <label>Username <input name=\"username\" autocomplete=\"username\" required autofocus></label>\
+                 <label>Password <input name=\"password\" type=\"password\" autocomplete=\"current-password\" required></label>\
+                 <button type=\"submit\">Sign in</button></form>",
+            );
+        }
+        if auth.oidc.is_some() {
+            body.push_str(
+                "<p><a class=\"sso\" href=\"/auth/oidc/login\">Sign in with single sign-on</a></p>",
+            );
+        }
+    }
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>Sign in - MicroClaw</title><style>\
+         body{{font-family:system-ui,sans-serif;max-width:22rem;margin:4rem auto;padding:0 1rem}}\
+         label{{display:block;margin:.75rem 0}}input{{display:block;width:100%;padding:.4rem;box-sizing:border-box}}\
+         button,.sso{{padding:.45rem 1rem}}.error{{color:#b42318}}\
+         </style></head><body><h1>MicroClaw</h1>{body}</body></html>"
+    )
+}
+
+/// `GET /login`.
+pub(super) async fn login_page(
+    State(state): State<WebState>,
+    headers: HeaderMap,
+    Query(query): Query<LoginQuery>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let auth = &state.app_state.config.web_auth;
+    if !auth.login_enabled() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "web logins are not configured".into(),
+        ));
+    }
+    let user = session_user(&state, &headers).await?;
+    Ok(Html(login_html(
+        auth,
+        user.as_ref(),
+        query.error.as_deref(),
+    )))
+}
+
+/// `POST /login` with a password.
+pub(super) async fn login_submit(
+    State(state): State<WebState>,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, (StatusCode, String)> {
+    let username = form.username.trim().to_string();
+    let Some(user) = state
+        .app_state
+        .config
+        .web_auth
+        .users
+        .iter()
+        .find(|u| u.username == username)
+    else {
+        warn!(target: "web", username = %username, "Web login for unknown user");
+        return Ok(Redirect::to("/login?error=invalid").into_response());
+    };
+    let password_hash = user.password_hash.clone();
+    let valid =
+        tokio::task::spawn_blocking(move || verify_password(&form.password, &password_hash))
+            .await
+            .unwrap_or(false);
+    if !valid {
+        warn!(target: "web", username = %username, "Web login with a wrong password");
+        return Ok(Redirect::to("/login?error=invalid").into_response());
+    }
+    start_session(&state, username).await
+}
+
+/// `POST /logout`: ends the session (form field or header carries the CSRF
+/// token) and clears the cookies.
+pub(super) async fn logout(
+    State(state): State<WebState>,
+    headers: HeaderMap,
+    Form(form): Form<LogoutForm>,
+) -> Result<Response, (StatusCode, String)> {
+    if let Some(user) = session_user(&state, &headers).await? {
+        let provided = header_value(&headers, CSRF_HEADER).unwrap_or(form.csrf_token);
+        if provided != user.csrf_token {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "missing or invalid CSRF token".into(),
+            ));
+        }
+        if let Some(token) = cookie(&headers, SESSION_COOKIE) {
+            let hash = token_hash(&token);
+            call_blocking(state.app_state.db.clone(), move |db| {
+                db.delete_web_session(&hash)
+            })
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+    }
+    let attrs = cookie_attrs(&state.app_state.config.web_auth, 0);
+    let mut resp = Redirect::to("/login").into_response();
+    for name in [SESSION_COOKIE, CSRF_COOKIE] {
+        if let Ok(value) = HeaderValue::from_str(&format!("{name}=; {attrs}")) {
+            resp.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    Ok(resp)
+}
+
+/// `GET /api/me`: who the UI is signed in as.
+pub(super) async fn api_me(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let caller = authenticate(&state, &headers, false).await?;
+    Ok(Json(json!({
+        "ok": true,
+        "username": caller.username(),
+        "admin": caller.is_admin(),
+        "login_enabled": state.app_state.config.web_auth.login_enabled(),
+    })))
+}
+
+/// OIDC logins between the redirect to the provider and the callback, by
+/// `state` parameter.
+#[derive(Clone, Default)]
+pub(super) struct OidcLogins {
+    pending: Arc<Mutex<HashMap<String, PendingLogin>>>,
+}
+
+struct PendingLogin {
+    nonce: String,
+    started: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcDiscovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+async fn discover(oidc: &WebOidcConfig) -> Result<OidcDiscovery, String> {
+    let issuer = oidc.issuer.trim_end_matches('/');
+    let url = format!("{issuer}/.well-known/openid-configuration");
+    let discovery: OidcDiscovery = reqwest::Client::new()
+        .get(&url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("OIDC discovery at {url}: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("OIDC discovery at {url}: {e}"))?;
+    if discovery.issuer.trim_end_matches('/') != issuer {
+        return Err(format!(
+            "OIDC discovery issuer {} does not match web_auth.oidc.issuer",
+            discovery.issuer
+        ));
+    }
+    Ok(discovery)
+}
+
+/// `GET /auth/oidc/login`: redirect to the provider.
+pub(super) async fn oidc_login(
+    State(state): State<WebState>,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(oidc) = state.app_state.config.web_auth.oidc.as_ref() else {
+        return Err((StatusCode::NOT_FOUND, "OIDC login is not configured".into()));
+    };
+    let discovery = discover(oidc)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let login_state = random_token()?;
+    let nonce = random_token()?;
+    {
+        let mut pending = state.oidc_logins.pending.lock().await;
+        pending.retain(|_, login| login.started.elapsed() < OIDC_LOGIN_TTL);
+        pending.insert(
+            login_state.clone(),
+            PendingLogin {
+                nonce: nonce.clone(),
+                started: Instant::now(),
+            },
+        );
+    }
+    let separator = if discovery.authorization_endpoint.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    let url = format!(
+        "{}{separator}response_type=code&scope=openid%20email&client_id={}&redirect_uri={}&state={}&nonce={}",
+        discovery.authorization_endpoint,
+        urlencoding::encode(&oidc.client_id),
+        urlencoding::encode(&oidc.redirect_url),
+        login_state,
+        nonce,
+    );
+    Ok(Redirect::to(&url).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct OidcCallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// `GET /auth/oidc/callback`: exchange the code and sign the user in.
+pub(super) async fn oidc_callback(
+    State(state): State<WebState>,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    match oidc_email(&state, query).await {
+        Ok(email) => start_session(&state, email).await,
+        Err(e) => {
+            warn!(target: "web", error = %e, "OIDC login failed");
+            Ok(Redirect::to("/login?error=oidc").into_response())
+        }
+    }
+}
+
+async fn oidc_email(state: &WebState, query: OidcCallbackQuery) -> Result<String, String> {
+    let oidc = state
+        .app_state
+        .config
+        .web_auth
+        .oidc
+        .as_ref()
+        .ok_or("OIDC login is not configured")?;
+    if let Some(error) = query.error {
+        return Err(format!("provider returned {error}"));
+    }
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        return Err("callback without code or state".into());
+    };
+    let login = state
+        .oidc_logins
+        .pending
+        .lock()
+        .await
+        .remove(&login_state)
+        .filter(|login| login.started.elapsed() < OIDC_LOGIN_TTL)
+        .ok_or("unknown or expired login state")?;
+
+    let discovery = discover(oidc).await?;
+    let tokens: serde_json::Value = reqwest::Client::new()
+        .post(&discovery.token_endpoint)
+        .timeout(Duration::from_secs(10))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", oidc.redirect_url.as_str()),
+            ("client_id", oidc.client_id.as_str()),
+            ("client_secret", oidc.client_secret.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("token exchange: {e}"))?
+        .json()
+        .await
+        .map_err(|e| format!("token exchange: {e}"))?;
+    let id_token = tokens["id_token"]
+        .as_str()
+        .ok_or("token response has no id_token")?;
+    // The ID token comes straight from the token endpoint over TLS, which
+    // OIDC Core 3.1.3.7 accepts in place of checking its signature.
+    let claims = id_token_claims(id_token)?;
+    let email = verified_email(
+        &claims,
+        &oidc.issuer,
+        &oidc.client_id,
+        &login.nonce,
+        chrono::Utc::now().timestamp(),
+    )?;
+    if !email_allowed(&oidc.allowed_emails, &email) {
+        return Err(format!("{email} is not in web_auth.oidc.allowed_emails"));
+    }
+    Ok(email)
+}
+
+fn id_token_claims(id_token: &str) -> Result<serde_json::Value, String> {
+    let payload = id_token.split('.').nth(1).ok_or("id_token is not a JWT")?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|_| "id_token payload is not base64url")?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("id_token payload: {e}"))
+}
+
+/// The lowercased, verified email from ID token claims meant for this client.
+fn verified_email(
+    claims: &serde_json::Value,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+    now: i64,
+) -> Result<String, String> {
+    let iss = claims["iss"].as_str().unwrap_or_default();
+    if iss.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        return Err(format!("id_token issuer {iss:?} does not match"));
+    }
+    let audience_ok = match &claims["aud"] {
+        serde_json::Value::String(aud) => aud == client_id,
+        serde_json::Value::Array(auds) => auds.iter().any(|a| a.as_str() == Some(client_id)),
+        _ => false,
+    };
+    if !audience_ok {
+        return Err("id_token is for another client".into());
+    }
+    if claims["nonce"].as_str() != Some(nonce) {
+        return Err("id_token nonce does not match".into());
+    }
+    if claims["exp"].as_i64().is_none_or(|exp| exp <= now) {
+        return Err("id_token has expired".into());
+    }
+    let verified = match &claims["email_verified"] {
+        serde_json::Value::Bool(v) => *v,
+        serde_json::Value::String(v) => v == "true",
+        _ => false,
+    };
+    let email = claims["email"]
+        .as_str()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| e.contains('@') && !e.contains(':'))
+        .ok_or("id_token has no usable email")?;
+    if !verified {
+        return Err(format!("{email} is not verified by the provider"));
+    }
+    Ok(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_hash_round_trip() {
+        let hash = hash_password("hunter2").unwrap();
+        assert!(hash.starts_with("pbkdf2-sha256$600000$"));
+        assert!(check_password_hash(&hash).is_ok());
+        assert!(verify_password("hunter2", &hash));
+        assert!(!verify_password("hunter3", &hash));
+        assert!(check_password_hash("plaintext").is_err());
+        assert!(!verify_password("plaintext", "plaintext"));
+    }
+
+    #[test]
+    fn test_cookie_parsing() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("theme=dark; microclaw_session=abc; microclaw_csrf=xyz"),
+        );
+        assert_eq!(cookie(&headers, SESSION_COOKIE).as_deref(), Some("abc"));
+        assert_eq!(cookie(&headers, CSRF_COOKIE).as_deref(), Some("xyz"));
+        assert_eq!(cookie(&headers, "missing"), None);
+    }
+
+    #[test]
+    fn test_email_allowed_by_address_or_domain() {
+        let allowed = vec!["ops@corp.example".to_string(), "@example.com".to_string()];
+        assert!(email_allowed(&allowed, "ops@corp.example"));
+        assert!(email_allowed(&allowed, "alice@example.com"));
+        assert!(!email_allowed(&allowed, "alice@evilexample.com"));
+        assert!(!email_allowed(&allowed, "dev@corp.example"));
+    }
+
+    #[test]
+    fn test_verified_email_checks_claims() {
+        let claims = json!({
+            "iss": "https://id.example.com/",
+            "aud": ["other", "microclaw"],
+            "nonce": "n1",
+            "exp": 2_000,
+            "email": "Alice@Example.com",
+            "email_verified": true,
+        });
+        let check = |claims: &serde_json::Value| {
+            verified_email(claims, "https://id.example.com", "microclaw", "n1", 1_000)
+        };
+        assert_eq!(check(&claims).unwrap(), "alice@example.com");
+
+        for (field, value) in [
+            ("iss", json!("https://evil.example.com")),
+            ("aud", json!("other")),
+            ("nonce", json!("n2")),
+            ("exp", json!(999)),
+            ("email_verified", json!(false)),
+        ] {
+            let mut bad = claims.clone();
+            bad[field] = value;
+            assert!(check(&bad).is_err(), "{field} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_caller_chat_keys() {
+        let user = Caller::User(WebUser {
+            username: "alice".into(),
+            admin: false,
+            csrf_token: "t".into(),
+        });
+        assert_eq!(user.chat_key("main"), "alice:main");
+        assert_eq!(user.own_session_key("alice:main").as_deref(), Some("main"));
+        assert_eq!(user.own_session_key("alicia:main"), None);
+        assert_eq!(user.own_session_key("main"), None);
+        assert!(!user.is_admin());
+        assert_eq!(Caller::Operator.chat_key("main"), "main");
+        assert!(Caller::Operator.is_admin());
+    }
+}
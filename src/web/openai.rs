@@ -0,0 +1,270 @@
+//! OpenAI-compatible `/v1/chat/completions` and `/v1/models`, backed by the
+//! agent loop (tools included).
+//!
+//! Conversation state stays server-side, as with `/api/send`: only the latest
+//! `user` message of a request is processed, and history comes from the
+//! session picked by the `X-Session-Key` header or the `user` field.
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::Event;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use super::{
+    auth_token_from_headers, normalize_session_key, send_and_store_response, sse_response,
+    start_stream_run, subscribe_run_events, Caller, SendRequest, WebState,
+};
+
+const ENDPOINT: &str = "/v1/chat/completions";
+const MODEL_ID: &str = "microclaw";
+
+type ApiError = (StatusCode, Json<Value>);
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ChatCompletionRequest {
+    #[serde(default)]
+    model: Option<String>,
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    stream: bool,
+    #[serde(default)]
+    user: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: Value,
+}
+
+fn api_error(status: StatusCode, message: &str, kind: &str) -> ApiError {
+    (
+        status,
+        Json(json!({"error": {"message": message, "type": kind, "code": null}})),
+    )
+}
+
+/// Compare keys through their SHA-256 digests so neither the content nor the
+/// length of the configured key leaks through response timing.
+fn api_key_matches(presented: &str, expected: &str) -> bool {
+    let (a, b) = (
+        Sha256::digest(presented.as_bytes()),
+        Sha256::digest(expected.as_bytes()),
+    );
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+fn check_api_key(headers: &HeaderMap, state: &WebState) -> Result<(), ApiError> {
+    let Some(expected) = state.app_state.config.openai_compat_api_key.as_deref() else {
+        return Err(api_error(
+            StatusCode::NOT_FOUND,
+            "OpenAI-compatible API is disabled; set openai_compat_api_key to enable it",
+            "invalid_request_error",
+        ));
+    };
+    if !auth_token_from_headers(headers).is_some_and(|key| api_key_matches(&key, expected)) {
+        return Err(api_error(
+            StatusCode::UNAUTHORIZED,
+            "Incorrect API key provided",
+            "invalid_request_error",
+        ));
+    }
+    Ok(())
+}
+
+/// Text of a message `content`: a plain string or an array of `text` parts.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter(|p| p.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn session_key(headers: &HeaderMap, user: Option<&str>) -> String {
+    let scope = headers
+        .get("x-session-key")
+        .and_then(|v| v.to_str().ok())
+        .or(user)
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    match scope {
+        Some(scope) => format!("openai-{scope}"),
+        None => "openai".to_string(),
+    }
+}
+
+fn chunk(id: &str, created: i64, model: &str, delta: Value, finish: Option<&str>) -> Event {
+    Event::default().data(
+        json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish}],
+        })
+        .to_string(),
+    )
+}
+
+pub(super) async fn chat_completions(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+    Json(req): Json<ChatCompletionRequest>,
+) -> Result<Response, ApiError> {
+    check_api_key(&headers, &state)?;
+    let message = req
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| content_text(&m.content))
+        .unwrap_or_default();
+    if message.trim().is_empty() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "messages must end with a non-empty user message",
+            "invalid_request_error",
+        ));
+    }
+    let model = req.model.unwrap_or_else(|| MODEL_ID.to_string());
+    let body = SendRequest {
+        session_key: Some(session_key(&headers, req.user.as_deref())),
+        sender_name: req.user,
+        message,
+        caller: Caller::Operator,
+    };
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let created = chrono::Utc::now().timestamp();
+
+    if !req.stream {
+        let key = normalize_session_key(body.session_key.as_deref());
+        state
+            .request_hub
+            .begin(&key, &state.limits)
+            .await
+            .map_err(|(status, msg)| api_error(status, &msg, "rate_limit_error"))?;
+        let result = send_and_store_response(state.clone(), body).await;
+        state.request_hub.end_with_limits(&key, &state.limits).await;
+        let Json(resp) = result.map_err(|(status, msg)| api_error(status, &msg, "server_error"))?;
+        let content = resp.get("response").and_then(|v| v.as_str()).unwrap_or("");
+        return Ok(Json(json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": created,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop",
+            }],
+        }))
+        .into_response());
+    }
+
+    let run_id = start_stream_run(&state, body, ENDPOINT)
+        .await
+        .map_err(|(status, msg)| api_error(status, &msg, "server_error"))?;
+    let Some((_, events)) = subscribe_run_events(&state, &run_id, None, ENDPOINT).await else {
+        return Err(api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "run not found",
+            "server_error",
+        ));
+    };
+    let stream = async_stream::stream! {
+        yield Ok::<Event, std::convert::Infallible>(
+            chunk(&id, created, &model, json!({"role": "assistant"}), None),
+        );
+        let mut streamed = false;
+        for await evt in events {
+            let data: Value = serde_json::from_str(&evt.data).unwrap_or_default();
+            match evt.event.as_str() {
+                "delta" => {
+                    let delta = data.get("delta").and_then(|v| v.as_str()).unwrap_or("");
+                    if !delta.is_empty() {
+                        streamed = true;
+                        yield Ok(chunk(&id, created, &model, json!({"content": delta}), None));
+                    }
+                }
+                "done" => {
+                    // Providers without streaming only report the final text.
+                    let response = data.get("response").and_then(|v| v.as_str()).unwrap_or("");
+                    if !streamed && !response.is_empty() {
+                        yield Ok(chunk(&id, created, &model, json!({"content": response}), None));
+                    }
+                    yield Ok(chunk(&id, created, &model, json!({}), Some("stop")));
+                    break;
+                }
+                "error" => {
+                    let message = data.get("error").and_then(|v| v.as_str()).unwrap_or("run failed");
+                    yield Ok(Event::default().data(
+                        json!({"error": {"message": message, "type": "server_error", "code": null}})
+                            .to_string(),
+                    ));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        yield Ok(Event::default().data("[DONE]"));
+    };
+    Ok(sse_response(stream).into_response())
+}
+
+pub(super) async fn list_models(
+    headers: HeaderMap,
+    State(state): State<WebState>,
+) -> Result<Json<Value>, ApiError> {
+    check_api_key(&headers, &state)?;
+    Ok(Json(json!({
+        "object": "list",
+        "data": [{"id": MODEL_ID, "object": "model", "created": 0, "owned_by": "microclaw"}],
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_matches() {
+        assert!(api_key_matches("sk-local", "sk-local"));
+        assert!(!api_key_matches("sk-locaL", "sk-local"));
+        assert!(!api_key_matches("sk-local-longer", "sk-local"));
+        assert!(!api_key_matches("", "sk-local"));
+    }
+
+    #[test]
+    fn test_content_text_and_session_key() {
+        assert_eq!(content_text(&json!("hi")), "hi");
+        assert_eq!(
+            content_text(&json!([
+                {"type": "text", "text": "a"},
+                {"type": "image_url", "image_url": {"url": "x"}},
+                {"type": "text", "text": "b"}
+            ])),
+            "a\nb"
+        );
+        assert_eq!(content_text(&Value::Null), "");
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(session_key(&headers, None), "openai");
+        assert_eq!(session_key(&headers, Some("alice")), "openai-alice");
+        headers.insert("x-session-key", "support".parse().unwrap());
+        assert_eq!(session_key(&headers, Some("alice")), "openai-support");
+    }
+}
@@ -0,0 +1,175 @@
+//! Per-chat workspace selection for file/shell tools.
+//!
+//! The effective isolation mode is resolved through an inheritance chain:
+//! the chat's `/workspace` override, then `working_dir_isolation` from config.
+//! The `topic` and `session` modes nest inside the chat workspace; `user`
+//! workspaces are shared by one user across the chats of a channel and are
+//! keyed by the sender's platform user id. All three fall back to the chat
+//! directory until their key is known; changing the mode needs a control chat.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::WorkingDirIsolation;
+use crate::db::call_blocking;
+use crate::runtime::AppState;
+use crate::tools::scoped_workspace_dir;
+
+/// Workspace selection for one agent turn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TurnWorkspace {
+    /// Chat override, `None` when the chat inherits the configured mode.
+    pub isolation_override: Option<WorkingDirIsolation>,
+    /// Sender, topic or session key for the effective mode.
+    pub key: Option<String>,
+}
+
+const WORKSPACE_USAGE: &str = "Usage:
+/workspace — show the workspace mode for this chat
+/workspace shared|chat|user|topic|session — switch mode for this chat
+/workspace topic <name> — work in the named topic workspace
+/workspace inherit — go back to the configured default";
+
+/// Resolve the chat override and the scope key tools should use this turn.
+/// The user scope is keyed by the sender's platform user id only; without one
+/// the turn uses the chat workspace.
+pub async fn resolve_turn_workspace(
+    state: &AppState,
+    chat_id: i64,
+    sender_id: Option<&str>,
+) -> TurnWorkspace {
+    let settings = call_blocking(state.db.clone(), move |db| db.get_chat_workspace(chat_id))
+        .await
+        .unwrap_or_default();
+    let isolation_override = settings
+        .isolation
+        .as_deref()
+        .and_then(WorkingDirIsolation::parse);
+    let key = match isolation_override.unwrap_or(state.config.working_dir_isolation) {
+        WorkingDirIsolation::Shared | WorkingDirIsolation::Chat => None,
+        WorkingDirIsolation::User => sender_id
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string),
+        WorkingDirIsolation::Topic => settings.topic,
+        WorkingDirIsolation::Session => call_blocking(state.db.clone(), move |db| {
+            db.ensure_workspace_session_id(chat_id)
+        })
+        .await
+        .ok(),
+    };
+    TurnWorkspace {
+        isolation_override,
+        key,
+    }
+}
+
+/// Directory file/shell tools use for this turn.
+pub fn working_dir_for(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    turn: &TurnWorkspace,
+) -> PathBuf {
+    scoped_workspace_dir(
+        Path::new(state.config.working_dir_for_channel(caller_channel)),
+        turn.isolation_override
+            .unwrap_or(state.config.working_dir_isolation),
+        caller_channel,
+        chat_id,
+        turn.key.as_deref(),
+    )
+}
+
+/// Resolve the turn workspace and return its directory.
+pub async fn turn_working_dir(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    sender_id: Option<&str>,
+) -> PathBuf {
+    let turn = resolve_turn_workspace(state, chat_id, sender_id).await;
+    working_dir_for(state, caller_channel, chat_id, &turn)
+}
+
+async fn describe(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    sender_id: Option<&str>,
+) -> String {
+    let turn = resolve_turn_workspace(state, chat_id, sender_id).await;
+    let mode = turn
+        .isolation_override
+        .unwrap_or(state.config.working_dir_isolation);
+    let origin = match turn.isolation_override {
+        Some(_) => format!(
+            "chat override; default is {}",
+            state.config.working_dir_isolation.as_str()
+        ),
+        None => "inherited from config".to_string(),
+    };
+    let dir = working_dir_for(state, caller_channel, chat_id, &turn);
+    let scope = match (mode, turn.key.as_deref()) {
+        (WorkingDirIsolation::User, Some(k)) => format!("\nUser: {k}"),
+        (WorkingDirIsolation::Topic, Some(k)) => format!("\nTopic: {k}"),
+        (WorkingDirIsolation::Session, Some(k)) => format!("\nSession: {k}"),
+        (WorkingDirIsolation::User, None) => {
+            "\nNo sender id; using the chat workspace.".to_string()
+        }
+        (WorkingDirIsolation::Topic, None) => {
+            "\nNo topic selected yet; using the chat workspace.".to_string()
+        }
+        _ => String::new(),
+    };
+    format!(
+        "Workspace mode: {} ({origin}){scope}\nDirectory: {}",
+        mode.as_str(),
+        dir.display()
+    )
+}
+
+/// Handle `/workspace`. Returns `None` when the text is not this command.
+pub async fn handle_workspace_command(
+    state: &AppState,
+    caller_channel: &str,
+    chat_id: i64,
+    sender_id: Option<&str>,
+    text: &str,
+) -> Option<String> {
+    let rest = text.trim().strip_prefix("/workspace")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut parts = rest.split_whitespace();
+    let arg = parts.next().unwrap_or("");
+    let topic = parts.collect::<Vec<_>>().join(" ");
+
+    let update = match arg {
+        "" => None,
+        "inherit" | "default" => Some((None, None)),
+        _ => match WorkingDirIsolation::parse(arg) {
+            Some(WorkingDirIsolation::Topic) if !topic.is_empty() => {
+                Some((Some(WorkingDirIsolation::Topic), Some(topic)))
+            }
+            Some(mode) if topic.is_empty() => Some((Some(mode), None)),
+            _ => return Some(WORKSPACE_USAGE.to_string()),
+        },
+    };
+    if let Some((mode, topic)) = update {
+        if !state.config.control_chat_ids.contains(&chat_id) {
+            return Some("Only control chats can change the workspace mode.".to_string());
+        }
+        let result = call_blocking(state.db.clone(), move |db| {
+            db.set_chat_workspace(
+                chat_id,
+                mode.map(WorkingDirIsolation::as_str),
+                topic.as_deref(),
+            )
+        })
+        .await;
+        if let Err(e) = result {
+            return Some(format!("Failed to update workspace: {e}"));
+        }
+    }
+    Some(describe(state, caller_channel, chat_id, sender_id).await)
+}
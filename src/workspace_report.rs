@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::config::Config;
+use crate::db::{call_blocking, Database};
+use crate::tools::chat_workspace_dir;
+use crate::tools::todo::{read_todos, todo_path};
+
+/// Template name accepted by `schedule_task`.
+pub const WORKSPACE_REPORT_TEMPLATE: &str = "workspace_report";
+/// Prompt stored on scheduled tasks that should render the workspace report
+/// instead of running the agent loop.
+pub const WORKSPACE_REPORT_PROMPT: &str = "[template:workspace_report]";
+/// Mondays at 09:00 in the scheduler timezone.
+pub const WORKSPACE_REPORT_DEFAULT_CRON: &str = "0 0 9 * * Mon";
+
+const REPORT_WINDOW_DAYS: u64 = 7;
+const MAX_LISTED_FILES: usize = 10;
+const MAX_WALK_ENTRIES: usize = 50_000;
+
+pub fn is_workspace_report_task(prompt: &str) -> bool {
+    prompt.trim() == WORKSPACE_REPORT_PROMPT
+}
+
+#[derive(Debug, Default)]
+struct WorkspaceStats {
+    total_files: usize,
+    total_bytes: u64,
+    changed: Vec<(PathBuf, SystemTime, u64)>,
+    truncated: bool,
+}
+
+fn collect_workspace_stats(root: &Path, since: SystemTime) -> WorkspaceStats {
+    let mut stats = WorkspaceStats::default();
+    let mut stack = vec![root.to_path_buf()];
+    let mut visited = 0usize;
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            visited += 1;
+            if visited > MAX_WALK_ENTRIES {
+                stats.truncated = true;
+                return stats;
+            }
+            // symlink_metadata so links pointing outside the workspace are not followed
+            let Ok(meta) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else if meta.is_file() {
+                stats.total_files += 1;
+                stats.total_bytes += meta.len();
+                if let Ok(modified) = meta.modified() {
+                    if modified >= since {
+                        stats.changed.push((entry.path(), modified, meta.len()));
+                    }
+                }
+            }
+        }
+    }
+    stats.changed.sort_by_key(|c| std::cmp::Reverse(c.1));
+    stats
+}
+
+pub(crate) fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn fmt_age(age: Duration) -> String {
+    let hours = age.as_secs() / 3600;
+    if hours < 48 {
+        format!("{hours}h")
+    } else {
+        format!("{}d", hours / 24)
+    }
+}
+
+fn workspace_lines(root: &Path, stats: &WorkspaceStats, quota_mb: u64) -> Vec<String> {
+    let mut lines = vec!["📁 Workspace".to_string()];
+    if quota_mb > 0 {
+        let quota_bytes = quota_mb.saturating_mul(1024 * 1024);
+        let pct = stats.total_bytes as f64 * 100.0 / quota_bytes as f64;
+        let warn = if stats.total_bytes >= quota_bytes {
+            " ⚠️ over quota"
+        } else if pct >= 80.0 {
+            " ⚠️ nearing quota"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "  Disk: {} / {} MB ({pct:.0}%){warn}",
+            fmt_bytes(stats.total_bytes),
+            quota_mb
+        ));
+    } else {
+        lines.push(format!(
+            "  Disk: {} (no quota configured)",
+            fmt_bytes(stats.total_bytes)
+        ));
+    }
+    let more = if stats.truncated { "+" } else { "" };
+    lines.push(format!("  Files: {}{more}", stats.total_files));
+    lines.push(format!(
+        "  Changed in last {REPORT_WINDOW_DAYS}d: {}{more}",
+        stats.changed.len()
+    ));
+    for (path, _, size) in stats.changed.iter().take(MAX_LISTED_FILES) {
+        let rel = path.strip_prefix(root).unwrap_or(path);
+        lines.push(format!("    - {} ({})", rel.display(), fmt_bytes(*size)));
+    }
+    if stats.changed.len() > MAX_LISTED_FILES {
+        lines.push(format!(
+            "    ... and {} more",
+            stats.changed.len() - MAX_LISTED_FILES
+        ));
+    }
+    lines
+}
+
+fn todo_lines(groups_dir: &Path, chat_id: i64, now: SystemTime) -> Vec<String> {
+    let mut lines = vec!["📝 Todos".to_string()];
+    let todos = read_todos(groups_dir, chat_id);
+    if todos.is_empty() {
+        lines.push("  (no todo list)".to_string());
+        return lines;
+    }
+    let open: Vec<_> = todos.iter().filter(|t| t.status != "completed").collect();
+    let in_progress = open.iter().filter(|t| t.status == "in_progress").count();
+    lines.push(format!(
+        "  Open: {} ({} in progress), completed: {}",
+        open.len(),
+        in_progress,
+        todos.len() - open.len()
+    ));
+
+    // Items carry no timestamps of their own, so the list's last write is
+    // the best available age signal.
+    let age = std::fs::metadata(todo_path(groups_dir, chat_id))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|m| now.duration_since(m).ok());
+    if let Some(age) = age {
+        lines.push(format!("  Last updated: {} ago", fmt_age(age)));
+        if !open.is_empty() && age >= Duration::from_secs(REPORT_WINDOW_DAYS * 86_400) {
+            lines.push(format!("  ⚠️ {} open item(s) look stale:", open.len()));
+            for item in open.iter().take(5) {
+                lines.push(format!("    - {}", item.task));
+            }
+        }
+    }
+    lines
+}
+
+async fn memory_lines(db: Arc<Database>, groups_dir: &Path, chat_id: i64) -> Vec<String> {
+    let mut lines = vec!["🧠 Memory".to_string()];
+    let cutoff =
+        (chrono::Utc::now() - chrono::Duration::days(REPORT_WINDOW_DAYS as i64)).to_rfc3339();
+    match call_blocking(db, move |db| db.get_all_memories_for_chat(Some(chat_id))).await {
+        Ok(memories) => {
+            let active = memories.iter().filter(|m| !m.is_archived).count();
+            let added = memories
+                .iter()
+                .filter(|m| m.created_at.as_str() >= cutoff.as_str())
+                .count();
+            lines.push(format!(
+                "  Structured: {active} active, {} archived, +{added} in last {REPORT_WINDOW_DAYS}d",
+                memories.len() - active
+            ));
+        }
+        Err(e) => lines.push(format!("  Structured: unavailable ({e})")),
+    }
+    let agents_md = groups_dir.join(chat_id.to_string()).join("AGENTS.md");
+    if let Ok(meta) = std::fs::metadata(agents_md) {
+        lines.push(format!("  AGENTS.md: {}", fmt_bytes(meta.len())));
+    }
+    lines
+}
+
+/// Build the weekly workspace report for a chat. Only reads the filesystem and
+/// the database, so scheduled reports never spend LLM tokens.
+pub async fn build_workspace_report(
+    db: Arc<Database>,
+    config: &Config,
+    channel: &str,
+    chat_id: i64,
+) -> String {
+    let now = SystemTime::now();
+    let since = now - Duration::from_secs(REPORT_WINDOW_DAYS * 86_400);
+    let root = chat_workspace_dir(
+        Path::new(config.working_dir_for_channel(channel)),
+        config.working_dir_isolation,
+        channel,
+        chat_id,
+    );
+    let groups_dir = Path::new(&config.data_dir).join("groups");
+
+    let walk_root = root.clone();
+    let stats = tokio::task::spawn_blocking(move || collect_workspace_stats(&walk_root, since))
+        .await
+        .unwrap_or_default();
+
+    let mut lines = vec![
+        format!("📊 Workspace report (chat {chat_id})"),
+        String::new(),
+    ];
+    lines.extend(workspace_lines(&root, &stats, config.workspace_quota_mb));
+    lines.push(String::new());
+    lines.extend(todo_lines(&groups_dir, chat_id, now));
+    lines.push(String::new());
+    lines.extend(memory_lines(db, &groups_dir, chat_id).await);
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WorkingDirIsolation;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("microclaw_wsr_{name}_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_is_workspace_report_task() {
+        assert!(is_workspace_report_task(WORKSPACE_REPORT_PROMPT));
+        assert!(is_workspace_report_task(" [template:workspace_report]\n"));
+        assert!(!is_workspace_report_task("summarize the workspace"));
+    }
+
+    #[test]
+    fn test_collect_workspace_stats_counts_recent_files() {
+        let root = temp_dir("stats");
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        std::fs::write(root.join("a.txt"), "hello").unwrap();
+        std::fs::write(root.join("nested/b.txt"), "world!").unwrap();
+
+        let since = SystemTime::now() - Duration::from_secs(60);
+        let stats = collect_workspace_stats(&root, since);
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_bytes, 11);
+        assert_eq!(stats.changed.len(), 2);
+
+        let future = SystemTime::now() + Duration::from_secs(3600);
+        assert!(collect_workspace_stats(&root, future).changed.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_workspace_lines_quota_warning() {
+        let stats = WorkspaceStats {
+            total_files: 1,
+            total_bytes: 2 * 1024 * 1024,
+            changed: Vec::new(),
+            truncated: false,
+        };
+        let lines = workspace_lines(Path::new("/tmp"), &stats, 1).join("\n");
+        assert!(lines.contains("over quota"));
+        let lines = workspace_lines(Path::new("/tmp"), &stats, 0).join("\n");
+        assert!(lines.contains("no quota configured"));
+    }
+
+    #[tokio::test]
+    async fn test_build_workspace_report_sections() {
+        let base = temp_dir("report");
+        let db = Arc::new(Database::new(base.join("db").to_str().unwrap()).unwrap());
+        let mut config: Config =
+            serde_yaml::from_str("telegram_bot_token: tok\nbot_username: bot\napi_key: key\n")
+                .unwrap();
+        config.data_dir = base.join("data").to_string_lossy().to_string();
+        config.working_dir = base.join("work").to_string_lossy().to_string();
+        config.working_dir_isolation = WorkingDirIsolation::Chat;
+
+        let ws = chat_workspace_dir(
+            Path::new(&config.working_dir),
+            WorkingDirIsolation::Chat,
+            "telegram",
+            42,
+        );
+        std::fs::create_dir_all(&ws).unwrap();
+        std::fs::write(ws.join("notes.md"), "# notes").unwrap();
+        let todo = Path::new(&config.data_dir).join("groups/42/TODO.json");
+        std::fs::create_dir_all(todo.parent().unwrap()).unwrap();
+        std::fs::write(&todo, r#"[{"task":"ship it","status":"pending"}]"#).unwrap();
+
+        let report = build_workspace_report(db, &config, "telegram", 42).await;
+        assert!(report.contains("Workspace report (chat 42)"));
+        assert!(report.contains("notes.md"));
+        assert!(report.contains("Open: 1 (0 in progress)"));
+        assert!(report.contains("Structured: 0 active"));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
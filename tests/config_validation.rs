@@ -32,6 +32,8 @@ fn minimal_config() -> Config {
         web_host: "127.0.0.1".into(),
         web_port: 3900,
         web_auth_token: None,
+        web_auth: Default::default(),
+        web_public_url: None,
         web_max_inflight_per_session: 2,
         web_max_requests_per_window: 8,
         web_rate_window_seconds: 10,
@@ -46,6 +48,72 @@ fn minimal_config() -> Config {
         reflector_enabled: true,
         reflector_interval_mins: 15,
         soul_path: None,
+        skip_tool_approval: false,
+        workspace_quota_mb: 0,
+        workspace_export_max_mb: 50,
+        file_preview_cards: false,
+        file_preview_lines: 12,
+        openai_compat_api_key: None,
+        voice_transcription_provider: None,
+        voice_transcription_base_url: None,
+        voice_transcription_model: None,
+        voice_transcription_command: None,
+        network_policy: Default::default(),
+        tool_output_limits: Default::default(),
+        bash_backend: Default::default(),
+        bash_container: Default::default(),
+        model_capabilities: Default::default(),
+        telegram_inline_mode: false,
+        telegram_inline_allowed_users: vec![],
+        discord_reply_in_threads: false,
+        telegram_bots: vec![],
+        stream_replies: true,
+        llm_fallbacks: vec![],
+        llm_fallback_timeout_secs: 120,
+        model_router: Default::default(),
+        chat_budget: Default::default(),
+        chat_budgets: Default::default(),
+        llm_max_retries: 3,
+        thinking: Default::default(),
+        gemini_safety_threshold: None,
+        aws_region: None,
+        aws_profile: None,
+        azure: Default::default(),
+        oauth: Default::default(),
+        llm_wire_log: Default::default(),
+        compaction_model: None,
+        context_compact_ratio: 0.7,
+        parallel_tools: Default::default(),
+        max_repeated_tool_failures: 2,
+        temperature: None,
+        personas: Default::default(),
+        block_high_risk_after_untrusted: false,
+        read_only: false,
+        db_encryption: false,
+        cancel_on_new_message: false,
+        global_budget: Default::default(),
+        pricing_file: None,
+        pricing_url: None,
+        pricing_refresh_hours: 24,
+        secret_refs: Vec::new(),
+        retention: Default::default(),
+        metrics: Default::default(),
+        otel: Default::default(),
+        log_format: Default::default(),
+        knowledge: Default::default(),
+        calendar: Default::default(),
+        smtp: Default::default(),
+        feeds: Default::default(),
+        file_watch: Default::default(),
+        ocr: Default::default(),
+        clawhooks: Vec::new(),
+        heartbeat: Default::default(),
+        progress_status: true,
+        turn_queue: Default::default(),
+        user_limits: Default::default(),
+        language: "en".into(),
+        memory_consolidation: Default::default(),
+        task_failure_alerts: Default::default(),
         channels: std::collections::HashMap::new(),
     }
 }
@@ -68,7 +136,8 @@ fn test_yaml_parse_minimal() {
         config.working_dir_isolation,
         WorkingDirIsolation::Chat
     ));
-    assert_eq!(config.max_session_messages, 40);
+    assert_eq!(config.max_session_messages, 0);
+    assert_eq!(config.context_compact_ratio, 0.7);
     assert_eq!(config.compact_keep_recent, 20);
 }
 
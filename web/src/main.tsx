@@ -288,6 +288,13 @@ if (typeof document !== 'undefined') {
   document.documentElement.setAttribute('data-ui-theme', readUiTheme())
 }
 
+// Signed-in browsers echo the session's CSRF token on every request.
+function readCsrfToken(): string {
+  if (typeof document === 'undefined') return ''
+  const match = document.cookie.match(/(?:^|;\s*)microclaw_csrf=([^;]+)/)
+  return match ? decodeURIComponent(match[1] || '') : ''
+}
+
 function makeHeaders(options: RequestInit = {}): HeadersInit {
   const headers: Record<string, string> = {
     ...(options.headers as Record<string, string> | undefined),
@@ -295,6 +302,10 @@ function makeHeaders(options: RequestInit = {}): HeadersInit {
   if (options.body && !headers['Content-Type']) {
     headers['Content-Type'] = 'application/json'
   }
+  const csrf = readCsrfToken()
+  if (csrf) {
+    headers['X-CSRF-Token'] = csrf
+  }
   return headers
 }
 
@@ -303,6 +314,10 @@ async function api<T>(
   options: RequestInit = {},
 ): Promise<T> {
   const res = await fetch(path, { ...options, headers: makeHeaders(options) })
+  if (res.status === 401) {
+    // Not signed in, or the login session ended.
+    window.location.assign('/login')
+  }
   const data = (await res.json().catch(() => ({}))) as Record<string, unknown>
   if (!res.ok) {
     throw new Error(String(data.error || data.message || `HTTP ${res.status}`))
@@ -597,9 +612,21 @@ function parseDiscordChannelCsv(input: string): number[] {
   return Array.from(new Set(out))
 }
 
-function normalizeWorkingDirIsolation(value: unknown): 'chat' | 'shared' {
-  const normalized = String(value || '').trim().toLowerCase()
-  return normalized === 'shared' ? 'shared' : 'chat'
+type WorkingDirIsolation = 'shared' | 'chat' | 'user' | 'topic' | 'session'
+
+function normalizeWorkingDirIsolation(value: unknown): WorkingDirIsolation {
+  const normalized = String(value || '').trim().toLowerCase().replace(/^per_/, '')
+  switch (normalized) {
+    case 'shared':
+    case 'user':
+    case 'topic':
+    case 'session':
+      return normalized
+    case 'thread':
+      return 'topic'
+    default:
+      return 'chat'
+  }
 }
 
 type ConfigFieldCardProps = {
@@ -760,25 +787,27 @@ function App() {
             throw new Error('This channel is read-only in Web UI. Send messages from the original channel.')
           }
 
-          const sendResponse = await api<{ run_id?: string }>('/api/send_stream', {
+          // The composer's stop button aborts this request; cancel the server-side run too.
+          options.abortSignal.addEventListener(
+            'abort',
+            () => {
+              void api('/api/stop', {
+                method: 'POST',
+                body: JSON.stringify({ session_key: sessionKey }),
+              }).catch(() => {})
+            },
+            { once: true },
+          )
+
+          // One request starts the run and streams its events back over SSE.
+          const streamResponse = await fetch('/api/chat', {
             method: 'POST',
+            headers: makeHeaders({ headers: { 'Content-Type': 'application/json' } }),
             body: JSON.stringify({
               session_key: sessionKey,
               sender_name: 'web-user',
               message: userText,
             }),
-            signal: options.abortSignal,
-          })
-
-          const runId = sendResponse.run_id
-          if (!runId) {
-            throw new Error('missing run_id')
-          }
-
-          const query = new URLSearchParams({ run_id: runId })
-          const streamResponse = await fetch(`/api/stream?${query.toString()}`, {
-            method: 'GET',
-            headers: makeHeaders(),
             cache: 'no-store',
             signal: options.abortSignal,
           })
@@ -819,6 +848,11 @@ function App() {
           for await (const event of parseSseFrames(streamResponse, options.abortSignal)) {
             const data = event.payload
 
+            if (event.event === 'run') {
+              setStatusText('Running...')
+              continue
+            }
+
             if (event.event === 'replay_meta') {
               if (data.replay_truncated === true) {
                 const oldest = typeof data.oldest_event_id === 'number' ? data.oldest_event_id : null
@@ -850,6 +884,13 @@ function App() {
               continue
             }
 
+            if (event.event === 'tool_progress') {
+              const name = typeof data.name === 'string' ? data.name : ''
+              const note = typeof data.note === 'string' ? data.note : ''
+              if (name && note) setStatusText(`tool: ${name}: ${note}`)
+              continue
+            }
+
             if (event.event === 'tool_result') {
               const payload = data as ToolResultPayload
               if (!payload.tool_use_id || !payload.name) continue
@@ -1446,6 +1487,9 @@ function App() {
                             >
                               <option value="chat">chat (per-chat isolated workspace)</option>
                               <option value="shared">shared (single shared workspace)</option>
+                              <option value="user">user (per-sender workspace inside each chat)</option>
+                              <option value="topic">topic (per /workspace topic inside each chat)</option>
+                              <option value="session">session (fresh workspace after each /reset)</option>
                             </select>
                           </ConfigFieldCard>
                           <ConfigFieldCard label="max_tokens" description={<>Maximum output tokens for one model response.</>}>